@@ -1,4 +1,6 @@
-use super::types::{BlockDeltaInfo, CellIndex, HashType, HeaderInfo, LiveCellInfo, TxInfo};
+use super::types::{
+    BlockDeltaInfo, CellIndex, HashType, HeaderInfo, LiveCellInfo, LockTxDelta, TxInfo,
+};
 use ckb_sdk::{Address, NetworkType};
 use ckb_types::{
     packed::{Header, OutPoint, Script},
@@ -50,9 +52,17 @@ pub enum KeyType {
     LockLiveCellIndex = 303,
     // key => value: {type}:{lock-hash}:{block-number}:{tx-index(u32)} => {tx-hash}
     LockTx = 304,
+    // >> NOTE: newer transaction stay front
+    // key => value: {type}:{lock-hash}:{block-number(u64::MAX - u64)}:{tx-index(u32::MAX - u32)} => {LockTxDelta}
+    LockTxDelta = 305,
 
     // key => value: {type}:{type-hash}:{block-number}:{CellIndex} => {OutPoint}
     TypeLiveCellIndex = 400,
+    // key => value: {type}:{type-hash} => Script
+    TypeScript = 401,
+    // >> Search cells by the args prefix of type script (like the sUDT cells of an owner)
+    // key => value: {type}:{code-hash}:{hash-type(u8)}:{args}:{block-number}:{CellIndex} => {OutPoint}
+    TypeArgsLiveCellIndex = 402,
 
     // key => value: {type}:{code-hash}:{block-number}:{CellIndex} => {OutPoint}
     CodeLiveCellIndex = 500,
@@ -84,8 +94,11 @@ impl KeyType {
             302 => KeyType::LockTotalCapacityIndex,
             303 => KeyType::LockLiveCellIndex,
             304 => KeyType::LockTx,
+            305 => KeyType::LockTxDelta,
 
             400 => KeyType::TypeLiveCellIndex,
+            401 => KeyType::TypeScript,
+            402 => KeyType::TypeArgsLiveCellIndex,
             500 => KeyType::CodeLiveCellIndex,
 
             value => panic!("Unexpected key type: value={}", value),
@@ -115,9 +128,14 @@ pub enum Key {
     LockLiveCellIndexPrefix(H256, Option<u64>),
     LockLiveCellIndex(H256, u64, CellIndex),
     LockTx(H256, u64, u32),
+    LockTxDeltaPrefix(H256),
+    LockTxDelta(H256, u64, u32),
 
     TypeLiveCellIndexPrefix(H256, Option<u64>),
     TypeLiveCellIndex(H256, u64, CellIndex),
+    TypeScript(H256),
+    TypeArgsLiveCellIndexPrefix(H256, u8, Vec<u8>),
+    TypeArgsLiveCellIndex(H256, u8, Vec<u8>, u64, CellIndex),
     CodeLiveCellIndexPrefix(H256, Option<u64>),
     CodeLiveCellIndex(H256, u64, CellIndex),
 }
@@ -208,6 +226,21 @@ impl Key {
                 bytes.extend(tx_index.to_be_bytes().to_vec());
                 bytes
             }
+            Key::LockTxDeltaPrefix(lock_hash) => {
+                let mut bytes = KeyType::LockTxDelta.to_bytes();
+                bytes.extend(lock_hash.as_bytes().to_vec());
+                bytes
+            }
+            Key::LockTxDelta(lock_hash, number, tx_index) => {
+                // NOTE: newer transaction stay front
+                let number = std::u64::MAX - number;
+                let tx_index = std::u32::MAX - tx_index;
+                let mut bytes = KeyType::LockTxDelta.to_bytes();
+                bytes.extend(lock_hash.as_bytes().to_vec());
+                bytes.extend(number.to_be_bytes().to_vec());
+                bytes.extend(tx_index.to_be_bytes().to_vec());
+                bytes
+            }
 
             Key::TypeLiveCellIndexPrefix(type_hash, number_opt) => {
                 let mut bytes = KeyType::TypeLiveCellIndex.to_bytes();
@@ -225,6 +258,28 @@ impl Key {
                 bytes.extend(cell_index.to_bytes());
                 bytes
             }
+            Key::TypeScript(type_hash) => {
+                let mut bytes = KeyType::TypeScript.to_bytes();
+                bytes.extend(type_hash.as_bytes().to_vec());
+                bytes
+            }
+            Key::TypeArgsLiveCellIndexPrefix(code_hash, hash_type, args_prefix) => {
+                let mut bytes = KeyType::TypeArgsLiveCellIndex.to_bytes();
+                bytes.extend(code_hash.as_bytes().to_vec());
+                bytes.push(*hash_type);
+                bytes.extend(args_prefix.clone());
+                bytes
+            }
+            Key::TypeArgsLiveCellIndex(code_hash, hash_type, args, number, cell_index) => {
+                let mut bytes = KeyType::TypeArgsLiveCellIndex.to_bytes();
+                bytes.extend(code_hash.as_bytes().to_vec());
+                bytes.push(*hash_type);
+                bytes.extend(args.clone());
+                // Must use big endian for sort
+                bytes.extend(number.to_be_bytes().to_vec());
+                bytes.extend(cell_index.to_bytes());
+                bytes
+            }
 
             Key::CodeLiveCellIndexPrefix(code_hash, number_opt) => {
                 let mut bytes = KeyType::CodeLiveCellIndex.to_bytes();
@@ -331,6 +386,18 @@ impl Key {
                 let tx_index = u32::from_be_bytes(tx_index_bytes);
                 Key::LockTx(lock_hash, number, tx_index)
             }
+            KeyType::LockTxDelta => {
+                let lock_hash_bytes = &args_bytes[..32];
+                let mut number_bytes = [0u8; 8];
+                let mut tx_index_bytes = [0u8; 4];
+                number_bytes.copy_from_slice(&args_bytes[32..40]);
+                tx_index_bytes.copy_from_slice(&args_bytes[40..]);
+                let lock_hash = H256::from_slice(lock_hash_bytes).unwrap();
+                // NOTE: newer transaction stay front
+                let number = std::u64::MAX - u64::from_be_bytes(number_bytes);
+                let tx_index = std::u32::MAX - u32::from_be_bytes(tx_index_bytes);
+                Key::LockTxDelta(lock_hash, number, tx_index)
+            }
             KeyType::TypeLiveCellIndex => {
                 let type_hash_bytes = &args_bytes[..32];
                 let mut number_bytes = [0u8; 8];
@@ -342,6 +409,24 @@ impl Key {
                 let cell_index = CellIndex::from_bytes(cell_index_bytes);
                 Key::TypeLiveCellIndex(type_hash, number, cell_index)
             }
+            KeyType::TypeScript => {
+                let type_hash = H256::from_slice(args_bytes).unwrap();
+                Key::TypeScript(type_hash)
+            }
+            KeyType::TypeArgsLiveCellIndex => {
+                // The args is variable length, the number and cell index are the last 16 bytes
+                let args_end = args_bytes.len() - 16;
+                let code_hash = H256::from_slice(&args_bytes[..32]).unwrap();
+                let hash_type = args_bytes[32];
+                let args = args_bytes[33..args_end].to_vec();
+                let mut number_bytes = [0u8; 8];
+                number_bytes.copy_from_slice(&args_bytes[args_end..args_end + 8]);
+                let mut cell_index_bytes = [0u8; 8];
+                cell_index_bytes.copy_from_slice(&args_bytes[args_end + 8..]);
+                let number = u64::from_be_bytes(number_bytes);
+                let cell_index = CellIndex::from_bytes(cell_index_bytes);
+                Key::TypeArgsLiveCellIndex(code_hash, hash_type, args, number, cell_index)
+            }
             KeyType::CodeLiveCellIndex => {
                 let code_hash_bytes = &args_bytes[..32];
                 let mut number_bytes = [0u8; 8];
@@ -375,8 +460,13 @@ impl Key {
             Key::LockLiveCellIndexPrefix(..) => KeyType::LockLiveCellIndex,
             Key::LockLiveCellIndex(..) => KeyType::LockLiveCellIndex,
             Key::LockTx(..) => KeyType::LockTx,
+            Key::LockTxDeltaPrefix(..) => KeyType::LockTxDelta,
+            Key::LockTxDelta(..) => KeyType::LockTxDelta,
             Key::TypeLiveCellIndexPrefix(..) => KeyType::TypeLiveCellIndex,
             Key::TypeLiveCellIndex(..) => KeyType::TypeLiveCellIndex,
+            Key::TypeScript(..) => KeyType::TypeScript,
+            Key::TypeArgsLiveCellIndexPrefix(..) => KeyType::TypeArgsLiveCellIndex,
+            Key::TypeArgsLiveCellIndex(..) => KeyType::TypeArgsLiveCellIndex,
             Key::CodeLiveCellIndexPrefix(..) => KeyType::CodeLiveCellIndex,
             Key::CodeLiveCellIndex(..) => KeyType::CodeLiveCellIndex,
         }
@@ -482,6 +572,13 @@ impl Key {
         )
     }
 
+    pub(crate) fn pair_lock_tx_delta(lock_hash: H256, value: &LockTxDelta) -> (Vec<u8>, Vec<u8>) {
+        (
+            Key::LockTxDelta(lock_hash, value.block_number, value.tx_index).to_bytes(),
+            bincode::serialize(value).unwrap(),
+        )
+    }
+
     pub(crate) fn pair_type_live_cell_index(
         (type_hash, number, cell_index): (H256, u64, CellIndex),
         value: &OutPoint,
@@ -492,6 +589,23 @@ impl Key {
         )
     }
 
+    pub(crate) fn pair_type_script(type_hash: H256, value: &Script) -> (Vec<u8>, Vec<u8>) {
+        (
+            Key::TypeScript(type_hash).to_bytes(),
+            value.as_slice().to_vec(),
+        )
+    }
+
+    pub(crate) fn pair_type_args_live_cell_index(
+        (code_hash, hash_type, args, number, cell_index): (H256, u8, Vec<u8>, u64, CellIndex),
+        value: &OutPoint,
+    ) -> (Vec<u8>, Vec<u8>) {
+        (
+            Key::TypeArgsLiveCellIndex(code_hash, hash_type, args, number, cell_index).to_bytes(),
+            value.as_slice().to_vec(),
+        )
+    }
+
     pub(crate) fn pair_code_live_cell_index(
         (code_hash, number, cell_index): (H256, u64, CellIndex),
         value: &OutPoint,
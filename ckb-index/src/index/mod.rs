@@ -5,7 +5,7 @@ use std::collections::BTreeMap;
 use std::fmt;
 use std::io;
 
-use ckb_sdk::{Address, GenesisInfo, NetworkType};
+use ckb_sdk::{stats::record_index_query, Address, GenesisInfo, NetworkType};
 use ckb_types::{
     core::{BlockView, HeaderView},
     packed::{Byte32, Header, OutPoint, Script},
@@ -15,9 +15,14 @@ use rocksdb::{ColumnFamily, DB};
 
 use crate::{KVReader, KVTxn, RocksReader, RocksTxn};
 pub use key::{Key, KeyMetrics, KeyType};
-pub use types::{CellIndex, HashType, LiveCellInfo, TxInfo};
+pub use types::{CellIndex, HashType, LiveCellInfo, LockTxDelta, TxInfo};
 
-use types::BlockDeltaInfo;
+use types::{BlockDeltaInfo, HeaderInfo};
+
+/// Blocks committed in one write batch while far behind the tip
+pub const DEFAULT_BATCH_SIZE: usize = 200;
+/// The max depth of `IndexDatabase::rollback`
+pub const MAX_ROLLBACK_BLOCKS: u64 = types::KEEP_RECENT_BLOCKS;
 
 // NOTE: You should reopen to increase database size when processed enough blocks
 //  [reference]: https://stackoverflow.com/a/33571804
@@ -29,6 +34,7 @@ pub struct IndexDatabase<'a> {
     last_header: Option<HeaderView>,
     tip_header: HeaderView,
     init_block_buf: Vec<BlockView>,
+    batch_size: usize,
     // Disable record tx info by default
     enable_explorer: bool,
 }
@@ -87,10 +93,24 @@ impl<'a> IndexDatabase<'a> {
             genesis_info,
             tip_header: genesis_header,
             init_block_buf: Vec::new(),
+            batch_size: DEFAULT_BATCH_SIZE,
             enable_explorer,
         })
     }
 
+    /// Number of blocks committed in one write batch during initial sync (at least 1)
+    pub fn set_batch_size(&mut self, batch_size: usize) {
+        self.batch_size = batch_size.max(1);
+    }
+
+    /// Commit the blocks buffered by initial sync
+    pub fn flush(&mut self) {
+        if !self.init_block_buf.is_empty() {
+            let blocks = self.init_block_buf.split_off(0);
+            self.commit_blocks(blocks);
+        }
+    }
+
     pub fn apply_next_block(&mut self, block: BlockView) -> Result<(), IndexError> {
         let number = block.header().number();
         let block_hash = block.header().hash();
@@ -104,23 +124,7 @@ impl<'a> IndexDatabase<'a> {
                 }
 
                 log::warn!("Rollback because of block: {:#x}", block_hash);
-                self.init_block_buf.clear();
-                // Reload last header
-                let last_block_delta: BlockDeltaInfo = {
-                    let reader = RocksReader::new(self.db, self.cf);
-                    let last_header: HeaderView = reader
-                        .get(&Key::LastHeader.to_bytes())
-                        .map(|bytes| Header::new_unchecked(bytes.into()).into_view())
-                        .unwrap();
-                    reader
-                        .get(&Key::BlockDelta(last_header.number()).to_bytes())
-                        .map(|bytes| bincode::deserialize(&bytes).unwrap())
-                        .unwrap()
-                };
-                let mut txn = RocksTxn::new(self.db, self.cf);
-                last_block_delta.rollback(&mut txn);
-                txn.commit();
-                self.last_header = last_block_delta.parent_header();
+                self.rollback(1)?;
                 return Ok(());
             }
             if number > self.tip_header.number() {
@@ -144,6 +148,40 @@ impl<'a> IndexDatabase<'a> {
         }
     }
 
+    /// Rewind the last `count` blocks (the buffered blocks are committed first), only the
+    /// recent `MAX_ROLLBACK_BLOCKS` blocks can be rolled back.
+    pub fn rollback(&mut self, count: u64) -> Result<(), IndexError> {
+        self.flush();
+        for _ in 0..count {
+            let last_number = match self.last_number() {
+                Some(number) if number > 0 => number,
+                _ => return Err(IndexError::RollbackTooDeep(0)),
+            };
+            let last_block_delta: BlockDeltaInfo = RocksReader::new(self.db, self.cf)
+                .get(&Key::BlockDelta(last_number).to_bytes())
+                .map(|bytes| bincode::deserialize(&bytes).unwrap())
+                .ok_or_else(|| IndexError::RollbackTooDeep(last_number))?;
+            let mut txn = RocksTxn::new(self.db, self.cf);
+            last_block_delta.rollback(&mut txn);
+            txn.commit();
+            self.last_header = last_block_delta.parent_header();
+        }
+        Ok(())
+    }
+
+    /// Hash of an indexed block, only the recent blocks are kept
+    pub fn get_block_hash(&self, number: u64) -> Option<Byte32> {
+        if self.last_number() == Some(number) {
+            return self.last_header.as_ref().map(HeaderView::hash);
+        }
+        RocksReader::new(self.db, self.cf)
+            .get(&Key::RecentHeader(number).to_bytes())
+            .map(|bytes| {
+                let info: HeaderInfo = bincode::deserialize(&bytes).unwrap();
+                info.header().hash()
+            })
+    }
+
     pub fn update_tip(&mut self, header: HeaderView) {
         self.tip_header = header
     }
@@ -170,6 +208,7 @@ impl<'a> IndexDatabase<'a> {
     }
 
     pub fn get_capacity(&self, lock_hash: Byte32) -> Option<u64> {
+        record_index_query("get_capacity");
         let reader = RocksReader::new(self.db, self.cf);
         reader
             .get(&Key::LockTotalCapacity(lock_hash.unpack()).to_bytes())
@@ -181,6 +220,7 @@ impl<'a> IndexDatabase<'a> {
     }
 
     pub fn get_lock_hash_by_address(&self, address: Address) -> Option<Byte32> {
+        record_index_query("get_lock_hash_by_address");
         let reader = RocksReader::new(self.db, self.cf);
         reader
             .get(&Key::SecpAddrLock(address).to_bytes())
@@ -188,6 +228,7 @@ impl<'a> IndexDatabase<'a> {
     }
 
     pub fn get_lock_script_by_hash(&self, lock_hash: Byte32) -> Option<Script> {
+        record_index_query("get_lock_script_by_hash");
         let reader = RocksReader::new(self.db, self.cf);
         reader
             .get(&Key::LockScript(lock_hash.unpack()).to_bytes())
@@ -221,6 +262,22 @@ impl<'a> IndexDatabase<'a> {
         self.get_live_cell_infos(key_prefix, key_start, terminator)
     }
 
+    /// Live cells of the type scripts with the code hash and hash type of `type_script` and the
+    /// args starting with its args (like the sUDT cells of an owner lock hash). The cells are
+    /// ordered by args then block number.
+    pub fn get_live_cells_by_type_args<F: FnMut(usize, &LiveCellInfo) -> (bool, bool)>(
+        &self,
+        type_script: Script,
+        terminator: F,
+    ) -> Vec<LiveCellInfo> {
+        let key_prefix = Key::TypeArgsLiveCellIndexPrefix(
+            type_script.code_hash().unpack(),
+            type_script.hash_type().as_slice()[0],
+            type_script.args().raw_data().to_vec(),
+        );
+        self.get_live_cell_infos(key_prefix.clone(), key_prefix, terminator)
+    }
+
     pub fn get_live_cells_by_code<F: FnMut(usize, &LiveCellInfo) -> (bool, bool)>(
         &self,
         code_hash: Byte32,
@@ -244,6 +301,7 @@ impl<'a> IndexDatabase<'a> {
                 .map(|bytes| bincode::deserialize(&bytes).unwrap())
         }
 
+        record_index_query("get_live_cell_infos");
         let reader = RocksReader::new(self.db, self.cf);
         let key_prefix = key_prefix.to_bytes();
         let key_start = key_start.to_bytes();
@@ -268,7 +326,25 @@ impl<'a> IndexDatabase<'a> {
         infos
     }
 
+    /// Transactions changing capacity of the lock, newest first
+    pub fn get_lock_txs(&self, lock_hash: Byte32, limit: usize) -> Vec<LockTxDelta> {
+        record_index_query("get_lock_txs");
+        let reader = RocksReader::new(self.db, self.cf);
+        let key_prefix = Key::LockTxDeltaPrefix(lock_hash.unpack()).to_bytes();
+
+        let mut deltas = Vec::new();
+        for (key_bytes, value_bytes) in reader.iter_from(&key_prefix) {
+            if key_bytes[..key_prefix.len()] != key_prefix[..] || deltas.len() >= limit {
+                log::debug!("Reach the end of this lock");
+                break;
+            }
+            deltas.push(bincode::deserialize(&value_bytes).unwrap());
+        }
+        deltas
+    }
+
     pub fn get_top_n(&self, n: usize) -> Vec<(Byte32, Option<Address>, u64)> {
+        record_index_query("get_top_n");
         let reader = RocksReader::new(self.db, self.cf);
         let key_prefix: Vec<u8> = KeyType::LockTotalCapacityIndex.to_bytes();
 
@@ -300,7 +376,7 @@ impl<'a> IndexDatabase<'a> {
         self.last_header = Some(header.clone());
         let blocks = if self.last_number().unwrap() < self.tip_header.number().saturating_sub(256) {
             self.init_block_buf.push(block);
-            if self.init_block_buf.len() >= 200 {
+            if self.init_block_buf.len() >= self.batch_size {
                 self.init_block_buf.split_off(0)
             } else {
                 Vec::new()
@@ -310,7 +386,12 @@ impl<'a> IndexDatabase<'a> {
             blocks.push(block);
             blocks
         };
+        if !blocks.is_empty() {
+            self.commit_blocks(blocks);
+        }
+    }
 
+    fn commit_blocks(&mut self, blocks: Vec<BlockView>) {
         let secp_data_hash = self.genesis_info.secp_data_hash();
         let secp_type_hash = self.genesis_info.secp_type_hash();
         let mut txn = RocksTxn::new(self.db, self.cf);
@@ -353,7 +434,10 @@ impl<'a> IndexDatabase<'a> {
                 KeyType::LockTotalCapacity,
                 KeyType::LockTotalCapacityIndex,
                 KeyType::LockLiveCellIndex,
+                KeyType::LockTxDelta,
                 KeyType::TypeLiveCellIndex,
+                KeyType::TypeScript,
+                KeyType::TypeArgsLiveCellIndex,
                 KeyType::CodeLiveCellIndex,
             ];
             if self.enable_explorer {
@@ -388,6 +472,8 @@ pub enum IndexError {
     IoError(String),
     InvalidGenesis(String),
     InvalidNetworkType(String),
+    // The block delta to rollback is not kept (block number)
+    RollbackTooDeep(u64),
 }
 
 impl From<io::Error> for IndexError {
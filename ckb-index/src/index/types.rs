@@ -1,4 +1,4 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 use ckb_types::{
     bytes::Bytes,
@@ -14,7 +14,7 @@ use crate::{KVReader, KVTxn};
 use ckb_sdk::Address;
 
 const KEEP_RECENT_HEADERS: u64 = 10_000;
-const KEEP_RECENT_BLOCKS: u64 = 200;
+pub(crate) const KEEP_RECENT_BLOCKS: u64 = 200;
 
 #[derive(Hash, Eq, PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
 #[repr(u8)]
@@ -47,6 +47,10 @@ pub struct BlockDeltaInfo {
     old_blocks: Vec<u64>,
     old_chain_capacity: u128,
     new_chain_capacity: u128,
+    // The type scripts of the new cells, recorded for `TypeArgsLiveCellIndex`. Not saved with
+    // the block delta, the rollback reads them from `TypeScript`.
+    #[serde(skip)]
+    type_scripts: Vec<Script>,
 }
 
 impl BlockDeltaInfo {
@@ -107,6 +111,7 @@ impl BlockDeltaInfo {
         let mut cell_added = 0;
         let mut locks: HashMap<H256, LockInfo> = HashMap::default();
         let mut live_cell_infos: HashMap<OutPoint, LiveCellInfo> = HashMap::default();
+        let mut type_scripts: Vec<Script> = Vec::new();
         let txs = block
             .transactions()
             .iter()
@@ -161,10 +166,12 @@ impl BlockDeltaInfo {
                     let cell_index = CellIndex::new(tx_index as u32, output_index as u32);
 
                     let type_hashes = output.type_().to_opt().map(|type_script| {
-                        (
+                        let hashes = (
                             type_script.code_hash().unpack(),
                             type_script.calc_script_hash().unpack(),
-                        )
+                        );
+                        type_scripts.push(type_script);
+                        hashes
                     });
 
                     let live_cell_info = LiveCellInfo {
@@ -257,6 +264,7 @@ impl BlockDeltaInfo {
             old_blocks,
             old_chain_capacity,
             new_chain_capacity,
+            type_scripts,
         }
     }
 
@@ -274,11 +282,21 @@ impl BlockDeltaInfo {
             self.locks.len(),
         );
 
+        for type_script in &self.type_scripts {
+            txn.put_pair(Key::pair_type_script(
+                type_script.calc_script_hash().unpack(),
+                type_script,
+            ));
+        }
+
         // Update cells and transactions
         for tx in &self.txs {
             if enable_explorer {
                 txn.put_pair(Key::pair_tx_map(tx.tx_hash.clone(), &tx.to_thin()));
             }
+            for (lock_hash, delta) in tx.lock_deltas() {
+                txn.put_pair(Key::pair_lock_tx_delta(lock_hash, &delta));
+            }
 
             for LiveCellInfo {
                 tx_hash,
@@ -307,6 +325,15 @@ impl BlockDeltaInfo {
                     txn.remove(
                         Key::TypeLiveCellIndex(script_hash.clone(), *number, *index).to_bytes(),
                     );
+                    if let Some((code_hash, hash_type, args, _, _)) =
+                        type_args_index(&*txn, script_hash, *number, *index)
+                    {
+                        // Not indexed by the older versions
+                        txn.remove_ok(
+                            Key::TypeArgsLiveCellIndex(code_hash, hash_type, args, *number, *index)
+                                .to_bytes(),
+                        );
+                    }
                 }
             }
 
@@ -342,6 +369,14 @@ impl BlockDeltaInfo {
                         (script_hash.clone(), *number, *index),
                         &out_point,
                     ));
+                    if let Some(type_args_index) =
+                        type_args_index(&*txn, script_hash, *number, *index)
+                    {
+                        txn.put_pair(Key::pair_type_args_live_cell_index(
+                            type_args_index,
+                            &out_point,
+                        ));
+                    }
                 }
             }
         }
@@ -427,6 +462,11 @@ impl BlockDeltaInfo {
         let mut delete_lock_txs: HashSet<(H256, u64, u32)> = HashSet::default();
         for tx in &self.txs {
             txn.remove_ok(Key::TxMap(tx.tx_hash.clone()).to_bytes());
+            for (lock_hash, delta) in tx.lock_deltas() {
+                txn.remove_ok(
+                    Key::LockTxDelta(lock_hash, delta.block_number, delta.tx_index).to_bytes(),
+                );
+            }
             for live_cell_info in &tx.inputs {
                 let LiveCellInfo {
                     tx_hash,
@@ -454,6 +494,14 @@ impl BlockDeltaInfo {
                         (script_hash.clone(), *number, *index),
                         &out_point,
                     ));
+                    if let Some(type_args_index) =
+                        type_args_index(&*txn, script_hash, *number, *index)
+                    {
+                        txn.put_pair(Key::pair_type_args_live_cell_index(
+                            type_args_index,
+                            &out_point,
+                        ));
+                    }
                 }
             }
 
@@ -479,6 +527,15 @@ impl BlockDeltaInfo {
                     txn.remove(
                         Key::TypeLiveCellIndex(script_hash.clone(), *number, *index).to_bytes(),
                     );
+                    if let Some((code_hash, hash_type, args, _, _)) =
+                        type_args_index(&*txn, script_hash, *number, *index)
+                    {
+                        // Not indexed by the older versions
+                        txn.remove_ok(
+                            Key::TypeArgsLiveCellIndex(code_hash, hash_type, args, *number, *index)
+                                .to_bytes(),
+                        );
+                    }
                 }
             }
         }
@@ -538,6 +595,28 @@ impl BlockDeltaInfo {
     }
 }
 
+// The `TypeArgsLiveCellIndex` key of a cell, `None` if the type script is not recorded (the cell
+// is indexed by an older version)
+fn type_args_index<'r, T: KVReader<'r>>(
+    reader: &T,
+    type_hash: &H256,
+    number: u64,
+    index: CellIndex,
+) -> Option<(H256, u8, Vec<u8>, u64, CellIndex)> {
+    reader
+        .get(&Key::TypeScript(type_hash.clone()).to_bytes())
+        .map(|bytes| {
+            let type_script = Script::new_unchecked(bytes.into());
+            (
+                type_script.code_hash().unpack(),
+                type_script.hash_type().as_slice()[0],
+                type_script.args().raw_data().to_vec(),
+                number,
+                index,
+            )
+        })
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LockInfo {
     script_opt: Option<Bytes>,
@@ -707,6 +786,32 @@ pub(crate) struct RichTxInfo {
 }
 
 impl RichTxInfo {
+    // Capacity spent and received by each lock in this transaction
+    pub(crate) fn lock_deltas(&self) -> BTreeMap<H256, LockTxDelta> {
+        let mut deltas: BTreeMap<H256, LockTxDelta> = BTreeMap::default();
+        let new_delta = || LockTxDelta {
+            tx_hash: self.tx_hash.clone(),
+            tx_index: self.tx_index,
+            block_number: self.block_number,
+            block_timestamp: self.block_timestamp,
+            input_capacity: 0,
+            output_capacity: 0,
+        };
+        for info in &self.inputs {
+            deltas
+                .entry(info.lock_hash.clone())
+                .or_insert_with(new_delta)
+                .input_capacity += info.capacity;
+        }
+        for info in &self.outputs {
+            deltas
+                .entry(info.lock_hash.clone())
+                .or_insert_with(new_delta)
+                .output_capacity += info.capacity;
+        }
+        deltas
+    }
+
     pub(crate) fn to_thin(&self) -> TxInfo {
         TxInfo {
             tx_hash: self.tx_hash.clone(),
@@ -752,3 +857,24 @@ impl TxInfo {
             .collect::<Vec<_>>()
     }
 }
+
+/// Capacity change of a lock by a transaction
+#[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize)]
+pub struct LockTxDelta {
+    pub tx_hash: H256,
+    // Transaction index in target block
+    pub tx_index: u32,
+    pub block_number: u64,
+    pub block_timestamp: u64,
+    // Total capacity of the spent cells owned by the lock
+    pub input_capacity: u64,
+    // Total capacity of the created cells owned by the lock
+    pub output_capacity: u64,
+}
+
+impl LockTxDelta {
+    /// Net balance change of the lock (in shannons, total issuance fits in i64)
+    pub fn balance_change(&self) -> i64 {
+        self.output_capacity as i64 - self.input_capacity as i64
+    }
+}
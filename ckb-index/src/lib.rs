@@ -1,14 +1,17 @@
 mod error;
 mod index;
 mod kvdb;
+mod snapshot;
 mod util;
 
 pub use error::Error;
 pub use index::{
     CellIndex, HashType, IndexDatabase, IndexError, Key as IndexKey, KeyMetrics as IndexKeyMetrics,
-    KeyType as IndexKeyType, LiveCellInfo, TxInfo,
+    KeyType as IndexKeyType, LiveCellInfo, LockTxDelta, TxInfo, DEFAULT_BATCH_SIZE,
+    MAX_ROLLBACK_BLOCKS,
 };
 pub use kvdb::{KVReader, KVTxn, RocksReader, RocksTxn};
-pub use util::{with_index_db, with_rocksdb};
+pub use snapshot::{export_snapshot, import_snapshot, read_snapshot_info, SnapshotInfo};
+pub use util::{clear_index_db, with_index_db, with_rocksdb};
 
 const ROCKSDB_COL_INDEX_DB: &str = "index-db";
@@ -0,0 +1,172 @@
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use ckb_types::{packed::Header, prelude::*, H256};
+use rocksdb::ops::{GetCF, GetColumnFamilys, IterateCF, WriteOps};
+use rocksdb::{ColumnFamily, IteratorMode, Snapshot, WriteBatch, DB};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{with_rocksdb, Error, IndexKey, ROCKSDB_COL_INDEX_DB};
+
+const SNAPSHOT_MAGIC: &[u8; 8] = b"CKBINDEX";
+const SNAPSHOT_VERSION: u32 = 1;
+// Key-value pairs written in one batch when importing
+const IMPORT_BATCH_SIZE: usize = 10_000;
+
+/// The header of a snapshot file, followed by all the key-value pairs of the index database
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotInfo {
+    pub version: u32,
+    pub genesis_hash: H256,
+    pub block_number: u64,
+    pub block_hash: H256,
+    pub pairs: u64,
+}
+
+/// Dump the index database to `path`, the database must be at `block_hash` if given. All the
+/// pairs are read from one snapshot of the database, so the dump is consistent.
+pub fn export_snapshot<P: AsRef<Path>>(
+    db: &DB,
+    cf: &ColumnFamily,
+    path: P,
+    block_hash: Option<&H256>,
+) -> Result<SnapshotInfo, Error> {
+    let snapshot = db.snapshot();
+    let genesis_hash = snapshot_get(&snapshot, cf, &IndexKey::GenesisHash.to_bytes())?
+        .map(|bytes| H256::from_slice(&bytes).expect("Invalid genesis hash"))
+        .ok_or_else(|| Error::Other("Index database not initialized".to_owned()))?;
+    let last_header = snapshot_get(&snapshot, cf, &IndexKey::LastHeader.to_bytes())?
+        .map(|bytes| Header::new_unchecked(bytes.into()).into_view())
+        .ok_or_else(|| Error::Other("Index database not initialized".to_owned()))?;
+    let last_hash: H256 = last_header.hash().unpack();
+    if let Some(block_hash) = block_hash {
+        if block_hash != &last_hash {
+            return Err(Error::Other(format!(
+                "Index database is at block#{} ({:#x}), not {:#x}",
+                last_header.number(),
+                last_hash,
+                block_hash
+            )));
+        }
+    }
+
+    let pairs = snapshot.iterator_cf(cf, IteratorMode::Start)?.count() as u64;
+    let info = SnapshotInfo {
+        version: SNAPSHOT_VERSION,
+        genesis_hash,
+        block_number: last_header.number(),
+        block_hash: last_hash,
+        pairs,
+    };
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(SNAPSHOT_MAGIC)?;
+    bincode::serialize_into(&mut writer, &info).map_err(|err| Error::Other(err.to_string()))?;
+    for (key, value) in snapshot.iterator_cf(cf, IteratorMode::Start)? {
+        let pair: (Vec<u8>, Vec<u8>) = (key.into(), value.into());
+        bincode::serialize_into(&mut writer, &pair).map_err(|err| Error::Other(err.to_string()))?;
+    }
+    writer.flush()?;
+    Ok(info)
+}
+
+/// Read the header of a snapshot file
+pub fn read_snapshot_info<P: AsRef<Path>>(path: P) -> Result<SnapshotInfo, Error> {
+    let mut reader = BufReader::new(File::open(path)?);
+    read_header(&mut reader)
+}
+
+/// Replace the index database of `genesis_hash` under `index_dir` by the snapshot. The snapshot
+/// is written to a temporary database first, which is moved into place once complete, so a
+/// failed import leaves the old database untouched. The database must not be opened meanwhile.
+pub fn import_snapshot<P: AsRef<Path>, Q: AsRef<Path>>(
+    index_dir: P,
+    genesis_hash: &H256,
+    path: Q,
+) -> Result<SnapshotInfo, Error> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let info = read_header(&mut reader)?;
+    if &info.genesis_hash != genesis_hash {
+        return Err(Error::Other(format!(
+            "Snapshot is for chain {:#x}, not {:#x}",
+            info.genesis_hash, genesis_hash
+        )));
+    }
+
+    let db_dir = index_dir.as_ref().join(format!("{:#x}", genesis_hash));
+    let import_dir = index_dir
+        .as_ref()
+        .join(format!("{:#x}.import", genesis_hash));
+    let old_dir = index_dir.as_ref().join(format!("{:#x}.old", genesis_hash));
+    // Left by an interrupted import
+    if import_dir.exists() {
+        fs::remove_dir_all(&import_dir)?;
+    }
+    let result = with_rocksdb(&import_dir, None, |db| {
+        let cf = db
+            .cf_handle(ROCKSDB_COL_INDEX_DB)
+            .expect("Get ColumnFamily failed");
+        let mut batch = WriteBatch::default();
+        let mut batch_len = 0;
+        for _ in 0..info.pairs {
+            let (key, value): (Vec<u8>, Vec<u8>) = bincode::deserialize_from(&mut reader)
+                .map_err(|err| Error::Other(format!("Invalid snapshot data: {}", err)))?;
+            batch.put_cf(cf, key, value)?;
+            batch_len += 1;
+            if batch_len >= IMPORT_BATCH_SIZE {
+                db.write(batch)?;
+                batch = WriteBatch::default();
+                batch_len = 0;
+            }
+        }
+        db.write(batch)?;
+        Ok(())
+    });
+    if let Err(err) = result {
+        let _ = fs::remove_dir_all(&import_dir);
+        return Err(err);
+    }
+
+    if old_dir.exists() {
+        fs::remove_dir_all(&old_dir)?;
+    }
+    if db_dir.exists() {
+        fs::rename(&db_dir, &old_dir)?;
+    }
+    if let Err(err) = fs::rename(&import_dir, &db_dir) {
+        // Put the old database back
+        if old_dir.exists() {
+            let _ = fs::rename(&old_dir, &db_dir);
+        }
+        return Err(err.into());
+    }
+    if old_dir.exists() {
+        fs::remove_dir_all(&old_dir)?;
+    }
+    Ok(info)
+}
+
+fn read_header<R: Read>(reader: &mut R) -> Result<SnapshotInfo, Error> {
+    let mut magic = [0u8; 8];
+    reader.read_exact(&mut magic)?;
+    if &magic != SNAPSHOT_MAGIC {
+        return Err(Error::Other("Not an index snapshot file".to_owned()));
+    }
+    let info: SnapshotInfo = bincode::deserialize_from(reader)
+        .map_err(|err| Error::Other(format!("Invalid snapshot header: {}", err)))?;
+    if info.version != SNAPSHOT_VERSION {
+        return Err(Error::Other(format!(
+            "Unsupported snapshot version: {}",
+            info.version
+        )));
+    }
+    Ok(info)
+}
+
+fn snapshot_get(
+    snapshot: &Snapshot,
+    cf: &ColumnFamily,
+    key: &[u8],
+) -> Result<Option<Vec<u8>>, Error> {
+    Ok(snapshot.get_cf(cf, key)?.map(|value| value.to_vec()))
+}
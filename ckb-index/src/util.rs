@@ -4,11 +4,14 @@ use std::time::{Duration, Instant};
 
 use ckb_types::H256;
 use rocksdb::{
-    ops::{GetColumnFamilys, OpenCF},
-    ColumnFamily, Options, DB,
+    ops::{GetColumnFamilys, OpenCF, WriteOps},
+    ColumnFamily, Options, WriteBatch, DB,
 };
 
-use crate::{Error, ROCKSDB_COL_INDEX_DB};
+use crate::{Error, KVReader, RocksReader, ROCKSDB_COL_INDEX_DB};
+
+// Keys deleted in one batch when clearing the database
+const CLEAR_BATCH_SIZE: usize = 10_000;
 
 pub fn with_rocksdb<P, T, F>(path: P, timeout: Option<Duration>, func: F) -> Result<T, Error>
 where
@@ -57,3 +60,20 @@ where
         func(db, cf)
     })
 }
+
+/// Remove all the data of the index database, it will be initialized again by
+/// `IndexDatabase::from_db`
+pub fn clear_index_db(db: &DB, cf: &ColumnFamily) -> Result<(), Error> {
+    let keys: Vec<Vec<u8>> = RocksReader::new(db, cf)
+        .iter_from(&[])
+        .map(|(key, _)| key)
+        .collect();
+    for chunk in keys.chunks(CLEAR_BATCH_SIZE) {
+        let mut batch = WriteBatch::default();
+        for key in chunk {
+            batch.delete_cf(cf, key)?;
+        }
+        db.write(batch)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,104 @@
+use ckb_types::{
+    core::{DepType, ScriptHashType},
+    h256,
+    packed::{Byte32, CellDep, OutPoint, Script},
+    prelude::*,
+    H256,
+};
+
+use crate::{Address, NetworkType};
+
+// Deployments from RFC 0026 (the `code_hash` is a type hash, the cell dep is a dep group)
+const MAINNET_ACP_CODE_HASH: H256 =
+    h256!("0xd369597ff47f29fbc0d47d2e3775370d1250b85140c670e4718af712983a2354");
+const MAINNET_ACP_DEP_TX_HASH: H256 =
+    h256!("0x4153a2014952d7cac45f285ce9a7c5c0c0e1b21f2d378b82ac1433cb11c25c4d");
+const TESTNET_ACP_CODE_HASH: H256 =
+    h256!("0x3419a1c09eb2567f6552ee7a8ecffd64155cffe0f1796e6e61ec088d740c1356");
+const TESTNET_ACP_DEP_TX_HASH: H256 =
+    h256!("0xec26b0f85ed839ece5f11c4c4e837ec359f5adc4420410f6453b1f6b60fb96a6");
+
+/// The anyone-can-pay lock, anyone can top up the cell without the signature of the owner
+/// (args: blake160 of the owner pubkey | minimal CKB amount exponent (optional) | minimal UDT
+/// amount exponent (optional)). It is not deployed in genesis block.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AcpConfig {
+    code_hash: Byte32,
+    dep_out_point: OutPoint,
+}
+
+impl AcpConfig {
+    pub fn new(code_hash: H256, dep_out_point: OutPoint) -> AcpConfig {
+        AcpConfig {
+            code_hash: code_hash.pack(),
+            dep_out_point,
+        }
+    }
+
+    /// The known deployment of mainnet and testnet, dev chain must be configured manually
+    pub fn from_network(network: NetworkType) -> Option<AcpConfig> {
+        let (code_hash, tx_hash) = match network {
+            NetworkType::MainNet => (MAINNET_ACP_CODE_HASH, MAINNET_ACP_DEP_TX_HASH),
+            NetworkType::TestNet => (TESTNET_ACP_CODE_HASH, TESTNET_ACP_DEP_TX_HASH),
+            NetworkType::Dev => return None,
+        };
+        Some(AcpConfig::new(code_hash, OutPoint::new(tx_hash.pack(), 0)))
+    }
+
+    pub fn code_hash(&self) -> &Byte32 {
+        &self.code_hash
+    }
+
+    pub fn cell_dep(&self) -> CellDep {
+        CellDep::new_builder()
+            .out_point(self.dep_out_point.clone())
+            .dep_type(DepType::DepGroup.into())
+            .build()
+    }
+
+    /// The anyone-can-pay lock owned by the same key as a secp address (no minimal amount)
+    pub fn lock_script(&self, address: &Address) -> Script {
+        address.lock_script(self.code_hash.clone())
+    }
+
+    pub fn is_acp_lock(&self, lock: &Script) -> bool {
+        let args_len = lock.args().raw_data().len();
+        lock.code_hash() == self.code_hash
+            && lock.hash_type() == ScriptHashType::Type.into()
+            && args_len >= 20
+            && args_len <= 22
+    }
+}
+
+/// The minimal capacity (shannons) of one payment to an anyone-can-pay cell
+pub fn acp_min_capacity(lock: &Script) -> u64 {
+    lock.args()
+        .raw_data()
+        .get(20)
+        .map(|exponent| 10u64.saturating_pow(u32::from(*exponent)))
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ckb_types::bytes::Bytes;
+
+    #[test]
+    fn test_acp_lock() {
+        let config = AcpConfig::from_network(NetworkType::TestNet).unwrap();
+        assert!(AcpConfig::from_network(NetworkType::Dev).is_none());
+
+        let address = Address::from_lock_arg(&[1u8; 20]).unwrap();
+        let lock = config.lock_script(&address);
+        assert!(config.is_acp_lock(&lock));
+        assert_eq!(acp_min_capacity(&lock), 0);
+        assert!(!config.is_acp_lock(&address.lock_script(Byte32::default())));
+
+        let mut args = vec![1u8; 20];
+        args.push(9);
+        let lock = lock.as_builder().args(Bytes::from(args).pack()).build();
+        assert!(config.is_acp_lock(&lock));
+        assert_eq!(acp_min_capacity(&lock), 1_000_000_000);
+    }
+}
@@ -0,0 +1,295 @@
+use bech32::convert_bits;
+use ckb_types::{
+    bytes::Bytes,
+    core::ScriptHashType,
+    packed::{Byte32, Script},
+    prelude::*,
+    H160,
+};
+
+use crate::{CodeHashIndex, GenesisInfo, NetworkType};
+
+const FORMAT_SHORT: u8 = 0x01;
+const FORMAT_FULL_DATA: u8 = 0x02;
+const FORMAT_FULL_TYPE: u8 = 0x04;
+
+const CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const GENERATOR: [u32; 5] = [
+    0x3b6a_57b2,
+    0x2650_8e6d,
+    0x1ea1_19fa,
+    0x3d42_33dd,
+    0x2a14_62b3,
+];
+
+/// Payload of all the address formats in RFC 0021: short (secp256k1 sighash/multisig by code
+/// hash index) and full (any lock script by `hash_type: data` or `hash_type: type`).
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub enum AddressPayload {
+    Short {
+        index: CodeHashIndex,
+        hash: H160,
+    },
+    Full {
+        hash_type: ScriptHashType,
+        code_hash: Byte32,
+        args: Bytes,
+    },
+}
+
+impl AddressPayload {
+    /// The short format is used for secp256k1 sighash and multisig locks, full format otherwise
+    pub fn from_lock_script(lock: &Script, genesis_info: &GenesisInfo) -> AddressPayload {
+        let code_hash = lock.code_hash();
+        let args = lock.args().raw_data();
+        if lock.hash_type() == ScriptHashType::Type.into() && args.len() == 20 {
+            let index = if &code_hash == genesis_info.secp_type_hash() {
+                Some(CodeHashIndex::Default)
+            } else if genesis_info.multisig_type_hash().ok() == Some(&code_hash) {
+                Some(CodeHashIndex::Multisig)
+            } else {
+                None
+            };
+            if let Some(index) = index {
+                let hash = H160::from_slice(&args).expect("20 bytes args");
+                return AddressPayload::Short { index, hash };
+            }
+        }
+        AddressPayload::full(lock)
+    }
+
+    /// The full format of any lock script
+    pub fn full(lock: &Script) -> AddressPayload {
+        let hash_type = if lock.hash_type() == ScriptHashType::Type.into() {
+            ScriptHashType::Type
+        } else {
+            ScriptHashType::Data
+        };
+        AddressPayload::Full {
+            hash_type,
+            code_hash: lock.code_hash(),
+            args: lock.args().raw_data(),
+        }
+    }
+
+    pub fn lock_script(&self, genesis_info: &GenesisInfo) -> Result<Script, String> {
+        match self {
+            AddressPayload::Short { index, hash } => {
+                let code_hash = match index {
+                    CodeHashIndex::Default => genesis_info.secp_type_hash().clone(),
+                    CodeHashIndex::Multisig => genesis_info.multisig_type_hash()?.clone(),
+                };
+                Ok(Script::new_builder()
+                    .code_hash(code_hash)
+                    .hash_type(ScriptHashType::Type.into())
+                    .args(hash.as_bytes().pack())
+                    .build())
+            }
+            AddressPayload::Full {
+                hash_type,
+                code_hash,
+                args,
+            } => Ok(Script::new_builder()
+                .code_hash(code_hash.clone())
+                .hash_type((*hash_type).into())
+                .args(args.pack())
+                .build()),
+        }
+    }
+
+    pub fn format_name(&self) -> &'static str {
+        match self {
+            AddressPayload::Short {
+                index: CodeHashIndex::Default,
+                ..
+            } => "short_sighash",
+            AddressPayload::Short {
+                index: CodeHashIndex::Multisig,
+                ..
+            } => "short_multisig",
+            AddressPayload::Full {
+                hash_type: ScriptHashType::Data,
+                ..
+            } => "full_data",
+            AddressPayload::Full { .. } => "full_type",
+        }
+    }
+
+    pub fn to_address_string(&self, network: NetworkType) -> String {
+        let mut data = Vec::new();
+        match self {
+            AddressPayload::Short { index, hash } => {
+                data.push(FORMAT_SHORT);
+                data.push(*index as u8);
+                data.extend_from_slice(hash.as_bytes());
+            }
+            AddressPayload::Full {
+                hash_type,
+                code_hash,
+                args,
+            } => {
+                data.push(match hash_type {
+                    ScriptHashType::Data => FORMAT_FULL_DATA,
+                    ScriptHashType::Type => FORMAT_FULL_TYPE,
+                });
+                data.extend_from_slice(code_hash.as_slice());
+                data.extend_from_slice(args);
+            }
+        }
+        let data = convert_bits(&data, 8, 5, true).expect("convert to 5 bits");
+        bech32_encode(network.to_prefix(), &data)
+    }
+
+    pub fn from_address_string(input: &str) -> Result<(NetworkType, AddressPayload), String> {
+        let (hrp, data) = bech32_decode(input)?;
+        let network =
+            NetworkType::from_prefix(&hrp).ok_or_else(|| format!("Invalid hrp: {}", hrp))?;
+        let data = convert_bits(&data, 5, 8, false).map_err(|err| format!("{:?}", err))?;
+        let payload = match data.first() {
+            Some(&FORMAT_SHORT) => {
+                if data.len() != 22 {
+                    return Err(format!("Invalid short address data length {}", data.len()));
+                }
+                let index = CodeHashIndex::from_u8(data[1])
+                    .ok_or_else(|| format!("Invalid code hash index: {:?}", data[1]))?;
+                let hash = H160::from_slice(&data[2..22]).map_err(|err| err.to_string())?;
+                AddressPayload::Short { index, hash }
+            }
+            Some(&format) if format == FORMAT_FULL_DATA || format == FORMAT_FULL_TYPE => {
+                if data.len() < 33 {
+                    return Err(format!("Invalid full address data length {}", data.len()));
+                }
+                let hash_type = if format == FORMAT_FULL_DATA {
+                    ScriptHashType::Data
+                } else {
+                    ScriptHashType::Type
+                };
+                AddressPayload::Full {
+                    hash_type,
+                    code_hash: Byte32::from_slice(&data[1..33]).map_err(|err| err.to_string())?,
+                    args: Bytes::from(data[33..].to_vec()),
+                }
+            }
+            Some(format) => return Err(format!("Invalid address format: {:?}", format)),
+            None => return Err("Empty address data".to_owned()),
+        };
+        Ok((network, payload))
+    }
+}
+
+// The `bech32` crate rejects strings longer than 90 characters, which full addresses usually are
+fn polymod(values: &[u8]) -> u32 {
+    let mut chk: u32 = 1;
+    for value in values {
+        let top = chk >> 25;
+        chk = ((chk & 0x01ff_ffff) << 5) ^ u32::from(*value);
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                chk ^= generator;
+            }
+        }
+    }
+    chk
+}
+
+fn hrp_expand(hrp: &str) -> Vec<u8> {
+    let mut values = hrp.bytes().map(|byte| byte >> 5).collect::<Vec<_>>();
+    values.push(0);
+    values.extend(hrp.bytes().map(|byte| byte & 0x1f));
+    values
+}
+
+fn bech32_encode(hrp: &str, data: &[u8]) -> String {
+    let mut values = hrp_expand(hrp);
+    values.extend_from_slice(data);
+    values.extend_from_slice(&[0u8; 6]);
+    let checksum = polymod(&values) ^ 1;
+    let mut output = format!("{}1", hrp);
+    for value in data {
+        output.push(CHARSET[*value as usize] as char);
+    }
+    for i in 0..6 {
+        let value = (checksum >> (5 * (5 - i))) & 0x1f;
+        output.push(CHARSET[value as usize] as char);
+    }
+    output
+}
+
+fn bech32_decode(input: &str) -> Result<(String, Vec<u8>), String> {
+    if input.to_lowercase() != input && input.to_uppercase() != input {
+        return Err("Mixed case address".to_owned());
+    }
+    let input = input.to_lowercase();
+    let pos = input
+        .rfind('1')
+        .ok_or_else(|| "Missing separator of address".to_owned())?;
+    if pos == 0 || pos + 7 > input.len() {
+        return Err("Invalid address length".to_owned());
+    }
+    let hrp = &input[..pos];
+    let mut data = Vec::with_capacity(input.len() - pos - 1);
+    for ch in input[pos + 1..].bytes() {
+        let value = CHARSET
+            .iter()
+            .position(|item| *item == ch)
+            .ok_or_else(|| format!("Invalid address character: {}", ch as char))?;
+        data.push(value as u8);
+    }
+    let mut values = hrp_expand(hrp);
+    values.extend_from_slice(&data);
+    if polymod(&values) != 1 {
+        return Err("Invalid address checksum".to_owned());
+    }
+    data.truncate(data.len() - 6);
+    Ok((hrp.to_owned(), data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Address;
+    use ckb_types::h160;
+
+    #[test]
+    fn test_short_address() {
+        let address_str = "ckb1qyqp8eqad7ffy42ezmchkjyz54rhcqf8q9pqrn323p";
+        let (network, payload) = AddressPayload::from_address_string(address_str).unwrap();
+        let hash = h160!("0x13e41d6F9292555916f17B4882a5477C01270142");
+        assert_eq!(network, NetworkType::MainNet);
+        assert_eq!(
+            payload,
+            AddressPayload::Short {
+                index: CodeHashIndex::Default,
+                hash: hash.clone(),
+            }
+        );
+        assert_eq!(payload.format_name(), "short_sighash");
+        assert_eq!(payload.to_address_string(NetworkType::MainNet), address_str);
+        assert_eq!(
+            Address::new_default(hash).to_string(NetworkType::MainNet),
+            address_str
+        );
+    }
+
+    #[test]
+    fn test_full_address() {
+        let lock = Script::new_builder()
+            .code_hash(Byte32::from_slice(&[7u8; 32]).unwrap())
+            .hash_type(ScriptHashType::Data.into())
+            .args(Bytes::from(vec![1u8; 40]).pack())
+            .build();
+        let payload = AddressPayload::full(&lock);
+        assert_eq!(payload.format_name(), "full_data");
+        let address_str = payload.to_address_string(NetworkType::TestNet);
+        assert!(address_str.len() > 90);
+        assert_eq!(
+            AddressPayload::from_address_string(&address_str),
+            Ok((NetworkType::TestNet, payload))
+        );
+
+        let mut broken = address_str.into_bytes();
+        let last = broken.len() - 1;
+        broken[last] = if broken[last] == b'q' { b'p' } else { b'q' };
+        assert!(AddressPayload::from_address_string(&String::from_utf8(broken).unwrap()).is_err());
+    }
+}
@@ -85,6 +85,18 @@ pub enum AddressType {
 pub enum CodeHashIndex {
     // SECP256K1 + blake160
     Default = 0x00,
+    // SECP256K1 + multisig
+    Multisig = 0x01,
+}
+
+impl CodeHashIndex {
+    pub fn from_u8(v: u8) -> Option<CodeHashIndex> {
+        match v {
+            0x00 => Some(CodeHashIndex::Default),
+            0x01 => Some(CodeHashIndex::Multisig),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Hash, Eq, PartialEq, Debug, Clone, Serialize, Deserialize)]
@@ -101,6 +113,17 @@ impl Address {
         Address { ty, index, hash }
     }
 
+    /// Address of a multisig lock, `hash` is blake160 of the multisig script
+    pub fn new_multisig(hash: H160) -> Address {
+        let ty = AddressType::Default;
+        let index = CodeHashIndex::Multisig;
+        Address { ty, index, hash }
+    }
+
+    pub fn index(&self) -> CodeHashIndex {
+        self.index
+    }
+
     pub fn hash(&self) -> &H160 {
         &self.hash
     }
@@ -136,11 +159,11 @@ impl Address {
         if data[0] != AddressType::Default as u8 {
             return Err(format!("Invalid address type: {:?}", data[0]));
         }
-        if data[1] != CodeHashIndex::Default as u8 {
-            return Err(format!("Invalid code hash index: {:?}", data[1]));
-        }
+        let index = CodeHashIndex::from_u8(data[1])
+            .ok_or_else(|| format!("Invalid code hash index: {:?}", data[1]))?;
         let hash = H160::from_slice(&data[2..22]).map_err(|err| err.to_string())?;
-        Ok((network, Self::new_default(hash)))
+        let ty = AddressType::Default;
+        Ok((network, Address { ty, index, hash }))
     }
 
     pub fn to_string(&self, network: NetworkType) -> String {
@@ -287,5 +310,14 @@ mod test {
             address.to_string(NetworkType::MainNet),
             "ckb1qyqp8eqad7ffy42ezmchkjyz54rhcqf8q9pqrn323p"
         );
+
+        let hash = h160!("0x4fb2be2e5d0c1a3b8694f832350a33c1685d477a");
+        let address = Address::new_multisig(hash);
+        let address_str = "ckb1qyq5lv479ewscx3ms620sv34pgeuz6zagaaqklhtgg";
+        assert_eq!(address.to_string(NetworkType::MainNet), address_str);
+        assert_eq!(
+            Address::from_input(address_str),
+            Ok((NetworkType::MainNet, address))
+        );
     }
 }
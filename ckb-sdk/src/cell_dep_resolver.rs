@@ -0,0 +1,54 @@
+use ckb_types::{
+    core::ScriptHashType,
+    packed::{CellDep, Script},
+    prelude::*,
+};
+
+use crate::{AcpConfig, GenesisInfo, NetworkType, SudtConfig};
+
+/// Resolve the cell dep of well-known system scripts: secp256k1 sighash and multisig, NervosDAO
+/// (from genesis block), simple UDT and anyone-can-pay (the known deployment of the network).
+pub struct CellDepResolver<'a> {
+    genesis_info: &'a GenesisInfo,
+    sudt: Option<SudtConfig>,
+    acp: Option<AcpConfig>,
+}
+
+impl<'a> CellDepResolver<'a> {
+    pub fn new(genesis_info: &'a GenesisInfo, network: NetworkType) -> CellDepResolver<'a> {
+        CellDepResolver {
+            genesis_info,
+            sudt: SudtConfig::from_network(network),
+            acp: AcpConfig::from_network(network),
+        }
+    }
+
+    /// Name and cell dep of the script, `None` if it is not a known system script
+    pub fn resolve(&self, script: &Script) -> Option<(&'static str, CellDep)> {
+        let code_hash = script.code_hash();
+        let is_type = script.hash_type() == ScriptHashType::Type.into();
+        let genesis_info = self.genesis_info;
+        if (is_type && &code_hash == genesis_info.secp_type_hash())
+            || (!is_type && &code_hash == genesis_info.secp_data_hash())
+        {
+            return Some(("secp256k1_blake160_sighash_all", genesis_info.secp_dep()));
+        }
+        if (is_type && &code_hash == genesis_info.dao_type_hash())
+            || (!is_type && &code_hash == genesis_info.dao_data_hash())
+        {
+            return Some(("dao", genesis_info.dao_dep()));
+        }
+        if is_type && genesis_info.multisig_type_hash().ok() == Some(&code_hash) {
+            if let Ok(dep) = genesis_info.multisig_dep() {
+                return Some(("secp256k1_blake160_multisig_all", dep));
+            }
+        }
+        if let Some(sudt) = self.sudt.as_ref().filter(|sudt| sudt.is_sudt_type(script)) {
+            return Some(("sudt", sudt.cell_dep()));
+        }
+        if let Some(acp) = self.acp.as_ref().filter(|acp| acp.is_acp_lock(script)) {
+            return Some(("anyone_can_pay", acp.cell_dep()));
+        }
+        None
+    }
+}
@@ -1,4 +1,6 @@
-use crate::Address;
+use crate::{
+    basic::CodeHashIndex, Address, Change, ChangePolicy, LockWitness, MultisigConfig, Since,
+};
 use ckb_crypto::secp::SECP256K1;
 use ckb_hash::new_blake2b;
 use ckb_resource::{CODE_HASH_DAO, CODE_HASH_SECP256K1_BLAKE160_SIGHASH_ALL};
@@ -12,6 +14,7 @@ use ckb_types::{
     prelude::*,
     H160, H256,
 };
+use rayon::prelude::*;
 use secp256k1::recovery::RecoverableSignature;
 
 pub const ONE_CKB: u64 = 100_000_000;
@@ -37,6 +40,10 @@ const SECP_GROUP_TRANSACTION_INDEX: usize = 1;
 const SECP_GROUP_OUTPUT_INDEX: usize = 0;
 const DAO_TRANSACTION_INDEX: usize = 0;
 const DAO_OUTPUT_INDEX: usize = 2;
+const MULTISIG_TRANSACTION_INDEX: usize = 0;
+const MULTISIG_OUTPUT_INDEX: usize = 4;
+const MULTISIG_GROUP_TRANSACTION_INDEX: usize = 1;
+const MULTISIG_GROUP_OUTPUT_INDEX: usize = 1;
 
 #[derive(Debug, Clone)]
 pub struct GenesisInfo {
@@ -46,6 +53,8 @@ pub struct GenesisInfo {
     secp_type_hash: Byte32,
     dao_data_hash: Byte32,
     dao_type_hash: Byte32,
+    // Old chain specs may not have multisig system cell
+    multisig_type_hash: Option<Byte32>,
 }
 
 impl GenesisInfo {
@@ -62,6 +71,7 @@ impl GenesisInfo {
         let mut secp_type_hash = None;
         let mut dao_data_hash = None;
         let mut dao_type_hash = None;
+        let mut multisig_type_hash = None;
         let out_points = genesis_block
             .transactions()
             .iter()
@@ -102,6 +112,13 @@ impl GenesisInfo {
                             }
                             dao_data_hash = Some(data_hash);
                         }
+                        if tx_index == MULTISIG_TRANSACTION_INDEX && index == MULTISIG_OUTPUT_INDEX
+                        {
+                            multisig_type_hash = output
+                                .type_()
+                                .to_opt()
+                                .map(|script| script.calc_script_hash());
+                        }
                         OutPoint::new(tx.hash(), index as u32)
                     })
                     .collect::<Vec<_>>()
@@ -123,6 +140,7 @@ impl GenesisInfo {
             secp_type_hash,
             dao_data_hash,
             dao_type_hash,
+            multisig_type_hash,
         })
     }
 
@@ -146,6 +164,38 @@ impl GenesisInfo {
         &self.dao_type_hash
     }
 
+    pub fn multisig_type_hash(&self) -> Result<&Byte32, String> {
+        self.multisig_type_hash
+            .as_ref()
+            .ok_or_else(|| "No type hash(multisig) found in txs[0][4]".to_owned())
+    }
+
+    /// Lock script of an address (secp sighash or multisig by code hash index)
+    pub fn lock_script(&self, address: &Address) -> Result<Script, String> {
+        match address.index() {
+            CodeHashIndex::Default => Ok(address.lock_script(self.secp_type_hash.clone())),
+            CodeHashIndex::Multisig => Ok(address.lock_script(self.multisig_type_hash()?.clone())),
+        }
+    }
+
+    /// Multisig lock with since (args: multisig hash | since), can not be unlocked before
+    /// `since`. A secp address is converted to a 1-of-1 multisig config.
+    pub fn timelock_script(&self, address: &Address, since: Since) -> Result<Script, String> {
+        let multisig_hash = match address.index() {
+            CodeHashIndex::Default => {
+                MultisigConfig::new(vec![address.hash().clone()], 0, 1)?.hash160()
+            }
+            CodeHashIndex::Multisig => address.hash().clone(),
+        };
+        let mut args = multisig_hash.as_bytes().to_vec();
+        args.extend_from_slice(&since.value().to_le_bytes());
+        Ok(Script::new_builder()
+            .args(Bytes::from(args).pack())
+            .code_hash(self.multisig_type_hash()?.clone())
+            .hash_type(ScriptHashType::Type.into())
+            .build())
+    }
+
     pub fn secp_dep(&self) -> CellDep {
         CellDep::new_builder()
             .out_point(
@@ -160,9 +210,25 @@ impl GenesisInfo {
             .out_point(self.out_points[DAO_TRANSACTION_INDEX][DAO_OUTPUT_INDEX].clone())
             .build()
     }
+
+    pub fn multisig_dep(&self) -> Result<CellDep, String> {
+        let out_point = self
+            .out_points
+            .get(MULTISIG_GROUP_TRANSACTION_INDEX)
+            .and_then(|out_points| out_points.get(MULTISIG_GROUP_OUTPUT_INDEX))
+            .ok_or_else(|| "No multisig dep group found in txs[1][1]".to_owned())?;
+        Ok(CellDep::new_builder()
+            .out_point(out_point.clone())
+            .dep_type(DepType::DepGroup.into())
+            .build())
+    }
 }
 
-// NOTE: We assume all inputs from same account
+// (cell dep of the lock, input, output, output data)
+type AcpCell = (CellDep, CellInput, CellOutput, Bytes);
+
+/// Builds the transfer and NervosDAO transactions. The inputs are locked by the sighash lock of
+/// `from_address` unless `set_input_locks` is called, and each lock group is signed once.
 #[derive(Debug)]
 pub struct TransferTransactionBuilder<'a> {
     from_address: &'a Address,
@@ -171,6 +237,14 @@ pub struct TransferTransactionBuilder<'a> {
     to_address: &'a Address,
     to_capacity: u64,
     tx_fee: u64,
+    extra_outputs: Vec<(Address, u64, Bytes)>,
+    change_address: Option<Address>,
+    change_policy: ChangePolicy,
+    to_since: Option<Since>,
+    to_lock: Option<Script>,
+    to_acp_cell: Option<AcpCell>,
+    output_type: Option<(Script, Option<CellDep>)>,
+    input_locks: Option<Vec<Script>>,
 
     inputs: Vec<CellInput>,
     outputs: Vec<(CellOutput, Bytes)>,
@@ -205,6 +279,14 @@ impl<'a> TransferTransactionBuilder<'a> {
             inputs,
             witnesses,
 
+            extra_outputs: Vec::new(),
+            change_address: None,
+            change_policy: ChangePolicy::default(),
+            to_since: None,
+            to_lock: None,
+            to_acp_cell: None,
+            output_type: None,
+            input_locks: None,
             outputs: Vec::new(),
             changes: Vec::new(),
             cell_deps: Vec::new(),
@@ -212,38 +294,162 @@ impl<'a> TransferTransactionBuilder<'a> {
         }
     }
 
+    /// Add an extra output besides the `to_address` one, also paid by sender
+    pub fn add_output(
+        &mut self,
+        address: Address,
+        capacity: u64,
+        data: Bytes,
+    ) -> Result<(), String> {
+        if self.from_capacity < self.output_capacity() + capacity + self.tx_fee {
+            return Err(format!(
+                "Capacity not enough: {} < {}",
+                self.from_capacity,
+                self.output_capacity() + capacity + self.tx_fee
+            ));
+        }
+        self.extra_outputs.push((address, capacity, data));
+        Ok(())
+    }
+
+    /// The change output goes to this address instead of sender
+    pub fn set_change_address(&mut self, address: Address) {
+        self.change_address = Some(address);
+    }
+
+    /// How the change (and the dust too small for a change output) is handled
+    pub fn set_change_policy(&mut self, policy: ChangePolicy) {
+        self.change_policy = policy;
+    }
+
+    /// The `to_address` output is time locked until `since`
+    pub fn set_to_since(&mut self, since: Since) {
+        self.to_since = Some(since);
+    }
+
+    /// The `to_address` output is locked by this script instead of the sighash lock of
+    /// `to_address` (can not be time locked)
+    pub fn set_to_lock(&mut self, lock: Script) {
+        self.to_lock = Some(lock);
+    }
+
+    /// `transfer` tops up this anyone-can-pay cell (owned by `to_address`) instead of creating
+    /// a new cell, the data of the cell is kept (`to_data` is ignored)
+    pub fn set_to_acp_cell(
+        &mut self,
+        acp_dep: CellDep,
+        input: CellInput,
+        output: CellOutput,
+        data: Bytes,
+    ) {
+        self.to_acp_cell = Some((acp_dep, input, output, data));
+    }
+
+    /// The outputs (not including the change) of `transfer` are typed by this script (like
+    /// simple UDT), `cell_dep` is the code of the type script (`None` for builtin scripts like
+    /// type id)
+    pub fn set_output_type(&mut self, type_script: Script, cell_dep: Option<CellDep>) {
+        self.output_type = Some((type_script, cell_dep));
+    }
+
+    /// The lock scripts of the inputs (same order) when they are not all locked by the sighash
+    /// lock of `from_address`, the inputs are signed by lock groups (see `transfer_from_locks`)
+    pub fn set_input_locks(&mut self, input_locks: Vec<Script>) {
+        self.input_locks = Some(input_locks);
+    }
+
+    fn output_capacity(&self) -> u64 {
+        self.to_capacity
+            + self
+                .extra_outputs
+                .iter()
+                .map(|(_, capacity, _)| capacity)
+                .sum::<u64>()
+    }
+
     pub fn transfer<F>(
         &mut self,
         genesis_info: &GenesisInfo,
-        build_witness: F,
+        mut build_witness: F,
     ) -> Result<TransactionView, String>
     where
         F: FnMut(&Vec<Vec<u8>>) -> Result<Bytes, String>,
     {
-        self.cell_deps.extend(vec![genesis_info.secp_dep()]);
-        self.build_outputs(genesis_info);
-        self.build_changes(genesis_info);
-        self.build_secp_witnesses(build_witness)?;
+        self.transfer_from_locks(genesis_info, |_, args| build_witness(args))
+    }
+
+    /// `transfer` spending the inputs of several locks (see `set_input_locks`),
+    /// `build_witness` is called once per lock group with its lock script
+    pub fn transfer_from_locks<F>(
+        &mut self,
+        genesis_info: &GenesisInfo,
+        build_witness: F,
+    ) -> Result<TransactionView, String>
+    where
+        F: FnMut(&Script, &Vec<Vec<u8>>) -> Result<Bytes, String>,
+    {
+        if let Some(acp_cell) = self.to_acp_cell.take() {
+            return self.transfer_to_acp(genesis_info, acp_cell, build_witness);
+        }
+        self.build_transfer(genesis_info)?;
+        self.build_secp_witnesses(genesis_info, |transaction, input_locks| {
+            sign_secp_groups(transaction, input_locks, build_witness)
+        })?;
+        Ok(self.build_transaction())
+    }
+
+    /// `transfer_from_locks` for a signer asking nothing from the user (like a private key), the
+    /// lock groups are signed in parallel (see `par_sign_secp_groups`)
+    pub fn par_transfer_from_locks<F>(
+        &mut self,
+        genesis_info: &GenesisInfo,
+        build_witness: F,
+    ) -> Result<TransactionView, String>
+    where
+        F: Fn(&Script, &Vec<Vec<u8>>) -> Result<Bytes, String> + Sync,
+    {
+        if let Some(acp_cell) = self.to_acp_cell.take() {
+            return self.transfer_to_acp(genesis_info, acp_cell, &build_witness);
+        }
+        self.build_transfer(genesis_info)?;
+        self.build_secp_witnesses(genesis_info, |transaction, input_locks| {
+            par_sign_secp_groups(transaction, input_locks, &build_witness)
+        })?;
         Ok(self.build_transaction())
     }
 
+    fn build_transfer(&mut self, genesis_info: &GenesisInfo) -> Result<(), String> {
+        self.cell_deps.extend(vec![genesis_info.secp_dep()]);
+        self.build_outputs(genesis_info)?;
+        self.build_output_type();
+        self.build_changes(genesis_info)?;
+        self.check_outputs()
+    }
+
+    /// Deposit to NervosDAO, `build_witness` is called once per lock group (see
+    /// `transfer_from_locks`)
     pub fn deposit_dao<F>(
         &mut self,
         genesis_info: &GenesisInfo,
         build_witness: F,
     ) -> Result<TransactionView, String>
     where
-        F: FnMut(&Vec<Vec<u8>>) -> Result<Bytes, String>,
+        F: FnMut(&Script, &Vec<Vec<u8>>) -> Result<Bytes, String>,
     {
         self.cell_deps
             .extend(vec![genesis_info.secp_dep(), genesis_info.dao_dep()]);
-        self.build_outputs(genesis_info);
-        self.build_changes(genesis_info);
+        self.build_outputs(genesis_info)?;
+        self.build_changes(genesis_info)?;
         self.build_dao_type(genesis_info);
-        self.build_secp_witnesses(build_witness)?;
+        self.check_outputs()?;
+        self.build_secp_witnesses(genesis_info, |transaction, input_locks| {
+            sign_secp_groups(transaction, input_locks, build_witness)
+        })?;
         Ok(self.build_transaction())
     }
 
+    /// Withdraw from NervosDAO, `build_witness` is called once per lock group (see
+    /// `transfer_from_locks`)
     pub fn withdraw_dao<F>(
         &mut self,
         withdraw_header_hash: H256,
@@ -252,54 +458,113 @@ impl<'a> TransferTransactionBuilder<'a> {
         build_witness: F,
     ) -> Result<TransactionView, String>
     where
-        F: FnMut(&Vec<Vec<u8>>) -> Result<Bytes, String>,
+        F: FnMut(&Script, &Vec<Vec<u8>>) -> Result<Bytes, String>,
     {
         self.cell_deps
             .extend(vec![genesis_info.secp_dep(), genesis_info.dao_dep()]);
         self.header_deps.push(withdraw_header_hash.pack());
         self.header_deps
             .extend(input_header_hashes.into_iter().map(|h| h.pack()));
-        self.build_outputs(genesis_info);
-        self.build_changes(genesis_info);
+        self.build_outputs(genesis_info)?;
+        self.build_changes(genesis_info)?;
+        self.check_outputs()?;
         self.build_dao_witnesses();
-        self.build_secp_witnesses(build_witness)?;
+        self.build_secp_witnesses(genesis_info, |transaction, input_locks| {
+            sign_secp_groups(transaction, input_locks, build_witness)
+        })?;
         Ok(self.build_transaction())
     }
 
-    // NOTE: We assume all inputs from same account
-    fn build_secp_witnesses<F>(&mut self, mut build_witness: F) -> Result<(), String>
+    // The ACP cell is spent as the last input (with an empty witness) and recreated as the first
+    // output with `to_capacity` more, only the sender's inputs are signed.
+    fn transfer_to_acp<F>(
+        &mut self,
+        genesis_info: &GenesisInfo,
+        acp_cell: AcpCell,
+        build_witness: F,
+    ) -> Result<TransactionView, String>
     where
-        F: FnMut(&Vec<Vec<u8>>) -> Result<Bytes, String>,
+        F: FnMut(&Script, &Vec<Vec<u8>>) -> Result<Bytes, String>,
     {
-        let transaction = self.build_transaction();
-
-        let init_witness = if self.witnesses[0].is_empty() {
-            WitnessArgs::default()
-        } else {
-            WitnessArgs::from_slice(&self.witnesses[0]).map_err(|err| err.to_string())?
-        };
-
-        let init_witness = init_witness
+        if self.to_since.is_some() || self.to_lock.is_some() {
+            return Err("Anyone-can-pay cell can not be time locked or relocked".to_owned());
+        }
+        let (acp_dep, acp_input, acp_output, acp_data) = acp_cell;
+        let sender_locks = self.input_locks(genesis_info)?;
+        self.cell_deps
+            .extend(vec![genesis_info.secp_dep(), acp_dep]);
+        self.build_outputs(genesis_info)?;
+        let old_capacity: u64 = acp_output.capacity().unpack();
+        let output = acp_output
             .as_builder()
-            .lock(Some(Bytes::from(vec![0u8; 65])).pack())
+            .capacity(Capacity::shannons(old_capacity + self.to_capacity).pack())
             .build();
-        let mut sign_args = vec![
-            transaction.hash().raw_data().to_vec(),
-            (init_witness.as_bytes().len() as u64)
-                .to_le_bytes()
-                .to_vec(),
-            init_witness.as_bytes().to_vec(),
-        ];
-        for other_witness in self.witnesses.iter().skip(1) {
-            sign_args.push((other_witness.len() as u64).to_le_bytes().to_vec());
-            sign_args.push(other_witness.to_vec());
+        self.outputs[0] = (output, acp_data);
+        self.build_changes(genesis_info)?;
+        self.check_outputs()?;
+        self.inputs.push(acp_input);
+        self.witnesses.push(Bytes::default());
+        sign_secp_groups(&self.build_transaction(), &sender_locks, build_witness)
+    }
+
+    /// Build an unsigned transaction spending multisig cells (`from_address` must be the
+    /// multisig address), the first witness lock is a placeholder for signatures.
+    pub fn transfer_multisig(
+        &mut self,
+        genesis_info: &GenesisInfo,
+        config: &MultisigConfig,
+    ) -> Result<TransactionView, String> {
+        if self.from_address != &config.to_address() {
+            return Err("Sender is not the multisig address of config".to_owned());
         }
-        let sig = build_witness(&sign_args)?;
-        self.witnesses[0] = init_witness
+        self.cell_deps.extend(vec![genesis_info.multisig_dep()?]);
+        self.build_outputs(genesis_info)?;
+        self.build_changes(genesis_info)?;
+        self.check_outputs()?;
+        self.witnesses[0] = WitnessArgs::default()
             .as_builder()
-            .lock(Some(sig).pack())
+            .lock(Some(LockWitness::Multisig(config).placeholder()).pack())
             .build()
             .as_bytes();
+        Ok(self.build_transaction())
+    }
+
+    // All the inputs are locked by the sighash lock of sender unless `input_locks` is set
+    fn input_locks(&self, genesis_info: &GenesisInfo) -> Result<Vec<Script>, String> {
+        match self.input_locks.as_ref() {
+            Some(input_locks) => {
+                if input_locks.len() != self.inputs.len() {
+                    return Err(format!(
+                        "Number of input locks ({}) not match inputs ({})",
+                        input_locks.len(),
+                        self.inputs.len()
+                    ));
+                }
+                Ok(input_locks.clone())
+            }
+            None => Ok(vec![
+                genesis_info.lock_script(self.from_address)?;
+                self.inputs.len()
+            ]),
+        }
+    }
+
+    // `sign_groups` signs the lock groups of the unsigned transaction (with the input locks)
+    fn build_secp_witnesses<S>(
+        &mut self,
+        genesis_info: &GenesisInfo,
+        sign_groups: S,
+    ) -> Result<(), String>
+    where
+        S: FnOnce(&TransactionView, &[Script]) -> Result<TransactionView, String>,
+    {
+        let input_locks = self.input_locks(genesis_info)?;
+        let transaction = sign_groups(&self.build_transaction(), &input_locks)?;
+        self.witnesses = transaction
+            .witnesses()
+            .into_iter()
+            .map(|witness| witness.raw_data())
+            .collect();
         Ok(())
     }
 
@@ -313,32 +578,73 @@ impl<'a> TransferTransactionBuilder<'a> {
         }
     }
 
-    fn build_outputs(&mut self, genesis_info: &GenesisInfo) {
+    fn build_outputs(&mut self, genesis_info: &GenesisInfo) -> Result<(), String> {
+        let to_lock = match (self.to_lock.as_ref(), self.to_since) {
+            (Some(_), Some(_)) => {
+                return Err("Custom target lock script can not be time locked".to_owned());
+            }
+            (Some(lock), None) => lock.clone(),
+            (None, Some(since)) => genesis_info.timelock_script(self.to_address, since)?,
+            (None, None) => genesis_info.lock_script(self.to_address)?,
+        };
         let output = CellOutput::new_builder()
             .capacity(Capacity::shannons(self.to_capacity).pack())
-            .lock(
-                self.to_address
-                    .lock_script(genesis_info.secp_type_hash.clone())
-                    .to_owned(),
-            )
+            .lock(to_lock)
             .build();
         self.outputs.push((output, self.to_data.clone()));
+        for (address, capacity, data) in self.extra_outputs.iter() {
+            let output = CellOutput::new_builder()
+                .capacity(Capacity::shannons(*capacity).pack())
+                .lock(genesis_info.lock_script(address)?)
+                .build();
+            self.outputs.push((output, data.clone()));
+        }
+        Ok(())
     }
 
-    // Exchange back to sender if the rest is enough to pay for a cell
-    fn build_changes(&mut self, genesis_info: &GenesisInfo) {
-        let rest_capacity = self.from_capacity - self.to_capacity - self.tx_fee;
-        if rest_capacity >= *MIN_SECP_CELL_CAPACITY {
-            // The rest send back to sender
-            let change = CellOutput::new_builder()
-                .capacity(Capacity::shannons(rest_capacity).pack())
-                .lock(
-                    self.from_address
-                        .lock_script(genesis_info.secp_type_hash.to_owned()),
-                )
-                .build();
-            let change_data = Bytes::default();
-            self.changes.push((change, change_data));
+    // Exchange back to sender (or the change address) if the rest is enough to pay for a cell,
+    // otherwise the dust is handled by the change policy
+    fn build_changes(&mut self, genesis_info: &GenesisInfo) -> Result<(), String> {
+        let rest_capacity = self.from_capacity - self.output_capacity() - self.tx_fee;
+        match self.change_policy.resolve(rest_capacity)? {
+            Change::Output(capacity) => {
+                let change_address = self.change_address.as_ref().unwrap_or(self.from_address);
+                let change = CellOutput::new_builder()
+                    .capacity(Capacity::shannons(capacity).pack())
+                    .lock(genesis_info.lock_script(change_address)?)
+                    .build();
+                let change_data = Bytes::default();
+                self.changes.push((change, change_data));
+            }
+            Change::Receiver(capacity) => {
+                let (output, _) = &mut self.outputs[0];
+                let old_capacity: u64 = output.capacity().unpack();
+                *output = output
+                    .clone()
+                    .as_builder()
+                    .capacity(Capacity::shannons(old_capacity + capacity).pack())
+                    .build();
+            }
+            Change::Nothing | Change::Fee(_) => {}
+        }
+        Ok(())
+    }
+
+    fn build_output_type(&mut self) {
+        if let Some((type_script, cell_dep)) = self.output_type.clone() {
+            self.cell_deps.extend(cell_dep);
+            self.outputs = self
+                .outputs
+                .iter()
+                .cloned()
+                .map(|(output, output_data)| {
+                    let new_output = output
+                        .as_builder()
+                        .type_(Some(type_script.clone()).pack())
+                        .build();
+                    (new_output, output_data)
+                })
+                .collect();
         }
     }
 
@@ -359,6 +665,13 @@ impl<'a> TransferTransactionBuilder<'a> {
             .collect();
     }
 
+    // Before signing, the change outputs follow the other outputs
+    fn check_outputs(&self) -> Result<(), String> {
+        let changes =
+            (self.outputs.len()..self.outputs.len() + self.changes.len()).collect::<Vec<_>>();
+        check_output_capacities(&self.build_transaction(), &changes)
+    }
+
     fn build_transaction(&self) -> TransactionView {
         let (outputs, outputs_data): (Vec<_>, Vec<_>) = self.outputs.iter().cloned().unzip();
         let (changes, changes_data): (Vec<_>, Vec<_>) = self.changes.iter().cloned().unzip();
@@ -375,6 +688,290 @@ impl<'a> TransferTransactionBuilder<'a> {
     }
 }
 
+/// Every output must hold its occupied capacity (lock script, type script and data), or the
+/// transaction is rejected by the node. `changes` are the indexes of the change outputs, a too
+/// small change is suggested to be paid as fee instead.
+pub fn check_output_capacities(
+    transaction: &TransactionView,
+    changes: &[usize],
+) -> Result<(), String> {
+    for (index, (output, data)) in transaction.outputs_with_data_iter().enumerate() {
+        let occupied = output
+            .occupied_capacity(Capacity::bytes(data.len()).map_err(|err| err.to_string())?)
+            .map_err(|err| err.to_string())?
+            .as_u64();
+        let capacity: u64 = output.capacity().unpack();
+        if capacity >= occupied {
+            continue;
+        }
+        let mut message = format!(
+            "Output #{} has {} shannons, less than its occupied capacity {} shannons (lock script, type script and {} bytes of data)",
+            index,
+            capacity,
+            occupied,
+            data.len()
+        );
+        if changes.contains(&index) {
+            message.push_str(", the change is too small for a cell: pay it as transaction fee (dust policy: fee) or raise the minimal change capacity");
+        }
+        return Err(message);
+    }
+    Ok(())
+}
+
+/// Sign a transaction of which all the inputs are locked by the same secp256k1 sighash-all lock
+/// (the signature goes to the lock of first witness, the other witnesses are kept).
+pub fn sign_secp_transaction<F>(
+    transaction: &TransactionView,
+    build_witness: F,
+) -> Result<TransactionView, String>
+where
+    F: FnMut(&Vec<Vec<u8>>) -> Result<Bytes, String>,
+{
+    let mut witnesses = transaction
+        .witnesses()
+        .into_iter()
+        .map(|witness| witness.raw_data())
+        .collect::<Vec<_>>();
+    if witnesses.is_empty() {
+        return Err("No witness found in transaction".to_owned());
+    }
+    witnesses[0] = sign_secp_witnesses(&transaction.hash(), &witnesses, build_witness)?;
+    Ok(transaction
+        .as_advanced_builder()
+        .set_witnesses(witnesses.iter().map(Pack::pack).collect())
+        .build())
+}
+
+/// Sign the inputs (by index) locked by the same secp256k1 sighash-all lock in a transaction
+/// which may have other lock groups. The signature goes to the witness of the first input in
+/// `group`, the message covers the witnesses of the group and the ones beyond inputs.
+pub fn sign_secp_group<F>(
+    transaction: &TransactionView,
+    group: &[usize],
+    build_witness: F,
+) -> Result<TransactionView, String>
+where
+    F: FnMut(&Vec<Vec<u8>>) -> Result<Bytes, String>,
+{
+    sign_lock_group(transaction, group, LockWitness::Sighash, build_witness)
+}
+
+/// The inputs (by index) grouped by lock script, in the order of their first input
+pub fn group_inputs_by_lock(input_locks: &[Script]) -> Vec<(Script, Vec<usize>)> {
+    let mut groups: Vec<(Script, Vec<usize>)> = Vec::new();
+    for (index, lock) in input_locks.iter().enumerate() {
+        match groups
+            .iter_mut()
+            .find(|(other, _)| other.as_slice() == lock.as_slice())
+        {
+            Some((_, group)) => group.push(index),
+            None => groups.push((lock.clone(), vec![index])),
+        }
+    }
+    groups
+}
+
+/// Sign the inputs of every secp256k1 sighash-all lock group, one message per group.
+/// `input_locks` are the locks of the first inputs (the rest inputs are not signed), and
+/// `build_witness` is called with the lock script of each group.
+pub fn sign_secp_groups<F>(
+    transaction: &TransactionView,
+    input_locks: &[Script],
+    mut build_witness: F,
+) -> Result<TransactionView, String>
+where
+    F: FnMut(&Script, &Vec<Vec<u8>>) -> Result<Bytes, String>,
+{
+    let mut transaction = transaction.clone();
+    for (lock, group) in group_inputs_by_lock(input_locks) {
+        transaction = sign_secp_group(&transaction, &group, |args| build_witness(&lock, args))?;
+    }
+    Ok(transaction)
+}
+
+/// `sign_secp_groups` with the groups signed in parallel, for a signer asking nothing from the
+/// user (like a private key). The groups cover disjoint witnesses, so every message is computed
+/// from the unsigned transaction.
+pub fn par_sign_secp_groups<F>(
+    transaction: &TransactionView,
+    input_locks: &[Script],
+    build_witness: F,
+) -> Result<TransactionView, String>
+where
+    F: Fn(&Script, &Vec<Vec<u8>>) -> Result<Bytes, String> + Sync,
+{
+    let inputs_len = transaction.inputs().len();
+    if input_locks.len() > inputs_len {
+        return Err(format!(
+            "Number of input locks ({}) exceeds inputs ({})",
+            input_locks.len(),
+            inputs_len
+        ));
+    }
+    let tx_hash = transaction.hash();
+    let mut witnesses = padded_witnesses(transaction);
+    let signed = group_inputs_by_lock(input_locks)
+        .par_iter()
+        .map(|(lock, group)| {
+            let group_witnesses = group
+                .iter()
+                .map(|index| witnesses[*index].clone())
+                .chain(witnesses[inputs_len..].iter().cloned())
+                .collect::<Vec<_>>();
+            let witness =
+                sign_secp_witnesses(&tx_hash, &group_witnesses, |args| build_witness(lock, args))?;
+            Ok((group[0], witness))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    for (index, witness) in signed {
+        witnesses[index] = witness;
+    }
+    Ok(transaction
+        .as_advanced_builder()
+        .set_witnesses(witnesses.iter().map(Pack::pack).collect())
+        .build())
+}
+
+/// The message (blake2b digest) signed by `sign_secp_group`, for verifying what a hardware
+/// wallet or another party is asked to sign.
+pub fn secp_group_message(transaction: &TransactionView, group: &[usize]) -> Result<H256, String> {
+    lock_group_message(transaction, group, LockWitness::Sighash)
+}
+
+/// `sign_secp_group` for the locks of other witness layout (like multisig or a custom
+/// `WitnessTemplate`), the message is computed with the placeholder of `lock_witness` and
+/// `build_witness` returns the whole witness lock.
+pub fn sign_lock_group<F>(
+    transaction: &TransactionView,
+    group: &[usize],
+    lock_witness: LockWitness,
+    build_witness: F,
+) -> Result<TransactionView, String>
+where
+    F: FnMut(&Vec<Vec<u8>>) -> Result<Bytes, String>,
+{
+    let (mut witnesses, group_witnesses) = group_witnesses(transaction, group)?;
+    witnesses[group[0]] = sign_witnesses(
+        &transaction.hash(),
+        &group_witnesses,
+        lock_witness.placeholder(),
+        build_witness,
+    )?;
+    Ok(transaction
+        .as_advanced_builder()
+        .set_witnesses(witnesses.iter().map(Pack::pack).collect())
+        .build())
+}
+
+/// The message (blake2b digest) signed by `sign_lock_group`
+pub fn lock_group_message(
+    transaction: &TransactionView,
+    group: &[usize],
+    lock_witness: LockWitness,
+) -> Result<H256, String> {
+    let (_, group_witnesses) = group_witnesses(transaction, group)?;
+    let mut message = [0u8; 32];
+    sign_witnesses(
+        &transaction.hash(),
+        &group_witnesses,
+        lock_witness.placeholder(),
+        |args| {
+            message = blake2b_args(args);
+            Ok(lock_witness.placeholder())
+        },
+    )?;
+    Ok(H256::from(message))
+}
+
+// All the witnesses (padded to inputs length) and the ones covered by the signature of a group
+fn group_witnesses(
+    transaction: &TransactionView,
+    group: &[usize],
+) -> Result<(Vec<Bytes>, Vec<Bytes>), String> {
+    let inputs_len = transaction.inputs().len();
+    if group.is_empty() || group.iter().any(|index| *index >= inputs_len) {
+        return Err(format!("Invalid input group: {:?}", group));
+    }
+    let witnesses = padded_witnesses(transaction);
+    let group_witnesses = group
+        .iter()
+        .map(|index| witnesses[*index].clone())
+        .chain(witnesses[inputs_len..].iter().cloned())
+        .collect::<Vec<_>>();
+    Ok((witnesses, group_witnesses))
+}
+
+// The witnesses of the transaction, padded with empty ones to the inputs length
+fn padded_witnesses(transaction: &TransactionView) -> Vec<Bytes> {
+    let mut witnesses = transaction
+        .witnesses()
+        .into_iter()
+        .map(|witness| witness.raw_data())
+        .collect::<Vec<_>>();
+    while witnesses.len() < transaction.inputs().len() {
+        witnesses.push(Bytes::default());
+    }
+    witnesses
+}
+
+// Return the signed first witness of a secp256k1 sighash-all lock group
+fn sign_secp_witnesses<F>(
+    tx_hash: &Byte32,
+    witnesses: &[Bytes],
+    build_witness: F,
+) -> Result<Bytes, String>
+where
+    F: FnMut(&Vec<Vec<u8>>) -> Result<Bytes, String>,
+{
+    sign_witnesses(
+        tx_hash,
+        witnesses,
+        LockWitness::Sighash.placeholder(),
+        build_witness,
+    )
+}
+
+// Return the signed first witness of a lock group, the message is computed with `placeholder`
+// as the witness lock
+fn sign_witnesses<F>(
+    tx_hash: &Byte32,
+    witnesses: &[Bytes],
+    placeholder: Bytes,
+    mut build_witness: F,
+) -> Result<Bytes, String>
+where
+    F: FnMut(&Vec<Vec<u8>>) -> Result<Bytes, String>,
+{
+    let init_witness = if witnesses[0].is_empty() {
+        WitnessArgs::default()
+    } else {
+        WitnessArgs::from_slice(&witnesses[0]).map_err(|err| err.to_string())?
+    };
+
+    let init_witness = init_witness
+        .as_builder()
+        .lock(Some(placeholder).pack())
+        .build();
+    let mut sign_args = vec![
+        tx_hash.raw_data().to_vec(),
+        (init_witness.as_bytes().len() as u64)
+            .to_le_bytes()
+            .to_vec(),
+        init_witness.as_bytes().to_vec(),
+    ];
+    for other_witness in witnesses.iter().skip(1) {
+        sign_args.push((other_witness.len() as u64).to_le_bytes().to_vec());
+        sign_args.push(other_witness.to_vec());
+    }
+    let sig = build_witness(&sign_args)?;
+    Ok(init_witness
+        .as_builder()
+        .lock(Some(sig).pack())
+        .build()
+        .as_bytes())
+}
+
 pub fn build_witness_with_key(privkey: &secp256k1::SecretKey, args: &[Vec<u8>]) -> Bytes {
     let message = secp256k1::Message::from_slice(&blake2b_args(args))
         .expect("Convert to secp256k1 message failed");
@@ -398,3 +995,76 @@ pub fn blake2b_args(args: &[Vec<u8>]) -> [u8; 32] {
     blake2b.finalize(&mut digest);
     digest
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_check_output_capacities() {
+        let lock = Script::new_builder()
+            .args(H160::default().as_bytes().pack())
+            .build();
+        let output = |capacity: u64| {
+            CellOutput::new_builder()
+                .lock(lock.clone())
+                .capacity(Capacity::shannons(capacity).pack())
+                .build()
+        };
+        let min = *MIN_SECP_CELL_CAPACITY;
+        let transaction = TransactionBuilder::default()
+            .output(output(min))
+            .output_data(Bytes::new().pack())
+            .output(output(min + 7))
+            .output_data(Bytes::from(vec![0u8; 8]).pack())
+            .build();
+        assert!(check_output_capacities(&transaction, &[]).is_ok());
+        let err = check_output_capacities(&transaction, &[1]).unwrap_err();
+        assert!(err.starts_with("Output #1 has"));
+        assert!(err.contains("dust policy"));
+    }
+
+    #[test]
+    fn test_sign_secp_groups() {
+        let lock = |arg: u8| {
+            Script::new_builder()
+                .args(Bytes::from(vec![arg; 20]).pack())
+                .build()
+        };
+        let input_locks = vec![lock(1), lock(2), lock(1)];
+        let groups = group_inputs_by_lock(&input_locks);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].1, vec![0, 2]);
+        assert_eq!(groups[1].1, vec![1]);
+
+        let input = |index: u32| CellInput::new(OutPoint::new(Byte32::default(), index), 0);
+        // The last input is not signed
+        let transaction = TransactionBuilder::default()
+            .inputs(vec![input(0), input(1), input(2), input(3)])
+            .build();
+        let mut signed_by = Vec::new();
+        let signed = sign_secp_groups(&transaction, &input_locks, |lock, _| {
+            signed_by.push(lock.args().raw_data()[0]);
+            Ok(Bytes::from(vec![lock.args().raw_data()[0]; 65]))
+        })
+        .unwrap();
+        assert_eq!(signed_by, vec![1, 2]);
+        let witness_lock = |index: usize| {
+            let witness = signed.witnesses().get(index).unwrap().raw_data();
+            if witness.is_empty() {
+                return None;
+            }
+            let witness = WitnessArgs::from_slice(&witness).unwrap();
+            witness.lock().to_opt().map(|lock| lock.raw_data()[0])
+        };
+        assert_eq!(witness_lock(0), Some(1));
+        assert_eq!(witness_lock(1), Some(2));
+        assert_eq!(witness_lock(2), None);
+        assert_eq!(witness_lock(3), None);
+        let par_signed = par_sign_secp_groups(&transaction, &input_locks, |lock, _| {
+            Ok(Bytes::from(vec![lock.args().raw_data()[0]; 65]))
+        })
+        .unwrap();
+        assert_eq!(par_signed.witnesses(), signed.witnesses());
+    }
+}
@@ -0,0 +1,130 @@
+use std::fmt;
+
+use crate::MIN_SECP_CELL_CAPACITY;
+
+/// What to do with the rest capacity too small for a change output
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum DustPolicy {
+    /// Donate it to the transaction fee
+    Fee,
+    /// Add it to the receiver output (the first output)
+    Receiver,
+    /// Refuse to build the transaction
+    Fail,
+}
+
+impl DustPolicy {
+    pub fn from_str(input: &str) -> Result<DustPolicy, String> {
+        match input {
+            "fee" => Ok(DustPolicy::Fee),
+            "receiver" => Ok(DustPolicy::Receiver),
+            "fail" => Ok(DustPolicy::Fail),
+            _ => Err(format!(
+                "Invalid dust policy: {}, expected: fee|receiver|fail",
+                input
+            )),
+        }
+    }
+}
+
+impl fmt::Display for DustPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DustPolicy::Fee => write!(f, "fee"),
+            DustPolicy::Receiver => write!(f, "receiver"),
+            DustPolicy::Fail => write!(f, "fail"),
+        }
+    }
+}
+
+/// How the rest capacity (inputs - outputs - fee) of a transaction is handled
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct ChangePolicy {
+    min_change_capacity: u64,
+    dust: DustPolicy,
+}
+
+impl Default for ChangePolicy {
+    fn default() -> ChangePolicy {
+        ChangePolicy {
+            min_change_capacity: *MIN_SECP_CELL_CAPACITY,
+            dust: DustPolicy::Fee,
+        }
+    }
+}
+
+/// The decision on the rest capacity
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Change {
+    /// Nothing left
+    Nothing,
+    /// Send back by a change output
+    Output(u64),
+    /// Add to the receiver output
+    Receiver(u64),
+    /// Paid as transaction fee
+    Fee(u64),
+}
+
+impl ChangePolicy {
+    /// `min_change_capacity` can not be less than the capacity of an empty secp256k1 cell
+    pub fn new(min_change_capacity: u64, dust: DustPolicy) -> Result<ChangePolicy, String> {
+        if min_change_capacity < *MIN_SECP_CELL_CAPACITY {
+            return Err(format!(
+                "Minimal change capacity must >= {} (shannons)",
+                *MIN_SECP_CELL_CAPACITY
+            ));
+        }
+        Ok(ChangePolicy {
+            min_change_capacity,
+            dust,
+        })
+    }
+
+    pub fn min_change_capacity(&self) -> u64 {
+        self.min_change_capacity
+    }
+
+    pub fn dust(&self) -> DustPolicy {
+        self.dust
+    }
+
+    pub fn resolve(&self, rest_capacity: u64) -> Result<Change, String> {
+        if rest_capacity == 0 {
+            Ok(Change::Nothing)
+        } else if rest_capacity >= self.min_change_capacity {
+            Ok(Change::Output(rest_capacity))
+        } else {
+            match self.dust {
+                DustPolicy::Fee => Ok(Change::Fee(rest_capacity)),
+                DustPolicy::Receiver => Ok(Change::Receiver(rest_capacity)),
+                DustPolicy::Fail => Err(format!(
+                    "The change {} (shannons) is less than minimal change capacity {} (shannons), dust_policy=fail",
+                    rest_capacity, self.min_change_capacity
+                )),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_change_policy() {
+        let min = *MIN_SECP_CELL_CAPACITY;
+        assert!(ChangePolicy::new(min - 1, DustPolicy::Fee).is_err());
+
+        let policy = ChangePolicy::default();
+        assert_eq!(policy.resolve(0), Ok(Change::Nothing));
+        assert_eq!(policy.resolve(min), Ok(Change::Output(min)));
+        assert_eq!(policy.resolve(min - 1), Ok(Change::Fee(min - 1)));
+
+        let policy = ChangePolicy::new(min * 2, DustPolicy::Receiver).unwrap();
+        assert_eq!(policy.resolve(min), Ok(Change::Receiver(min)));
+        let policy = ChangePolicy::new(min, DustPolicy::Fail).unwrap();
+        assert!(policy.resolve(1).is_err());
+        assert_eq!(DustPolicy::from_str("receiver"), Ok(DustPolicy::Receiver));
+    }
+}
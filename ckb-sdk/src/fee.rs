@@ -0,0 +1,122 @@
+use ckb_types::{bytes::Bytes, core::TransactionView, prelude::*};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::MultisigConfig;
+
+const SIGNATURE_SIZE: usize = 65;
+// A transaction is stored in block as an item of dynamic vector (4 bytes offset)
+const TRANSACTION_OFFSET_SIZE: usize = 4;
+
+/// Witness lock of the inputs by lock type, used as signature placeholder so the size of an
+/// unsigned transaction equals the signed one.
+#[derive(Debug, Clone, Copy)]
+pub enum LockWitness<'a> {
+    // secp256k1 sighash-all (keystore, private key and hardware wallet are the same)
+    Sighash,
+    Multisig(&'a MultisigConfig),
+    // Non-standard lock signed by one secp256k1 recoverable signature
+    Custom(WitnessTemplate),
+}
+
+impl<'a> LockWitness<'a> {
+    pub fn lock_size(&self) -> usize {
+        match self {
+            LockWitness::Sighash => SIGNATURE_SIZE,
+            LockWitness::Multisig(config) => config.placeholder_witness_lock().len(),
+            LockWitness::Custom(template) => template.lock_size,
+        }
+    }
+
+    pub fn placeholder(&self) -> Bytes {
+        match self {
+            LockWitness::Sighash => Bytes::from(vec![0u8; SIGNATURE_SIZE]),
+            LockWitness::Multisig(config) => config.placeholder_witness_lock(),
+            LockWitness::Custom(template) => Bytes::from(vec![0u8; template.lock_size]),
+        }
+    }
+}
+
+/// Witness lock layout of a non-standard lock: `lock_size` bytes (zeroed when computing the
+/// signing message) with the 65 bytes signature at `signature_offset`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub struct WitnessTemplate {
+    pub lock_size: usize,
+    pub signature_offset: usize,
+}
+
+impl WitnessTemplate {
+    pub fn new(lock_size: usize, signature_offset: usize) -> Result<WitnessTemplate, String> {
+        if signature_offset + SIGNATURE_SIZE > lock_size {
+            return Err(format!(
+                "Signature (offset: {}, size: {}) is out of the witness lock (size: {})",
+                signature_offset, SIGNATURE_SIZE, lock_size
+            ));
+        }
+        Ok(WitnessTemplate {
+            lock_size,
+            signature_offset,
+        })
+    }
+
+    /// The witness lock with the signature put in place, the rest bytes are zero
+    pub fn witness_lock(&self, signature: &[u8]) -> Result<Bytes, String> {
+        if signature.len() != SIGNATURE_SIZE {
+            return Err(format!(
+                "Invalid signature length: {}, expected: {}",
+                signature.len(),
+                SIGNATURE_SIZE
+            ));
+        }
+        let mut data = vec![0u8; self.lock_size];
+        data[self.signature_offset..self.signature_offset + SIGNATURE_SIZE]
+            .copy_from_slice(signature);
+        Ok(Bytes::from(data))
+    }
+}
+
+/// Size of a transaction in block (the fee is charged by it)
+pub fn tx_size_in_block(transaction: &TransactionView) -> u64 {
+    (transaction.data().as_slice().len() + TRANSACTION_OFFSET_SIZE) as u64
+}
+
+/// Fee of a transaction, `fee_rate` is shannons per 1000 bytes (rounded up)
+pub fn calculate_tx_fee(tx_size: u64, fee_rate: u64) -> u64 {
+    let fee = u128::from(tx_size) * u128::from(fee_rate);
+    ((fee + 999) / 1000) as u64
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ckb_types::H160;
+
+    #[test]
+    fn test_calculate_tx_fee() {
+        assert_eq!(calculate_tx_fee(1000, 1000), 1000);
+        assert_eq!(calculate_tx_fee(1001, 1000), 1001);
+        assert_eq!(calculate_tx_fee(355, 1), 1);
+        assert_eq!(calculate_tx_fee(0, 1000), 0);
+    }
+
+    #[test]
+    fn test_lock_witness_size() {
+        assert_eq!(LockWitness::Sighash.lock_size(), 65);
+        let config = MultisigConfig::new(
+            vec![H160::default(), H160::from_slice(&[1u8; 20]).unwrap()],
+            0,
+            2,
+        )
+        .unwrap();
+        let lock_witness = LockWitness::Multisig(&config);
+        assert_eq!(lock_witness.lock_size(), 4 + 20 * 2 + 65 * 2);
+        assert_eq!(lock_witness.placeholder().len(), lock_witness.lock_size());
+
+        assert!(WitnessTemplate::new(80, 16).is_err());
+        let template = WitnessTemplate::new(81, 16).unwrap();
+        assert_eq!(LockWitness::Custom(template).placeholder().len(), 81);
+        let witness_lock = template.witness_lock(&[1u8; 65]).unwrap();
+        assert_eq!(&witness_lock[0..16], &[0u8; 16][..]);
+        assert_eq!(&witness_lock[16..81], &[1u8; 65][..]);
+        assert!(template.witness_lock(&[1u8; 64]).is_err());
+    }
+}
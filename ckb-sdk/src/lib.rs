@@ -1,21 +1,50 @@
+mod acp;
+mod address_payload;
 mod basic;
+mod cell_dep_resolver;
 mod chain;
+mod change;
 mod error;
+mod fee;
+mod multisig;
 mod rpc;
+mod since;
+mod sudt;
 mod transaction;
+mod type_id;
 
+pub mod stats;
 pub mod wallet;
 
-pub use basic::{Address, NetworkType, OldAddress, OldAddressFormat};
+pub use acp::{acp_min_capacity, AcpConfig};
+pub use address_payload::AddressPayload;
+pub use basic::{Address, CodeHashIndex, NetworkType, OldAddress, OldAddressFormat};
+pub use cell_dep_resolver::CellDepResolver;
 pub use chain::{
-    blake2b_args, build_witness_with_key, serialize_signature, GenesisInfo,
+    blake2b_args, build_witness_with_key, check_output_capacities, group_inputs_by_lock,
+    lock_group_message, par_sign_secp_groups, secp_group_message, serialize_signature,
+    sign_lock_group, sign_secp_group, sign_secp_groups, sign_secp_transaction, GenesisInfo,
     TransferTransactionBuilder, MIN_SECP_CELL_CAPACITY, ONE_CKB,
 };
+pub use change::{Change, ChangePolicy, DustPolicy};
 pub use error::Error;
-pub use rpc::HttpRpcClient;
+pub use fee::{calculate_tx_fee, tx_size_in_block, LockWitness, WitnessTemplate};
+pub use multisig::{
+    multisig_sign_args, recover_pubkey, recover_signer, set_multisig_witness, MultisigConfig,
+};
+pub use rpc::{
+    connection_config, retry_policy, set_connection_config, set_retry_policy, split_basic_auth,
+    BlockEconomicState, ConnectionConfig, Consensus, FeeRateStatics, HttpIndexerRpcClient,
+    HttpRpcClient, JsonU64, RetryPolicy, SubscriptionClient, SubscriptionEvent,
+    SUBSCRIPTION_TOPICS,
+};
+pub use since::{Since, SinceType};
+pub use sudt::{parse_udt_amount, udt_amount_data, udt_cell_capacity, SudtConfig, UDT_AMOUNT_LEN};
 pub use transaction::{
     MockCellDep, MockInfo, MockInput, MockResourceLoader, MockTransaction, MockTransactionHelper,
-    ReprMockCellDep, ReprMockInfo, ReprMockInput, ReprMockTransaction,
+    ReprMockCellDep, ReprMockInfo, ReprMockInput, ReprMockTransaction, ScriptGroupCycle,
+    ScriptGroupKind,
 };
+pub use type_id::{is_type_id_script, type_id_script, TYPE_ID_CODE_HASH};
 
 pub use ckb_crypto::secp::SECP256K1;
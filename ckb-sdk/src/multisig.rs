@@ -0,0 +1,257 @@
+use std::collections::HashSet;
+
+use ckb_hash::blake2b_256;
+use ckb_types::{
+    bytes::Bytes,
+    core::TransactionView,
+    packed::{self, WitnessArgs},
+    prelude::*,
+    H160, H256,
+};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{Address, SECP256K1};
+
+const MULTISIG_SCRIPT_RESERVED: u8 = 0;
+const SIGNATURE_SIZE: usize = 65;
+
+/// Multisig config of `secp256k1_blake160_multisig_all` lock, the lock args is blake160 of
+/// multisig script: `S | R | M | N | blake160(Pubkey1) | ... | blake160(PubkeyN)`
+#[derive(Hash, Eq, PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct MultisigConfig {
+    sighash_addresses: Vec<H160>,
+    require_first_n: u8,
+    threshold: u8,
+}
+
+impl MultisigConfig {
+    pub fn new(
+        sighash_addresses: Vec<H160>,
+        require_first_n: u8,
+        threshold: u8,
+    ) -> Result<MultisigConfig, String> {
+        if sighash_addresses.is_empty() || sighash_addresses.len() > 255 {
+            return Err(format!(
+                "Invalid pubkey count: {}, expected: 1..255",
+                sighash_addresses.len()
+            ));
+        }
+        let unique = sighash_addresses.iter().collect::<HashSet<_>>();
+        if unique.len() != sighash_addresses.len() {
+            return Err("Duplicated pubkey in multisig config".to_owned());
+        }
+        if threshold == 0 || threshold as usize > sighash_addresses.len() {
+            return Err(format!(
+                "Invalid threshold: {}, expected: 1..{}",
+                threshold,
+                sighash_addresses.len()
+            ));
+        }
+        if require_first_n > threshold {
+            return Err(format!(
+                "require_first_n({}) can not be greater than threshold({})",
+                require_first_n, threshold
+            ));
+        }
+        Ok(MultisigConfig {
+            sighash_addresses,
+            require_first_n,
+            threshold,
+        })
+    }
+
+    pub fn sighash_addresses(&self) -> &[H160] {
+        &self.sighash_addresses
+    }
+
+    pub fn require_first_n(&self) -> u8 {
+        self.require_first_n
+    }
+
+    pub fn threshold(&self) -> u8 {
+        self.threshold
+    }
+
+    pub fn contains(&self, lock_arg: &H160) -> bool {
+        self.sighash_addresses.contains(lock_arg)
+    }
+
+    /// The multisig script (put in front of signatures in witness lock)
+    pub fn to_witness_data(&self) -> Vec<u8> {
+        let mut data = vec![
+            MULTISIG_SCRIPT_RESERVED,
+            self.require_first_n,
+            self.threshold,
+            self.sighash_addresses.len() as u8,
+        ];
+        for lock_arg in &self.sighash_addresses {
+            data.extend_from_slice(lock_arg.as_bytes());
+        }
+        data
+    }
+
+    /// The lock args of the multisig lock
+    pub fn hash160(&self) -> H160 {
+        let hash = blake2b_256(self.to_witness_data());
+        H160::from_slice(&hash[0..20]).expect("Generate hash(H160) from multisig script failed")
+    }
+
+    pub fn to_address(&self) -> Address {
+        Address::new_multisig(self.hash160())
+    }
+
+    /// Witness lock used when computing signing message: multisig script + zeroed signatures
+    pub fn placeholder_witness_lock(&self) -> Bytes {
+        let mut data = self.to_witness_data();
+        data.extend(vec![0u8; SIGNATURE_SIZE * self.threshold as usize]);
+        Bytes::from(data)
+    }
+
+    /// Witness lock with signatures, the signatures are ordered by pubkeys of the config
+    pub fn build_witness_lock(&self, signatures: &[(H160, Bytes)]) -> Result<Bytes, String> {
+        if signatures.len() < self.threshold as usize {
+            return Err(format!(
+                "Not enough signatures: {} < {}",
+                signatures.len(),
+                self.threshold
+            ));
+        }
+        let mut data = self.to_witness_data();
+        let mut count = 0;
+        for lock_arg in &self.sighash_addresses {
+            if count == self.threshold {
+                break;
+            }
+            if let Some((_, signature)) = signatures.iter().find(|(arg, _)| arg == lock_arg) {
+                data.extend_from_slice(&signature);
+                count += 1;
+            }
+        }
+        if count < self.threshold {
+            return Err("Signatures not match the multisig config".to_owned());
+        }
+        for lock_arg in &self.sighash_addresses[0..self.require_first_n as usize] {
+            if signatures.iter().all(|(arg, _)| arg != lock_arg) {
+                return Err(format!("Signature of {:#x} is required", lock_arg));
+            }
+        }
+        Ok(Bytes::from(data))
+    }
+}
+
+/// Arguments of signing message for multisig inputs (same layout as secp sighash-all, but the
+/// first witness lock is `MultisigConfig::placeholder_witness_lock`)
+pub fn multisig_sign_args(
+    transaction: &TransactionView,
+    config: &MultisigConfig,
+) -> Result<Vec<Vec<u8>>, String> {
+    let witnesses = transaction
+        .witnesses()
+        .into_iter()
+        .map(|witness| witness.raw_data())
+        .collect::<Vec<_>>();
+    if witnesses.is_empty() {
+        return Err("No witness found in transaction".to_owned());
+    }
+    let init_witness = if witnesses[0].is_empty() {
+        WitnessArgs::default()
+    } else {
+        WitnessArgs::from_slice(&witnesses[0]).map_err(|err| err.to_string())?
+    }
+    .as_builder()
+    .lock(Some(config.placeholder_witness_lock()).pack())
+    .build();
+    let mut sign_args = vec![
+        transaction.hash().raw_data().to_vec(),
+        (init_witness.as_bytes().len() as u64)
+            .to_le_bytes()
+            .to_vec(),
+        init_witness.as_bytes().to_vec(),
+    ];
+    for other_witness in witnesses.iter().skip(1) {
+        sign_args.push((other_witness.len() as u64).to_le_bytes().to_vec());
+        sign_args.push(other_witness.to_vec());
+    }
+    Ok(sign_args)
+}
+
+/// Recover the signer (lock arg) of a multisig signature
+pub fn recover_signer(message: &H256, signature: &[u8]) -> Result<H160, String> {
+    let pubkey = recover_pubkey(message, signature)?;
+    Address::from_pubkey(&pubkey).map(|address| address.hash().clone())
+}
+
+/// Recover the public key of a 65 bytes recoverable signature (compact signature | recovery id)
+pub fn recover_pubkey(message: &H256, signature: &[u8]) -> Result<secp256k1::PublicKey, String> {
+    if signature.len() != SIGNATURE_SIZE {
+        return Err(format!("Invalid signature length: {}", signature.len()));
+    }
+    let recov_id = secp256k1::recovery::RecoveryId::from_i32(i32::from(signature[64]))
+        .map_err(|err| err.to_string())?;
+    let signature =
+        secp256k1::recovery::RecoverableSignature::from_compact(&signature[0..64], recov_id)
+            .map_err(|err| err.to_string())?;
+    let message =
+        secp256k1::Message::from_slice(message.as_bytes()).map_err(|err| err.to_string())?;
+    SECP256K1
+        .recover(&message, &signature)
+        .map_err(|err| err.to_string())
+}
+
+/// Replace the lock of first witness with the signed multisig witness lock
+pub fn set_multisig_witness(
+    transaction: &TransactionView,
+    witness_lock: Bytes,
+) -> Result<TransactionView, String> {
+    let mut witnesses = transaction
+        .witnesses()
+        .into_iter()
+        .collect::<Vec<packed::Bytes>>();
+    let init_witness = if witnesses[0].raw_data().is_empty() {
+        WitnessArgs::default()
+    } else {
+        WitnessArgs::from_slice(&witnesses[0].raw_data()).map_err(|err| err.to_string())?
+    };
+    witnesses[0] = init_witness
+        .as_builder()
+        .lock(Some(witness_lock).pack())
+        .build()
+        .as_bytes()
+        .pack();
+    Ok(transaction
+        .as_advanced_builder()
+        .set_witnesses(witnesses)
+        .build())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ckb_types::h160;
+
+    #[test]
+    fn test_multisig_config() {
+        let config = MultisigConfig::new(
+            vec![
+                h160!("0x9b41c025515b00c24e2e2042df7b221af5c1891f"),
+                h160!("0xe732dcd15b7618eb1d7a11e6a68e4579b5be0114"),
+            ],
+            0,
+            2,
+        )
+        .unwrap();
+        assert_eq!(
+            faster_hex::hex_string(&config.to_witness_data()).unwrap(),
+            "000002029b41c025515b00c24e2e2042df7b221af5c1891fe732dcd15b7618eb1d7a11e6a68e4579b5be0114"
+        );
+        assert_eq!(
+            config.hash160(),
+            h160!("0x35ed7b939b4ac9cb447b82340fd8f26d344f7a62")
+        );
+        assert_eq!(config.placeholder_witness_lock().len(), 44 + 65 * 2);
+
+        assert!(MultisigConfig::new(vec![H160::default()], 0, 2).is_err());
+        assert!(MultisigConfig::new(vec![H160::default(), H160::default()], 0, 1).is_err());
+        assert!(MultisigConfig::new(vec![H160::default()], 2, 1).is_err());
+    }
+}
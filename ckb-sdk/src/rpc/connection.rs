@@ -0,0 +1,79 @@
+//! How the RPC connections are made: the trusted CA certificates of https urls and the password
+//! of the basic auth urls which do not contain one. Process wide like the retry policy.
+
+use std::io;
+use std::sync::RwLock;
+
+use hyper::client::{Client, HttpConnector};
+use hyper_tls::HttpsConnector;
+use jsonrpc_client_http::{HttpTransport, HttpTransportBuilder};
+use native_tls::{Certificate, TlsConnector};
+use tokio_core::reactor::Handle;
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ConnectionConfig {
+    /// Trusted CA certificates (PEM), the system trust store is used when it is None
+    ca_cert: Option<Vec<u8>>,
+    /// Used by the urls like `https://user@host`, so the password is not kept in the url
+    pub basic_auth_password: Option<String>,
+}
+
+impl ConnectionConfig {
+    pub fn ca_cert(&self) -> Option<&[u8]> {
+        self.ca_cert.as_ref().map(Vec::as_slice)
+    }
+
+    /// The certificate is checked here, so creating the transport will not fail on it
+    pub fn set_ca_cert(&mut self, pem: Vec<u8>) -> Result<(), String> {
+        Certificate::from_pem(&pem).map_err(|err| format!("Invalid CA certificate: {}", err))?;
+        self.ca_cert = Some(pem);
+        Ok(())
+    }
+
+    /// A standalone transport of both http and https urls
+    pub fn http_transport(&self) -> Result<HttpTransport, String> {
+        let ca_cert = self.ca_cert.clone();
+        HttpTransportBuilder::with_client(move |handle: &Handle| {
+            https_client(ca_cert.as_ref().map(Vec::as_slice), handle)
+        })
+        .standalone()
+        .map_err(|err| format!("Create RPC transport failed: {}", err))
+    }
+}
+
+fn https_client(
+    ca_cert: Option<&[u8]>,
+    handle: &Handle,
+) -> Result<Client<HttpsConnector<HttpConnector>, hyper::Body>, io::Error> {
+    let tls_error = |err| io::Error::new(io::ErrorKind::Other, err);
+    let mut http = HttpConnector::new(1, handle);
+    http.enforce_http(false);
+    let mut builder = TlsConnector::builder().map_err(tls_error)?;
+    if let Some(pem) = ca_cert {
+        let cert = Certificate::from_pem(pem).map_err(tls_error)?;
+        builder.add_root_certificate(cert).map_err(tls_error)?;
+    }
+    let tls = builder.build().map_err(tls_error)?;
+    Ok(Client::configure()
+        .connector(HttpsConnector::from((http, tls)))
+        .build(handle))
+}
+
+lazy_static::lazy_static! {
+    static ref CONNECTION_CONFIG: RwLock<ConnectionConfig> =
+        RwLock::new(ConnectionConfig::default());
+}
+
+/// Used by the clients created after this call
+pub fn set_connection_config(config: ConnectionConfig) {
+    *CONNECTION_CONFIG
+        .write()
+        .expect("Connection config lock poisoned") = config;
+}
+
+pub fn connection_config() -> ConnectionConfig {
+    CONNECTION_CONFIG
+        .read()
+        .expect("Connection config lock poisoned")
+        .clone()
+}
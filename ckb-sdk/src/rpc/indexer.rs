@@ -0,0 +1,168 @@
+//! Client of a standalone ckb-indexer service, an alternative of the local index database.
+
+use ckb_jsonrpc_types::{JsonBytes, Script};
+use ckb_types::H256;
+use jsonrpc_client_core::{expand_params, jsonrpc_client};
+use serde_derive::{Deserialize, Serialize};
+
+use super::client::StatsHttpHandle;
+use super::retry::retry_policy;
+use super::types::JsonU64;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScriptType {
+    Lock,
+    Type,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Order {
+    Asc,
+    Desc,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SearchKey {
+    pub script: Script,
+    pub script_type: ScriptType,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IndexerTip {
+    pub block_hash: H256,
+    pub block_number: JsonU64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct OptionIndexerTip(pub Option<IndexerTip>);
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IndexerOutPoint {
+    pub tx_hash: H256,
+    pub index: JsonU64,
+}
+
+/// The numbers are serialized as hex by ckb-indexer, so the cell output is not the one of
+/// `ckb-jsonrpc-types`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IndexerCellOutput {
+    pub capacity: JsonU64,
+    pub lock: Script,
+    #[serde(rename = "type")]
+    pub type_: Option<Script>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IndexerCell {
+    pub output: IndexerCellOutput,
+    pub output_data: JsonBytes,
+    pub out_point: IndexerOutPoint,
+    pub block_number: JsonU64,
+    pub tx_index: JsonU64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IoType {
+    Input,
+    Output,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IndexerTx {
+    pub tx_hash: H256,
+    pub block_number: JsonU64,
+    pub tx_index: JsonU64,
+    pub io_index: JsonU64,
+    pub io_type: IoType,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Pagination<T> {
+    pub objects: Vec<T>,
+    pub last_cursor: JsonBytes,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CellsCapacity {
+    pub capacity: JsonU64,
+    pub block_hash: H256,
+    pub block_number: JsonU64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct OptionCellsCapacity(pub Option<CellsCapacity>);
+
+jsonrpc_client!(pub struct IndexerRpcClient {
+    pub fn get_tip(&mut self) -> RpcRequest<OptionIndexerTip>;
+    pub fn get_cells(
+        &mut self,
+        search_key: SearchKey,
+        order: Order,
+        limit: JsonU64,
+        after_cursor: Option<JsonBytes>
+    ) -> RpcRequest<Pagination<IndexerCell>>;
+    pub fn get_transactions(
+        &mut self,
+        search_key: SearchKey,
+        order: Order,
+        limit: JsonU64,
+        after_cursor: Option<JsonBytes>
+    ) -> RpcRequest<Pagination<IndexerTx>>;
+    pub fn get_cells_capacity(&mut self, search_key: SearchKey) -> RpcRequest<OptionCellsCapacity>;
+});
+
+impl IndexerRpcClient<StatsHttpHandle> {
+    pub fn from_uri(server: &str) -> IndexerRpcClient<StatsHttpHandle> {
+        IndexerRpcClient::new(StatsHttpHandle::new(server, retry_policy()))
+    }
+}
+
+pub type HttpIndexerRpcClient = IndexerRpcClient<StatsHttpHandle>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cells_pagination() {
+        let content = r#"{
+            "last_cursor": "0x01",
+            "objects": [{
+                "block_number": "0x1a",
+                "out_point": {
+                    "index": "0x1",
+                    "tx_hash": "0x8f8c79eb6671709633fe6a46de93c0fedc9c1b8a6527a18d3983879542635c9f"
+                },
+                "output": {
+                    "capacity": "0x174876e800",
+                    "lock": {
+                        "args": "0xc2baa1d5b45a3ad6452b9c98ad8e2cc52e5123c7",
+                        "code_hash": "0x9bd7e06f3ecf4be0f2fcd2188b23f1b9fcc88e5d4b65a8637b17723bbda3cce8",
+                        "hash_type": "type"
+                    },
+                    "type": null
+                },
+                "output_data": "0x",
+                "tx_index": "0x2"
+            }]
+        }"#;
+        let page: Pagination<IndexerCell> = serde_json::from_str(content).unwrap();
+        let cell = &page.objects[0];
+        assert_eq!(cell.block_number.value(), 26);
+        assert_eq!(cell.tx_index.value(), 2);
+        assert_eq!(cell.out_point.index.value(), 1);
+        assert_eq!(cell.output.capacity.value(), 100_000_000_000);
+        assert!(cell.output.type_.is_none());
+    }
+
+    #[test]
+    fn test_search_key() {
+        let order = serde_json::to_string(&Order::Desc).unwrap();
+        assert_eq!(order, r#""desc""#);
+        let script_type = serde_json::to_string(&ScriptType::Lock).unwrap();
+        assert_eq!(script_type, r#""lock""#);
+    }
+}
@@ -1,10 +1,27 @@
 mod client;
+mod connection;
+mod indexer;
+mod retry;
+mod subscription;
+mod types;
 
 pub use ckb_jsonrpc_types::{
     BlockNumber, BlockView, CellOutputWithOutPoint, CellWithStatus, ChainInfo, EpochNumber,
     EpochView, HeaderView, Node, OutPoint, Transaction, TransactionWithStatus, TxPoolInfo,
 };
+pub use client::split_basic_auth;
 pub use client::{
     CellOutputWithOutPoints, HttpRpcClient, Nodes, OptionBlockView, OptionEpochView, OptionH256,
-    OptionTransactionWithStatus, RpcClient,
+    OptionTransactionWithStatus, RpcClient, StatsHttpHandle,
+};
+pub use connection::{connection_config, set_connection_config, ConnectionConfig};
+pub use indexer::{
+    CellsCapacity, HttpIndexerRpcClient, IndexerCell, IndexerCellOutput, IndexerOutPoint,
+    IndexerRpcClient, IndexerTip, IndexerTx, IoType, Order, Pagination, ScriptType, SearchKey,
+};
+pub use retry::{retry_policy, set_retry_policy, RetryPolicy};
+pub use subscription::{SubscriptionClient, SubscriptionEvent, SUBSCRIPTION_TOPICS};
+pub use types::{
+    BlockEconomicState, BlockIssuance, Consensus, FeeRateStatics, JsonU64, MinerReward,
+    OptionBlockEconomicState, OptionFeeRateStatics,
 };
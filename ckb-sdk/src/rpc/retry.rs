@@ -0,0 +1,133 @@
+//! Retry and failover of RPC calls. The policy is process wide, so the clients created anywhere
+//! (commands, the background index thread, TUI) follow the same settings.
+
+use std::collections::HashMap;
+use std::sync::{mpsc, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tokio_timer::{
+    timer::{Handle, Timer},
+    Delay,
+};
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RetryPolicy {
+    /// Retry times after the first failed round (every round tries all the urls)
+    pub max_retries: u32,
+    /// Wait before the first retry, doubled for each next retry
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Primary url => secondary url, tried when the primary one failed
+    pub failover_urls: HashMap<String, String>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy {
+            max_retries: 0,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(8),
+            failover_urls: HashMap::default(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// The wait before retry round `round` (start from 1)
+    pub fn backoff(&self, round: u32) -> Duration {
+        let factor = 1u32
+            .checked_shl(round.saturating_sub(1))
+            .unwrap_or(u32::max_value());
+        self.initial_backoff
+            .checked_mul(factor)
+            .map(|backoff| backoff.min(self.max_backoff))
+            .unwrap_or(self.max_backoff)
+    }
+
+    /// The url itself and its failover url (if any)
+    pub fn urls(&self, url: &str) -> Vec<String> {
+        let mut urls = vec![url.to_owned()];
+        if let Some(failover_url) = self.failover_urls.get(url) {
+            if failover_url != url {
+                urls.push(failover_url.clone());
+            }
+        }
+        urls
+    }
+
+    /// The backoff of retry round `round` as a future, not to block the thread polling the
+    /// RPC future
+    pub fn backoff_delay(&self, round: u32) -> Delay {
+        BACKOFF_TIMER.delay(Instant::now() + self.backoff(round))
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref RETRY_POLICY: RwLock<RetryPolicy> = RwLock::new(RetryPolicy::default());
+    // The RPC futures are waited outside of a tokio runtime, so the delays are driven by a
+    // timer thread of their own
+    static ref BACKOFF_TIMER: Handle = {
+        let (sender, receiver) = mpsc::channel();
+        thread::Builder::new()
+            .name("rpc-backoff-timer".to_string())
+            .spawn(move || {
+                let mut timer = Timer::default();
+                sender
+                    .send(timer.handle())
+                    .expect("Send backoff timer handle failed");
+                loop {
+                    timer.turn(None).expect("Backoff timer failed");
+                }
+            })
+            .expect("Spawn backoff timer thread failed");
+        receiver.recv().expect("Receive backoff timer handle failed")
+    };
+}
+
+/// Used by the clients created after this call
+pub fn set_retry_policy(policy: RetryPolicy) {
+    *RETRY_POLICY.write().expect("Retry policy lock poisoned") = policy;
+}
+
+pub fn retry_policy() -> RetryPolicy {
+    RETRY_POLICY
+        .read()
+        .expect("Retry policy lock poisoned")
+        .clone()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy {
+            max_retries: 5,
+            ..Default::default()
+        };
+        assert_eq!(policy.backoff(1), Duration::from_millis(500));
+        assert_eq!(policy.backoff(2), Duration::from_secs(1));
+        assert_eq!(policy.backoff(4), Duration::from_secs(4));
+        assert_eq!(policy.backoff(5), Duration::from_secs(8));
+        assert_eq!(policy.backoff(40), Duration::from_secs(8));
+    }
+
+    #[test]
+    fn test_urls() {
+        let mut policy = RetryPolicy::default();
+        policy.failover_urls.insert(
+            "http://127.0.0.1:8114".to_owned(),
+            "http://127.0.0.1:8115".to_owned(),
+        );
+        assert_eq!(
+            policy.urls("http://127.0.0.1:8114"),
+            vec!["http://127.0.0.1:8114", "http://127.0.0.1:8115"]
+        );
+        assert_eq!(
+            policy.urls("http://127.0.0.1:8115"),
+            vec!["http://127.0.0.1:8115"]
+        );
+    }
+}
@@ -0,0 +1,174 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+
+use serde_json::{json, Value};
+
+/// Topics supported by the subscription RPC of the node
+pub const SUBSCRIPTION_TOPICS: &[&str] = &[
+    "new_tip_header",
+    "new_tip_block",
+    "new_transaction",
+    "proposed_transaction",
+];
+
+/// A long-lived client of the node's raw TCP subscription endpoint (`[rpc] tcp_listen_address` in
+/// ckb.toml), the messages are newline delimited JSON-RPC. The WebSocket endpoint
+/// (`ws_listen_address`) is not supported.
+pub struct SubscriptionClient {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    next_id: u64,
+}
+
+/// A notification pushed by the node
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubscriptionEvent {
+    pub subscription: String,
+    pub result: Value,
+}
+
+impl SubscriptionClient {
+    /// Connect to `host:port` (the `tcp://` scheme is optional)
+    pub fn connect(address: &str) -> Result<SubscriptionClient, String> {
+        let address = if address.starts_with("tcp://") {
+            &address[6..]
+        } else if address.starts_with("ws://") || address.starts_with("wss://") {
+            return Err(format!(
+                "WebSocket is not supported: {}, use the raw TCP subscription address ([rpc] tcp_listen_address in ckb.toml)",
+                address
+            ));
+        } else if address.contains("://") {
+            return Err(format!(
+                "Unsupported subscription address: {}, only tcp://host:port is supported",
+                address
+            ));
+        } else {
+            address
+        };
+        let writer = TcpStream::connect(address)
+            .map_err(|err| format!("Connect to {} failed: {}", address, err))?;
+        let reader = BufReader::new(writer.try_clone().map_err(|err| err.to_string())?);
+        Ok(SubscriptionClient {
+            reader,
+            writer,
+            next_id: 0,
+        })
+    }
+
+    /// Subscribe a topic, returns the subscription id
+    pub fn subscribe(&mut self, topic: &str) -> Result<String, String> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let request = json!({
+            "id": id,
+            "jsonrpc": "2.0",
+            "method": "subscribe",
+            "params": [topic],
+        });
+        self.writer
+            .write_all(format!("{}\n", request).as_bytes())
+            .map_err(|err| err.to_string())?;
+        loop {
+            let message = self
+                .read_message()?
+                .ok_or_else(|| "Connection closed by node".to_owned())?;
+            if message["id"] != json!(id) {
+                continue;
+            }
+            if !message["error"].is_null() {
+                return Err(format!("Subscribe {} failed: {}", topic, message["error"]));
+            }
+            return message["result"]
+                .as_str()
+                .map(ToOwned::to_owned)
+                .ok_or_else(|| format!("Invalid subscribe response: {}", message));
+        }
+    }
+
+    /// Block until next notification, returns `None` when the connection is closed
+    pub fn next_event(&mut self) -> Result<Option<SubscriptionEvent>, String> {
+        while let Some(message) = self.read_message()? {
+            if message["method"] != json!("subscribe") {
+                continue;
+            }
+            let params = &message["params"];
+            let subscription = params["subscription"]
+                .as_str()
+                .map(ToOwned::to_owned)
+                .unwrap_or_default();
+            // The result is serialized to a json string by the node
+            let result = match params["result"].as_str() {
+                Some(content) => serde_json::from_str(content).unwrap_or_else(|_| json!(content)),
+                None => params["result"].clone(),
+            };
+            return Ok(Some(SubscriptionEvent {
+                subscription,
+                result,
+            }));
+        }
+        Ok(None)
+    }
+
+    fn read_message(&mut self) -> Result<Option<Value>, String> {
+        loop {
+            let mut line = String::new();
+            let size = self
+                .reader
+                .read_line(&mut line)
+                .map_err(|err| err.to_string())?;
+            if size == 0 {
+                return Ok(None);
+            }
+            if line.trim().is_empty() {
+                continue;
+            }
+            return serde_json::from_str(line.trim())
+                .map(Some)
+                .map_err(|err| format!("Invalid message from node: {}", err));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn test_subscribe_and_receive() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut writer = stream;
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let request: Value = serde_json::from_str(&line).unwrap();
+            assert_eq!(request["params"], json!(["new_tip_header"]));
+            let response = json!({"jsonrpc": "2.0", "result": "0x0", "id": request["id"]});
+            let notification = json!({
+                "jsonrpc": "2.0",
+                "method": "subscribe",
+                "params": {"result": "{\"number\":\"0x10\"}", "subscription": "0x0"},
+            });
+            writer
+                .write_all(format!("{}\n{}\n", response, notification).as_bytes())
+                .unwrap();
+        });
+
+        let mut client = SubscriptionClient::connect(&format!("tcp://{}", address)).unwrap();
+        assert_eq!(client.subscribe("new_tip_header").unwrap(), "0x0");
+        let event = client.next_event().unwrap().unwrap();
+        assert_eq!(event.subscription, "0x0");
+        assert_eq!(event.result, json!({"number": "0x10"}));
+        server.join().unwrap();
+        assert_eq!(client.next_event().unwrap(), None);
+    }
+
+    #[test]
+    fn test_unsupported_scheme() {
+        assert!(SubscriptionClient::connect("ws://127.0.0.1:18114").is_err());
+    }
+}
@@ -0,0 +1,140 @@
+//! Types of the RPC methods added by newer nodes, they are not defined by the `ckb-jsonrpc-types`
+//! this crate depends on.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use ckb_types::H256;
+use serde::de::{self, Deserializer, Visitor};
+use serde::ser::Serializer;
+use serde_derive::{Deserialize, Serialize};
+
+/// An unsigned 64-bit integer in JSON, newer nodes serialize it as a hex string ("0x1a") while
+/// the old ones use a decimal string ("26"), both (and plain numbers) are accepted.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub struct JsonU64(pub u64);
+
+impl JsonU64 {
+    pub fn value(self) -> u64 {
+        self.0
+    }
+}
+
+impl serde::Serialize for JsonU64 {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{:#x}", self.0))
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for JsonU64 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct JsonU64Visitor;
+
+        impl<'de> Visitor<'de> for JsonU64Visitor {
+            type Value = JsonU64;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a hex string, a decimal string or an unsigned integer")
+            }
+
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<JsonU64, E> {
+                Ok(JsonU64(value))
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<JsonU64, E> {
+                let result = if value.starts_with("0x") {
+                    u64::from_str_radix(&value[2..], 16)
+                } else {
+                    value.parse::<u64>()
+                };
+                result
+                    .map(JsonU64)
+                    .map_err(|err| E::custom(format!("invalid number {}: {}", value, err)))
+            }
+        }
+
+        deserializer.deserialize_any(JsonU64Visitor)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BlockIssuance {
+    pub primary: JsonU64,
+    pub secondary: JsonU64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MinerReward {
+    pub primary: JsonU64,
+    pub secondary: JsonU64,
+    pub committed: JsonU64,
+    pub proposal: JsonU64,
+}
+
+/// The issuance and rewards of a block (`get_block_economic_state`), all in shannons
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BlockEconomicState {
+    pub issuance: BlockIssuance,
+    pub miner_reward: MinerReward,
+    pub txs_fee: JsonU64,
+    pub finalized_at: H256,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct OptionBlockEconomicState(pub Option<BlockEconomicState>);
+
+/// The consensus parameters of the chain (`get_consensus`), the fields not used by ckb-cli are
+/// kept as is in `others`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Consensus {
+    pub id: String,
+    pub genesis_hash: H256,
+    #[serde(default)]
+    pub dao_type_hash: Option<H256>,
+    #[serde(default)]
+    pub secp256k1_blake160_sighash_all_type_hash: Option<H256>,
+    #[serde(default)]
+    pub secp256k1_blake160_multisig_all_type_hash: Option<H256>,
+    pub initial_primary_epoch_reward: JsonU64,
+    pub secondary_epoch_reward: JsonU64,
+    pub epoch_duration_target: JsonU64,
+    pub max_block_cycles: JsonU64,
+    pub max_block_bytes: JsonU64,
+    pub primary_epoch_reward_halving_interval: JsonU64,
+    #[serde(flatten)]
+    pub others: BTreeMap<String, serde_json::Value>,
+}
+
+/// The fee rates (unit: shannons/KB) of recent transactions (`get_fee_rate_statics`)
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FeeRateStatics {
+    pub mean: JsonU64,
+    pub median: JsonU64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct OptionFeeRateStatics(pub Option<FeeRateStatics>);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_u64() {
+        let values: Vec<JsonU64> = serde_json::from_str(r#"["0x1a", "26", 26]"#).unwrap();
+        assert_eq!(values, vec![JsonU64(26); 3]);
+        assert!(serde_json::from_str::<JsonU64>(r#""0xzz""#).is_err());
+        assert_eq!(serde_json::to_string(&JsonU64(26)).unwrap(), r#""0x1a""#);
+    }
+
+    #[test]
+    fn test_fee_rate_statics() {
+        let statics: OptionFeeRateStatics =
+            serde_json::from_str(r#"{"mean": "0x3e8", "median": "0x7d0"}"#).unwrap();
+        let statics = statics.0.unwrap();
+        assert_eq!(statics.mean.value(), 1000);
+        assert_eq!(statics.median.value(), 2000);
+        let empty: OptionFeeRateStatics = serde_json::from_str("null").unwrap();
+        assert!(empty.0.is_none());
+    }
+}
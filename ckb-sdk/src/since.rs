@@ -0,0 +1,95 @@
+// See: https://github.com/nervosnetwork/rfcs/blob/master/rfcs/0017-tx-valid-since/0017-tx-valid-since.md
+const FLAG_RELATIVE: u64 = 0x8000_0000_0000_0000;
+const METRIC_TYPE_FLAG_MASK: u64 = 0x6000_0000_0000_0000;
+const VALUE_MASK: u64 = 0x00ff_ffff_ffff_ffff;
+const EPOCH_NUMBER_MASK: u64 = 0x00ff_ffff;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SinceType {
+    BlockNumber = 0,
+    EpochNumberWithFraction = 1,
+    Timestamp = 2,
+}
+
+/// The `since` field of a transaction input (also used as the lock period of multisig lock)
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Since(u64);
+
+impl Since {
+    pub fn new(ty: SinceType, value: u64, is_relative: bool) -> Since {
+        let relative_flag = if is_relative { FLAG_RELATIVE } else { 0 };
+        Since(relative_flag | ((ty as u64) << 61) | (value & VALUE_MASK))
+    }
+
+    /// Since the start of an epoch (epoch fraction is 0/1)
+    pub fn from_epoch(number: u64, is_relative: bool) -> Since {
+        let value = (number & EPOCH_NUMBER_MASK) | (1 << 40);
+        Since::new(SinceType::EpochNumberWithFraction, value, is_relative)
+    }
+
+    /// Since a timestamp (in seconds, compared with the median time of previous 37 blocks)
+    pub fn from_timestamp(seconds: u64, is_relative: bool) -> Since {
+        Since::new(SinceType::Timestamp, seconds, is_relative)
+    }
+
+    pub fn from_raw_value(value: u64) -> Since {
+        Since(value)
+    }
+
+    pub fn value(self) -> u64 {
+        self.0
+    }
+
+    pub fn is_relative(self) -> bool {
+        self.0 & FLAG_RELATIVE != 0
+    }
+
+    pub fn since_type(self) -> Option<SinceType> {
+        match (self.0 & METRIC_TYPE_FLAG_MASK) >> 61 {
+            0 => Some(SinceType::BlockNumber),
+            1 => Some(SinceType::EpochNumberWithFraction),
+            2 => Some(SinceType::Timestamp),
+            _ => None,
+        }
+    }
+
+    /// The epoch number or timestamp or block number
+    pub fn extract_metric(self) -> Option<(SinceType, u64)> {
+        let value = self.0 & VALUE_MASK;
+        self.since_type().map(|ty| match ty {
+            SinceType::EpochNumberWithFraction => (ty, value & EPOCH_NUMBER_MASK),
+            _ => (ty, value),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_since() {
+        let since = Since::from_epoch(100, false);
+        assert_eq!(since.value(), 0x2000_0100_0000_0064);
+        assert_eq!(
+            since.extract_metric(),
+            Some((SinceType::EpochNumberWithFraction, 100))
+        );
+        assert!(!since.is_relative());
+
+        let since = Since::from_timestamp(1_574_000_000, false);
+        assert_eq!(since.value(), 0x4000_0000_5dd1_5580);
+        assert_eq!(
+            since.extract_metric(),
+            Some((SinceType::Timestamp, 1_574_000_000))
+        );
+
+        let since = Since::new(SinceType::BlockNumber, 42, true);
+        assert!(since.is_relative());
+        assert_eq!(since.extract_metric(), Some((SinceType::BlockNumber, 42)));
+        assert_eq!(
+            Since::from_raw_value(0x6000_0000_0000_0000).since_type(),
+            None
+        );
+    }
+}
@@ -0,0 +1,98 @@
+//! Counters of RPC calls, index database queries and cache lookups, reported by `--timings`.
+//! The counters are per thread, so the background index thread does not pollute the numbers
+//! of the command running in the main thread.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+use serde_derive::Serialize;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl CacheStats {
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f64 / total as f64
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Stats {
+    // RPC method => calls
+    pub rpc_calls: BTreeMap<String, u64>,
+    // Index database query => calls
+    pub index_queries: BTreeMap<String, u64>,
+    // Cache name => hits/misses
+    pub caches: BTreeMap<String, CacheStats>,
+}
+
+thread_local! {
+    static STATS: RefCell<Stats> = RefCell::new(Stats::default());
+}
+
+pub fn record_rpc_call(method: &str) {
+    STATS.with(|stats| {
+        *stats
+            .borrow_mut()
+            .rpc_calls
+            .entry(method.to_owned())
+            .or_default() += 1;
+    });
+}
+
+pub fn record_index_query(query: &str) {
+    STATS.with(|stats| {
+        *stats
+            .borrow_mut()
+            .index_queries
+            .entry(query.to_owned())
+            .or_default() += 1;
+    });
+}
+
+pub fn record_cache(name: &str, hit: bool) {
+    STATS.with(|stats| {
+        let mut stats = stats.borrow_mut();
+        let cache = stats.caches.entry(name.to_owned()).or_default();
+        if hit {
+            cache.hits += 1;
+        } else {
+            cache.misses += 1;
+        }
+    });
+}
+
+/// Take the counters of current thread and reset them
+pub fn take() -> Stats {
+    STATS.with(|stats| stats.replace(Stats::default()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_stats() {
+        take();
+        record_rpc_call("get_tip_header");
+        record_rpc_call("get_tip_header");
+        record_index_query("get_capacity");
+        record_cache("genesis_info", false);
+        record_cache("genesis_info", true);
+        record_cache("genesis_info", true);
+        record_cache("genesis_info", true);
+        let stats = take();
+        assert_eq!(stats.rpc_calls["get_tip_header"], 2);
+        assert_eq!(stats.index_queries["get_capacity"], 1);
+        assert_eq!(stats.caches["genesis_info"].hit_rate(), 0.75);
+        assert!(take().rpc_calls.is_empty());
+    }
+}
@@ -0,0 +1,132 @@
+use ckb_types::{
+    bytes::Bytes,
+    core::{Capacity, DepType, ScriptHashType},
+    h256,
+    packed::{Byte32, CellDep, CellOutput, OutPoint, Script},
+    prelude::*,
+    H256,
+};
+
+use crate::NetworkType;
+
+// Deployments from RFC 0025 (the `code_hash` is a type hash, the cell dep is the code cell)
+const MAINNET_SUDT_CODE_HASH: H256 =
+    h256!("0x5e7a36a77e68eecc013dfa2fe6a23f3b6c344b04005808694ae6dd45eea4cfd5");
+const MAINNET_SUDT_DEP_TX_HASH: H256 =
+    h256!("0xc7813f6a415144643970c2e88e0bb6ca6a8edc5dd7c1022746f628284a9936d5");
+const TESTNET_SUDT_CODE_HASH: H256 =
+    h256!("0xc5e5dcf215925f7ef4dfaf5f4b4f105bc321c02776d6e7d52a1db3fcd9d011a4");
+const TESTNET_SUDT_DEP_TX_HASH: H256 =
+    h256!("0xe12877ebd2c3c364dc46c5c992bcfaf4fee33fa13eebdf82c591fc9825aab769");
+
+/// Length of the UDT amount (u128, little endian) at the beginning of cell data
+pub const UDT_AMOUNT_LEN: usize = 16;
+
+/// The simple UDT type script, a token is identified by the owner (issuer) lock hash in args.
+/// It is not deployed in genesis block.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SudtConfig {
+    code_hash: Byte32,
+    dep_out_point: OutPoint,
+}
+
+impl SudtConfig {
+    pub fn new(code_hash: H256, dep_out_point: OutPoint) -> SudtConfig {
+        SudtConfig {
+            code_hash: code_hash.pack(),
+            dep_out_point,
+        }
+    }
+
+    /// The known deployment of mainnet and testnet, dev chain must be configured manually
+    pub fn from_network(network: NetworkType) -> Option<SudtConfig> {
+        let (code_hash, tx_hash) = match network {
+            NetworkType::MainNet => (MAINNET_SUDT_CODE_HASH, MAINNET_SUDT_DEP_TX_HASH),
+            NetworkType::TestNet => (TESTNET_SUDT_CODE_HASH, TESTNET_SUDT_DEP_TX_HASH),
+            NetworkType::Dev => return None,
+        };
+        Some(SudtConfig::new(code_hash, OutPoint::new(tx_hash.pack(), 0)))
+    }
+
+    pub fn code_hash(&self) -> &Byte32 {
+        &self.code_hash
+    }
+
+    pub fn cell_dep(&self) -> CellDep {
+        CellDep::new_builder()
+            .out_point(self.dep_out_point.clone())
+            .dep_type(DepType::Code.into())
+            .build()
+    }
+
+    /// Type script of the token issued by the owner lock
+    pub fn type_script(&self, owner_lock_hash: &Byte32) -> Script {
+        Script::new_builder()
+            .code_hash(self.code_hash.clone())
+            .hash_type(ScriptHashType::Type.into())
+            .args(owner_lock_hash.as_bytes().pack())
+            .build()
+    }
+
+    pub fn is_sudt_type(&self, script: &Script) -> bool {
+        script.code_hash() == self.code_hash && script.hash_type() == ScriptHashType::Type.into()
+    }
+}
+
+pub fn udt_amount_data(amount: u128) -> Bytes {
+    Bytes::from(amount.to_le_bytes().to_vec())
+}
+
+/// The UDT amount of cell data, extra bytes after the amount are allowed
+pub fn parse_udt_amount(data: &[u8]) -> Result<u128, String> {
+    if data.len() < UDT_AMOUNT_LEN {
+        return Err(format!(
+            "Invalid UDT cell data length: {}, expected >= {}",
+            data.len(),
+            UDT_AMOUNT_LEN
+        ));
+    }
+    let mut buf = [0u8; UDT_AMOUNT_LEN];
+    buf.copy_from_slice(&data[..UDT_AMOUNT_LEN]);
+    Ok(u128::from_le_bytes(buf))
+}
+
+/// The minimal capacity of a UDT cell (lock, type and the amount)
+pub fn udt_cell_capacity(lock: &Script, type_script: &Script) -> u64 {
+    CellOutput::new_builder()
+        .lock(lock.clone())
+        .type_(Some(type_script.clone()).pack())
+        .build()
+        .occupied_capacity(Capacity::bytes(UDT_AMOUNT_LEN).expect("capacity of 16 bytes"))
+        .expect("occupied capacity of UDT cell")
+        .as_u64()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Address, ONE_CKB};
+
+    #[test]
+    fn test_udt_amount() {
+        let data = udt_amount_data(1_000_000);
+        assert_eq!(data.len(), UDT_AMOUNT_LEN);
+        assert_eq!(parse_udt_amount(&data), Ok(1_000_000));
+        let mut data = udt_amount_data(std::u128::MAX).to_vec();
+        data.extend_from_slice(&[1, 2, 3]);
+        assert_eq!(parse_udt_amount(&data), Ok(std::u128::MAX));
+        assert!(parse_udt_amount(&[0u8; 8]).is_err());
+    }
+
+    #[test]
+    fn test_udt_cell_capacity() {
+        let config = SudtConfig::from_network(NetworkType::MainNet).unwrap();
+        let lock = Address::from_lock_arg(&[1u8; 20])
+            .unwrap()
+            .lock_script(Byte32::default());
+        let type_script = config.type_script(&lock.calc_script_hash());
+        assert!(config.is_sudt_type(&type_script));
+        // 8 (capacity) + 53 (lock) + 65 (type) + 16 (amount)
+        assert_eq!(udt_cell_capacity(&lock, &type_script), 142 * ONE_CKB);
+    }
+}
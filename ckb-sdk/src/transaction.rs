@@ -1,5 +1,5 @@
 use ckb_hash::new_blake2b;
-use ckb_script::TransactionScriptsVerifier;
+use ckb_script::{ScriptGroup, TransactionScriptsVerifier};
 use ckb_types::{
     bytes::Bytes,
     core::{cell::resolve_transaction, Capacity, Cycle, ScriptHashType},
@@ -9,6 +9,7 @@ use ckb_types::{
 };
 use failure::Error as FailureError;
 use fnv::FnvHashSet;
+use serde_derive::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::convert::TryInto;
 
@@ -313,6 +314,120 @@ impl<'a> MockTransactionHelper<'a> {
             .verify(max_cycle)
             .map_err(|err| format!("Verify script error: {:?}", err))
     }
+
+    /// Verify the transaction by local ScriptVerifier, one script group at a time, and report
+    /// the cycles consumed by each group (lock groups first, then type groups).
+    pub fn verify_script_groups<L: MockResourceLoader>(
+        &mut self,
+        max_cycle: Cycle,
+        loader: L,
+    ) -> Result<Vec<ScriptGroupCycle>, String> {
+        let resource = Resource::from_both(self.mock_tx, loader)?;
+        let tx = self.mock_tx.core_transaction();
+        let rtx = {
+            let mut seen_inputs = FnvHashSet::default();
+            resolve_transaction(tx.clone(), &mut seen_inputs, &resource, &resource)
+                .map_err(|err| format!("Resolve transaction error: {:?}", err))?
+        };
+
+        let mut lock_groups: Vec<ScriptGroup> = Vec::new();
+        let mut type_groups: Vec<ScriptGroup> = Vec::new();
+        fn group_of<'b>(groups: &'b mut Vec<ScriptGroup>, script: &Script) -> &'b mut ScriptGroup {
+            let script_hash = script.calc_script_hash();
+            let idx = match groups
+                .iter()
+                .position(|group| group.script.calc_script_hash() == script_hash)
+            {
+                Some(idx) => idx,
+                None => {
+                    groups.push(ScriptGroup::new(script));
+                    groups.len() - 1
+                }
+            };
+            &mut groups[idx]
+        }
+        for (idx, cell_meta) in rtx.resolved_inputs.iter().enumerate() {
+            let output = &cell_meta.cell_output;
+            group_of(&mut lock_groups, &output.lock())
+                .input_indices
+                .push(idx);
+            if let Some(type_script) = output.type_().to_opt() {
+                group_of(&mut type_groups, &type_script)
+                    .input_indices
+                    .push(idx);
+            }
+        }
+        for (idx, output) in tx.outputs().into_iter().enumerate() {
+            if let Some(type_script) = output.type_().to_opt() {
+                group_of(&mut type_groups, &type_script)
+                    .output_indices
+                    .push(idx);
+            }
+        }
+
+        let mut verifier = TransactionScriptsVerifier::new(&rtx, &resource);
+        verifier.set_debug_printer(|script_hash, message| {
+            println!("script: {:x}, debug: {}", script_hash, message);
+        });
+        let mut remain_cycle = max_cycle;
+        let mut results = Vec::new();
+        let groups = lock_groups
+            .iter()
+            .map(|group| (ScriptGroupKind::Lock, group))
+            .chain(
+                type_groups
+                    .iter()
+                    .map(|group| (ScriptGroupKind::Type, group)),
+            );
+        for (kind, group) in groups {
+            let script_hash: H256 = group.script.calc_script_hash().unpack();
+            let cycle = verifier
+                .verify_script_group(group, remain_cycle)
+                .map_err(|err| {
+                    format!(
+                        "Verify script error: {:?}, {} script: {:#x}",
+                        err, kind, script_hash
+                    )
+                })?;
+            remain_cycle = remain_cycle.saturating_sub(cycle);
+            results.push(ScriptGroupCycle {
+                kind,
+                script_hash,
+                code_hash: group.script.code_hash().unpack(),
+                input_indices: group.input_indices.clone(),
+                output_indices: group.output_indices.clone(),
+                cycle,
+            });
+        }
+        Ok(results)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScriptGroupKind {
+    Lock,
+    Type,
+}
+
+impl std::fmt::Display for ScriptGroupKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ScriptGroupKind::Lock => write!(f, "lock"),
+            ScriptGroupKind::Type => write!(f, "type"),
+        }
+    }
+}
+
+/// The cycles consumed by one script group of a transaction
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ScriptGroupCycle {
+    pub kind: ScriptGroupKind,
+    pub script_hash: H256,
+    pub code_hash: H256,
+    pub input_indices: Vec<usize>,
+    pub output_indices: Vec<usize>,
+    pub cycle: Cycle,
 }
 
 #[cfg(test)]
@@ -0,0 +1,50 @@
+use ckb_hash::new_blake2b;
+use ckb_types::{
+    core::ScriptHashType,
+    h256,
+    packed::{CellInput, Script},
+    prelude::*,
+    H256,
+};
+
+/// Code hash of the builtin type id script ("TYPE_ID" in ascii)
+pub const TYPE_ID_CODE_HASH: H256 =
+    h256!("0x00000000000000000000000000000000000000000000000000545950455f4944");
+
+/// The type id script of the output at `output_index` of a transaction, the args is unique
+/// since it is the hash of the first input (which can only be spent once).
+pub fn type_id_script(first_input: &CellInput, output_index: u64) -> Script {
+    let mut blake2b = new_blake2b();
+    blake2b.update(first_input.as_slice());
+    blake2b.update(&output_index.to_le_bytes());
+    let mut args = [0u8; 32];
+    blake2b.finalize(&mut args);
+    Script::new_builder()
+        .code_hash(TYPE_ID_CODE_HASH.pack())
+        .hash_type(ScriptHashType::Type.into())
+        .args(args.to_vec().pack())
+        .build()
+}
+
+pub fn is_type_id_script(script: &Script) -> bool {
+    script.code_hash() == TYPE_ID_CODE_HASH.pack()
+        && script.hash_type() == ScriptHashType::Type.into()
+        && script.args().raw_data().len() == 32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ckb_types::packed::OutPoint;
+
+    #[test]
+    fn test_type_id_script() {
+        let input = CellInput::new(OutPoint::new(Default::default(), 0), 0);
+        let script = type_id_script(&input, 0);
+        assert!(is_type_id_script(&script));
+        assert_eq!(script, type_id_script(&input, 0));
+        assert_ne!(script.args(), type_id_script(&input, 1).args());
+        let other_input = CellInput::new(OutPoint::new(Default::default(), 1), 0);
+        assert_ne!(script.args(), type_id_script(&other_input, 0).args());
+    }
+}
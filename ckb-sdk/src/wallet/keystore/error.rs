@@ -35,6 +35,15 @@ pub enum Error {
     #[fail(display = "Invalid secp256k1 secret key")]
     InvalidSecpSecret,
 
+    #[fail(display = "Invalid mnemonic: {}", _0)]
+    InvalidMnemonic(String),
+
+    #[fail(display = "Account has no mnemonic: {:x}", _0)]
+    MnemonicNotFound(H160),
+
+    #[fail(display = "Checksum mismatch of key file: {}", _0)]
+    ChecksumMismatch(String),
+
     #[fail(display = "IO error: {}", _0)]
     Io(String),
 
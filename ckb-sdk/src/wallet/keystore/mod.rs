@@ -11,11 +11,12 @@ use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 
 use super::bip32::{ChainCode, ChildNumber, DerivationPath, ExtendedPrivKey, ExtendedPubKey};
+use super::mnemonic::{bip44_path, MnemonicEntropy};
 use chrono::{Datelike, Timelike, Utc};
 use ckb_crypto::secp::SECP256K1;
 use ckb_hash::blake2b_256;
 use ckb_types::{H160, H256};
-use faster_hex::hex_decode;
+use faster_hex::{hex_decode, hex_string};
 use rand::Rng;
 use secp256k1::recovery::RecoverableSignature;
 use uuid::Uuid;
@@ -25,6 +26,7 @@ pub use passphrase::{CipherParams, Crypto, KdfParams, ScryptParams, ScryptType};
 pub use util::{zeroize_privkey, zeroize_slice};
 
 const KEYSTORE_VERSION: u32 = 3;
+const BACKUP_VERSION: u32 = 1;
 
 pub struct KeyStore {
     keys_dir: PathBuf,
@@ -100,6 +102,26 @@ impl KeyStore {
     pub fn lock(&mut self, address: &H160) -> bool {
         self.unlocked_keys.remove(address).is_some()
     }
+    /// Lock all the unlocked keys (the private keys are zeroized when dropped)
+    pub fn lock_all(&mut self) -> Vec<H160> {
+        self.unlocked_keys
+            .drain()
+            .map(|(address, _)| address)
+            .collect()
+    }
+    /// Lock the expired keys, they are only checked when used otherwise
+    pub fn lock_expired(&mut self) -> Vec<H160> {
+        let expired = self
+            .unlocked_keys
+            .iter()
+            .filter(|(_, timed_key)| timed_key.is_expired())
+            .map(|(address, _)| address.clone())
+            .collect::<Vec<_>>();
+        for address in &expired {
+            self.unlocked_keys.remove(address);
+        }
+        expired
+    }
     pub fn unlock(&mut self, address: &H160, password: &[u8]) -> Result<KeyTimeout, Error> {
         self.unlock_inner(address, password, None)
     }
@@ -143,6 +165,31 @@ impl KeyStore {
         self.files.insert(key.address().clone(), filepath);
         Ok(key.address().clone())
     }
+    pub fn import_mnemonic(&mut self, phrase: &str, password: &[u8]) -> Result<H160, Error> {
+        let entropy = MnemonicEntropy::from_phrase(phrase)?;
+        let key = Key::from_mnemonic(entropy)?;
+        self.import_key(&key, password)
+    }
+    pub fn export_mnemonic(&self, address: &H160, password: &[u8]) -> Result<String, Error> {
+        let filepath = self.get_filepath(address)?;
+        let key = self.storage.get_key(address, &filepath, password)?;
+        key.mnemonic
+            .as_ref()
+            .map(MnemonicEntropy::phrase)
+            .ok_or_else(|| Error::MnemonicNotFound(address.clone()))
+    }
+    /// Derive a child key (as a standalone key) from an account by BIP-32 path, the path of an
+    /// account imported by mnemonic is from the root key of the mnemonic (see `Key::derive_privkey`)
+    pub fn derive_key(
+        &self,
+        address: &H160,
+        path: &DerivationPath,
+        password: &[u8],
+    ) -> Result<Key, Error> {
+        let filepath = self.get_filepath(address)?;
+        let key = self.storage.get_key(address, &filepath, password)?;
+        Ok(Key::new(key.derive_privkey(path)?))
+    }
     pub fn export(
         &self,
         address: &H160,
@@ -154,6 +201,89 @@ impl KeyStore {
         let key = self.storage.get_key(address, &filepath, password)?;
         Ok(key.to_json(new_password, scrypt_type))
     }
+    /// Bundle all key files into one archive encrypted by `passphrase`, every key file is kept
+    /// as is (still encrypted by its own password) along with its blake2b checksum.
+    pub fn backup(&mut self, passphrase: &[u8]) -> Result<serde_json::Value, Error> {
+        self.refresh_dir()?;
+        let mut files = self.files.iter().collect::<Vec<_>>();
+        files.sort_by(|a, b| a.0.cmp(b.0));
+        let mut entries = Vec::with_capacity(files.len());
+        for (address, filepath) in files {
+            let content = fs::read_to_string(filepath)?;
+            let filename = filepath
+                .file_name()
+                .and_then(OsStr::to_str)
+                .ok_or_else(|| Error::Other(format!("Invalid key file: {:?}", filepath)))?;
+            entries.push(serde_json::json!({
+                "filename": filename,
+                "address": format!("{:x}", address),
+                "checksum": hex_string(&blake2b_256(content.as_bytes())).unwrap(),
+                "content": content,
+            }));
+        }
+        let payload = serde_json::to_vec(&entries).map_err(|err| Error::Io(err.to_string()))?;
+        let crypto = Crypto::encrypt_key_scrypt(&payload, passphrase, self.storage.scrypt_type);
+        Ok(serde_json::json!({
+            "version": BACKUP_VERSION,
+            "count": entries.len(),
+            "crypto": crypto.to_json(),
+        }))
+    }
+    /// Restore key files from a backup archive. All checksums are verified before any file is
+    /// written, the accounts already in keystore are skipped and reported as collisions.
+    pub fn restore(
+        &mut self,
+        archive: &serde_json::Value,
+        passphrase: &[u8],
+    ) -> Result<RestoreReport, Error> {
+        let version = util::get_u64(archive, "version")? as u32;
+        if version != BACKUP_VERSION {
+            return Err(Error::ParseJsonFailed(format!(
+                "Unsupported backup version: {}",
+                version
+            )));
+        }
+        let crypto = util::get_value(archive, "crypto").and_then(Crypto::from_json)?;
+        let payload = crypto.decrypt(passphrase)?;
+        let entries: Vec<serde_json::Value> = serde_json::from_slice(&payload)
+            .map_err(|err| Error::ParseJsonFailed(err.to_string()))?;
+
+        let mut key_files = Vec::with_capacity(entries.len());
+        for entry in &entries {
+            let filename = util::get_str(entry, "filename")?;
+            let content = util::get_str(entry, "content")?;
+            let checksum = util::get_hex_bin(entry, "checksum")?;
+            if checksum[..] != blake2b_256(content.as_bytes())[..] {
+                return Err(Error::ChecksumMismatch(filename.to_owned()));
+            }
+            let data: serde_json::Value = serde_json::from_str(content)
+                .map_err(|err| Error::ParseJsonFailed(format!("{}: {}", filename, err)))?;
+            let address = util::get_hex_bin(&data, "address").and_then(|bin| {
+                H160::from_slice(&bin).map_err(|err| Error::ParseJsonFailed(err.to_string()))
+            })?;
+            if Path::new(filename).file_name().and_then(OsStr::to_str) != Some(filename) {
+                return Err(Error::ParseJsonFailed(format!(
+                    "Invalid key filename: {}",
+                    filename
+                )));
+            }
+            key_files.push((address, filename, content));
+        }
+
+        self.refresh_dir()?;
+        let mut report = RestoreReport::default();
+        for (address, filename, content) in key_files {
+            if self.files.contains_key(&address) {
+                report.collisions.push(address);
+                continue;
+            }
+            let filepath = self.storage.join_path(filename);
+            fs::write(&filepath, content)?;
+            self.files.insert(address.clone(), filepath);
+            report.restored.push(address);
+        }
+        Ok(report)
+    }
     pub fn export_key(&self, address: &H160, password: &[u8]) -> Result<MasterPrivKey, Error> {
         let filepath = self.get_filepath(address)?;
         let key = self.storage.get_key(address, &filepath, password)?;
@@ -193,15 +323,37 @@ impl KeyStore {
         let key = self.storage.get_key(address, &filepath, password)?;
         Ok(key.master_privkey.sign_recoverable(hash))
     }
+    /// Sign many messages with the key decrypted only once (decrypting is the slow part)
+    pub fn sign_recoverable_batch_with_password(
+        &self,
+        address: &H160,
+        hashes: &[H256],
+        password: &[u8],
+    ) -> Result<Vec<RecoverableSignature>, Error> {
+        let filepath = self.get_filepath(address)?;
+        let key = self.storage.get_key(address, &filepath, password)?;
+        Ok(hashes
+            .iter()
+            .map(|hash| key.master_privkey.sign_recoverable(hash))
+            .collect())
+    }
     pub fn extended_pubkey(
         &mut self,
         address: &H160,
         path: Option<&DerivationPath>,
     ) -> Result<ExtendedPubKey, Error> {
-        Ok(self
-            .get_timed_key(address)?
-            .master_privkey()
-            .extended_pubkey(path)?)
+        self.get_timed_key(address)?.key.extended_pubkey(path)
+    }
+
+    pub fn extended_pubkey_with_password(
+        &self,
+        address: &H160,
+        path: Option<&DerivationPath>,
+        password: &[u8],
+    ) -> Result<ExtendedPubKey, Error> {
+        let filepath = self.get_filepath(address)?;
+        let key = self.storage.get_key(address, &filepath, password)?;
+        key.extended_pubkey(path)
     }
 
     // NOTE: assume refresh keystore directory is not a hot action
@@ -269,6 +421,14 @@ impl KeyStore {
     }
 }
 
+/// Result of `KeyStore::restore`
+#[derive(Debug, Default)]
+pub struct RestoreReport {
+    pub restored: Vec<H160>,
+    // Accounts already exist in keystore (not overwritten)
+    pub collisions: Vec<H160>,
+}
+
 /// KeyStore protected by password
 #[derive(Clone)]
 struct PassphraseKeyStore {
@@ -400,6 +560,8 @@ pub struct Key {
     address: H160,
     // The extended secp256k1 private key (privkey + chaincode)
     master_privkey: MasterPrivKey,
+    // The BIP-39 mnemonic entropy if the key is restored from (or created with) mnemonic
+    mnemonic: Option<MnemonicEntropy>,
 }
 
 impl Key {
@@ -410,9 +572,42 @@ impl Key {
             id,
             address,
             master_privkey,
+            mnemonic: None,
+        }
+    }
+
+    /// The key of the first receiving address (m/44'/309'/0'/0/0), so the account is the same as
+    /// in other CKB wallets restored from the mnemonic
+    pub fn from_mnemonic(mnemonic: MnemonicEntropy) -> Result<Key, Error> {
+        let privkey = mnemonic
+            .master_privkey()?
+            .derive_privkey(&bip44_path(false, 0))?;
+        let mut key = Key::new(privkey);
+        key.mnemonic = Some(mnemonic);
+        Ok(key)
+    }
+
+    /// Derive a child key by BIP-32 path, from the root key of the mnemonic if the key is
+    /// restored from one (the key itself is a child of it)
+    pub fn derive_privkey(&self, path: &DerivationPath) -> Result<MasterPrivKey, Error> {
+        match self.mnemonic.as_ref() {
+            Some(mnemonic) => Ok(mnemonic.master_privkey()?.derive_privkey(path)?),
+            None => Ok(self.master_privkey.derive_privkey(path)?),
         }
     }
 
+    /// The extended public key by BIP-32 path, from the same root as `derive_privkey`
+    pub fn extended_pubkey(&self, path: Option<&DerivationPath>) -> Result<ExtendedPubKey, Error> {
+        match self.mnemonic.as_ref() {
+            Some(mnemonic) => Ok(mnemonic.master_privkey()?.extended_pubkey(path)?),
+            None => Ok(self.master_privkey.extended_pubkey(path)?),
+        }
+    }
+
+    pub fn has_mnemonic(&self) -> bool {
+        self.mnemonic.is_some()
+    }
+
     pub fn address(&self) -> &H160 {
         &self.address
     }
@@ -459,12 +654,20 @@ impl Key {
         let mut key_bytes = [0u8; 64];
         key_bytes[..].copy_from_slice(&key_vec[..]);
         let master_privkey = MasterPrivKey::from_bytes(key_bytes)?;
+        let mnemonic = match data.get("mnemonic") {
+            Some(value) => {
+                let entropy = Crypto::from_json(value)?.decrypt(password)?;
+                Some(MnemonicEntropy::from_bytes(entropy)?)
+            }
+            None => None,
+        };
 
         let address = master_privkey.address();
         Ok(Key {
             id,
             address,
             master_privkey,
+            mnemonic,
         })
     }
 
@@ -474,12 +677,18 @@ impl Key {
         let address_hex = format!("{:x}", self.address);
         let master_privkey = self.master_privkey.to_bytes();
         let crypto = Crypto::encrypt_key_scrypt(&master_privkey, password, scrypt_type);
-        serde_json::json!({
+        let mut value = serde_json::json!({
             "id": id_str,
             "version": KEYSTORE_VERSION,
             "address": address_hex,
             "crypto": crypto.to_json(),
-        })
+        });
+        if let Some(ref mnemonic) = self.mnemonic {
+            let mnemonic_crypto =
+                Crypto::encrypt_key_scrypt(mnemonic.as_bytes(), password, scrypt_type);
+            value["mnemonic"] = mnemonic_crypto.to_json();
+        }
+        value
     }
 }
 
@@ -514,6 +723,13 @@ impl MasterPrivKey {
         }
     }
 
+    pub fn from_extended_privkey(sk: &ExtendedPrivKey) -> MasterPrivKey {
+        MasterPrivKey {
+            secp_secret_key: sk.private_key,
+            chain_code: sk.chain_code.0,
+        }
+    }
+
     pub fn from_bytes(bytes: [u8; 64]) -> Result<MasterPrivKey, Error> {
         let secp_secret_key = secp256k1::SecretKey::from_slice(&bytes[0..32])
             .map_err(|_| Error::InvalidSecpSecret)?;
@@ -544,14 +760,26 @@ impl MasterPrivKey {
         SECP256K1.sign_recoverable(&message, &self.secp_secret_key)
     }
 
-    pub fn extended_pubkey(&self, path: Option<&DerivationPath>) -> Result<ExtendedPubKey, String> {
-        let sk = ExtendedPrivKey {
+    pub fn derive_privkey(&self, path: &DerivationPath) -> Result<MasterPrivKey, String> {
+        let sub_sk = self
+            .to_extended_privkey()
+            .derive_priv(&SECP256K1, path)
+            .map_err(|err| err.to_string())?;
+        Ok(MasterPrivKey::from_extended_privkey(&sub_sk))
+    }
+
+    fn to_extended_privkey(&self) -> ExtendedPrivKey {
+        ExtendedPrivKey {
             depth: 0,
             parent_fingerprint: Default::default(),
             child_number: ChildNumber::Normal { index: 0 },
             private_key: self.secp_secret_key,
             chain_code: ChainCode(self.chain_code),
-        };
+        }
+    }
+
+    pub fn extended_pubkey(&self, path: Option<&DerivationPath>) -> Result<ExtendedPubKey, String> {
+        let sk = self.to_extended_privkey();
         let sub_sk = if let Some(path) = path {
             sk.derive_priv(&SECP256K1, path)
                 .map_err(|err| err.to_string())?
@@ -0,0 +1,138 @@
+use bip39::{Language, Mnemonic, MnemonicType, Seed};
+
+use super::bip32::{DerivationPath, ExtendedPrivKey};
+use super::keystore::{zeroize_slice, Error, MasterPrivKey};
+
+/// The default BIP-44 account path of CKB (coin type: 309)
+pub const CKB_ACCOUNT_PATH: &str = "m/44'/309'/0'";
+
+/// BIP-39 mnemonic entropy, zeroized on drop
+pub struct MnemonicEntropy(Vec<u8>);
+
+impl MnemonicEntropy {
+    /// Generate a new 12 words mnemonic
+    pub fn generate() -> MnemonicEntropy {
+        let mnemonic = Mnemonic::new(MnemonicType::Words12, Language::English);
+        MnemonicEntropy(mnemonic.entropy().to_vec())
+    }
+
+    pub fn from_phrase(phrase: &str) -> Result<MnemonicEntropy, Error> {
+        let phrase = phrase.split_whitespace().collect::<Vec<_>>().join(" ");
+        let mnemonic = Mnemonic::from_phrase(&phrase, Language::English)
+            .map_err(|err| Error::InvalidMnemonic(err.to_string()))?;
+        Ok(MnemonicEntropy(mnemonic.entropy().to_vec()))
+    }
+
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<MnemonicEntropy, Error> {
+        Mnemonic::from_entropy(&bytes, Language::English)
+            .map_err(|err| Error::InvalidMnemonic(err.to_string()))?;
+        Ok(MnemonicEntropy(bytes))
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn phrase(&self) -> String {
+        Mnemonic::from_entropy(&self.0, Language::English)
+            .expect("entropy checked when construct")
+            .phrase()
+            .to_owned()
+    }
+
+    /// The root(m) key derived from BIP-39 seed (empty passphrase)
+    pub fn master_privkey(&self) -> Result<MasterPrivKey, Error> {
+        let mnemonic = Mnemonic::from_entropy(&self.0, Language::English)
+            .map_err(|err| Error::InvalidMnemonic(err.to_string()))?;
+        let seed = Seed::new(&mnemonic, "");
+        let root_key = ExtendedPrivKey::new_master(seed.as_bytes())
+            .map_err(|err| Error::Other(err.to_string()))?;
+        Ok(MasterPrivKey::from_extended_privkey(&root_key))
+    }
+}
+
+impl Clone for MnemonicEntropy {
+    fn clone(&self) -> MnemonicEntropy {
+        MnemonicEntropy(self.0.clone())
+    }
+}
+
+impl Drop for MnemonicEntropy {
+    fn drop(&mut self) {
+        zeroize_slice(&mut self.0);
+    }
+}
+
+/// The BIP-44 address path of CKB: m/44'/309'/0'/{change}/{index}
+pub fn bip44_path(change: bool, index: u32) -> DerivationPath {
+    format!(
+        "{}/{}/{}",
+        CKB_ACCOUNT_PATH,
+        if change { 1 } else { 0 },
+        index
+    )
+    .parse()
+    .expect("valid BIP-44 path")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::keystore::Key;
+    use super::*;
+
+    #[test]
+    fn test_mnemonic_roundtrip() {
+        let entropy = MnemonicEntropy::generate();
+        let phrase = entropy.phrase();
+        assert_eq!(phrase.split(' ').count(), 12);
+        let restored = MnemonicEntropy::from_phrase(&phrase).unwrap();
+        assert_eq!(restored.as_bytes(), entropy.as_bytes());
+        assert_eq!(
+            restored.master_privkey().unwrap().address(),
+            entropy.master_privkey().unwrap().address()
+        );
+        assert!(MnemonicEntropy::from_phrase("invalid mnemonic phrase").is_err());
+
+        let key = Key::from_mnemonic(restored).unwrap();
+        let account_privkey = entropy
+            .master_privkey()
+            .unwrap()
+            .derive_privkey(&bip44_path(false, 0))
+            .unwrap();
+        assert_eq!(key.address(), &account_privkey.address());
+    }
+
+    #[test]
+    fn test_mnemonic_extended_pubkey() {
+        use super::super::bip32::ChildNumber;
+        use ckb_crypto::secp::SECP256K1;
+        use ckb_hash::blake2b_256;
+
+        let key = Key::from_mnemonic(MnemonicEntropy::generate()).unwrap();
+        let account_path: DerivationPath = CKB_ACCOUNT_PATH.parse().unwrap();
+        // The first receiving address listed by `account extended-address`
+        let receiving_pubkey = key
+            .extended_pubkey(Some(&account_path))
+            .unwrap()
+            .derive_pub(
+                &SECP256K1,
+                &[
+                    ChildNumber::from_normal_idx(0).unwrap(),
+                    ChildNumber::from_normal_idx(0).unwrap(),
+                ],
+            )
+            .unwrap();
+        let lock_arg = &blake2b_256(&receiving_pubkey.public_key.serialize()[..])[0..20];
+        assert_eq!(lock_arg, key.address().as_bytes());
+        assert_eq!(
+            key.derive_privkey(&bip44_path(false, 0)).unwrap().address(),
+            *key.address()
+        );
+    }
+
+    #[test]
+    fn test_bip44_path() {
+        assert_eq!(bip44_path(false, 3).to_string(), "m/44'/309'/0'/0/3");
+        assert_eq!(bip44_path(true, 0).to_string(), "m/44'/309'/0'/1/0");
+    }
+}
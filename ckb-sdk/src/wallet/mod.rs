@@ -1,6 +1,7 @@
 mod bip32;
 mod error;
 mod keystore;
+mod mnemonic;
 
 pub use bip32::{
     ChainCode, ChildNumber, DerivationPath, Error as Bip32Error, ExtendedPrivKey, ExtendedPubKey,
@@ -9,5 +10,6 @@ pub use bip32::{
 pub use error::Error as WalletError;
 pub use keystore::{
     zeroize_privkey, zeroize_slice, CipherParams, Crypto, Error as KeyStoreError, KdfParams, Key,
-    KeyStore, KeyTimeout, MasterPrivKey, ScryptParams, ScryptType,
+    KeyStore, KeyTimeout, MasterPrivKey, RestoreReport, ScryptParams, ScryptType,
 };
+pub use mnemonic::{bip44_path, MnemonicEntropy, CKB_ACCOUNT_PATH};
@@ -1,33 +1,48 @@
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
 use std::time::{Duration, Instant};
 
 use ansi_term::Colour::Green;
 use ckb_jsonrpc_types::BlockNumber;
 use ckb_types::{core::service::Request, core::BlockView};
+use ckb_util::Mutex;
 use regex::Regex;
 use rustyline::config::Configurer;
 use rustyline::error::ReadlineError;
+use rustyline::history::History;
 use rustyline::{Cmd, CompletionType, Config, EditMode, Editor, KeyPress};
 use serde_json::json;
 
 use crate::subcommands::{
-    AccountSubCommand, CliSubCommand, IndexController, IndexRequest, MockTxSubCommand,
-    RpcSubCommand, UtilSubCommand, WalletSubCommand,
+    AccountSubCommand, ChainSubCommand, CliSubCommand, FeeGuard, IndexController, IndexRequest,
+    IndexSubCommand, MockTxSubCommand, RpcSubCommand, TxSubCommand, UtilSubCommand,
+    WalletSubCommand,
 };
 use crate::utils::{
+    arg_parser::{ArgParser, CapacityParser, FilePathParser, FromStrParser},
     completer::CkbCompleter,
-    config::GlobalConfig,
-    other::check_alerts,
+    config::{ChangeStrategy, GlobalConfig},
+    node_capabilities,
+    other::{check_alerts, print_timings, strip_url_password},
+    policy,
     printer::{ColorWhen, OutputFormat, Printable},
+    sign_history,
 };
 use ckb_sdk::{
+    stats::{self, record_cache},
     wallet::{KeyStore, ScryptType},
-    GenesisInfo, HttpRpcClient,
+    ChangePolicy, DustPolicy, GenesisInfo, HttpRpcClient,
 };
 
-const ENV_PATTERN: &str = r"\$\{\s*(?P<key>\S+)\s*\}";
+const ENV_PATTERN: &str = r"\$\{\s*(?P<key>\S+)\s*\}|\$(?P<name>[A-Z][A-Z0-9_]*)";
+// Removed from the output before capturing it into variables
+const COLOR_PATTERN: &str = r"\x1b\[[0-9;]*m";
+// Nested `source` commands
+const MAX_SOURCE_DEPTH: usize = 16;
 
 /// Interactive command line
 pub struct InteractiveEnv {
@@ -35,16 +50,22 @@ pub struct InteractiveEnv {
     config_file: PathBuf,
     history_file: PathBuf,
     index_dir: PathBuf,
+    keystore_dir: PathBuf,
     parser: clap::App<'static, 'static>,
-    key_store: KeyStore,
+    // Shared with the thread locking the expired keys
+    key_store: Arc<Mutex<KeyStore>>,
     rpc_client: HttpRpcClient,
     index_controller: IndexController,
     genesis_info: Option<GenesisInfo>,
+    // The files being run by `source` (outermost first), a file can not source itself
+    sourcing: Vec<PathBuf>,
 }
 
 impl InteractiveEnv {
     pub fn from_config(
         ckb_cli_dir: PathBuf,
+        index_dir: PathBuf,
+        keystore_dir: PathBuf,
         mut config: GlobalConfig,
         index_controller: IndexController,
     ) -> Result<InteractiveEnv, String> {
@@ -55,10 +76,6 @@ impl InteractiveEnv {
         history_file.push("history");
         let mut config_file = ckb_cli_dir.clone();
         config_file.push("config");
-        let mut index_dir = ckb_cli_dir.clone();
-        index_dir.push("index");
-        let mut keystore_dir = ckb_cli_dir.clone();
-        keystore_dir.push("keystore");
 
         let mut env_file = ckb_cli_dir.clone();
         env_file.push("env_vars");
@@ -74,18 +91,28 @@ impl InteractiveEnv {
         let parser = crate::build_interactive();
         let rpc_client = HttpRpcClient::from_uri(config.get_url());
         fs::create_dir_all(&keystore_dir).map_err(|err| err.to_string())?;
-        let key_store = KeyStore::from_dir(keystore_dir, ScryptType::default())
+        let key_store = KeyStore::from_dir(keystore_dir.clone(), ScryptType::default())
             .map_err(|err| err.to_string())?;
+        let key_store = Arc::new(Mutex::new(key_store));
+        // The keys unlocked by `account unlock` are erased from memory once expired, not only
+        // when they are used next time
+        let expiring_key_store = Arc::clone(&key_store);
+        thread::spawn(move || loop {
+            thread::sleep(Duration::from_secs(1));
+            expiring_key_store.lock().lock_expired();
+        });
         Ok(InteractiveEnv {
             config,
             config_file,
             index_dir,
+            keystore_dir,
             history_file,
             parser,
             rpc_client,
             key_store,
             index_controller,
             genesis_info: None,
+            sourcing: Vec::new(),
         })
     }
 
@@ -122,32 +149,38 @@ impl InteractiveEnv {
 
         let rl_config = Config::builder()
             .history_ignore_space(true)
+            .history_ignore_dups(true)
+            .max_history_size(self.config.history_size())
             .completion_type(CompletionType::List)
             .edit_mode(EditMode::Emacs)
             .build();
-        let helper = CkbCompleter::new(self.parser.clone());
+        let mut helper = CkbCompleter::new(self.parser.clone());
+        // A separate keystore only listing the accounts for completion
+        if let Ok(key_store) = KeyStore::from_dir(self.keystore_dir.clone(), ScryptType::default())
+        {
+            helper = helper.with_key_store(key_store);
+        }
         let mut rl = Editor::with_config(rl_config);
         rl.set_helper(Some(helper));
         rl.bind_sequence(KeyPress::Meta('N'), Cmd::HistorySearchForward);
         rl.bind_sequence(KeyPress::Meta('P'), Cmd::HistorySearchBackward);
+        // Also in Vi mode
+        rl.bind_sequence(KeyPress::Ctrl('R'), Cmd::ReverseSearchHistory);
         if rl.load_history(&self.history_file).is_err() {
             eprintln!("No previous history.");
         }
 
-        Request::call(
-            self.index_controller.sender(),
-            IndexRequest::UpdateUrl(self.config.get_url().to_string()),
-        );
-        let mut last_save_history = Instant::now();
+        self.start_index();
         loop {
             rl_mode(
                 &mut rl,
                 self.config.completion_style(),
                 self.config.edit_style(),
             );
+            rl.set_max_history_size(self.config.history_size());
             match rl.readline(&prompt) {
                 Ok(line) => {
-                    match self.handle_command(line.as_str(), &env_regex) {
+                    match self.handle_command(line.as_str(), &env_regex, rl.history()) {
                         Ok(true) => {
                             break;
                         }
@@ -156,7 +189,12 @@ impl InteractiveEnv {
                             eprintln!("{}", err.to_string());
                         }
                     }
-                    rl.add_history_entry(line.as_str());
+                    // Saved after every command, so the history survives a killed session
+                    if rl.add_history_entry(line.as_str()) {
+                        if let Err(err) = rl.save_history(&self.history_file) {
+                            eprintln!("Save command history failed: {}", err);
+                        }
+                    }
                 }
                 Err(ReadlineError::Interrupted) => {
                     println!("CTRL-C");
@@ -170,14 +208,6 @@ impl InteractiveEnv {
                     break;
                 }
             }
-
-            if last_save_history.elapsed() >= Duration::from_secs(120) {
-                if let Err(err) = rl.save_history(&self.history_file) {
-                    eprintln!("Save command history failed: {}", err);
-                    break;
-                }
-                last_save_history = Instant::now();
-            }
         }
         if let Err(err) = rl.save_history(&self.history_file) {
             eprintln!("Save command history failed: {}", err);
@@ -185,6 +215,91 @@ impl InteractiveEnv {
         Ok(())
     }
 
+    /// Run the commands of a file (`--batch-file`) like `source <file>`, stop on first error
+    pub fn run_batch(&mut self, path: &Path) -> Result<(), String> {
+        let env_regex = Regex::new(ENV_PATTERN).unwrap();
+        self.start_index();
+        self.source_file(path, &env_regex, &History::new())
+            .map(|_| ())
+    }
+
+    fn start_index(&mut self) {
+        // No local index sync when the cells are searched by ckb-indexer
+        if self.config.indexer_url().is_none() {
+            Request::call(
+                self.index_controller.sender(),
+                IndexRequest::UpdateUrl(self.config.get_url().to_string()),
+            );
+        }
+    }
+
+    /// Run the commands of a file line by line (empty lines and `#` comments are skipped),
+    /// returns true if `exit` is called. A file sourcing itself (directly or not) is an error.
+    fn source_file(
+        &mut self,
+        path: &Path,
+        env_regex: &Regex,
+        history: &History,
+    ) -> Result<bool, String> {
+        let canonical_path = path
+            .canonicalize()
+            .map_err(|err| format!("Read {} failed: {}", path.display(), err))?;
+        if self.sourcing.contains(&canonical_path) {
+            return Err(format!("Recursive source of {}", path.display()));
+        }
+        if self.sourcing.len() >= MAX_SOURCE_DEPTH {
+            return Err(format!(
+                "Source {} failed: nested deeper than {} files",
+                path.display(),
+                MAX_SOURCE_DEPTH
+            ));
+        }
+        let content = fs::read_to_string(path)
+            .map_err(|err| format!("Read {} failed: {}", path.display(), err))?;
+        self.sourcing.push(canonical_path);
+        let result = self.run_source_lines(path, &content, env_regex, history);
+        self.sourcing.pop();
+        result
+    }
+
+    fn run_source_lines(
+        &mut self,
+        path: &Path,
+        content: &str,
+        env_regex: &Regex,
+        history: &History,
+    ) -> Result<bool, String> {
+        for (idx, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            eprintln!("> {}", line);
+            let exit = self
+                .handle_command(line, env_regex, history)
+                .map_err(|err| format!("{}:{}: {}", path.display(), idx + 1, err))?;
+            if exit {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Print the output of a command, and capture it for the following commands: the output as
+    /// `LAST_OUTPUT` and the sent transaction hash as `LAST_TX_HASH`.
+    fn print_output(&mut self, output: String) {
+        println!("{}", output);
+        let output = Regex::new(COLOR_PATTERN)
+            .unwrap()
+            .replace_all(output.trim(), "")
+            .into_owned();
+        if let Some(tx_hash) = captured_tx_hash(&output) {
+            self.config
+                .set("LAST_TX_HASH".to_owned(), json!(tx_hash.to_owned()));
+        }
+        self.config.set("LAST_OUTPUT".to_owned(), json!(output));
+    }
+
     fn print_logo(&mut self) {
         println!(
             "{}",
@@ -208,6 +323,7 @@ impl InteractiveEnv {
     }
 
     fn genesis_info(&mut self) -> Result<GenesisInfo, String> {
+        record_cache("genesis_info", self.genesis_info.is_some());
         if self.genesis_info.is_none() {
             let genesis_block: BlockView = self
                 .rpc_client
@@ -222,27 +338,86 @@ impl InteractiveEnv {
         Ok(self.genesis_info.clone().unwrap())
     }
 
-    fn handle_command(&mut self, line: &str, env_regex: &Regex) -> Result<bool, String> {
+    fn handle_command(
+        &mut self,
+        line: &str,
+        env_regex: &Regex,
+        history: &History,
+    ) -> Result<bool, String> {
         let args = match shell_words::split(self.config.replace_cmd(&env_regex, line).as_str()) {
             Ok(args) => args,
             Err(e) => return Err(e.to_string()),
         };
+        sign_history::set_command(line);
+        policy::clear_approvals();
 
         let format = self.config.output_format();
         let color = ColorWhen::new(self.config.color()).color();
         let debug = self.config.debug();
-        match self.parser.clone().get_matches_from_safe(args) {
-            Ok(matches) => {
-                match matches.subcommand() {
+        let timings = self.config.timings();
+        stats::take();
+        let start_time = Instant::now();
+        let result =
+            match self.parser.clone().get_matches_from_safe(args) {
+                Ok(matches) => match matches.subcommand() {
                     ("config", Some(m)) => {
-                        m.value_of("url").and_then(|url| {
-                            let index_sender = self.index_controller.sender();
-                            Request::call(index_sender, IndexRequest::UpdateUrl(url.to_string()));
+                        if let Some(inputs) = m.values_of("add-node") {
+                            for input in inputs {
+                                self.config.add_node(input)?;
+                            }
+                        }
+                        if let Some(name) = m.value_of("remove-node") {
+                            self.config.remove_node(name)?;
+                        }
+                        let old_url = self.config.get_url().to_string();
+                        if let Some(url) = m.value_of("url") {
                             self.config.set_url(url.to_string());
-                            self.rpc_client = HttpRpcClient::from_uri(self.config.get_url());
+                            self.config.clear_node();
+                        }
+                        if let Some(name) = m.value_of("node") {
+                            self.config.select_node(name)?;
+                        }
+                        if self.config.get_url() != old_url {
+                            let url = self.config.get_url().to_string();
+                            if self.config.indexer_url().is_none() {
+                                let index_sender = self.index_controller.sender();
+                                Request::call(index_sender, IndexRequest::UpdateUrl(url.clone()));
+                            }
+                            self.rpc_client = HttpRpcClient::from_uri(&url);
+                            node_capabilities::reset();
                             self.genesis_info = None;
-                            Some(())
-                        });
+                        }
+                        if m.is_present("rpc-retries") || m.is_present("failover-url") {
+                            let mut retry_policy = self.config.retry_policy().clone();
+                            if let Some(max_retries) = FromStrParser::<u32>::default()
+                                .from_matches_opt(m, "rpc-retries", false)?
+                            {
+                                retry_policy.max_retries = max_retries;
+                            }
+                            if let Some(failover_url) = m.value_of("failover-url") {
+                                let url = self.config.get_url().to_owned();
+                                if failover_url.is_empty() {
+                                    retry_policy.failover_urls.remove(&url);
+                                } else {
+                                    retry_policy
+                                        .failover_urls
+                                        .insert(url, failover_url.to_owned());
+                                }
+                            }
+                            self.config.set_retry_policy(retry_policy);
+                            self.rpc_client = HttpRpcClient::from_uri(self.config.get_url());
+                        }
+                        if let Some(indexer_url) = m.value_of("indexer-url") {
+                            if indexer_url.is_empty() {
+                                self.config.set_indexer_url(None);
+                                Request::call(
+                                    self.index_controller.sender(),
+                                    IndexRequest::UpdateUrl(self.config.get_url().to_string()),
+                                );
+                            } else {
+                                self.config.set_indexer_url(Some(indexer_url.to_owned()));
+                            }
+                        }
                         if m.is_present("color") {
                             self.config.switch_color();
                         }
@@ -257,6 +432,10 @@ impl InteractiveEnv {
                             self.config.switch_debug();
                         }
 
+                        if m.is_present("timings") {
+                            self.config.switch_timings();
+                        }
+
                         if m.is_present("edit_style") {
                             self.config.switch_edit_style();
                         }
@@ -265,16 +444,85 @@ impl InteractiveEnv {
                             self.config.switch_completion_style();
                         }
 
+                        if let Some(history_size) = FromStrParser::<usize>::default()
+                            .from_matches_opt(m, "history-size", false)?
+                        {
+                            self.config.set_history_size(history_size);
+                        }
+
+                        if let Some(value) = m.value_of("change-strategy") {
+                            self.config
+                                .set_change_strategy(ChangeStrategy::from_str(value)?);
+                        }
+
+                        if m.is_present("min-change-capacity") || m.is_present("dust-policy") {
+                            let old_policy = self.config.change_policy();
+                            let min_change_capacity: u64 = CapacityParser
+                                .from_matches_opt(m, "min-change-capacity", false)?
+                                .unwrap_or_else(|| old_policy.min_change_capacity());
+                            let dust_policy = match m.value_of("dust-policy") {
+                                Some(value) => DustPolicy::from_str(value)?,
+                                None => old_policy.dust(),
+                            };
+                            self.config.set_change_policy(ChangePolicy::new(
+                                min_change_capacity,
+                                dust_policy,
+                            )?);
+                        }
+
+                        if m.is_present("max-tx-fee") || m.is_present("max-fee-percent") {
+                            let old_guard = self.config.fee_guard();
+                            let max_fee: u64 = CapacityParser
+                                .from_matches_opt(m, "max-tx-fee", false)?
+                                .unwrap_or(old_guard.max_fee);
+                            let max_fee_percent: u64 = FromStrParser::<u64>::default()
+                                .from_matches_opt(m, "max-fee-percent", false)?
+                                .unwrap_or(old_guard.max_fee_percent);
+                            self.config.set_fee_guard(FeeGuard {
+                                max_fee,
+                                max_fee_percent,
+                            });
+                        }
+
                         self.config.print();
                         let mut file = fs::File::create(self.config_file.as_path())
                             .map_err(|err| format!("open config error: {:?}", err))?;
+                        // No plaintext password in the config file
+                        let nodes: BTreeMap<&String, String> = self
+                            .config
+                            .nodes()
+                            .iter()
+                            .map(|(name, url)| (name, strip_url_password(url)))
+                            .collect();
+                        let failover_urls: HashMap<String, String> = self
+                            .config
+                            .retry_policy()
+                            .failover_urls
+                            .iter()
+                            .map(|(url, failover_url)| {
+                                (strip_url_password(url), strip_url_password(failover_url))
+                            })
+                            .collect();
                         let content = serde_json::to_string_pretty(&json!({
-                            "url": self.config.get_url().to_string(),
+                            "url": strip_url_password(self.config.get_url()),
+                            "nodes": nodes,
+                            "node": self.config.node(),
                             "color": self.config.color(),
                             "debug": self.config.debug(),
+                            "timings": self.config.timings(),
                             "output_format": self.config.output_format().to_string(),
                             "completion_style": self.config.completion_style(),
                             "edit_style": self.config.edit_style(),
+                            "history_size": self.config.history_size(),
+                            "change_strategy": self.config.change_strategy().to_string(),
+                            "min_change_capacity": self.config.change_policy().min_change_capacity(),
+                            "dust_policy": self.config.change_policy().dust().to_string(),
+                            "max_tx_fee": self.config.fee_guard().max_fee,
+                            "max_fee_percent": self.config.fee_guard().max_fee_percent,
+                            "rpc_retries": self.config.retry_policy().max_retries,
+                            "rpc_backoff_ms": self.config.retry_policy().initial_backoff.as_millis() as u64,
+                            "failover_urls": failover_urls,
+                            "indexer_url": self.config.indexer_url().map(strip_url_password),
                         }))
                         .unwrap();
                         file.write_all(content.as_bytes())
@@ -296,6 +544,28 @@ impl InteractiveEnv {
                         self.config.print();
                         Ok(())
                     }
+                    ("source", Some(m)) => {
+                        let path: PathBuf = FilePathParser::new(true).from_matches(m, "file")?;
+                        if self.source_file(&path, env_regex, history)? {
+                            return Ok(true);
+                        }
+                        Ok(())
+                    }
+                    ("history", Some(m)) => {
+                        let limit: usize =
+                            FromStrParser::<usize>::default().from_matches(m, "limit")?;
+                        let keyword = m.value_of("search").unwrap_or("");
+                        let entries = history
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, entry)| entry.contains(keyword))
+                            .collect::<Vec<_>>();
+                        let skip = entries.len().saturating_sub(limit);
+                        for (idx, entry) in entries.into_iter().skip(skip) {
+                            println!("{:>5}  {}", idx + 1, entry);
+                        }
+                        Ok(())
+                    }
                     ("rpc", Some(sub_matches)) => {
                         check_alerts(&mut self.rpc_client);
                         let output = RpcSubCommand::new(&mut self.rpc_client).process(
@@ -304,60 +574,156 @@ impl InteractiveEnv {
                             color,
                             debug,
                         )?;
-                        println!("{}", output);
+                        self.print_output(output);
                         Ok(())
                     }
                     ("account", Some(sub_matches)) => {
                         let genesis_info = self.genesis_info().ok();
                         let output = AccountSubCommand::new(
                             &mut self.rpc_client,
-                            &mut self.key_store,
+                            &mut self.key_store.lock(),
                             genesis_info,
                         )
                         .process(&sub_matches, format, color, debug)?;
-                        println!("{}", output);
+                        self.print_output(output);
                         Ok(())
                     }
                     ("mock-tx", Some(sub_matches)) => {
                         let genesis_info = self.genesis_info().ok();
                         let output = MockTxSubCommand::new(
                             &mut self.rpc_client,
-                            &mut self.key_store,
+                            &mut self.key_store.lock(),
+                            genesis_info,
+                        )
+                        .with_fee_guard(self.config.fee_guard())
+                        .process(&sub_matches, format, color, debug)?;
+                        self.print_output(output);
+                        Ok(())
+                    }
+                    ("tx", Some(sub_matches)) => {
+                        let genesis_info = self.genesis_info().ok();
+                        let output = TxSubCommand::new(
+                            &mut self.rpc_client,
+                            &mut self.key_store.lock(),
                             genesis_info,
                         )
+                        .with_fee_guard(self.config.fee_guard())
                         .process(&sub_matches, format, color, debug)?;
-                        println!("{}", output);
+                        self.print_output(output);
                         Ok(())
                     }
                     ("util", Some(sub_matches)) => {
                         let genesis_info = self.genesis_info().ok();
-                        let output = UtilSubCommand::new(&mut self.rpc_client, genesis_info)
+                        let output = UtilSubCommand::new(
+                            &mut self.rpc_client,
+                            &mut self.key_store.lock(),
+                            genesis_info,
+                        )
+                        .process(&sub_matches, format, color, debug)?;
+                        self.print_output(output);
+                        Ok(())
+                    }
+                    ("chain", Some(sub_matches)) => {
+                        let genesis_info = self.genesis_info().ok();
+                        let output = ChainSubCommand::new(&mut self.rpc_client, genesis_info)
                             .process(&sub_matches, format, color, debug)?;
-                        println!("{}", output);
+                        self.print_output(output);
+                        Ok(())
+                    }
+                    ("index", Some(sub_matches)) => {
+                        let genesis_info = self.genesis_info().ok();
+                        let output = IndexSubCommand::new(
+                            &mut self.rpc_client,
+                            genesis_info,
+                            self.index_dir.clone(),
+                            self.index_controller.clone(),
+                        )
+                        .process(&sub_matches, format, color, debug)?;
+                        self.print_output(output);
                         Ok(())
                     }
                     ("wallet", Some(sub_matches)) => {
                         let genesis_info = self.genesis_info()?;
                         let output = WalletSubCommand::new(
                             &mut self.rpc_client,
-                            &mut self.key_store,
+                            &mut self.key_store.lock(),
                             Some(genesis_info),
                             self.index_dir.clone(),
                             self.index_controller.clone(),
+                            self.config.change_strategy().clone(),
                             true,
                         )
+                        .with_change_policy(self.config.change_policy())
+                        .with_indexer_url(self.config.indexer_url())
+                        .with_default_fee_rate(self.config.default_fee_rate())
+                        .with_fee_guard(self.config.fee_guard())
                         .process(&sub_matches, format, color, debug)?;
-                        println!("{}", output);
+                        self.print_output(output);
                         Ok(())
                     }
                     ("exit", _) => {
                         return Ok(true);
                     }
                     _ => Ok(()),
-                }
+                },
+                Err(err) => Err(err.to_string()),
             }
-            Err(err) => Err(err.to_string()),
+            .map(|_| false);
+        if timings {
+            print_timings(start_time.elapsed());
         }
-        .map(|_| false)
+        result
+    }
+}
+
+// The transaction hash printed by a sending command (like `wallet transfer`), `tx_hash: 0x..` in
+// yaml or `{"tx_hash": "0x.."}` in JSON
+fn captured_tx_hash(output: &str) -> Option<&str> {
+    let output = output
+        .trim()
+        .trim_start_matches('{')
+        .trim_end_matches('}')
+        .trim();
+    let value = match output.find(':') {
+        Some(pos) if output[..pos].trim_matches(|c| c == '"' || c == '\'') == "tx_hash" => {
+            &output[pos + 1..]
+        }
+        Some(_) => return None,
+        None => output,
+    };
+    let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+    let is_hash = value.len() == 66
+        && value.starts_with("0x")
+        && value[2..].chars().all(|c| c.is_ascii_hexdigit());
+    if is_hash {
+        Some(value)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_captured_tx_hash() {
+        let tx_hash = "0x8f8c79eb6671709633fe6a46de93c0fedc9c1b8a6527a18d3983879542635c9f";
+        assert_eq!(captured_tx_hash(tx_hash), Some(tx_hash));
+        assert_eq!(captured_tx_hash(&format!("\"{}\"", tx_hash)), Some(tx_hash));
+        assert_eq!(
+            captured_tx_hash(&format!("tx_hash: {}", tx_hash)),
+            Some(tx_hash)
+        );
+        assert_eq!(
+            captured_tx_hash(&format!("{{\n  \"tx_hash\": \"{}\"\n}}", tx_hash)),
+            Some(tx_hash)
+        );
+        assert_eq!(
+            captured_tx_hash(&format!("tx_hash: {}\ncapacity: 100", tx_hash)),
+            None
+        );
+        assert_eq!(captured_tx_hash("capacity: 100"), None);
+        assert_eq!(captured_tx_hash(&tx_hash[..64]), None);
     }
 }
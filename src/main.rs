@@ -3,27 +3,43 @@ use std::env;
 use std::fs;
 use std::io::{self, Read};
 use std::iter::FromIterator;
+use std::path::{Path, PathBuf};
 use std::process;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use ckb_build_info::Version;
-use ckb_sdk::HttpRpcClient;
+use ckb_sdk::{
+    set_connection_config, stats, ChangePolicy, ConnectionConfig, DustPolicy, HttpRpcClient,
+    RetryPolicy,
+};
 use ckb_util::RwLock;
 use clap::crate_version;
-use clap::{App, AppSettings, Arg, SubCommand};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
 #[cfg(unix)]
 use subcommands::TuiSubCommand;
 
 use interactive::InteractiveEnv;
 use subcommands::{
-    start_index_thread, AccountSubCommand, CliSubCommand, IndexThreadState, MockTxSubCommand,
-    RpcSubCommand, UtilSubCommand, WalletSubCommand,
+    start_index_thread, AccountSubCommand, ChainSubCommand, CliSubCommand, FeeGuard,
+    IndexSubCommand, IndexSyncConfig, IndexThreadState, MockTxSubCommand, RpcSubCommand,
+    ServerSubCommand, TxSubCommand, UtilSubCommand, WalletSubCommand,
 };
 use utils::{
-    arg_parser::{ArgParser, UrlParser},
-    config::GlobalConfig,
-    other::{check_alerts, get_key_store},
-    printer::{ColorWhen, OutputFormat},
+    arg_parser::{ArgParser, CapacityParser, FilePathParser, FromStrParser, UrlParser},
+    config::{parse_node, ChangeStrategy, GlobalConfig},
+    i18n::{set_lang, Lang},
+    metrics::start_metrics_server,
+    other::{
+        check_alerts, get_ckb_cli_dir, get_key_store, get_network_type, print_timings,
+        read_password_file, set_password_file,
+    },
+    printer::{
+        extract_field, set_capacity_unit, structured_output, CapacityUnit, ColorWhen, OutputFormat,
+        Printable,
+    },
+    profile::{Profile, ProfileFile},
+    sign_history,
 };
 
 mod interactive;
@@ -42,19 +58,51 @@ fn main() -> Result<(), io::Error> {
     let version_short = version.short();
     let version_long = version.long();
     let matches = build_cli(&version_short, &version_long).get_matches();
+    let lang = matches
+        .value_of("lang")
+        .map(|value| Lang::from_str(value).unwrap())
+        .unwrap_or_else(Lang::from_env);
+    set_lang(lang);
 
     let mut env_map: HashMap<String, String> = HashMap::from_iter(env::vars());
     let api_uri_opt = matches
         .value_of("url")
         .map(ToOwned::to_owned)
+        .or_else(|| env_map.remove("CKB_CLI_URL"))
         .or_else(|| env_map.remove("API_URL"));
 
-    let mut ckb_cli_dir = dirs::home_dir().unwrap();
-    ckb_cli_dir.push(".ckb-cli");
+    let ckb_cli_dir = get_ckb_cli_dir();
     let mut resource_dir = ckb_cli_dir.clone();
     resource_dir.push("resource");
-    let mut index_dir = ckb_cli_dir.clone();
-    index_dir.push("index");
+    // Defaults of `~/.ckb-cli/config.toml`, the arguments take precedence
+    let (profile_name, profile) = match ProfileFile::load_default()
+        .and_then(|file| file.select(matches.value_of("profile")))
+    {
+        Ok(Some((name, profile))) => (Some(name), profile),
+        Ok(None) => (None, Profile::default()),
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+    };
+    let profile_network = profile.network_type().unwrap_or_else(|err| {
+        eprintln!(
+            "Invalid profile {}: {}",
+            profile_name.as_ref().unwrap(),
+            err
+        );
+        process::exit(1);
+    });
+    let index_dir = env_map
+        .remove("CKB_CLI_INDEX_DIR")
+        .map(PathBuf::from)
+        .or_else(|| profile.index_dir())
+        .unwrap_or_else(|| ckb_cli_dir.join("index"));
+    let keystore_dir = env_map
+        .remove("CKB_CLI_KEYSTORE")
+        .map(PathBuf::from)
+        .or_else(|| profile.keystore_dir())
+        .unwrap_or_else(|| ckb_cli_dir.join("keystore"));
     let index_state = Arc::new(RwLock::new(IndexThreadState::default()));
 
     let mut config = GlobalConfig::new(api_uri_opt.clone(), Arc::clone(&index_state));
@@ -62,48 +110,274 @@ fn main() -> Result<(), io::Error> {
     config_file.push("config");
 
     let mut output_format = OutputFormat::Yaml;
+    let mut ca_cert_file = None;
     if config_file.as_path().exists() {
         let mut file = fs::File::open(&config_file)?;
         let mut content = String::new();
         file.read_to_string(&mut content)?;
         let configs: serde_json::Value = serde_json::from_str(content.as_str()).unwrap();
+        if let Some(nodes) = configs["nodes"].as_object() {
+            config.set_nodes(
+                nodes
+                    .iter()
+                    .filter_map(|(name, url)| {
+                        url.as_str().map(|url| (name.clone(), url.to_owned()))
+                    })
+                    .collect(),
+            );
+        }
         if api_uri_opt.is_none() {
             if let Some(value) = configs["url"].as_str() {
                 config.set_url(value.to_string());
             }
+            if let Some(name) = configs["node"].as_str() {
+                if let Err(err) = config.select_node(name) {
+                    eprintln!("Invalid node in config: {}", err);
+                }
+            }
         }
         config.set_debug(configs["debug"].as_bool().unwrap_or(false));
+        config.set_timings(configs["timings"].as_bool().unwrap_or(false));
         config.set_color(ansi_support && configs["color"].as_bool().unwrap_or(true));
         output_format =
             OutputFormat::from_str(&configs["output_format"].as_str().unwrap_or("yaml"))
                 .unwrap_or(OutputFormat::Yaml);
         config.set_output_format(output_format);
+        if let Some(value) = configs["capacity_unit"].as_str() {
+            match CapacityUnit::from_str(value) {
+                Ok(unit) => set_capacity_unit(unit),
+                Err(err) => eprintln!("Invalid capacity_unit in config: {}", err),
+            }
+        }
+        // The audit log of signatures, enabled by default
+        sign_history::set_enabled(configs["sign_history"].as_bool().unwrap_or(true));
         config.set_completion_style(configs["completion_style"].as_bool().unwrap_or(true));
         config.set_edit_style(configs["edit_style"].as_bool().unwrap_or(true));
+        if let Some(history_size) = configs["history_size"].as_u64() {
+            config.set_history_size(history_size as usize);
+        }
+        if let Some(value) = configs["change_strategy"].as_str() {
+            match ChangeStrategy::from_str(value) {
+                Ok(change_strategy) => config.set_change_strategy(change_strategy),
+                Err(err) => eprintln!("Invalid change_strategy in config: {}", err),
+            }
+        }
+        ca_cert_file = configs["ca_cert"].as_str().map(ToOwned::to_owned);
+        let mut retry_policy = RetryPolicy::default();
+        if let Some(max_retries) = configs["rpc_retries"].as_u64() {
+            retry_policy.max_retries = max_retries as u32;
+        }
+        if let Some(backoff) = configs["rpc_backoff_ms"].as_u64() {
+            retry_policy.initial_backoff = Duration::from_millis(backoff);
+        }
+        if let Some(failover_urls) = configs["failover_urls"].as_object() {
+            retry_policy.failover_urls = failover_urls
+                .iter()
+                .filter_map(|(url, failover_url)| {
+                    failover_url
+                        .as_str()
+                        .map(|failover_url| (url.clone(), failover_url.to_owned()))
+                })
+                .collect();
+        }
+        config.set_retry_policy(retry_policy);
+        if let Some(indexer_url) = configs["indexer_url"].as_str() {
+            config.set_indexer_url(Some(indexer_url.to_owned()));
+        }
+        let default_policy = ChangePolicy::default();
+        let min_change_capacity = configs["min_change_capacity"]
+            .as_u64()
+            .unwrap_or_else(|| default_policy.min_change_capacity());
+        let dust_policy = match configs["dust_policy"].as_str().map(DustPolicy::from_str) {
+            Some(Ok(dust_policy)) => dust_policy,
+            Some(Err(err)) => {
+                eprintln!("Invalid dust_policy in config: {}", err);
+                default_policy.dust()
+            }
+            None => default_policy.dust(),
+        };
+        match ChangePolicy::new(min_change_capacity, dust_policy) {
+            Ok(change_policy) => config.set_change_policy(change_policy),
+            Err(err) => eprintln!("Invalid min_change_capacity in config: {}", err),
+        }
+        let default_guard = FeeGuard::default();
+        config.set_fee_guard(FeeGuard {
+            max_fee: configs["max_tx_fee"]
+                .as_u64()
+                .unwrap_or(default_guard.max_fee),
+            max_fee_percent: configs["max_fee_percent"]
+                .as_u64()
+                .unwrap_or(default_guard.max_fee_percent),
+        });
+    }
+
+    config.set_profile(profile_name.clone());
+    config.set_default_fee_rate(profile.fee_rate);
+    if api_uri_opt.is_none() {
+        if let Some(url) = profile.url.as_ref() {
+            config.set_url(url.clone());
+        }
+    }
+    match profile.output_format() {
+        Ok(Some(format)) => {
+            output_format = format;
+            config.set_output_format(format);
+        }
+        Ok(None) => {}
+        Err(err) => eprintln!("Invalid output_format in profile: {}", err),
+    }
+
+    sign_history::set_command(&env::args().skip(1).collect::<Vec<_>>().join(" "));
+
+    if let Some(unit) = matches.value_of("capacity-unit") {
+        set_capacity_unit(CapacityUnit::from_str(unit).unwrap());
+    }
+
+    if let Some(name) = matches.value_of("node") {
+        if let Err(err) = config.select_node(name) {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+    }
+
+    if let Some(password_file) = matches.value_of("password-file") {
+        set_password_file(PathBuf::from(password_file));
+    }
+    // Before any RPC client is created
+    let mut connection = ConnectionConfig::default();
+    if let Some(path) = matches
+        .value_of("ca-cert")
+        .map(ToOwned::to_owned)
+        .or(ca_cert_file)
+    {
+        if let Err(err) = fs::read(&path)
+            .map_err(|err| format!("Read CA certificate file {:?} failed: {}", path, err))
+            .and_then(|pem| connection.set_ca_cert(pem))
+        {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+    }
+    if let Some(path) = matches
+        .value_of("rpc-password-file")
+        .map(ToOwned::to_owned)
+        .or_else(|| env_map.remove("CKB_CLI_RPC_PASSWORD_FILE"))
+    {
+        match read_password_file(Path::new(&path)) {
+            Ok(password) => connection.basic_auth_password = Some(password),
+            Err(err) => {
+                eprintln!("{}", err);
+                process::exit(1);
+            }
+        }
+    }
+    set_connection_config(connection);
+    if let Some(indexer_url) = matches.value_of("indexer-url") {
+        config.set_indexer_url(Some(indexer_url.to_owned()));
+    }
+    if matches.is_present("rpc-retries") || matches.is_present("failover-url") {
+        let mut retry_policy = config.retry_policy().clone();
+        if let Some(max_retries) = matches.value_of("rpc-retries") {
+            retry_policy.max_retries = max_retries.parse::<u32>().unwrap();
+        }
+        if let Some(failover_url) = matches.value_of("failover-url") {
+            retry_policy
+                .failover_urls
+                .insert(config.get_url().to_owned(), failover_url.to_owned());
+        }
+        config.set_retry_policy(retry_policy);
     }
 
     let api_uri = config.get_url().to_string();
-    let index_controller = start_index_thread(api_uri.as_str(), index_dir.clone(), index_state);
+    let mut sync_config = IndexSyncConfig::default();
+    if let Some(batch_size) = matches.value_of("index-batch-size") {
+        sync_config.batch_size = batch_size.parse::<usize>().unwrap();
+    }
+    if let Some(concurrency) = matches.value_of("index-fetch-concurrency") {
+        sync_config.fetch_concurrency = concurrency.parse::<usize>().unwrap();
+    }
+    let index_controller = start_index_thread(
+        api_uri.as_str(),
+        index_dir.clone(),
+        index_state,
+        sync_config,
+    );
+    if let Some(port) = matches.value_of("metrics-port") {
+        let metrics = Arc::clone(index_controller.metrics());
+        let address = format!("127.0.0.1:{}", port);
+        if let Err(err) = start_metrics_server(&address, move || metrics.render()) {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+    }
     let mut rpc_client = HttpRpcClient::from_uri(api_uri.as_str());
     check_alerts(&mut rpc_client);
+    // Not to send transactions to a wrong chain by the profile, skipped if the node is down
+    if let (Some(network), true) = (profile_network, talks_to_node(&matches)) {
+        if let Ok(actual) = get_network_type(&mut rpc_client) {
+            if actual != network {
+                eprintln!(
+                    "The node {} is {}, but the network of profile {} is {}",
+                    api_uri,
+                    actual,
+                    profile_name.as_ref().unwrap(),
+                    network
+                );
+                process::exit(1);
+            }
+        }
+    }
 
-    let color = ColorWhen::new(!matches.is_present("no-color")).color();
+    let print_color = ColorWhen::new(!matches.is_present("no-color")).color();
     let debug = matches.is_present("debug");
-    if let Some(format) = matches.value_of("output-format") {
-        output_format = OutputFormat::from_str(format).unwrap();
+    let timings = matches.is_present("timings");
+    // Exclude the alert checking above
+    stats::take();
+    let start_time = Instant::now();
+    // The default value not to override the config and profile
+    if matches.occurrences_of("output-format") > 0 {
+        output_format = OutputFormat::from_str(matches.value_of("output-format").unwrap()).unwrap();
     }
+    // Pick the field from the JSON output
+    let only_field = matches.value_of("only-field");
+    if only_field.is_some() {
+        output_format = OutputFormat::Json;
+    }
+    // Opt-in, so the scripts parsing the plain JSON output keep working
+    let structured = matches.is_present("structured-output") && output_format == OutputFormat::Json;
+    // The JSON output is parsed again to pick a field or to be wrapped as a structured document,
+    // and CSV is usually piped into other programs
+    let color =
+        print_color && !structured && only_field.is_none() && output_format != OutputFormat::Csv;
     let result = match matches.subcommand() {
         #[cfg(unix)]
-        ("tui", _) => TuiSubCommand::new(
-            api_uri.to_string(),
-            index_dir.clone(),
-            index_controller.clone(),
-        )
-        .start(),
+        ("tui", Some(sub_matches)) => utils::arg_parser::AddressParser
+            .from_matches_opt(sub_matches, "address", false)
+            .and_then(|address| {
+                // Listed in the accounts panel, the same order as `account list`
+                let mut accounts = get_key_store(&keystore_dir)
+                    .map(|mut key_store| {
+                        key_store
+                            .get_accounts()
+                            .iter()
+                            .map(|(lock_arg, filepath)| (filepath.clone(), lock_arg.clone()))
+                            .collect::<Vec<_>>()
+                    })
+                    .unwrap_or_default();
+                accounts.sort_by(|a, b| a.0.cmp(&b.0));
+                TuiSubCommand::new(
+                    api_uri.to_string(),
+                    index_dir.clone(),
+                    index_controller.clone(),
+                    address,
+                )
+                .with_accounts(accounts.into_iter().map(|(_, lock_arg)| lock_arg).collect())
+                .start()
+            }),
         ("rpc", Some(sub_matches)) => {
             RpcSubCommand::new(&mut rpc_client).process(&sub_matches, output_format, color, debug)
         }
-        ("account", Some(sub_matches)) => get_key_store(&ckb_cli_dir).and_then(|mut key_store| {
+        ("account", Some(sub_matches)) => get_key_store(&keystore_dir).and_then(|mut key_store| {
             AccountSubCommand::new(&mut rpc_client, &mut key_store, None).process(
                 &sub_matches,
                 output_format,
@@ -111,36 +385,97 @@ fn main() -> Result<(), io::Error> {
                 debug,
             )
         }),
-        ("mock-tx", Some(sub_matches)) => get_key_store(&ckb_cli_dir).and_then(|mut key_store| {
-            MockTxSubCommand::new(&mut rpc_client, &mut key_store, None).process(
-                &sub_matches,
-                output_format,
-                color,
-                debug,
-            )
+        ("mock-tx", Some(sub_matches)) => get_key_store(&keystore_dir).and_then(|mut key_store| {
+            MockTxSubCommand::new(&mut rpc_client, &mut key_store, None)
+                .with_fee_guard(config.fee_guard())
+                .process(&sub_matches, output_format, color, debug)
         }),
-        ("util", Some(sub_matches)) => UtilSubCommand::new(&mut rpc_client, None).process(
+        ("tx", Some(sub_matches)) => get_key_store(&keystore_dir).and_then(|mut key_store| {
+            TxSubCommand::new(&mut rpc_client, &mut key_store, None)
+                .with_fee_guard(config.fee_guard())
+                .process(&sub_matches, output_format, color, debug)
+        }),
+        ("chain", Some(sub_matches)) => ChainSubCommand::new(&mut rpc_client, None).process(
             &sub_matches,
             output_format,
             color,
             debug,
         ),
-        ("wallet", Some(sub_matches)) => get_key_store(&ckb_cli_dir).and_then(|mut key_store| {
+        ("util", Some(sub_matches)) => get_key_store(&keystore_dir).and_then(|mut key_store| {
+            UtilSubCommand::new(&mut rpc_client, &mut key_store, None).process(
+                &sub_matches,
+                output_format,
+                color,
+                debug,
+            )
+        }),
+        ("index", Some(sub_matches)) => IndexSubCommand::new(
+            &mut rpc_client,
+            None,
+            index_dir.clone(),
+            index_controller.clone(),
+        )
+        .process(&sub_matches, output_format, color, debug),
+        ("wallet", Some(sub_matches)) => get_key_store(&keystore_dir).and_then(|mut key_store| {
+            // ckb-indexer needs no local sync
+            let sync_first = matches.is_present("sync-first") && config.indexer_url().is_none();
+            if sync_first {
+                let timeout: u64 =
+                    FromStrParser::<u64>::default().from_matches(&matches, "sync-timeout")?;
+                index_controller.sync_to_tip(api_uri.as_str(), Duration::from_secs(timeout))?;
+            }
             WalletSubCommand::new(
                 &mut rpc_client,
                 &mut key_store,
                 None,
                 index_dir.clone(),
                 index_controller.clone(),
+                config.change_strategy().clone(),
                 false,
             )
+            .with_change_policy(config.change_policy())
+            .with_indexer_url(config.indexer_url())
+            .with_synced_index(sync_first)
+            .with_default_fee_rate(config.default_fee_rate())
+            .with_fee_guard(config.fee_guard())
             .process(&sub_matches, output_format, color, debug)
         }),
+        ("server", Some(sub_matches)) => get_key_store(&keystore_dir).and_then(|mut key_store| {
+            ServerSubCommand::new(
+                &mut rpc_client,
+                &mut key_store,
+                api_uri.clone(),
+                index_dir.clone(),
+                index_controller.clone(),
+                config.change_strategy().clone(),
+                config.change_policy(),
+                config.fee_guard(),
+                config.indexer_url().map(ToOwned::to_owned),
+            )
+            .start(&sub_matches)
+        }),
         _ => {
-            if let Err(err) =
-                InteractiveEnv::from_config(ckb_cli_dir, config, index_controller.clone())
-                    .and_then(|mut env| env.start())
-            {
+            let batch_file = matches.value_of("batch-file");
+            let sync_first = matches.is_present("sync-first") && config.indexer_url().is_none();
+            let controller = index_controller.clone();
+            if let Err(err) = InteractiveEnv::from_config(
+                ckb_cli_dir,
+                index_dir,
+                keystore_dir,
+                config,
+                index_controller.clone(),
+            )
+            .and_then(|mut env| match batch_file {
+                Some(path) => {
+                    if sync_first {
+                        let timeout: u64 = FromStrParser::<u64>::default()
+                            .from_matches(&matches, "sync-timeout")?;
+                        controller.sync_to_tip(api_uri.as_str(), Duration::from_secs(timeout))?;
+                    }
+                    env.run_batch(Path::new(path))
+                }
+                None => env.start(),
+            }) {
                 eprintln!("Process error: {}", err);
                 index_controller.shutdown();
                 process::exit(1);
@@ -150,9 +485,30 @@ fn main() -> Result<(), io::Error> {
         }
     };
 
+    if timings {
+        print_timings(start_time.elapsed());
+    }
     match result {
         Ok(message) => {
-            println!("{}", message);
+            if let Some(path) = only_field {
+                let value = serde_json::from_str(&message)
+                    .unwrap_or_else(|_| serde_json::Value::String(message.trim_end().to_owned()));
+                match extract_field(&value, path) {
+                    Ok(field) => println!("{}", field),
+                    Err(err) => {
+                        eprintln!("{}", err);
+                        index_controller.shutdown();
+                        process::exit(1);
+                    }
+                }
+            } else if structured {
+                println!(
+                    "{}",
+                    structured_output(&message).render(output_format, print_color)
+                );
+            } else {
+                println!("{}", message);
+            }
             index_controller.shutdown();
         }
         Err(err) => {
@@ -198,6 +554,15 @@ fn get_version() -> Version {
     }
 }
 
+/// The commands working with the node (interactive mode included), `account` and `util` are
+/// the offline ones (the node is only asked for optional information like the genesis block)
+fn talks_to_node(matches: &ArgMatches) -> bool {
+    match matches.subcommand_name() {
+        Some("account") | Some("util") => false,
+        _ => true,
+    }
+}
+
 pub fn build_cli<'a>(version_short: &'a str, version_long: &'a str) -> App<'a, 'a> {
     let app = App::new("ckb-cli")
         .version(version_short)
@@ -207,24 +572,167 @@ pub fn build_cli<'a>(version_short: &'a str, version_long: &'a str) -> App<'a, '
         .subcommand(RpcSubCommand::subcommand())
         .subcommand(AccountSubCommand::subcommand("account"))
         .subcommand(MockTxSubCommand::subcommand("mock-tx"))
+        .subcommand(TxSubCommand::subcommand("tx"))
         .subcommand(UtilSubCommand::subcommand("util"))
         .subcommand(WalletSubCommand::subcommand())
+        .subcommand(ChainSubCommand::subcommand("chain"))
+        .subcommand(IndexSubCommand::subcommand("index"))
+        .subcommand(ServerSubCommand::subcommand("server"))
         .arg(
             Arg::with_name("url")
                 .long("url")
                 .takes_value(true)
                 .validator(|input| UrlParser.validate(input))
-                .help("RPC API server url"),
+                .help("RPC API server url, https and basic auth are supported: https://user@host:port (the password from --rpc-password-file)"),
+        )
+        .arg(
+            Arg::with_name("node")
+                .long("node")
+                .takes_value(true)
+                .conflicts_with("url")
+                .help("Use the RPC url of a named node in config (see: `config --add-node` in interactive mode)"),
+        )
+        .arg(
+            Arg::with_name("ca-cert")
+                .long("ca-cert")
+                .takes_value(true)
+                .validator(|input| FilePathParser::new(true).validate(input))
+                .help("Trusted CA certificates file (PEM) to validate the https RPC url, default is the system trust store (also config: ca_cert)"),
+        )
+        .arg(
+            Arg::with_name("rpc-password-file")
+                .long("rpc-password-file")
+                .takes_value(true)
+                .validator(|input| FilePathParser::new(true).validate(input))
+                .help("Read the basic auth password of the RPC urls from the first line of this file (not accessible by other users), used when the url has a user but no password (env: CKB_CLI_RPC_PASSWORD_FILE)"),
+        )
+        .arg(
+            Arg::with_name("rpc-retries")
+                .long("rpc-retries")
+                .takes_value(true)
+                .validator(|input| FromStrParser::<u32>::default().validate(input))
+                .help("Retry times of a failed RPC call (with exponential backoff), default from config or 0, send_transaction is never retried"),
+        )
+        .arg(
+            Arg::with_name("failover-url")
+                .long("failover-url")
+                .takes_value(true)
+                .validator(|input| UrlParser.validate(input))
+                .help("Secondary RPC url, tried when the call to RPC API server url failed (except send_transaction)"),
+        )
+        .arg(
+            Arg::with_name("indexer-url")
+                .long("indexer-url")
+                .takes_value(true)
+                .validator(|input| UrlParser.validate(input))
+                .help("Search the live cells (wallet commands) by this running ckb-indexer instead of the local index database, so no local sync is needed (also config: indexer_url)"),
+        )
+        .arg(
+            Arg::with_name("sync-first")
+                .long("sync-first")
+                .alias("local-only")
+                .help("Sync the local index database to the tip of the node before running the wallet command (or --batch-file), so the index queries work outside interactive mode"),
+        )
+        .arg(
+            Arg::with_name("sync-timeout")
+                .long("sync-timeout")
+                .takes_value(true)
+                .default_value("600")
+                .validator(|input| FromStrParser::<u64>::default().validate(input))
+                .help("Give up waiting for the index sync of --sync-first after this many seconds"),
+        )
+        .arg(
+            Arg::with_name("batch-file")
+                .long("batch-file")
+                .takes_value(true)
+                .validator(|input| FilePathParser::new(true).validate(input))
+                .help("Run the interactive mode commands of this file line by line (stop on first error), `${LAST_TX_HASH}` is the transaction sent by the previous command"),
+        )
+        .arg(
+            Arg::with_name("metrics-port")
+                .long("metrics-port")
+                .takes_value(true)
+                .validator(|input| FromStrParser::<u16>::default().validate(input))
+                .help("Serve the index thread metrics (Prometheus text format) at http://127.0.0.1:<port>/metrics"),
+        )
+        .arg(
+            Arg::with_name("index-batch-size")
+                .long("index-batch-size")
+                .takes_value(true)
+                .validator(|input| {
+                    FromStrParser::<usize>::default()
+                        .parse(&input)
+                        .and_then(|size| {
+                            if size == 0 {
+                                Err("index batch size must be greater than 0".to_owned())
+                            } else {
+                                Ok(())
+                            }
+                        })
+                })
+                .help("Blocks written to the index database in one batch (and prefetched from the node) during initial sync [default: 200]"),
+        )
+        .arg(
+            Arg::with_name("index-fetch-concurrency")
+                .long("index-fetch-concurrency")
+                .takes_value(true)
+                .validator(|input| {
+                    FromStrParser::<usize>::default()
+                        .parse(&input)
+                        .and_then(|concurrency| {
+                            if concurrency == 0 {
+                                Err("index fetch concurrency must be greater than 0".to_owned())
+                            } else {
+                                Ok(())
+                            }
+                        })
+                })
+                .help("Concurrent RPC requests fetching blocks for the index database (applied in order) [default: 4]"),
         )
         .arg(
             Arg::with_name("output-format")
                 .long("output-format")
                 .takes_value(true)
-                .possible_values(&["yaml", "json"])
+                .possible_values(&["yaml", "json", "csv", "table"])
                 .default_value("yaml")
                 .global(true)
                 .help("Select output format"),
         )
+        .arg(
+            Arg::with_name("structured-output")
+                .long("structured-output")
+                .global(true)
+                .help("Print the json output as one versioned document: {\"schema_version\": <version>, \"result\": <output>} (plain text outputs like a hash are strings)"),
+        )
+        .arg(
+            Arg::with_name("only-field")
+                .long("only-field")
+                .takes_value(true)
+                .global(true)
+                .help("Only print this field of the output, the path is separated by `.` (like: live_cells.0.capacity, `.` for the whole output), strings are printed without quotes"),
+        )
+        .arg(
+            Arg::with_name("profile")
+                .long("profile")
+                .takes_value(true)
+                .global(true)
+                .help("Select the profile of ~/.ckb-cli/config.toml (url, network, output format, default fee rate, keystore and index directory), the arguments take precedence"),
+        )
+        .arg(
+            Arg::with_name("password-file")
+                .long("password-file")
+                .takes_value(true)
+                .global(true)
+                .help("Read the keystore password from the first line of this file (not accessible by other users) instead of prompting, `-` for stdin and /dev/fd/<n> for a file descriptor (env: CKB_CLI_PASSWORD_FILE)"),
+        )
+        .arg(
+            Arg::with_name("capacity-unit")
+                .long("capacity-unit")
+                .takes_value(true)
+                .possible_values(&["shannon", "ckb", "auto"])
+                .global(true)
+                .help("Unit of the printed capacities: shannon (default, raw number), ckb (like 1,250.00000000), auto (like 1,250.5 CKB or 100 shannons)"),
+        )
         .arg(
             Arg::with_name("no-color")
                 .long("no-color")
@@ -236,10 +744,28 @@ pub fn build_cli<'a>(version_short: &'a str, version_long: &'a str) -> App<'a, '
                 .long("debug")
                 .global(true)
                 .help("Display request parameters"),
+        )
+        .arg(
+            Arg::with_name("lang")
+                .long("lang")
+                .takes_value(true)
+                .global(true)
+                .validator(|input| Lang::from_str(&input).map(|_| ()))
+                .help("Language of the messages: en, zh-CN (default from CKB_CLI_LANG or LANG env)"),
+        )
+        .arg(
+            Arg::with_name("timings")
+                .long("timings")
+                .global(true)
+                .help("Report wall time, RPC calls by method, index queries and cache hit rates of the command (to stderr)"),
         );
 
     #[cfg(unix)]
-    let app = app.subcommand(SubCommand::with_name("tui").about("Enter TUI mode"));
+    let app = app.subcommand(
+        SubCommand::with_name("tui").about("Enter TUI mode").arg(
+            utils::arg::address().help("Show the NervosDAO cells (deposited, prepared, interest, unlock countdown) of this address"),
+        ),
+    );
 
     app
 }
@@ -261,6 +787,49 @@ pub fn build_interactive() -> App<'static, 'static> {
                         .takes_value(true)
                         .help("Config RPC API url"),
                 )
+                .arg(
+                    Arg::with_name("node")
+                        .long("node")
+                        .takes_value(true)
+                        .conflicts_with("url")
+                        .help("Switch to a named node (the url of the node is used)"),
+                )
+                .arg(
+                    Arg::with_name("add-node")
+                        .long("add-node")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .validator(|input| parse_node(&input).map(|_| ()))
+                        .help("Add (or replace) a named node, format: <name>=<url>, example: testnet=http://127.0.0.1:8114"),
+                )
+                .arg(
+                    Arg::with_name("remove-node")
+                        .long("remove-node")
+                        .takes_value(true)
+                        .help("Remove a named node"),
+                )
+                .arg(
+                    Arg::with_name("rpc-retries")
+                        .long("rpc-retries")
+                        .takes_value(true)
+                        .validator(|input| FromStrParser::<u32>::default().validate(input))
+                        .help("Retry times of a failed RPC call (with exponential backoff), send_transaction is never retried"),
+                )
+                .arg(
+                    Arg::with_name("failover-url")
+                        .long("failover-url")
+                        .takes_value(true)
+                        .validator(|input| if input.is_empty() { Ok(()) } else { UrlParser.validate(input) })
+                        .help("Secondary RPC url of current url, tried when the call to current url failed, except send_transaction (empty to remove)"),
+                )
+                .arg(
+                    Arg::with_name("indexer-url")
+                        .long("indexer-url")
+                        .takes_value(true)
+                        .validator(|input| if input.is_empty() { Ok(()) } else { UrlParser.validate(input) })
+                        .help("Search the live cells by this running ckb-indexer instead of the local index database (empty to use the local index database)"),
+                )
                 .arg(
                     Arg::with_name("color")
                         .long("color")
@@ -271,11 +840,16 @@ pub fn build_interactive() -> App<'static, 'static> {
                         .long("debug")
                         .help("Switch debug mode"),
                 )
+                .arg(
+                    Arg::with_name("timings")
+                        .long("timings")
+                        .help("Switch timings report (wall time, RPC calls, index queries) of each command"),
+                )
                 .arg(
                     Arg::with_name("output-format")
                         .long("output-format")
                         .takes_value(true)
-                        .possible_values(&["yaml", "json"])
+                        .possible_values(&["yaml", "json", "csv", "table"])
                         .default_value("yaml")
                         .help("Select output format"),
                 )
@@ -288,9 +862,80 @@ pub fn build_interactive() -> App<'static, 'static> {
                     Arg::with_name("edit_style")
                         .long("edit_style")
                         .help("Switch edit style"),
+                )
+                .arg(
+                    Arg::with_name("history-size")
+                        .long("history-size")
+                        .takes_value(true)
+                        .validator(|input| FromStrParser::<usize>::default().validate(input))
+                        .help("Max number of commands kept in the history file (~/.ckb-cli/history)"),
+                )
+                .arg(
+                    Arg::with_name("change-strategy")
+                        .long("change-strategy")
+                        .takes_value(true)
+                        .validator(|input| ChangeStrategy::from_str(&input).map(|_| ()))
+                        .help("Where the change output goes: reuse (sender address), derive (new BIP-44 change address of the account) or fixed:<address>"),
+                )
+                .arg(
+                    Arg::with_name("min-change-capacity")
+                        .long("min-change-capacity")
+                        .takes_value(true)
+                        .validator(|input| CapacityParser.validate(input))
+                        .help("Minimal capacity of the change output (unit: CKB, format: 61.5), the smaller change is handled by --dust-policy"),
+                )
+                .arg(
+                    Arg::with_name("dust-policy")
+                        .long("dust-policy")
+                        .takes_value(true)
+                        .possible_values(&["fee", "receiver", "fail"])
+                        .help("What to do with the change less than minimal change capacity: fee (donate to transaction fee), receiver (add to the receiver output) or fail"),
+                )
+                .arg(
+                    Arg::with_name("max-tx-fee")
+                        .long("max-tx-fee")
+                        .takes_value(true)
+                        .validator(|input| CapacityParser.validate(input))
+                        .help("Refuse to send the transactions paying more fee (unit: CKB, format: 0.5) unless --force-fee"),
+                )
+                .arg(
+                    Arg::with_name("max-fee-percent")
+                        .long("max-fee-percent")
+                        .takes_value(true)
+                        .validator(|input| FromStrParser::<u64>::default().validate(input))
+                        .help("Refuse to send the transactions paying more fee than this percentage of the transferred capacity unless --force-fee"),
                 ),
         )
         .subcommand(SubCommand::with_name("info").about("Display global variables"))
+        .subcommand(
+            SubCommand::with_name("source")
+                .about("Run the commands of a file line by line, stop on first error")
+                .arg(
+                    Arg::with_name("file")
+                        .required(true)
+                        .index(1)
+                        .validator(|input| FilePathParser::new(true).validate(input))
+                        .help("Commands file, empty lines and lines starting with # are skipped"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("history")
+                .about("List (or search) the command history, Ctrl-R searches it while editing")
+                .arg(
+                    Arg::with_name("search")
+                        .long("search")
+                        .takes_value(true)
+                        .help("Only list the commands containing this text"),
+                )
+                .arg(
+                    Arg::with_name("limit")
+                        .long("limit")
+                        .takes_value(true)
+                        .validator(|input| FromStrParser::<usize>::default().validate(input))
+                        .default_value("20")
+                        .help("List the last <limit> (matched) commands"),
+                ),
+        )
         .subcommand(
             SubCommand::with_name("exit")
                 .visible_alias("quit")
@@ -299,6 +944,9 @@ pub fn build_interactive() -> App<'static, 'static> {
         .subcommand(RpcSubCommand::subcommand())
         .subcommand(AccountSubCommand::subcommand("account"))
         .subcommand(MockTxSubCommand::subcommand("mock-tx"))
+        .subcommand(TxSubCommand::subcommand("tx"))
         .subcommand(UtilSubCommand::subcommand("util"))
         .subcommand(WalletSubCommand::subcommand())
+        .subcommand(ChainSubCommand::subcommand("chain"))
+        .subcommand(IndexSubCommand::subcommand("index"))
 }
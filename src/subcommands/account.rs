@@ -1,24 +1,44 @@
 use std::fs;
 use std::io::Write;
 use std::path::{Path, PathBuf};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
+use ckb_hash::blake2b_256;
 use ckb_jsonrpc_types::BlockNumber;
 use ckb_sdk::{
-    wallet::{DerivationPath, Key, KeyStore, MasterPrivKey},
-    Address, GenesisInfo, HttpRpcClient, NetworkType,
+    blake2b_args, serialize_signature, sign_secp_group,
+    stats::record_cache,
+    wallet::{
+        bip44_path, ChildNumber, DerivationPath, ExtendedPubKey, Key, KeyStore, KeyStoreError,
+        MasterPrivKey, CKB_ACCOUNT_PATH,
+    },
+    Address, GenesisInfo, HttpRpcClient, NetworkType, SECP256K1,
+};
+use ckb_types::{
+    core::{BlockView, TransactionView},
+    packed::{CellOutput, Script},
+    prelude::*,
+    H160, H256,
 };
-use ckb_types::{core::BlockView, prelude::*, H160, H256};
 use clap::{App, Arg, ArgMatches, SubCommand};
+use faster_hex::hex_string;
 
 use super::CliSubCommand;
 use crate::utils::{
+    address_book::{annotate_labels, AddressBook},
+    arg,
     arg_parser::{
-        ArgParser, DurationParser, ExtendedPrivkeyPathParser, FixedHashParser, FromStrParser,
-        PrivkeyPathParser, PrivkeyWrapper,
+        ArgParser, DurationParser, ExtendedPrivkeyPathParser, FilePathParser, FixedHashParser,
+        FromStrParser, PrivkeyPathParser, PrivkeyWrapper, PubkeyHexParser,
     },
-    other::read_password,
+    multisig_configs::MultisigConfigs,
+    other::{
+        check_lock_activity, create_private_file, get_ckb_cli_dir, read_password, read_tx_file,
+    },
+    policy::{check_policy, transaction_outflow, SignKind, SignRequest, SigningPolicy},
     printer::{OutputFormat, Printable},
+    sign_history::{sign_witness, witness_with_key, SignHistory},
+    watch_only::{WatchOnlyAccount, WatchOnlyAccounts},
 };
 
 pub struct AccountSubCommand<'a> {
@@ -41,6 +61,7 @@ impl<'a> AccountSubCommand<'a> {
     }
 
     fn genesis_info(&mut self) -> Result<GenesisInfo, String> {
+        record_cache("genesis_info", self.genesis_info.is_some());
         if self.genesis_info.is_none() {
             let genesis_block: BlockView = self
                 .rpc_client
@@ -69,6 +90,11 @@ impl<'a> AccountSubCommand<'a> {
             .long("extended-privkey-path")
             .takes_value(true)
             .help("Extended private key path (include master private key and chain code)");
+        let arg_label_name = Arg::with_name("name")
+            .long("name")
+            .takes_value(true)
+            .required(true)
+            .help("The label name");
         SubCommand::with_name(name)
             .about("Manage accounts")
             .subcommands(vec![
@@ -88,18 +114,102 @@ impl<'a> AccountSubCommand<'a> {
                          .required_unless("privkey-path")
                          .validator(|input| ExtendedPrivkeyPathParser.validate(input))
                     ),
+                SubCommand::with_name("import-watch-only")
+                    .about("Import a watch-only account (no private key) by lock-arg or public key, transactions from it are built but not signed")
+                    .arg(arg_lock_arg.clone().required_unless("pubkey"))
+                    .arg(arg::pubkey().required_unless("lock-arg")),
+                SubCommand::with_name("remove-watch-only")
+                    .about("Remove a watch-only account")
+                    .arg(arg_lock_arg.clone()),
+                SubCommand::with_name("label")
+                    .about("Set a label of a lock-arg in address book, the label can be used as --to-address or --from-account")
+                    .arg(arg_lock_arg.clone())
+                    .arg(arg_label_name.clone()),
+                SubCommand::with_name("unlabel")
+                    .about("Remove a label from address book")
+                    .arg(arg_label_name.clone()),
+                SubCommand::with_name("labels").about("List all labels in address book"),
                 SubCommand::with_name("unlock")
-                    .about("Unlock an account")
+                    .about("Unlock an account for a while (interactive mode), the signing commands will not ask for the password")
                     .arg(arg_lock_arg.clone())
                     .arg(
                         Arg::with_name("keep")
                             .long("keep")
+                            .visible_alias("duration")
                             .takes_value(true)
                             .validator(|input| DurationParser.validate(input))
                             .required(true)
-                            .help("How long before the key expired, format: 30s, 15m, 1h (repeat unlock will increase the time)")
+                            .help("How long before the key expired (then it is locked and erased from memory), format: 30s, 15m, 1h (repeat unlock will increase the time)")
+                    ),
+                SubCommand::with_name("lock")
+                    .about("Lock the unlocked account now, the key is erased from memory")
+                    .arg(arg_lock_arg.clone().required(false).required_unless("all"))
+                    .arg(
+                        Arg::with_name("all")
+                            .long("all")
+                            .conflicts_with("lock-arg")
+                            .help("Lock all the unlocked accounts")
+                    ),
+                SubCommand::with_name("sign-tx")
+                    .about("Sign the inputs (locked by secp256k1 sighash-all lock) of an account or private key in a transaction file")
+                    .arg(
+                        Arg::with_name("tx-file")
+                            .long("tx-file")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FilePathParser::new(true).validate(input))
+                            .help("Transaction file (json format, the `transaction` field is used if present, the lock scripts of inputs are given by the optional `input_locks` field or fetched from node)"),
+                    )
+                    .arg(arg_lock_arg.clone().required_unless("privkey-path"))
+                    .arg(
+                        arg_privkey_path
+                            .clone()
+                            .conflicts_with("lock-arg")
+                            .validator(|input| PrivkeyPathParser.validate(input))
+                            .help("Sign with the private key file instead of an account"),
+                    )
+                    .arg(
+                        Arg::with_name("output-file")
+                            .long("output-file")
+                            .takes_value(true)
+                            .validator(|input| FilePathParser::new(false).validate(input))
+                            .help("Write the signed transaction (json format) to this file, print it when absent"),
+                    )
+                    .arg(arg::override_policy()),
+                SubCommand::with_name("bench-sign")
+                    .about("Measure the signing throughput (secp256k1 recoverable signatures), with the key of an account (decrypted once) or a throwaway key")
+                    .arg(
+                        arg_lock_arg
+                            .clone()
+                            .required(false)
+                            .help("Sign with this account (the password is asked), a throwaway key is used when absent")
+                    )
+                    .arg(
+                        Arg::with_name("count")
+                            .long("count")
+                            .takes_value(true)
+                            .default_value("1000")
+                            .validator(|input| FromStrParser::<usize>::default().validate(input))
+                            .help("Number of messages to sign")
                     ),
-                SubCommand::with_name("update")
+                SubCommand::with_name("sign-history")
+                    .about("Show the signatures produced by ckb-cli (the audit log in ~/.ckb-cli/sign-history), the newest first")
+                    .arg(
+                        arg_lock_arg
+                            .clone()
+                            .required(false)
+                            .help("Only show the signatures of this account")
+                    )
+                    .arg(
+                        Arg::with_name("limit")
+                            .long("limit")
+                            .takes_value(true)
+                            .default_value("20")
+                            .validator(|input| FromStrParser::<usize>::default().validate(input))
+                            .help("Max number of records to show")
+                    ),
+                SubCommand::with_name("update-password")
+                    .alias("update")
                     .about("Update password of an account")
                     .arg(arg_lock_arg.clone()),
                 SubCommand::with_name("export")
@@ -111,6 +221,60 @@ impl<'a> AccountSubCommand<'a> {
                             .required(true)
                             .help("Output extended private key path (PrivKey + ChainCode)")
                     ),
+                SubCommand::with_name("export-privkey")
+                    .about("Export the secp256k1 private key as hex plain text, can be used by --privkey-path (USE WITH YOUR OWN RISK)")
+                    .arg(arg_lock_arg.clone())
+                    .arg(
+                        Arg::with_name("output-file")
+                            .long("output-file")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FilePathParser::new(false).validate(input))
+                            .help("Output private key file path")
+                    ),
+                SubCommand::with_name("backup")
+                    .about("Backup all accounts into one passphrase encrypted archive file")
+                    .arg(
+                        Arg::with_name("output-file")
+                            .long("output-file")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FilePathParser::new(false).validate(input))
+                            .help("Output backup archive file path")
+                    ),
+                SubCommand::with_name("restore")
+                    .about("Restore accounts from a backup archive (existing accounts are not overwritten)")
+                    .arg(
+                        Arg::with_name("backup-path")
+                            .long("backup-path")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FilePathParser::new(true).validate(input))
+                            .help("The backup archive file path")
+                    ),
+                SubCommand::with_name("import-mnemonic")
+                    .about("Import an account from BIP-39 mnemonic words (English wordlist), the key of m/44'/309'/0'/0/0 (first receiving address of the BIP-44 account)")
+                    .arg(
+                        Arg::with_name("mnemonic-path")
+                            .long("mnemonic-path")
+                            .takes_value(true)
+                            .validator(|input| FilePathParser::new(true).validate(input))
+                            .help("File contains the mnemonic words (will prompt input when absent)")
+                    ),
+                SubCommand::with_name("export-mnemonic")
+                    .about("Export mnemonic words of an account imported by mnemonic (USE WITH YOUR OWN RISK)")
+                    .arg(arg_lock_arg.clone()),
+                SubCommand::with_name("derive")
+                    .about("Derive a child key by BIP-32 path and import it as a new account (usable by --from-account)")
+                    .arg(arg_lock_arg.clone())
+                    .arg(
+                        Arg::with_name("path")
+                            .long("path")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FromStrParser::<DerivationPath>::new().validate(input))
+                            .help("The derivation path (from the root key of the mnemonic for an account imported by mnemonic), example: m/44'/309'/0'/0/1")
+                    ),
                 SubCommand::with_name("extended-address")
                     .about("Extended address (see: BIP-44)")
                     .arg(arg_lock_arg.clone())
@@ -119,12 +283,237 @@ impl<'a> AccountSubCommand<'a> {
                             .long("path")
                             .takes_value(true)
                             .validator(|input| FromStrParser::<DerivationPath>::new().validate(input))
-                            .help("The address path")
+                            .help("The address path (when absent, list the BIP-44 receiving and change addresses)")
+                    )
+                    .arg(
+                        Arg::with_name("receiving-count")
+                            .long("receiving-count")
+                            .takes_value(true)
+                            .default_value("5")
+                            .validator(|input| FromStrParser::<u32>::default().validate(input))
+                            .help("Number of receiving addresses (m/44'/309'/0'/0/i) to list")
+                    )
+                    .arg(
+                        Arg::with_name("change-count")
+                            .long("change-count")
+                            .takes_value(true)
+                            .default_value("5")
+                            .validator(|input| FromStrParser::<u32>::default().validate(input))
+                            .help("Number of change addresses (m/44'/309'/0'/1/i) to list")
                     ),
             ])
     }
 }
 
+impl<'a> AccountSubCommand<'a> {
+    // The signatures are not recorded in the sign history, the messages are not transactions
+    fn bench_sign(&mut self, m: &ArgMatches) -> Result<serde_json::Value, String> {
+        let lock_arg: Option<H160> =
+            FixedHashParser::<H160>::default().from_matches_opt(m, "lock-arg", false)?;
+        let count: usize = FromStrParser::<usize>::default().from_matches(m, "count")?;
+        let messages = (0..count as u64)
+            .map(|idx| H256::from(blake2b_256(idx.to_le_bytes())))
+            .collect::<Vec<_>>();
+
+        let (unlock_time, sign_time) = if let Some(lock_arg) = lock_arg.as_ref() {
+            let password = read_password(false, None)?;
+            let start = Instant::now();
+            self.key_store
+                .sign_recoverable_batch_with_password(lock_arg, &[], password.as_bytes())
+                .map_err(|err| err.to_string())?;
+            let unlock_time = start.elapsed();
+            let start = Instant::now();
+            self.key_store
+                .sign_recoverable_batch_with_password(lock_arg, &messages, password.as_bytes())
+                .map_err(|err| err.to_string())?;
+            (
+                Some(unlock_time),
+                start.elapsed().checked_sub(unlock_time).unwrap_or_default(),
+            )
+        } else {
+            let privkey = secp256k1::SecretKey::from_slice(&blake2b_256(b"ckb-cli bench-sign"))
+                .expect("Generate throwaway private key failed");
+            let start = Instant::now();
+            for message in &messages {
+                let message = secp256k1::Message::from_slice(message.as_bytes())
+                    .expect("Convert to secp256k1 message failed");
+                SECP256K1.sign_recoverable(&message, &privkey);
+            }
+            (None, start.elapsed())
+        };
+        let sign_micros = sign_time.as_micros().max(1);
+        Ok(serde_json::json!({
+            "count": count,
+            "unlock_ms": unlock_time.map(|time| time.as_millis() as u64),
+            "sign_ms": sign_time.as_millis() as u64,
+            "signatures_per_second": (count as u128 * 1_000_000 / sign_micros) as u64,
+        }))
+    }
+
+    fn sign_tx(
+        &mut self,
+        m: &ArgMatches,
+        format: OutputFormat,
+        color: bool,
+    ) -> Result<String, String> {
+        let (transaction, value) = read_tx_file(m.value_of("tx-file").unwrap())?;
+        let privkey: Option<PrivkeyWrapper> =
+            PrivkeyPathParser.from_matches_opt(m, "privkey-path", false)?;
+        let lock_arg: H160 = match privkey.as_ref() {
+            Some(privkey) => {
+                let pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, privkey);
+                H160::from_slice(&blake2b_256(&pubkey.serialize()[..])[0..20]).unwrap()
+            }
+            None => FixedHashParser::<H160>::default().from_matches(m, "lock-arg")?,
+        };
+
+        // The signing policy needs the capacities of the inputs
+        let policy_enabled = SigningPolicy::load(&get_ckb_cli_dir())?.is_some();
+        let input_cells = if value.get("input_locks").is_none() || policy_enabled {
+            Some(self.input_cells(&transaction)?)
+        } else {
+            None
+        };
+        let input_locks: Vec<Script> = if value.get("input_locks").is_some() {
+            let locks: Vec<ckb_jsonrpc_types::Script> =
+                serde_json::from_value(value["input_locks"].clone())
+                    .map_err(|err| format!("Parse input_locks failed: {}", err))?;
+            locks.into_iter().map(Script::from).collect()
+        } else {
+            input_cells
+                .as_ref()
+                .unwrap()
+                .iter()
+                .map(|output| output.lock())
+                .collect()
+        };
+        if input_locks.len() != transaction.inputs().len() {
+            return Err(format!(
+                "Number of input_locks ({}) not match inputs ({})",
+                input_locks.len(),
+                transaction.inputs().len()
+            ));
+        }
+
+        let lock_script = self
+            .genesis_info()?
+            .lock_script(&Address::from_lock_arg(lock_arg.as_bytes())?)?;
+        let group = input_locks
+            .iter()
+            .enumerate()
+            .filter(|(_, lock)| lock.as_slice() == lock_script.as_slice())
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        if group.is_empty() {
+            return Err(format!("No input is locked by {:#x}", lock_arg));
+        }
+        let policy = match input_cells.as_ref() {
+            Some(input_cells) if policy_enabled => {
+                let genesis_info = self.genesis_info()?;
+                let (capacity, to_addresses) =
+                    transaction_outflow(&transaction, input_cells, &lock_script, &genesis_info)?;
+                let from_address = Address::from_lock_arg(lock_arg.as_bytes())?;
+                let request = SignRequest {
+                    kind: SignKind::Transfer,
+                    from_address: &from_address,
+                    to_addresses: &to_addresses,
+                    capacity,
+                    cosigned: false,
+                };
+                check_policy(&request, m.is_present("override-policy"))?
+                    .map(|policy| (policy, capacity))
+            }
+            _ => None,
+        };
+        let key_store = &mut self.key_store;
+        let signed_tx = sign_secp_group(&transaction, &group, |args| {
+            if let Some(privkey) = privkey.as_ref() {
+                return witness_with_key(privkey, args);
+            }
+            sign_witness(&lock_arg, args, |sign_hash| {
+                match key_store.sign_recoverable(&lock_arg, sign_hash) {
+                    Err(KeyStoreError::AccountLocked(_)) => {
+                        let password = read_password(false, None)?;
+                        key_store
+                            .sign_recoverable_with_password(
+                                &lock_arg,
+                                sign_hash,
+                                password.as_bytes(),
+                            )
+                            .map_err(|err| err.to_string())
+                    }
+                    result => result.map_err(|err| err.to_string()),
+                }
+            })
+        })?;
+        // The transaction may be sent by others, record it once signed
+        if let Some((policy, capacity)) = policy {
+            policy.record(capacity)?;
+        }
+
+        let tx_hash: H256 = signed_tx.hash().unpack();
+        let tx_json: ckb_jsonrpc_types::Transaction = signed_tx.data().into();
+        let resp = if let Some(path) = m.value_of("output-file") {
+            let content = serde_json::to_string_pretty(&tx_json).map_err(|err| err.to_string())?;
+            fs::write(path, content).map_err(|err| err.to_string())?;
+            serde_json::json!({
+                "tx_hash": tx_hash,
+                "signed_inputs": group,
+                "output_file": path,
+            })
+        } else {
+            serde_json::json!({
+                "tx_hash": tx_hash,
+                "signed_inputs": group,
+                "transaction": tx_json,
+            })
+        };
+        Ok(resp.render(format, color))
+    }
+
+    // The cells spent by the inputs
+    fn input_cells(&mut self, transaction: &TransactionView) -> Result<Vec<CellOutput>, String> {
+        let mut cells = Vec::new();
+        for input in transaction.inputs().into_iter() {
+            let out_point = input.previous_output();
+            let tx_hash: H256 = out_point.tx_hash().unpack();
+            let index: u32 = out_point.index().unpack();
+            let previous_tx = self
+                .rpc_client
+                .get_transaction(tx_hash.clone())
+                .call()
+                .map_err(|err| format!("Send get_transaction error: {}", err))?
+                .0
+                .ok_or_else(|| format!("Input transaction not found: {:#x}", tx_hash))?;
+            let output = previous_tx
+                .transaction
+                .inner
+                .outputs
+                .get(index as usize)
+                .cloned()
+                .ok_or_else(|| format!("Input cell not found: {:#x}-{}", tx_hash, index))?;
+            cells.push(CellOutput::from(output));
+        }
+        Ok(cells)
+    }
+
+    fn extended_pubkey(
+        &mut self,
+        lock_arg: &H160,
+        path: Option<&DerivationPath>,
+    ) -> Result<ExtendedPubKey, String> {
+        match self.key_store.extended_pubkey(lock_arg, path) {
+            Err(KeyStoreError::AccountLocked(_)) => {
+                let password = read_password(false, None)?;
+                self.key_store
+                    .extended_pubkey_with_password(lock_arg, path, password.as_bytes())
+                    .map_err(|err| err.to_string())
+            }
+            result => result.map_err(|err| err.to_string()),
+        }
+    }
+}
+
 impl<'a> CliSubCommand for AccountSubCommand<'a> {
     fn process(
         &mut self,
@@ -171,10 +560,130 @@ impl<'a> CliSubCommand for AccountSubCommand<'a> {
                         })
                     })
                     .collect::<Vec<_>>();
-                Ok(serde_json::json!(resp).render(format, color))
+                let offset = resp.len();
+                let watch_only = WatchOnlyAccounts::load_default()?
+                    .list()
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, (lock_arg, account))| {
+                        let address = Address::from_lock_arg(lock_arg.as_bytes()).unwrap();
+                        let lock_hash_opt: Option<H256> = genesis_info_opt.as_ref().map(|info| {
+                            address
+                                .lock_script(info.secp_type_hash().clone())
+                                .calc_script_hash()
+                                .unpack()
+                        });
+                        serde_json::json!({
+                            "#": offset + idx,
+                            "lock_arg": format!("{:x}", lock_arg),
+                            "lock_hash": lock_hash_opt,
+                            "address": {
+                                "mainnet": address.to_string(NetworkType::MainNet),
+                                "testnet": address.to_string(NetworkType::TestNet),
+                            },
+                            "pubkey": account.pubkey,
+                            "status": "watch-only",
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                let mut resp = serde_json::json!(resp
+                    .into_iter()
+                    .chain(watch_only.into_iter())
+                    .collect::<Vec<_>>());
+                annotate_labels(&mut resp, &AddressBook::load_default()?);
+                Ok(resp.render(format, color))
+            }
+            ("import-watch-only", Some(m)) => {
+                let pubkey_opt: Option<secp256k1::PublicKey> =
+                    PubkeyHexParser.from_matches_opt(m, "pubkey", false)?;
+                let (lock_arg, pubkey) = if let Some(pubkey) = pubkey_opt {
+                    let address = Address::from_pubkey(&pubkey)?;
+                    let pubkey_hex = format!("0x{}", hex_string(&pubkey.serialize()).unwrap());
+                    (address.hash().clone(), Some(pubkey_hex))
+                } else {
+                    let lock_arg: H160 =
+                        FixedHashParser::<H160>::default().from_matches(m, "lock-arg")?;
+                    (lock_arg, None)
+                };
+                if self.key_store.has_account(&lock_arg) {
+                    return Err(format!("Account {:x} already exists in keystore", lock_arg));
+                }
+                if MultisigConfigs::load_default()?.contains(&lock_arg) {
+                    return Err(format!(
+                        "{:#x} is the lock arg of a multisig config, not a secp256k1 lock arg",
+                        lock_arg
+                    ));
+                }
+                let mut accounts = WatchOnlyAccounts::load_default()?;
+                if accounts.contains(&lock_arg) {
+                    eprintln!(
+                        "[WARNING]: Watch-only account {:x} already imported, will be overwritten",
+                        lock_arg
+                    );
+                }
+                let address = Address::from_lock_arg(lock_arg.as_bytes()).unwrap();
+                // The node may be offline when importing, checked before saving the account so a
+                // failed check leaves nothing imported
+                let on_chain_activity = match self.genesis_info() {
+                    Ok(genesis_info) => {
+                        let lock_script = genesis_info.lock_script(&address)?;
+                        check_lock_activity(
+                            self.rpc_client,
+                            lock_script.calc_script_hash().unpack(),
+                        )?
+                    }
+                    Err(_) => None,
+                };
+                accounts.add(lock_arg.clone(), WatchOnlyAccount { pubkey })?;
+                let resp = serde_json::json!({
+                    "lock_arg": format!("{:x}", lock_arg),
+                    "address": {
+                        "mainnet": address.to_string(NetworkType::MainNet),
+                        "testnet": address.to_string(NetworkType::TestNet),
+                    },
+                    "on_chain_activity": on_chain_activity,
+                });
+                Ok(resp.render(format, color))
+            }
+            ("remove-watch-only", Some(m)) => {
+                let lock_arg: H160 =
+                    FixedHashParser::<H160>::default().from_matches(m, "lock-arg")?;
+                WatchOnlyAccounts::load_default()?.remove(&lock_arg)?;
+                Ok("success".to_owned())
+            }
+            ("label", Some(m)) => {
+                let lock_arg: H160 =
+                    FixedHashParser::<H160>::default().from_matches(m, "lock-arg")?;
+                let label = m.value_of("name").unwrap();
+                AddressBook::load_default()?.set(label, lock_arg)?;
+                Ok("success".to_owned())
+            }
+            ("unlabel", Some(m)) => {
+                let label = m.value_of("name").unwrap();
+                AddressBook::load_default()?.remove(label)?;
+                Ok("success".to_owned())
+            }
+            ("labels", _) => {
+                let book = AddressBook::load_default()?;
+                let resp = book
+                    .list()
+                    .iter()
+                    .map(|(label, lock_arg)| {
+                        let address = Address::from_lock_arg(lock_arg.as_bytes()).unwrap();
+                        serde_json::json!({
+                            "label": label,
+                            "lock_arg": format!("{:x}", lock_arg),
+                            "address": {
+                                "mainnet": address.to_string(NetworkType::MainNet),
+                                "testnet": address.to_string(NetworkType::TestNet),
+                            },
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                Ok(resp.render(format, color))
             }
             ("new", _) => {
-                println!("Your new account is locked with a password. Please give a password. Do not forget this password.");
+                eprintln!("Your new account is locked with a password. Please give a password. Do not forget this password.");
 
                 let pass = read_password(true, None)?;
                 let lock_arg = self
@@ -240,7 +749,41 @@ impl<'a> CliSubCommand for AccountSubCommand<'a> {
                 });
                 Ok(resp.render(format, color))
             }
-            ("update", Some(m)) => {
+            ("lock", Some(m)) => {
+                let lock_arg: Option<H160> =
+                    FixedHashParser::<H160>::default().from_matches_opt(m, "lock-arg", false)?;
+                let locked = match lock_arg {
+                    Some(lock_arg) if self.key_store.lock(&lock_arg) => vec![lock_arg],
+                    Some(lock_arg) => {
+                        return Err(format!("Account {:#x} is not unlocked", lock_arg));
+                    }
+                    None => self.key_store.lock_all(),
+                };
+                let resp = serde_json::json!({
+                    "locked": locked,
+                });
+                Ok(resp.render(format, color))
+            }
+            ("sign-tx", Some(m)) => self.sign_tx(m, format, color),
+            ("bench-sign", Some(m)) => Ok(self.bench_sign(m)?.render(format, color)),
+            ("sign-history", Some(m)) => {
+                let lock_arg: Option<H160> =
+                    FixedHashParser::<H160>::default().from_matches_opt(m, "lock-arg", false)?;
+                let limit: usize = FromStrParser::<usize>::default().from_matches(m, "limit")?;
+                let records = SignHistory::default_path()
+                    .records()?
+                    .into_iter()
+                    .rev()
+                    .filter(|record| {
+                        lock_arg
+                            .as_ref()
+                            .map_or(true, |arg| arg == &record.lock_arg)
+                    })
+                    .take(limit)
+                    .collect::<Vec<_>>();
+                Ok(records.render(format, color))
+            }
+            ("update-password", Some(m)) => {
                 let lock_arg: H160 =
                     FixedHashParser::<H160>::default().from_matches(m, "lock-arg")?;
                 let old_password = read_password(false, Some("Old password"))?;
@@ -266,7 +809,7 @@ impl<'a> CliSubCommand for AccountSubCommand<'a> {
                 let bytes = master_privkey.to_bytes();
                 let privkey = H256::from_slice(&bytes[0..32]).unwrap();
                 let chain_code = H256::from_slice(&bytes[32..64]).unwrap();
-                let mut file = fs::File::create(key_path).map_err(|err| err.to_string())?;
+                let mut file = create_private_file(key_path).map_err(|err| err.to_string())?;
                 file.write(format!("{:x}\n", privkey).as_bytes())
                     .map_err(|err| err.to_string())?;
                 file.write(format!("{:x}", chain_code).as_bytes())
@@ -276,19 +819,130 @@ impl<'a> CliSubCommand for AccountSubCommand<'a> {
                     key_path
                 ))
             }
-            ("extended-address", Some(m)) => {
+            ("export-privkey", Some(m)) => {
                 let lock_arg: H160 =
                     FixedHashParser::<H160>::default().from_matches(m, "lock-arg")?;
-                let path: Option<DerivationPath> =
-                    FromStrParser::<DerivationPath>::new().from_matches_opt(m, "path", false)?;
-
-                let extended_pubkey = self
+                let key_path = m.value_of("output-file").unwrap();
+                if Path::new(key_path).exists() {
+                    return Err(format!("File exists: {}", key_path));
+                }
+                let password = read_password(false, None)?;
+                let master_privkey = self
+                    .key_store
+                    .export_key(&lock_arg, password.as_bytes())
+                    .map_err(|err| err.to_string())?;
+                let bytes = master_privkey.to_bytes();
+                let privkey = H256::from_slice(&bytes[0..32]).unwrap();
+                create_private_file(key_path)
+                    .and_then(|mut file| file.write_all(format!("{:x}\n", privkey).as_bytes()))
+                    .map_err(|err| err.to_string())?;
+                Ok(format!(
+                    "Success exported private key of account {:x} to: \"{}\", please use this file carefully",
+                    lock_arg, key_path
+                ))
+            }
+            ("backup", Some(m)) => {
+                let backup_path = m.value_of("output-file").unwrap();
+                if Path::new(backup_path).exists() {
+                    return Err(format!("File exists: {}", backup_path));
+                }
+                let passphrase = read_password(true, Some("Backup passphrase"))?;
+                let archive = self
+                    .key_store
+                    .backup(passphrase.as_bytes())
+                    .map_err(|err| err.to_string())?;
+                let content = serde_json::to_string(&archive).map_err(|err| err.to_string())?;
+                create_private_file(backup_path)
+                    .and_then(|mut file| file.write_all(content.as_bytes()))
+                    .map_err(|err| err.to_string())?;
+                let resp = serde_json::json!({
+                    "path": backup_path,
+                    "accounts": archive["count"],
+                });
+                Ok(resp.render(format, color))
+            }
+            ("restore", Some(m)) => {
+                let backup_path: PathBuf =
+                    FilePathParser::new(true).from_matches(m, "backup-path")?;
+                let content = fs::read_to_string(&backup_path).map_err(|err| err.to_string())?;
+                let archive: serde_json::Value =
+                    serde_json::from_str(&content).map_err(|err| err.to_string())?;
+                let passphrase = read_password(false, Some("Backup passphrase"))?;
+                let report = self
+                    .key_store
+                    .restore(&archive, passphrase.as_bytes())
+                    .map_err(|err| err.to_string())?;
+                let resp = serde_json::json!({
+                    "restored": report
+                        .restored
+                        .iter()
+                        .map(|lock_arg| format!("{:x}", lock_arg))
+                        .collect::<Vec<_>>(),
+                    "collisions": report
+                        .collisions
+                        .iter()
+                        .map(|lock_arg| format!("{:x}", lock_arg))
+                        .collect::<Vec<_>>(),
+                });
+                Ok(resp.render(format, color))
+            }
+            ("import-mnemonic", Some(m)) => {
+                let phrase = if let Some(path) = m.value_of("mnemonic-path") {
+                    fs::read_to_string(path).map_err(|err| err.to_string())?
+                } else {
+                    read_password(false, Some("Mnemonic words"))?
+                };
+                let password = read_password(true, None)?;
+                let lock_arg = self
+                    .key_store
+                    .import_mnemonic(&phrase, password.as_bytes())
+                    .map_err(|err| err.to_string())?;
+                let address = Address::from_lock_arg(lock_arg.as_bytes()).unwrap();
+                let resp = serde_json::json!({
+                    "lock_arg": format!("{:x}", lock_arg),
+                    "address": {
+                        "mainnet": address.to_string(NetworkType::MainNet),
+                        "testnet": address.to_string(NetworkType::TestNet),
+                    },
+                });
+                Ok(resp.render(format, color))
+            }
+            ("export-mnemonic", Some(m)) => {
+                let lock_arg: H160 =
+                    FixedHashParser::<H160>::default().from_matches(m, "lock-arg")?;
+                let password = read_password(false, None)?;
+                let phrase = self
                     .key_store
-                    .extended_pubkey(&lock_arg, path.as_ref())
+                    .export_mnemonic(&lock_arg, password.as_bytes())
                     .map_err(|err| err.to_string())?;
-                let address = Address::from_pubkey(&extended_pubkey.public_key)?;
                 let resp = serde_json::json!({
-                    "lock_arg": format!("{:x}", address.hash()),
+                    "lock_arg": format!("{:x}", lock_arg),
+                    "mnemonic": phrase,
+                });
+                Ok(resp.render(format, color))
+            }
+            ("derive", Some(m)) => {
+                let lock_arg: H160 =
+                    FixedHashParser::<H160>::default().from_matches(m, "lock-arg")?;
+                let path: DerivationPath =
+                    FromStrParser::<DerivationPath>::new().from_matches(m, "path")?;
+                let password = read_password(false, None)?;
+                let key = self
+                    .key_store
+                    .derive_key(&lock_arg, &path, password.as_bytes())
+                    .map_err(|err| err.to_string())?;
+                let child_lock_arg = if self.key_store.has_account(key.address()) {
+                    key.address().clone()
+                } else {
+                    self.key_store
+                        .import_key(&key, password.as_bytes())
+                        .map_err(|err| err.to_string())?
+                };
+                let address = Address::from_lock_arg(child_lock_arg.as_bytes()).unwrap();
+                let resp = serde_json::json!({
+                    "parent_lock_arg": format!("{:x}", lock_arg),
+                    "path": path.to_string(),
+                    "lock_arg": format!("{:x}", child_lock_arg),
                     "address": {
                         "mainnet": address.to_string(NetworkType::MainNet),
                         "testnet": address.to_string(NetworkType::TestNet),
@@ -296,6 +950,61 @@ impl<'a> CliSubCommand for AccountSubCommand<'a> {
                 });
                 Ok(resp.render(format, color))
             }
+            ("extended-address", Some(m)) => {
+                let lock_arg: H160 =
+                    FixedHashParser::<H160>::default().from_matches(m, "lock-arg")?;
+                let path: Option<DerivationPath> =
+                    FromStrParser::<DerivationPath>::new().from_matches_opt(m, "path", false)?;
+
+                if path.is_some() {
+                    let extended_pubkey = self.extended_pubkey(&lock_arg, path.as_ref())?;
+                    let address = Address::from_pubkey(&extended_pubkey.public_key)?;
+                    let resp = serde_json::json!({
+                        "lock_arg": format!("{:x}", address.hash()),
+                        "address": {
+                            "mainnet": address.to_string(NetworkType::MainNet),
+                            "testnet": address.to_string(NetworkType::TestNet),
+                        },
+                    });
+                    return Ok(resp.render(format, color));
+                }
+
+                let receiving_count: u32 =
+                    FromStrParser::<u32>::default().from_matches(m, "receiving-count")?;
+                let change_count: u32 =
+                    FromStrParser::<u32>::default().from_matches(m, "change-count")?;
+                let account_path: DerivationPath = CKB_ACCOUNT_PATH.parse().unwrap();
+                let account_pubkey = self.extended_pubkey(&lock_arg, Some(&account_path))?;
+                let list_addresses = |change: bool, count: u32| {
+                    (0..count)
+                        .map(|index| {
+                            let children = vec![
+                                ChildNumber::from_normal_idx(if change { 1 } else { 0 })
+                                    .map_err(|err| err.to_string())?,
+                                ChildNumber::from_normal_idx(index)
+                                    .map_err(|err| err.to_string())?,
+                            ];
+                            let pubkey = account_pubkey
+                                .derive_pub(&SECP256K1, &children)
+                                .map_err(|err| err.to_string())?;
+                            let address = Address::from_pubkey(&pubkey.public_key)?;
+                            Ok(serde_json::json!({
+                                "path": bip44_path(change, index).to_string(),
+                                "lock_arg": format!("{:x}", address.hash()),
+                                "address": {
+                                    "mainnet": address.to_string(NetworkType::MainNet),
+                                    "testnet": address.to_string(NetworkType::TestNet),
+                                },
+                            }))
+                        })
+                        .collect::<Result<Vec<_>, String>>()
+                };
+                let resp = serde_json::json!({
+                    "receiving": list_addresses(false, receiving_count)?,
+                    "change": list_addresses(true, change_count)?,
+                });
+                Ok(resp.render(format, color))
+            }
             _ => Err(matches.usage().to_owned()),
         }
     }
@@ -0,0 +1,283 @@
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use ckb_jsonrpc_types::{BlockNumber, BlockReward, BlockView};
+use ckb_sdk::{GenesisInfo, HttpRpcClient};
+use ckb_types::{packed, prelude::*, H256};
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use super::CliSubCommand;
+use crate::utils::{
+    arg,
+    arg_parser::{ArgParser, DurationParser, FixedHashParser, FromStrParser},
+    other::{get_address, get_genesis_info},
+    printer::{OutputFormat, Printable},
+};
+
+pub struct ChainSubCommand<'a> {
+    rpc_client: &'a mut HttpRpcClient,
+    genesis_info: Option<GenesisInfo>,
+}
+
+impl<'a> ChainSubCommand<'a> {
+    pub fn new(
+        rpc_client: &'a mut HttpRpcClient,
+        genesis_info: Option<GenesisInfo>,
+    ) -> ChainSubCommand<'a> {
+        ChainSubCommand {
+            rpc_client,
+            genesis_info,
+        }
+    }
+
+    pub fn subcommand(name: &'static str) -> App<'static, 'static> {
+        let arg_number = Arg::with_name("number")
+            .long("number")
+            .takes_value(true)
+            .validator(|input| FromStrParser::<u64>::default().validate(input))
+            .help("Block number");
+        let arg_hash = Arg::with_name("hash")
+            .long("hash")
+            .takes_value(true)
+            .validator(|input| FixedHashParser::<H256>::default().validate(input))
+            .help("Block hash");
+        SubCommand::with_name(name)
+            .about("Chain information query (cellbase, miner reward, sync status)")
+            .subcommands(vec![
+                SubCommand::with_name("get-cellbase")
+                    .about("Get cellbase outputs and reward details of a block")
+                    .arg(arg_number.required_unless("hash"))
+                    .arg(arg_hash.required_unless("number")),
+                SubCommand::with_name("miner-reward")
+                    .about("Report cellbase rewards earned by a miner lock in a block range")
+                    .arg(arg::from_block_number().required(true))
+                    .arg(arg::to_block_number().required(true))
+                    .arg(arg::lock_hash())
+                    .arg(arg::address())
+                    .arg(arg::pubkey())
+                    .arg(arg::lock_arg()),
+                SubCommand::with_name("wait-synced")
+                    .about("Block until the node is synced (not in initial block download, has peers and a fresh tip), RPC errors are retried, exit with error on timeout")
+                    .arg(
+                        Arg::with_name("timeout")
+                            .long("timeout")
+                            .takes_value(true)
+                            .validator(|input| DurationParser.validate(input))
+                            .help("Max time to wait, format: 30s, 15m, 1h (default: wait forever)"),
+                    )
+                    .arg(
+                        Arg::with_name("max-tip-age")
+                            .long("max-tip-age")
+                            .takes_value(true)
+                            .default_value("2m")
+                            .validator(|input| DurationParser.validate(input))
+                            .help("The tip block is considered fresh when its timestamp is not older than this"),
+                    ),
+            ])
+    }
+
+    fn get_block(&mut self, number: u64) -> Result<BlockView, String> {
+        self.rpc_client
+            .get_block_by_number(BlockNumber::from(number))
+            .call()
+            .map_err(|err| err.to_string())?
+            .0
+            .ok_or_else(|| format!("Block #{} not found", number))
+    }
+
+    fn sync_status(&mut self, max_tip_age: Duration) -> Result<serde_json::Value, String> {
+        let chain_info = self
+            .rpc_client
+            .get_blockchain_info()
+            .call()
+            .map_err(|err| err.to_string())?;
+        let local_node = self
+            .rpc_client
+            .local_node_info()
+            .call()
+            .map_err(|err| err.to_string())?;
+        let peers = self
+            .rpc_client
+            .get_peers()
+            .call()
+            .map_err(|err| err.to_string())?
+            .0
+            .len();
+        let tip = self
+            .rpc_client
+            .get_tip_header()
+            .call()
+            .map_err(|err| err.to_string())?;
+        let now_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|err| err.to_string())?
+            .as_millis() as u64;
+        let tip_age = Duration::from_millis(now_ms.saturating_sub(tip.inner.timestamp.value()));
+        let synced = !chain_info.is_initial_block_download && peers > 0 && tip_age <= max_tip_age;
+        Ok(serde_json::json!({
+            "synced": synced,
+            "chain": chain_info.chain,
+            "is_initial_block_download": chain_info.is_initial_block_download,
+            "node_id": local_node.node_id,
+            "peers": peers,
+            "tip_number": tip.inner.number,
+            "tip_hash": tip.hash,
+            "tip_age_secs": tip_age.as_secs(),
+        }))
+    }
+
+    fn get_reward(&mut self, block_hash: H256) -> Result<Option<BlockReward>, String> {
+        self.rpc_client
+            .get_cellbase_output_capacity_details(block_hash)
+            .call()
+            .map(|reward| reward.0)
+            .map_err(|err| err.to_string())
+    }
+}
+
+impl<'a> CliSubCommand for ChainSubCommand<'a> {
+    fn process(
+        &mut self,
+        matches: &ArgMatches,
+        format: OutputFormat,
+        color: bool,
+        _debug: bool,
+    ) -> Result<String, String> {
+        match matches.subcommand() {
+            ("get-cellbase", Some(m)) => {
+                let number_opt: Option<u64> =
+                    FromStrParser::<u64>::default().from_matches_opt(m, "number", false)?;
+                let block = if let Some(number) = number_opt {
+                    self.get_block(number)?
+                } else {
+                    let hash: H256 = FixedHashParser::<H256>::default().from_matches(m, "hash")?;
+                    self.rpc_client
+                        .get_block(hash.clone())
+                        .call()
+                        .map_err(|err| err.to_string())?
+                        .0
+                        .ok_or_else(|| format!("Block {:#x} not found", hash))?
+                };
+                let cellbase = block
+                    .transactions
+                    .get(0)
+                    .ok_or_else(|| "Cellbase not found in block".to_owned())?;
+                let reward = self.get_reward(block.header.hash.clone())?;
+                let resp = serde_json::json!({
+                    "block_number": block.header.inner.number,
+                    "block_hash": block.header.hash,
+                    "cellbase_hash": cellbase.hash,
+                    "outputs": cellbase.inner.outputs,
+                    "reward": reward,
+                });
+                Ok(resp.render(format, color))
+            }
+            ("miner-reward", Some(m)) => {
+                let from_number: u64 = FromStrParser::<u64>::default().from_matches(m, "from")?;
+                let to_number: u64 = FromStrParser::<u64>::default().from_matches(m, "to")?;
+                if from_number > to_number {
+                    return Err(format!(
+                        "Invalid block range: from({}) > to({})",
+                        from_number, to_number
+                    ));
+                }
+                let lock_hash_opt: Option<H256> =
+                    FixedHashParser::<H256>::default().from_matches_opt(m, "lock-hash", false)?;
+                let lock_hash: H256 = if let Some(lock_hash) = lock_hash_opt {
+                    lock_hash
+                } else {
+                    let genesis_info = get_genesis_info(&mut self.genesis_info, self.rpc_client)?;
+                    let address = get_address(m)?;
+                    genesis_info
+                        .lock_script(&address)?
+                        .calc_script_hash()
+                        .unpack()
+                };
+
+                let mut blocks = Vec::new();
+                let (mut primary, mut secondary, mut proposal_reward, mut tx_fee, mut total) =
+                    (0, 0, 0, 0, 0);
+                for number in from_number..=to_number {
+                    let block = self.get_block(number)?;
+                    let is_miner = block
+                        .transactions
+                        .get(0)
+                        .and_then(|cellbase| cellbase.inner.outputs.get(0))
+                        .map(|output| {
+                            let lock: packed::Script = output.lock.clone().into();
+                            let hash: H256 = lock.calc_script_hash().unpack();
+                            hash == lock_hash
+                        })
+                        .unwrap_or(false);
+                    if !is_miner {
+                        continue;
+                    }
+                    if let Some(reward) = self.get_reward(block.header.hash.clone())? {
+                        primary += reward.primary.value();
+                        secondary += reward.secondary.value();
+                        proposal_reward += reward.proposal_reward.value();
+                        tx_fee += reward.tx_fee.value();
+                        total += reward.total.value();
+                        blocks.push(serde_json::json!({
+                            "number": number,
+                            "hash": block.header.hash,
+                            "reward": reward,
+                        }));
+                    }
+                }
+                let resp = serde_json::json!({
+                    "lock_hash": lock_hash,
+                    "from": from_number,
+                    "to": to_number,
+                    "blocks": blocks,
+                    "total": {
+                        "blocks": blocks.len(),
+                        "primary": primary,
+                        "secondary": secondary,
+                        "proposal_reward": proposal_reward,
+                        "tx_fee": tx_fee,
+                        "total": total,
+                    },
+                });
+                Ok(resp.render(format, color))
+            }
+            ("wait-synced", Some(m)) => {
+                let timeout_opt: Option<Duration> =
+                    DurationParser.from_matches_opt(m, "timeout", false)?;
+                let max_tip_age: Duration = DurationParser.from_matches(m, "max-tip-age")?;
+                let start = Instant::now();
+                let mut last_error: Option<String> = None;
+                loop {
+                    // The node may be starting or restarting, the RPC errors are retried until
+                    // the timeout
+                    let last_status = match self.sync_status(max_tip_age) {
+                        Ok(status) => {
+                            if status["synced"].as_bool().unwrap_or(false) {
+                                return Ok(status.render(format, color));
+                            }
+                            last_error = None;
+                            status.render(format, color)
+                        }
+                        Err(err) => {
+                            if last_error.as_ref() != Some(&err) {
+                                eprintln!("Get sync status failed (will retry): {}", err);
+                            }
+                            last_error = Some(err.clone());
+                            err
+                        }
+                    };
+                    if let Some(timeout) = timeout_opt {
+                        if start.elapsed() >= timeout {
+                            return Err(format!(
+                                "Wait synced timeout, last status:\n{}",
+                                last_status
+                            ));
+                        }
+                    }
+                    thread::sleep(Duration::from_secs(1));
+                }
+            }
+            _ => Err(matches.usage().to_owned()),
+        }
+    }
+}
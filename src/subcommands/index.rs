@@ -0,0 +1,188 @@
+use std::path::PathBuf;
+
+use ckb_index::{export_snapshot, read_snapshot_info, with_index_db};
+use ckb_sdk::{GenesisInfo, HttpRpcClient};
+use ckb_types::{core::service::Request, prelude::*, H256};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use serde_json::json;
+
+use super::{CliSubCommand, IndexController, IndexRequest, IndexResponse};
+use crate::utils::{
+    arg_parser::{ArgParser, FilePathParser, FixedHashParser, FromStrParser},
+    other::{dir_size, get_genesis_info},
+    printer::{OutputFormat, Printable},
+};
+
+pub struct IndexSubCommand<'a> {
+    rpc_client: &'a mut HttpRpcClient,
+    genesis_info: Option<GenesisInfo>,
+    index_dir: PathBuf,
+    index_controller: IndexController,
+}
+
+impl<'a> IndexSubCommand<'a> {
+    pub fn new(
+        rpc_client: &'a mut HttpRpcClient,
+        genesis_info: Option<GenesisInfo>,
+        index_dir: PathBuf,
+        index_controller: IndexController,
+    ) -> IndexSubCommand<'a> {
+        IndexSubCommand {
+            rpc_client,
+            genesis_info,
+            index_dir,
+            index_controller,
+        }
+    }
+
+    pub fn subcommand(name: &'static str) -> App<'static, 'static> {
+        SubCommand::with_name(name)
+            .about("Manage the local index database")
+            .subcommands(vec![
+                SubCommand::with_name("status").about(
+                    "Show the index thread state, last indexed block, database path and size",
+                ),
+                SubCommand::with_name("rebuild")
+                    .about("Rebuild the index database from scratch or from a recent block (works while the index thread is running)")
+                    .arg(
+                        Arg::with_name("from-block")
+                            .long("from-block")
+                            .takes_value(true)
+                            .validator(|input| FromStrParser::<u64>::default().validate(input))
+                            .help("Re-index the blocks from this number, default is from scratch"),
+                    ),
+                SubCommand::with_name("export-snapshot")
+                    .about("Dump the index database of current chain to a snapshot file")
+                    .arg(
+                        Arg::with_name("output-file")
+                            .long("output-file")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FilePathParser::new(false).validate(input))
+                            .help("Snapshot file path"),
+                    )
+                    .arg(
+                        Arg::with_name("block-hash")
+                            .long("block-hash")
+                            .takes_value(true)
+                            .validator(|input| FixedHashParser::<H256>::default().validate(input))
+                            .help("Only export when the index database is at this block"),
+                    ),
+                SubCommand::with_name("import-snapshot")
+                    .about("Replace the index database of current chain by a snapshot file")
+                    .arg(
+                        Arg::with_name("input-file")
+                            .long("input-file")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FilePathParser::new(true).validate(input))
+                            .help("Snapshot file path"),
+                    ),
+            ])
+    }
+
+    fn genesis_hash(&mut self) -> Result<H256, String> {
+        let genesis_info = get_genesis_info(&mut self.genesis_info, self.rpc_client)?;
+        Ok(genesis_info.header().hash().unpack())
+    }
+}
+
+impl<'a> CliSubCommand for IndexSubCommand<'a> {
+    fn process(
+        &mut self,
+        matches: &ArgMatches,
+        format: OutputFormat,
+        color: bool,
+        _debug: bool,
+    ) -> Result<String, String> {
+        match matches.subcommand() {
+            ("status", _) => {
+                let genesis_hash = self.genesis_hash()?;
+                let mut db_path = self.index_dir.clone();
+                db_path.push(format!("{:#x}", genesis_hash));
+                let state = self.index_controller.state().read().clone();
+                let (last_block, tip_number) = match state.processed() {
+                    Some((info, tip_number)) => (Some(json!(info)), Some(tip_number)),
+                    None => (None, None),
+                };
+                let resp = json!({
+                    "state": state.to_string(),
+                    "last_block": last_block,
+                    "tip_number": tip_number,
+                    "genesis_hash": genesis_hash,
+                    "db_path": db_path.to_string_lossy(),
+                    "db_size": dir_size(&db_path),
+                });
+                Ok(resp.render(format, color))
+            }
+            ("rebuild", Some(m)) => {
+                let from_number: Option<u64> =
+                    FromStrParser::<u64>::default().from_matches_opt(m, "from-block", false)?;
+                match Request::call(
+                    self.index_controller.sender(),
+                    IndexRequest::Rebuild(from_number),
+                ) {
+                    Some(IndexResponse::Ok) => {}
+                    Some(IndexResponse::Error(err)) => return Err(err),
+                    None => return Err("Index thread stopped".to_owned()),
+                }
+                let resp = json!({
+                    "from_block": from_number.unwrap_or(0),
+                    "state": self.index_controller.state().read().to_string(),
+                });
+                Ok(resp.render(format, color))
+            }
+            ("export-snapshot", Some(m)) => {
+                let output_file: PathBuf =
+                    FilePathParser::new(false).from_matches(m, "output-file")?;
+                let block_hash: Option<H256> =
+                    FixedHashParser::<H256>::default().from_matches_opt(m, "block-hash", false)?;
+                let genesis_hash = self.genesis_hash()?;
+                let info = with_index_db(&self.index_dir, genesis_hash, |db, cf| {
+                    export_snapshot(db, cf, &output_file, block_hash.as_ref())
+                })
+                .map_err(|err| format!("Export snapshot failed: {}", err))?;
+                let resp = json!({
+                    "file": output_file.to_string_lossy(),
+                    "genesis_hash": info.genesis_hash,
+                    "block_number": info.block_number,
+                    "block_hash": info.block_hash,
+                    "pairs": info.pairs,
+                });
+                Ok(resp.render(format, color))
+            }
+            ("import-snapshot", Some(m)) => {
+                let input_file: PathBuf =
+                    FilePathParser::new(true).from_matches(m, "input-file")?;
+                let snapshot_info = read_snapshot_info(&input_file)
+                    .map_err(|err| format!("Read snapshot failed: {}", err))?;
+                let genesis_hash = self.genesis_hash()?;
+                if snapshot_info.genesis_hash != genesis_hash {
+                    return Err(format!(
+                        "Snapshot is for chain {:#x}, but the node is on chain {:#x}",
+                        snapshot_info.genesis_hash, genesis_hash
+                    ));
+                }
+                // Done by the index thread, it does not write the database meanwhile
+                match Request::call(
+                    self.index_controller.sender(),
+                    IndexRequest::ImportSnapshot(input_file),
+                ) {
+                    Some(IndexResponse::Ok) => {}
+                    Some(IndexResponse::Error(err)) => {
+                        return Err(format!("Import snapshot failed: {}", err))
+                    }
+                    None => return Err("Index thread stopped".to_owned()),
+                }
+                let resp = json!({
+                    "genesis_hash": snapshot_info.genesis_hash,
+                    "block_number": snapshot_info.block_number,
+                    "block_hash": snapshot_info.block_hash,
+                    "pairs": snapshot_info.pairs,
+                });
+                Ok(resp.render(format, color))
+            }
+            _ => Err(matches.usage().to_owned()),
+        }
+    }
+}
@@ -1,26 +1,32 @@
+use std::collections::HashSet;
 use std::fs;
 use std::io::{Read, Write};
 use std::path::PathBuf;
+use std::process::Command;
 
 use ckb_sdk::{
     wallet::KeyStore, GenesisInfo, HttpRpcClient, MockCellDep, MockInfo, MockInput,
     MockResourceLoader, MockTransaction, MockTransactionHelper, ReprMockTransaction,
+    ScriptGroupCycle,
 };
 use ckb_types::{
     bytes::Bytes,
     core::{
-        capacity_bytes, Capacity, HeaderBuilder, HeaderView, ScriptHashType, TransactionBuilder,
+        capacity_bytes, Capacity, DepType, HeaderBuilder, HeaderView, ScriptHashType,
+        TransactionBuilder,
     },
     h256,
-    packed::{CellDep, CellInput, CellOutput, OutPoint, Script},
+    packed::{CellDep, CellInput, CellOutput, OutPoint, OutPointVec, Script, Transaction},
     prelude::*,
     H160, H256,
 };
 use clap::{App, Arg, ArgMatches, SubCommand};
 
-use super::CliSubCommand;
+use super::{CliSubCommand, FeeGuard, TxSender};
 use crate::utils::{
-    arg_parser::{ArgParser, FilePathParser, FixedHashParser},
+    arg,
+    arg_parser::{ArgParser, FilePathParser, FixedHashParser, FromStrParser},
+    elf::{extract_pc, find_symbol, loaded_size},
     other::{get_genesis_info, get_singer},
     printer::{OutputFormat, Printable},
 };
@@ -29,6 +35,7 @@ pub struct MockTxSubCommand<'a> {
     rpc_client: &'a mut HttpRpcClient,
     key_store: &'a mut KeyStore,
     genesis_info: Option<GenesisInfo>,
+    fee_guard: FeeGuard,
 }
 
 impl<'a> MockTxSubCommand<'a> {
@@ -41,9 +48,16 @@ impl<'a> MockTxSubCommand<'a> {
             rpc_client,
             key_store,
             genesis_info,
+            fee_guard: FeeGuard::default(),
         }
     }
 
+    /// The limits of transaction fee checked before `send` (unless `--force-fee`)
+    pub fn with_fee_guard(mut self, fee_guard: FeeGuard) -> MockTxSubCommand<'a> {
+        self.fee_guard = fee_guard;
+        self
+    }
+
     pub fn subcommand(name: &'static str) -> App<'static, 'static> {
         let arg_tx_file = Arg::with_name("tx-file")
             .long("tx-file")
@@ -63,7 +77,7 @@ impl<'a> MockTxSubCommand<'a> {
             .required(true)
             .help("The lock_arg (identifier) of the account");
         SubCommand::with_name(name)
-            .about("Handle mock transactions (verify/send)")
+            .about("Handle mock transactions (verify/debug/send/record)")
             .subcommands(vec![
                 SubCommand::with_name("template")
                     .about("Print mock transaction template")
@@ -79,10 +93,81 @@ impl<'a> MockTxSubCommand<'a> {
                     ),
                 SubCommand::with_name("verify")
                     .about("Verify a mock transaction in local")
-                    .arg(arg_tx_file.clone()),
+                    .arg(arg_tx_file.clone())
+                    .arg(
+                        Arg::with_name("symbolize")
+                            .long("symbolize")
+                            .help("When verification failed, map the program counter to the nearest function symbol of the script binaries (unstripped binary only)"),
+                    )
+                    .arg(
+                        Arg::with_name("pc")
+                            .long("pc")
+                            .takes_value(true)
+                            .requires("symbolize")
+                            .validator(|input| parse_pc(&input).map(|_| ()))
+                            .help("The program counter to symbolize (default: extracted from the error message)"),
+                    )
+                    .arg(
+                        Arg::with_name("profile")
+                            .long("profile")
+                            .help("Verify script groups one by one, report the cycles consumed by each lock/type script group and the size its binary occupies once loaded into VM (loaded_size, not the peak memory)"),
+                    ),
+                SubCommand::with_name("debug")
+                    .about("Dump a script group of the mock transaction for ckb-debugger (and run it by --debugger)")
+                    .arg(arg_tx_file.clone())
+                    .arg(
+                        Arg::with_name("script-group-type")
+                            .long("script-group-type")
+                            .takes_value(true)
+                            .required(true)
+                            .possible_values(&["lock", "type"])
+                            .help("Type of the script group to debug"),
+                    )
+                    .arg(
+                        Arg::with_name("script-hash")
+                            .long("script-hash")
+                            .takes_value(true)
+                            .required_unless("input-index")
+                            .conflicts_with("input-index")
+                            .validator(|input| FixedHashParser::<H256>::default().validate(input))
+                            .help("Script hash of the script group to debug"),
+                    )
+                    .arg(
+                        Arg::with_name("input-index")
+                            .long("input-index")
+                            .takes_value(true)
+                            .validator(|input| FromStrParser::<usize>::default().validate(input))
+                            .help("Debug the script group of this input (lock or type script, by --script-group-type)"),
+                    )
+                    .arg(
+                        arg_output_file
+                            .clone()
+                            .required(true)
+                            .help("The dump file for ckb-debugger --tx-file (format: json)"),
+                    )
+                    .arg(
+                        Arg::with_name("debugger")
+                            .long("debugger")
+                            .takes_value(true)
+                            .help("Path of ckb-debugger, run the script group by it after dumped"),
+                    ),
                 SubCommand::with_name("send")
                     .about("Complete then send a transaction")
-                    .arg(arg_tx_file.clone()),
+                    .arg(arg_tx_file.clone())
+                    .arg(arg::wait_for_commit())
+                    .arg(arg::yes())
+                    .arg(arg::force_fee()),
+                SubCommand::with_name("record")
+                    .about("Record a transaction on chain (with all the input cells, cell deps and header deps) as a mock transaction, to verify it offline")
+                    .arg(
+                        Arg::with_name("tx-hash")
+                            .long("tx-hash")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FixedHashParser::<H256>::default().validate(input))
+                            .help("Hash of the transaction"),
+                    )
+                    .arg(arg_output_file.clone().help("Save to a output file")),
             ])
     }
 }
@@ -93,7 +178,7 @@ impl<'a> CliSubCommand for MockTxSubCommand<'a> {
         matches: &ArgMatches,
         format: OutputFormat,
         color: bool,
-        _debug: bool,
+        debug: bool,
     ) -> Result<String, String> {
         let genesis_info = get_genesis_info(&mut self.genesis_info, self.rpc_client)?;
 
@@ -133,21 +218,26 @@ impl<'a> CliSubCommand for MockTxSubCommand<'a> {
             Ok((mock_tx, cycle))
         };
 
-        let output_tx = |m: &ArgMatches, mock_tx: &MockTransaction| -> Result<(), String> {
+        // Write the mock transaction to `--output-file`, or return it to be printed as part of
+        // the command output
+        let output_tx = |m: &ArgMatches,
+                         mock_tx: &MockTransaction|
+         -> Result<Option<serde_json::Value>, String> {
             let output_opt: Option<PathBuf> =
                 FilePathParser::new(false).from_matches_opt(m, "output-file", false)?;
-            let output_color = output_opt.as_ref().map(|_| false).unwrap_or(color);
-            let output_content =
-                ReprMockTransaction::from(mock_tx.clone()).render(OutputFormat::Json, output_color);
+            let repr_tx = ReprMockTransaction::from(mock_tx.clone());
             if let Some(output) = output_opt {
+                let output_content = repr_tx.render(OutputFormat::Json, false);
                 let mut out_file = fs::File::create(output).map_err(|err| err.to_string())?;
                 out_file
                     .write_all(output_content.as_bytes())
                     .map_err(|err| err.to_string())?;
+                Ok(None)
             } else {
-                println!("{}", output_content);
+                serde_json::to_value(&repr_tx)
+                    .map(Some)
+                    .map_err(|err| err.to_string())
             }
-            Ok(())
         };
 
         match matches.subcommand() {
@@ -205,21 +295,56 @@ impl<'a> CliSubCommand for MockTxSubCommand<'a> {
                     let mut helper = MockTransactionHelper::new(&mut mock_tx);
                     helper.fill_deps(&genesis_info, |_| unreachable!())?;
                 }
-                output_tx(m, &mock_tx)?;
-
-                Ok(String::new())
+                match output_tx(m, &mock_tx)? {
+                    Some(value) => Ok(value.render(format, color)),
+                    None => Ok(String::new()),
+                }
             }
             ("complete", Some(m)) => {
                 let (mock_tx, _cycle) = complete_tx(m, true, false)?;
-                output_tx(m, &mock_tx)?;
+                let mock_tx_value = output_tx(m, &mock_tx)?;
                 let tx_hash: H256 = mock_tx.core_transaction().hash().unpack();
-                let resp = serde_json::json!({
+                let mut resp = serde_json::json!({
                     "tx-hash": tx_hash,
                 });
+                if let Some(value) = mock_tx_value {
+                    resp["mock-tx"] = value;
+                }
                 Ok(resp.render(format, color))
             }
             ("verify", Some(m)) => {
-                let (mock_tx, cycle) = complete_tx(m, false, true)?;
+                let (mut mock_tx, _) = complete_tx(m, false, false)?;
+                if m.is_present("profile") {
+                    let groups = {
+                        let loader = Loader {
+                            rpc_client: self.rpc_client,
+                        };
+                        MockTransactionHelper::new(&mut mock_tx)
+                            .verify_script_groups(u64::max_value(), loader)?
+                    };
+                    return Ok(profile(&mock_tx, groups).render(format, color));
+                }
+                let result = {
+                    let loader = Loader {
+                        rpc_client: self.rpc_client,
+                    };
+                    MockTransactionHelper::new(&mut mock_tx).verify(u64::max_value(), loader)
+                };
+                let cycle = match result {
+                    Ok(cycle) => cycle,
+                    Err(err) if m.is_present("symbolize") => {
+                        let pc_opt = match m.value_of("pc") {
+                            Some(input) => Some(parse_pc(input)?),
+                            None => extract_pc(&err),
+                        };
+                        let symbols = match pc_opt {
+                            Some(pc) => symbolize(&mock_tx, pc),
+                            None => vec!["No program counter found in error, try --pc".to_owned()],
+                        };
+                        return Err(format!("{}\n{}", err, symbols.join("\n")));
+                    }
+                    Err(err) => return Err(err),
+                };
                 let tx_hash: H256 = mock_tx.core_transaction().hash().unpack();
                 let resp = serde_json::json!({
                     "tx-hash": tx_hash,
@@ -227,13 +352,91 @@ impl<'a> CliSubCommand for MockTxSubCommand<'a> {
                 });
                 Ok(resp.render(format, color))
             }
+            ("debug", Some(m)) => {
+                let (mock_tx, _) = complete_tx(m, false, false)?;
+                let group_type = m.value_of("script-group-type").unwrap();
+                let input_index_opt: Option<usize> =
+                    FromStrParser::<usize>::default().from_matches_opt(m, "input-index", false)?;
+                let script_hash: H256 = match input_index_opt {
+                    Some(index) => {
+                        let mock_input = mock_tx
+                            .mock_info
+                            .inputs
+                            .get(index)
+                            .ok_or_else(|| format!("Input index out of bound: {}", index))?;
+                        let script = if group_type == "lock" {
+                            mock_input.output.lock()
+                        } else {
+                            mock_input
+                                .output
+                                .type_()
+                                .to_opt()
+                                .ok_or_else(|| format!("Input {} has no type script", index))?
+                        };
+                        script.calc_script_hash().unpack()
+                    }
+                    None => FixedHashParser::<H256>::default().from_matches(m, "script-hash")?,
+                };
+                let (inputs, outputs) = script_group(&mock_tx, group_type == "lock", &script_hash);
+                if inputs.is_empty() && outputs.is_empty() {
+                    return Err(format!(
+                        "No {} script group found by script hash: {:#x}",
+                        group_type, script_hash
+                    ));
+                }
+                output_tx(m, &mock_tx)?;
+
+                let output_file = m.value_of("output-file").unwrap();
+                let script_hash_str = format!("{:#x}", script_hash);
+                let debugger_args = vec![
+                    "--tx-file",
+                    output_file,
+                    "--script-group-type",
+                    group_type,
+                    "--script-hash",
+                    script_hash_str.as_str(),
+                ];
+                if let Some(debugger) = m.value_of("debugger") {
+                    let status = Command::new(debugger)
+                        .args(&debugger_args)
+                        .status()
+                        .map_err(|err| format!("Run {} failed: {}", debugger, err))?;
+                    if !status.success() {
+                        return Err(format!("{} exit with: {}", debugger, status));
+                    }
+                }
+                let resp = serde_json::json!({
+                    "script-group-type": group_type,
+                    "script-hash": script_hash,
+                    "inputs": inputs,
+                    "outputs": outputs,
+                    "debugger-command": format!("ckb-debugger {}", debugger_args.join(" ")),
+                });
+                Ok(resp.render(format, color))
+            }
             ("send", Some(m)) => {
                 let (mock_tx, _cycle) = complete_tx(m, false, true)?;
-                let resp = self
-                    .rpc_client
-                    .send_transaction(mock_tx.core_transaction().data().into())
-                    .call()
-                    .map_err(|err| format!("Send transaction error: {}", err))?;
+                TxSender::new(self.rpc_client, Some(genesis_info.clone()))
+                    .with_fee_guard(self.fee_guard)
+                    .send(m, mock_tx.core_transaction(), format, color, debug)
+            }
+            ("record", Some(m)) => {
+                let tx_hash: H256 =
+                    FixedHashParser::<H256>::default().from_matches(m, "tx-hash")?;
+                let mut loader = Loader {
+                    rpc_client: self.rpc_client,
+                };
+                let mock_tx = loader.record(tx_hash.clone())?;
+                let mock_tx_value = output_tx(m, &mock_tx)?;
+                let mut resp = serde_json::json!({
+                    "tx-hash": tx_hash,
+                    "inputs": mock_tx.mock_info.inputs.len(),
+                    "cell_deps": mock_tx.mock_info.cell_deps.len(),
+                    "header_deps": mock_tx.mock_info.header_deps.len(),
+                });
+                if let Some(value) = mock_tx_value {
+                    resp["mock-tx"] = value;
+                }
                 Ok(resp.render(format, color))
             }
             _ => Err(matches.usage().to_owned()),
@@ -241,10 +444,234 @@ impl<'a> CliSubCommand for MockTxSubCommand<'a> {
     }
 }
 
+fn parse_pc(input: &str) -> Result<u64, String> {
+    let hex = if input.starts_with("0x") || input.starts_with("0X") {
+        &input[2..]
+    } else {
+        input
+    };
+    u64::from_str_radix(hex, 16).map_err(|err| format!("Invalid pc {}: {}", input, err))
+}
+
+// All the lock scripts and type scripts which will be executed by the transaction
+fn tx_scripts(mock_tx: &MockTransaction) -> Vec<Script> {
+    let tx = mock_tx.core_transaction();
+    let mut scripts: Vec<Script> = Vec::new();
+    for mock_input in &mock_tx.mock_info.inputs {
+        scripts.push(mock_input.output.lock());
+        scripts.extend(mock_input.output.type_().to_opt());
+    }
+    for output in tx.outputs().into_iter() {
+        scripts.extend(output.type_().to_opt());
+    }
+    scripts
+}
+
+// The cell dep which contains the binary of the script
+fn find_binary<'a>(mock_tx: &'a MockTransaction, script: &Script) -> Option<&'a MockCellDep> {
+    let code_hash = script.code_hash();
+    let is_data = script.hash_type() == ScriptHashType::Data.into();
+    mock_tx.mock_info.cell_deps.iter().find(|dep| {
+        if is_data {
+            CellOutput::calc_data_hash(&dep.data) == code_hash
+        } else {
+            dep.output
+                .type_()
+                .to_opt()
+                .map(|type_script| type_script.calc_script_hash() == code_hash)
+                .unwrap_or(false)
+        }
+    })
+}
+
+// The input and output indices of a lock (or type) script group
+fn script_group(
+    mock_tx: &MockTransaction,
+    is_lock: bool,
+    script_hash: &H256,
+) -> (Vec<usize>, Vec<usize>) {
+    let matches = |script_opt: Option<Script>| {
+        script_opt
+            .map(|script| {
+                let hash: H256 = script.calc_script_hash().unpack();
+                &hash == script_hash
+            })
+            .unwrap_or(false)
+    };
+    let inputs = mock_tx
+        .mock_info
+        .inputs
+        .iter()
+        .enumerate()
+        .filter(|(_, mock_input)| {
+            if is_lock {
+                matches(Some(mock_input.output.lock()))
+            } else {
+                matches(mock_input.output.type_().to_opt())
+            }
+        })
+        .map(|(idx, _)| idx)
+        .collect::<Vec<_>>();
+    let outputs = if is_lock {
+        Vec::new()
+    } else {
+        mock_tx
+            .tx
+            .raw()
+            .outputs()
+            .into_iter()
+            .enumerate()
+            .filter(|(_, output)| matches(output.type_().to_opt()))
+            .map(|(idx, _)| idx)
+            .collect::<Vec<_>>()
+    };
+    (inputs, outputs)
+}
+
+/// Lookup the symbol of `pc` in every script binary (from cell deps) referenced by the transaction.
+/// The verifier does not tell which script group failed, so all candidates are reported.
+fn symbolize(mock_tx: &MockTransaction, pc: u64) -> Vec<String> {
+    let mut lines = vec![format!("Symbols of pc {:#x}:", pc)];
+    let mut seen = HashSet::new();
+    for script in tx_scripts(mock_tx) {
+        let is_data = script.hash_type() == ScriptHashType::Data.into();
+        if !seen.insert((script.code_hash(), is_data)) {
+            continue;
+        }
+        let binary = find_binary(mock_tx, &script);
+        let code_hash: H256 = script.code_hash().unpack();
+        let line = match binary.map(|dep| find_symbol(&dep.data, pc)) {
+            Some(Ok(Some(info))) => format!(
+                "  script code_hash {:#x}: <{}+{:#x}> (symbol at {:#x})",
+                code_hash, info.name, info.offset, info.address
+            ),
+            Some(Ok(None)) => format!(
+                "  script code_hash {:#x}: no symbol found (binary stripped?)",
+                code_hash
+            ),
+            Some(Err(err)) => format!("  script code_hash {:#x}: {}", code_hash, err),
+            None => format!(
+                "  script code_hash {:#x}: binary not found in cell deps",
+                code_hash
+            ),
+        };
+        lines.push(line);
+    }
+    lines
+}
+
+/// Render the cycles of every script group. `loaded_size` is the size of loadable ELF segments
+/// (null when the binary is not found or not a valid ELF), the VM does not report the peak memory.
+fn profile(mock_tx: &MockTransaction, groups: Vec<ScriptGroupCycle>) -> serde_json::Value {
+    let scripts = tx_scripts(mock_tx);
+    let total_cycle: u64 = groups.iter().map(|group| group.cycle).sum();
+    let groups = groups
+        .into_iter()
+        .map(|group| {
+            let size = scripts
+                .iter()
+                .find(|script| {
+                    let script_hash: H256 = script.calc_script_hash().unpack();
+                    script_hash == group.script_hash
+                })
+                .and_then(|script| find_binary(mock_tx, script))
+                .and_then(|dep| loaded_size(&dep.data).ok());
+            serde_json::json!({
+                "type": group.kind,
+                "script_hash": group.script_hash,
+                "code_hash": group.code_hash,
+                "inputs": group.input_indices,
+                "outputs": group.output_indices,
+                "cycle": group.cycle,
+                "loaded_size": size,
+            })
+        })
+        .collect::<Vec<_>>();
+    let tx_hash: H256 = mock_tx.core_transaction().hash().unpack();
+    serde_json::json!({
+        "tx-hash": tx_hash,
+        "cycle": total_cycle,
+        "groups": groups,
+    })
+}
+
 struct Loader<'a> {
     rpc_client: &'a mut HttpRpcClient,
 }
 
+impl<'a> Loader<'a> {
+    // The transaction and all the cells (dead or live) it depends on, the cells of dep groups
+    // are expanded as code deps
+    fn record(&mut self, tx_hash: H256) -> Result<MockTransaction, String> {
+        let tx: Transaction = self
+            .get_transaction(tx_hash.clone())?
+            .ok_or_else(|| format!("Transaction not found: {:#x}", tx_hash))?;
+        let mut mock_info = MockInfo::default();
+        for input in tx.raw().inputs().into_iter() {
+            let (output, data) = self.get_cell(input.previous_output())?;
+            mock_info.inputs.push(MockInput {
+                input,
+                output,
+                data,
+            });
+        }
+        let mut seen = HashSet::new();
+        for cell_dep in tx.raw().cell_deps().into_iter() {
+            let (output, data) = self.get_cell(cell_dep.out_point())?;
+            if cell_dep.dep_type() == DepType::DepGroup.into() {
+                let out_points = OutPointVec::from_slice(&data)
+                    .map_err(|err| format!("Invalid dep group data: {}", err))?;
+                for out_point in out_points.into_iter() {
+                    if !seen.insert(out_point.clone()) {
+                        continue;
+                    }
+                    let (output, data) = self.get_cell(out_point.clone())?;
+                    mock_info.cell_deps.push(MockCellDep {
+                        cell_dep: CellDep::new_builder()
+                            .out_point(out_point)
+                            .dep_type(DepType::Code.into())
+                            .build(),
+                        output,
+                        data,
+                    });
+                }
+            }
+            mock_info.cell_deps.push(MockCellDep {
+                cell_dep,
+                output,
+                data,
+            });
+        }
+        for header_hash in tx.raw().header_deps().into_iter() {
+            let header = self
+                .get_header(header_hash.unpack())?
+                .ok_or_else(|| format!("Header not found: {:#x}", header_hash))?;
+            mock_info.header_deps.push(header);
+        }
+        Ok(MockTransaction { mock_info, tx })
+    }
+
+    fn get_transaction(&mut self, tx_hash: H256) -> Result<Option<Transaction>, String> {
+        self.rpc_client
+            .get_transaction(tx_hash)
+            .call()
+            .map(|tx_opt| tx_opt.0.map(|tx| tx.transaction.inner.into()))
+            .map_err(|err| err.to_string())
+    }
+
+    // The cell (live or spent) by the transaction which creates it
+    fn get_cell(&mut self, out_point: OutPoint) -> Result<(CellOutput, Bytes), String> {
+        let index: u32 = out_point.index().unpack();
+        self.get_transaction(out_point.tx_hash().unpack())?
+            .and_then(|tx| {
+                let output = tx.raw().outputs().get(index as usize)?;
+                let data = tx.outputs_data().get(index as usize)?;
+                Some((output, data.raw_data()))
+            })
+            .ok_or_else(|| format!("Cell not found: {:#x}-{}", out_point.tx_hash(), index))
+    }
+}
+
 impl<'a> MockResourceLoader for Loader<'a> {
     fn get_header(&mut self, hash: H256) -> Result<Option<HeaderView>, String> {
         self.rpc_client
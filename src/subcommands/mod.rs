@@ -1,8 +1,12 @@
 pub mod account;
+pub mod chain;
+pub mod index;
 pub mod mock_tx;
 pub mod rpc;
+pub mod server;
 #[cfg(unix)]
 pub mod tui;
+pub mod tx;
 pub mod util;
 pub mod wallet;
 
@@ -10,12 +14,16 @@ pub mod wallet;
 pub use self::tui::TuiSubCommand;
 
 pub use account::AccountSubCommand;
+pub use chain::ChainSubCommand;
+pub use index::IndexSubCommand;
 pub use mock_tx::MockTxSubCommand;
 pub use rpc::RpcSubCommand;
+pub use server::ServerSubCommand;
+pub use tx::TxSubCommand;
 pub use util::UtilSubCommand;
 pub use wallet::{
-    start_index_thread, IndexController, IndexRequest, IndexResponse, IndexThreadState,
-    WalletSubCommand,
+    start_index_thread, FeeGuard, IndexController, IndexRequest, IndexResponse, IndexSyncConfig,
+    IndexThreadState, TxSender, WalletSubCommand,
 };
 
 use clap::ArgMatches;
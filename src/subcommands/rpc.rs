@@ -1,19 +1,23 @@
 use ckb_jsonrpc_types::{
     BlockNumber, EpochNumber, OutPoint, Timestamp, Transaction, Uint32, Uint64,
 };
-use ckb_sdk::HttpRpcClient;
+use ckb_sdk::{HttpRpcClient, JsonU64, SubscriptionClient, SUBSCRIPTION_TOPICS};
 use ckb_types::H256;
 use clap::{App, Arg, ArgMatches, SubCommand};
 use ipnetwork::IpNetwork;
 use multiaddr::Multiaddr;
+use std::collections::HashMap;
 use std::fs;
+use std::io::{self, Write};
 use std::path::PathBuf;
 use std::time::Duration;
 
 use super::CliSubCommand;
 use crate::utils::arg_parser::{
-    ArgParser, DurationParser, FilePathParser, FixedHashParser, FromStrParser,
+    ArgParser, DurationParser, FilePathParser, FixedHashParser, FromStrParser, PeerIdParser,
 };
+use crate::utils::node_capabilities::{self, Feature};
+use crate::utils::other::ckb_string;
 use crate::utils::printer::{OutputFormat, Printable};
 
 pub struct RpcSubCommand<'a> {
@@ -58,6 +62,7 @@ impl<'a> RpcSubCommand<'a> {
             .long("peer-id")
             .takes_value(true)
             .required(true)
+            .validator(|input| PeerIdParser.validate(input))
             .help("Node's peer id");
 
         SubCommand::with_name("rpc")
@@ -76,6 +81,9 @@ impl<'a> RpcSubCommand<'a> {
                 SubCommand::with_name("get_cellbase_output_capacity_details")
                     .about("Get block header content by hash")
                     .arg(arg_hash.clone().help("Block hash")),
+                SubCommand::with_name("get_block_economic_state")
+                    .about("Get block economic state (issuance, miner reward and fee, unit: CKB) by hash")
+                    .arg(arg_hash.clone().help("Block hash")),
                 SubCommand::with_name("get_cells_by_lock_hash")
                     .about("Get cells by lock script hash")
                     .arg(arg_hash.clone().help("Lock hash"))
@@ -95,6 +103,7 @@ impl<'a> RpcSubCommand<'a> {
                             .required(true)
                             .help("To block number"),
                     ),
+                SubCommand::with_name("get_consensus").about("Get consensus parameters of the chain (epoch rewards in CKB)"),
                 SubCommand::with_name("get_current_epoch").about("Get current epoch information"),
                 SubCommand::with_name("get_epoch_by_number")
                     .about("Get epoch information by epoch number")
@@ -128,11 +137,39 @@ impl<'a> RpcSubCommand<'a> {
                             .long("with-data")
                             .help("Get live cell with data")
                     ),
+                SubCommand::with_name("get_fee_rate_statics")
+                    .about("Get mean and median fee rates (unit: CKB/KB) of recent blocks")
+                    .arg(
+                        Arg::with_name("target")
+                            .long("target")
+                            .takes_value(true)
+                            .validator(|input| FromStrParser::<u64>::default().validate(input))
+                            .help("Count of recent blocks to statistics (default is decided by the node)")
+                    ),
                 SubCommand::with_name("get_tip_block_number").about("Get tip block number"),
                 SubCommand::with_name("get_tip_header").about("Get tip header"),
                 SubCommand::with_name("get_transaction")
                     .about("Get transaction content by transaction hash")
                     .arg(arg_hash.clone().help("Tx hash")),
+                SubCommand::with_name("subscribe")
+                    .about("Subscribe topics from the raw TCP subscription endpoint of the node (WebSocket is not supported), print events as json lines until interrupted")
+                    .arg(
+                        Arg::with_name("address")
+                            .long("address")
+                            .takes_value(true)
+                            .required(true)
+                            .help("The raw TCP subscription address of the node ([rpc] tcp_listen_address in ckb.toml, not ws_listen_address), example: tcp://127.0.0.1:18114")
+                    )
+                    .arg(
+                        Arg::with_name("topic")
+                            .long("topic")
+                            .takes_value(true)
+                            .multiple(true)
+                            .number_of_values(1)
+                            .required(true)
+                            .possible_values(SUBSCRIPTION_TOPICS)
+                            .help("The topic to subscribe (can be repeated)")
+                    ),
                 // [Indexer]
                 SubCommand::with_name("deindex_lock_hash")
                     .arg(arg_hash.clone().help("Lock script hash"))
@@ -279,6 +316,33 @@ impl<'a> CliSubCommand for RpcSubCommand<'a> {
                     .map_err(|err| err.to_string())?;
                 Ok(resp.render(format, color))
             }
+            ("get_block_economic_state", Some(m)) => {
+                node_capabilities::require(self.rpc_client, Feature::BlockEconomicState)?;
+                let hash: H256 = FixedHashParser::<H256>::default().from_matches(m, "hash")?;
+
+                let resp = self
+                    .rpc_client
+                    .get_block_economic_state(hash)
+                    .call()
+                    .map_err(|err| err.to_string())?;
+                let resp = resp.0.map(|state| {
+                    serde_json::json!({
+                        "issuance": {
+                            "primary": ckb_string(state.issuance.primary.value()),
+                            "secondary": ckb_string(state.issuance.secondary.value()),
+                        },
+                        "miner_reward": {
+                            "primary": ckb_string(state.miner_reward.primary.value()),
+                            "secondary": ckb_string(state.miner_reward.secondary.value()),
+                            "committed": ckb_string(state.miner_reward.committed.value()),
+                            "proposal": ckb_string(state.miner_reward.proposal.value()),
+                        },
+                        "txs_fee": ckb_string(state.txs_fee.value()),
+                        "finalized_at": state.finalized_at,
+                    })
+                });
+                Ok(resp.render(format, color))
+            }
             ("get_cells_by_lock_hash", Some(m)) => {
                 let lock_hash: H256 = FixedHashParser::<H256>::default().from_matches(m, "hash")?;
                 let from_number: u64 = FromStrParser::<u64>::default().from_matches(m, "from")?;
@@ -295,6 +359,31 @@ impl<'a> CliSubCommand for RpcSubCommand<'a> {
                     .map_err(|err| err.to_string())?;
                 Ok(resp.render(format, color))
             }
+            ("get_consensus", _) => {
+                node_capabilities::require(self.rpc_client, Feature::Consensus)?;
+                let consensus = self
+                    .rpc_client
+                    .get_consensus()
+                    .call()
+                    .map_err(|err| err.to_string())?;
+                let mut resp = serde_json::json!({
+                    "id": consensus.id,
+                    "genesis_hash": consensus.genesis_hash,
+                    "dao_type_hash": consensus.dao_type_hash,
+                    "secp256k1_blake160_sighash_all_type_hash": consensus.secp256k1_blake160_sighash_all_type_hash,
+                    "secp256k1_blake160_multisig_all_type_hash": consensus.secp256k1_blake160_multisig_all_type_hash,
+                    "initial_primary_epoch_reward": ckb_string(consensus.initial_primary_epoch_reward.value()),
+                    "secondary_epoch_reward": ckb_string(consensus.secondary_epoch_reward.value()),
+                    "primary_epoch_reward_halving_interval": consensus.primary_epoch_reward_halving_interval.value(),
+                    "epoch_duration_target": format!("{}s", consensus.epoch_duration_target.value()),
+                    "max_block_cycles": consensus.max_block_cycles.value(),
+                    "max_block_bytes": consensus.max_block_bytes.value(),
+                });
+                for (key, value) in consensus.others {
+                    resp[key] = value;
+                }
+                Ok(resp.render(format, color))
+            }
             ("get_current_epoch", _) => {
                 let resp = self
                     .rpc_client
@@ -349,6 +438,25 @@ impl<'a> CliSubCommand for RpcSubCommand<'a> {
                     .map_err(|err| err.to_string())?;
                 Ok(resp.render(format, color))
             }
+            ("get_fee_rate_statics", Some(m)) => {
+                node_capabilities::require(self.rpc_client, Feature::FeeRateStatics)?;
+                let target: Option<u64> =
+                    FromStrParser::<u64>::default().from_matches_opt(m, "target", false)?;
+
+                let resp = self
+                    .rpc_client
+                    .get_fee_rate_statics(target.map(JsonU64))
+                    .call()
+                    .map_err(|err| err.to_string())?;
+                // shannons/KB => CKB/KB
+                let resp = resp.0.map(|statics| {
+                    serde_json::json!({
+                        "mean": format!("{}/KB", ckb_string(statics.mean.value())),
+                        "median": format!("{}/KB", ckb_string(statics.median.value())),
+                    })
+                });
+                Ok(resp.render(format, color))
+            }
             ("get_tip_block_number", _) => {
                 let resp = self
                     .rpc_client
@@ -375,8 +483,30 @@ impl<'a> CliSubCommand for RpcSubCommand<'a> {
                     .map_err(|err| err.to_string())?;
                 Ok(resp.render(format, color))
             }
+            ("subscribe", Some(m)) => {
+                let address = m.value_of("address").unwrap();
+                let mut client = SubscriptionClient::connect(address)?;
+                let mut topics = HashMap::new();
+                for topic in m.values_of("topic").unwrap() {
+                    let subscription = client.subscribe(topic)?;
+                    topics.insert(subscription, topic.to_owned());
+                }
+                let stdout = io::stdout();
+                while let Some(event) = client.next_event()? {
+                    let line = serde_json::json!({
+                        "topic": topics.get(&event.subscription),
+                        "subscription": event.subscription,
+                        "event": event.result,
+                    });
+                    let mut handle = stdout.lock();
+                    writeln!(handle, "{}", line).map_err(|err| err.to_string())?;
+                    handle.flush().map_err(|err| err.to_string())?;
+                }
+                Err(String::from("Subscription closed by node"))
+            }
             // [Indexer]
             ("deindex_lock_hash", Some(m)) => {
+                node_capabilities::require(self.rpc_client, Feature::Indexer)?;
                 let hash: H256 = FixedHashParser::<H256>::default().from_matches(m, "hash")?;
 
                 self.rpc_client
@@ -386,6 +516,7 @@ impl<'a> CliSubCommand for RpcSubCommand<'a> {
                 Ok(String::from("DONE"))
             }
             ("get_live_cells_by_lock_hash", Some(m)) => {
+                node_capabilities::require(self.rpc_client, Feature::Indexer)?;
                 let hash: H256 = FixedHashParser::<H256>::default().from_matches(m, "hash")?;
                 let page: u64 = FromStrParser::<u64>::default().from_matches(m, "page")?;
                 let perpage: u8 = FromStrParser::<u8>::default().from_matches(m, "perpage")?;
@@ -404,6 +535,7 @@ impl<'a> CliSubCommand for RpcSubCommand<'a> {
                 Ok(resp.render(format, color))
             }
             ("get_transactions_by_lock_hash", Some(m)) => {
+                node_capabilities::require(self.rpc_client, Feature::Indexer)?;
                 let hash: H256 = FixedHashParser::<H256>::default().from_matches(m, "hash")?;
                 let page: u64 = FromStrParser::<u64>::default().from_matches(m, "page")?;
                 let perpage: u8 = FromStrParser::<u8>::default().from_matches(m, "perpage")?;
@@ -422,6 +554,7 @@ impl<'a> CliSubCommand for RpcSubCommand<'a> {
                 Ok(resp.render(format, color))
             }
             ("index_lock_hash", Some(m)) => {
+                node_capabilities::require(self.rpc_client, Feature::Indexer)?;
                 let hash: H256 = FixedHashParser::<H256>::default().from_matches(m, "hash")?;
                 let index_from: Option<u64> =
                     FromStrParser::<u64>::default().from_matches_opt(m, "index-from", false)?;
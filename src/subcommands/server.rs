@@ -0,0 +1,607 @@
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use ckb_jsonrpc_types::BlockNumber;
+use ckb_sdk::{wallet::KeyStore, ChangePolicy, GenesisInfo, HttpRpcClient};
+use ckb_types::{
+    core::{service::Request, BlockView},
+    H160,
+};
+use clap::{App, AppSettings, Arg, SubCommand};
+use serde_json::{json, Value};
+
+use super::{
+    ChainSubCommand, CliSubCommand, FeeGuard, IndexController, IndexRequest, RpcSubCommand,
+    UtilSubCommand, WalletSubCommand,
+};
+use crate::utils::{
+    arg_parser::{ArgParser, DurationParser, FilePathParser, FixedHashParser},
+    config::ChangeStrategy,
+    other::{create_private_file, get_ckb_cli_dir, read_password},
+    policy,
+    printer::OutputFormat,
+    sign_history,
+};
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const COMMAND_ERROR: i64 = -32000;
+
+// The top level commands served, the method name of a request is one of them
+const METHODS: &[&str] = &["rpc", "util", "chain", "wallet"];
+// The subcommands which sign arbitrary data by the unlocked accounts
+const SIGNING_COMMANDS: &[(&str, &str)] = &[("util", "sign-message")];
+
+/// The arguments prompting on the terminal, which would block the server (it serves the requests
+/// one by one) and write the prompt into the response stream
+const PROMPTING_ARGS: &[(&str, &str)] = &[
+    ("with-password", "unlock the account by --unlock"),
+    (
+        "override-policy",
+        "the signing policy can not be overridden by a request",
+    ),
+    (
+        "to-lock-script",
+        "the output lock script can not be confirmed by a request",
+    ),
+];
+
+const MAX_BODY_SIZE: usize = 1024 * 1024;
+const IO_TIMEOUT: Duration = Duration::from_secs(10);
+const TOKEN_FILE_NAME: &str = "server-token";
+
+type HttpError = (&'static str, String);
+
+struct HttpRequest {
+    method: String,
+    content_type: Option<String>,
+    origin: Option<String>,
+    authorization: Option<String>,
+    body: Vec<u8>,
+}
+
+pub struct ServerSubCommand<'a> {
+    rpc_client: &'a mut HttpRpcClient,
+    key_store: &'a mut KeyStore,
+    genesis_info: Option<GenesisInfo>,
+    url: String,
+    index_dir: PathBuf,
+    index_controller: IndexController,
+    change_strategy: ChangeStrategy,
+    change_policy: ChangePolicy,
+    fee_guard: FeeGuard,
+    indexer_url: Option<String>,
+    parser: App<'static, 'static>,
+    token: String,
+}
+
+impl<'a> ServerSubCommand<'a> {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        rpc_client: &'a mut HttpRpcClient,
+        key_store: &'a mut KeyStore,
+        url: String,
+        index_dir: PathBuf,
+        index_controller: IndexController,
+        change_strategy: ChangeStrategy,
+        change_policy: ChangePolicy,
+        fee_guard: FeeGuard,
+        indexer_url: Option<String>,
+    ) -> ServerSubCommand<'a> {
+        let parser = App::new("server")
+            .global_setting(AppSettings::NoBinaryName)
+            .global_setting(AppSettings::DisableVersion)
+            .subcommand(RpcSubCommand::subcommand())
+            .subcommand(UtilSubCommand::subcommand("util"))
+            .subcommand(ChainSubCommand::subcommand("chain"))
+            .subcommand(WalletSubCommand::subcommand());
+        ServerSubCommand {
+            rpc_client,
+            key_store,
+            genesis_info: None,
+            url,
+            index_dir,
+            index_controller,
+            change_strategy,
+            change_policy,
+            fee_guard,
+            indexer_url,
+            parser,
+            token: String::new(),
+        }
+    }
+
+    pub fn subcommand(name: &'static str) -> App<'static, 'static> {
+        SubCommand::with_name(name)
+            .about("Serve the rpc/util/chain/wallet commands as a JSON-RPC API over HTTP (method: command name, params: arguments)")
+            .arg(
+                Arg::with_name("listen")
+                    .long("listen")
+                    .takes_value(true)
+                    .default_value("127.0.0.1:8120")
+                    .help("Listen address of the JSON-RPC server"),
+            )
+            .arg(
+                Arg::with_name("unlock")
+                    .long("unlock")
+                    .takes_value(true)
+                    .multiple(true)
+                    .number_of_values(1)
+                    .validator(|input| FixedHashParser::<H160>::default().validate(input))
+                    .help("Unlock this account (lock arg) at startup to sign the transactions of requests, the password is read from terminal"),
+            )
+            .arg(
+                Arg::with_name("keep")
+                    .long("keep")
+                    .visible_alias("duration")
+                    .takes_value(true)
+                    .default_value("1h")
+                    .validator(|input| DurationParser.validate(input))
+                    .help("How long the unlocked accounts are kept unlocked, format: 30s, 15m, 1h"),
+            )
+            .arg(
+                Arg::with_name("token-file")
+                    .long("token-file")
+                    .takes_value(true)
+                    .validator(|input| FilePathParser::new(true).validate(input))
+                    .help("Read the bearer token of the requests from this file (the first line), a random token is generated into ~/.ckb-cli/server-token by default"),
+            )
+    }
+
+    pub fn start(&mut self, matches: &clap::ArgMatches) -> Result<String, String> {
+        let address = matches.value_of("listen").unwrap();
+        let lock_args: Vec<H160> =
+            FixedHashParser::<H160>::default().from_matches_vec(matches, "unlock")?;
+        let keep: Duration = DurationParser.from_matches(matches, "keep")?;
+        let token_file: Option<PathBuf> =
+            FilePathParser::new(true).from_matches_opt(matches, "token-file", false)?;
+        self.token = match token_file {
+            Some(path) => read_token(&path)?,
+            None => {
+                let path = get_ckb_cli_dir().join(TOKEN_FILE_NAME);
+                let token = generate_token(&path)?;
+                eprintln!(
+                    "Bearer token of the requests is written to {}",
+                    path.display()
+                );
+                token
+            }
+        };
+        for lock_arg in &lock_args {
+            let password = read_password(false, Some(&format!("Password of {:#x}", lock_arg)))?;
+            self.key_store
+                .timed_unlock(lock_arg, password.as_bytes(), keep)
+                .map_err(|err| format!("Unlock {:#x} failed: {}", lock_arg, err))?;
+        }
+
+        let listener = TcpListener::bind(address)
+            .map_err(|err| format!("Bind server address {} failed: {}", address, err))?;
+        let local_addr = listener.local_addr().map_err(|err| err.to_string())?;
+        // Keep the local index warm, no local sync when the cells are searched by ckb-indexer
+        if self.indexer_url.is_none() {
+            Request::call(
+                self.index_controller.sender(),
+                IndexRequest::UpdateUrl(self.url.clone()),
+            );
+        }
+        eprintln!("JSON-RPC server listening on http://{}", local_addr);
+        for stream in listener.incoming() {
+            // Erase the expired keys before serving the next request
+            for lock_arg in self.key_store.lock_expired() {
+                eprintln!("Account {:#x} is locked (expired)", lock_arg);
+            }
+            match stream {
+                Ok(stream) => {
+                    if let Err(err) = self.handle_connection(stream) {
+                        log::debug!("server request error: {}", err);
+                    }
+                }
+                Err(err) => log::debug!("server connection error: {}", err),
+            }
+        }
+        Ok(String::new())
+    }
+
+    fn handle_connection(&mut self, stream: TcpStream) -> Result<(), String> {
+        // A stalled client must not block the (single threaded) server forever
+        stream
+            .set_read_timeout(Some(IO_TIMEOUT))
+            .and_then(|_| stream.set_write_timeout(Some(IO_TIMEOUT)))
+            .map_err(|err| err.to_string())?;
+        let (status, body) = match read_http_request(&stream)
+            .and_then(|request| self.check_request(&request).map(|_| request))
+        {
+            Ok(request) => {
+                let response = match serde_json::from_slice::<Value>(&request.body) {
+                    Ok(Value::Array(requests)) => {
+                        Value::Array(requests.into_iter().map(|req| self.handle(req)).collect())
+                    }
+                    Ok(request) => self.handle(request),
+                    Err(err) => error_response(Value::Null, PARSE_ERROR, err.to_string()),
+                };
+                ("200 OK", response.to_string())
+            }
+            Err((status, message)) => (status, json!({ "error": message }).to_string()),
+        };
+        let response = format!(
+            "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        );
+        let mut writer = stream;
+        writer
+            .write_all(response.as_bytes())
+            .map_err(|err| err.to_string())
+    }
+
+    fn check_request(&self, request: &HttpRequest) -> Result<(), HttpError> {
+        if request.method != "POST" {
+            return Err(("405 Method Not Allowed", "Only POST is allowed".to_owned()));
+        }
+        // Browsers always send an Origin header on a cross-origin POST, refuse them to prevent
+        // the CSRF and DNS rebinding attacks from a web page.
+        if request.origin.is_some() {
+            return Err((
+                "403 Forbidden",
+                "Requests from browsers are not allowed".to_owned(),
+            ));
+        }
+        let expected = format!("Bearer {}", self.token);
+        let authorized = request
+            .authorization
+            .as_ref()
+            .map(|value| constant_time_eq(value.as_bytes(), expected.as_bytes()))
+            .unwrap_or(false);
+        if !authorized {
+            return Err((
+                "401 Unauthorized",
+                "Missing or invalid bearer token".to_owned(),
+            ));
+        }
+        let is_json = request
+            .content_type
+            .as_ref()
+            .and_then(|value| value.split(';').next())
+            .map(|mime| mime.trim().eq_ignore_ascii_case("application/json"))
+            .unwrap_or(false);
+        if !is_json {
+            return Err((
+                "415 Unsupported Media Type",
+                "Content-Type must be application/json".to_owned(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn handle(&mut self, request: Value) -> Value {
+        let (id, args) = match parse_call(&request) {
+            Ok(call) => call,
+            Err((id, code, message)) => return error_response(id, code, message),
+        };
+        match self.process(args) {
+            Ok(output) => {
+                // The output is rendered as json, keep the plain text outputs as string
+                let result =
+                    serde_json::from_str(&output).unwrap_or_else(|_| Value::String(output));
+                json!({"jsonrpc": "2.0", "id": id, "result": result})
+            }
+            Err(err) => error_response(id, COMMAND_ERROR, err),
+        }
+    }
+
+    fn process(&mut self, args: Vec<String>) -> Result<String, String> {
+        sign_history::set_command(&format!("[server] {}", args.join(" ")));
+        policy::clear_approvals();
+        check_args(&args)?;
+        let matches = self
+            .parser
+            .clone()
+            .get_matches_from_safe(args)
+            .map_err(|err| err.to_string())?;
+        let format = OutputFormat::Json;
+        match matches.subcommand() {
+            ("rpc", Some(m)) => {
+                RpcSubCommand::new(self.rpc_client).process(m, format, false, false)
+            }
+            ("util", Some(m)) => {
+                let genesis_info = self.genesis_info().ok();
+                UtilSubCommand::new(self.rpc_client, self.key_store, genesis_info)
+                    .process(m, format, false, false)
+            }
+            ("chain", Some(m)) => {
+                let genesis_info = self.genesis_info().ok();
+                ChainSubCommand::new(self.rpc_client, genesis_info).process(m, format, false, false)
+            }
+            ("wallet", Some(m)) => {
+                let genesis_info = self.genesis_info()?;
+                WalletSubCommand::new(
+                    self.rpc_client,
+                    self.key_store,
+                    Some(genesis_info),
+                    self.index_dir.clone(),
+                    self.index_controller.clone(),
+                    self.change_strategy.clone(),
+                    // Sign by the unlocked accounts
+                    true,
+                )
+                .with_change_policy(self.change_policy)
+                .with_indexer_url(self.indexer_url.as_ref().map(String::as_str))
+                // No terminal to confirm, the requests are authenticated by the bearer token
+                .with_confirmation(false)
+                .with_fee_guard(self.fee_guard)
+                .process(m, format, false, false)
+            }
+            _ => Err(matches.usage().to_owned()),
+        }
+    }
+
+    fn genesis_info(&mut self) -> Result<GenesisInfo, String> {
+        if self.genesis_info.is_none() {
+            let genesis_block: BlockView = self
+                .rpc_client
+                .get_block_by_number(BlockNumber::from(0))
+                .call()
+                .map_err(|err| err.to_string())?
+                .0
+                .expect("Can not get genesis block?")
+                .into();
+            self.genesis_info = Some(GenesisInfo::from_block(&genesis_block)?);
+        }
+        Ok(self.genesis_info.clone().unwrap())
+    }
+}
+
+/// Read a HTTP request, the body is limited to `MAX_BODY_SIZE` bytes.
+fn read_http_request(stream: &TcpStream) -> Result<HttpRequest, HttpError> {
+    let bad_request = |err: String| ("400 Bad Request", err);
+    let mut reader = BufReader::new(
+        stream
+            .try_clone()
+            .map_err(|err| bad_request(err.to_string()))?,
+    )
+    .take(MAX_BODY_SIZE as u64);
+    let mut request_line = String::new();
+    reader
+        .read_line(&mut request_line)
+        .map_err(|err| bad_request(err.to_string()))?;
+    let mut request = HttpRequest {
+        method: request_line
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_owned(),
+        content_type: None,
+        origin: None,
+        authorization: None,
+        body: Vec::new(),
+    };
+    let mut content_length = 0;
+    loop {
+        let mut line = String::new();
+        let size = reader
+            .read_line(&mut line)
+            .map_err(|err| bad_request(err.to_string()))?;
+        if size == 0 || line.trim().is_empty() {
+            break;
+        }
+        let mut parts = line.splitn(2, ':');
+        if let (Some(name), Some(value)) = (parts.next(), parts.next()) {
+            let name = name.trim().to_ascii_lowercase();
+            let value = value.trim().to_owned();
+            match name.as_str() {
+                "content-length" => {
+                    content_length = value
+                        .parse::<usize>()
+                        .map_err(|err| bad_request(format!("Invalid Content-Length: {}", err)))?;
+                }
+                "content-type" => request.content_type = Some(value),
+                "origin" => request.origin = Some(value),
+                "authorization" => request.authorization = Some(value),
+                _ => {}
+            }
+        }
+    }
+    if content_length > MAX_BODY_SIZE {
+        return Err((
+            "413 Payload Too Large",
+            format!("Request body exceeds {} bytes", MAX_BODY_SIZE),
+        ));
+    }
+    if request.method == "POST" {
+        // Read from the underlying reader, the headers are counted by the limit above
+        let mut reader = reader.into_inner();
+        request.body = vec![0u8; content_length];
+        reader
+            .read_exact(&mut request.body)
+            .map_err(|err| bad_request(err.to_string()))?;
+    }
+    Ok(request)
+}
+
+/// Refuse the arguments which touch the local files, sign arbitrary data or prompt on the
+/// terminal, the server only signs the transactions it builds.
+fn check_args(args: &[String]) -> Result<(), String> {
+    if let (Some(command), Some(subcommand)) = (args.get(0), args.get(1)) {
+        if SIGNING_COMMANDS
+            .iter()
+            .any(|(cmd, sub)| cmd == command && sub == subcommand)
+        {
+            return Err(format!(
+                "{} {} is not supported by the server",
+                command, subcommand
+            ));
+        }
+    }
+    for arg in args {
+        if !arg.starts_with("--") {
+            continue;
+        }
+        let name = arg[2..].splitn(2, '=').next().unwrap_or_default();
+        if let Some((_, hint)) = PROMPTING_ARGS
+            .iter()
+            .find(|(prompting, _)| *prompting == name)
+        {
+            return Err(format!(
+                "--{} is not supported by the server (it prompts on the terminal), {}",
+                name, hint
+            ));
+        }
+        if ["path", "input", "output"].contains(&name)
+            || name.ends_with("-path")
+            || name.ends_with("-file")
+            || name.ends_with("-dir")
+        {
+            return Err(format!(
+                "--{} is not supported by the server, the local files are not accessible",
+                name
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn read_token(path: &Path) -> Result<String, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|err| format!("Read token file {} failed: {}", path.display(), err))?;
+    let token = content.lines().next().unwrap_or_default().trim().to_owned();
+    if token.is_empty() {
+        return Err(format!("Token file {} is empty", path.display()));
+    }
+    Ok(token)
+}
+
+fn generate_token(path: &Path) -> Result<String, String> {
+    let token =
+        faster_hex::hex_string(&rand::random::<[u8; 32]>()).map_err(|err| err.to_string())?;
+    // A new file, so the mode of a token file left by an old version is not kept
+    if let Err(err) = fs::remove_file(path) {
+        if err.kind() != std::io::ErrorKind::NotFound {
+            return Err(format!(
+                "Remove token file {} failed: {}",
+                path.display(),
+                err
+            ));
+        }
+    }
+    let mut file = create_private_file(path)
+        .map_err(|err| format!("Create token file {} failed: {}", path.display(), err))?;
+    writeln!(file, "{}", token).map_err(|err| err.to_string())?;
+    Ok(token)
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Check a JSON-RPC request, returns the id and the command line arguments (method + params).
+fn parse_call(request: &Value) -> Result<(Value, Vec<String>), (Value, i64, String)> {
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let method = request
+        .get("method")
+        .and_then(Value::as_str)
+        .ok_or_else(|| (id.clone(), INVALID_REQUEST, "Missing method".to_owned()))?;
+    if !METHODS.contains(&method) {
+        return Err((
+            id,
+            METHOD_NOT_FOUND,
+            format!(
+                "Method not found: {}, expected one of: {}",
+                method,
+                METHODS.join(", ")
+            ),
+        ));
+    }
+    let mut args = vec![method.to_owned()];
+    match request.get("params") {
+        Some(Value::Array(params)) => {
+            for param in params {
+                match param {
+                    Value::String(param) => args.push(param.clone()),
+                    Value::Number(param) => args.push(param.to_string()),
+                    _ => {
+                        return Err((
+                            id,
+                            INVALID_PARAMS,
+                            "Params must be an array of strings (the command arguments)".to_owned(),
+                        ));
+                    }
+                }
+            }
+        }
+        None | Some(Value::Null) => {}
+        Some(_) => {
+            return Err((
+                id,
+                INVALID_PARAMS,
+                "Params must be an array of strings (the command arguments)".to_owned(),
+            ));
+        }
+    }
+    Ok((id, args))
+}
+
+fn error_response(id: Value, code: i64, message: String) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": {"code": code, "message": message},
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_call() {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 3,
+            "method": "wallet",
+            "params": ["get-capacity", "--address", "ckt1qyq", "--limit", 10],
+        });
+        let (id, args) = parse_call(&request).unwrap();
+        assert_eq!(id, json!(3));
+        assert_eq!(
+            args,
+            vec![
+                "wallet",
+                "get-capacity",
+                "--address",
+                "ckt1qyq",
+                "--limit",
+                "10"
+            ]
+        );
+
+        let request = json!({"id": 4, "method": "account", "params": ["list"]});
+        let (id, code, _) = parse_call(&request).unwrap_err();
+        assert_eq!(id, json!(4));
+        assert_eq!(code, METHOD_NOT_FOUND);
+
+        let request = json!({"id": 5, "method": "util", "params": {"a": 1}});
+        assert_eq!(parse_call(&request).unwrap_err().1, INVALID_PARAMS);
+    }
+
+    #[test]
+    fn test_check_args() {
+        let args = |line: &str| -> Vec<String> { line.split(' ').map(String::from).collect() };
+        assert!(check_args(&args("wallet get-capacity --address ckt1qyq")).is_ok());
+        assert!(check_args(&args("wallet transfer --max-inputs 3")).is_ok());
+        assert!(check_args(&args("wallet transfer --privkey-path /etc/passwd")).is_err());
+        assert!(check_args(&args("wallet transfer --privkey-path=/etc/passwd")).is_err());
+        assert!(check_args(&args("util key-info --path key")).is_err());
+        assert!(check_args(&args("chain export --output-dir /tmp")).is_err());
+        assert!(check_args(&args("util tx-hash --tx-file tx.json")).is_err());
+        assert!(check_args(&args("util sign-message --message abc")).is_err());
+        assert!(check_args(&args("wallet transfer --with-password")).is_err());
+        assert!(check_args(&args("wallet transfer --override-policy")).is_err());
+        assert!(check_args(&args("wallet transfer --to-lock-script=lock.json")).is_err());
+    }
+}
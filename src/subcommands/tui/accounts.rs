@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use ckb_index::{with_index_db, IndexDatabase};
+use ckb_jsonrpc_types::BlockNumber;
+use ckb_sdk::{Address, GenesisInfo, HttpRpcClient, NetworkType};
+use ckb_types::{
+    core::{EpochNumberWithFraction, HeaderView},
+    prelude::*,
+    H160, H256,
+};
+use ckb_util::RwLock;
+
+use crate::subcommands::wallet::{
+    is_cellbase, is_dao_cell, is_mature, is_plain_cell, Bucket, IndexController,
+};
+
+#[derive(Clone, Debug)]
+pub struct AccountBalance {
+    pub(crate) lock_arg: H160,
+    pub(crate) address: Address,
+    /// Plain cells (mature cellbase included)
+    pub(crate) spendable: Bucket,
+    /// Cellbase cells not mature yet
+    pub(crate) immature: Bucket,
+    /// NervosDAO cells (deposited and prepared)
+    pub(crate) dao: Bucket,
+}
+
+#[derive(Default)]
+pub struct AccountsState {
+    pub(crate) tip_hash: Option<H256>,
+    pub(crate) balances: Vec<AccountBalance>,
+    pub(crate) error: Option<String>,
+}
+
+/// Reload the balances of the keystore accounts from the local index database when a new block
+/// arrives.
+pub fn start_accounts_thread(
+    url: String,
+    index_dir: PathBuf,
+    index_controller: IndexController,
+    network_type: NetworkType,
+    genesis_info: GenesisInfo,
+    accounts: Vec<H160>,
+    state: Arc<RwLock<AccountsState>>,
+) {
+    let mut rpc_client = HttpRpcClient::from_uri(url.as_str());
+    thread::spawn(move || loop {
+        match rpc_client.get_tip_header().call() {
+            Ok(tip_header) => {
+                let tip_header: HeaderView = tip_header.into();
+                let tip_hash: H256 = tip_header.hash().unpack();
+                let changed = state.read().tip_hash.as_ref() != Some(&tip_hash);
+                if changed && index_controller.state().read().is_processing() {
+                    let result = load_balances(
+                        &mut rpc_client,
+                        &index_dir,
+                        network_type,
+                        &genesis_info,
+                        &accounts,
+                        &tip_header,
+                    );
+                    let mut state = state.write();
+                    state.tip_hash = Some(tip_hash);
+                    match result {
+                        Ok(balances) => {
+                            state.balances = balances;
+                            state.error = None;
+                        }
+                        Err(err) => state.error = Some(err),
+                    }
+                }
+            }
+            Err(err) => log::info!("Load tip header error: {}", err),
+        }
+        thread::sleep(Duration::from_secs(1));
+    });
+}
+
+fn load_balances(
+    rpc_client: &mut HttpRpcClient,
+    index_dir: &PathBuf,
+    network_type: NetworkType,
+    genesis_info: &GenesisInfo,
+    accounts: &[H160],
+    tip_header: &HeaderView,
+) -> Result<Vec<AccountBalance>, String> {
+    let dao_type_hash: H256 = genesis_info.dao_type_hash().unpack();
+    let genesis_hash: H256 = genesis_info.header().hash().unpack();
+    let mut lock_hashes = Vec::with_capacity(accounts.len());
+    for lock_arg in accounts {
+        let address = Address::from_lock_arg(lock_arg.as_bytes())?;
+        let lock_hash = genesis_info.lock_script(&address)?.calc_script_hash();
+        lock_hashes.push((lock_arg.clone(), address, lock_hash));
+    }
+    let account_cells = with_index_db(index_dir, genesis_hash, |backend, cf| {
+        let db = IndexDatabase::from_db(backend, cf, network_type, genesis_info.clone(), false)?;
+        let account_cells = lock_hashes
+            .into_iter()
+            .map(|(lock_arg, address, lock_hash)| {
+                let infos = db.get_live_cells_by_lock(lock_hash, None, |_, _| (false, true));
+                (lock_arg, address, infos)
+            })
+            .collect::<Vec<_>>();
+        Ok(account_cells)
+    })
+    .map_err(|err| format!("Open db error: {}", err))?;
+
+    // The maturity of cellbase cells is decided by the epoch of their blocks, the same check as
+    // `wallet get-capacity`
+    let tip_epoch = tip_header.epoch();
+    let mut block_epochs: HashMap<u64, EpochNumberWithFraction> = HashMap::new();
+    let mut balances = Vec::with_capacity(account_cells.len());
+    for (lock_arg, address, infos) in account_cells {
+        let mut balance = AccountBalance {
+            lock_arg,
+            address,
+            spendable: Bucket::default(),
+            immature: Bucket::default(),
+            dao: Bucket::default(),
+        };
+        for info in infos {
+            if is_plain_cell(&info) {
+                let immature = is_cellbase(&info) && {
+                    let cellbase_epoch = block_epoch(rpc_client, &mut block_epochs, info.number)?;
+                    !is_mature(cellbase_epoch, tip_epoch)
+                };
+                if immature {
+                    balance.immature.add(&info);
+                } else {
+                    balance.spendable.add(&info);
+                }
+            } else if is_dao_cell(&info, &dao_type_hash) {
+                balance.dao.add(&info);
+            }
+        }
+        balances.push(balance);
+    }
+    Ok(balances)
+}
+
+fn block_epoch(
+    rpc_client: &mut HttpRpcClient,
+    block_epochs: &mut HashMap<u64, EpochNumberWithFraction>,
+    number: u64,
+) -> Result<EpochNumberWithFraction, String> {
+    if let Some(epoch) = block_epochs.get(&number) {
+        return Ok(*epoch);
+    }
+    let epoch = rpc_client
+        .get_header_by_number(BlockNumber::from(number))
+        .call()
+        .map_err(|err| format!("Send get_header_by_number error: {}", err))?
+        .0
+        .map(|header| HeaderView::from(header).epoch())
+        .ok_or_else(|| format!("Header #{} not found", number))?;
+    block_epochs.insert(number, epoch);
+    Ok(epoch)
+}
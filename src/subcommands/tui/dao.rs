@@ -0,0 +1,198 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use ckb_index::{with_index_db, IndexDatabase, LiveCellInfo};
+use ckb_jsonrpc_types::BlockNumber;
+use ckb_sdk::{Address, GenesisInfo, HttpRpcClient, NetworkType};
+use ckb_types::{
+    bytes::Bytes,
+    core::{Capacity, EpochNumberWithFraction, HeaderView},
+    packed::CellOutput,
+    prelude::*,
+    H256,
+};
+use ckb_util::RwLock;
+
+use crate::subcommands::wallet::{
+    dao_unlock_epoch, epoch_value, is_dao_cell, is_dao_deposit, IndexController,
+};
+
+#[derive(Clone, Debug)]
+pub struct DaoCell {
+    pub(crate) out_point: (H256, u32),
+    pub(crate) capacity: u64,
+    pub(crate) prepared: bool,
+    pub(crate) deposit_number: u64,
+    pub(crate) interest: u64,
+    pub(crate) unlock_epoch: EpochNumberWithFraction,
+    // Epochs left before the cell can be withdrawn (deposited cells: the end of current period)
+    pub(crate) remaining_epochs: f64,
+}
+
+#[derive(Default)]
+pub struct DaoState {
+    // The account and the tip of current cells
+    pub(crate) address: Option<Address>,
+    pub(crate) tip_hash: Option<H256>,
+    pub(crate) cells: Vec<DaoCell>,
+    pub(crate) error: Option<String>,
+}
+
+/// Reload the NervosDAO cells of the active account when a new block arrives or the account
+/// is switched, the cells are searched in the local index database.
+pub fn start_dao_thread(
+    url: String,
+    index_dir: PathBuf,
+    index_controller: IndexController,
+    network_type: NetworkType,
+    genesis_info: GenesisInfo,
+    active: Arc<RwLock<Option<Address>>>,
+    state: Arc<RwLock<DaoState>>,
+) {
+    let mut rpc_client = HttpRpcClient::from_uri(url.as_str());
+    thread::spawn(move || loop {
+        let address = active.read().clone();
+        if let Some(address) = address {
+            match rpc_client.get_tip_header().call() {
+                Ok(tip_header) => {
+                    let tip_header: HeaderView = tip_header.into();
+                    let tip_hash: H256 = tip_header.hash().unpack();
+                    let changed = {
+                        let state = state.read();
+                        state.address.as_ref() != Some(&address)
+                            || state.tip_hash.as_ref() != Some(&tip_hash)
+                    };
+                    if changed && index_controller.state().read().is_processing() {
+                        let result = load_dao_cells(
+                            &mut rpc_client,
+                            &index_dir,
+                            network_type,
+                            &genesis_info,
+                            &address,
+                            &tip_header,
+                        );
+                        let mut state = state.write();
+                        state.address = Some(address);
+                        state.tip_hash = Some(tip_hash);
+                        match result {
+                            Ok(cells) => {
+                                state.cells = cells;
+                                state.error = None;
+                            }
+                            Err(err) => state.error = Some(err),
+                        }
+                    }
+                }
+                Err(err) => log::info!("Load tip header error: {}", err),
+            }
+        }
+        thread::sleep(Duration::from_secs(1));
+    });
+}
+
+fn load_dao_cells(
+    rpc_client: &mut HttpRpcClient,
+    index_dir: &PathBuf,
+    network_type: NetworkType,
+    genesis_info: &GenesisInfo,
+    address: &Address,
+    tip_header: &HeaderView,
+) -> Result<Vec<DaoCell>, String> {
+    let lock_hash = genesis_info.lock_script(address)?.calc_script_hash();
+    let dao_type_hash: H256 = genesis_info.dao_type_hash().unpack();
+    let genesis_hash: H256 = genesis_info.header().hash().unpack();
+    let infos: Vec<LiveCellInfo> = with_index_db(index_dir, genesis_hash, |backend, cf| {
+        let db = IndexDatabase::from_db(backend, cf, network_type, genesis_info.clone(), false)?;
+        Ok(db.get_live_cells_by_lock(lock_hash, None, |_, info| {
+            (false, is_dao_cell(info, &dao_type_hash))
+        }))
+    })
+    .map_err(|err| format!("Open db error: {}", err))?;
+
+    let mut cells = Vec::with_capacity(infos.len());
+    for info in infos {
+        let cell = rpc_client
+            .get_live_cell(info.out_point().into(), true)
+            .call()
+            .map_err(|err| format!("get_live_cell by RPC call failed: {}", err))?
+            .cell;
+        // Spent after the index updated
+        let (output, data) = match cell {
+            Some(cell) => (
+                CellOutput::from(cell.output),
+                cell.data
+                    .map(|data| data.content.into_bytes())
+                    .unwrap_or_default(),
+            ),
+            None => continue,
+        };
+        let prepared = !is_dao_deposit(&data);
+        // Not a valid prepared cell
+        if prepared && data.len() != 8 {
+            continue;
+        }
+        // A deposited cell accrues interest until now, a prepared cell until the prepare block
+        let (deposit_number, withdraw_header) = if prepared {
+            let mut number_bytes = [0u8; 8];
+            number_bytes.copy_from_slice(&data[0..8]);
+            (
+                u64::from_le_bytes(number_bytes),
+                get_header(rpc_client, info.number)?,
+            )
+        } else {
+            (info.number, tip_header.clone())
+        };
+        let deposit_header = get_header(rpc_client, deposit_number)?;
+        let max_withdraw = maximum_withdraw(&output, &data, &deposit_header, &withdraw_header)?;
+        let unlock_epoch = dao_unlock_epoch(deposit_header.epoch(), withdraw_header.epoch());
+        cells.push(DaoCell {
+            out_point: (info.tx_hash.clone(), info.tx_index),
+            capacity: info.capacity,
+            prepared,
+            deposit_number,
+            interest: max_withdraw.saturating_sub(info.capacity),
+            unlock_epoch,
+            remaining_epochs: epoch_value(unlock_epoch) - epoch_value(tip_header.epoch()),
+        });
+    }
+    Ok(cells)
+}
+
+fn get_header(rpc_client: &mut HttpRpcClient, number: u64) -> Result<HeaderView, String> {
+    rpc_client
+        .get_header_by_number(BlockNumber::from(number))
+        .call()
+        .map_err(|err| format!("Send get_header_by_number error: {}", err))?
+        .0
+        .map(Into::into)
+        .ok_or_else(|| format!("Header #{} not found", number))
+}
+
+// The accumulated rate (AR) is the second u64 of the dao field in header
+fn accumulated_rate(header: &HeaderView) -> u64 {
+    let dao = header.dao().raw_data();
+    let mut ar_bytes = [0u8; 8];
+    ar_bytes.copy_from_slice(&dao[8..16]);
+    u64::from_le_bytes(ar_bytes)
+}
+
+/// The free capacity of a NervosDAO cell grows by the accumulated rate between the deposit
+/// and the withdraw block, the occupied capacity earns nothing.
+fn maximum_withdraw(
+    output: &CellOutput,
+    data: &Bytes,
+    deposit_header: &HeaderView,
+    withdraw_header: &HeaderView,
+) -> Result<u64, String> {
+    let capacity: u64 = output.capacity().unpack();
+    let occupied = output
+        .occupied_capacity(Capacity::bytes(data.len()).map_err(|err| err.to_string())?)
+        .map_err(|err| err.to_string())?
+        .as_u64();
+    let free = u128::from(capacity.saturating_sub(occupied));
+    let withdraw = free * u128::from(accumulated_rate(withdraw_header))
+        / u128::from(accumulated_rate(deposit_header));
+    Ok(withdraw as u64 + occupied)
+}
@@ -1,4 +1,7 @@
+mod accounts;
+mod dao;
 mod state;
+mod txs;
 mod util;
 mod widgets;
 
@@ -21,16 +24,19 @@ use tui::{Frame, Terminal};
 // use chrono::{Local, DateTime, TimeZone};
 use ckb_index::{with_index_db, IndexDatabase};
 use ckb_jsonrpc_types::BlockNumber;
-use ckb_sdk::{GenesisInfo, HttpRpcClient, NetworkType, ONE_CKB};
+use ckb_sdk::{Address, GenesisInfo, HttpRpcClient, NetworkType, ONE_CKB};
 use ckb_types::{
     core::{service::Request, BlockView},
     prelude::*,
-    H256,
+    H160, H256,
 };
 
 use super::wallet::{IndexController, IndexRequest};
-use crate::utils::other::get_network_type;
+use crate::utils::other::{get_network_type, mask_url_password};
+use accounts::{start_accounts_thread, AccountsState};
+use dao::{start_dao_thread, DaoState};
 use state::{start_rpc_thread, State, SummaryInfo};
+use txs::{start_txs_thread, transaction_detail, TxsState};
 use util::{human_capacity, ts_now, App, Event, Events, TabsState};
 use widgets::List;
 
@@ -38,6 +44,8 @@ pub struct TuiSubCommand {
     url: String,
     index_dir: PathBuf,
     index_controller: IndexController,
+    address: Option<Address>,
+    accounts: Vec<H160>,
 }
 
 impl TuiSubCommand {
@@ -45,14 +53,23 @@ impl TuiSubCommand {
         url: String,
         index_dir: PathBuf,
         index_controller: IndexController,
+        address: Option<Address>,
     ) -> TuiSubCommand {
         TuiSubCommand {
             url,
             index_dir,
             index_controller,
+            address,
+            accounts: Vec::new(),
         }
     }
 
+    /// The keystore accounts listed in the accounts panel
+    pub fn with_accounts(mut self, accounts: Vec<H160>) -> TuiSubCommand {
+        self.accounts = accounts;
+        self
+    }
+
     pub fn start(self) -> Result<String, String> {
         let (network_type, genesis_info) = {
             let mut rpc_client = HttpRpcClient::from_uri(&self.url);
@@ -63,7 +80,7 @@ impl TuiSubCommand {
                 .map_err(|err| {
                     format!(
                         "Get genesis block from {} failed: {}\n[Hint]: you can use `ckb-cli --url <URL> tui` to override API url",
-                        self.url,
+                        mask_url_password(&self.url),
                         err.to_string()
                     )
                 })?
@@ -90,10 +107,57 @@ impl TuiSubCommand {
             IndexRequest::UpdateUrl(self.url.clone()),
         );
         start_rpc_thread(self.url.clone(), Arc::clone(&state));
+        // The account of NervosDAO panel, switched in accounts panel
+        let first_account = match self.accounts.first() {
+            Some(lock_arg) => Some(Address::from_lock_arg(lock_arg.as_bytes())?),
+            None => None,
+        };
+        let active = Arc::new(RwLock::new(self.address.clone().or(first_account)));
+        let dao_state = Arc::new(RwLock::new(DaoState::default()));
+        start_dao_thread(
+            self.url.clone(),
+            self.index_dir.clone(),
+            self.index_controller.clone(),
+            network_type,
+            genesis_info.clone(),
+            Arc::clone(&active),
+            Arc::clone(&dao_state),
+        );
+        let accounts_state = Arc::new(RwLock::new(AccountsState::default()));
+        start_accounts_thread(
+            self.url.clone(),
+            self.index_dir.clone(),
+            self.index_controller.clone(),
+            network_type,
+            genesis_info.clone(),
+            self.accounts.clone(),
+            Arc::clone(&accounts_state),
+        );
+        let txs_state = Arc::new(RwLock::new(TxsState::default()));
+        start_txs_thread(
+            self.url.clone(),
+            self.index_dir.clone(),
+            self.index_controller.clone(),
+            network_type,
+            genesis_info.clone(),
+            Arc::clone(&active),
+            Arc::clone(&txs_state),
+        );
         // App
         let mut app = App {
             menu_active: true,
-            tabs: TabsState::new(vec!["Summary", "Recent Blocks", "Peers", "Top Capacity"]),
+            tabs: TabsState::new(vec![
+                "Summary",
+                "Recent Blocks",
+                "Peers",
+                "Top Capacity",
+                "NervosDAO",
+                "Accounts",
+                "Transactions",
+            ]),
+            account_index: 0,
+            tx_index: 0,
+            tx_detail: None,
         };
 
         // Main loop
@@ -139,7 +203,11 @@ impl TuiSubCommand {
                         rect: body_chunks[1],
                     };
                     match app.tabs.index {
-                        0 => render_summary(&state.read(), self.url.as_str(), content_context),
+                        0 => render_summary(
+                            &state.read(),
+                            &mask_url_password(&self.url),
+                            content_context,
+                        ),
                         1 => render_blocks(&state.read(), content_context),
                         2 => render_peers(&state.read(), content_context),
                         3 => {
@@ -158,6 +226,27 @@ impl TuiSubCommand {
                                 content_context,
                             )
                         }
+                        4 => render_dao(
+                            &dao_state.read(),
+                            active.read().as_ref(),
+                            network_type,
+                            content_context,
+                        ),
+                        ACCOUNTS_TAB => render_accounts(
+                            &accounts_state.read(),
+                            app.account_index,
+                            active.read().as_ref(),
+                            network_type,
+                            content_context,
+                        ),
+                        TXS_TAB => render_txs(
+                            &state.read(),
+                            &txs_state.read(),
+                            &app,
+                            active.read().as_ref(),
+                            network_type,
+                            content_context,
+                        ),
                         _ => {}
                     }
                 })
@@ -177,13 +266,53 @@ impl TuiSubCommand {
                     Key::Down | Key::Char('j') => {
                         if app.menu_active {
                             app.tabs.next();
+                        } else if app.tabs.index == ACCOUNTS_TAB
+                            && app.account_index + 1 < accounts_state.read().balances.len()
+                        {
+                            app.account_index += 1;
+                        } else if app.tabs.index == TXS_TAB
+                            && app.tx_detail.is_none()
+                            && app.tx_index + 1 < txs_state.read().deltas.len()
+                        {
+                            app.tx_index += 1;
                         }
                     }
                     Key::Up | Key::Char('k') => {
                         if app.menu_active {
                             app.tabs.previous();
+                        } else if app.tabs.index == ACCOUNTS_TAB && app.account_index > 0 {
+                            app.account_index -= 1;
+                        } else if app.tabs.index == TXS_TAB
+                            && app.tx_detail.is_none()
+                            && app.tx_index > 0
+                        {
+                            app.tx_index -= 1;
+                        }
+                    }
+                    // Switch the active account
+                    Key::Char('\n') => {
+                        if !app.menu_active && app.tabs.index == ACCOUNTS_TAB {
+                            if let Some(balance) =
+                                accounts_state.read().balances.get(app.account_index)
+                            {
+                                *active.write() = Some(balance.address.clone());
+                            }
+                        }
+                        // Show the inputs/outputs of the selected transaction
+                        if !app.menu_active && app.tabs.index == TXS_TAB {
+                            let tx_hash = txs_state
+                                .read()
+                                .deltas
+                                .get(app.tx_index)
+                                .map(|delta| delta.tx_hash.clone());
+                            if let Some(tx_hash) = tx_hash {
+                                app.tx_detail = Some(transaction_detail(&self.url, &tx_hash));
+                            }
                         }
                     }
+                    Key::Esc | Key::Backspace => {
+                        app.tx_detail = None;
+                    }
                     _ => {}
                 },
                 Event::Tick => {}
@@ -193,6 +322,9 @@ impl TuiSubCommand {
     }
 }
 
+const ACCOUNTS_TAB: usize = 5;
+const TXS_TAB: usize = 6;
+
 struct RenderContext<'a, 'b, B: Backend> {
     block: Block<'a>,
     frame: &'a mut Frame<'b, B>,
@@ -548,3 +680,287 @@ fn render_top_capacity<B: Backend>(
     };
     List::new(lines.into_iter()).render(ctx.frame, top_capacity_chunks[0]);
 }
+
+fn render_dao<B: Backend>(
+    state: &DaoState,
+    active: Option<&Address>,
+    network_type: NetworkType,
+    ctx: RenderContext<B>,
+) {
+    ctx.block.clone().render(ctx.frame, ctx.rect);
+    let dao_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(2)
+        .constraints([Constraint::Percentage(100)].as_ref())
+        .split(ctx.rect);
+    let address = match active {
+        Some(address) => address,
+        None => {
+            let lines = vec![Text::raw(
+                "No account selected, switch in the accounts panel or start by `ckb-cli tui --address <address>`",
+            )];
+            List::new(lines.into_iter()).render(ctx.frame, dao_chunks[0]);
+            return;
+        }
+    };
+    // Still loading the cells of the account
+    if state.address.as_ref() != Some(address) {
+        let lines = vec![Text::raw(format!(
+            "Loading NervosDAO cells of {} (after the index is ready)",
+            address.to_string(network_type)
+        ))];
+        List::new(lines.into_iter()).render(ctx.frame, dao_chunks[0]);
+        return;
+    }
+
+    let (deposited, prepared): (Vec<_>, Vec<_>) =
+        state.cells.iter().partition(|cell| !cell.prepared);
+    let sum = |cells: &[&dao::DaoCell]| {
+        (
+            cells.iter().map(|cell| cell.capacity).sum::<u64>(),
+            cells.iter().map(|cell| cell.interest).sum::<u64>(),
+        )
+    };
+    let (deposited_capacity, deposited_interest) = sum(&deposited);
+    let (prepared_capacity, prepared_interest) = sum(&prepared);
+    let mut lines = vec![
+        Text::styled(
+            address.to_string(network_type),
+            Style::default().modifier(Modifier::BOLD),
+        ),
+        Text::raw(format!(
+            "  [deposited]: {} cells, {} (interest: {})",
+            deposited.len(),
+            human_capacity(deposited_capacity),
+            human_capacity(deposited_interest),
+        )),
+        Text::raw(format!(
+            "  [prepared ]: {} cells, {} (interest: {})",
+            prepared.len(),
+            human_capacity(prepared_capacity),
+            human_capacity(prepared_interest),
+        )),
+    ];
+    if let Some(err) = state.error.as_ref() {
+        lines.push(Text::styled(
+            format!("  [error    ]: {}", err),
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+    lines.push(Text::raw(""));
+    for cell in deposited.iter().chain(prepared.iter()) {
+        let (tx_hash, index) = &cell.out_point;
+        let (status, color) = if !cell.prepared {
+            ("deposited", Color::Blue)
+        } else if cell.remaining_epochs <= 0.0 {
+            ("withdrawable", Color::Green)
+        } else {
+            ("prepared", Color::Yellow)
+        };
+        // Unlock countdown, one epoch is about 4 hours
+        let countdown = if cell.remaining_epochs > 0.0 {
+            format!(
+                "{:.1} epochs (~{:.0} hours)",
+                cell.remaining_epochs,
+                cell.remaining_epochs * 4.0
+            )
+        } else {
+            "now".to_owned()
+        };
+        lines.push(Text::styled(
+            format!("{:x}-{} [{}]", tx_hash, index, status),
+            Style::default().fg(color).modifier(Modifier::BOLD),
+        ));
+        lines.push(Text::raw(format!(
+            "  [capacity]: {}.{} ({}), interest: {}",
+            cell.capacity / ONE_CKB,
+            cell.capacity % ONE_CKB,
+            human_capacity(cell.capacity),
+            human_capacity(cell.interest),
+        )));
+        lines.push(Text::raw(format!(
+            "  [deposit ]: block#{}, unlock at epoch {} ({}/{}), in {}",
+            cell.deposit_number,
+            cell.unlock_epoch.number(),
+            cell.unlock_epoch.index(),
+            cell.unlock_epoch.length(),
+            countdown,
+        )));
+    }
+    List::new(lines.into_iter()).render(ctx.frame, dao_chunks[0]);
+}
+
+fn render_accounts<B: Backend>(
+    state: &AccountsState,
+    selected: usize,
+    active: Option<&Address>,
+    network_type: NetworkType,
+    ctx: RenderContext<B>,
+) {
+    ctx.block.clone().render(ctx.frame, ctx.rect);
+    let accounts_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(2)
+        .constraints([Constraint::Percentage(100)].as_ref())
+        .split(ctx.rect);
+    let mut lines = Vec::new();
+    if let Some(err) = state.error.as_ref() {
+        lines.push(Text::styled(
+            format!("Load balances error: {}", err),
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+    if state.tip_hash.is_none() {
+        lines.push(Text::raw(
+            "Loading the balances of keystore accounts (after the index is ready)",
+        ));
+    } else if state.balances.is_empty() {
+        lines.push(Text::raw(
+            "No account in keystore, create one by `ckb-cli account new`",
+        ));
+    } else {
+        lines.push(Text::raw(
+            "Select by Up/Down, press Enter to switch the account of other panels",
+        ));
+        lines.push(Text::raw(""));
+    }
+    for (idx, balance) in state.balances.iter().enumerate() {
+        let is_active = active == Some(&balance.address);
+        let mut style = Style::default().modifier(Modifier::BOLD);
+        if idx == selected {
+            style = style.fg(Color::Black).bg(Color::LightYellow);
+        } else if is_active {
+            style = style.fg(Color::Green);
+        }
+        lines.push(Text::styled(
+            format!(
+                "{} {:#x} {}",
+                if is_active { "*" } else { " " },
+                balance.lock_arg,
+                balance.address.to_string(network_type)
+            ),
+            style,
+        ));
+        lines.push(Text::raw(format!(
+            "    [spendable]: {} ({} cells)",
+            human_capacity(balance.spendable.capacity),
+            balance.spendable.cells,
+        )));
+        lines.push(Text::raw(format!(
+            "    [immature ]: {} ({} cells)",
+            human_capacity(balance.immature.capacity),
+            balance.immature.cells,
+        )));
+        lines.push(Text::raw(format!(
+            "    [NervosDAO]: {} ({} cells)",
+            human_capacity(balance.dao.capacity),
+            balance.dao.cells,
+        )));
+    }
+    List::new(lines.into_iter()).render(ctx.frame, accounts_chunks[0]);
+}
+
+fn render_txs<B: Backend>(
+    state: &State,
+    txs_state: &TxsState,
+    app: &App,
+    active: Option<&Address>,
+    network_type: NetworkType,
+    ctx: RenderContext<B>,
+) {
+    ctx.block.clone().render(ctx.frame, ctx.rect);
+    let txs_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(2)
+        .constraints([Constraint::Length(2), Constraint::Min(1)].as_ref())
+        .split(ctx.rect);
+    let tx_pool_info = state
+        .tx_pool
+        .as_ref()
+        .map(|info| {
+            format!(
+                "pending={}, proposed={}, orphan={}",
+                info.pending.value(),
+                info.proposed.value(),
+                info.orphan.value(),
+            )
+        })
+        .unwrap_or_else(|| "<unknown>".to_string());
+    let pool_lines = vec![
+        Text::styled("TxPool ", Style::default().modifier(Modifier::BOLD)),
+        Text::raw(format!(": {}", tx_pool_info)),
+    ];
+    Paragraph::new(pool_lines.iter())
+        .alignment(Alignment::Left)
+        .render(ctx.frame, txs_chunks[0]);
+
+    if let Some(detail) = app.tx_detail.as_ref() {
+        let mut lines = vec![Text::styled(
+            "Press Esc to go back",
+            Style::default().fg(Color::Yellow),
+        )];
+        lines.extend(detail.iter().map(|line| Text::raw(line.clone())));
+        List::new(lines.into_iter()).render(ctx.frame, txs_chunks[1]);
+        return;
+    }
+
+    let address = match active {
+        Some(address) => address,
+        None => {
+            let lines = vec![Text::raw(
+                "No account selected, switch in the accounts panel or start by `ckb-cli tui --address <address>`",
+            )];
+            List::new(lines.into_iter()).render(ctx.frame, txs_chunks[1]);
+            return;
+        }
+    };
+    if txs_state.address.as_ref() != Some(address) {
+        let lines = vec![Text::raw(format!(
+            "Loading the transactions of {} (after the index is ready)",
+            address.to_string(network_type)
+        ))];
+        List::new(lines.into_iter()).render(ctx.frame, txs_chunks[1]);
+        return;
+    }
+
+    let mut lines = vec![Text::styled(
+        format!(
+            "{} (select by Up/Down, press Enter for inputs/outputs)",
+            address.to_string(network_type)
+        ),
+        Style::default().modifier(Modifier::BOLD),
+    )];
+    if let Some(err) = txs_state.error.as_ref() {
+        lines.push(Text::styled(
+            format!("Load transactions error: {}", err),
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+    for (idx, delta) in txs_state.deltas.iter().enumerate() {
+        let balance_change = delta.balance_change();
+        let (direction, color) = if balance_change > 0 {
+            ("in  ", Color::Green)
+        } else if balance_change < 0 {
+            ("out ", Color::Red)
+        } else {
+            ("self", Color::Blue)
+        };
+        let mut style = Style::default().fg(color);
+        if idx == app.tx_index {
+            style = style.fg(Color::Black).bg(Color::LightYellow);
+        }
+        let change = if balance_change < 0 {
+            format!("-{}", human_capacity(balance_change.abs() as u64))
+        } else {
+            format!("+{}", human_capacity(balance_change as u64))
+        };
+        lines.push(Text::styled(
+            format!(
+                "{} #{:<10} {:x} {}",
+                direction, delta.block_number, delta.tx_hash, change
+            ),
+            style,
+        ));
+    }
+    List::new(lines.into_iter()).render(ctx.frame, txs_chunks[1]);
+}
@@ -0,0 +1,108 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use ckb_index::{with_index_db, IndexDatabase, LockTxDelta};
+use ckb_sdk::{Address, GenesisInfo, HttpRpcClient, NetworkType};
+use ckb_types::{prelude::*, H256};
+use ckb_util::RwLock;
+use serde_json::json;
+
+use crate::subcommands::wallet::IndexController;
+use crate::utils::printer::{OutputFormat, Printable};
+
+// Recent transactions shown in the panel
+const MAX_RECENT_TXS: usize = 50;
+
+#[derive(Default)]
+pub struct TxsState {
+    // The account and the tip of current transactions
+    pub(crate) address: Option<Address>,
+    pub(crate) tip_hash: Option<H256>,
+    pub(crate) deltas: Vec<LockTxDelta>,
+    pub(crate) error: Option<String>,
+}
+
+/// Reload the recent committed transactions of the active account from the local index database
+/// when a new block arrives or the account is switched.
+pub fn start_txs_thread(
+    url: String,
+    index_dir: PathBuf,
+    index_controller: IndexController,
+    network_type: NetworkType,
+    genesis_info: GenesisInfo,
+    active: Arc<RwLock<Option<Address>>>,
+    state: Arc<RwLock<TxsState>>,
+) {
+    let mut rpc_client = HttpRpcClient::from_uri(url.as_str());
+    thread::spawn(move || loop {
+        let address = active.read().clone();
+        if let Some(address) = address {
+            match rpc_client.get_tip_header().call() {
+                Ok(tip_header) => {
+                    let tip_hash = tip_header.hash;
+                    let changed = {
+                        let state = state.read();
+                        state.address.as_ref() != Some(&address)
+                            || state.tip_hash.as_ref() != Some(&tip_hash)
+                    };
+                    if changed && index_controller.state().read().is_processing() {
+                        let result = load_deltas(&index_dir, network_type, &genesis_info, &address);
+                        let mut state = state.write();
+                        state.address = Some(address);
+                        state.tip_hash = Some(tip_hash);
+                        match result {
+                            Ok(deltas) => {
+                                state.deltas = deltas;
+                                state.error = None;
+                            }
+                            Err(err) => state.error = Some(err),
+                        }
+                    }
+                }
+                Err(err) => log::info!("Load tip header error: {}", err),
+            }
+        }
+        thread::sleep(Duration::from_secs(1));
+    });
+}
+
+fn load_deltas(
+    index_dir: &PathBuf,
+    network_type: NetworkType,
+    genesis_info: &GenesisInfo,
+    address: &Address,
+) -> Result<Vec<LockTxDelta>, String> {
+    let lock_hash = genesis_info.lock_script(address)?.calc_script_hash();
+    let genesis_hash: H256 = genesis_info.header().hash().unpack();
+    with_index_db(index_dir, genesis_hash, |backend, cf| {
+        let db = IndexDatabase::from_db(backend, cf, network_type, genesis_info.clone(), false)?;
+        Ok(db.get_lock_txs(lock_hash, MAX_RECENT_TXS))
+    })
+    .map_err(|err| format!("Open db error: {}", err))
+}
+
+/// The inputs and outputs of a transaction rendered by the printer (yaml), one line per item
+pub fn transaction_detail(url: &str, tx_hash: &H256) -> Vec<String> {
+    let mut rpc_client = HttpRpcClient::from_uri(url);
+    let content = match rpc_client.get_transaction(tx_hash.clone()).call() {
+        Ok(tx_opt) => match tx_opt.0 {
+            Some(tx_with_status) => {
+                let tx = tx_with_status.transaction;
+                json!({
+                    "tx_hash": tx.hash,
+                    "status": tx_with_status.tx_status.status,
+                    "block_hash": tx_with_status.tx_status.block_hash,
+                    "inputs": tx.inner.inputs,
+                    "outputs": tx.inner.outputs,
+                    "outputs_data": tx.inner.outputs_data,
+                })
+                .render(OutputFormat::Yaml, false)
+            }
+            None => format!("Transaction {:#x} not found", tx_hash),
+        },
+        Err(err) => format!("Send get_transaction error: {}", err),
+    };
+    content.lines().map(ToOwned::to_owned).collect()
+}
@@ -27,6 +27,11 @@ pub fn human_capacity(value: u64) -> String {
 pub struct App {
     pub(crate) menu_active: bool,
     pub(crate) tabs: TabsState,
+    // Selected row of the accounts panel
+    pub(crate) account_index: usize,
+    // Selected row of the transactions panel, and its inputs/outputs when opened
+    pub(crate) tx_index: usize,
+    pub(crate) tx_detail: Option<Vec<String>>,
 }
 
 pub struct TabsState {
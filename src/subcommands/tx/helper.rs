@@ -0,0 +1,1030 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+use ckb_jsonrpc_types::JsonBytes;
+use ckb_sdk::{
+    lock_group_message, recover_signer, secp_group_message, sign_lock_group, sign_secp_group,
+    LockWitness, MultisigConfig, WitnessTemplate,
+};
+use ckb_types::{
+    bytes::Bytes,
+    core::{Capacity, ScriptHashType, TransactionBuilder, TransactionView},
+    packed::{self, Byte32, CellDep, CellInput, CellOutput, OutPoint, Script, WitnessArgs},
+    prelude::*,
+    H160, H256,
+};
+use serde_derive::{Deserialize, Serialize};
+
+/// Version of the tx helper file, increased when the format changes incompatibly
+pub const TX_HELPER_VERSION: u32 = 1;
+
+/// How the inputs of a lock are unlocked
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum LockKind {
+    /// secp256k1 blake160 sighash-all, signed by the owner of the lock arg
+    Sighash,
+    /// secp256k1 blake160 multisig-all, signed by the threshold of the registered config
+    Multisig,
+    /// Non-standard lock signed by one secp256k1 signature in the layout of its witness template
+    Custom,
+    /// The witnesses are provided by other tools
+    Unknown,
+}
+
+/// The inputs of a lock script in the transaction
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LockGroup {
+    pub script: ckb_jsonrpc_types::Script,
+    pub kind: LockKind,
+    /// Input indexes
+    pub inputs: Vec<usize>,
+    /// Lock args of the signers needed by the lock (any threshold of them for multisig)
+    pub required_signers: Vec<H160>,
+    /// The lock arg of the multisig config (see `TxHelper::multisig_configs`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub multisig_config: Option<H160>,
+    /// The witness lock layout of a custom lock
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub witness_template: Option<WitnessTemplate>,
+}
+
+/// Code hashes (hash type: type) of the system locks recognized in the inputs
+pub struct LockCodeHashes {
+    pub sighash: Byte32,
+    pub multisig: Option<Byte32>,
+}
+
+/// Portable unsigned transaction passed between the parties (and machines) building and
+/// signing it: the transaction, the cells it spends, the lock groups with their signers and the
+/// signatures collected so far.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TxHelper {
+    pub version: u32,
+    pub transaction: ckb_jsonrpc_types::Transaction,
+    /// The cells spent by the inputs (same order), so the inputs can be signed offline
+    pub input_cells: Vec<ckb_jsonrpc_types::CellOutput>,
+    /// multisig lock arg => config, the multisig inputs are signed once their config is added
+    #[serde(default)]
+    pub multisig_configs: BTreeMap<H160, MultisigConfig>,
+    /// lock hash => lock group
+    pub locks: BTreeMap<H256, LockGroup>,
+    /// lock hash => signer lock arg => signature
+    pub signatures: BTreeMap<H256, BTreeMap<H160, JsonBytes>>,
+}
+
+impl Default for TxHelper {
+    fn default() -> TxHelper {
+        TxHelper {
+            version: TX_HELPER_VERSION,
+            transaction: TransactionBuilder::default().build().data().into(),
+            input_cells: Vec::new(),
+            multisig_configs: BTreeMap::default(),
+            locks: BTreeMap::default(),
+            signatures: BTreeMap::default(),
+        }
+    }
+}
+
+impl TxHelper {
+    pub fn load(path: &str) -> Result<TxHelper, String> {
+        let content = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        TxHelper::parse(&content, path)
+    }
+
+    /// Parse the content of a tx helper file, `source` is shown in the errors
+    pub fn parse(content: &str, source: &str) -> Result<TxHelper, String> {
+        let helper: TxHelper = serde_json::from_str(content)
+            .map_err(|err| format!("Parse tx helper file {} failed: {}", source, err))?;
+        if helper.version > TX_HELPER_VERSION {
+            return Err(format!(
+                "Tx helper file {} is version {}, only version {} and below are supported",
+                source, helper.version, TX_HELPER_VERSION
+            ));
+        }
+        Ok(helper)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(self).map_err(|err| err.to_string())?;
+        fs::write(path, content).map_err(|err| err.to_string())
+    }
+
+    /// Single line json, for transferring by QR codes
+    pub fn to_compact_json(&self) -> Result<String, String> {
+        serde_json::to_string(self).map_err(|err| err.to_string())
+    }
+
+    pub fn transaction(&self) -> TransactionView {
+        packed::Transaction::from(self.transaction.clone()).into_view()
+    }
+
+    /// The messages of the lock groups with signatures, taken before an edit (see
+    /// `update_transaction`)
+    fn signed_messages(&self) -> BTreeMap<H256, H256> {
+        self.signatures
+            .keys()
+            .filter_map(|lock_hash| {
+                self.sign_message(lock_hash)
+                    .ok()
+                    .map(|message| (lock_hash.clone(), message))
+            })
+            .collect()
+    }
+
+    /// Replace the transaction, the signatures of the lock groups whose message is changed (by
+    /// the transaction hash, their inputs or witnesses) since `old_messages` are invalidated
+    fn update_transaction(
+        &mut self,
+        transaction: TransactionView,
+        old_messages: &BTreeMap<H256, H256>,
+    ) {
+        self.transaction = transaction.data().into();
+        let affected = self
+            .signatures
+            .keys()
+            .filter(|lock_hash| {
+                self.sign_message(lock_hash).ok().as_ref() != old_messages.get(*lock_hash)
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        let count = affected
+            .iter()
+            .map(|lock_hash| self.signatures[lock_hash].len())
+            .sum::<usize>();
+        if count > 0 {
+            eprintln!(
+                "[WARNING]: The signing message of {} lock group(s) is changed, {} collected signature(s) are removed, please sign them again: {}",
+                affected.len(),
+                count,
+                affected
+                    .iter()
+                    .map(|lock_hash| format!("{:#x}", lock_hash))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+        for lock_hash in &affected {
+            self.signatures.remove(lock_hash);
+        }
+    }
+
+    /// Add an input spending `cell`, the lock group is recognized by the code hashes of the
+    /// system locks
+    pub fn add_input(
+        &mut self,
+        input: CellInput,
+        cell: CellOutput,
+        code_hashes: &LockCodeHashes,
+    ) -> Result<(), String> {
+        let transaction = self.transaction();
+        if transaction
+            .inputs()
+            .into_iter()
+            .any(|other| other.previous_output() == input.previous_output())
+        {
+            return Err("The cell is already an input".to_owned());
+        }
+        let old_messages = self.signed_messages();
+        let index = transaction.inputs().len();
+        let lock = cell.lock();
+        let lock_hash: H256 = lock.calc_script_hash().unpack();
+        let configs = &self.multisig_configs;
+        let group = self
+            .locks
+            .entry(lock_hash)
+            .or_insert_with(|| lock_group(&lock, code_hashes, configs));
+        group.inputs.push(index);
+        self.input_cells.push(cell.into());
+        self.update_transaction(
+            transaction.as_advanced_builder().input(input).build(),
+            &old_messages,
+        );
+        Ok(())
+    }
+
+    /// Replace the input spending `old_out_point` (e.g. spent by another transaction) by `input`
+    /// spending `cell`, the witness of the input is cleared. Returns the replaced cell.
+    pub fn replace_input(
+        &mut self,
+        old_out_point: &OutPoint,
+        input: CellInput,
+        cell: CellOutput,
+        code_hashes: &LockCodeHashes,
+    ) -> Result<CellOutput, String> {
+        let transaction = self.transaction();
+        let mut inputs = transaction.inputs().into_iter().collect::<Vec<_>>();
+        let index = inputs
+            .iter()
+            .position(|other| &other.previous_output() == old_out_point)
+            .ok_or_else(|| "Input not found in the transaction".to_owned())?;
+        if inputs
+            .iter()
+            .any(|other| other.previous_output() == input.previous_output())
+        {
+            return Err("The cell is already an input".to_owned());
+        }
+        let old_messages = self.signed_messages();
+        let old_cell: CellOutput = self.input_cells[index].clone().into();
+
+        let old_lock_hash: H256 = old_cell.lock().calc_script_hash().unpack();
+        let lock = cell.lock();
+        let lock_hash: H256 = lock.calc_script_hash().unpack();
+        if lock_hash != old_lock_hash {
+            if let Some(group) = self.locks.get_mut(&old_lock_hash) {
+                group.inputs.retain(|other| *other != index);
+                if group.inputs.is_empty() {
+                    self.locks.remove(&old_lock_hash);
+                }
+            }
+            let configs = &self.multisig_configs;
+            let group = self
+                .locks
+                .entry(lock_hash)
+                .or_insert_with(|| lock_group(&lock, code_hashes, configs));
+            group.inputs.push(index);
+            group.inputs.sort();
+        }
+        self.input_cells[index] = cell.into();
+
+        inputs[index] = input;
+        let mut witnesses = transaction.witnesses().into_iter().collect::<Vec<_>>();
+        if index < witnesses.len() {
+            witnesses[index] = Bytes::default().pack();
+        }
+        self.update_transaction(
+            transaction
+                .as_advanced_builder()
+                .set_inputs(inputs)
+                .set_witnesses(witnesses)
+                .build(),
+            &old_messages,
+        );
+        Ok(old_cell)
+    }
+
+    /// Register a multisig config, the inputs of its lock are recognized (also the ones added
+    /// before). Returns the lock arg of the config.
+    pub fn add_multisig_config(
+        &mut self,
+        config: MultisigConfig,
+        code_hashes: &LockCodeHashes,
+    ) -> H160 {
+        let lock_arg = config.hash160();
+        self.multisig_configs.insert(lock_arg.clone(), config);
+        for group in self.locks.values_mut() {
+            if group.kind == LockKind::Unknown {
+                let script: Script = group.script.clone().into();
+                let mut new_group = lock_group(&script, code_hashes, &self.multisig_configs);
+                new_group.inputs = group.inputs.clone();
+                *group = new_group;
+            }
+        }
+        lock_arg
+    }
+
+    pub fn add_output(&mut self, output: CellOutput, data: Bytes) {
+        let old_messages = self.signed_messages();
+        let transaction = self
+            .transaction()
+            .as_advanced_builder()
+            .output(output)
+            .output_data(data.pack())
+            .build();
+        self.update_transaction(transaction, &old_messages);
+    }
+
+    /// Remove an output (and its data), returns the removed output
+    pub fn remove_output(&mut self, index: usize) -> Result<CellOutput, String> {
+        let transaction = self.transaction();
+        let mut outputs = transaction.outputs().into_iter().collect::<Vec<_>>();
+        let mut outputs_data = transaction.outputs_data().into_iter().collect::<Vec<_>>();
+        if index >= outputs.len() {
+            return Err(format!(
+                "Output index out of range: {} >= {}",
+                index,
+                outputs.len()
+            ));
+        }
+        let old_messages = self.signed_messages();
+        let removed = outputs.remove(index);
+        outputs_data.remove(index);
+        self.update_transaction(
+            transaction
+                .as_advanced_builder()
+                .set_outputs(outputs)
+                .set_outputs_data(outputs_data)
+                .build(),
+            &old_messages,
+        );
+        Ok(removed)
+    }
+
+    /// Set the capacity of an output, it must cover the occupied capacity. Returns the old
+    /// capacity.
+    pub fn set_output_capacity(&mut self, index: usize, capacity: u64) -> Result<u64, String> {
+        let transaction = self.transaction();
+        let mut outputs = transaction.outputs().into_iter().collect::<Vec<_>>();
+        let data = transaction
+            .outputs_data()
+            .get(index)
+            .map(|data| data.raw_data())
+            .ok_or_else(|| format!("Output index out of range: {} >= {}", index, outputs.len()))?;
+        let output = outputs[index]
+            .clone()
+            .as_builder()
+            .capacity(capacity.pack())
+            .build();
+        let occupied = output
+            .occupied_capacity(Capacity::bytes(data.len()).map_err(|err| err.to_string())?)
+            .map_err(|err| err.to_string())?
+            .as_u64();
+        if capacity < occupied {
+            return Err(format!(
+                "Capacity of output #{} is less than its occupied capacity: {} < {} (shannons)",
+                index, capacity, occupied
+            ));
+        }
+        let old_capacity: u64 = outputs[index].capacity().unpack();
+        let old_messages = self.signed_messages();
+        outputs[index] = output;
+        self.update_transaction(
+            transaction
+                .as_advanced_builder()
+                .set_outputs(outputs)
+                .build(),
+            &old_messages,
+        );
+        Ok(old_capacity)
+    }
+
+    pub fn add_cell_dep(&mut self, cell_dep: CellDep) {
+        let transaction = self.transaction();
+        if transaction
+            .cell_deps()
+            .into_iter()
+            .all(|other| other != cell_dep)
+        {
+            let old_messages = self.signed_messages();
+            self.update_transaction(
+                transaction.as_advanced_builder().cell_dep(cell_dep).build(),
+                &old_messages,
+            );
+        }
+    }
+
+    /// Add a header dep if not added yet, returns its index in the header deps
+    pub fn add_header_dep(&mut self, header_hash: Byte32) -> usize {
+        let transaction = self.transaction();
+        let header_deps = transaction.header_deps();
+        if let Some(index) = header_deps
+            .into_iter()
+            .position(|other| other == header_hash)
+        {
+            return index;
+        }
+        let index = transaction.header_deps().len();
+        let old_messages = self.signed_messages();
+        self.update_transaction(
+            transaction
+                .as_advanced_builder()
+                .header_dep(header_hash)
+                .build(),
+            &old_messages,
+        );
+        index
+    }
+
+    /// Set the `input_type` field of the witness of an input (like the header dep index of a
+    /// NervosDAO withdrawing cell), the lock field is kept
+    pub fn set_input_type(&mut self, input_index: usize, input_type: Bytes) -> Result<(), String> {
+        let transaction = self.transaction();
+        if input_index >= transaction.inputs().len() {
+            return Err(format!("Input #{} not found", input_index));
+        }
+        let mut witnesses = transaction
+            .witnesses()
+            .into_iter()
+            .map(|witness| witness.raw_data())
+            .collect::<Vec<_>>();
+        while witnesses.len() <= input_index {
+            witnesses.push(Bytes::default());
+        }
+        let witness = if witnesses[input_index].is_empty() {
+            WitnessArgs::default()
+        } else {
+            WitnessArgs::from_slice(&witnesses[input_index]).map_err(|err| err.to_string())?
+        };
+        witnesses[input_index] = witness
+            .as_builder()
+            .input_type(Some(input_type).pack())
+            .build()
+            .as_bytes();
+        let old_messages = self.signed_messages();
+        self.update_transaction(
+            transaction
+                .as_advanced_builder()
+                .set_witnesses(witnesses.iter().map(Pack::pack).collect())
+                .build(),
+            &old_messages,
+        );
+        Ok(())
+    }
+
+    /// Inputs minus outputs, None when the outputs exceed the inputs
+    pub fn fee(&self) -> Option<u64> {
+        let input_capacity: u64 = self
+            .input_cells
+            .iter()
+            .map(|cell| cell.capacity.value())
+            .sum();
+        let output_capacity: u64 = self
+            .transaction()
+            .outputs()
+            .into_iter()
+            .map(|output| Unpack::<u64>::unpack(&output.capacity()))
+            .sum();
+        input_capacity.checked_sub(output_capacity)
+    }
+
+    /// The message signed by the signers of a lock group
+    pub fn sign_message(&self, lock_hash: &H256) -> Result<H256, String> {
+        let group = self.group(lock_hash)?;
+        match group.kind {
+            LockKind::Sighash => secp_group_message(&self.transaction(), &group.inputs),
+            LockKind::Multisig => lock_group_message(
+                &self.transaction(),
+                &group.inputs,
+                LockWitness::Multisig(self.multisig_config(group)?),
+            ),
+            LockKind::Custom => lock_group_message(
+                &self.transaction(),
+                &group.inputs,
+                LockWitness::Custom(witness_template(group)?),
+            ),
+            LockKind::Unknown => Err(format!("Lock {:#x} is not signed by ckb-cli", lock_hash)),
+        }
+    }
+
+    /// Sign the inputs of a non-standard lock by `signer`, the signature is put in the witness
+    /// lock by `template`. The signatures of the lock group are dropped.
+    pub fn set_witness_template(
+        &mut self,
+        lock_hash: &H256,
+        template: WitnessTemplate,
+        signer: H160,
+    ) -> Result<(), String> {
+        let group = self
+            .locks
+            .get_mut(lock_hash)
+            .ok_or_else(|| format!("Lock {:#x} not found in the transaction", lock_hash))?;
+        match group.kind {
+            LockKind::Custom | LockKind::Unknown => {}
+            kind => {
+                return Err(format!(
+                    "The witness layout of {:?} lock {:#x} can not be changed",
+                    kind, lock_hash
+                ))
+            }
+        }
+        group.kind = LockKind::Custom;
+        group.required_signers = vec![signer];
+        group.witness_template = Some(template);
+        self.signatures.remove(lock_hash);
+        Ok(())
+    }
+
+    /// The sighash lock groups `signer` is required by
+    pub fn groups_of_signer(&self, signer: &H160) -> Vec<H256> {
+        self.locks
+            .iter()
+            .filter(|(_, group)| group.required_signers.contains(signer))
+            .map(|(lock_hash, _)| lock_hash.clone())
+            .collect()
+    }
+
+    /// Add a signature after checking it is signed by a required signer of the lock group
+    pub fn add_signature(
+        &mut self,
+        lock_hash: &H256,
+        signer: &H160,
+        signature: Bytes,
+    ) -> Result<(), String> {
+        let group = self.group(lock_hash)?;
+        if !group.required_signers.contains(signer) {
+            return Err(format!(
+                "{:#x} is not a signer of lock {:#x}",
+                signer, lock_hash
+            ));
+        }
+        let message = self.sign_message(lock_hash)?;
+        if &recover_signer(&message, &signature)? != signer {
+            return Err(format!(
+                "Invalid signature of {:#x} for lock {:#x}",
+                signer, lock_hash
+            ));
+        }
+        self.signatures
+            .entry(lock_hash.clone())
+            .or_insert_with(BTreeMap::default)
+            .insert(signer.clone(), JsonBytes::from_bytes(signature));
+        Ok(())
+    }
+
+    /// The signers not signed yet of the lock groups not ready, by lock hash. A multisig group
+    /// is ready once the threshold (with the first n required signers) is reached.
+    pub fn missing_signers(&self) -> BTreeMap<H256, Vec<H160>> {
+        self.locks
+            .iter()
+            .filter_map(|(lock_hash, group)| {
+                let signed = self.signatures.get(lock_hash);
+                let is_signed = |signer: &H160| {
+                    signed
+                        .map(|signatures| signatures.contains_key(signer))
+                        .unwrap_or(false)
+                };
+                let is_ready = match group.kind {
+                    LockKind::Multisig => self
+                        .multisig_config(group)
+                        .map(|config| {
+                            let signers = config.sighash_addresses();
+                            signers.iter().filter(|signer| is_signed(signer)).count()
+                                >= config.threshold() as usize
+                                && signers[..config.require_first_n() as usize]
+                                    .iter()
+                                    .all(|signer| is_signed(signer))
+                        })
+                        .unwrap_or(false),
+                    _ => group
+                        .required_signers
+                        .iter()
+                        .all(|signer| is_signed(signer)),
+                };
+                if is_ready {
+                    return None;
+                }
+                let missing = group
+                    .required_signers
+                    .iter()
+                    .filter(|signer| !is_signed(signer))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                Some((lock_hash.clone(), missing))
+            })
+            .collect()
+    }
+
+    /// Add the signatures of another copy of the same transaction (signed on another machine),
+    /// returns the number of new signatures
+    pub fn merge_signatures(&mut self, other: &TxHelper) -> Result<usize, String> {
+        if other.transaction().hash() != self.transaction().hash() {
+            return Err("Not the same transaction".to_owned());
+        }
+        let mut added = 0;
+        for (lock_hash, signatures) in &other.signatures {
+            for (signer, signature) in signatures {
+                let is_new = self
+                    .signatures
+                    .get(lock_hash)
+                    .map(|signatures| !signatures.contains_key(signer))
+                    .unwrap_or(true);
+                if is_new {
+                    self.add_signature(lock_hash, signer, signature.clone().into_bytes())?;
+                    added += 1;
+                }
+            }
+        }
+        Ok(added)
+    }
+
+    /// The transaction with the witnesses of the signed lock groups, all the required
+    /// signatures must be collected. The witnesses of the unknown locks are kept.
+    pub fn build_transaction(&self) -> Result<TransactionView, String> {
+        if let Some((lock_hash, signers)) = self.missing_signers().into_iter().next() {
+            return Err(format!(
+                "Lock {:#x} is not signed by {}",
+                lock_hash,
+                signers
+                    .iter()
+                    .map(|signer| format!("{:#x}", signer))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+        let mut transaction = self.transaction();
+        for (lock_hash, group) in &self.locks {
+            match group.kind {
+                LockKind::Sighash => {
+                    let signature = self.signatures[lock_hash][&group.required_signers[0]]
+                        .clone()
+                        .into_bytes();
+                    transaction =
+                        sign_secp_group(&transaction, &group.inputs, |_| Ok(signature.clone()))?;
+                }
+                LockKind::Multisig => {
+                    let config = self.multisig_config(group)?;
+                    let signatures = self.signatures[lock_hash]
+                        .iter()
+                        .map(|(signer, signature)| (signer.clone(), signature.clone().into_bytes()))
+                        .collect::<Vec<_>>();
+                    let witness_lock = config.build_witness_lock(&signatures)?;
+                    transaction = sign_lock_group(
+                        &transaction,
+                        &group.inputs,
+                        LockWitness::Multisig(config),
+                        |_| Ok(witness_lock.clone()),
+                    )?;
+                }
+                LockKind::Custom => {
+                    let template = witness_template(group)?;
+                    let signature = &self.signatures[lock_hash][&group.required_signers[0]];
+                    let witness_lock = template.witness_lock(signature.as_bytes())?;
+                    transaction = sign_lock_group(
+                        &transaction,
+                        &group.inputs,
+                        LockWitness::Custom(template),
+                        |_| Ok(witness_lock.clone()),
+                    )?;
+                }
+                LockKind::Unknown => {}
+            }
+        }
+        Ok(transaction)
+    }
+
+    fn multisig_config(&self, group: &LockGroup) -> Result<&MultisigConfig, String> {
+        group
+            .multisig_config
+            .as_ref()
+            .and_then(|lock_arg| self.multisig_configs.get(lock_arg))
+            .ok_or_else(|| "Multisig config not found in the tx helper file".to_owned())
+    }
+
+    fn group(&self, lock_hash: &H256) -> Result<&LockGroup, String> {
+        self.locks
+            .get(lock_hash)
+            .ok_or_else(|| format!("Lock not found in the transaction: {:#x}", lock_hash))
+    }
+}
+
+fn witness_template(group: &LockGroup) -> Result<WitnessTemplate, String> {
+    group
+        .witness_template
+        .ok_or_else(|| "Witness template of the custom lock not found".to_owned())
+}
+
+fn lock_group(
+    lock: &Script,
+    code_hashes: &LockCodeHashes,
+    multisig_configs: &BTreeMap<H160, MultisigConfig>,
+) -> LockGroup {
+    let args = lock.args().raw_data();
+    let is_type = lock.hash_type() == ScriptHashType::Type.into();
+    let code_hash = lock.code_hash();
+    let mut group = LockGroup {
+        script: lock.clone().into(),
+        kind: LockKind::Unknown,
+        inputs: Vec::new(),
+        required_signers: Vec::new(),
+        multisig_config: None,
+        witness_template: None,
+    };
+    if is_type && code_hash == code_hashes.sighash && args.len() == 20 {
+        group.kind = LockKind::Sighash;
+        group.required_signers = vec![H160::from_slice(&args).unwrap()];
+    } else if is_type
+        && code_hashes.multisig.as_ref() == Some(&code_hash)
+        // The multisig lock arg, optionally followed by the since (8 bytes)
+        && (args.len() == 20 || args.len() == 28)
+    {
+        let lock_arg = H160::from_slice(&args[0..20]).unwrap();
+        if let Some(config) = multisig_configs.get(&lock_arg) {
+            group.kind = LockKind::Multisig;
+            group.required_signers = config.sighash_addresses().to_vec();
+            group.multisig_config = Some(lock_arg);
+        }
+    }
+    group
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ckb_sdk::{build_witness_with_key, Address, SECP256K1};
+
+    #[test]
+    fn test_sign_tx_helper() {
+        let secp_type_hash: Byte32 = H256::from([1u8; 32]).pack();
+        let privkey = secp256k1::SecretKey::from_slice(&[3u8; 32]).unwrap();
+        let pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, &privkey);
+        let signer = Address::from_pubkey(&pubkey).unwrap().hash().clone();
+        let sighash_lock = Script::new_builder()
+            .code_hash(secp_type_hash.clone())
+            .hash_type(ScriptHashType::Type.into())
+            .args(Bytes::from(signer.as_bytes().to_vec()).pack())
+            .build();
+        let other_lock = Script::new_builder()
+            .args(Bytes::from(vec![2]).pack())
+            .build();
+        let cell = |lock: &Script| {
+            CellOutput::new_builder()
+                .lock(lock.clone())
+                .capacity(Capacity::shannons(1000).pack())
+                .build()
+        };
+        let input = |index: u32| CellInput::new(OutPoint::new(Byte32::default(), index), 0);
+        let code_hashes = LockCodeHashes {
+            sighash: secp_type_hash.clone(),
+            multisig: Some(H256::from([2u8; 32]).pack()),
+        };
+
+        let mut helper = TxHelper::default();
+        helper
+            .add_input(input(0), cell(&sighash_lock), &code_hashes)
+            .unwrap();
+        helper
+            .add_input(input(1), cell(&other_lock), &code_hashes)
+            .unwrap();
+        helper
+            .add_input(input(2), cell(&sighash_lock), &code_hashes)
+            .unwrap();
+        assert!(helper
+            .add_input(input(2), cell(&sighash_lock), &code_hashes)
+            .is_err());
+        helper.add_output(cell(&other_lock), Bytes::new());
+        assert_eq!(helper.fee(), Some(2000));
+
+        let lock_hash: H256 = sighash_lock.calc_script_hash().unpack();
+        let group = &helper.locks[&lock_hash];
+        assert_eq!(group.kind, LockKind::Sighash);
+        assert_eq!(group.inputs, vec![0, 2]);
+        assert_eq!(helper.groups_of_signer(&signer), vec![lock_hash.clone()]);
+        assert!(helper.build_transaction().is_err());
+
+        let message = helper.sign_message(&lock_hash).unwrap();
+        let signature = build_witness_with_key(&privkey, &[message.as_bytes().to_vec()]);
+        // Signed the wrong message
+        assert!(helper
+            .add_signature(&lock_hash, &signer, signature)
+            .is_err());
+        let signature = serialize(&privkey, &message);
+        helper
+            .add_signature(&lock_hash, &signer, signature.clone())
+            .unwrap();
+        assert!(helper.missing_signers().is_empty());
+
+        let transaction = helper.build_transaction().unwrap();
+        let witness =
+            WitnessArgs::from_slice(&transaction.witnesses().get(0).unwrap().raw_data()).unwrap();
+        assert_eq!(witness.lock().to_opt().unwrap().raw_data(), signature);
+
+        // Changing the transaction invalidates the signatures
+        helper.add_output(cell(&other_lock), Bytes::new());
+        assert_eq!(helper.missing_signers().len(), 1);
+        assert_eq!(helper.add_header_dep(Byte32::default()), 0);
+        assert_eq!(helper.add_header_dep(H256::from([1u8; 32]).pack()), 1);
+        assert_eq!(helper.add_header_dep(Byte32::default()), 0);
+        helper
+            .set_input_type(2, Bytes::from(1u64.to_le_bytes().to_vec()))
+            .unwrap();
+        assert!(helper.set_input_type(3, Bytes::new()).is_err());
+        helper
+            .add_signature(
+                &lock_hash,
+                &signer,
+                serialize(&privkey, &helper.sign_message(&lock_hash).unwrap()),
+            )
+            .unwrap();
+
+        // The other lock is signed in its own witness layout
+        let other_hash: H256 = other_lock.calc_script_hash().unpack();
+        assert!(helper.sign_message(&other_hash).is_err());
+        let template = WitnessTemplate::new(100, 20).unwrap();
+        helper
+            .set_witness_template(&other_hash, template, signer.clone())
+            .unwrap();
+        assert!(helper
+            .set_witness_template(&lock_hash, template, signer.clone())
+            .is_err());
+        assert_eq!(helper.groups_of_signer(&signer).len(), 2);
+        let message = helper.sign_message(&other_hash).unwrap();
+        let signature = serialize(&privkey, &message);
+        helper
+            .add_signature(&other_hash, &signer, signature.clone())
+            .unwrap();
+        let transaction = helper.build_transaction().unwrap();
+        let witness =
+            WitnessArgs::from_slice(&transaction.witnesses().get(1).unwrap().raw_data()).unwrap();
+        let witness_lock = witness.lock().to_opt().unwrap().raw_data();
+        assert_eq!(witness_lock.len(), 100);
+        assert_eq!(&witness_lock[20..85], &signature[..]);
+    }
+
+    #[test]
+    fn test_multisig_tx_helper() {
+        let multisig_type_hash: Byte32 = H256::from([2u8; 32]).pack();
+        let code_hashes = LockCodeHashes {
+            sighash: H256::from([1u8; 32]).pack(),
+            multisig: Some(multisig_type_hash.clone()),
+        };
+        let privkeys = (1..=3u8)
+            .map(|n| secp256k1::SecretKey::from_slice(&[n; 32]).unwrap())
+            .collect::<Vec<_>>();
+        let signers = privkeys
+            .iter()
+            .map(|privkey| {
+                let pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, privkey);
+                Address::from_pubkey(&pubkey).unwrap().hash().clone()
+            })
+            .collect::<Vec<_>>();
+        let config = MultisigConfig::new(signers.clone(), 1, 2).unwrap();
+        let multisig_lock = Script::new_builder()
+            .code_hash(multisig_type_hash)
+            .hash_type(ScriptHashType::Type.into())
+            .args(Bytes::from(config.hash160().as_bytes().to_vec()).pack())
+            .build();
+        let cell = CellOutput::new_builder()
+            .lock(multisig_lock.clone())
+            .capacity(Capacity::shannons(1000).pack())
+            .build();
+        let lock_hash: H256 = multisig_lock.calc_script_hash().unpack();
+
+        let mut helper = TxHelper::default();
+        helper
+            .add_input(
+                CellInput::new(OutPoint::new(Byte32::default(), 0), 0),
+                cell.clone(),
+                &code_hashes,
+            )
+            .unwrap();
+        helper.add_output(cell, Bytes::new());
+        // Recognized once the config is added
+        assert_eq!(helper.locks[&lock_hash].kind, LockKind::Unknown);
+        helper.add_multisig_config(config.clone(), &code_hashes);
+        assert_eq!(helper.locks[&lock_hash].kind, LockKind::Multisig);
+        assert_eq!(
+            helper.groups_of_signer(&signers[2]),
+            vec![lock_hash.clone()]
+        );
+
+        // Signed on different machines
+        let message = helper.sign_message(&lock_hash).unwrap();
+        let mut copy_a = helper.clone();
+        let mut copy_b = helper.clone();
+        copy_a
+            .add_signature(&lock_hash, &signers[1], serialize(&privkeys[1], &message))
+            .unwrap();
+        copy_b
+            .add_signature(&lock_hash, &signers[2], serialize(&privkeys[2], &message))
+            .unwrap();
+        assert_eq!(helper.merge_signatures(&copy_a).unwrap(), 1);
+        assert_eq!(helper.merge_signatures(&copy_b).unwrap(), 1);
+        assert_eq!(helper.merge_signatures(&copy_b).unwrap(), 0);
+        // Threshold reached, but the first signer is required
+        assert_eq!(
+            helper.missing_signers()[&lock_hash],
+            vec![signers[0].clone()]
+        );
+        assert!(helper.build_transaction().is_err());
+
+        helper
+            .add_signature(&lock_hash, &signers[0], serialize(&privkeys[0], &message))
+            .unwrap();
+        assert!(helper.missing_signers().is_empty());
+        let transaction = helper.build_transaction().unwrap();
+        let witness =
+            WitnessArgs::from_slice(&transaction.witnesses().get(0).unwrap().raw_data()).unwrap();
+        let witness_lock = witness.lock().to_opt().unwrap().raw_data();
+        assert_eq!(witness_lock.len(), config.placeholder_witness_lock().len());
+
+        let mut other = helper.clone();
+        other.add_output(CellOutput::default(), Bytes::new());
+        assert!(helper.merge_signatures(&other).is_err());
+    }
+
+    #[test]
+    fn test_edit_tx_helper() {
+        let secp_type_hash: Byte32 = H256::from([1u8; 32]).pack();
+        let code_hashes = LockCodeHashes {
+            sighash: secp_type_hash.clone(),
+            multisig: None,
+        };
+        let privkeys = (3..=4u8)
+            .map(|n| secp256k1::SecretKey::from_slice(&[n; 32]).unwrap())
+            .collect::<Vec<_>>();
+        let signers = privkeys
+            .iter()
+            .map(|privkey| {
+                let pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, privkey);
+                Address::from_pubkey(&pubkey).unwrap().hash().clone()
+            })
+            .collect::<Vec<_>>();
+        let locks = signers
+            .iter()
+            .map(|signer| {
+                Script::new_builder()
+                    .code_hash(secp_type_hash.clone())
+                    .hash_type(ScriptHashType::Type.into())
+                    .args(Bytes::from(signer.as_bytes().to_vec()).pack())
+                    .build()
+            })
+            .collect::<Vec<_>>();
+        let lock_hashes = locks
+            .iter()
+            .map(|lock| lock.calc_script_hash().unpack())
+            .collect::<Vec<H256>>();
+        let cell = |lock: &Script, capacity: u64| {
+            CellOutput::new_builder()
+                .lock(lock.clone())
+                .capacity(Capacity::shannons(capacity).pack())
+                .build()
+        };
+        let input = |index: u32| CellInput::new(OutPoint::new(Byte32::default(), index), 0);
+        let sign_all = |helper: &mut TxHelper| {
+            for idx in 0..2 {
+                let message = helper.sign_message(&lock_hashes[idx]).unwrap();
+                helper
+                    .add_signature(
+                        &lock_hashes[idx],
+                        &signers[idx],
+                        serialize(&privkeys[idx], &message),
+                    )
+                    .unwrap();
+            }
+            assert!(helper.missing_signers().is_empty());
+        };
+
+        let mut helper = TxHelper::default();
+        helper
+            .add_input(input(0), cell(&locks[0], 100_000_000_000), &code_hashes)
+            .unwrap();
+        helper
+            .add_input(input(1), cell(&locks[1], 100_000_000_000), &code_hashes)
+            .unwrap();
+        helper.add_output(cell(&locks[1], 150_000_000_000), Bytes::new());
+        helper.add_output(cell(&locks[0], 49_000_000_000), Bytes::new());
+        sign_all(&mut helper);
+
+        // Only the witness of the first group is changed, the other signatures survive
+        helper
+            .set_input_type(0, Bytes::from(1u64.to_le_bytes().to_vec()))
+            .unwrap();
+        assert!(!helper.signatures.contains_key(&lock_hashes[0]));
+        assert!(helper.signatures.contains_key(&lock_hashes[1]));
+        assert_eq!(
+            helper.missing_signers().keys().collect::<Vec<_>>(),
+            vec![&lock_hashes[0]]
+        );
+        sign_all(&mut helper);
+
+        // All the groups sign the transaction hash
+        assert!(helper.set_output_capacity(1, 1000).is_err());
+        assert_eq!(
+            helper.set_output_capacity(1, 48_000_000_000),
+            Ok(49_000_000_000)
+        );
+        assert_eq!(helper.fee(), Some(2_000_000_000));
+        assert_eq!(helper.missing_signers().len(), 2);
+        sign_all(&mut helper);
+
+        assert!(helper.remove_output(2).is_err());
+        let removed = helper.remove_output(1).unwrap();
+        assert_eq!(removed.lock(), locks[0]);
+        assert_eq!(helper.transaction().outputs().len(), 1);
+        assert_eq!(helper.missing_signers().len(), 2);
+
+        // The group of the first signer is gone with its only input
+        assert!(helper
+            .replace_input(
+                &input(0).previous_output(),
+                input(1),
+                cell(&locks[1], 1000),
+                &code_hashes
+            )
+            .is_err());
+        let old_cell = helper
+            .replace_input(
+                &input(0).previous_output(),
+                input(2),
+                cell(&locks[1], 100_000_000_000),
+                &code_hashes,
+            )
+            .unwrap();
+        assert_eq!(old_cell.lock(), locks[0]);
+        assert!(!helper.locks.contains_key(&lock_hashes[0]));
+        assert_eq!(helper.locks[&lock_hashes[1]].inputs, vec![0, 1]);
+        assert!(helper
+            .transaction()
+            .witnesses()
+            .get(0)
+            .unwrap()
+            .raw_data()
+            .is_empty());
+        assert_eq!(helper.groups_of_signer(&signers[0]), Vec::<H256>::new());
+    }
+
+    fn serialize(privkey: &secp256k1::SecretKey, message: &H256) -> Bytes {
+        let message = secp256k1::Message::from_slice(message.as_bytes()).unwrap();
+        ckb_sdk::serialize_signature(&SECP256K1.sign_recoverable(&message, privkey))
+    }
+}
@@ -0,0 +1,981 @@
+mod helper;
+
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use ckb_jsonrpc_types::{BlockNumber, CellWithStatus};
+use ckb_sdk::{
+    check_output_capacities, recover_signer, serialize_signature,
+    wallet::{KeyStore, KeyStoreError},
+    Address, GenesisInfo, HttpRpcClient, MultisigConfig, Since, SinceType, WitnessTemplate,
+    SECP256K1,
+};
+use ckb_types::{
+    bytes::Bytes,
+    core::{Capacity, HeaderView},
+    packed::{CellDep, CellInput, CellOutput, OutPoint, Script},
+    prelude::*,
+    H160, H256,
+};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use rayon::prelude::*;
+
+use super::{
+    wallet::{dao_unlock_epoch, is_dao_deposit},
+    CliSubCommand, FeeGuard, TxSender,
+};
+use crate::utils::{
+    arg,
+    arg_parser::{
+        ArgParser, CapacityParser, DirPathParser, FilePathParser, FixedHashParser, FromStrParser,
+        HexParser, LabeledAddressParser, LabeledLockArgParser, OutPointParser, PrivkeyPathParser,
+        PrivkeyWrapper,
+    },
+    multisig_configs::MultisigConfigs,
+    other::{get_ckb_cli_dir, get_genesis_info, read_password},
+    policy::{
+        authorize_signer, check_policy, transaction_outflow, SignKind, SignRequest, SigningPolicy,
+    },
+    printer::{OutputFormat, Printable},
+    qr::{split_frames, FrameCollector, QrCode},
+    sign_history::record_digest,
+};
+use helper::{LockCodeHashes, TxHelper};
+
+pub struct TxSubCommand<'a> {
+    rpc_client: &'a mut HttpRpcClient,
+    key_store: &'a mut KeyStore,
+    genesis_info: Option<GenesisInfo>,
+    fee_guard: FeeGuard,
+}
+
+impl<'a> TxSubCommand<'a> {
+    pub fn new(
+        rpc_client: &'a mut HttpRpcClient,
+        key_store: &'a mut KeyStore,
+        genesis_info: Option<GenesisInfo>,
+    ) -> TxSubCommand<'a> {
+        TxSubCommand {
+            rpc_client,
+            key_store,
+            genesis_info,
+            fee_guard: FeeGuard::default(),
+        }
+    }
+
+    /// The limits of transaction fee checked before `send` (unless `--force-fee`)
+    pub fn with_fee_guard(mut self, fee_guard: FeeGuard) -> TxSubCommand<'a> {
+        self.fee_guard = fee_guard;
+        self
+    }
+
+    pub fn subcommand(name: &'static str) -> App<'static, 'static> {
+        let arg_tx_file = Arg::with_name("tx-file")
+            .long("tx-file")
+            .takes_value(true)
+            .required(true)
+            .validator(|input| FilePathParser::new(true).validate(input))
+            .help("Tx helper file (format: json)");
+        let arg_output_index = Arg::with_name("output-index")
+            .long("output-index")
+            .takes_value(true)
+            .required(true)
+            .validator(|input| FromStrParser::<usize>::default().validate(input))
+            .help("Index of the output");
+        let arg_change_index = Arg::with_name("change-index")
+            .long("change-index")
+            .takes_value(true)
+            .validator(|input| FromStrParser::<usize>::default().validate(input))
+            .help("Index of the change output re-balanced by the capacity difference (otherwise the fee changes)");
+        SubCommand::with_name(name)
+            .about("Build a transaction step by step in a tx helper file (the transaction, its input cells, the signers and the collected signatures), sign it on other machines and send it")
+            .subcommands(vec![
+                SubCommand::with_name("init")
+                    .about("Create an empty tx helper file")
+                    .arg(
+                        arg_tx_file
+                            .clone()
+                            .validator(|input| FilePathParser::new(false).validate(input)),
+                    ),
+                SubCommand::with_name("add-multisig-config")
+                    .about("Add a multisig config to the tx helper file, the inputs of its multisig lock can be signed by the signers of the config")
+                    .arg(
+                        Arg::with_name("multisig-lock-arg")
+                            .long("multisig-lock-arg")
+                            .takes_value(true)
+                            .required_unless("sighash-lock-arg")
+                            .validator(|input| FixedHashParser::<H160>::default().validate(input))
+                            .help("Lock argument of a multisig config created by `wallet multisig create-config`"),
+                    )
+                    .arg(
+                        Arg::with_name("sighash-lock-arg")
+                            .long("sighash-lock-arg")
+                            .takes_value(true)
+                            .multiple(true)
+                            .conflicts_with("multisig-lock-arg")
+                            .validator(|input| LabeledLockArgParser.validate(input))
+                            .help("Lock argument or label of a signer (can be repeated, order matters)"),
+                    )
+                    .arg(
+                        Arg::with_name("require-first-n")
+                            .long("require-first-n")
+                            .takes_value(true)
+                            .default_value("0")
+                            .validator(|input| FromStrParser::<u8>::default().validate(input))
+                            .help("The first n signers must sign"),
+                    )
+                    .arg(
+                        Arg::with_name("threshold")
+                            .long("threshold")
+                            .takes_value(true)
+                            .required_unless("multisig-lock-arg")
+                            .validator(|input| FromStrParser::<u8>::default().validate(input))
+                            .help("Number of signatures required"),
+                    )
+                    .arg(arg_tx_file.clone()),
+                SubCommand::with_name("add-input")
+                    .about("Add a live cell as input, the cell deps of the secp256k1 sighash (or multisig) lock and NervosDAO are added if needed, so are the header deps of withdrawing NervosDAO cells")
+                    .arg(
+                        Arg::with_name("tx-hash")
+                            .long("tx-hash")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FixedHashParser::<H256>::default().validate(input))
+                            .help("Transaction hash of the cell"),
+                    )
+                    .arg(
+                        Arg::with_name("index")
+                            .long("index")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FromStrParser::<u32>::default().validate(input))
+                            .help("Output index of the cell"),
+                    )
+                    .arg(arg_tx_file.clone()),
+                SubCommand::with_name("add-output")
+                    .about("Add an output to an address")
+                    .arg(arg::to_address().required(true))
+                    .arg(arg::capacity().required(true))
+                    .arg(arg::to_data())
+                    .arg(arg_tx_file.clone()),
+                SubCommand::with_name("replace-input")
+                    .about("Replace an input (e.g. spent by another transaction) by another live cell, the witness of the input is cleared")
+                    .arg(
+                        Arg::with_name("old-out-point")
+                            .long("old-out-point")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| OutPointParser.validate(input))
+                            .help("Out point of the input to replace, format: {tx-hash}-{index}"),
+                    )
+                    .arg(
+                        Arg::with_name("new-out-point")
+                            .long("new-out-point")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| OutPointParser.validate(input))
+                            .help("Out point of the new input cell, format: {tx-hash}-{index}"),
+                    )
+                    .arg(arg_change_index.clone())
+                    .arg(arg_tx_file.clone()),
+                SubCommand::with_name("remove-output")
+                    .about("Remove an output")
+                    .arg(arg_output_index.clone().help("Index of the output to remove"))
+                    .arg(arg_change_index.clone())
+                    .arg(arg_tx_file.clone()),
+                SubCommand::with_name("set-output-capacity")
+                    .about("Adjust the capacity of an output")
+                    .arg(arg_output_index.clone())
+                    .arg(arg::capacity().required(true))
+                    .arg(arg_change_index.clone())
+                    .arg(arg_tx_file.clone()),
+                SubCommand::with_name("set-witness-template")
+                    .about("Sign the inputs of a non-standard lock by a secp256k1 signature put at the given position of the witness lock")
+                    .arg(
+                        Arg::with_name("lock-hash")
+                            .long("lock-hash")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FixedHashParser::<H256>::default().validate(input))
+                            .help("Lock hash of the lock group"),
+                    )
+                    .arg(
+                        Arg::with_name("lock-size")
+                            .long("lock-size")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FromStrParser::<usize>::default().validate(input))
+                            .help("Size of the witness lock (zeroed as placeholder when signing)"),
+                    )
+                    .arg(
+                        Arg::with_name("signature-offset")
+                            .long("signature-offset")
+                            .takes_value(true)
+                            .default_value("0")
+                            .validator(|input| FromStrParser::<usize>::default().validate(input))
+                            .help("Position of the 65 bytes signature in the witness lock"),
+                    )
+                    .arg(
+                        Arg::with_name("signer")
+                            .long("signer")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| LabeledLockArgParser.validate(input))
+                            .help("Lock argument or label of the signer"),
+                    )
+                    .arg(arg_tx_file.clone()),
+                SubCommand::with_name("info")
+                    .about("Show the transaction, fee, lock groups and the missing signatures of a tx helper file")
+                    .arg(arg_tx_file.clone()),
+                SubCommand::with_name("sign-inputs")
+                    .about("Sign the inputs of the lock groups the account is a signer of, the signatures are printed (or added to the file by --add-signatures)")
+                    .arg(arg::privkey_path().required_unless("from-account"))
+                    .arg(arg::from_account().required_unless("privkey-path"))
+                    .arg(
+                        Arg::with_name("add-signatures")
+                            .long("add-signatures")
+                            .help("Add the signatures to the tx helper file"),
+                    )
+                    .arg(arg::override_policy())
+                    .arg(arg_tx_file.clone()),
+                SubCommand::with_name("add-signature")
+                    .about("Add a signature (printed by `tx sign-inputs` on another machine), the signer is recovered and checked against the lock group")
+                    .arg(
+                        Arg::with_name("lock-hash")
+                            .long("lock-hash")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FixedHashParser::<H256>::default().validate(input))
+                            .help("Lock hash of the lock group"),
+                    )
+                    .arg(
+                        Arg::with_name("signature")
+                            .long("signature")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| HexParser.validate(input))
+                            .help("The recoverable signature (65 bytes)"),
+                    )
+                    .arg(arg_tx_file.clone()),
+                SubCommand::with_name("merge")
+                    .about("Merge the signatures of other copies of the tx helper file (signed on other machines), every signature is verified")
+                    .arg(
+                        Arg::with_name("from-tx-file")
+                            .long("from-tx-file")
+                            .takes_value(true)
+                            .required(true)
+                            .multiple(true)
+                            .validator(|input| FilePathParser::new(true).validate(input))
+                            .help("The tx helper file to merge from (can be repeated)"),
+                    )
+                    .arg(arg_tx_file.clone()),
+                SubCommand::with_name("export-qr")
+                    .about("Show the tx helper file as QR code frames (animated in terminal, or PNG files by --output-dir), for signing on an air-gapped machine")
+                    .arg(
+                        Arg::with_name("output-dir")
+                            .long("output-dir")
+                            .takes_value(true)
+                            .validator(|input| DirPathParser::default().validate(input))
+                            .help("Write the frames as PNG files (frame-001.png, ...) into the directory instead of showing them in terminal"),
+                    )
+                    .arg(
+                        Arg::with_name("chunk-size")
+                            .long("chunk-size")
+                            .takes_value(true)
+                            .default_value("400")
+                            .validator(|input| FromStrParser::<usize>::default().validate(input))
+                            .help("Max bytes of the tx helper file in one frame (the smaller, the easier to scan)"),
+                    )
+                    .arg(
+                        Arg::with_name("interval-ms")
+                            .long("interval-ms")
+                            .takes_value(true)
+                            .default_value("800")
+                            .validator(|input| FromStrParser::<u64>::default().validate(input))
+                            .help("Time each frame is shown in terminal (unit: milliseconds)"),
+                    )
+                    .arg(
+                        Arg::with_name("repeat")
+                            .long("repeat")
+                            .takes_value(true)
+                            .default_value("5")
+                            .validator(|input| FromStrParser::<u32>::default().validate(input))
+                            .help("Times all the frames are shown in terminal"),
+                    )
+                    .arg(arg_tx_file.clone()),
+                SubCommand::with_name("import-qr")
+                    .about("Rebuild a tx helper file from the scanned QR code frames (one frame text per line from --frames-file or stdin, in any order)")
+                    .arg(
+                        Arg::with_name("frames-file")
+                            .long("frames-file")
+                            .takes_value(true)
+                            .validator(|input| FilePathParser::new(true).validate(input))
+                            .help("The scanned frames, one per line (default: stdin, until all the frames are read)"),
+                    )
+                    .arg(
+                        Arg::with_name("overwrite")
+                            .long("overwrite")
+                            .help("Overwrite the tx helper file if it exists"),
+                    )
+                    .arg(
+                        arg_tx_file
+                            .clone()
+                            .validator(|input| FilePathParser::new(false).validate(input)),
+                    ),
+                SubCommand::with_name("send")
+                    .about("Put the collected signatures into the witnesses, then send the transaction")
+                    .arg(arg_tx_file)
+                    .arg(arg::wait_for_commit())
+                    .arg(arg::yes())
+                    .arg(arg::force_fee()),
+            ])
+    }
+
+    // Works offline, the signers are shown by lock args
+    fn info(&self, helper: &TxHelper) -> serde_json::Value {
+        let transaction = helper.transaction();
+        let tx_hash: H256 = transaction.hash().unpack();
+        let locks = helper
+            .locks
+            .iter()
+            .map(|(lock_hash, group)| {
+                let signed = helper
+                    .signatures
+                    .get(lock_hash)
+                    .map(|signatures| signatures.keys().cloned().collect::<Vec<_>>())
+                    .unwrap_or_default();
+                let threshold = group
+                    .multisig_config
+                    .as_ref()
+                    .and_then(|lock_arg| helper.multisig_configs.get(lock_arg))
+                    .map(|config| config.threshold());
+                serde_json::json!({
+                    "lock_hash": lock_hash,
+                    "kind": group.kind,
+                    "inputs": group.inputs,
+                    "required_signers": group.required_signers,
+                    "threshold": threshold,
+                    "witness_template": group.witness_template,
+                    "signed_by": signed,
+                })
+            })
+            .collect::<Vec<_>>();
+        serde_json::json!({
+            "tx_hash": tx_hash,
+            "inputs": transaction.inputs().len(),
+            "outputs": transaction.outputs().len(),
+            "fee": helper.fee(),
+            "locks": locks,
+            "ready": helper.missing_signers().is_empty(),
+        })
+    }
+
+    // The output and data of a live cell
+    fn live_cell(&mut self, out_point: &OutPoint) -> Result<(CellOutput, Bytes), String> {
+        let cell: CellWithStatus = self
+            .rpc_client
+            .get_live_cell(out_point.clone().into(), true)
+            .call()
+            .map_err(|err| format!("get_live_cell by RPC call failed: {}", err))?;
+        match (cell.status.as_str(), cell.cell) {
+            ("live", Some(info)) => Ok((
+                info.output.into(),
+                info.data
+                    .map(|data| data.content.into_bytes())
+                    .unwrap_or_default(),
+            )),
+            (status, _) => {
+                let tx_hash: H256 = out_point.tx_hash().unpack();
+                let index: u32 = out_point.index().unpack();
+                Err(format!(
+                    "Not a live cell: {:#x}-{}, status: {}",
+                    tx_hash, index, status
+                ))
+            }
+        }
+    }
+
+    fn lock_code_hashes(&mut self) -> Result<LockCodeHashes, String> {
+        let genesis_info = get_genesis_info(&mut self.genesis_info, self.rpc_client)?;
+        Ok(LockCodeHashes {
+            sighash: genesis_info.secp_type_hash().clone(),
+            multisig: genesis_info.multisig_type_hash().ok().cloned(),
+        })
+    }
+
+    // A NervosDAO cell is withdrawn with the header of the block it is in. A prepared cell also
+    // needs the deposit header (its index goes to the input type of the witness) and is spent
+    // since the end of its lock period, an absolute epoch. Returns the block hash of the cell,
+    // and the deposit block hash and the since of a prepared cell.
+    fn dao_withdraw_info(
+        &mut self,
+        tx_hash: &H256,
+        data: &[u8],
+    ) -> Result<(H256, Option<(H256, u64)>), String> {
+        let block_hash = self
+            .rpc_client
+            .get_transaction(tx_hash.clone())
+            .call()
+            .map_err(|err| format!("Send get_transaction error: {}", err))?
+            .0
+            .and_then(|tx_with_status| tx_with_status.tx_status.block_hash)
+            .ok_or_else(|| format!("Transaction not committed: {:#x}", tx_hash))?;
+        if is_dao_deposit(data) {
+            return Ok((block_hash, None));
+        }
+        if data.len() != 8 {
+            return Err(format!("Invalid NervosDAO cell data: {:?}", data));
+        }
+        let mut number_bytes = [0u8; 8];
+        number_bytes.copy_from_slice(data);
+        let deposit_number = u64::from_le_bytes(number_bytes);
+        let deposit_header: HeaderView = self
+            .rpc_client
+            .get_header_by_number(BlockNumber::from(deposit_number))
+            .call()
+            .map_err(|err| format!("Send get_header_by_number error: {}", err))?
+            .0
+            .map(Into::into)
+            .ok_or_else(|| format!("Header #{} not found", deposit_number))?;
+        let prepare_header: HeaderView = self
+            .rpc_client
+            .get_header(block_hash.clone())
+            .call()
+            .map_err(|err| format!("Send get_header error: {}", err))?
+            .0
+            .map(Into::into)
+            .ok_or_else(|| format!("Block header not found: {:#x}", block_hash))?;
+        let unlock_epoch = dao_unlock_epoch(deposit_header.epoch(), prepare_header.epoch());
+        let since = Since::new(
+            SinceType::EpochNumberWithFraction,
+            unlock_epoch.full_value(),
+            false,
+        );
+        Ok((
+            block_hash,
+            Some((deposit_header.hash().unpack(), since.value())),
+        ))
+    }
+
+    fn export_qr(&self, m: &ArgMatches) -> Result<serde_json::Value, String> {
+        let helper = TxHelper::load(m.value_of("tx-file").unwrap())?;
+        let chunk_size: usize = FromStrParser::<usize>::default().from_matches(m, "chunk-size")?;
+        if chunk_size == 0 {
+            return Err("--chunk-size must be greater than 0".to_owned());
+        }
+        let frames = split_frames(&helper.to_compact_json()?, chunk_size)
+            .iter()
+            .map(|frame| QrCode::encode(frame.as_bytes()))
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let output_dir_opt: Option<PathBuf> =
+            DirPathParser::default().from_matches_opt(m, "output-dir", false)?;
+        if let Some(output_dir) = output_dir_opt {
+            fs::create_dir_all(&output_dir).map_err(|err| err.to_string())?;
+            let mut files = Vec::new();
+            for (idx, frame) in frames.iter().enumerate() {
+                let path = output_dir.join(format!("frame-{:03}.png", idx + 1));
+                frame.write_png(&path, 4)?;
+                files.push(path.to_string_lossy().into_owned());
+            }
+            return Ok(serde_json::json!({
+                "frames": frames.len(),
+                "files": files,
+            }));
+        }
+
+        let interval: u64 = FromStrParser::<u64>::default().from_matches(m, "interval-ms")?;
+        let repeat: u32 = FromStrParser::<u32>::default().from_matches(m, "repeat")?;
+        let stdout = io::stdout();
+        for _ in 0..repeat {
+            for (idx, frame) in frames.iter().enumerate() {
+                let mut handle = stdout.lock();
+                // Clear the screen and move the cursor to the top left corner
+                write!(
+                    handle,
+                    "\x1b[2J\x1b[H{}Frame {}/{}\n",
+                    frame.to_terminal_string(),
+                    idx + 1,
+                    frames.len()
+                )
+                .and_then(|_| handle.flush())
+                .map_err(|err| err.to_string())?;
+                thread::sleep(Duration::from_millis(interval));
+            }
+        }
+        Ok(serde_json::json!({ "frames": frames.len() }))
+    }
+
+    fn import_qr(&self, m: &ArgMatches) -> Result<TxHelper, String> {
+        let path = m.value_of("tx-file").unwrap();
+        if Path::new(path).exists() && !m.is_present("overwrite") {
+            return Err(format!(
+                "Tx helper file already exists: {} (use --overwrite to replace it)",
+                path
+            ));
+        }
+        let reader: Box<dyn BufRead> = match m.value_of("frames-file") {
+            Some(frames_path) => Box::new(io::BufReader::new(
+                fs::File::open(frames_path).map_err(|err| err.to_string())?,
+            )),
+            None => Box::new(io::BufReader::new(io::stdin())),
+        };
+        let mut collector = FrameCollector::default();
+        for line in reader.lines() {
+            let line = line.map_err(|err| err.to_string())?;
+            let frame = line.trim();
+            if frame.is_empty() {
+                continue;
+            }
+            if collector.add(frame)? {
+                eprintln!(
+                    "Frame {}/{} received",
+                    collector.received(),
+                    collector.total()
+                );
+            }
+            if collector.is_complete() {
+                break;
+            }
+        }
+        let helper = TxHelper::parse(&collector.text()?, "from QR codes")?;
+        helper.save(path)?;
+        Ok(helper)
+    }
+
+    fn sign_inputs(&mut self, m: &ArgMatches) -> Result<serde_json::Value, String> {
+        let from_privkey: Option<PrivkeyWrapper> =
+            PrivkeyPathParser.from_matches_opt(m, "privkey-path", false)?;
+        let from_account: Option<H160> =
+            LabeledLockArgParser.from_matches_opt(m, "from-account", false)?;
+        let path = m.value_of("tx-file").unwrap();
+        let mut helper = TxHelper::load(path)?;
+
+        let signer = if let Some(privkey) = from_privkey.as_ref() {
+            let pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, privkey);
+            Address::from_pubkey(&pubkey)?.hash().clone()
+        } else {
+            from_account.clone().unwrap()
+        };
+        let lock_hashes = helper.groups_of_signer(&signer);
+        if lock_hashes.is_empty() {
+            return Err(format!("{:#x} is not a signer of the transaction", signer));
+        }
+        let transaction = helper.transaction();
+        let tx_hash: H256 = transaction.hash().unpack();
+
+        // The policy sees the capacity the signed lock groups lose and the locks receiving it
+        let policy = if SigningPolicy::load(&get_ckb_cli_dir())?.is_some() {
+            let genesis_info = get_genesis_info(&mut self.genesis_info, self.rpc_client)?;
+            let input_cells = helper
+                .input_cells
+                .iter()
+                .cloned()
+                .map(CellOutput::from)
+                .collect::<Vec<_>>();
+            let mut capacity = 0;
+            let mut to_addresses: Vec<Address> = Vec::new();
+            for lock_hash in &lock_hashes {
+                let lock = Script::from(helper.locks[lock_hash].script.clone());
+                let (outflow, addresses) =
+                    transaction_outflow(&transaction, &input_cells, &lock, &genesis_info)?;
+                capacity += outflow;
+                for address in addresses {
+                    if !to_addresses.contains(&address) {
+                        to_addresses.push(address);
+                    }
+                }
+            }
+            // Only the multisig groups of two or more signatures are co-signed
+            let cosigned = lock_hashes.iter().all(|lock_hash| {
+                helper.locks[lock_hash]
+                    .multisig_config
+                    .as_ref()
+                    .and_then(|lock_arg| helper.multisig_configs.get(lock_arg))
+                    .map(|config| config.threshold() >= 2)
+                    .unwrap_or(false)
+            });
+            let from_address = Address::from_lock_arg(signer.as_bytes())?;
+            let request = SignRequest {
+                kind: SignKind::Transfer,
+                from_address: &from_address,
+                to_addresses: &to_addresses,
+                capacity,
+                cosigned,
+            };
+            check_policy(&request, m.is_present("override-policy"))?
+                .map(|policy| (policy, capacity))
+        } else {
+            None
+        };
+        authorize_signer(&signer)?;
+        // A sweep may have hundreds of lock groups, the messages (and the signatures of a private
+        // key) are computed in parallel
+        let messages = lock_hashes
+            .par_iter()
+            .map(|lock_hash| helper.sign_message(lock_hash))
+            .collect::<Result<Vec<_>, String>>()?;
+        let raw_signatures = if let Some(privkey) = from_privkey.as_ref() {
+            messages
+                .par_iter()
+                .map(|message| {
+                    let message = secp256k1::Message::from_slice(message.as_bytes())
+                        .expect("Convert to secp256k1 message failed");
+                    SECP256K1.sign_recoverable(&message, privkey)
+                })
+                .collect::<Vec<_>>()
+        } else {
+            // The unlocked account (interactive mode) needs no password, otherwise the key is
+            // decrypted once for all the lock groups
+            let unlocked = messages
+                .iter()
+                .map(|message| self.key_store.sign_recoverable(&signer, message))
+                .collect::<Result<Vec<_>, _>>();
+            match unlocked {
+                Ok(signatures) => signatures,
+                Err(KeyStoreError::AccountLocked(_)) => {
+                    let prompt = format!("Password for [{:x}]", signer);
+                    let password = read_password(false, Some(prompt.as_str()))?;
+                    self.key_store
+                        .sign_recoverable_batch_with_password(
+                            &signer,
+                            &messages,
+                            password.as_bytes(),
+                        )
+                        .map_err(|err| err.to_string())?
+                }
+                Err(err) => return Err(err.to_string()),
+            }
+        };
+        let mut signatures = Vec::new();
+        for ((lock_hash, message), signature) in lock_hashes
+            .into_iter()
+            .zip(messages.into_iter())
+            .zip(raw_signatures.iter())
+        {
+            record_digest(&signer, Some(tx_hash.clone()), message)?;
+            let signature = serialize_signature(signature);
+            if m.is_present("add-signatures") {
+                helper.add_signature(&lock_hash, &signer, signature.clone())?;
+            }
+            signatures.push(serde_json::json!({
+                "lock_hash": lock_hash,
+                "signer": signer,
+                "signature": format!("0x{}", faster_hex::hex_string(&signature).unwrap()),
+            }));
+        }
+        if m.is_present("add-signatures") {
+            helper.save(path)?;
+        }
+        // The signatures may be sent by others, record the capacity once signed
+        if let Some((policy, capacity)) = policy {
+            policy.record(capacity)?;
+        }
+        Ok(serde_json::json!({
+            "tx_hash": tx_hash,
+            "signatures": signatures,
+        }))
+    }
+}
+
+impl<'a> CliSubCommand for TxSubCommand<'a> {
+    fn process(
+        &mut self,
+        matches: &ArgMatches,
+        format: OutputFormat,
+        color: bool,
+        debug: bool,
+    ) -> Result<String, String> {
+        match matches.subcommand() {
+            ("init", Some(m)) => {
+                let path = m.value_of("tx-file").unwrap();
+                if Path::new(path).exists() {
+                    return Err(format!("Tx helper file already exists: {}", path));
+                }
+                let helper = TxHelper::default();
+                helper.save(path)?;
+                Ok(self.info(&helper).render(format, color))
+            }
+            ("add-multisig-config", Some(m)) => {
+                let path = m.value_of("tx-file").unwrap();
+                let mut helper = TxHelper::load(path)?;
+                let config = if m.is_present("multisig-lock-arg") {
+                    let lock_arg: H160 =
+                        FixedHashParser::<H160>::default().from_matches(m, "multisig-lock-arg")?;
+                    MultisigConfigs::load_default()?.get(&lock_arg)?.clone()
+                } else {
+                    let sighash_addresses: Vec<H160> =
+                        LabeledLockArgParser.from_matches_vec(m, "sighash-lock-arg")?;
+                    let require_first_n: u8 =
+                        FromStrParser::<u8>::default().from_matches(m, "require-first-n")?;
+                    let threshold: u8 =
+                        FromStrParser::<u8>::default().from_matches(m, "threshold")?;
+                    MultisigConfig::new(sighash_addresses, require_first_n, threshold)?
+                };
+                let code_hashes = self.lock_code_hashes()?;
+                helper.add_multisig_config(config, &code_hashes);
+                helper.save(path)?;
+                Ok(self.info(&helper).render(format, color))
+            }
+            ("add-input", Some(m)) => {
+                let tx_hash: H256 =
+                    FixedHashParser::<H256>::default().from_matches(m, "tx-hash")?;
+                let index: u32 = FromStrParser::<u32>::default().from_matches(m, "index")?;
+                let path = m.value_of("tx-file").unwrap();
+                let mut helper = TxHelper::load(path)?;
+                let genesis_info = get_genesis_info(&mut self.genesis_info, self.rpc_client)?;
+
+                let out_point = OutPoint::new(tx_hash.pack(), index);
+                let (output, data) = self.live_cell(&out_point)?;
+                let cell_dep = lock_cell_dep(&genesis_info, &output)?;
+                let dao_info = if is_dao_cell(&genesis_info, &output) {
+                    Some(self.dao_withdraw_info(&tx_hash, &data)?)
+                } else {
+                    None
+                };
+                let since = dao_info
+                    .as_ref()
+                    .and_then(|(_, prepared)| prepared.as_ref())
+                    .map(|(_, since)| *since)
+                    .unwrap_or(0);
+                let input_index = helper.transaction().inputs().len();
+                let code_hashes = self.lock_code_hashes()?;
+                helper.add_input(CellInput::new(out_point, since), output, &code_hashes)?;
+                if let Some(cell_dep) = cell_dep {
+                    helper.add_cell_dep(cell_dep);
+                }
+                if let Some((block_hash, prepared)) = dao_info {
+                    helper.add_cell_dep(genesis_info.dao_dep());
+                    helper.add_header_dep(block_hash.pack());
+                    if let Some((deposit_hash, _)) = prepared {
+                        let header_index = helper.add_header_dep(deposit_hash.pack()) as u64;
+                        helper.set_input_type(
+                            input_index,
+                            Bytes::from(header_index.to_le_bytes().to_vec()),
+                        )?;
+                    }
+                }
+                helper.save(path)?;
+                Ok(self.info(&helper).render(format, color))
+            }
+            ("add-output", Some(m)) => {
+                let to_address: Address = LabeledAddressParser.from_matches(m, "to-address")?;
+                let capacity: u64 = CapacityParser.from_matches(m, "capacity")?;
+                let to_data: Option<Bytes> = HexParser.from_matches_opt(m, "to-data", false)?;
+                let path = m.value_of("tx-file").unwrap();
+                let mut helper = TxHelper::load(path)?;
+                let genesis_info = get_genesis_info(&mut self.genesis_info, self.rpc_client)?;
+
+                let output = CellOutput::new_builder()
+                    .capacity(Capacity::shannons(capacity).pack())
+                    .lock(genesis_info.lock_script(&to_address)?)
+                    .build();
+                helper.add_output(output, to_data.unwrap_or_default());
+                check_output_capacities(&helper.transaction(), &[])?;
+                helper.save(path)?;
+                Ok(self.info(&helper).render(format, color))
+            }
+            ("replace-input", Some(m)) => {
+                let old_out_point: OutPoint = OutPointParser.from_matches(m, "old-out-point")?;
+                let new_out_point: OutPoint = OutPointParser.from_matches(m, "new-out-point")?;
+                let change_index: Option<usize> =
+                    FromStrParser::<usize>::default().from_matches_opt(m, "change-index", false)?;
+                let path = m.value_of("tx-file").unwrap();
+                let mut helper = TxHelper::load(path)?;
+                let genesis_info = get_genesis_info(&mut self.genesis_info, self.rpc_client)?;
+
+                let (output, _) = self.live_cell(&new_out_point)?;
+                // The header deps of a withdrawing NervosDAO cell are not replaced
+                if is_dao_cell(&genesis_info, &output) {
+                    return Err("Add the NervosDAO cell by `tx add-input` instead".to_owned());
+                }
+                let cell_dep = lock_cell_dep(&genesis_info, &output)?;
+                let new_capacity: u64 = output.capacity().unpack();
+                let code_hashes = self.lock_code_hashes()?;
+                let old_output = helper.replace_input(
+                    &old_out_point,
+                    CellInput::new(new_out_point, 0),
+                    output,
+                    &code_hashes,
+                )?;
+                if let Some(cell_dep) = cell_dep {
+                    helper.add_cell_dep(cell_dep);
+                }
+                let old_capacity: u64 = old_output.capacity().unpack();
+                rebalance_change(
+                    &mut helper,
+                    change_index,
+                    i128::from(new_capacity) - i128::from(old_capacity),
+                )?;
+                helper.save(path)?;
+                Ok(self.info(&helper).render(format, color))
+            }
+            ("remove-output", Some(m)) => {
+                let output_index: usize =
+                    FromStrParser::<usize>::default().from_matches(m, "output-index")?;
+                let change_index: Option<usize> =
+                    FromStrParser::<usize>::default().from_matches_opt(m, "change-index", false)?;
+                let path = m.value_of("tx-file").unwrap();
+                let mut helper = TxHelper::load(path)?;
+                if change_index == Some(output_index) {
+                    return Err("The change output can not be the removed one".to_owned());
+                }
+
+                let removed = helper.remove_output(output_index)?;
+                // The indexes after the removed output are shifted
+                let change_index = change_index.map(|index| {
+                    if index > output_index {
+                        index - 1
+                    } else {
+                        index
+                    }
+                });
+                let removed_capacity: u64 = removed.capacity().unpack();
+                rebalance_change(&mut helper, change_index, i128::from(removed_capacity))?;
+                helper.save(path)?;
+                Ok(self.info(&helper).render(format, color))
+            }
+            ("set-output-capacity", Some(m)) => {
+                let output_index: usize =
+                    FromStrParser::<usize>::default().from_matches(m, "output-index")?;
+                let capacity: u64 = CapacityParser.from_matches(m, "capacity")?;
+                let change_index: Option<usize> =
+                    FromStrParser::<usize>::default().from_matches_opt(m, "change-index", false)?;
+                let path = m.value_of("tx-file").unwrap();
+                let mut helper = TxHelper::load(path)?;
+                if change_index == Some(output_index) {
+                    return Err("The change output can not be the adjusted one".to_owned());
+                }
+
+                let old_capacity = helper.set_output_capacity(output_index, capacity)?;
+                rebalance_change(
+                    &mut helper,
+                    change_index,
+                    i128::from(old_capacity) - i128::from(capacity),
+                )?;
+                helper.save(path)?;
+                Ok(self.info(&helper).render(format, color))
+            }
+            ("set-witness-template", Some(m)) => {
+                let lock_hash: H256 =
+                    FixedHashParser::<H256>::default().from_matches(m, "lock-hash")?;
+                let lock_size: usize =
+                    FromStrParser::<usize>::default().from_matches(m, "lock-size")?;
+                let signature_offset: usize =
+                    FromStrParser::<usize>::default().from_matches(m, "signature-offset")?;
+                let signer: H160 = LabeledLockArgParser.from_matches(m, "signer")?;
+                let path = m.value_of("tx-file").unwrap();
+                let mut helper = TxHelper::load(path)?;
+
+                let template = WitnessTemplate::new(lock_size, signature_offset)?;
+                helper.set_witness_template(&lock_hash, template, signer)?;
+                helper.save(path)?;
+                Ok(self.info(&helper).render(format, color))
+            }
+            ("info", Some(m)) => {
+                let helper = TxHelper::load(m.value_of("tx-file").unwrap())?;
+                Ok(self.info(&helper).render(format, color))
+            }
+            ("sign-inputs", Some(m)) => Ok(self.sign_inputs(m)?.render(format, color)),
+            ("add-signature", Some(m)) => {
+                let lock_hash: H256 =
+                    FixedHashParser::<H256>::default().from_matches(m, "lock-hash")?;
+                let signature: Bytes = HexParser.from_matches(m, "signature")?;
+                let path = m.value_of("tx-file").unwrap();
+                let mut helper = TxHelper::load(path)?;
+
+                let message = helper.sign_message(&lock_hash)?;
+                let signer = recover_signer(&message, &signature)?;
+                helper.add_signature(&lock_hash, &signer, signature)?;
+                helper.save(path)?;
+                Ok(self.info(&helper).render(format, color))
+            }
+            ("merge", Some(m)) => {
+                let path = m.value_of("tx-file").unwrap();
+                let mut helper = TxHelper::load(path)?;
+                let mut merged = Vec::new();
+                for from_path in m.values_of("from-tx-file").unwrap() {
+                    let other = TxHelper::load(from_path)
+                        .map_err(|err| format!("{}: {}", from_path, err))?;
+                    let added = helper
+                        .merge_signatures(&other)
+                        .map_err(|err| format!("{}: {}", from_path, err))?;
+                    merged.push(serde_json::json!({
+                        "tx_file": from_path,
+                        "new_signatures": added,
+                    }));
+                }
+                helper.save(path)?;
+                let mut resp = self.info(&helper);
+                resp["merged"] = serde_json::json!(merged);
+                Ok(resp.render(format, color))
+            }
+            ("export-qr", Some(m)) => Ok(self.export_qr(m)?.render(format, color)),
+            ("import-qr", Some(m)) => {
+                let helper = self.import_qr(m)?;
+                Ok(self.info(&helper).render(format, color))
+            }
+            ("send", Some(m)) => {
+                let helper = TxHelper::load(m.value_of("tx-file").unwrap())?;
+                let transaction = helper.build_transaction()?;
+                TxSender::new(self.rpc_client, self.genesis_info.clone())
+                    .with_fee_guard(self.fee_guard)
+                    .send(m, transaction, format, color, debug)
+            }
+            _ => Err(matches.usage().to_owned()),
+        }
+    }
+}
+
+// The cell dep of the secp256k1 sighash or multisig lock of the cell
+fn lock_cell_dep(
+    genesis_info: &GenesisInfo,
+    output: &CellOutput,
+) -> Result<Option<CellDep>, String> {
+    let code_hash = output.lock().code_hash();
+    if &code_hash == genesis_info.secp_type_hash() {
+        Ok(Some(genesis_info.secp_dep()))
+    } else if genesis_info.multisig_type_hash().ok() == Some(&code_hash) {
+        Ok(Some(genesis_info.multisig_dep()?))
+    } else {
+        Ok(None)
+    }
+}
+
+fn is_dao_cell(genesis_info: &GenesisInfo, output: &CellOutput) -> bool {
+    output
+        .type_()
+        .to_opt()
+        .map(|script| &script.code_hash() == genesis_info.dao_type_hash())
+        .unwrap_or(false)
+}
+
+// Add `delta` capacity to the change output (if any), the fee takes the difference otherwise
+fn rebalance_change(
+    helper: &mut TxHelper,
+    change_index: Option<usize>,
+    delta: i128,
+) -> Result<(), String> {
+    let change_index = match change_index {
+        Some(index) if delta != 0 => index,
+        _ => return Ok(()),
+    };
+    let change = helper
+        .transaction()
+        .outputs()
+        .get(change_index)
+        .ok_or_else(|| format!("Change output #{} not found", change_index))?;
+    let old_capacity: u64 = change.capacity().unpack();
+    let new_capacity = i128::from(old_capacity) + delta;
+    if new_capacity < 0 {
+        return Err(format!(
+            "Change output can not afford the difference: {} + ({}) < 0",
+            old_capacity, delta
+        ));
+    }
+    helper.set_output_capacity(change_index, new_capacity as u64)?;
+    Ok(())
+}
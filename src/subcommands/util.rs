@@ -1,7 +1,15 @@
 use ckb_crypto::secp::SECP256K1;
-use ckb_hash::blake2b_256;
-use ckb_jsonrpc_types::{Script as RpcScript, Transaction as RpcTransaction};
-use ckb_sdk::{Address, GenesisInfo, HttpRpcClient, NetworkType, OldAddress};
+use ckb_hash::{blake2b_256, new_blake2b};
+use ckb_jsonrpc_types::{
+    BlockView as RpcBlockView, CellDep as RpcCellDep, HeaderView as RpcHeaderView,
+    Script as RpcScript, Transaction as RpcTransaction,
+};
+use ckb_sdk::{
+    recover_pubkey, secp_group_message, serialize_signature,
+    wallet::{KeyStore, KeyStoreError},
+    Address, AddressPayload, CellDepResolver, CodeHashIndex, GenesisInfo, HttpRpcClient,
+    NetworkType, OldAddress,
+};
 use ckb_types::{
     packed,
     prelude::*,
@@ -10,31 +18,76 @@ use ckb_types::{
 };
 use clap::{App, Arg, ArgMatches, SubCommand};
 use faster_hex::hex_string;
+use serde_derive::{Deserialize, Serialize};
 use std::fs;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
 use std::path::PathBuf;
 
 use super::CliSubCommand;
 use crate::utils::{
+    arg,
     arg_parser::{
         AddressParser, ArgParser, FilePathParser, FixedHashParser, FromStrParser, HexParser,
-        PrivkeyPathParser, PrivkeyWrapper, PubkeyHexParser,
+        LabeledLockArgParser, PrivkeyPathParser, PrivkeyWrapper, PubkeyHexParser, ScriptParser,
     },
-    other::{get_address, get_genesis_info},
+    other::{get_address, get_genesis_info, get_network_type, read_password, read_tx_file},
     printer::{OutputFormat, Printable},
+    sign_history::record_digest,
 };
 
+// The packed types of `blockchain.mol`
+const MOLECULE_TYPES: &[&str] = &[
+    "Uint32",
+    "Uint64",
+    "Uint128",
+    "Byte32",
+    "Uint256",
+    "Bytes",
+    "BytesOpt",
+    "BytesVec",
+    "Byte32Vec",
+    "ScriptOpt",
+    "ProposalShortId",
+    "UncleBlockVec",
+    "TransactionVec",
+    "ProposalShortIdVec",
+    "CellDepVec",
+    "CellInputVec",
+    "CellOutputVec",
+    "Script",
+    "OutPoint",
+    "CellInput",
+    "CellOutput",
+    "CellDep",
+    "RawTransaction",
+    "Transaction",
+    "RawHeader",
+    "Header",
+    "UncleBlock",
+    "Block",
+    "CellbaseWitness",
+    "WitnessArgs",
+];
+
+/// Prefixed to the signed messages, so a message signature can never be a transaction signature
+/// (the same as Neuron)
+const MESSAGE_MAGIC: &[u8] = b"Nervos Message:";
+
 pub struct UtilSubCommand<'a> {
     rpc_client: &'a mut HttpRpcClient,
+    key_store: &'a mut KeyStore,
     genesis_info: Option<GenesisInfo>,
 }
 
 impl<'a> UtilSubCommand<'a> {
     pub fn new(
         rpc_client: &'a mut HttpRpcClient,
+        key_store: &'a mut KeyStore,
         genesis_info: Option<GenesisInfo>,
     ) -> UtilSubCommand<'a> {
         UtilSubCommand {
             rpc_client,
+            key_store,
             genesis_info,
         }
     }
@@ -78,6 +131,28 @@ impl<'a> UtilSubCommand<'a> {
             .default_value("binary")
             .possible_values(&["binary", "hash"])
             .help("Serialize output type");
+        let tx_file_arg = Arg::with_name("tx-file")
+            .long("tx-file")
+            .takes_value(true)
+            .required(true)
+            .validator(|input| FilePathParser::new(true).validate(input))
+            .help("Transaction file (json format, see rpc send_transaction, the `transaction` field is used if present)");
+        let expected_hash_arg = Arg::with_name("expected")
+            .long("expected")
+            .takes_value(true)
+            .validator(|input| FixedHashParser::<H256>::default().validate(input))
+            .help("Verify the computed hash matches this one");
+        let arg_message = Arg::with_name("message")
+            .long("message")
+            .takes_value(true)
+            .required_unless("message-file")
+            .help("The message (UTF-8 text)");
+        let arg_message_file = Arg::with_name("message-file")
+            .long("message-file")
+            .takes_value(true)
+            .conflicts_with("message")
+            .validator(|input| FilePathParser::new(true).validate(input))
+            .help("File of the message (any binary)");
         SubCommand::with_name(name)
             .about("Utilities")
             .subcommands(vec![
@@ -89,11 +164,110 @@ impl<'a> UtilSubCommand<'a> {
                     .arg(arg_pubkey.clone().required(false))
                     .arg(arg_address.clone().required(false))
                     .arg(arg_lock_arg.clone()),
+                SubCommand::with_name("to-address")
+                    .about("Convert a lock script to address (short format for secp256k1 sighash/multisig locks, full format otherwise)")
+                    .arg(Arg::with_name("lock-script")
+                         .long("lock-script")
+                         .takes_value(true)
+                         .required(true)
+                         .validator(|input| ScriptParser.validate(input))
+                         .help("Lock script (JSON or hex of the serialized script)"))
+                    .arg(Arg::with_name("full")
+                         .long("full")
+                         .help("Always use the full format")),
+                SubCommand::with_name("parse-address")
+                    .about("Print all the fields and the lock script of an address (short or full format)")
+                    .arg(Arg::with_name("address")
+                         .long("address")
+                         .takes_value(true)
+                         .required(true)
+                         .validator(|input| AddressPayload::from_address_string(&input).map(|_| ()))
+                         .help("Address of any format")),
+                SubCommand::with_name("batch-convert")
+                    .about("Convert the addresses (old or new format of any network) in a CSV file to new format addresses of a network, line by line")
+                    .arg(Arg::with_name("input")
+                         .long("input")
+                         .takes_value(true)
+                         .required(true)
+                         .validator(|input| FilePathParser::new(true).validate(input))
+                         .help("Input CSV file path"))
+                    .arg(Arg::with_name("output")
+                         .long("output")
+                         .takes_value(true)
+                         .required(true)
+                         .validator(|input| FilePathParser::new(false).validate(input))
+                         .help("Output CSV file path"))
+                    .arg(Arg::with_name("address-column")
+                         .long("address-column")
+                         .takes_value(true)
+                         .default_value("0")
+                         .validator(|input| FromStrParser::<usize>::default().validate(input))
+                         .help("Index of the address column (starts from 0)"))
+                    .arg(Arg::with_name("delimiter")
+                         .long("delimiter")
+                         .takes_value(true)
+                         .default_value(",")
+                         .validator(|input| parse_delimiter(&input).map(|_| ()))
+                         .help("Delimiter of input columns (one character, or \\t for tab)"))
+                    .arg(Arg::with_name("output-delimiter")
+                         .long("output-delimiter")
+                         .takes_value(true)
+                         .validator(|input| parse_delimiter(&input).map(|_| ()))
+                         .help("Delimiter of output columns (default: same as input)"))
+                    .arg(Arg::with_name("network")
+                         .long("network")
+                         .takes_value(true)
+                         .default_value("mainnet")
+                         .possible_values(&["mainnet", "testnet"])
+                         .help("Network of the converted addresses"))
+                    .arg(Arg::with_name("keep-columns")
+                         .long("keep-columns")
+                         .help("Keep the other columns (the address column is replaced), only the converted address is written by default")),
+                SubCommand::with_name("blake2b")
+                    .about("Hash data by blake2b with CKB personalization (ckb-default-hash), from --binary-hex, --binary-path or stdin")
+                    .arg(Arg::with_name("binary-hex")
+                         .long("binary-hex")
+                         .takes_value(true)
+                         .validator(|input| HexParser.validate(input))
+                         .help("Data to hash (hex)"))
+                    .arg(Arg::with_name("binary-path")
+                         .long("binary-path")
+                         .takes_value(true)
+                         .conflicts_with("binary-hex")
+                         .validator(|input| FilePathParser::new(true).validate(input))
+                         .help("File to hash"))
+                    .arg(Arg::with_name("prefix-160")
+                         .long("prefix-160")
+                         .help("Only output the first 20 bytes (like lock args)")),
+                SubCommand::with_name("molecule")
+                    .about("Molecule serialization of packed types")
+                    .subcommand(
+                        SubCommand::with_name("decode")
+                            .about("Decode a molecule binary to json")
+                            .arg(Arg::with_name("type")
+                                 .long("type")
+                                 .takes_value(true)
+                                 .required(true)
+                                 .possible_values(MOLECULE_TYPES)
+                                 .help("Packed type of the binary"))
+                            .arg(binary_hex_arg.clone().help("Molecule binary hex")),
+                    ),
                 SubCommand::with_name("serialize-tx")
                     .about("Serialize a transaction from json file to hex binary or hash")
                     .arg(json_path_arg.clone()
                          .help("Transaction content (json format, without witnesses/hash, see rpc get_transaction)"))
-                    .arg(serialize_output_type_arg.clone()),
+                    .arg(serialize_output_type_arg.clone())
+                    .arg(Arg::with_name("with-witnesses")
+                         .long("with-witnesses")
+                         .help("Serialize the complete transaction (with witnesses) instead of the raw transaction")),
+                SubCommand::with_name("tx-hash")
+                    .about("Compute the hash of a transaction (witnesses not included)")
+                    .arg(tx_file_arg.clone())
+                    .arg(expected_hash_arg.clone()),
+                SubCommand::with_name("witness-hash")
+                    .about("Compute the witness hash of a transaction (the hash of the complete transaction with witnesses)")
+                    .arg(tx_file_arg.clone())
+                    .arg(expected_hash_arg.clone()),
                 SubCommand::with_name("deserialize-tx")
                     .about("Deserialize a transaction from binary hex to json")
                     .arg(binary_hex_arg.clone().help("Transaction binary hex")),
@@ -105,6 +279,80 @@ impl<'a> UtilSubCommand<'a> {
                 SubCommand::with_name("deserialize-script")
                     .about("Deserialize a script from hex binary to json")
                     .arg(binary_hex_arg.clone().help("Script binary hex")),
+                SubCommand::with_name("sighash-message")
+                    .about("Compute the message (blake2b digest) signed for a secp256k1 sighash-all input group, the group is the inputs with the same lock as --input-index")
+                    .arg(tx_file_arg.clone())
+                    .arg(Arg::with_name("input-index")
+                         .long("input-index")
+                         .takes_value(true)
+                         .default_value("0")
+                         .validator(|input| FromStrParser::<usize>::default().validate(input))
+                         .help("Index of an input in the lock group")),
+                SubCommand::with_name("verify-signature")
+                    .about("Recover the public key of a recoverable signature and check it matches a public key or lock arg")
+                    .arg(Arg::with_name("signature")
+                         .long("signature")
+                         .takes_value(true)
+                         .required(true)
+                         .validator(|input| HexParser.validate(input))
+                         .help("Recoverable signature (65 bytes hex: compact signature | recovery id)"))
+                    .arg(Arg::with_name("message")
+                         .long("message")
+                         .takes_value(true)
+                         .required(true)
+                         .validator(|input| FixedHashParser::<H256>::default().validate(input))
+                         .help("The signed message (32 bytes hex)"))
+                    .arg(arg_pubkey.clone().required_unless("lock-arg").conflicts_with("lock-arg"))
+                    .arg(arg_lock_arg.clone().required_unless("pubkey")),
+                SubCommand::with_name("sign-message")
+                    .about("Sign a message (prefixed by \"Nervos Message:\", hashed by blake2b) by an account or private key, to prove the ownership of an address off-chain")
+                    .arg(arg_message.clone())
+                    .arg(arg_message_file.clone())
+                    .arg(arg_privkey.clone().required_unless("from-account").conflicts_with("from-account"))
+                    .arg(arg::from_account().required_unless("privkey-path").help("The account's lock-arg or label (sign by this account)")),
+                SubCommand::with_name("verify-message")
+                    .about("Verify a message signature (printed by sign-message) against an address")
+                    .arg(arg_message)
+                    .arg(arg_message_file)
+                    .arg(Arg::with_name("signature")
+                         .long("signature")
+                         .takes_value(true)
+                         .required(true)
+                         .validator(|input| HexParser.validate(input))
+                         .help("Recoverable signature (65 bytes hex)"))
+                    .arg(arg_address.clone().help("Address of the signer (secp256k1 sighash, short format)")),
+                SubCommand::with_name("tx-proof")
+                    .about("Export a merkle proof that a committed transaction is in a block (with the block header), verified offline by verify-tx-proof")
+                    .arg(Arg::with_name("tx-hash")
+                         .long("tx-hash")
+                         .takes_value(true)
+                         .required(true)
+                         .validator(|input| FixedHashParser::<H256>::default().validate(input))
+                         .help("Transaction hash"))
+                    .arg(Arg::with_name("output")
+                         .long("output")
+                         .takes_value(true)
+                         .validator(|input| FilePathParser::new(false).validate(input))
+                         .help("Write the proof into the file (format: json) instead of printing it")),
+                SubCommand::with_name("verify-tx-proof")
+                    .about("Verify a transaction proof file offline: the header matches its hash and the transaction is in the transactions root of the header")
+                    .arg(Arg::with_name("proof-file")
+                         .long("proof-file")
+                         .takes_value(true)
+                         .required(true)
+                         .validator(|input| FilePathParser::new(true).validate(input))
+                         .help("Proof file exported by tx-proof"))
+                    .arg(Arg::with_name("block-hash")
+                         .long("block-hash")
+                         .takes_value(true)
+                         .validator(|input| FixedHashParser::<H256>::default().validate(input))
+                         .help("The block hash trusted (from a node or a light client), the proof must be of this block")),
+                SubCommand::with_name("cell-dep-resolver")
+                    .about("Resolve the cell deps of well-known system scripts (sighash, multisig, NervosDAO, simple UDT, anyone-can-pay) run by a transaction (input locks, input and output types)")
+                    .arg(tx_file_arg.clone())
+                    .arg(Arg::with_name("patch")
+                         .long("patch")
+                         .help("Add the missing cell deps to the transaction file")),
                 SubCommand::with_name("compact-to-difficulty")
                     .about("Convert compact target value to difficulty value")
                     .arg(Arg::with_name("compact-target")
@@ -145,6 +393,160 @@ impl<'a> UtilSubCommand<'a> {
     }
 }
 
+impl<'a> UtilSubCommand<'a> {
+    fn cell_dep_resolver(
+        &mut self,
+        m: &ArgMatches,
+        format: OutputFormat,
+        color: bool,
+    ) -> Result<String, String> {
+        let tx_file = m.value_of("tx-file").unwrap();
+        let (tx, mut file_value) = read_tx_file(tx_file)?;
+        let genesis_info = get_genesis_info(&mut self.genesis_info, self.rpc_client)?;
+        let network_type = get_network_type(self.rpc_client)?;
+        let resolver = CellDepResolver::new(&genesis_info, network_type);
+
+        // The scripts run by the transaction: the lock and type scripts of inputs and the type
+        // scripts of outputs (output locks are not run)
+        let mut scripts = Vec::new();
+        for input in tx.inputs().into_iter() {
+            let input_cell = self.input_cell(&input.previous_output())?;
+            scripts.push(input_cell.lock());
+            scripts.extend(input_cell.type_().to_opt());
+        }
+        for output in tx.outputs().into_iter() {
+            scripts.extend(output.type_().to_opt());
+        }
+
+        let mut cell_deps = tx.cell_deps().into_iter().collect::<Vec<_>>();
+        let mut resolved = Vec::new();
+        let mut unresolved = Vec::new();
+        for script in scripts {
+            match resolver.resolve(&script) {
+                Some((name, cell_dep)) => {
+                    if !cell_deps.contains(&cell_dep) {
+                        resolved.push(serde_json::json!({
+                            "script": name,
+                            "cell_dep": RpcCellDep::from(cell_dep.clone()),
+                        }));
+                        cell_deps.push(cell_dep);
+                    }
+                }
+                None => {
+                    let script = RpcScript::from(script);
+                    if !unresolved.contains(&script) {
+                        unresolved.push(script);
+                    }
+                }
+            }
+        }
+
+        let patched_tx: RpcTransaction = tx
+            .as_advanced_builder()
+            .set_cell_deps(cell_deps)
+            .build()
+            .data()
+            .into();
+        if m.is_present("patch") && !resolved.is_empty() {
+            let tx_value = serde_json::to_value(&patched_tx).map_err(|err| err.to_string())?;
+            if file_value.get("transaction").is_some() {
+                file_value["transaction"] = tx_value;
+            } else {
+                file_value = tx_value;
+            }
+            let content =
+                serde_json::to_string_pretty(&file_value).map_err(|err| err.to_string())?;
+            fs::write(tx_file, content).map_err(|err| err.to_string())?;
+        }
+        let resp = serde_json::json!({
+            "missing_cell_deps": resolved,
+            // Not system scripts, the cell deps must be provided manually
+            "unknown_scripts": unresolved,
+            "cell_deps": patched_tx.cell_deps,
+        });
+        Ok(resp.render(format, color))
+    }
+
+    fn sign_message(&mut self, m: &ArgMatches) -> Result<serde_json::Value, String> {
+        let digest = message_digest(&read_message(m)?);
+        let from_privkey: Option<PrivkeyWrapper> =
+            PrivkeyPathParser.from_matches_opt(m, "privkey-path", false)?;
+        let (lock_arg, signature) = if let Some(privkey) = from_privkey {
+            let pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, &privkey);
+            let message = secp256k1::Message::from_slice(digest.as_bytes())
+                .expect("Convert to secp256k1 message failed");
+            (
+                Address::from_pubkey(&pubkey)?.hash().clone(),
+                SECP256K1.sign_recoverable(&message, &privkey),
+            )
+        } else {
+            let lock_arg: H160 = LabeledLockArgParser.from_matches(m, "from-account")?;
+            // The unlocked account (interactive mode) needs no password
+            let signature = match self.key_store.sign_recoverable(&lock_arg, &digest) {
+                Ok(signature) => signature,
+                Err(KeyStoreError::AccountLocked(_)) => {
+                    let prompt = format!("Password for [{:x}]", lock_arg);
+                    let password = read_password(false, Some(prompt.as_str()))?;
+                    self.key_store
+                        .sign_recoverable_with_password(&lock_arg, &digest, password.as_bytes())
+                        .map_err(|err| err.to_string())?
+                }
+                Err(err) => return Err(err.to_string()),
+            };
+            (lock_arg, signature)
+        };
+        record_digest(&lock_arg, None, digest.clone())?;
+        let signature = serialize_signature(&signature);
+        Ok(serde_json::json!({
+            "lock_arg": lock_arg,
+            "digest": digest,
+            "signature": format!("0x{}", hex_string(&signature).unwrap()),
+        }))
+    }
+
+    // The node of this version has no get_transaction_proof RPC, the proof is built from the
+    // transaction hashes of the block
+    fn tx_proof(&mut self, m: &ArgMatches) -> Result<serde_json::Value, String> {
+        let tx_hash: H256 = FixedHashParser::<H256>::default().from_matches(m, "tx-hash")?;
+        let block_hash = self
+            .rpc_client
+            .get_transaction(tx_hash.clone())
+            .call()
+            .map_err(|err| format!("Send get_transaction error: {}", err))?
+            .0
+            .ok_or_else(|| format!("Transaction not found: {:#x}", tx_hash))?
+            .tx_status
+            .block_hash
+            .ok_or_else(|| format!("Transaction {:#x} is not committed", tx_hash))?;
+        let block = self
+            .rpc_client
+            .get_block(block_hash.clone())
+            .call()
+            .map_err(|err| format!("Send get_block error: {}", err))?
+            .0
+            .ok_or_else(|| format!("Block {:#x} not found", block_hash))?;
+        let proof = TxProof::new(&block, &tx_hash)?;
+        serde_json::to_value(&proof).map_err(|err| err.to_string())
+    }
+
+    fn input_cell(&mut self, out_point: &packed::OutPoint) -> Result<packed::CellOutput, String> {
+        let tx_hash: H256 = out_point.tx_hash().unpack();
+        let index: u32 = out_point.index().unpack();
+        let tx = self
+            .rpc_client
+            .get_transaction(tx_hash.clone())
+            .call()
+            .map_err(|err| format!("Send get_transaction error: {}", err))?
+            .0
+            .ok_or_else(|| format!("Input transaction not found: {:#x}", tx_hash))?;
+        let tx: packed::Transaction = tx.transaction.inner.into();
+        tx.raw()
+            .outputs()
+            .get(index as usize)
+            .ok_or_else(|| format!("Input cell not found: {:#x}-{}", tx_hash, index))
+    }
+}
+
 impl<'a> CliSubCommand for UtilSubCommand<'a> {
     fn process(
         &mut self,
@@ -176,7 +578,7 @@ impl<'a> CliSubCommand for UtilSubCommand<'a> {
 
                 let genesis_info = get_genesis_info(&mut self.genesis_info, self.rpc_client)?;
                 let secp_type_hash = genesis_info.secp_type_hash();
-                println!(
+                eprintln!(
                     r#"Put this config in < ckb.toml >:
 
 [block_assembler]
@@ -205,14 +607,148 @@ args = ["{:#x}"]
                 });
                 Ok(resp.render(format, color))
             }
+            ("to-address", Some(m)) => {
+                let lock: packed::Script = ScriptParser.from_matches(m, "lock-script")?;
+                let payload = if m.is_present("full") {
+                    AddressPayload::full(&lock)
+                } else {
+                    let genesis_info = get_genesis_info(&mut self.genesis_info, self.rpc_client)?;
+                    AddressPayload::from_lock_script(&lock, &genesis_info)
+                };
+                let lock_hash: H256 = lock.calc_script_hash().unpack();
+                let resp = serde_json::json!({
+                    "format": payload.format_name(),
+                    "mainnet": payload.to_address_string(NetworkType::MainNet),
+                    "testnet": payload.to_address_string(NetworkType::TestNet),
+                    "lock_hash": lock_hash,
+                });
+                Ok(resp.render(format, color))
+            }
+            ("parse-address", Some(m)) => {
+                let (network, payload) =
+                    AddressPayload::from_address_string(m.value_of("address").unwrap())?;
+                let genesis_info = get_genesis_info(&mut self.genesis_info, self.rpc_client)?;
+                let lock = payload.lock_script(&genesis_info)?;
+                let lock_hash: H256 = lock.calc_script_hash().unpack();
+                let mut resp = serde_json::json!({
+                    "network": network,
+                    "format": payload.format_name(),
+                    "lock_script": RpcScript::from(lock),
+                    "lock_hash": lock_hash,
+                });
+                if let AddressPayload::Short { index, hash } = payload {
+                    resp["code_hash_index"] = serde_json::json!(index as u8);
+                    resp["lock_arg"] = serde_json::json!(hash);
+                }
+                Ok(resp.render(format, color))
+            }
+            ("batch-convert", Some(m)) => {
+                let input: PathBuf = FilePathParser::new(true).from_matches(m, "input")?;
+                let output: PathBuf = FilePathParser::new(false).from_matches(m, "output")?;
+                let column: usize =
+                    FromStrParser::<usize>::default().from_matches(m, "address-column")?;
+                let delimiter = parse_delimiter(m.value_of("delimiter").unwrap())?;
+                let output_delimiter = match m.value_of("output-delimiter") {
+                    Some(value) => parse_delimiter(value)?,
+                    None => delimiter,
+                };
+                let network = match m.value_of("network") {
+                    Some("testnet") => NetworkType::TestNet,
+                    _ => NetworkType::MainNet,
+                };
+                let keep_columns = m.is_present("keep-columns");
+
+                let reader = BufReader::new(fs::File::open(&input).map_err(|err| err.to_string())?);
+                let mut writer =
+                    BufWriter::new(fs::File::create(&output).map_err(|err| err.to_string())?);
+                let mut lines = 0;
+                for (line_number, line) in reader.lines().enumerate() {
+                    let line = line.map_err(|err| err.to_string())?;
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    let columns = line.split(delimiter).collect::<Vec<_>>();
+                    let address = columns
+                        .get(column)
+                        .ok_or_else(|| format!("Line {}: no column {}", line_number + 1, column))?;
+                    let converted = convert_address(address.trim(), network)
+                        .map_err(|err| format!("Line {}: {}", line_number + 1, err))?;
+                    let new_line = if keep_columns {
+                        columns
+                            .iter()
+                            .enumerate()
+                            .map(|(index, value)| {
+                                if index == column {
+                                    converted.as_str()
+                                } else {
+                                    *value
+                                }
+                            })
+                            .collect::<Vec<_>>()
+                            .join(&output_delimiter.to_string())
+                    } else {
+                        converted
+                    };
+                    writeln!(writer, "{}", new_line).map_err(|err| err.to_string())?;
+                    lines += 1;
+                }
+                writer.flush().map_err(|err| err.to_string())?;
+                let resp = serde_json::json!({
+                    "converted": lines,
+                    "output": output.to_string_lossy(),
+                });
+                Ok(resp.render(format, color))
+            }
+            ("blake2b", Some(m)) => {
+                let mut hasher = new_blake2b();
+                let binary_opt: Option<Vec<u8>> =
+                    HexParser.from_matches_opt(m, "binary-hex", false)?;
+                if let Some(binary) = binary_opt {
+                    hasher.update(&binary);
+                } else {
+                    let mut reader: Box<dyn Read> = match m.value_of("binary-path") {
+                        Some(path) => {
+                            Box::new(fs::File::open(path).map_err(|err| err.to_string())?)
+                        }
+                        None => Box::new(io::stdin()),
+                    };
+                    let mut buf = [0u8; 8192];
+                    loop {
+                        let size = reader.read(&mut buf).map_err(|err| err.to_string())?;
+                        if size == 0 {
+                            break;
+                        }
+                        hasher.update(&buf[..size]);
+                    }
+                }
+                let mut hash = [0u8; 32];
+                hasher.finalize(&mut hash);
+                let hash = if m.is_present("prefix-160") {
+                    &hash[0..20]
+                } else {
+                    &hash[..]
+                };
+                Ok(format!("0x{}", hex_string(hash).unwrap()))
+            }
+            ("molecule", Some(m)) => match m.subcommand() {
+                ("decode", Some(m)) => {
+                    let binary: Vec<u8> = HexParser.from_matches(m, "binary-hex")?;
+                    let value = decode_molecule(m.value_of("type").unwrap(), &binary)?;
+                    Ok(value.render(format, color))
+                }
+                _ => Err(m.usage().to_owned()),
+            },
             ("serialize-tx", Some(m)) => {
                 let json_path: PathBuf = FilePathParser::new(true).from_matches(m, "json-path")?;
                 let content = fs::read_to_string(json_path).map_err(|err| err.to_string())?;
                 let rpc_tx: RpcTransaction =
                     serde_json::from_str(&content).map_err(|err| err.to_string())?;
                 let tx: packed::Transaction = rpc_tx.into();
+                let with_witnesses = m.is_present("with-witnesses");
                 let output = match m.value_of("output-type") {
+                    Some("binary") if with_witnesses => hex_string(tx.as_slice()).unwrap(),
                     Some("binary") => hex_string(tx.raw().as_slice()).unwrap(),
+                    Some("hash") if with_witnesses => format!("{:#x}", tx.calc_witness_hash()),
                     Some("hash") => format!("{:#x}", tx.calc_tx_hash()),
                     _ => panic!("Invalid output type"),
                 };
@@ -248,6 +784,132 @@ args = ["{:#x}"]
                     .into();
                 Ok(rpc_script.render(format, color))
             }
+            ("tx-hash", Some(m)) => {
+                let (tx, _) = read_tx_file(m.value_of("tx-file").unwrap())?;
+                check_hash(
+                    m,
+                    "tx_hash",
+                    tx.data().calc_tx_hash().unpack(),
+                    format,
+                    color,
+                )
+            }
+            ("witness-hash", Some(m)) => {
+                let (tx, _) = read_tx_file(m.value_of("tx-file").unwrap())?;
+                check_hash(
+                    m,
+                    "witness_hash",
+                    tx.data().calc_witness_hash().unpack(),
+                    format,
+                    color,
+                )
+            }
+            ("sighash-message", Some(m)) => {
+                let (tx, _) = read_tx_file(m.value_of("tx-file").unwrap())?;
+                let input_index: usize =
+                    FromStrParser::<usize>::default().from_matches(m, "input-index")?;
+                let mut locks = Vec::new();
+                for input in tx.inputs().into_iter() {
+                    locks.push(self.input_lock(&input.previous_output())?);
+                }
+                let lock = locks
+                    .get(input_index)
+                    .cloned()
+                    .ok_or_else(|| format!("Input index out of range: {}", input_index))?;
+                let group = (0..locks.len())
+                    .filter(|index| locks[*index] == lock)
+                    .collect::<Vec<_>>();
+                let message = secp_group_message(&tx, &group)?;
+                let lock_hash: H256 = lock.calc_script_hash().unpack();
+                let resp = serde_json::json!({
+                    "lock_hash": lock_hash,
+                    "group": group,
+                    "message": message,
+                });
+                Ok(resp.render(format, color))
+            }
+            ("verify-signature", Some(m)) => {
+                let signature: Vec<u8> = HexParser.from_matches(m, "signature")?;
+                let message: H256 =
+                    FixedHashParser::<H256>::default().from_matches(m, "message")?;
+                let pubkey_opt: Option<secp256k1::PublicKey> =
+                    PubkeyHexParser.from_matches_opt(m, "pubkey", false)?;
+                let lock_arg_opt: Option<H160> =
+                    FixedHashParser::<H160>::default().from_matches_opt(m, "lock-arg", false)?;
+                let recovered = recover_pubkey(&message, &signature)?;
+                let recovered_lock_arg =
+                    H160::from_slice(&blake2b_256(&recovered.serialize()[..])[0..20])
+                        .expect("lock arg of pubkey");
+                let valid = match (pubkey_opt, lock_arg_opt) {
+                    (Some(pubkey), _) => pubkey == recovered,
+                    (None, Some(lock_arg)) => lock_arg == recovered_lock_arg,
+                    (None, None) => unreachable!(),
+                };
+                let resp = serde_json::json!({
+                    "pubkey": hex_string(&recovered.serialize()[..]).unwrap(),
+                    "lock_arg": recovered_lock_arg,
+                    "valid": valid,
+                });
+                Ok(resp.render(format, color))
+            }
+            ("sign-message", Some(m)) => Ok(self.sign_message(m)?.render(format, color)),
+            ("verify-message", Some(m)) => {
+                let digest = message_digest(&read_message(m)?);
+                let signature: Vec<u8> = HexParser.from_matches(m, "signature")?;
+                let address: Address = AddressParser.from_matches(m, "address")?;
+                // The lock arg of a multisig address is not a pubkey hash
+                if address.index() != CodeHashIndex::Default {
+                    return Err(
+                        "Only the secp256k1 sighash (short format) address can sign a message"
+                            .to_owned(),
+                    );
+                }
+                let recovered = recover_pubkey(&digest, &signature)?;
+                let recovered_lock_arg = Address::from_pubkey(&recovered)?.hash().clone();
+                let resp = serde_json::json!({
+                    "lock_arg": recovered_lock_arg,
+                    "digest": digest,
+                    "valid": &recovered_lock_arg == address.hash(),
+                });
+                Ok(resp.render(format, color))
+            }
+            ("tx-proof", Some(m)) => {
+                let proof = self.tx_proof(m)?;
+                match m.value_of("output") {
+                    Some(path) => {
+                        let content =
+                            serde_json::to_string_pretty(&proof).map_err(|err| err.to_string())?;
+                        fs::write(path, content)
+                            .map_err(|err| format!("Write {} failed: {}", path, err))?;
+                        let resp = serde_json::json!({
+                            "tx_hash": proof["tx_hash"],
+                            "block_hash": proof["block_hash"],
+                            "proof_file": path,
+                        });
+                        Ok(resp.render(format, color))
+                    }
+                    None => Ok(proof.render(format, color)),
+                }
+            }
+            ("verify-tx-proof", Some(m)) => {
+                let path = m.value_of("proof-file").unwrap();
+                let content = fs::read_to_string(path)
+                    .map_err(|err| format!("Read {} failed: {}", path, err))?;
+                let proof: TxProof = serde_json::from_str(&content)
+                    .map_err(|err| format!("Parse proof file {} failed: {}", path, err))?;
+                let expected_block_hash: Option<H256> =
+                    FixedHashParser::<H256>::default().from_matches_opt(m, "block-hash", false)?;
+                let result = proof.verify(expected_block_hash.as_ref());
+                let resp = serde_json::json!({
+                    "tx_hash": proof.tx_hash,
+                    "block_hash": proof.block_hash,
+                    "block_number": proof.header.inner.number,
+                    "valid": result.is_ok(),
+                    "error": result.err(),
+                });
+                Ok(resp.render(format, color))
+            }
+            ("cell-dep-resolver", Some(m)) => self.cell_dep_resolver(m, format, color),
             ("compact-to-difficulty", Some(m)) => {
                 let compact_target: u32 = FromStrParser::<u32>::default()
                     .from_matches(m, "compact-target")
@@ -282,3 +944,408 @@ args = ["{:#x}"]
         }
     }
 }
+
+// Print the hash, error if it does not match `--expected`
+fn check_hash(
+    m: &ArgMatches,
+    name: &str,
+    hash: H256,
+    format: OutputFormat,
+    color: bool,
+) -> Result<String, String> {
+    let expected: Option<H256> =
+        FixedHashParser::<H256>::default().from_matches_opt(m, "expected", false)?;
+    if let Some(expected) = expected {
+        if expected != hash {
+            return Err(format!(
+                "{} mismatch, expected: {:#x}, actual: {:#x}",
+                name, expected, hash
+            ));
+        }
+    }
+    Ok(serde_json::json!({ name: hash }).render(format, color))
+}
+
+// A new format address of the network, the input can be old or new format (any network)
+fn convert_address(input: &str, network: NetworkType) -> Result<String, String> {
+    if let Ok((_, payload)) = AddressPayload::from_address_string(input) {
+        return Ok(payload.to_address_string(network));
+    }
+    let address = AddressParser.parse(input)?;
+    Ok(address.to_string(network))
+}
+
+fn parse_delimiter(input: &str) -> Result<char, String> {
+    if input == "\\t" {
+        return Ok('\t');
+    }
+    let mut chars = input.chars();
+    match (chars.next(), chars.next()) {
+        (Some(delimiter), None) => Ok(delimiter),
+        _ => Err(format!(
+            "Invalid delimiter: {}, expected one character",
+            input
+        )),
+    }
+}
+
+// Decode a molecule binary of a packed type (one of `MOLECULE_TYPES`) to json, the structures
+// with an RPC type are rendered as the RPC does. The numbers are little endian in molecule.
+fn decode_molecule(type_name: &str, binary: &[u8]) -> Result<serde_json::Value, String> {
+    macro_rules! decode {
+        ($packed:ty) => {
+            <$packed>::from_slice(binary).map_err(|err| err.to_string())?
+        };
+    }
+    macro_rules! vec_json {
+        ($packed:ty, $convert:expr) => {
+            serde_json::json!(decode!($packed)
+                .into_iter()
+                .map($convert)
+                .collect::<Vec<_>>())
+        };
+    }
+    fn hex(data: &[u8]) -> String {
+        format!("0x{}", hex_string(data).unwrap())
+    }
+    fn opt_hex(data: Option<packed::Bytes>) -> Option<String> {
+        data.map(|data| hex(&data.raw_data()))
+    }
+    let value = match type_name {
+        "Uint32" => {
+            let mut bytes = [0u8; 4];
+            bytes.copy_from_slice(decode!(packed::Uint32).as_slice());
+            serde_json::json!(u32::from_le_bytes(bytes))
+        }
+        "Uint64" => {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(decode!(packed::Uint64).as_slice());
+            serde_json::json!(u64::from_le_bytes(bytes))
+        }
+        "Uint128" => {
+            let mut bytes = [0u8; 16];
+            bytes.copy_from_slice(decode!(packed::Uint128).as_slice());
+            // u128 is out of the range of JSON number
+            serde_json::json!(u128::from_le_bytes(bytes).to_string())
+        }
+        "Byte32" => serde_json::json!(hex(decode!(packed::Byte32).as_slice())),
+        "Uint256" => serde_json::json!(hex(decode!(packed::Uint256).as_slice())),
+        "Bytes" => serde_json::json!(hex(&decode!(packed::Bytes).raw_data())),
+        "BytesOpt" => serde_json::json!(opt_hex(decode!(packed::BytesOpt).to_opt())),
+        "BytesVec" => vec_json!(packed::BytesVec, |data| hex(&data.raw_data())),
+        "Byte32Vec" => vec_json!(packed::Byte32Vec, |data| hex(data.as_slice())),
+        "ScriptOpt" => serde_json::json!(decode!(packed::ScriptOpt).to_opt().map(RpcScript::from)),
+        "ProposalShortId" => serde_json::json!(ckb_jsonrpc_types::ProposalShortId::from(decode!(
+            packed::ProposalShortId
+        ))),
+        "UncleBlockVec" => vec_json!(packed::UncleBlockVec, ckb_jsonrpc_types::UncleBlock::from),
+        "TransactionVec" => vec_json!(packed::TransactionVec, RpcTransaction::from),
+        "ProposalShortIdVec" => vec_json!(
+            packed::ProposalShortIdVec,
+            ckb_jsonrpc_types::ProposalShortId::from
+        ),
+        "CellDepVec" => vec_json!(packed::CellDepVec, RpcCellDep::from),
+        "CellInputVec" => vec_json!(packed::CellInputVec, ckb_jsonrpc_types::CellInput::from),
+        "CellOutputVec" => vec_json!(packed::CellOutputVec, ckb_jsonrpc_types::CellOutput::from),
+        "Script" => serde_json::json!(RpcScript::from(decode!(packed::Script))),
+        "OutPoint" => {
+            serde_json::json!(ckb_jsonrpc_types::OutPoint::from(decode!(packed::OutPoint)))
+        }
+        "CellInput" => serde_json::json!(ckb_jsonrpc_types::CellInput::from(decode!(
+            packed::CellInput
+        ))),
+        "CellOutput" => serde_json::json!(ckb_jsonrpc_types::CellOutput::from(decode!(
+            packed::CellOutput
+        ))),
+        "CellDep" => serde_json::json!(RpcCellDep::from(decode!(packed::CellDep))),
+        "RawTransaction" => {
+            // The fields of `Transaction` but the witnesses
+            let raw = decode!(packed::RawTransaction);
+            let mut value = serde_json::json!(RpcTransaction::from(
+                packed::Transaction::new_builder().raw(raw).build()
+            ));
+            value.as_object_mut().unwrap().remove("witnesses");
+            value
+        }
+        "Transaction" => serde_json::json!(RpcTransaction::from(decode!(packed::Transaction))),
+        "RawHeader" => {
+            // The fields of `Header` but the nonce
+            let raw = decode!(packed::RawHeader);
+            let mut value = serde_json::json!(ckb_jsonrpc_types::Header::from(
+                packed::Header::new_builder().raw(raw).build()
+            ));
+            value.as_object_mut().unwrap().remove("nonce");
+            value
+        }
+        "Header" => serde_json::json!(ckb_jsonrpc_types::Header::from(decode!(packed::Header))),
+        "UncleBlock" => serde_json::json!(ckb_jsonrpc_types::UncleBlock::from(decode!(
+            packed::UncleBlock
+        ))),
+        "Block" => serde_json::json!(ckb_jsonrpc_types::Block::from(decode!(packed::Block))),
+        "CellbaseWitness" => {
+            let witness = decode!(packed::CellbaseWitness);
+            serde_json::json!({
+                "lock": RpcScript::from(witness.lock()),
+                "message": hex(&witness.message().raw_data()),
+            })
+        }
+        "WitnessArgs" => {
+            let witness = decode!(packed::WitnessArgs);
+            serde_json::json!({
+                "lock": opt_hex(witness.lock().to_opt()),
+                "input_type": opt_hex(witness.input_type().to_opt()),
+                "output_type": opt_hex(witness.output_type().to_opt()),
+            })
+        }
+        _ => return Err(format!("Unsupported molecule type: {}", type_name)),
+    };
+    Ok(value)
+}
+
+fn read_message(m: &ArgMatches) -> Result<Vec<u8>, String> {
+    match m.value_of("message-file") {
+        Some(path) => fs::read(path).map_err(|err| format!("Read {} failed: {}", path, err)),
+        None => Ok(m.value_of("message").unwrap().as_bytes().to_vec()),
+    }
+}
+
+/// blake2b_256("Nervos Message:" | message)
+pub fn message_digest(message: &[u8]) -> H256 {
+    let mut hasher = new_blake2b();
+    hasher.update(MESSAGE_MAGIC);
+    hasher.update(message);
+    let mut digest = [0u8; 32];
+    hasher.finalize(&mut digest);
+    H256::from(digest)
+}
+
+/// A committed transaction and the merkle proof (complete binary merkle tree) of it in a block.
+/// The transactions root of the header is the root of `[tx hashes root, witness hashes root]`.
+#[derive(Serialize, Deserialize)]
+struct TxProof {
+    tx_hash: H256,
+    block_hash: H256,
+    header: RpcHeaderView,
+    index: usize,
+    leaves: usize,
+    lemmas: Vec<H256>,
+    witnesses_root: H256,
+}
+
+impl TxProof {
+    fn new(block: &RpcBlockView, tx_hash: &H256) -> Result<TxProof, String> {
+        let tx_hashes = block
+            .transactions
+            .iter()
+            .map(|tx| tx.hash.clone())
+            .collect::<Vec<_>>();
+        let witness_hashes = block
+            .transactions
+            .iter()
+            .map(|tx| {
+                packed::Transaction::from(tx.inner.clone())
+                    .calc_witness_hash()
+                    .unpack()
+            })
+            .collect::<Vec<H256>>();
+        let index = tx_hashes
+            .iter()
+            .position(|hash| hash == tx_hash)
+            .ok_or_else(|| format!("Transaction {:#x} not found in block", tx_hash))?;
+        let (tx_hashes_root, lemmas) = merkle_proof(&tx_hashes, index);
+        let (witnesses_root, _) = merkle_proof(&witness_hashes, 0);
+        let root = merge_nodes(&tx_hashes_root, &witnesses_root);
+        if root != block.header.inner.transactions_root {
+            return Err(format!(
+                "Transactions root mismatch: {:#x} (header: {:#x})",
+                root, block.header.inner.transactions_root
+            ));
+        }
+        Ok(TxProof {
+            tx_hash: tx_hash.clone(),
+            block_hash: block.header.hash.clone(),
+            header: block.header.clone(),
+            index,
+            leaves: tx_hashes.len(),
+            lemmas,
+            witnesses_root,
+        })
+    }
+
+    fn verify(&self, expected_block_hash: Option<&H256>) -> Result<(), String> {
+        let header_hash: H256 = packed::Header::from(self.header.inner.clone())
+            .into_view()
+            .hash()
+            .unpack();
+        if header_hash != self.block_hash {
+            return Err(format!(
+                "Header hash {:#x} does not match the block hash",
+                header_hash
+            ));
+        }
+        if let Some(expected) = expected_block_hash {
+            if expected != &self.block_hash {
+                return Err(format!(
+                    "The proof is of another block: {:#x}",
+                    self.block_hash
+                ));
+            }
+        }
+        let tx_hashes_root =
+            merkle_root_of_proof(&self.tx_hash, self.index, self.leaves, &self.lemmas)?;
+        // The root of the 2 leaves tree
+        let root = merge_nodes(&tx_hashes_root, &self.witnesses_root);
+        if root != self.header.inner.transactions_root {
+            return Err("Transaction is not in the transactions root of the header".to_owned());
+        }
+        Ok(())
+    }
+}
+
+fn merge_nodes(left: &H256, right: &H256) -> H256 {
+    let mut hasher = new_blake2b();
+    hasher.update(left.as_bytes());
+    hasher.update(right.as_bytes());
+    let mut hash = [0u8; 32];
+    hasher.finalize(&mut hash);
+    H256::from(hash)
+}
+
+// The leaves are the last nodes of the tree, node i is the parent of node 2i+1 and 2i+2. Returns
+// the root and the siblings from the leaf up to the root.
+fn merkle_proof(leaves: &[H256], index: usize) -> (H256, Vec<H256>) {
+    let len = leaves.len();
+    let mut nodes = vec![H256::default(); len - 1];
+    nodes.extend(leaves.iter().cloned());
+    for i in (0..len - 1).rev() {
+        nodes[i] = merge_nodes(&nodes[2 * i + 1], &nodes[2 * i + 2]);
+    }
+    let mut lemmas = Vec::new();
+    let mut node = len - 1 + index;
+    while node > 0 {
+        let sibling = if node % 2 == 1 { node + 1 } else { node - 1 };
+        lemmas.push(nodes[sibling].clone());
+        node = (node - 1) / 2;
+    }
+    (nodes[0].clone(), lemmas)
+}
+
+fn merkle_root_of_proof(
+    leaf: &H256,
+    index: usize,
+    leaves: usize,
+    lemmas: &[H256],
+) -> Result<H256, String> {
+    if index >= leaves {
+        return Err(format!("Leaf index {} out of range {}", index, leaves));
+    }
+    let mut node = leaves - 1 + index;
+    let mut hash = leaf.clone();
+    for lemma in lemmas {
+        if node == 0 {
+            return Err("Too many lemmas in the proof".to_owned());
+        }
+        hash = if node % 2 == 1 {
+            merge_nodes(&hash, lemma)
+        } else {
+            merge_nodes(lemma, &hash)
+        };
+        node = (node - 1) / 2;
+    }
+    if node != 0 {
+        return Err("Missing lemmas in the proof".to_owned());
+    }
+    Ok(hash)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ckb_types::h256;
+
+    // The genesis block of a rc/v0.24 dev chain
+    const GENESIS_JSON: &str = include_str!("../../ckb-sdk/src/test-data/genesis_block.json");
+
+    fn leaves(count: u8) -> Vec<H256> {
+        (0..count).map(|i| H256::from([i + 1; 32])).collect()
+    }
+
+    #[test]
+    fn test_message_digest() {
+        // blake2b_256("Nervos Message:hello")
+        assert_eq!(
+            message_digest(b"hello"),
+            h256!("0xb0b1e8d9bf460ea3d4ad088e50f117b0b43aa414108f92841ce3e11e2ea1366e")
+        );
+        // Never the digest of the bare message
+        assert_ne!(message_digest(b"hello"), H256::from(blake2b_256(b"hello")));
+        assert_ne!(message_digest(b"hello"), message_digest(b"hello "));
+    }
+
+    #[test]
+    fn test_merkle_proof() {
+        let leaf = leaves(1);
+        assert_eq!(merkle_proof(&leaf, 0), (leaf[0].clone(), Vec::new()));
+
+        let pair = leaves(2);
+        let (root, lemmas) = merkle_proof(&pair, 1);
+        assert_eq!(root, merge_nodes(&pair[0], &pair[1]));
+        assert_eq!(lemmas, vec![pair[0].clone()]);
+
+        // nodes: [root, n1, n2, n3, l0, l1, l2, l3, l4], n1 = (n3, l0), n2 = (l1, l2), n3 = (l3, l4)
+        let five = leaves(5);
+        let n3 = merge_nodes(&five[3], &five[4]);
+        let n1 = merge_nodes(&n3, &five[0]);
+        let n2 = merge_nodes(&five[1], &five[2]);
+        let (root, lemmas) = merkle_proof(&five, 4);
+        assert_eq!(root, merge_nodes(&n1, &n2));
+        assert_eq!(lemmas, vec![five[3].clone(), five[0].clone(), n2]);
+    }
+
+    #[test]
+    fn test_merkle_root_of_proof() {
+        for count in 1..=9 {
+            let leaves = leaves(count);
+            for index in 0..leaves.len() {
+                let (root, lemmas) = merkle_proof(&leaves, index);
+                assert_eq!(
+                    merkle_root_of_proof(&leaves[index], index, leaves.len(), &lemmas),
+                    Ok(root.clone())
+                );
+                let other = (index + 1) % leaves.len();
+                if other != index {
+                    assert_ne!(
+                        merkle_root_of_proof(&leaves[other], index, leaves.len(), &lemmas),
+                        Ok(root)
+                    );
+                }
+            }
+        }
+        let leaves = leaves(4);
+        let (_, mut lemmas) = merkle_proof(&leaves, 2);
+        assert!(merkle_root_of_proof(&leaves[2], 4, 4, &lemmas).is_err());
+        assert!(merkle_root_of_proof(&leaves[2], 2, 4, &lemmas[1..]).is_err());
+        lemmas.push(leaves[0].clone());
+        assert!(merkle_root_of_proof(&leaves[2], 2, 4, &lemmas).is_err());
+    }
+
+    #[test]
+    fn test_tx_proof() {
+        let block: RpcBlockView = serde_json::from_str(GENESIS_JSON).unwrap();
+        for tx in &block.transactions {
+            let proof = TxProof::new(&block, &tx.hash).unwrap();
+            assert_eq!(proof.verify(Some(&block.header.hash)), Ok(()));
+            // Round trip of the proof file
+            let content = serde_json::to_string(&proof).unwrap();
+            let proof: TxProof = serde_json::from_str(&content).unwrap();
+            assert_eq!(proof.verify(None), Ok(()));
+        }
+
+        let mut proof = TxProof::new(&block, &block.transactions[1].hash).unwrap();
+        assert!(proof.verify(Some(&H256::default())).is_err());
+        proof.witnesses_root = H256::default();
+        assert!(proof.verify(None).is_err());
+
+        assert!(TxProof::new(&block, &H256::default()).is_err());
+    }
+}
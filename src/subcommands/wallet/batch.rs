@@ -0,0 +1,133 @@
+use ckb_sdk::{Address, TransferTransactionBuilder};
+use ckb_types::{bytes::Bytes, packed::CellInput};
+use serde_derive::Deserialize;
+
+use crate::utils::arg_parser::{AddressParser, ArgParser, CapacityParser};
+
+/// One recipient of `wallet transfer-batch`
+#[derive(Debug, Clone)]
+pub struct Recipient {
+    pub address: Address,
+    pub address_str: String,
+    pub capacity: u64,
+}
+
+#[derive(Deserialize)]
+struct RecipientItem {
+    address: String,
+    // Same format as --capacity (unit: CKB)
+    capacity: String,
+}
+
+/// Parse recipients file, JSON (`[{"address": "ckt1..", "capacity": "100.5"}]`) or CSV
+/// (`<address>,<capacity>` per line, empty lines and lines start with `#` are ignored, a header
+/// line is allowed).
+pub fn parse_recipients(content: &str) -> Result<Vec<Recipient>, String> {
+    let items = if content.trim_start().starts_with('[') {
+        serde_json::from_str::<Vec<RecipientItem>>(content)
+            .map_err(|err| format!("Parse recipients JSON failed: {}", err))?
+            .into_iter()
+            .enumerate()
+            .map(|(idx, item)| (idx + 1, item.address, item.capacity))
+            .collect::<Vec<_>>()
+    } else {
+        let mut items = Vec::new();
+        for (idx, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || (idx == 0 && line.starts_with("address"))
+            {
+                continue;
+            }
+            let parts = line.split(',').map(str::trim).collect::<Vec<_>>();
+            if parts.len() != 2 {
+                return Err(format!(
+                    "Invalid recipient line {}: {}, format: <address>,<capacity>",
+                    idx + 1,
+                    line
+                ));
+            }
+            items.push((idx + 1, parts[0].to_owned(), parts[1].to_owned()));
+        }
+        items
+    };
+
+    let mut recipients = Vec::with_capacity(items.len());
+    for (no, address_str, capacity) in items {
+        let address = AddressParser
+            .parse(&address_str)
+            .map_err(|err| format!("Invalid address of recipient {}: {}", no, err))?;
+        let capacity = CapacityParser
+            .parse(&capacity)
+            .map_err(|err| format!("Invalid capacity of recipient {}: {}", no, err))?;
+        recipients.push(Recipient {
+            address,
+            address_str,
+            capacity,
+        });
+    }
+    if recipients.is_empty() {
+        return Err("No recipients found".to_owned());
+    }
+    Ok(recipients)
+}
+
+/// Transaction builder paying all the recipients of a chunk
+pub fn batch_tx_args<'a>(
+    from_address: &'a Address,
+    to_data: &'a Bytes,
+    chunk: &'a [Recipient],
+    change_address: &Address,
+    total_capacity: u64,
+    tx_fee: u64,
+    inputs: Vec<CellInput>,
+) -> Result<TransferTransactionBuilder<'a>, String> {
+    let mut tx_args = TransferTransactionBuilder::new(
+        from_address,
+        total_capacity,
+        to_data,
+        &chunk[0].address,
+        chunk[0].capacity,
+        tx_fee,
+        inputs,
+    );
+    tx_args.set_change_address(change_address.clone());
+    for recipient in &chunk[1..] {
+        tx_args.add_output(recipient.address.clone(), recipient.capacity, Bytes::new())?;
+    }
+    Ok(tx_args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ckb_sdk::{NetworkType, ONE_CKB};
+
+    #[test]
+    fn test_parse_recipients() {
+        let address_a = Address::from_lock_arg(&[1u8; 20])
+            .unwrap()
+            .to_string(NetworkType::TestNet);
+        let address_b = Address::from_lock_arg(&[2u8; 20])
+            .unwrap()
+            .to_string(NetworkType::TestNet);
+        let csv = format!(
+            "address,capacity\n{},100\n\n# comment\n{},61.5\n",
+            address_a, address_b
+        );
+        let recipients = parse_recipients(&csv).unwrap();
+        assert_eq!(recipients.len(), 2);
+        assert_eq!(recipients[0].capacity, 100 * ONE_CKB);
+        assert_eq!(recipients[1].capacity, 61 * ONE_CKB + ONE_CKB / 2);
+
+        let json = format!(
+            r#"[{{"address": "{}", "capacity": "100"}}, {{"address": "{}", "capacity": "61.5"}}]"#,
+            address_a, address_b
+        );
+        let recipients = parse_recipients(&json).unwrap();
+        assert_eq!(recipients.len(), 2);
+        assert_eq!(recipients[1].address_str, address_b);
+
+        assert!(parse_recipients("address,capacity\n").is_err());
+        assert!(parse_recipients(&format!("{},100,1", address_a)).is_err());
+    }
+}
@@ -0,0 +1,454 @@
+use ckb_index::{CellIndex, LiveCellInfo, LockTxDelta};
+use ckb_jsonrpc_types::BlockNumber;
+use ckb_sdk::{
+    HttpIndexerRpcClient, IndexerCell, IndexerTx, IoType, JsonU64, Order, ScriptType, SearchKey,
+};
+use ckb_types::{
+    core::HeaderView,
+    packed::{Byte32, OutPoint, Script},
+    prelude::*,
+    H256,
+};
+use clap::ArgMatches;
+
+use super::WalletSubCommand;
+use crate::utils::arg_parser::{ArgParser, CapacityParser, FromStrParser};
+
+// Cells fetched by one `get_cells` call of ckb-indexer
+const INDEXER_PAGE_SIZE: u64 = 500;
+
+/// The live cells to search, from the local index database or the ckb-indexer of
+/// `--indexer-url`. ckb-indexer searches cells by script, the hash based queries only work with
+/// the local index database.
+pub(super) enum CellQuery {
+    Lock(Script),
+    Type(Script),
+    // The type scripts with the same code hash and hash type, and the args starting with the
+    // args of this script
+    TypeArgs(Script),
+    LockHash(Byte32),
+    TypeHash(Byte32),
+    CodeHash(Byte32),
+}
+
+impl CellQuery {
+    /// The lock or type script hash of the query
+    pub(super) fn script_hash(&self) -> Option<Byte32> {
+        match self {
+            CellQuery::Lock(script) | CellQuery::Type(script) => Some(script.calc_script_hash()),
+            CellQuery::LockHash(hash) | CellQuery::TypeHash(hash) => Some(hash.clone()),
+            CellQuery::TypeArgs(_) | CellQuery::CodeHash(_) => None,
+        }
+    }
+
+    /// The cells of a `TypeArgs` query are ordered by args first, then block number
+    pub(super) fn ordered_by_number(&self) -> bool {
+        match self {
+            CellQuery::TypeArgs(_) => false,
+            _ => true,
+        }
+    }
+}
+
+/// Filters of `--from-block`, `--to-block`, `--min-capacity` and `--limit` to page the live
+/// cells of large accounts.
+pub(super) struct CellFilter {
+    pub(super) from_number: Option<u64>,
+    pub(super) to_number: Option<u64>,
+    pub(super) min_capacity: u64,
+    pub(super) limit: Option<usize>,
+}
+
+impl CellFilter {
+    pub(super) fn from_matches(m: &ArgMatches) -> Result<CellFilter, String> {
+        Ok(CellFilter {
+            from_number: FromStrParser::<u64>::default().from_matches_opt(m, "from", false)?,
+            to_number: FromStrParser::<u64>::default().from_matches_opt(m, "to", false)?,
+            min_capacity: CapacityParser
+                .from_matches_opt(m, "min-capacity", false)?
+                .unwrap_or(0),
+            limit: FromStrParser::<usize>::default().from_matches_opt(m, "limit", false)?,
+        })
+    }
+}
+
+impl<'a> WalletSubCommand<'a> {
+    /// Live cells of the query from `from_number` (block number), the cells are iterated by
+    /// block number (see `CellQuery::ordered_by_number`) and `terminator` works like the one of
+    /// `IndexDatabase::get_live_cell_infos`.
+    pub(super) fn live_cells<F>(
+        &mut self,
+        query: CellQuery,
+        from_number: Option<u64>,
+        terminator: F,
+    ) -> Result<Vec<LiveCellInfo>, String>
+    where
+        F: FnMut(usize, &LiveCellInfo) -> (bool, bool),
+    {
+        if let CellQuery::TypeArgs(type_script) = query {
+            return self.live_cells_by_type_args(type_script, from_number, terminator);
+        }
+        if let Some(client) = self.indexer_client.as_mut() {
+            let search_key = indexer_search_key(query)?;
+            return get_indexer_cells(client, search_key, from_number, terminator);
+        }
+        self.with_db(|db| match query {
+            CellQuery::Lock(lock) => {
+                db.get_live_cells_by_lock(lock.calc_script_hash(), from_number, terminator)
+            }
+            CellQuery::Type(type_script) => {
+                db.get_live_cells_by_type(type_script.calc_script_hash(), from_number, terminator)
+            }
+            CellQuery::LockHash(lock_hash) => {
+                db.get_live_cells_by_lock(lock_hash, from_number, terminator)
+            }
+            CellQuery::TypeHash(type_hash) => {
+                db.get_live_cells_by_type(type_hash, from_number, terminator)
+            }
+            CellQuery::CodeHash(code_hash) => {
+                db.get_live_cells_by_code(code_hash, from_number, terminator)
+            }
+            CellQuery::TypeArgs(_) => unreachable!(),
+        })
+    }
+
+    // The cells of an args prefix are iterated in the key order of both the local index and
+    // ckb-indexer: by args, then block number. So the cells before `from_number` are skipped
+    // instead of starting from it, and `terminator` stops the iteration (like a limit).
+    fn live_cells_by_type_args<F>(
+        &mut self,
+        type_script: Script,
+        from_number: Option<u64>,
+        mut terminator: F,
+    ) -> Result<Vec<LiveCellInfo>, String>
+    where
+        F: FnMut(usize, &LiveCellInfo) -> (bool, bool),
+    {
+        if let Some(client) = self.indexer_client.as_mut() {
+            let search_key = indexer_search_key(CellQuery::TypeArgs(type_script))?;
+            // Also skipped by `get_indexer_cells`
+            return get_indexer_cells(client, search_key, from_number, terminator);
+        }
+        let from_number = from_number.unwrap_or(0);
+        let mut idx = 0;
+        self.with_db(|db| {
+            db.get_live_cells_by_type_args(type_script, |_, info| {
+                if info.number < from_number {
+                    return (false, false);
+                }
+                let result = terminator(idx, info);
+                idx += 1;
+                result
+            })
+        })
+    }
+
+    /// One page of the live cells matching `filter` and `predicate`, with the block number the
+    /// next page starts from (`None` if this is the last page). A page always ends at a block
+    /// boundary, so it may have a few more cells than the limit but no cell is repeated or
+    /// skipped between pages. The cells not ordered by block number (see
+    /// `CellQuery::ordered_by_number`) have only one page, cut at the limit.
+    pub(super) fn live_cells_page<F>(
+        &mut self,
+        query: CellQuery,
+        filter: &CellFilter,
+        mut predicate: F,
+    ) -> Result<(Vec<LiveCellInfo>, Option<u64>), String>
+    where
+        F: FnMut(&LiveCellInfo) -> bool,
+    {
+        let to_number = filter.to_number.unwrap_or(std::u64::MAX);
+        let limit = filter.limit.unwrap_or(std::usize::MAX);
+        let mut count = 0;
+        let mut last_number = None;
+        let mut next_from_block = None;
+        let ordered_by_number = query.ordered_by_number();
+        let infos = self.live_cells(query, filter.from_number, |_, info| {
+            if info.number > to_number {
+                return (ordered_by_number, false);
+            }
+            if count >= limit && !ordered_by_number {
+                return (true, false);
+            }
+            if count >= limit && last_number != Some(info.number) {
+                next_from_block = Some(info.number);
+                return (true, false);
+            }
+            let push_info = info.capacity >= filter.min_capacity && predicate(info);
+            if push_info {
+                count += 1;
+                last_number = Some(info.number);
+            }
+            (false, push_info)
+        })?;
+        Ok((infos, next_from_block))
+    }
+
+    /// Total capacity of the live cells of a lock (`CellQuery::Lock` or `CellQuery::LockHash`),
+    /// `None` if the lock never owned any cell.
+    pub(super) fn live_capacity(&mut self, query: CellQuery) -> Result<Option<u64>, String> {
+        if let Some(client) = self.indexer_client.as_mut() {
+            let search_key = indexer_search_key(query)?;
+            return client
+                .get_cells_capacity(search_key)
+                .call()
+                .map(|capacity| capacity.0.map(|capacity| capacity.capacity.value()))
+                .map_err(|err| format!("get_cells_capacity by indexer RPC call failed: {}", err));
+        }
+        let lock_hash = match query {
+            CellQuery::Lock(lock) => lock.calc_script_hash(),
+            CellQuery::LockHash(lock_hash) => lock_hash,
+            _ => return Err("Capacity is only indexed by lock".to_owned()),
+        };
+        self.with_db(|db| db.get_capacity(lock_hash))
+    }
+
+    /// Position of the first lock without any live cell
+    pub(super) fn first_empty_lock(&mut self, locks: Vec<Script>) -> Result<Option<usize>, String> {
+        if self.indexer_client.is_none() {
+            return self.with_db(|db| {
+                locks
+                    .into_iter()
+                    .position(|lock| db.get_capacity(lock.calc_script_hash()).unwrap_or(0) == 0)
+            });
+        }
+        for (position, lock) in locks.into_iter().enumerate() {
+            if self.live_capacity(CellQuery::Lock(lock))?.unwrap_or(0) == 0 {
+                return Ok(Some(position));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Transactions changing capacity of a lock (newest first). The records of ckb-indexer only
+    /// have the positions of the cells, the capacities and timestamps are fetched from the node.
+    pub(super) fn lock_txs(
+        &mut self,
+        query: CellQuery,
+        limit: usize,
+    ) -> Result<Vec<LockTxDelta>, String> {
+        if self.indexer_client.is_none() {
+            let lock_hash = query
+                .script_hash()
+                .ok_or_else(|| "Transactions are only indexed by lock".to_owned())?;
+            return self.with_db(|db| db.get_lock_txs(lock_hash, limit));
+        }
+        let search_key = indexer_search_key(query)?;
+        let client = self.indexer_client.as_mut().unwrap();
+        // One record for every input or output, the records of a transaction are adjacent
+        let mut records: Vec<IndexerTx> = Vec::new();
+        let mut tx_count = 0;
+        let mut cursor = None;
+        'pages: loop {
+            let page = client
+                .get_transactions(
+                    search_key.clone(),
+                    Order::Desc,
+                    JsonU64(INDEXER_PAGE_SIZE),
+                    cursor,
+                )
+                .call()
+                .map_err(|err| format!("get_transactions by indexer RPC call failed: {}", err))?;
+            let page_size = page.objects.len() as u64;
+            for record in page.objects {
+                let is_new_tx = records
+                    .last()
+                    .map(|last| last.tx_hash != record.tx_hash)
+                    .unwrap_or(true);
+                if is_new_tx {
+                    if tx_count >= limit {
+                        break 'pages;
+                    }
+                    tx_count += 1;
+                }
+                records.push(record);
+            }
+            if page_size < INDEXER_PAGE_SIZE {
+                break;
+            }
+            cursor = Some(page.last_cursor);
+        }
+
+        let mut deltas: Vec<LockTxDelta> = Vec::new();
+        for record in records {
+            let is_new_tx = deltas
+                .last()
+                .map(|last| last.tx_hash != record.tx_hash)
+                .unwrap_or(true);
+            if is_new_tx {
+                let block_number = record.block_number.value();
+                deltas.push(LockTxDelta {
+                    tx_hash: record.tx_hash.clone(),
+                    tx_index: record.tx_index.value() as u32,
+                    block_number,
+                    block_timestamp: self.block_timestamp(block_number)?,
+                    input_capacity: 0,
+                    output_capacity: 0,
+                });
+            }
+            let io_index = record.io_index.value() as u32;
+            match record.io_type {
+                IoType::Input => {
+                    let out_point = self.previous_output(&record.tx_hash, io_index)?;
+                    let capacity: u64 = self.get_cell_output(&out_point)?.capacity().unpack();
+                    deltas.last_mut().unwrap().input_capacity += capacity;
+                }
+                IoType::Output => {
+                    let out_point = OutPoint::new(record.tx_hash.pack(), io_index);
+                    let capacity: u64 = self.get_cell_output(&out_point)?.capacity().unpack();
+                    deltas.last_mut().unwrap().output_capacity += capacity;
+                }
+            }
+        }
+        Ok(deltas)
+    }
+
+    /// The block number the ckb-indexer or the local index database synced to
+    pub(super) fn indexed_number(&mut self) -> Result<Option<u64>, String> {
+        match self.indexer_client.as_mut() {
+            Some(client) => client
+                .get_tip()
+                .call()
+                .map(|tip| tip.0.map(|tip| tip.block_number.value()))
+                .map_err(|err| format!("get_tip by indexer RPC call failed: {}", err)),
+            None => self.with_db(|db| db.last_number()),
+        }
+    }
+
+    fn previous_output(&mut self, tx_hash: &H256, input_index: u32) -> Result<OutPoint, String> {
+        self.rpc_client
+            .get_transaction(tx_hash.clone())
+            .call()
+            .map_err(|err| format!("Send get_transaction error: {}", err))?
+            .0
+            .ok_or_else(|| format!("Transaction not found: {:#x}", tx_hash))?
+            .transaction
+            .inner
+            .inputs
+            .get(input_index as usize)
+            .map(|input| input.previous_output.clone().into())
+            .ok_or_else(|| format!("Input not found: {:#x}-{}", tx_hash, input_index))
+    }
+
+    fn block_timestamp(&mut self, number: u64) -> Result<u64, String> {
+        self.rpc_client
+            .get_header_by_number(BlockNumber::from(number))
+            .call()
+            .map_err(|err| format!("Send get_header_by_number error: {}", err))?
+            .0
+            .map(|header| HeaderView::from(header).timestamp())
+            .ok_or_else(|| format!("Header #{} not found", number))
+    }
+
+    /// The commands only supported by the local index database (like `top-capacity`)
+    pub(super) fn check_local_index(&self, command: &str) -> Result<(), String> {
+        if self.indexer_client.is_some() {
+            Err(format!(
+                "`{}` needs the local index database, it is not supported with --indexer-url",
+                command
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Check the local index database is ready, ckb-indexer is always ready
+    pub(super) fn check_index_ready(&mut self) -> Result<(), String> {
+        if self.indexer_client.is_some() {
+            Ok(())
+        } else {
+            self.with_db(|_| ())
+        }
+    }
+}
+
+fn indexer_search_key(query: CellQuery) -> Result<SearchKey, String> {
+    match query {
+        CellQuery::Lock(lock) => Ok(SearchKey {
+            script: lock.into(),
+            script_type: ScriptType::Lock,
+        }),
+        CellQuery::Type(type_script) => Ok(SearchKey {
+            script: type_script.into(),
+            script_type: ScriptType::Type,
+        }),
+        // ckb-indexer matches the args of the search key as a prefix
+        CellQuery::TypeArgs(type_script) => Ok(SearchKey {
+            script: type_script.into(),
+            script_type: ScriptType::Type,
+        }),
+        CellQuery::LockHash(_) | CellQuery::TypeHash(_) | CellQuery::CodeHash(_) => Err(
+            "ckb-indexer searches cells by script, the lock hash, type hash and code hash queries need the local index database (use --address instead of --lock-hash)"
+                .to_owned(),
+        ),
+    }
+}
+
+fn get_indexer_cells<F>(
+    client: &mut HttpIndexerRpcClient,
+    search_key: SearchKey,
+    from_number: Option<u64>,
+    mut terminator: F,
+) -> Result<Vec<LiveCellInfo>, String>
+where
+    F: FnMut(usize, &LiveCellInfo) -> (bool, bool),
+{
+    let from_number = from_number.unwrap_or(0);
+    let mut infos = Vec::new();
+    let mut idx = 0;
+    let mut cursor = None;
+    loop {
+        let page = client
+            .get_cells(
+                search_key.clone(),
+                Order::Asc,
+                JsonU64(INDEXER_PAGE_SIZE),
+                cursor,
+            )
+            .call()
+            .map_err(|err| format!("get_cells by indexer RPC call failed: {}", err))?;
+        let page_size = page.objects.len() as u64;
+        for cell in page.objects {
+            if cell.block_number.value() < from_number {
+                continue;
+            }
+            let info = to_live_cell_info(cell);
+            let (stop, push_info) = terminator(idx, &info);
+            if push_info {
+                infos.push(info);
+            }
+            if stop {
+                return Ok(infos);
+            }
+            idx += 1;
+        }
+        if page_size < INDEXER_PAGE_SIZE {
+            return Ok(infos);
+        }
+        cursor = Some(page.last_cursor);
+    }
+}
+
+fn to_live_cell_info(cell: IndexerCell) -> LiveCellInfo {
+    let lock: Script = cell.output.lock.into();
+    let type_hashes = cell.output.type_.map(|type_script| {
+        let type_script: Script = type_script.into();
+        let code_hash: H256 = type_script.code_hash().unpack();
+        let script_hash: H256 = type_script.calc_script_hash().unpack();
+        (code_hash, script_hash)
+    });
+    let output_index = cell.out_point.index.value() as u32;
+    LiveCellInfo {
+        tx_hash: cell.out_point.tx_hash,
+        tx_index: output_index,
+        data_bytes: cell.output_data.as_bytes().len() as u64,
+        lock_hash: lock.calc_script_hash().unpack(),
+        type_hashes,
+        capacity: cell.output.capacity.value(),
+        number: cell.block_number.value(),
+        index: CellIndex {
+            tx_index: cell.tx_index.value() as u32,
+            output_index,
+        },
+    }
+}
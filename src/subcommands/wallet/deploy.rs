@@ -0,0 +1,393 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::slice;
+
+use ckb_hash::blake2b_256;
+use ckb_index::LiveCellInfo;
+use ckb_jsonrpc_types::CellWithStatus;
+use ckb_sdk::{type_id_script, Address, TransferTransactionBuilder, TYPE_ID_CODE_HASH};
+use ckb_types::{
+    bytes::Bytes,
+    core::{Capacity, ScriptHashType},
+    packed::{CellInput, CellOutput, OutPoint, Script},
+    prelude::*,
+    H256,
+};
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use super::{
+    check_to_address_prefix, is_live_cell, is_secp_cell, privkey_or_account, unsigned_transaction,
+    CellQuery, WalletSubCommand,
+};
+use crate::utils::{
+    arg,
+    arg_parser::{ArgParser, CapacityParser, FilePathParser, LabeledAddressParser},
+    deployments::{Deployment, Deployments},
+    i18n::tf,
+    other::{get_network_type, now_seconds},
+    policy::SignKind,
+    printer::{OutputFormat, Printable},
+};
+
+pub fn subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("deploy")
+        .about("Deploy a script binary as the data of a new cell, the code hash (and type id) is recorded in ~/.ckb-cli/deployments")
+        .arg(arg::privkey_path().required_unless("from-account"))
+        .arg(arg::from_account().required_unless("privkey-path"))
+        .arg(
+            Arg::with_name("binary-path")
+                .long("binary-path")
+                .takes_value(true)
+                .required(true)
+                .validator(|input| FilePathParser::new(true).validate(input))
+                .help("Script binary file path"),
+        )
+        .arg(
+            Arg::with_name("name")
+                .long("name")
+                .takes_value(true)
+                .help("Name of the deployment (default: file name of the binary)"),
+        )
+        .arg(
+            Arg::with_name("type-id")
+                .long("type-id")
+                .help("Type the cell by a type id script, the script can be upgraded and referenced by the type hash"),
+        )
+        .arg(arg::to_address().help("Owner address or label of the code cell (default: sender)"))
+        .arg(arg::tx_fee().required(true))
+        .arg(arg::with_password())
+        .arg(arg::include_frozen())
+        .arg(arg::coin_selection())
+        .arg(arg::wait_for_commit())
+        .arg(arg::yes())
+        .arg(arg::force_fee())
+        .arg(arg::override_policy())
+}
+
+pub fn upgrade_subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("upgrade-contract")
+        .about("Replace the binary of a type id deployment (the type hash is kept), signed by the owner of the code cell")
+        .arg(arg::privkey_path().required_unless("from-account"))
+        .arg(arg::from_account().required_unless("privkey-path"))
+        .arg(
+            Arg::with_name("name")
+                .long("name")
+                .takes_value(true)
+                .required(true)
+                .help("Name of the deployment (see list-deployments)"),
+        )
+        .arg(
+            Arg::with_name("binary-path")
+                .long("binary-path")
+                .takes_value(true)
+                .required(true)
+                .validator(|input| FilePathParser::new(true).validate(input))
+                .help("New script binary file path"),
+        )
+        .arg(arg::tx_fee().required(true))
+        .arg(arg::with_password())
+        .arg(arg::include_frozen())
+        .arg(arg::coin_selection())
+        .arg(arg::wait_for_commit())
+        .arg(arg::yes())
+        .arg(arg::force_fee())
+        .arg(arg::override_policy())
+}
+
+pub fn list_subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("list-deployments").about("List the script binaries deployed by `deploy`")
+}
+
+impl<'a> WalletSubCommand<'a> {
+    pub fn deploy(
+        &mut self,
+        m: &ArgMatches,
+        format: OutputFormat,
+        color: bool,
+        debug: bool,
+    ) -> Result<String, String> {
+        let (from_address, from_privkey, from_account) = privkey_or_account(m)?;
+        let binary_path: PathBuf = FilePathParser::new(true).from_matches(m, "binary-path")?;
+        let tx_fee: u64 = CapacityParser.from_matches(m, "tx-fee")?;
+        let to_address: Address = LabeledAddressParser
+            .from_matches_opt(m, "to-address", false)?
+            .unwrap_or_else(|| from_address.clone());
+        let name = match m.value_of("name") {
+            Some(name) => name.to_owned(),
+            None => binary_path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .ok_or_else(|| format!("Invalid binary path: {:?}", binary_path))?,
+        };
+        let binary = Bytes::from(fs::read(&binary_path).map_err(|err| err.to_string())?);
+        let with_type_id = m.is_present("type-id");
+
+        let network_type = get_network_type(self.rpc_client)?;
+        check_to_address_prefix(m, network_type)?;
+        let genesis_info = self.genesis_info()?;
+        let from_lock = genesis_info.lock_script(&from_address)?;
+        let to_lock = genesis_info.lock_script(&to_address)?;
+        // The type id args depends on the inputs, its length is fixed
+        let type_placeholder = if with_type_id {
+            Some(type_id_script(&CellInput::default(), 0))
+        } else {
+            None
+        };
+        let capacity = code_cell_capacity(&to_lock, type_placeholder, binary.len())?;
+
+        // For check index database is ready
+        self.check_index_ready()?;
+        let (infos, total_capacity) =
+            self.collect_live_cells(m, from_lock, capacity + tx_fee, is_secp_cell)?;
+        if total_capacity < capacity + tx_fee {
+            return Err(tf(
+                "capacity-not-enough",
+                &[&from_address.to_string(network_type), &total_capacity],
+            ));
+        }
+        let policy = self.check_policy(
+            m,
+            SignKind::Transfer,
+            &from_address,
+            slice::from_ref(&to_address),
+            capacity,
+        )?;
+        let inputs = infos.iter().map(LiveCellInfo::input).collect::<Vec<_>>();
+        // The code cell is the first output
+        let type_script = if with_type_id {
+            Some(type_id_script(&inputs[0], 0))
+        } else {
+            None
+        };
+        let change_address = self.change_address(from_account.as_ref(), &from_address)?;
+        let mut tx_args = TransferTransactionBuilder::new(
+            &from_address,
+            total_capacity,
+            &binary,
+            &to_address,
+            capacity,
+            tx_fee,
+            inputs,
+        );
+        tx_args.set_change_address(change_address);
+        tx_args.set_change_policy(self.change_policy);
+        if let Some(type_script) = type_script.as_ref() {
+            tx_args.set_output_type(type_script.clone(), None);
+        }
+        let transaction = self.sign_transfer(
+            m,
+            &mut tx_args,
+            &genesis_info,
+            from_privkey.as_ref(),
+            from_account.as_ref(),
+        )?;
+        let tx_hash: H256 = transaction.hash().unpack();
+        if let Some(lock_arg) = from_account.as_ref() {
+            // Not recorded, the transaction is signed and sent by others
+            if self.is_watch_only(lock_arg)? {
+                return Ok(unsigned_transaction(transaction, format, color));
+            }
+        }
+        self.send_transaction(m, transaction, format, color, debug)?;
+        if let Some(policy) = policy {
+            policy.record(capacity)?;
+        }
+        let deployment = new_deployment(tx_hash, &binary_path, &binary, type_script.as_ref());
+        Ok(record_deployment(name, deployment).render(format, color))
+    }
+
+    /// Replace the binary of a type id deployment, the type script (so the type hash) is kept
+    pub fn upgrade_contract(
+        &mut self,
+        m: &ArgMatches,
+        format: OutputFormat,
+        color: bool,
+        debug: bool,
+    ) -> Result<String, String> {
+        let (from_address, from_privkey, from_account) = privkey_or_account(m)?;
+        let name = m.value_of("name").unwrap().to_owned();
+        let binary_path: PathBuf = FilePathParser::new(true).from_matches(m, "binary-path")?;
+        let tx_fee: u64 = CapacityParser.from_matches(m, "tx-fee")?;
+        let binary = Bytes::from(fs::read(&binary_path).map_err(|err| err.to_string())?);
+
+        let deployment = Deployments::load_default()?
+            .get(&name)
+            .cloned()
+            .ok_or_else(|| format!("Deployment not found: {}", name))?;
+        let type_script = match deployment.type_id_args.as_ref() {
+            Some(args) => Script::new_builder()
+                .code_hash(TYPE_ID_CODE_HASH.pack())
+                .hash_type(ScriptHashType::Type.into())
+                .args(args.as_bytes().pack())
+                .build(),
+            None => {
+                return Err(format!(
+                    "Deployment {} is not typed by type id, can not be upgraded",
+                    name
+                ));
+            }
+        };
+
+        let network_type = get_network_type(self.rpc_client)?;
+        let genesis_info = self.genesis_info()?;
+        let from_lock = genesis_info.lock_script(&from_address)?;
+        let (code_out_point, code_output) = self.find_type_id_cell(&deployment, &type_script)?;
+        if code_output.lock() != from_lock {
+            return Err(format!(
+                "The code cell of {} is not owned by sender {}",
+                name,
+                from_address.to_string(network_type)
+            ));
+        }
+        let old_capacity: u64 = code_output.capacity().unpack();
+        let capacity = code_cell_capacity(&from_lock, Some(type_script.clone()), binary.len())?;
+
+        // For check index database is ready
+        self.check_index_ready()?;
+        // The capacity of the old code cell pays for the new one first
+        let (infos, total_capacity) = self.collect_live_cells(
+            m,
+            from_lock,
+            (capacity + tx_fee).saturating_sub(old_capacity),
+            is_secp_cell,
+        )?;
+        let from_capacity = old_capacity + total_capacity;
+        if from_capacity < capacity + tx_fee {
+            return Err(tf(
+                "capacity-not-enough",
+                &[&from_address.to_string(network_type), &from_capacity],
+            ));
+        }
+        // The new code cell is owned by the sender, as a transfer to itself
+        let policy = self.check_policy(
+            m,
+            SignKind::Transfer,
+            &from_address,
+            slice::from_ref(&from_address),
+            capacity,
+        )?;
+        // The old code cell must be the only input typed by the type id script
+        let inputs = std::iter::once(CellInput::new(code_out_point, 0))
+            .chain(infos.iter().map(LiveCellInfo::input))
+            .collect::<Vec<_>>();
+        let change_address = self.change_address(from_account.as_ref(), &from_address)?;
+        let mut tx_args = TransferTransactionBuilder::new(
+            &from_address,
+            from_capacity,
+            &binary,
+            &from_address,
+            capacity,
+            tx_fee,
+            inputs,
+        );
+        tx_args.set_change_address(change_address);
+        tx_args.set_change_policy(self.change_policy);
+        tx_args.set_output_type(type_script.clone(), None);
+        let transaction = self.sign_transfer(
+            m,
+            &mut tx_args,
+            &genesis_info,
+            from_privkey.as_ref(),
+            from_account.as_ref(),
+        )?;
+        let tx_hash: H256 = transaction.hash().unpack();
+        if let Some(lock_arg) = from_account.as_ref() {
+            if self.is_watch_only(lock_arg)? {
+                return Ok(unsigned_transaction(transaction, format, color));
+            }
+        }
+        self.send_transaction(m, transaction, format, color, debug)?;
+        if let Some(policy) = policy {
+            policy.record(capacity)?;
+        }
+        let deployment = new_deployment(tx_hash, &binary_path, &binary, Some(&type_script));
+        Ok(record_deployment(name, deployment).render(format, color))
+    }
+
+    // The recorded code cell, or the live cell of the type id script if it is upgraded by
+    // others (the index database is searched)
+    fn find_type_id_cell(
+        &mut self,
+        deployment: &Deployment,
+        type_script: &Script,
+    ) -> Result<(OutPoint, CellOutput), String> {
+        let type_hash = type_script.calc_script_hash();
+        let recorded = OutPoint::new(deployment.tx_hash.pack(), deployment.index);
+        let mut candidates = vec![recorded];
+        candidates.extend(
+            self.live_cells(CellQuery::Type(type_script.clone()), None, |_, _| {
+                (true, true)
+            })?
+            .iter()
+            .map(LiveCellInfo::out_point),
+        );
+        for out_point in candidates {
+            let resp: CellWithStatus = self
+                .rpc_client
+                .get_live_cell(out_point.clone().into(), false)
+                .call()
+                .map_err(|err| format!("get_live_cell by RPC call failed: {}", err))?;
+            if !is_live_cell(&resp) {
+                continue;
+            }
+            if let Some(cell) = resp.cell {
+                let output: CellOutput = cell.output.into();
+                if output.type_().to_opt().as_ref() == Some(type_script) {
+                    return Ok((out_point, output));
+                }
+            }
+        }
+        Err(format!(
+            "Live code cell of type hash {:#x} not found",
+            type_hash
+        ))
+    }
+}
+
+fn new_deployment(
+    tx_hash: H256,
+    binary_path: &Path,
+    binary: &Bytes,
+    type_script: Option<&Script>,
+) -> Deployment {
+    Deployment {
+        tx_hash,
+        // The code cell is always the first output
+        index: 0,
+        data_hash: H256::from_slice(&blake2b_256(binary)).unwrap(),
+        type_id_args: type_script
+            .map(|script| H256::from_slice(&script.args().raw_data()).unwrap()),
+        type_hash: type_script.map(|script| script.calc_script_hash().unpack()),
+        binary_path: binary_path.to_string_lossy().into_owned(),
+        deployed_at: now_seconds(),
+    }
+}
+
+// The transaction is sent, failing to record the deployment is not fatal
+fn record_deployment(name: String, deployment: Deployment) -> serde_json::Value {
+    if let Err(err) =
+        Deployments::load_default().and_then(|mut list| list.add(name.clone(), deployment.clone()))
+    {
+        eprintln!("{}", tf("record-deployment-failed", &[&err]));
+    }
+    serde_json::json!({
+        "name": name,
+        "deployment": deployment,
+    })
+}
+
+// Capacity of the cell with the binary as data
+fn code_cell_capacity(
+    lock: &Script,
+    type_script: Option<Script>,
+    data_len: usize,
+) -> Result<u64, String> {
+    let data_capacity = Capacity::bytes(data_len).map_err(|err| err.to_string())?;
+    CellOutput::new_builder()
+        .lock(lock.clone())
+        .type_(type_script.pack())
+        .build()
+        .occupied_capacity(data_capacity)
+        .map(Capacity::as_u64)
+        .map_err(|err| err.to_string())
+}
@@ -0,0 +1,124 @@
+use ckb_sdk::ONE_CKB;
+use ckb_types::{bytes::Bytes, core::TransactionView, packed::CellOutput, prelude::*};
+
+/// Refuse to send the transactions paying absurd fee (like `--capacity` and `--tx-fee` swapped)
+/// unless `--force-fee` is given, configured by `max_tx_fee` (unit: shannons) and
+/// `max_fee_percent` in `~/.ckb-cli/config`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct FeeGuard {
+    /// Unit: shannons
+    pub max_fee: u64,
+    /// Max fee in percentage of the transferred capacity
+    pub max_fee_percent: u64,
+}
+
+impl Default for FeeGuard {
+    fn default() -> FeeGuard {
+        FeeGuard {
+            max_fee: ONE_CKB,
+            max_fee_percent: 5,
+        }
+    }
+}
+
+impl FeeGuard {
+    pub fn check(&self, fee: u64, transferred: u64) -> Result<(), String> {
+        if fee > self.max_fee {
+            return Err(format!(
+                "Transaction fee {} shannons exceeds the max fee {} shannons, use --force-fee to send it anyway",
+                fee, self.max_fee
+            ));
+        }
+        if u128::from(fee) * 100 > u128::from(transferred) * u128::from(self.max_fee_percent) {
+            return Err(format!(
+                "Transaction fee {} shannons exceeds {}% of the transferred capacity {} shannons, use --force-fee to send it anyway",
+                fee, self.max_fee_percent, transferred
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// The fee (inputs minus outputs) and the capacity transferred to other locks than the inputs
+/// (all the outputs when they go back to the input locks, like deposit to NervosDAO). None when
+/// the outputs exceed the inputs (NervosDAO interest included).
+pub fn fee_and_transferred(
+    transaction: &TransactionView,
+    input_cells: &[(CellOutput, Bytes)],
+) -> Option<(u64, u64)> {
+    let input_capacity: u64 = input_cells
+        .iter()
+        .map(|(output, _)| Unpack::<u64>::unpack(&output.capacity()))
+        .sum();
+    let outputs = transaction.outputs().into_iter().collect::<Vec<_>>();
+    let output_capacity: u64 = outputs
+        .iter()
+        .map(|output| Unpack::<u64>::unpack(&output.capacity()))
+        .sum();
+    let fee = input_capacity.checked_sub(output_capacity)?;
+    let transferred: u64 = outputs
+        .iter()
+        .filter(|output| {
+            input_cells
+                .iter()
+                .all(|(input, _)| input.lock().as_slice() != output.lock().as_slice())
+        })
+        .map(|output| Unpack::<u64>::unpack(&output.capacity()))
+        .sum();
+    if transferred == 0 {
+        Some((fee, output_capacity))
+    } else {
+        Some((fee, transferred))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ckb_types::{
+        core::{Capacity, TransactionBuilder},
+        packed::Script,
+    };
+
+    fn cell(arg: u8, capacity: u64) -> CellOutput {
+        CellOutput::new_builder()
+            .lock(
+                Script::new_builder()
+                    .args(Bytes::from(vec![arg]).pack())
+                    .build(),
+            )
+            .capacity(Capacity::shannons(capacity).pack())
+            .build()
+    }
+
+    #[test]
+    fn test_fee_guard() {
+        let inputs = vec![(cell(1, 1000 * ONE_CKB), Bytes::new())];
+        let transaction = TransactionBuilder::default()
+            .output(cell(2, 100 * ONE_CKB))
+            .output(cell(1, 899 * ONE_CKB))
+            .build();
+        assert_eq!(
+            fee_and_transferred(&transaction, &inputs),
+            Some((ONE_CKB, 100 * ONE_CKB))
+        );
+        let transaction = TransactionBuilder::default()
+            .output(cell(1, 999 * ONE_CKB))
+            .build();
+        assert_eq!(
+            fee_and_transferred(&transaction, &inputs),
+            Some((ONE_CKB, 999 * ONE_CKB))
+        );
+        let transaction = TransactionBuilder::default()
+            .output(cell(1, 1001 * ONE_CKB))
+            .build();
+        assert_eq!(fee_and_transferred(&transaction, &inputs), None);
+
+        let guard = FeeGuard::default();
+        assert!(guard.check(ONE_CKB / 1000, 100 * ONE_CKB).is_ok());
+        assert!(guard.check(ONE_CKB, 100 * ONE_CKB).is_ok());
+        assert!(guard.check(ONE_CKB + 1, 100 * ONE_CKB).is_err());
+        // 8% of the transferred capacity
+        assert!(guard.check(ONE_CKB / 2, 61 * ONE_CKB / 10).is_err());
+    }
+}
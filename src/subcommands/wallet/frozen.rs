@@ -0,0 +1,65 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::PathBuf;
+
+use ckb_index::LiveCellInfo;
+use ckb_types::{packed::OutPoint, prelude::*, H256};
+
+/// Out-points frozen by user, collectors never spend them (unless `--include-frozen`).
+/// Stored in `~/.ckb-cli/frozen-cells`
+pub struct FrozenCells {
+    path: PathBuf,
+    out_points: BTreeSet<String>,
+}
+
+impl FrozenCells {
+    pub fn load(path: PathBuf) -> Result<FrozenCells, String> {
+        let out_points = if path.exists() {
+            let content = fs::read_to_string(&path).map_err(|err| err.to_string())?;
+            serde_json::from_str(&content)
+                .map_err(|err| format!("Parse frozen cells file {:?} failed: {}", path, err))?
+        } else {
+            BTreeSet::default()
+        };
+        Ok(FrozenCells { path, out_points })
+    }
+
+    pub fn contains(&self, info: &LiveCellInfo) -> bool {
+        self.out_points
+            .contains(&format!("{:#x}-{}", info.tx_hash, info.tx_index))
+    }
+
+    pub fn freeze(&mut self, out_points: &[OutPoint]) -> Result<(), String> {
+        self.out_points
+            .extend(out_points.iter().map(out_point_to_string));
+        self.save()
+    }
+
+    pub fn unfreeze(&mut self, out_points: &[OutPoint]) -> Result<(), String> {
+        for out_point in out_points {
+            self.out_points.remove(&out_point_to_string(out_point));
+        }
+        self.save()
+    }
+
+    pub fn clear(&mut self) -> Result<(), String> {
+        self.out_points.clear();
+        self.save()
+    }
+
+    pub fn list(&self) -> Vec<String> {
+        self.out_points.iter().cloned().collect()
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let content =
+            serde_json::to_string_pretty(&self.out_points).map_err(|err| err.to_string())?;
+        fs::write(&self.path, content).map_err(|err| err.to_string())
+    }
+}
+
+pub fn out_point_to_string(out_point: &OutPoint) -> String {
+    let tx_hash: H256 = out_point.tx_hash().unpack();
+    let index: u32 = out_point.index().unpack();
+    format!("{:#x}-{}", tx_hash, index)
+}
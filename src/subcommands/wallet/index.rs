@@ -1,16 +1,21 @@
 use std::fmt;
 use std::path::PathBuf;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::{Duration, Instant};
 
-use ckb_index::{with_index_db, IndexDatabase};
+use ckb_index::{
+    clear_index_db, import_snapshot, with_index_db, Error as IndexDbError, IndexDatabase,
+    DEFAULT_BATCH_SIZE, MAX_ROLLBACK_BLOCKS,
+};
 use ckb_jsonrpc_types::BlockNumber;
 use ckb_sdk::GenesisInfo;
 use ckb_sdk::HttpRpcClient;
+use ckb_sdk::NetworkType;
 use ckb_types::{
     core::{service::Request, BlockView, HeaderView},
+    packed::Byte32,
     prelude::*,
     H256,
 };
@@ -18,17 +23,35 @@ use ckb_util::RwLock;
 use crossbeam_channel::{Receiver, Sender};
 use serde_derive::{Deserialize, Serialize};
 
-use crate::utils::other::get_network_type;
+use crate::utils::{
+    metrics::write_metric,
+    other::{dir_size, get_network_type},
+};
 
 pub enum IndexRequest {
     UpdateUrl(String),
+    // Rebuild the index from a block number (from scratch if `None`)
+    Rebuild(Option<u64>),
+    // Replace the index database by a snapshot file (see `index import-snapshot`)
+    ImportSnapshot(PathBuf),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum IndexResponse {
     Ok,
+    Error(String),
 }
 
+// The requests changing the index database, done by the index thread between the rounds of
+// indexing (the database is closed then)
+enum DbTask {
+    Rebuild(Option<u64>),
+    ImportSnapshot(PathBuf),
+}
+
+// A database task, responded after it is done
+type PendingTask = (DbTask, Sender<IndexResponse>);
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CapacityResult {
     pub lock_hash: H256,
@@ -99,6 +122,13 @@ impl IndexThreadState {
             _ => false,
         }
     }
+    /// The last processed block and the tip number
+    pub fn processed(&self) -> Option<(&SimpleBlockInfo, u64)> {
+        match self {
+            IndexThreadState::Processing(Some(info), tip_number) => Some((info, *tip_number)),
+            _ => None,
+        }
+    }
     #[cfg_attr(windows, allow(dead_code))]
     pub fn is_processing(&self) -> bool {
         match self {
@@ -137,8 +167,152 @@ impl Default for IndexThreadState {
     }
 }
 
+/// Counters of the index thread, exported by the metrics endpoint (`--metrics-port`)
+pub struct IndexMetrics {
+    index_dir: PathBuf,
+    synced_number: AtomicU64,
+    tip_number: AtomicU64,
+    blocks_processed: AtomicU64,
+    rpc_errors: AtomicU64,
+    db_errors: AtomicU64,
+    rollback_blocks: AtomicU64,
+    // (time, blocks_processed) of last render, for the blocks/sec rate
+    last_sample: Mutex<(Instant, u64)>,
+}
+
+impl IndexMetrics {
+    fn new(index_dir: PathBuf) -> IndexMetrics {
+        IndexMetrics {
+            index_dir,
+            synced_number: AtomicU64::new(0),
+            tip_number: AtomicU64::new(0),
+            blocks_processed: AtomicU64::new(0),
+            rpc_errors: AtomicU64::new(0),
+            db_errors: AtomicU64::new(0),
+            rollback_blocks: AtomicU64::new(0),
+            last_sample: Mutex::new((Instant::now(), 0)),
+        }
+    }
+
+    fn block_processed(&self, number: u64) {
+        self.synced_number.store(number, Ordering::Relaxed);
+        self.blocks_processed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn rpc_error<E: ToString>(&self, err: E) -> String {
+        self.rpc_errors.fetch_add(1, Ordering::Relaxed);
+        err.to_string()
+    }
+
+    fn db_error(&self, err: IndexDbError) -> String {
+        // The RPC errors inside `with_index_db` are already counted
+        let is_rpc_error = match err {
+            IndexDbError::Other(_) => true,
+            _ => false,
+        };
+        if !is_rpc_error {
+            self.db_errors.fetch_add(1, Ordering::Relaxed);
+        }
+        err.to_string()
+    }
+
+    /// Render in Prometheus text format, blocks/sec is the average since last render
+    pub fn render(&self) -> String {
+        let blocks_processed = self.blocks_processed.load(Ordering::Relaxed);
+        let blocks_per_second = {
+            let mut last_sample = self.last_sample.lock().expect("Metrics lock poisoned");
+            let elapsed = last_sample.0.elapsed().as_millis() as f64 / 1000.0;
+            let rate = if elapsed > 0.0 {
+                (blocks_processed - last_sample.1) as f64 / elapsed
+            } else {
+                0.0
+            };
+            *last_sample = (Instant::now(), blocks_processed);
+            rate
+        };
+
+        let mut output = String::new();
+        let metrics = [
+            (
+                "ckb_cli_index_synced_block_number",
+                "gauge",
+                "Number of the last block applied to the index database",
+                self.synced_number.load(Ordering::Relaxed) as f64,
+            ),
+            (
+                "ckb_cli_index_tip_block_number",
+                "gauge",
+                "Number of the tip block of the node",
+                self.tip_number.load(Ordering::Relaxed) as f64,
+            ),
+            (
+                "ckb_cli_index_blocks_processed_total",
+                "counter",
+                "Blocks applied to the index database since start",
+                blocks_processed as f64,
+            ),
+            (
+                "ckb_cli_index_blocks_per_second",
+                "gauge",
+                "Blocks applied per second since last scrape",
+                blocks_per_second,
+            ),
+            (
+                "ckb_cli_index_db_size_bytes",
+                "gauge",
+                "Total size of the index database files",
+                dir_size(&self.index_dir) as f64,
+            ),
+            (
+                "ckb_cli_index_rpc_errors_total",
+                "counter",
+                "Failed RPC calls of the index thread",
+                self.rpc_errors.load(Ordering::Relaxed) as f64,
+            ),
+            (
+                "ckb_cli_index_db_errors_total",
+                "counter",
+                "Failed index database operations",
+                self.db_errors.load(Ordering::Relaxed) as f64,
+            ),
+            (
+                "ckb_cli_index_rollback_blocks_total",
+                "counter",
+                "Blocks rolled back because of chain reorganization",
+                self.rollback_blocks.load(Ordering::Relaxed) as f64,
+            ),
+        ];
+        for (name, kind, help, value) in metrics.iter() {
+            write_metric(&mut output, name, kind, help, *value);
+        }
+        output
+    }
+}
+
+const DEFAULT_FETCH_CONCURRENCY: usize = 4;
+
+/// Options of the index sync
+#[derive(Debug, Clone)]
+pub struct IndexSyncConfig {
+    /// Blocks committed in one write batch during initial sync, also the number of blocks
+    /// prefetched from the node
+    pub batch_size: usize,
+    /// Concurrent RPC requests fetching the blocks
+    pub fetch_concurrency: usize,
+}
+
+impl Default for IndexSyncConfig {
+    fn default() -> IndexSyncConfig {
+        IndexSyncConfig {
+            batch_size: DEFAULT_BATCH_SIZE,
+            fetch_concurrency: DEFAULT_FETCH_CONCURRENCY,
+        }
+    }
+}
+
 pub struct IndexController {
     state: Arc<RwLock<IndexThreadState>>,
+    metrics: Arc<IndexMetrics>,
     sender: Sender<Request<IndexRequest, IndexResponse>>,
     shutdown: Arc<AtomicBool>,
 }
@@ -147,6 +321,7 @@ impl Clone for IndexController {
     fn clone(&self) -> IndexController {
         IndexController {
             state: Arc::clone(&self.state),
+            metrics: Arc::clone(&self.metrics),
             shutdown: Arc::clone(&self.shutdown),
             sender: self.sender.clone(),
         }
@@ -157,9 +332,36 @@ impl IndexController {
     pub fn state(&self) -> &Arc<RwLock<IndexThreadState>> {
         &self.state
     }
+    pub fn metrics(&self) -> &Arc<IndexMetrics> {
+        &self.metrics
+    }
     pub fn sender(&self) -> &Sender<Request<IndexRequest, IndexResponse>> {
         &self.sender
     }
+    /// Start the index thread (if not started) and wait until the index database reaches the
+    /// tip of the node, for using the local index outside interactive mode (`--sync-first`).
+    pub fn sync_to_tip(&self, url: &str, timeout: Duration) -> Result<(), String> {
+        if !self.state().read().is_started() {
+            Request::call(self.sender(), IndexRequest::UpdateUrl(url.to_owned()));
+        }
+        let start_time = Instant::now();
+        loop {
+            if let Some((info, tip_number)) = self.state().read().processed() {
+                if info.number >= tip_number {
+                    return Ok(());
+                }
+            }
+            if start_time.elapsed() >= timeout {
+                return Err(format!(
+                    "Index database not synced to the tip in {} seconds (state: {})",
+                    timeout.as_secs(),
+                    self.state().read().to_string()
+                ));
+            }
+            thread::sleep(Duration::from_millis(200));
+        }
+    }
+
     pub fn shutdown(&self) {
         let start_time = Instant::now();
         self.shutdown.store(true, Ordering::Relaxed);
@@ -184,19 +386,23 @@ pub fn start_index_thread(
     url: &str,
     index_dir: PathBuf,
     state: Arc<RwLock<IndexThreadState>>,
+    sync_config: IndexSyncConfig,
 ) -> IndexController {
     let mut rpc_url = url.to_owned();
     let (sender, receiver) = crossbeam_channel::bounded::<Request<IndexRequest, IndexResponse>>(1);
     let shutdown = Arc::new(AtomicBool::new(false));
+    let metrics = Arc::new(IndexMetrics::new(index_dir.clone()));
     let state_clone = Arc::clone(&state);
     let shutdown_clone = Arc::clone(&shutdown);
+    let metrics_clone = Arc::clone(&metrics);
 
     thread::Builder::new()
         .name("index".to_string())
         .spawn(move || {
+            let mut pending: Option<PendingTask> = None;
             loop {
                 // Wait first request
-                match try_recv(&receiver, &mut rpc_url) {
+                match try_recv(&receiver, &mut rpc_url, &mut pending) {
                     Some(true) => {
                         state.write().stop();
                         log::info!("Index database thread stopped");
@@ -212,9 +418,12 @@ pub fn start_index_thread(
                 match process(
                     &receiver,
                     &mut rpc_url,
+                    &mut pending,
                     &mut rpc_client,
                     &index_dir,
                     &state,
+                    &metrics_clone,
+                    &sync_config,
                     &shutdown_clone,
                 ) {
                     Ok(true) => {
@@ -238,6 +447,7 @@ pub fn start_index_thread(
 
     IndexController {
         state: state_clone,
+        metrics,
         sender,
         shutdown,
     }
@@ -246,13 +456,16 @@ pub fn start_index_thread(
 fn process(
     receiver: &Receiver<Request<IndexRequest, IndexResponse>>,
     rpc_url: &mut String,
+    pending: &mut Option<PendingTask>,
     rpc_client: &mut HttpRpcClient,
     index_dir: &PathBuf,
     state: &Arc<RwLock<IndexThreadState>>,
+    metrics: &Arc<IndexMetrics>,
+    sync_config: &IndexSyncConfig,
     shutdown: &Arc<AtomicBool>,
 ) -> Result<bool, String> {
     let old_rpc_url = rpc_url.clone();
-    if let Some(exit) = try_recv(&receiver, rpc_url) {
+    if let Some(exit) = try_recv(&receiver, rpc_url, pending) {
         return Ok(exit);
     }
 
@@ -263,29 +476,51 @@ fn process(
     let genesis_block: BlockView = rpc_client
         .get_block_by_number(BlockNumber::from(0))
         .call()
-        .map_err(|err| err.to_string())?
+        .map_err(|err| metrics.rpc_error(err))?
         .0
         .expect("Can not get genesis block?")
         .into();
-    let network_type = get_network_type(rpc_client)?;
+    let network_type = get_network_type(rpc_client).map_err(|err| metrics.rpc_error(err))?;
     let genesis_info = GenesisInfo::from_block(&genesis_block).unwrap();
     let genesis_hash: H256 = genesis_info.header().hash().unpack();
 
+    if let Some((task, responder)) = pending.take() {
+        let result = match task {
+            DbTask::Rebuild(from_number) => {
+                rebuild_index_db(index_dir, &genesis_info, network_type, from_number)
+            }
+            DbTask::ImportSnapshot(path) => {
+                log::info!("Import the index database from snapshot {:?}", path);
+                import_snapshot(index_dir, &genesis_hash, &path).map(|_| ())
+            }
+        };
+        let response = match result {
+            Ok(()) => IndexResponse::Ok,
+            Err(err) => IndexResponse::Error(err.to_string()),
+        };
+        let _ = responder.send(response);
+    }
+
     let mut next_get_tip = Instant::now();
     let mut tip_header = genesis_info.header().clone();
     let mut next_number = 0;
+    let mut checked_tip: Option<Byte32> = None;
     loop {
         if next_get_tip <= Instant::now() {
             next_get_tip = Instant::now() + Duration::from_secs(1);
             tip_header = rpc_client
                 .get_tip_header()
                 .call()
-                .map_err(|err| err.to_string())?
+                .map_err(|err| metrics.rpc_error(err))?
                 .into();
+            metrics
+                .tip_number
+                .store(tip_header.number(), Ordering::Relaxed);
             log::debug!("Update to tip {}", tip_header.number());
         }
 
-        if tip_header.number() >= next_number {
+        // A changed tip may be a reorg even if there is no new block to index
+        if tip_header.number() >= next_number || checked_tip != Some(tip_header.hash()) {
             let exit_opt = with_index_db(index_dir, genesis_hash.clone(), |backend, cf| {
                 let mut db =
                     IndexDatabase::from_db(backend, cf, network_type, genesis_info.clone(), false)
@@ -295,37 +530,83 @@ fn process(
                         .expect("Apply genesis block failed");
                 }
                 db.update_tip(tip_header.clone());
-                while tip_header.number() > db.last_number().unwrap() {
+                db.set_batch_size(sync_config.batch_size);
+                checked_tip = Some(tip_header.hash());
+                match reorg_depth(&db, rpc_client, metrics)? {
+                    Some(0) => {}
+                    Some(depth) => {
+                        log::warn!("Chain reorganized, rollback {} blocks", depth);
+                        db.rollback(depth)?;
+                        metrics.rollback_blocks.fetch_add(depth, Ordering::Relaxed);
+                    }
+                    None => {
+                        log::warn!(
+                            "Chain reorganized deeper than {} blocks, rebuild the index database",
+                            MAX_ROLLBACK_BLOCKS
+                        );
+                        clear_index_db(backend, cf)?;
+                        next_number = 0;
+                        return Ok(None);
+                    }
+                }
+                let mut fetcher_opt: Option<BlockFetcher> = None;
+                let result = loop {
+                    if tip_header.number() <= db.last_number().unwrap() {
+                        break Ok(None);
+                    }
                     if shutdown.load(Ordering::Relaxed) {
-                        return Ok(Some(true));
+                        break Ok(Some(true));
                     }
-                    if let Some(exit) = try_recv(&receiver, rpc_url) {
-                        return Ok(Some(exit));
+                    if let Some(exit) = try_recv(&receiver, rpc_url, pending) {
+                        break Ok(Some(exit));
                     }
-                    let next_block_number = BlockNumber::from(db.next_number().unwrap());
-                    if let Some(next_block) = rpc_client
-                        .get_block_by_number(next_block_number)
-                        .call()
-                        .map_err(|err| err.to_string())?
-                        .0
-                    {
-                        db.apply_next_block(next_block.into())
-                            .expect("Add block failed");
-                        state
-                            .write()
-                            .processing(db.last_header().cloned(), tip_header.number());
-                    } else {
-                        log::warn!("fork happening, wait a second");
-                        thread::sleep(Duration::from_secs(1));
+                    let next_block_number = db.next_number().unwrap();
+                    let fetcher = fetcher_opt.get_or_insert_with(|| {
+                        BlockFetcher::start(
+                            rpc_url.as_str(),
+                            next_block_number,
+                            tip_header.number(),
+                            sync_config.batch_size,
+                            sync_config.fetch_concurrency,
+                            Arc::clone(metrics),
+                        )
+                    });
+                    match fetcher.next() {
+                        Some(Ok(Some(next_block))) => {
+                            if let Err(err) = db.apply_next_block(next_block) {
+                                break Err(err.into());
+                            }
+                            if db.last_number() != Some(next_block_number) {
+                                // Rollback happened, fetch again from the new next number
+                                fetcher_opt = None;
+                            } else {
+                                metrics.block_processed(next_block_number);
+                            }
+                            state
+                                .write()
+                                .processing(db.last_header().cloned(), tip_header.number());
+                        }
+                        Some(Ok(None)) => {
+                            log::warn!("fork happening, wait a second");
+                            fetcher_opt = None;
+                            thread::sleep(Duration::from_secs(1));
+                        }
+                        Some(Err(err)) => break Err(err.into()),
+                        None => fetcher_opt = None,
                     }
+                };
+                // Do not lose the blocks already applied, whatever the result is
+                db.flush();
+                if result.is_err() {
+                    return result;
                 }
                 next_number = db.last_number().unwrap() + 1;
                 state
                     .write()
                     .processing(db.last_header().cloned(), tip_header.number());
-                Ok(None)
+                result
             })
-            .map_err(|err| err.to_string())?;
+            .map_err(|err| metrics.db_error(err))?;
             if let Some(exit) = exit_opt {
                 return Ok(exit);
             }
@@ -334,19 +615,164 @@ fn process(
         if shutdown.load(Ordering::Relaxed) {
             return Ok(true);
         }
-        if let Some(exit) = try_recv(&receiver, rpc_url) {
+        if let Some(exit) = try_recv(&receiver, rpc_url, pending) {
             return Ok(exit);
         }
         thread::sleep(Duration::from_millis(100));
     }
 }
 
+/// Rollback the index database to the parent of block `from_number`, or clear it
+fn rebuild_index_db(
+    index_dir: &PathBuf,
+    genesis_info: &GenesisInfo,
+    network_type: NetworkType,
+    from_number: Option<u64>,
+) -> Result<(), IndexDbError> {
+    let genesis_hash: H256 = genesis_info.header().hash().unpack();
+    with_index_db(index_dir, genesis_hash, |backend, cf| {
+        let from_number = match from_number {
+            Some(number) if number > 0 => number,
+            _ => {
+                log::info!("Rebuild the index database from scratch");
+                return clear_index_db(backend, cf);
+            }
+        };
+        let mut db =
+            IndexDatabase::from_db(backend, cf, network_type, genesis_info.clone(), false)?;
+        let last_number = match db.last_number() {
+            Some(number) => number,
+            None => return Ok(()),
+        };
+        if from_number > last_number {
+            return Err(IndexDbError::Other(format!(
+                "Block #{} is not indexed yet (last indexed block: #{})",
+                from_number, last_number
+            )));
+        }
+        let depth = last_number - from_number + 1;
+        if depth > MAX_ROLLBACK_BLOCKS {
+            return Err(IndexDbError::Other(format!(
+                "Only the recent {} blocks can be rebuilt (last indexed block: #{}), rebuild from scratch instead",
+                MAX_ROLLBACK_BLOCKS, last_number
+            )));
+        }
+        log::info!("Rebuild the index database from block #{}", from_number);
+        db.rollback(depth)?;
+        Ok(())
+    })
+}
+
+/// Fetch blocks `[from, to]` by `concurrency` background threads, at most `prefetch` blocks
+/// ahead of the applying. Worker `i` fetches the blocks `from + i + k * concurrency`, so the blocks
+/// are received in order by polling the workers one by one. A worker stops at the first missing
+/// block or error.
+struct BlockFetcher {
+    receivers: Vec<Receiver<Result<Option<BlockView>, String>>>,
+    next_worker: usize,
+    stop: Arc<AtomicBool>,
+}
+
+impl BlockFetcher {
+    fn start(
+        url: &str,
+        from: u64,
+        to: u64,
+        prefetch: usize,
+        concurrency: usize,
+        metrics: Arc<IndexMetrics>,
+    ) -> BlockFetcher {
+        let concurrency = concurrency.max(1);
+        let stop = Arc::new(AtomicBool::new(false));
+        let receivers = (0..concurrency)
+            .map(|worker| {
+                let (sender, receiver) =
+                    crossbeam_channel::bounded((prefetch / concurrency).max(1));
+                let stop = Arc::clone(&stop);
+                let metrics = Arc::clone(&metrics);
+                let mut rpc_client = HttpRpcClient::from_uri(url);
+                thread::Builder::new()
+                    .name(format!("index-fetch-{}", worker))
+                    .spawn(move || {
+                        let mut number = from + worker as u64;
+                        while number <= to {
+                            if stop.load(Ordering::Relaxed) {
+                                break;
+                            }
+                            let result = rpc_client
+                                .get_block_by_number(BlockNumber::from(number))
+                                .call()
+                                .map(|block_opt| block_opt.0.map(Into::into))
+                                .map_err(|err| metrics.rpc_error(err));
+                            let finished = match result {
+                                Ok(Some(_)) => false,
+                                _ => true,
+                            };
+                            if sender.send(result).is_err() || finished {
+                                break;
+                            }
+                            number += concurrency as u64;
+                        }
+                    })
+                    .expect("Spawn index fetch thread failed");
+                receiver
+            })
+            .collect();
+        BlockFetcher {
+            receivers,
+            next_worker: 0,
+            stop,
+        }
+    }
+
+    fn next(&mut self) -> Option<Result<Option<BlockView>, String>> {
+        let receiver = &self.receivers[self.next_worker];
+        self.next_worker = (self.next_worker + 1) % self.receivers.len();
+        receiver.recv().ok()
+    }
+}
+
+impl Drop for BlockFetcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Blocks to rollback to get back to the main chain of the node, `None` means the fork point is
+/// deeper than `MAX_ROLLBACK_BLOCKS`. Only the blocks the node has with another hash are rolled
+/// back, a node behind the index (no block at that number) is not a reorg.
+fn reorg_depth(
+    db: &IndexDatabase,
+    rpc_client: &mut HttpRpcClient,
+    metrics: &IndexMetrics,
+) -> Result<Option<u64>, String> {
+    let last_number = db.last_number().unwrap();
+    for depth in 0..=MAX_ROLLBACK_BLOCKS.min(last_number) {
+        let number = last_number - depth;
+        let local_hash: H256 = match db.get_block_hash(number) {
+            Some(hash) => hash.unpack(),
+            None => return Ok(None),
+        };
+        let node_hash = rpc_client
+            .get_block_hash(BlockNumber::from(number))
+            .call()
+            .map_err(|err| metrics.rpc_error(err))?
+            .0;
+        match node_hash {
+            Some(node_hash) if node_hash != local_hash => {}
+            _ => return Ok(Some(depth)),
+        }
+    }
+    Ok(None)
+}
+
 fn try_recv(
     receiver: &Receiver<Request<IndexRequest, IndexResponse>>,
     rpc_url: &mut String,
+    pending: &mut Option<PendingTask>,
 ) -> Option<bool> {
     match receiver.try_recv() {
-        Ok(request) => Some(process_request(request, rpc_url)),
+        Ok(request) => Some(process_request(request, rpc_url, pending)),
         Err(err) => {
             if err.is_disconnected() {
                 log::info!("Sender dropped, exit index thread");
@@ -358,7 +784,11 @@ fn try_recv(
     }
 }
 
-fn process_request(request: Request<IndexRequest, IndexResponse>, rpc_url: &mut String) -> bool {
+fn process_request(
+    request: Request<IndexRequest, IndexResponse>,
+    rpc_url: &mut String,
+    pending: &mut Option<PendingTask>,
+) -> bool {
     let Request {
         responder,
         arguments,
@@ -368,5 +798,19 @@ fn process_request(request: Request<IndexRequest, IndexResponse>, rpc_url: &mut
             *rpc_url = url;
             responder.send(IndexResponse::Ok).is_err()
         }
+        IndexRequest::Rebuild(from_number) => {
+            replace_pending(pending, (DbTask::Rebuild(from_number), responder))
+        }
+        IndexRequest::ImportSnapshot(path) => {
+            replace_pending(pending, (DbTask::ImportSnapshot(path), responder))
+        }
+    }
+}
+
+// Replace the previous task, it is responded with an error
+fn replace_pending(pending: &mut Option<PendingTask>, task: PendingTask) -> bool {
+    if let Some((_, old_responder)) = pending.replace(task) {
+        let _ = old_responder.send(IndexResponse::Error("Replaced by a new request".to_owned()));
     }
+    false
 }
@@ -1,43 +1,104 @@
+mod batch;
+mod cells;
+mod deploy;
+mod fee_guard;
+mod frozen;
 mod index;
+mod multisig;
+mod payout;
+mod pending;
+mod preview;
+mod selection;
+mod sender;
+mod spendable;
+mod spent;
+mod sudt;
+mod sweep;
 
+use std::cmp;
 use std::fs;
 use std::io::Read;
 use std::path::PathBuf;
+use std::slice;
 
 use ckb_hash::blake2b_256;
-use ckb_jsonrpc_types::{BlockNumber, CellWithStatus, HeaderView, TransactionWithStatus};
+use ckb_jsonrpc_types::{
+    BlockNumber, CellData, CellInfo, CellWithStatus, EpochNumber, HeaderView, JsonBytes,
+    TransactionWithStatus,
+};
 use ckb_types::{
     bytes::Bytes,
-    core::{BlockView, TransactionView},
-    packed::{Byte32, CellInput, Script},
+    core::{
+        BlockView, Capacity, EpochNumberWithFraction, HeaderView as CoreHeaderView, ScriptHashType,
+        TransactionView,
+    },
+    packed::{self, CellDep, CellInput, CellOutput, OutPoint, Script},
     prelude::*,
     H160, H256,
 };
-use clap::{App, ArgMatches, SubCommand};
+use clap::{App, Arg, ArgMatches, SubCommand};
 use faster_hex::hex_string;
 
 use super::CliSubCommand;
 use crate::utils::{
+    address_book::{annotate_labels, AddressBook},
     arg,
     arg_parser::{
-        AddressParser, ArgParser, CapacityParser, FixedHashParser, FromStrParser, HexParser,
-        PrivkeyPathParser, PrivkeyWrapper,
+        AddressParser, ArgParser, CapacityParser, FilePathParser, FixedHashParser, FromStrParser,
+        HexParser, LabeledAddressParser, LabeledLockArgParser, OutPointParser, PrivkeyPathParser,
+        PrivkeyWrapper, ScriptParser,
     },
-    other::{check_address_prefix, get_address, get_network_type, read_password},
+    cell_schema::CellSchemas,
+    config::ChangeStrategy,
+    deployments::Deployments,
+    i18n::{t, tf},
+    ledger::LedgerSigner,
+    other::{
+        check_address_prefix, get_address, get_ckb_cli_dir, get_network_type, has_password_file,
+        read_confirmation, read_password, read_tx_file,
+    },
+    policy::{check_policy, policy_address, SignKind, SignRequest, SigningPolicy},
     printer::{OutputFormat, Printable},
+    sign_history::{sign_witness, witness_with_key},
+    watch_only::WatchOnlyAccounts,
 };
-use ckb_index::{with_index_db, IndexDatabase, LiveCellInfo};
+use batch::{batch_tx_args, parse_recipients};
+use cells::{CellFilter, CellQuery};
+use ckb_index::{with_index_db, CellIndex, IndexDatabase, LiveCellInfo};
 use ckb_sdk::{
-    blake2b_args, build_witness_with_key, serialize_signature,
-    wallet::{KeyStore, KeyStoreError},
-    Address, GenesisInfo, HttpRpcClient, TransferTransactionBuilder, MIN_SECP_CELL_CAPACITY,
-    ONE_CKB, SECP256K1,
+    acp_min_capacity, calculate_tx_fee, sign_secp_transaction,
+    stats::record_cache,
+    tx_size_in_block,
+    wallet::{ChildNumber, DerivationPath, KeyStore, KeyStoreError, CKB_ACCOUNT_PATH},
+    AcpConfig, Address, ChangePolicy, GenesisInfo, HttpIndexerRpcClient, HttpRpcClient,
+    LockWitness, NetworkType, Since, TransferTransactionBuilder, MIN_SECP_CELL_CAPACITY, ONE_CKB,
+    SECP256K1,
 };
+use frozen::{out_point_to_string, FrozenCells};
+use payout::{parse_shares, split_rewards, PayoutLedger};
+use pending::PendingTxs;
+use selection::CoinSelection;
+use sender::wait_for_commit;
+use spendable::Balance;
+use spent::SpentCells;
+use sweep::{max_chained_cells, reclaimed_occupied_capacity, split_sweep_inputs};
+
+// Search range of the empty change address (change_strategy=derive)
+const MAX_CHANGE_ADDRESS_INDEX: u32 = 100;
+// Default timeout of `--wait-for-commit` (in seconds)
+const DEFAULT_WAIT_COMMIT_TIMEOUT: u64 = 600;
+
+pub use fee_guard::FeeGuard;
 pub use index::{
-    start_index_thread, CapacityResult, IndexController, IndexRequest, IndexResponse,
-    IndexThreadState, SimpleBlockInfo,
+    start_index_thread, CapacityResult, IndexController, IndexMetrics, IndexRequest, IndexResponse,
+    IndexSyncConfig, IndexThreadState, SimpleBlockInfo,
+};
+pub use sender::TxSender;
+pub use spendable::{
+    dao_unlock_epoch, epoch_value, is_cellbase, is_dao_cell, is_dao_deposit, is_mature,
+    is_plain_cell, Bucket, DAO_LOCK_PERIOD_EPOCHS,
 };
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 pub struct WalletSubCommand<'a> {
     rpc_client: &'a mut HttpRpcClient,
@@ -45,7 +106,20 @@ pub struct WalletSubCommand<'a> {
     genesis_info: Option<GenesisInfo>,
     index_dir: PathBuf,
     index_controller: IndexController,
+    change_strategy: ChangeStrategy,
+    change_policy: ChangePolicy,
     interactive: bool,
+    // The index thread synced to the tip before the command (`--sync-first`)
+    index_synced: bool,
+    // The cells are searched by this ckb-indexer instead of the local index database
+    indexer_client: Option<HttpIndexerRpcClient>,
+    // Inputs spent by this command, the index database may not know them yet
+    spent_inputs: HashSet<String>,
+    // Used when neither `--tx-fee` nor `--fee-rate` is given (`fee_rate` of the profile)
+    default_fee_rate: Option<u64>,
+    // Preview the transaction and ask for confirmation before sending it (unless `--yes`)
+    confirm_send: bool,
+    fee_guard: FeeGuard,
 }
 
 impl<'a> WalletSubCommand<'a> {
@@ -55,6 +129,7 @@ impl<'a> WalletSubCommand<'a> {
         genesis_info: Option<GenesisInfo>,
         index_dir: PathBuf,
         index_controller: IndexController,
+        change_strategy: ChangeStrategy,
         interactive: bool,
     ) -> WalletSubCommand<'a> {
         WalletSubCommand {
@@ -63,11 +138,72 @@ impl<'a> WalletSubCommand<'a> {
             genesis_info,
             index_dir,
             index_controller,
+            change_strategy,
+            change_policy: ChangePolicy::default(),
             interactive,
+            index_synced: false,
+            indexer_client: None,
+            spent_inputs: HashSet::new(),
+            default_fee_rate: None,
+            confirm_send: true,
+            fee_guard: FeeGuard::default(),
+        }
+    }
+
+    /// The change policy (minimal change capacity and dust policy) of the built transactions
+    pub fn with_change_policy(mut self, change_policy: ChangePolicy) -> WalletSubCommand<'a> {
+        self.change_policy = change_policy;
+        self
+    }
+
+    /// The local index database is usable outside interactive mode, the index thread is synced
+    /// to the tip before running the command (`--sync-first`).
+    pub fn with_synced_index(mut self, index_synced: bool) -> WalletSubCommand<'a> {
+        self.index_synced = index_synced;
+        self
+    }
+
+    /// Search the live cells by a running ckb-indexer (`--indexer-url`) instead of the local
+    /// index database, it works in non-interactive mode too.
+    pub fn with_indexer_url(mut self, indexer_url: Option<&str>) -> WalletSubCommand<'a> {
+        self.indexer_client = indexer_url.map(HttpIndexerRpcClient::from_uri);
+        self
+    }
+
+    /// The fee rate used when neither `--tx-fee` nor `--fee-rate` is given
+    pub fn with_default_fee_rate(mut self, fee_rate: Option<u64>) -> WalletSubCommand<'a> {
+        self.default_fee_rate = fee_rate;
+        self
+    }
+
+    /// Disable the preview and confirmation before sending transactions, for the callers without
+    /// a terminal (like the JSON-RPC server)
+    pub fn with_confirmation(mut self, confirm_send: bool) -> WalletSubCommand<'a> {
+        self.confirm_send = confirm_send;
+        self
+    }
+
+    /// The limits of transaction fee checked before sending (unless `--force-fee`)
+    pub fn with_fee_guard(mut self, fee_guard: FeeGuard) -> WalletSubCommand<'a> {
+        self.fee_guard = fee_guard;
+        self
+    }
+
+    /// `--fee-rate`, or the default fee rate if `--tx-fee` is not given either
+    fn fee_rate(&self, m: &ArgMatches) -> Result<Option<u64>, String> {
+        let fee_rate: Option<u64> =
+            FromStrParser::<u64>::default().from_matches_opt(m, "fee-rate", false)?;
+        if fee_rate.is_some() || m.is_present("tx-fee") {
+            Ok(fee_rate)
+        } else {
+            self.default_fee_rate
+                .map(Some)
+                .ok_or_else(|| "<tx-fee> or <fee-rate> is required".to_owned())
         }
     }
 
     fn genesis_info(&mut self) -> Result<GenesisInfo, String> {
+        record_cache("genesis_info", self.genesis_info.is_some());
         if self.genesis_info.is_none() {
             let genesis_block: BlockView = self
                 .rpc_client
@@ -82,12 +218,318 @@ impl<'a> WalletSubCommand<'a> {
         Ok(self.genesis_info.clone().unwrap())
     }
 
+    /// Collect live cells (filtered by `is_wanted`) of a lock until `target` capacity reached,
+    /// the cells are picked in the order of `--coin-selection`.
+    fn collect_live_cells<F>(
+        &mut self,
+        m: &ArgMatches,
+        lock: Script,
+        target: u64,
+        is_wanted: F,
+    ) -> Result<(Vec<LiveCellInfo>, u64), String>
+    where
+        F: Fn(&CellWithStatus) -> bool,
+    {
+        let selection = CoinSelection::from_matches(m)?;
+        let frozen = load_frozen(m)?;
+        let is_spent = self.spent_filter()?;
+        let is_frozen = |info: &LiveCellInfo| {
+            frozen.as_ref().map(|f| f.contains(info)).unwrap_or(false) || is_spent(info)
+        };
+        // Index iterates cells by block number, so the candidates are already oldest first
+        let mut candidates = self.live_cells(CellQuery::Lock(lock.clone()), None, |_, info| {
+            (false, !is_frozen(info))
+        })?;
+        // The outputs of the transactions in tx-pool are not known by `get_live_cell`
+        let mut unconfirmed: HashMap<String, CellWithStatus> = HashMap::new();
+        if m.is_present("allow-unconfirmed-inputs") {
+            let indexed = candidates.iter().map(out_point_key).collect::<HashSet<_>>();
+            for (info, resp) in self.unconfirmed_cells(&lock)? {
+                let key = out_point_key(&info);
+                if indexed.contains(&key) || is_frozen(&info) {
+                    continue;
+                }
+                if let Some(resp) = resp {
+                    unconfirmed.insert(key, resp);
+                }
+                candidates.push(info);
+            }
+        }
+        selection.sort(&mut candidates);
+
+        let mut total_capacity = 0;
+        let mut infos = Vec::new();
+        for info in candidates {
+            if total_capacity >= target {
+                break;
+            }
+            let resp: CellWithStatus = match unconfirmed.remove(&out_point_key(&info)) {
+                Some(resp) => resp,
+                None => self
+                    .rpc_client
+                    .get_live_cell(info.out_point().into(), true)
+                    .call()
+                    .map_err(|err| format!("get_live_cell by RPC call failed: {}", err))?,
+            };
+            if is_live_cell(&resp) && is_wanted(&resp) {
+                total_capacity += info.capacity;
+                infos.push(info);
+            }
+        }
+        Ok((infos, total_capacity))
+    }
+
+    /// The outputs of the pending transactions (see `PendingTxs`) to the lock, the transactions
+    /// dropped by the node are skipped. The cells of the transactions still in tx-pool are
+    /// given with their status, the committed ones are checked by `get_live_cell` as usual.
+    /// Unconfirmed cells are ordered after the indexed cells.
+    fn unconfirmed_cells(
+        &mut self,
+        lock: &Script,
+    ) -> Result<Vec<(LiveCellInfo, Option<CellWithStatus>)>, String> {
+        let lock_hash: H256 = lock.calc_script_hash().unpack();
+        let mut cells = Vec::new();
+        for transaction in PendingTxs::load_default()?.transactions() {
+            let tx_hash: H256 = transaction.hash().unpack();
+            let tx_status = match self
+                .rpc_client
+                .get_transaction(tx_hash.clone())
+                .call()
+                .map_err(|err| format!("Send get_transaction error: {}", err))?
+                .0
+            {
+                Some(tx) => tx.tx_status,
+                None => continue,
+            };
+            for (output_index, (output, data)) in transaction.outputs_with_data_iter().enumerate() {
+                if output.lock().as_slice() != lock.as_slice() {
+                    continue;
+                }
+                let type_hashes = output.type_().to_opt().map(|type_script| {
+                    (
+                        type_script.code_hash().unpack(),
+                        type_script.calc_script_hash().unpack(),
+                    )
+                });
+                let info = LiveCellInfo {
+                    tx_hash: tx_hash.clone(),
+                    tx_index: output_index as u32,
+                    data_bytes: data.len() as u64,
+                    lock_hash: lock_hash.clone(),
+                    type_hashes,
+                    capacity: output.capacity().unpack(),
+                    number: u64::max_value(),
+                    // Not a cellbase
+                    index: CellIndex {
+                        tx_index: u32::max_value(),
+                        output_index: output_index as u32,
+                    },
+                };
+                let resp = if tx_status.block_hash.is_some() {
+                    None
+                } else {
+                    Some(CellWithStatus {
+                        cell: Some(CellInfo {
+                            output: output.clone().into(),
+                            data: Some(CellData {
+                                hash: CellOutput::calc_data_hash(&data).unpack(),
+                                content: JsonBytes::from_bytes(data),
+                            }),
+                        }),
+                        status: "live".to_owned(),
+                    })
+                };
+                cells.push((info, resp));
+            }
+        }
+        Ok(cells)
+    }
+
+    /// Inputs of the sent transactions (by this command or the recent ones in `spent-cells`
+    /// journal), the index database may not know they are spent yet.
+    fn spent_filter(&self) -> Result<impl Fn(&LiveCellInfo) -> bool, String> {
+        let journal = SpentCells::load_default()?;
+        let spent_inputs = self.spent_inputs.clone();
+        Ok(move |info: &LiveCellInfo| {
+            let key = out_point_key(info);
+            spent_inputs.contains(&key) || journal.contains(&key)
+        })
+    }
+
+    /// Collect live cells for `capacity` plus transaction fee. The fee is `--tx-fee`, or
+    /// estimated by `--fee-rate` from a dry-run transaction (built by `build_dry_run` with
+    /// placeholder witness lock of the final size), so the fee matches the signed transaction.
+    fn collect_live_cells_with_fee<F, B>(
+        &mut self,
+        m: &ArgMatches,
+        lock: Script,
+        capacity: u64,
+        is_wanted: F,
+        build_dry_run: B,
+    ) -> Result<(Vec<LiveCellInfo>, u64, u64), String>
+    where
+        F: Fn(&CellWithStatus) -> bool,
+        B: Fn(Vec<CellInput>, u64, u64) -> Result<TransactionView, String>,
+    {
+        self.collect_live_cells_with_fee_from(m, lock, 0, capacity, is_wanted, build_dry_run)
+    }
+
+    /// `collect_live_cells_with_fee` when `provided` capacity is already in the inputs (the
+    /// output of a previous transaction), only the rest is collected from live cells. The
+    /// returned total capacity does not include `provided`.
+    fn collect_live_cells_with_fee_from<F, B>(
+        &mut self,
+        m: &ArgMatches,
+        lock: Script,
+        provided: u64,
+        capacity: u64,
+        is_wanted: F,
+        build_dry_run: B,
+    ) -> Result<(Vec<LiveCellInfo>, u64, u64), String>
+    where
+        F: Fn(&CellWithStatus) -> bool,
+        B: Fn(Vec<CellInput>, u64, u64) -> Result<TransactionView, String>,
+    {
+        let fee_rate = self.fee_rate(m)?;
+        let mut tx_fee: u64 = match fee_rate {
+            Some(_) => 0,
+            None => CapacityParser.from_matches(m, "tx-fee")?,
+        };
+        loop {
+            let target = (capacity + tx_fee).saturating_sub(provided);
+            let (infos, total_capacity) =
+                self.collect_live_cells(m, lock.clone(), target, &is_wanted)?;
+            let fee_rate = match fee_rate {
+                Some(fee_rate) if total_capacity >= target => fee_rate,
+                _ => return Ok((infos, total_capacity, tx_fee)),
+            };
+            let inputs = infos.iter().map(LiveCellInfo::input).collect::<Vec<_>>();
+            let dry_run_tx = build_dry_run(inputs, total_capacity, tx_fee)?;
+            let estimated_fee = calculate_tx_fee(tx_size_in_block(&dry_run_tx), fee_rate);
+            // More inputs may be collected for the higher fee, estimate again
+            if estimated_fee <= tx_fee {
+                return Ok((infos, total_capacity, tx_fee));
+            }
+            tx_fee = estimated_fee;
+        }
+    }
+
+    /// `collect_live_cells_with_fee` drawing from the locks in order until the capacity (and
+    /// fee) is reached, the cells are returned with their locks.
+    fn collect_live_cells_from_locks<B>(
+        &mut self,
+        m: &ArgMatches,
+        locks: &[Script],
+        capacity: u64,
+        build_dry_run: B,
+    ) -> Result<(Vec<(Script, LiveCellInfo)>, u64, u64), String>
+    where
+        B: Fn(&[(Script, LiveCellInfo)], u64, u64) -> Result<TransactionView, String>,
+    {
+        let fee_rate = self.fee_rate(m)?;
+        let mut tx_fee: u64 = match fee_rate {
+            Some(_) => 0,
+            None => CapacityParser.from_matches(m, "tx-fee")?,
+        };
+        loop {
+            let mut cells: Vec<(Script, LiveCellInfo)> = Vec::new();
+            let mut total_capacity = 0;
+            for lock in locks {
+                if total_capacity >= capacity + tx_fee {
+                    break;
+                }
+                let (infos, lock_capacity) = self.collect_live_cells(
+                    m,
+                    lock.clone(),
+                    capacity + tx_fee - total_capacity,
+                    is_secp_cell,
+                )?;
+                total_capacity += lock_capacity;
+                cells.extend(infos.into_iter().map(|info| (lock.clone(), info)));
+            }
+            let fee_rate = match fee_rate {
+                Some(fee_rate) if total_capacity >= capacity + tx_fee => fee_rate,
+                _ => return Ok((cells, total_capacity, tx_fee)),
+            };
+            let dry_run_tx = build_dry_run(&cells, total_capacity, tx_fee)?;
+            let estimated_fee = calculate_tx_fee(tx_size_in_block(&dry_run_tx), fee_rate);
+            // More inputs may be collected for the higher fee, estimate again
+            if estimated_fee <= tx_fee {
+                return Ok((cells, total_capacity, tx_fee));
+            }
+            tx_fee = estimated_fee;
+        }
+    }
+
+    /// Resolve the change address by `change_strategy` config
+    fn change_address(
+        &mut self,
+        from_account: Option<&H160>,
+        from_address: &Address,
+    ) -> Result<Address, String> {
+        match self.change_strategy.clone() {
+            ChangeStrategy::Reuse => Ok(from_address.clone()),
+            ChangeStrategy::Fixed(address) => {
+                let network_type = get_network_type(self.rpc_client)?;
+                check_address_prefix(&address, network_type)?;
+                AddressParser.parse(&address)
+            }
+            ChangeStrategy::Derive => {
+                let lock_arg = from_account.ok_or_else(|| {
+                    "change_strategy=derive only works with --from-account".to_owned()
+                })?;
+                let account_path: DerivationPath = CKB_ACCOUNT_PATH.parse().unwrap();
+                let account_pubkey = match self
+                    .key_store
+                    .extended_pubkey(lock_arg, Some(&account_path))
+                {
+                    Err(KeyStoreError::AccountLocked(_)) => {
+                        let password =
+                            read_password(false, Some("Password (derive change address)"))?;
+                        self.key_store.extended_pubkey_with_password(
+                            lock_arg,
+                            Some(&account_path),
+                            password.as_bytes(),
+                        )
+                    }
+                    result => result,
+                }
+                .map_err(|err| err.to_string())?;
+                let secp_type_hash = self.genesis_info()?.secp_type_hash().clone();
+                let candidates = (0..MAX_CHANGE_ADDRESS_INDEX)
+                    .map(|index| {
+                        let children = vec![
+                            ChildNumber::from_normal_idx(1).map_err(|err| err.to_string())?,
+                            ChildNumber::from_normal_idx(index).map_err(|err| err.to_string())?,
+                        ];
+                        let pubkey = account_pubkey
+                            .derive_pub(&SECP256K1, &children)
+                            .map_err(|err| err.to_string())?;
+                        Address::from_pubkey(&pubkey.public_key)
+                    })
+                    .collect::<Result<Vec<_>, String>>()?;
+                // The first change address holds nothing
+                let locks = candidates
+                    .iter()
+                    .map(|address| address.lock_script(secp_type_hash.clone()))
+                    .collect();
+                let position = self.first_empty_lock(locks)?.ok_or_else(|| {
+                    format!(
+                        "All the first {} change addresses are in use",
+                        MAX_CHANGE_ADDRESS_INDEX
+                    )
+                })?;
+                Ok(candidates[position].clone())
+            }
+        }
+    }
+
     fn with_db<F, T>(&mut self, func: F) -> Result<T, String>
     where
         F: FnOnce(IndexDatabase) -> T,
     {
-        if !self.interactive {
-            return Err("ERROR: This is an interactive mode only sub-command".to_string());
+        if !self.interactive && !self.index_synced {
+            return Err(t("interactive-only").to_owned());
         }
 
         let network_type = get_network_type(self.rpc_client)?;
@@ -98,79 +540,386 @@ impl<'a> WalletSubCommand<'a> {
             Ok(func(db))
         })
         .map_err(|_err| {
-            format!(
-                "index database may not ready, sync process: {}",
-                self.index_controller.state().read().to_string()
+            tf(
+                "index-not-ready",
+                &[&self.index_controller.state().read().to_string()],
             )
         })
     }
 
+    /// The lock to query by `--lock-hash`, or by `--address` (`--pubkey`, `--lock-arg`)
+    fn lock_query(&mut self, m: &ArgMatches) -> Result<(CellQuery, Option<Address>), String> {
+        let lock_hash_opt: Option<H256> =
+            FixedHashParser::<H256>::default().from_matches_opt(m, "lock-hash", false)?;
+        if let Some(lock_hash) = lock_hash_opt {
+            return Ok((CellQuery::LockHash(lock_hash.pack()), None));
+        }
+        let address = get_address(m)?;
+        let lock = self.genesis_info()?.lock_script(&address)?;
+        Ok((CellQuery::Lock(lock), Some(address)))
+    }
+
     pub fn subcommand() -> App<'static, 'static> {
         SubCommand::with_name("wallet")
             .about("Transfer / query balance (with local index) / key utils")
             .subcommands(vec![
                 SubCommand::with_name("transfer")
-                    .about("Transfer capacity to an address (can have data)")
-                    .arg(arg::privkey_path().required_unless(arg::from_account().b.name))
-                    .arg(arg::from_account().required_unless(arg::privkey_path().b.name))
-                    .arg(arg::to_address().required(true))
+                    .about("Transfer capacity to an address or any lock script (can have data)")
+                    .arg(arg::wait_for_commit())
+                    .arg(arg::yes())
+                    .arg(arg::force_fee())
+                    .arg(arg::privkey_path().required_unless_one(&["from-account", "from-ledger-account", "from-all"]))
+                    .arg(
+                        arg::from_account()
+                            .required_unless_one(&["privkey-path", "from-ledger-account", "from-all"])
+                            .multiple(true)
+                            .number_of_values(1)
+                            .help("The account's lock-arg or label (transfer from this account, repeat it to draw from several accounts in one transaction)"),
+                    )
+                    .arg(arg::from_ledger_account().required_unless_one(&["privkey-path", "from-account", "from-all"]))
+                    .arg(arg::from_all())
+                    .arg(arg::to_address().required_unless_one(&["to-lock-script", "to-acp-address"]))
+                    .arg(arg::to_lock_script())
+                    .arg(arg::to_acp_address())
+                    .arg(arg::acp_code_hash())
+                    .arg(arg::acp_cell_dep())
                     .arg(arg::to_data())
                     .arg(arg::to_data_path())
                     .arg(arg::capacity().required(true))
-                    .arg(arg::tx_fee().required(true))
-                    .arg(arg::with_password()),
+                    .arg(arg::tx_fee())
+                    .arg(arg::fee_rate())
+                    .arg(arg::with_password())
+                    .arg(arg::override_policy())
+                    .arg(arg::include_frozen())
+                    .arg(arg::allow_unconfirmed_inputs())
+                    .arg(arg::coin_selection())
+                    .arg(arg::since_epoch())
+                    .arg(arg::since_timestamp()),
                 SubCommand::with_name("deposit-dao")
                     .about("Deposit capacity into NervosDAO(can have data)")
-                    .arg(arg::privkey_path().required_unless(arg::from_account().b.name))
-                    .arg(arg::from_account().required_unless(arg::privkey_path().b.name))
+                    .arg(arg::wait_for_commit())
+                    .arg(arg::yes())
+                    .arg(arg::force_fee())
+                    .arg(arg::privkey_path().required_unless_one(&["from-account", "from-ledger-account"]))
+                    .arg(arg::from_account().required_unless_one(&["privkey-path", "from-ledger-account"]))
+                    .arg(arg::from_ledger_account().required_unless_one(&["privkey-path", "from-account"]))
                     .arg(arg::to_address())
                     .arg(arg::to_data())
                     .arg(arg::to_data_path())
                     .arg(arg::capacity().required(true))
                     .arg(arg::tx_fee().required(true))
-                    .arg(arg::with_password()),
+                    .arg(arg::with_password())
+                    .arg(arg::override_policy())
+                    .arg(arg::include_frozen())
+                    .arg(arg::allow_unconfirmed_inputs())
+                    .arg(arg::coin_selection()),
                 SubCommand::with_name("withdraw-dao")
                     .about("Withdraw capacity from NervosDAO(can have data)")
-                    .arg(arg::privkey_path().required_unless(arg::from_account().b.name))
-                    .arg(arg::from_account().required_unless(arg::privkey_path().b.name))
+                    .arg(arg::wait_for_commit())
+                    .arg(arg::yes())
+                    .arg(arg::force_fee())
+                    .arg(arg::privkey_path().required_unless_one(&["from-account", "from-ledger-account"]))
+                    .arg(arg::from_account().required_unless_one(&["privkey-path", "from-ledger-account"]))
+                    .arg(arg::from_ledger_account().required_unless_one(&["privkey-path", "from-account"]))
                     .arg(arg::to_address())
                     .arg(arg::to_data())
                     .arg(arg::to_data_path())
                     .arg(arg::capacity().required(true))
                     .arg(arg::tx_fee().required(true))
-                    .arg(arg::with_password()),
+                    .arg(arg::with_password())
+                    .arg(arg::override_policy())
+                    .arg(arg::include_frozen())
+                    .arg(arg::coin_selection()),
+                SubCommand::with_name("payout")
+                    .about("Distribute matured cellbase rewards to miners by shares (for mining pool)")
+                    .arg(arg::wait_for_commit())
+                    .arg(arg::yes())
+                    .arg(arg::force_fee())
+                    .arg(arg::privkey_path().required_unless_one(&["from-account", "from-ledger-account"]))
+                    .arg(arg::from_account().required_unless_one(&["privkey-path", "from-ledger-account"]))
+                    .arg(arg::from_ledger_account().required_unless_one(&["privkey-path", "from-account"]))
+                    .arg(
+                        Arg::with_name("shares-path")
+                            .long("shares-path")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FilePathParser::new(true).validate(input))
+                            .help("Shares CSV file, each line: <address>,<shares>"),
+                    )
+                    .arg(
+                        Arg::with_name("pool-fee-rate")
+                            .long("pool-fee-rate")
+                            .takes_value(true)
+                            .default_value("0")
+                            .validator(|input| FromStrParser::<u8>::default().validate(input))
+                            .help("Percentage of rewards kept by pool operator (left as change)"),
+                    )
+                    .arg(arg::tx_fee().required(true))
+                    .arg(arg::with_password())
+                    .arg(arg::override_policy())
+                    .arg(arg::include_frozen()),
+                SubCommand::with_name("transfer-batch")
+                    .about("Transfer capacity to many recipients (CSV/JSON file), chunked into several transactions by --max-outputs")
+                    .arg(arg::wait_for_commit())
+                    .arg(arg::yes())
+                    .arg(arg::force_fee())
+                    .arg(arg::privkey_path().required_unless_one(&["from-account", "from-ledger-account"]))
+                    .arg(arg::from_account().required_unless_one(&["privkey-path", "from-ledger-account"]))
+                    .arg(arg::from_ledger_account().required_unless_one(&["privkey-path", "from-account"]))
+                    .arg(
+                        Arg::with_name("recipients-path")
+                            .long("recipients-path")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FilePathParser::new(true).validate(input))
+                            .help("Recipients file, CSV (each line: <address>,<capacity>) or JSON ([{\"address\": .., \"capacity\": ..}])"),
+                    )
+                    .arg(
+                        Arg::with_name("max-outputs")
+                            .long("max-outputs")
+                            .takes_value(true)
+                            .default_value("100")
+                            .validator(|input| FromStrParser::<usize>::default().validate(input))
+                            .help("Max recipients paid by one transaction"),
+                    )
+                    .arg(arg::tx_fee())
+                    .arg(arg::fee_rate())
+                    .arg(arg::with_password())
+                    .arg(arg::override_policy())
+                    .arg(arg::include_frozen())
+                    .arg(arg::allow_unconfirmed_inputs())
+                    .arg(arg::coin_selection()),
+                SubCommand::with_name("bump-fee")
+                    .about("Raise the fee of a pending transaction (sent by wallet). The node has no replace-by-fee, so a transaction still in the pool is bumped by a child transaction spending its change output of sender with the extra fee (child-pays-for-parent), and a transaction dropped by the node is rebuilt with a higher fee paid by its change output")
+                    .arg(arg::wait_for_commit())
+                    .arg(arg::yes())
+                    .arg(arg::force_fee())
+                    .arg(
+                        Arg::with_name("tx-hash")
+                            .takes_value(true)
+                            .required(true)
+                            .index(1)
+                            .validator(|input| FixedHashParser::<H256>::default().validate(input))
+                            .help("Hash of the pending transaction"),
+                    )
+                    .arg(arg::privkey_path().required_unless_one(&["from-account", "from-ledger-account"]))
+                    .arg(arg::from_account().required_unless_one(&["privkey-path", "from-ledger-account"]))
+                    .arg(arg::from_ledger_account().required_unless_one(&["privkey-path", "from-account"]))
+                    .arg(
+                        Arg::with_name("change-index")
+                            .long("change-index")
+                            .takes_value(true)
+                            .validator(|input| FromStrParser::<usize>::default().validate(input))
+                            .help("Index of the output paying the extra fee, or spent by the child transaction (default: the last output of sender)"),
+                    )
+                    .arg(arg::tx_fee().help("The new transaction fee, of both the parent and the child transaction when bumped by a child (unit: CKB, format: 0.335)"))
+                    .arg(arg::fee_rate())
+                    .arg(arg::with_password())
+                    .arg(arg::override_policy()),
+                SubCommand::with_name("tx-status")
+                    .about("Show status (and the confirming block) of a transaction, and the local record if it is sent by wallet")
+                    .arg(
+                        Arg::with_name("tx-hash")
+                            .index(1)
+                            .required(true)
+                            .validator(|input| FixedHashParser::<H256>::default().validate(input))
+                            .help("Transaction hash"),
+                    )
+                    .arg(arg::wait_for_commit()),
+                SubCommand::with_name("rebroadcast")
+                    .about("Send a previously sent transaction (from the pending transactions journal) or a signed transaction file to current node again")
+                    .arg(arg::wait_for_commit())
+                    .arg(
+                        Arg::with_name("tx-hash")
+                            .long("tx-hash")
+                            .takes_value(true)
+                            .required_unless("tx-file")
+                            .validator(|input| FixedHashParser::<H256>::default().validate(input))
+                            .help("Hash of the transaction sent by wallet"),
+                    )
+                    .arg(
+                        Arg::with_name("tx-file")
+                            .long("tx-file")
+                            .takes_value(true)
+                            .conflicts_with("tx-hash")
+                            .validator(|input| FilePathParser::new(true).validate(input))
+                            .help("Signed transaction file (json format, see rpc send_transaction, the `transaction` field is used if present)"),
+                    ),
+                SubCommand::with_name("sweep")
+                    .about("Send all the spendable capacity (matured cellbase included) of an account to an address, chained transactions are used when the cells not fit in one transaction")
+                    .arg(arg::wait_for_commit())
+                    .arg(arg::yes())
+                    .arg(arg::force_fee())
+                    .arg(arg::privkey_path().required_unless_one(&["from-account", "from-ledger-account"]))
+                    .arg(arg::from_account().required_unless_one(&["privkey-path", "from-ledger-account"]))
+                    .arg(arg::from_ledger_account().required_unless_one(&["privkey-path", "from-account"]))
+                    .arg(arg::to_address().required(true))
+                    .arg(
+                        Arg::with_name("max-inputs")
+                            .long("max-inputs")
+                            .takes_value(true)
+                            .default_value("500")
+                            .validator(|input| FromStrParser::<usize>::default().validate(input))
+                            .help("Max inputs of one transaction"),
+                    )
+                    .arg(arg::tx_fee().help("The fee of each transaction (unit: CKB, format: 0.335)"))
+                    .arg(arg::fee_rate())
+                    .arg(arg::with_password())
+                    .arg(arg::override_policy())
+                    .arg(arg::include_frozen()),
+                SubCommand::with_name("top-up")
+                    .about("Consolidate the small cells of an account into one cell by chained transactions sent back to itself, without an account it reports the keystore accounts having many small cells")
+                    .arg(arg::wait_for_commit())
+                    .arg(arg::yes())
+                    .arg(arg::force_fee())
+                    .arg(arg::privkey_path().conflicts_with_all(&["from-account", "from-ledger-account"]))
+                    .arg(arg::from_account().conflicts_with("from-ledger-account"))
+                    .arg(arg::from_ledger_account())
+                    .arg(
+                        Arg::with_name("max-cell-capacity")
+                            .long("max-cell-capacity")
+                            .takes_value(true)
+                            .default_value("1000")
+                            .validator(|input| CapacityParser.validate(input))
+                            .help("Only the cells with no more capacity are consolidated (unit: CKB, format: 123.335)"),
+                    )
+                    .arg(
+                        Arg::with_name("min-cells")
+                            .long("min-cells")
+                            .takes_value(true)
+                            .default_value("10")
+                            .validator(|input| FromStrParser::<usize>::default().validate(input))
+                            .help("Skip the accounts with fewer small cells"),
+                    )
+                    .arg(
+                        Arg::with_name("max-inputs")
+                            .long("max-inputs")
+                            .takes_value(true)
+                            .default_value("100")
+                            .validator(|input| FromStrParser::<usize>::default().validate(input))
+                            .help("Max inputs of one transaction"),
+                    )
+                    .arg(
+                        Arg::with_name("max-txs")
+                            .long("max-txs")
+                            .takes_value(true)
+                            .default_value("5")
+                            .validator(|input| FromStrParser::<usize>::default().validate(input))
+                            .help("Max transactions sent in one run, the rest cells are left to the next run"),
+                    )
+                    .arg(arg::tx_fee().help("The fee of each transaction (unit: CKB, format: 0.335)"))
+                    .arg(arg::fee_rate())
+                    .arg(arg::with_password())
+                    .arg(arg::override_policy())
+                    .arg(arg::include_frozen()),
+                multisig::subcommand(),
+                sudt::subcommand(),
+                deploy::subcommand(),
+                deploy::upgrade_subcommand(),
+                deploy::list_subcommand(),
+                SubCommand::with_name("freeze")
+                    .about("Mark cells as frozen, they will not be spent by transfer/deposit/withdraw")
+                    .arg(arg::out_points().required(true)),
+                SubCommand::with_name("unfreeze")
+                    .about("Unfreeze frozen cells")
+                    .arg(arg::out_points().required(true)),
+                SubCommand::with_name("list-frozen").about("List all frozen cells"),
+                SubCommand::with_name("unlock-cells")
+                    .about("Remove cells from the spent cells journal (inputs of the sent transactions skipped by transfer until the index catches up), by default the stale ones (transaction dropped or already indexed)")
+                    .arg(arg::out_points().conflicts_with("all"))
+                    .arg(
+                        Arg::with_name("all")
+                            .long("all")
+                            .help("Remove all the cells"),
+                    ),
+                SubCommand::with_name("clear-frozen").about("Unfreeze all frozen cells"),
                 SubCommand::with_name("get-capacity")
                     .about("Get capacity by lock script hash or address or lock arg or pubkey")
                     .arg(arg::lock_hash())
                     .arg(arg::address())
                     .arg(arg::pubkey())
                     .arg(arg::lock_arg()),
+                SubCommand::with_name("get-balance")
+                    .about("Get capacity grouped by spendable, immature cellbase, NervosDAO deposited/prepared, anyone-can-pay and unknown (with data or other type script) cells")
+                    .arg(arg::lock_hash())
+                    .arg(arg::address())
+                    .arg(arg::pubkey())
+                    .arg(arg::lock_arg())
+                    .arg(arg::acp_code_hash())
+                    .arg(arg::acp_cell_dep()),
                 SubCommand::with_name("get-dao-capacity")
                     .about("Get NervosDAO deposited capacity by lock script hash or address or lock arg or pubkey")
                     .arg(arg::lock_hash())
                     .arg(arg::address())
                     .arg(arg::pubkey())
-                    .arg(arg::lock_arg()),
+                    .arg(arg::lock_arg())
+                    .arg(arg::page_limit())
+                    .arg(arg::from_block_number())
+                    .arg(arg::to_block_number())
+                    .arg(arg::min_capacity()),
+                SubCommand::with_name("verify-dao-deposit")
+                    .about("Check a committed transaction deposits to NervosDAO for an address (for auditing deposits), show the deposit block and epoch, and when to prepare (withdraw phase 1) in current lock period")
+                    .arg(
+                        Arg::with_name("tx-hash")
+                            .long("tx-hash")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FixedHashParser::<H256>::default().validate(input))
+                            .help("Hash of the deposit transaction"),
+                    )
+                    .arg(arg::address().required(true).help("Address the deposits belong to")),
                 SubCommand::with_name("get-live-cells")
-                    .about("Get live cells by lock/type/code  hash")
+                    .about("Get live cells by address or lock/type/code  hash, cell data is decoded by the schemas in ~/.ckb-cli/cell-schemas")
+                    .arg(arg::address())
                     .arg(arg::lock_hash())
                     .arg(arg::type_hash())
                     .arg(arg::code_hash())
+                    .arg(arg::type_script())
                     .arg(arg::live_cells_limit())
                     .arg(arg::from_block_number())
-                    .arg(arg::to_block_number()),
+                    .arg(arg::to_block_number())
+                    .arg(arg::min_capacity()),
                 // Move to index subcommand
                 SubCommand::with_name("get-lock-by-address")
                     .about("Get lock script (include hash) by address")
                     .arg(arg::address().required(true)),
                 // Move to index subcommand
                 SubCommand::with_name("db-metrics").about("Show index database metrics"),
+                SubCommand::with_name("analyze-cells")
+                    .about("Analyze live cells of a lock (count, capacity, age distribution)")
+                    .arg(arg::lock_hash())
+                    .arg(arg::address())
+                    .arg(arg::pubkey())
+                    .arg(arg::lock_arg())
+                    .arg(
+                        Arg::with_name("older-than-epochs")
+                            .long("older-than-epochs")
+                            .takes_value(true)
+                            .default_value("180")
+                            .validator(|input| FromStrParser::<u64>::default().validate(input))
+                            .help("Report the cells created more than this number of epochs ago"),
+                    ),
                 SubCommand::with_name("top-capacity")
                     .about("Show top n capacity owned by lock script hash")
                     .arg(arg::top_n()),
+                SubCommand::with_name("tx-history")
+                    .about("Show incoming/outgoing transactions of an address (newest first), only the blocks indexed by this version are covered")
+                    .arg(arg::lock_hash())
+                    .arg(arg::address())
+                    .arg(
+                        Arg::with_name("limit")
+                            .long("limit")
+                            .takes_value(true)
+                            .default_value("20")
+                            .validator(|input| FromStrParser::<usize>::default().validate(input))
+                            .help("Max number of transactions"),
+                    ),
             ])
     }
 
+    /// Transfer from a private key, a ledger account, or one or several keystore accounts
+    /// (`--from-account` repeated or `--from-all`) in one transaction: one lock group per
+    /// account and one fee for all, the change goes back to the first account.
     pub fn transfer(
         &mut self,
         m: &ArgMatches,
@@ -180,99 +929,219 @@ impl<'a> WalletSubCommand<'a> {
     ) -> Result<String, String> {
         let from_privkey: Option<PrivkeyWrapper> =
             PrivkeyPathParser.from_matches_opt(m, "privkey-path", false)?;
-        let from_account: Option<H160> =
-            FixedHashParser::<H160>::default().from_matches_opt(m, "from-account", false)?;
+        let from_ledger: Option<LedgerSigner> = from_ledger(m)?;
+        // Only the keystore accounts are signed by lock groups
+        let from_accounts = if from_privkey.is_none() && from_ledger.is_none() {
+            self.from_accounts(m)?
+        } else {
+            Vec::new()
+        };
         let capacity: u64 = CapacityParser.from_matches(m, "capacity")?;
-        let tx_fee: u64 = CapacityParser.from_matches(m, "tx-fee")?;
-        let from_address = if let Some(from_privkey) = from_privkey.as_ref() {
+        let from_addresses = if let Some(from_privkey) = from_privkey.as_ref() {
             let from_pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, from_privkey);
             let pubkey_hash = blake2b_256(&from_pubkey.serialize()[..]);
-            Address::from_lock_arg(&pubkey_hash[0..20])?
+            vec![Address::from_lock_arg(&pubkey_hash[0..20])?]
+        } else if let Some(ledger) = from_ledger.as_ref() {
+            vec![ledger.address()?]
         } else {
-            Address::from_lock_arg(from_account.as_ref().unwrap().as_bytes())?
+            from_accounts
+                .iter()
+                .map(|lock_arg| Address::from_lock_arg(lock_arg.as_bytes()))
+                .collect::<Result<Vec<_>, String>>()?
+        };
+        let from_address = from_addresses
+            .first()
+            .cloned()
+            .ok_or_else(|| "No account to transfer from".to_owned())?;
+        let to_lock: Option<Script> = ScriptParser.from_matches_opt(m, "to-lock-script", false)?;
+        let to_acp_address: Option<Address> =
+            LabeledAddressParser.from_matches_opt(m, "to-acp-address", false)?;
+        let genesis_info = self.genesis_info()?;
+        let to_address: Address = if let Some(lock) = to_lock.as_ref() {
+            // The signing policy sees the real address of a sighash lock, other locks by the
+            // lock hash prefix (never in `allowed_addresses`)
+            policy_address(lock, &genesis_info)?
+        } else if let Some(address) = to_acp_address.as_ref() {
+            address.clone()
+        } else {
+            LabeledAddressParser.from_matches(m, "to-address")?
         };
-        let to_address: Address = AddressParser.from_matches(m, "to-address")?;
         let to_data = to_data(m)?;
         let with_password = m.is_present("with-password");
 
-        check_capacity(capacity, to_data.len())?;
+        if let Some(lock) = to_lock.as_ref() {
+            confirm_to_lock(lock, capacity, to_data.len())?;
+        } else if to_acp_address.is_some() {
+            if !to_data.is_empty() {
+                return Err("Can not set data of the anyone-can-pay cell".to_owned());
+            }
+        } else {
+            check_capacity(capacity, to_data.len())?;
+        }
         let network_type = get_network_type(self.rpc_client)?;
-        let genesis_info = self.genesis_info()?;
         let secp_type_hash = genesis_info.secp_type_hash();
+        let to_since = self.to_since(m)?;
+        if to_since.is_some() {
+            genesis_info.multisig_type_hash()?;
+            // The since value (8 bytes) is appended to lock args
+            check_capacity(capacity, to_data.len() + 8)?;
+        }
 
-        check_address_prefix(m.value_of("to-address").unwrap(), network_type)?;
+        check_to_address_prefix(m, network_type)?;
         // For check index database is ready
-        self.with_db(|_| ())?;
-        let index_dir = self.index_dir.clone();
-        let genesis_hash = genesis_info.header().hash();
-        let genesis_info_clone = genesis_info.clone();
-        let mut total_capacity = 0;
-        let terminator = |_, info: &LiveCellInfo| {
-            let out_point = info.out_point();
-            let resp: CellWithStatus = self
-                .rpc_client
-                .get_live_cell(out_point.into(), true)
-                .call()
-                .expect("get_live_cell by RPC call failed");
-            if is_live_cell(&resp) && is_secp_cell(&resp) {
-                total_capacity += info.capacity;
-                (total_capacity >= capacity + tx_fee, true)
-            } else {
-                (false, false)
-            }
+        self.check_index_ready()?;
+        let from_locks = from_addresses
+            .iter()
+            .map(|address| address.lock_script(secp_type_hash.clone()))
+            .collect::<Vec<_>>();
+        let change_address = self.change_address(from_accounts.first(), &from_address)?;
+        let change_policy = self.change_policy;
+        let acp_cell = match to_acp_address.as_ref() {
+            Some(address) => Some(self.find_acp_cell(m, address, capacity)?),
+            None => None,
         };
-        let infos: Vec<LiveCellInfo> =
-            with_index_db(&index_dir, genesis_hash.unpack(), |backend, cf| {
-                let db =
-                    IndexDatabase::from_db(backend, cf, network_type, genesis_info_clone, false)?;
-                Ok(db.get_live_cells_by_lock(
-                    from_address
-                        .lock_script(secp_type_hash.clone())
-                        .calc_script_hash(),
-                    None,
-                    terminator,
-                ))
-            })
-            .map_err(|_err| {
-                format!(
-                    "index database may not ready, sync process: {}",
-                    self.index_controller.state().read().to_string()
-                )
+        let (from_ref, to_data_ref, to_address_ref) = (&from_address, &to_data, &to_address);
+        let tx_builder =
+            move |cells: &[(Script, LiveCellInfo)], total_capacity: u64, tx_fee: u64| {
+                let mut tx_args = TransferTransactionBuilder::new(
+                    from_ref,
+                    total_capacity,
+                    to_data_ref,
+                    to_address_ref,
+                    capacity,
+                    tx_fee,
+                    cells.iter().map(|(_, info)| info.input()).collect(),
+                );
+                tx_args.set_change_address(change_address.clone());
+                tx_args.set_change_policy(change_policy);
+                tx_args.set_input_locks(cells.iter().map(|(lock, _)| lock.clone()).collect());
+                if let Some(since) = to_since {
+                    tx_args.set_to_since(since);
+                }
+                if let Some(lock) = to_lock.clone() {
+                    tx_args.set_to_lock(lock);
+                }
+                if let Some((acp_dep, input, output, data)) = acp_cell.clone() {
+                    tx_args.set_to_acp_cell(acp_dep, input, output, data);
+                }
+                tx_args
+            };
+        let (cells, total_capacity, tx_fee) =
+            self.collect_live_cells_from_locks(m, &from_locks, capacity, |cells, total, fee| {
+                tx_builder(cells, total, fee).transfer_from_locks(&genesis_info, |_, _| {
+                    Ok(LockWitness::Sighash.placeholder())
+                })
             })?;
 
         if total_capacity < capacity + tx_fee {
-            return Err(format!(
-                "Capacity not enough: {} => {}",
-                from_address.to_string(network_type),
-                total_capacity,
+            let addresses = from_addresses
+                .iter()
+                .map(|address| address.to_string(network_type))
+                .collect::<Vec<_>>();
+            return Err(tf(
+                "capacity-not-enough",
+                &[&addresses.join(", "), &total_capacity],
             ));
         }
-        let inputs = infos.iter().map(LiveCellInfo::input).collect::<Vec<_>>();
-        let mut tx_args = TransferTransactionBuilder::new(
-            &from_address,
-            total_capacity,
-            &to_data,
-            &to_address,
-            capacity,
-            tx_fee,
-            inputs,
-        );
+
+        // Each account is checked with the part of `capacity` it pays, in the drawn order
+        let mut policies = Vec::new();
+        let mut passwords: HashMap<H160, String> = HashMap::new();
+        let mut rest_capacity = capacity;
+        for (idx, (address, lock)) in from_addresses.iter().zip(from_locks.iter()).enumerate() {
+            let account_capacity: u64 = cells
+                .iter()
+                .filter(|(other, _)| other.as_slice() == lock.as_slice())
+                .map(|(_, info)| info.capacity)
+                .sum();
+            if account_capacity == 0 {
+                continue;
+            }
+            let paid = cmp::min(account_capacity, rest_capacity);
+            rest_capacity -= paid;
+            if let Some(policy) = self.check_policy(
+                m,
+                SignKind::Transfer,
+                address,
+                slice::from_ref(&to_address),
+                paid,
+            )? {
+                policies.push((policy, paid));
+            }
+            if let Some(lock_arg) = from_accounts.get(idx).filter(|_| with_password) {
+                let prompt = if from_accounts.len() > 1 {
+                    Some(format!("Password for [{:x}]", lock_arg))
+                } else {
+                    None
+                };
+                passwords.insert(
+                    lock_arg.clone(),
+                    read_password(false, prompt.as_ref().map(String::as_str))?,
+                );
+            }
+        }
+        let mut tx_args = tx_builder(&cells, total_capacity, tx_fee);
         let transaction = if let Some(privkey) = from_privkey.as_ref() {
-            tx_args.transfer(&genesis_info, |args| {
-                Ok(build_witness_with_key(privkey, args))
-            })
+            // Nothing to ask from the user, the lock groups are signed in parallel
+            tx_args
+                .par_transfer_from_locks(&genesis_info, |_, args| witness_with_key(privkey, args))
         } else {
-            let lock_arg = from_account.as_ref().unwrap();
-            let password = if with_password {
-                Some(read_password(false, None)?)
-            } else {
-                None
-            };
-            tx_args.transfer(&genesis_info, |args| {
-                self.build_witness_with_keystore(lock_arg, args, &password)
+            tx_args.transfer_from_locks(&genesis_info, |lock, args| {
+                if let Some(ledger) = from_ledger.as_ref() {
+                    ledger.build_witness(args)
+                } else {
+                    let lock_arg = H160::from_slice(&lock.args().raw_data())
+                        .map_err(|err| format!("Invalid lock arg: {:?}", err))?;
+                    let password = passwords.get(&lock_arg).cloned();
+                    self.build_witness_with_keystore(&lock_arg, args, &password)
+                }
             })
         }?;
-        self.send_transaction(transaction, format, color, debug)
+        if let [lock_arg] = from_accounts.as_slice() {
+            if self.is_watch_only(lock_arg)? {
+                return Ok(unsigned_transaction(transaction, format, color));
+            }
+        }
+        let resp = self.send_transaction(m, transaction, format, color, debug)?;
+        for (policy, paid) in policies {
+            policy.record(paid)?;
+        }
+        Ok(resp)
+    }
+
+    /// The keystore accounts to transfer from: `--from-account` (repeated) or all the accounts
+    /// by `--from-all`. Only a single account can be watch-only (the transaction is printed
+    /// unsigned).
+    fn from_accounts(&mut self, m: &ArgMatches) -> Result<Vec<H160>, String> {
+        let lock_args: Vec<H160> = if m.is_present("from-all") {
+            let mut lock_args = self
+                .key_store
+                .get_accounts()
+                .keys()
+                .cloned()
+                .collect::<Vec<_>>();
+            lock_args.sort();
+            if lock_args.is_empty() {
+                return Err("No account found in keystore".to_owned());
+            }
+            lock_args
+        } else {
+            LabeledLockArgParser.from_matches_vec(m, "from-account")?
+        };
+        let mut accounts: Vec<H160> = Vec::new();
+        for lock_arg in lock_args {
+            if !accounts.contains(&lock_arg) {
+                accounts.push(lock_arg);
+            }
+        }
+        if accounts.len() > 1 {
+            for lock_arg in &accounts {
+                if self.is_watch_only(lock_arg)? {
+                    return Err(format!("Watch-only account {:#x} can not sign", lock_arg));
+                }
+            }
+        }
+        Ok(accounts)
     }
 
     pub fn deposit_dao(
@@ -285,17 +1154,20 @@ impl<'a> WalletSubCommand<'a> {
         let from_privkey: Option<PrivkeyWrapper> =
             PrivkeyPathParser.from_matches_opt(m, "privkey-path", false)?;
         let from_account: Option<H160> =
-            FixedHashParser::<H160>::default().from_matches_opt(m, "from-account", false)?;
+            LabeledLockArgParser.from_matches_opt(m, "from-account", false)?;
         let capacity: u64 = CapacityParser.from_matches(m, "capacity")?;
         let tx_fee: u64 = CapacityParser.from_matches(m, "tx-fee")?;
+        let from_ledger: Option<LedgerSigner> = from_ledger(m)?;
         let from_address = if let Some(from_privkey) = from_privkey.as_ref() {
             let from_pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, from_privkey);
             let pubkey_hash = blake2b_256(&from_pubkey.serialize()[..]);
             Address::from_lock_arg(&pubkey_hash[0..20])?
+        } else if let Some(ledger) = from_ledger.as_ref() {
+            ledger.address()?
         } else {
             Address::from_lock_arg(from_account.as_ref().unwrap().as_bytes())?
         };
-        let to_address: Address = AddressParser
+        let to_address: Address = LabeledAddressParser
             .from_matches_opt(m, "to-address", false)?
             .unwrap_or_else(|| from_address.clone());
         let to_data = to_data(m)?;
@@ -306,58 +1178,29 @@ impl<'a> WalletSubCommand<'a> {
         let genesis_info = self.genesis_info()?;
         let secp_type_hash = genesis_info.secp_type_hash();
 
-        if let Some(address) = m.value_of("to-address") {
-            check_address_prefix(address, network_type)?;
-        }
+        check_to_address_prefix(m, network_type)?;
         // For check index database is ready
-        self.with_db(|_| ())?;
-        let index_dir = self.index_dir.clone();
-        let genesis_hash = genesis_info.header().hash();
-        let genesis_info_clone = genesis_info.clone();
-        let mut total_capacity = 0;
-        let terminator = |_, info: &LiveCellInfo| {
-            let out_point = info.out_point();
-            let resp: CellWithStatus = self
-                .rpc_client
-                .get_live_cell(out_point.into(), true)
-                .call()
-                .expect("get_live_cell by RPC call failed");
-            if is_live_cell(&resp) && is_secp_cell(&resp) {
-                total_capacity += info.capacity;
-                (total_capacity >= capacity + tx_fee, true)
-            } else {
-                (false, false)
-            }
-        };
-        let infos: Vec<LiveCellInfo> =
-            with_index_db(&index_dir, genesis_hash.unpack(), |backend, cf| {
-                let db =
-                    IndexDatabase::from_db(backend, cf, network_type, genesis_info_clone, false)?;
-
-                Ok(db.get_live_cells_by_lock(
-                    from_address
-                        .lock_script(secp_type_hash.clone())
-                        .calc_script_hash(),
-                    None,
-                    terminator,
-                ))
-            })
-            .map_err(|_err| {
-                format!(
-                    "index database may not ready, sync process: {}",
-                    self.index_controller.state().read().to_string()
-                )
-            })?;
+        self.check_index_ready()?;
+        let from_lock = from_address.lock_script(secp_type_hash.clone());
+        let (infos, total_capacity) =
+            self.collect_live_cells(m, from_lock, capacity + tx_fee, is_secp_cell)?;
 
         if total_capacity < capacity + tx_fee {
-            return Err(format!(
-                "Capacity not enough: {} => {}",
-                from_address.to_string(network_type),
-                total_capacity,
+            return Err(tf(
+                "capacity-not-enough",
+                &[&from_address.to_string(network_type), &total_capacity],
             ));
         }
 
+        let policy = self.check_policy(
+            m,
+            SignKind::DepositDao,
+            &from_address,
+            slice::from_ref(&to_address),
+            capacity,
+        )?;
         let inputs = infos.iter().map(LiveCellInfo::input).collect::<Vec<_>>();
+        let change_address = self.change_address(from_account.as_ref(), &from_address)?;
         let mut tx_args = TransferTransactionBuilder::new(
             &from_address,
             total_capacity,
@@ -367,10 +1210,12 @@ impl<'a> WalletSubCommand<'a> {
             tx_fee,
             inputs,
         );
+        tx_args.set_change_address(change_address);
+        tx_args.set_change_policy(self.change_policy);
         let transaction = if let Some(privkey) = from_privkey.as_ref() {
-            tx_args.deposit_dao(&genesis_info, |args| {
-                Ok(build_witness_with_key(privkey, args))
-            })
+            tx_args.deposit_dao(&genesis_info, |_, args| witness_with_key(privkey, args))
+        } else if let Some(ledger) = from_ledger.as_ref() {
+            tx_args.deposit_dao(&genesis_info, |_, args| ledger.build_witness(args))
         } else {
             let lock_arg = from_account.as_ref().unwrap();
             let password = if with_password {
@@ -378,11 +1223,20 @@ impl<'a> WalletSubCommand<'a> {
             } else {
                 None
             };
-            tx_args.deposit_dao(&genesis_info, |args| {
+            tx_args.deposit_dao(&genesis_info, |_, args| {
                 self.build_witness_with_keystore(lock_arg, args, &password)
             })
         }?;
-        self.send_transaction(transaction, format, color, debug)
+        if let Some(lock_arg) = from_account.as_ref() {
+            if self.is_watch_only(lock_arg)? {
+                return Ok(unsigned_transaction(transaction, format, color));
+            }
+        }
+        let resp = self.send_transaction(m, transaction, format, color, debug)?;
+        if let Some(policy) = policy {
+            policy.record(capacity)?;
+        }
+        Ok(resp)
     }
 
     pub fn withdraw_dao(
@@ -395,17 +1249,20 @@ impl<'a> WalletSubCommand<'a> {
         let from_privkey: Option<PrivkeyWrapper> =
             PrivkeyPathParser.from_matches_opt(m, "privkey-path", false)?;
         let from_account: Option<H160> =
-            FixedHashParser::<H160>::default().from_matches_opt(m, "from-account", false)?;
+            LabeledLockArgParser.from_matches_opt(m, "from-account", false)?;
         let capacity: u64 = CapacityParser.from_matches(m, "capacity")?;
         let tx_fee: u64 = CapacityParser.from_matches(m, "tx-fee")?;
+        let from_ledger: Option<LedgerSigner> = from_ledger(m)?;
         let from_address = if let Some(from_privkey) = from_privkey.as_ref() {
             let from_pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, from_privkey);
             let pubkey_hash = blake2b_256(&from_pubkey.serialize()[..]);
             Address::from_lock_arg(&pubkey_hash[0..20])?
+        } else if let Some(ledger) = from_ledger.as_ref() {
+            ledger.address()?
         } else {
             Address::from_lock_arg(from_account.as_ref().unwrap().as_bytes())?
         };
-        let to_address: Address = AddressParser
+        let to_address: Address = LabeledAddressParser
             .from_matches_opt(m, "to-address", false)?
             .unwrap_or_else(|| from_address.clone());
         let to_data = to_data(m)?;
@@ -416,59 +1273,34 @@ impl<'a> WalletSubCommand<'a> {
         let genesis_info = self.genesis_info()?;
         let secp_type_hash = genesis_info.secp_type_hash();
 
-        if let Some(address) = m.value_of("to-address") {
-            check_address_prefix(address, network_type)?;
-        }
+        check_to_address_prefix(m, network_type)?;
         // For check index database is ready
-        self.with_db(|_| ())?;
-        let index_dir = self.index_dir.clone();
-        let genesis_hash = genesis_info.header().hash();
-        let genesis_info_clone = genesis_info.clone();
-        let mut total_capacity = 0;
-        let terminator = |_, info: &LiveCellInfo| {
-            let out_point = info.out_point();
-            let resp: CellWithStatus = self
-                .rpc_client
-                .get_live_cell(out_point.into(), true)
-                .call()
-                .expect("get_live_cell by RPC call failed");
-            if is_live_cell(&resp) && is_dao_cell(&resp, genesis_info.dao_type_hash()) {
-                total_capacity += info.capacity;
-                (total_capacity >= capacity + tx_fee, true)
-            } else {
-                (false, false)
-            }
-        };
-        let infos: Vec<LiveCellInfo> =
-            with_index_db(&index_dir, genesis_hash.unpack(), |backend, cf| {
-                let db =
-                    IndexDatabase::from_db(backend, cf, network_type, genesis_info_clone, false)?;
-                Ok(db.get_live_cells_by_lock(
-                    from_address
-                        .lock_script(secp_type_hash.clone())
-                        .calc_script_hash(),
-                    None,
-                    terminator,
-                ))
-            })
-            .map_err(|_err| {
-                format!(
-                    "index database may not ready, sync process: {}",
-                    self.index_controller.state().read().to_string()
-                )
+        self.check_index_ready()?;
+        let dao_type_hash = genesis_info.dao_type_hash().clone();
+        let from_lock = from_address.lock_script(secp_type_hash.clone());
+        let (infos, total_capacity) =
+            self.collect_live_cells(m, from_lock, capacity + tx_fee, |resp| {
+                is_dao_cell(&resp, &dao_type_hash)
             })?;
 
         if total_capacity < capacity + tx_fee {
-            return Err(format!(
-                "Capacity not enough: {} => {}",
-                from_address.to_string(network_type),
-                total_capacity,
+            return Err(tf(
+                "capacity-not-enough",
+                &[&from_address.to_string(network_type), &total_capacity],
             ));
         }
 
+        let policy = self.check_policy(
+            m,
+            SignKind::WithdrawDao,
+            &from_address,
+            slice::from_ref(&to_address),
+            capacity,
+        )?;
         let inputs_and_header_hashes = build_dao_inputs(&mut self.rpc_client, infos)?;
         let (inputs, input_header_hashes) = inputs_and_header_hashes.into_iter().unzip();
         let withdraw_header_hash = build_dao_withdraw_hash(&mut self.rpc_client)?;
+        let change_address = self.change_address(from_account.as_ref(), &from_address)?;
         let mut tx_args = TransferTransactionBuilder::new(
             &from_address,
             total_capacity,
@@ -478,12 +1310,21 @@ impl<'a> WalletSubCommand<'a> {
             tx_fee,
             inputs,
         );
+        tx_args.set_change_address(change_address);
+        tx_args.set_change_policy(self.change_policy);
         let transaction = if let Some(privkey) = from_privkey.as_ref() {
             tx_args.withdraw_dao(
                 withdraw_header_hash,
                 input_header_hashes,
                 &genesis_info,
-                |args| Ok(build_witness_with_key(privkey, args)),
+                |_, args| witness_with_key(privkey, args),
+            )
+        } else if let Some(ledger) = from_ledger.as_ref() {
+            tx_args.withdraw_dao(
+                withdraw_header_hash,
+                input_header_hashes,
+                &genesis_info,
+                |_, args| ledger.build_witness(args),
             )
         } else {
             let lock_arg = from_account.as_ref().unwrap();
@@ -496,61 +1337,1524 @@ impl<'a> WalletSubCommand<'a> {
                 withdraw_header_hash,
                 input_header_hashes,
                 &genesis_info,
-                |args| self.build_witness_with_keystore(lock_arg, args, &password),
+                |_, args| self.build_witness_with_keystore(lock_arg, args, &password),
             )
         }?;
-        self.send_transaction(transaction, format, color, debug)
+        if let Some(lock_arg) = from_account.as_ref() {
+            if self.is_watch_only(lock_arg)? {
+                return Ok(unsigned_transaction(transaction, format, color));
+            }
+        }
+        let resp = self.send_transaction(m, transaction, format, color, debug)?;
+        if let Some(policy) = policy {
+            policy.record(capacity)?;
+        }
+        Ok(resp)
     }
 
-    fn build_witness_with_keystore(
-        &mut self,
-        lock_arg: &H160,
-        args: &[Vec<u8>],
-        password: &Option<String>,
-    ) -> Result<Bytes, String> {
-        let sign_hash = H256::from_slice(&blake2b_args(args))
-            .expect("converting digest of [u8; 32] to H256 should be ok");
-        let signature_result = if self.interactive && password.is_none() {
-            self.key_store
-                    .sign_recoverable(lock_arg, &sign_hash)
-                    .map_err(|err| {
-                        match err {
-                            KeyStoreError::AccountLocked(lock_arg) => {
-                                format!("Account(lock_arg={:x}) locked or not exists, your may use `account unlock` to unlock it or use --with-password", lock_arg)
-                            }
-                            err => err.to_string(),
-                        }
-                    })
-        } else if let Some(password) = password {
-            self.key_store
-                .sign_recoverable_with_password(lock_arg, &sign_hash, password.as_bytes())
-                .map_err(|err| err.to_string())
+    /// Parse `--since-epoch`/`--since-timestamp`, the lock time must be later than chain tip
+    fn to_since(&mut self, m: &ArgMatches) -> Result<Option<Since>, String> {
+        let since_epoch: Option<u64> =
+            FromStrParser::<u64>::default().from_matches_opt(m, "since-epoch", false)?;
+        let since_timestamp: Option<u64> =
+            FromStrParser::<u64>::default().from_matches_opt(m, "since-timestamp", false)?;
+        if let Some(epoch) = since_epoch {
+            let current_epoch = self
+                .rpc_client
+                .get_current_epoch()
+                .call()
+                .map_err(|err| err.to_string())?
+                .number
+                .value();
+            if epoch <= current_epoch {
+                return Err(format!(
+                    "--since-epoch {} is not later than current epoch {}",
+                    epoch, current_epoch
+                ));
+            }
+            Ok(Some(Since::from_epoch(epoch, false)))
+        } else if let Some(timestamp) = since_timestamp {
+            let tip_header: HeaderView = self
+                .rpc_client
+                .get_tip_header()
+                .call()
+                .map_err(|err| format!("Send get_tip_header error: {}", err))?;
+            let tip_timestamp = tip_header.inner.timestamp.value() / 1000;
+            if timestamp <= tip_timestamp {
+                return Err(format!(
+                    "--since-timestamp {} is not later than tip block timestamp {}",
+                    timestamp, tip_timestamp
+                ));
+            }
+            Ok(Some(Since::from_timestamp(timestamp, false)))
         } else {
-            return Err("Password required to unlock the keystore".to_owned());
-        };
-        signature_result.map(|signature| serialize_signature(&signature))
+            Ok(None)
+        }
     }
 
-    fn send_transaction(
+    /// Evaluate local signing policy before any signature is produced
+    fn check_policy(
+        &self,
+        m: &ArgMatches,
+        kind: SignKind,
+        from_address: &Address,
+        to_addresses: &[Address],
+        capacity: u64,
+    ) -> Result<Option<SigningPolicy>, String> {
+        let request = SignRequest {
+            kind,
+            from_address,
+            to_addresses,
+            capacity,
+            cosigned: false,
+        };
+        check_policy(&request, m.is_present("override-policy"))
+    }
+
+    pub fn payout(
         &mut self,
-        transaction: TransactionView,
+        m: &ArgMatches,
+        format: OutputFormat,
+        color: bool,
+        debug: bool,
+    ) -> Result<String, String> {
+        let from_privkey: Option<PrivkeyWrapper> =
+            PrivkeyPathParser.from_matches_opt(m, "privkey-path", false)?;
+        let from_account: Option<H160> =
+            LabeledLockArgParser.from_matches_opt(m, "from-account", false)?;
+        let from_ledger: Option<LedgerSigner> = from_ledger(m)?;
+        let from_address = if let Some(from_privkey) = from_privkey.as_ref() {
+            let from_pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, from_privkey);
+            let pubkey_hash = blake2b_256(&from_pubkey.serialize()[..]);
+            Address::from_lock_arg(&pubkey_hash[0..20])?
+        } else if let Some(ledger) = from_ledger.as_ref() {
+            ledger.address()?
+        } else {
+            Address::from_lock_arg(from_account.as_ref().unwrap().as_bytes())?
+        };
+        let tx_fee: u64 = CapacityParser.from_matches(m, "tx-fee")?;
+        let pool_fee_rate: u8 = FromStrParser::<u8>::default().from_matches(m, "pool-fee-rate")?;
+        if pool_fee_rate > 100 {
+            return Err("pool-fee-rate must <= 100".to_owned());
+        }
+        let shares_content = fs::read_to_string(m.value_of("shares-path").unwrap())
+            .map_err(|err| err.to_string())?;
+        let shares = parse_shares(&shares_content)?;
+        let with_password = m.is_present("with-password");
+
+        let network_type = get_network_type(self.rpc_client)?;
+        for share in &shares {
+            check_address_prefix(&share.address_str, network_type)?;
+        }
+        let genesis_info = self.genesis_info()?;
+        let from_lock = from_address.lock_script(genesis_info.secp_type_hash().clone());
+
+        let mut ledger = PayoutLedger::load(get_ckb_cli_dir().join("payout-ledger"))?;
+        let frozen = load_frozen(m)?;
+        let is_spent = self.spent_filter()?;
+        let cellbase_infos = self.live_cells(CellQuery::Lock(from_lock), None, |_, info| {
+            let is_frozen =
+                frozen.as_ref().map(|f| f.contains(info)).unwrap_or(false) || is_spent(info);
+            (
+                false,
+                info.index.tx_index == 0 && !ledger.is_paid(info) && !is_frozen,
+            )
+        })?;
+        let tip_epoch = self.tip_epoch()?;
+        let mut infos = Vec::new();
+        for info in cellbase_infos {
+            if spendable::is_mature(self.block_epoch(info.number)?, tip_epoch) {
+                infos.push(info);
+            }
+        }
+
+        let total_capacity: u64 = infos.iter().map(|info| info.capacity).sum();
+        if total_capacity <= tx_fee {
+            return Err(format!(
+                "No enough matured cellbase capacity to pay out: {} (cells: {})",
+                total_capacity,
+                infos.len()
+            ));
+        }
+        let distributable = (total_capacity - tx_fee) / 100 * u64::from(100 - pool_fee_rate);
+        let (payouts, skipped) = split_rewards(distributable, &shares);
+        if payouts.is_empty() {
+            return Err("All payouts are too small to afford a cell".to_owned());
+        }
+
+        // One transaction, the payouts are checked (and recorded) together
+        let payout_capacity: u64 = payouts.iter().map(|(_, amount)| amount).sum();
+        let payout_addresses = payouts
+            .iter()
+            .map(|(share, _)| share.address.clone())
+            .collect::<Vec<_>>();
+        let policy = self.check_policy(
+            m,
+            SignKind::Transfer,
+            &from_address,
+            &payout_addresses,
+            payout_capacity,
+        )?;
+        let inputs = infos.iter().map(LiveCellInfo::input).collect::<Vec<_>>();
+        let to_data = Bytes::new();
+        let change_address = self.change_address(from_account.as_ref(), &from_address)?;
+        let mut tx_args = TransferTransactionBuilder::new(
+            &from_address,
+            total_capacity,
+            &to_data,
+            &payouts[0].0.address,
+            payouts[0].1,
+            tx_fee,
+            inputs,
+        );
+        tx_args.set_change_address(change_address);
+        tx_args.set_change_policy(self.change_policy);
+        for (share, amount) in payouts.iter().skip(1) {
+            tx_args.add_output(share.address.clone(), *amount, Bytes::new())?;
+        }
+        let transaction = if let Some(privkey) = from_privkey.as_ref() {
+            tx_args.transfer(&genesis_info, |args| witness_with_key(privkey, args))
+        } else if let Some(ledger) = from_ledger.as_ref() {
+            tx_args.transfer(&genesis_info, |args| ledger.build_witness(args))
+        } else {
+            let lock_arg = from_account.as_ref().unwrap();
+            let password = if with_password {
+                Some(read_password(false, None)?)
+            } else {
+                None
+            };
+            tx_args.transfer(&genesis_info, |args| {
+                self.build_witness_with_keystore(lock_arg, args, &password)
+            })
+        }?;
+        if let Some(lock_arg) = from_account.as_ref() {
+            if self.is_watch_only(lock_arg)? {
+                return Ok(unsigned_transaction(transaction, format, color));
+            }
+        }
+        let tx_hash: H256 = transaction.hash().unpack();
+        self.send_transaction(m, transaction, format, color, debug)?;
+        ledger.record(&infos, &tx_hash)?;
+        if let Some(policy) = policy {
+            policy.record(payout_capacity)?;
+        }
+
+        let resp = serde_json::json!({
+            "transaction_hash": tx_hash,
+            "cellbase_cells": infos.len(),
+            "total_capacity": total_capacity,
+            "tx_fee": tx_fee,
+            "payouts": payouts.iter().map(|(share, amount)| serde_json::json!({
+                "address": share.address_str,
+                "shares": share.weight,
+                "capacity": amount,
+            })).collect::<Vec<_>>(),
+            "skipped": skipped.iter().map(|(share, amount)| serde_json::json!({
+                "address": share.address_str,
+                "shares": share.weight,
+                "capacity": amount,
+            })).collect::<Vec<_>>(),
+        });
+        Ok(resp.render(format, color))
+    }
+
+    pub fn transfer_batch(
+        &mut self,
+        m: &ArgMatches,
+        format: OutputFormat,
+        color: bool,
+        debug: bool,
+    ) -> Result<String, String> {
+        let from_privkey: Option<PrivkeyWrapper> =
+            PrivkeyPathParser.from_matches_opt(m, "privkey-path", false)?;
+        let from_account: Option<H160> =
+            LabeledLockArgParser.from_matches_opt(m, "from-account", false)?;
+        let from_ledger: Option<LedgerSigner> = from_ledger(m)?;
+        let from_address = if let Some(from_privkey) = from_privkey.as_ref() {
+            let from_pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, from_privkey);
+            let pubkey_hash = blake2b_256(&from_pubkey.serialize()[..]);
+            Address::from_lock_arg(&pubkey_hash[0..20])?
+        } else if let Some(ledger) = from_ledger.as_ref() {
+            ledger.address()?
+        } else {
+            Address::from_lock_arg(from_account.as_ref().unwrap().as_bytes())?
+        };
+        let recipients_content = fs::read_to_string(m.value_of("recipients-path").unwrap())
+            .map_err(|err| err.to_string())?;
+        let recipients = parse_recipients(&recipients_content)?;
+        let max_outputs: usize =
+            FromStrParser::<usize>::default().from_matches(m, "max-outputs")?;
+        if max_outputs == 0 {
+            return Err("max-outputs must > 0".to_owned());
+        }
+        let with_password = m.is_present("with-password");
+
+        let network_type = get_network_type(self.rpc_client)?;
+        for recipient in &recipients {
+            check_address_prefix(&recipient.address_str, network_type)?;
+            check_capacity(recipient.capacity, 0)?;
+        }
+        let genesis_info = self.genesis_info()?;
+        // For check index database is ready
+        self.check_index_ready()?;
+        let from_lock = from_address.lock_script(genesis_info.secp_type_hash().clone());
+        let to_addresses = recipients
+            .iter()
+            .map(|recipient| recipient.address.clone())
+            .collect::<Vec<_>>();
+        let total_capacity: u64 = recipients.iter().map(|recipient| recipient.capacity).sum();
+        let policy = self.check_policy(
+            m,
+            SignKind::Transfer,
+            &from_address,
+            &to_addresses,
+            total_capacity,
+        )?;
+        let change_address = self.change_address(from_account.as_ref(), &from_address)?;
+        let change_policy = self.change_policy;
+        let password = if from_privkey.is_none() && from_ledger.is_none() && with_password {
+            Some(read_password(false, None)?)
+        } else {
+            None
+        };
+        let is_watch_only = match from_account.as_ref() {
+            Some(lock_arg) => self.is_watch_only(lock_arg)?,
+            None => false,
+        };
+
+        let to_data = Bytes::new();
+        let chunks = recipients.chunks(max_outputs).collect::<Vec<_>>();
+        let mut reports = Vec::new();
+        // The change of previous chunk: (input, capacity), spent by the next chunk so the
+        // chunks do not wait for each other to be committed
+        let mut carried: Option<(CellInput, u64)> = None;
+        for (chunk_idx, chunk) in chunks.iter().enumerate() {
+            let is_last = chunk_idx + 1 == chunks.len();
+            // Only the last change goes to the change address, the others are spent at once
+            let chunk_change_address = if is_last {
+                &change_address
+            } else {
+                &from_address
+            };
+            let capacity: u64 = chunk.iter().map(|recipient| recipient.capacity).sum();
+            let carried_input = carried.as_ref().map(|(input, _)| input.clone());
+            let carried_capacity = carried.as_ref().map(|(_, capacity)| *capacity).unwrap_or(0);
+            let with_carried = |inputs: Vec<CellInput>| {
+                carried_input
+                    .iter()
+                    .cloned()
+                    .chain(inputs)
+                    .collect::<Vec<_>>()
+            };
+            let chunk_result = self
+                .collect_live_cells_with_fee_from(
+                    m,
+                    from_lock.clone(),
+                    carried_capacity,
+                    capacity,
+                    is_secp_cell,
+                    |inputs, total_capacity, tx_fee| {
+                        let mut tx_args = batch_tx_args(
+                            &from_address,
+                            &to_data,
+                            chunk,
+                            chunk_change_address,
+                            carried_capacity + total_capacity,
+                            tx_fee,
+                            with_carried(inputs),
+                        )?;
+                        tx_args.set_change_policy(change_policy);
+                        tx_args.transfer(&genesis_info, |_| Ok(LockWitness::Sighash.placeholder()))
+                    },
+                )
+                .and_then(|(infos, total_capacity, tx_fee)| {
+                    let total_capacity = carried_capacity + total_capacity;
+                    if total_capacity < capacity + tx_fee {
+                        return Err(tf(
+                            "capacity-not-enough",
+                            &[&from_address.to_string(network_type), &total_capacity],
+                        ));
+                    }
+                    let inputs = with_carried(infos.iter().map(LiveCellInfo::input).collect());
+                    let mut tx_args = batch_tx_args(
+                        &from_address,
+                        &to_data,
+                        chunk,
+                        chunk_change_address,
+                        total_capacity,
+                        tx_fee,
+                        inputs,
+                    )?;
+                    tx_args.set_change_policy(change_policy);
+                    let transaction = if let Some(privkey) = from_privkey.as_ref() {
+                        tx_args.transfer(&genesis_info, |args| witness_with_key(privkey, args))
+                    } else if let Some(ledger) = from_ledger.as_ref() {
+                        tx_args.transfer(&genesis_info, |args| ledger.build_witness(args))
+                    } else {
+                        let lock_arg = from_account.as_ref().unwrap();
+                        tx_args.transfer(&genesis_info, |args| {
+                            self.build_witness_with_keystore(lock_arg, args, &password)
+                        })
+                    }?;
+                    Ok((infos, tx_fee, transaction))
+                });
+            let (infos, tx_fee, transaction) = chunk_result.map_err(|err| {
+                format!(
+                    "Transfer of chunk {} failed: {}, sent transactions: {}",
+                    chunk_idx,
+                    err,
+                    serde_json::json!(reports)
+                )
+            })?;
+            let tx_hash: H256 = transaction.hash().unpack();
+            // The change output follows the recipients
+            carried = transaction.outputs().get(chunk.len()).map(|output| {
+                let out_point = OutPoint::new_builder()
+                    .tx_hash(tx_hash.pack())
+                    .index((chunk.len() as u32).pack())
+                    .build();
+                (CellInput::new(out_point, 0), output.capacity().unpack())
+            });
+            let mut report = serde_json::json!({
+                "chunk": chunk_idx,
+                "transaction_hash": tx_hash,
+                "recipients": chunk.len(),
+                "capacity": capacity,
+                "tx_fee": tx_fee,
+            });
+            if is_watch_only {
+                let transaction_view: ckb_jsonrpc_types::TransactionView = transaction.into();
+                report["signed"] = serde_json::json!(false);
+                report["transaction"] = serde_json::json!(transaction_view);
+            } else {
+                self.send_transaction(m, transaction, format, color, debug)
+                    .map_err(|err| {
+                        format!(
+                            "Send transaction of chunk {} failed: {}, sent transactions: {}",
+                            chunk_idx,
+                            err,
+                            serde_json::json!(reports)
+                        )
+                    })?;
+                if let Some(policy) = policy.as_ref() {
+                    policy.record(capacity)?;
+                }
+            }
+            self.spent_inputs.extend(infos.iter().map(out_point_key));
+            reports.push(report);
+        }
+
+        let resp = serde_json::json!({
+            "recipients": recipients.len(),
+            "total_capacity": total_capacity,
+            "transactions": reports,
+        });
+        Ok(resp.render(format, color))
+    }
+
+    pub fn sweep(
+        &mut self,
+        m: &ArgMatches,
+        format: OutputFormat,
+        color: bool,
+        debug: bool,
+    ) -> Result<String, String> {
+        let from_privkey: Option<PrivkeyWrapper> =
+            PrivkeyPathParser.from_matches_opt(m, "privkey-path", false)?;
+        let from_account: Option<H160> =
+            LabeledLockArgParser.from_matches_opt(m, "from-account", false)?;
+        let from_ledger: Option<LedgerSigner> = from_ledger(m)?;
+        let from_address = if let Some(from_privkey) = from_privkey.as_ref() {
+            let from_pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, from_privkey);
+            let pubkey_hash = blake2b_256(&from_pubkey.serialize()[..]);
+            Address::from_lock_arg(&pubkey_hash[0..20])?
+        } else if let Some(ledger) = from_ledger.as_ref() {
+            ledger.address()?
+        } else {
+            Address::from_lock_arg(from_account.as_ref().unwrap().as_bytes())?
+        };
+        let to_address: Address = LabeledAddressParser.from_matches(m, "to-address")?;
+
+        let network_type = get_network_type(self.rpc_client)?;
+        check_to_address_prefix(m, network_type)?;
+        let genesis_info = self.genesis_info()?;
+        let from_lock = from_address.lock_script(genesis_info.secp_type_hash().clone());
+        let (infos, immature) = self.spendable_secp_cells(m, from_lock)?;
+        if infos.is_empty() {
+            return Err(format!(
+                "No spendable cells found: {}",
+                from_address.to_string(network_type)
+            ));
+        }
+        let total_capacity: u64 = infos.iter().map(|info| info.capacity).sum();
+        let policy = self.check_policy(
+            m,
+            SignKind::Transfer,
+            &from_address,
+            slice::from_ref(&to_address),
+            total_capacity,
+        )?;
+        let is_watch_only = match from_account.as_ref() {
+            Some(lock_arg) => self.is_watch_only(lock_arg)?,
+            None => false,
+        };
+        let (reports, swept_capacity) = self.send_chained(
+            m,
+            from_privkey.as_ref(),
+            from_ledger.as_ref(),
+            from_account.as_ref(),
+            &from_address,
+            &to_address,
+            &infos,
+            format,
+            color,
+            debug,
+        )?;
+        if !is_watch_only {
+            if let Some(policy) = policy {
+                policy.record(swept_capacity)?;
+            }
+        }
+
+        let resp = serde_json::json!({
+            "cells": infos.len(),
+            "immature_cells": immature,
+            "total_capacity": total_capacity,
+            "swept_capacity": swept_capacity,
+            "transactions": reports,
+        });
+        Ok(resp.render(format, color))
+    }
+
+    pub fn top_up(
+        &mut self,
+        m: &ArgMatches,
         format: OutputFormat,
         color: bool,
         debug: bool,
     ) -> Result<String, String> {
-        let transaction_view: ckb_jsonrpc_types::TransactionView = transaction.clone().into();
-        if debug {
-            println!(
-                "[Send Transaction]:\n{}",
-                transaction_view.render(format, color)
+        let from_privkey: Option<PrivkeyWrapper> =
+            PrivkeyPathParser.from_matches_opt(m, "privkey-path", false)?;
+        let from_account: Option<H160> =
+            LabeledLockArgParser.from_matches_opt(m, "from-account", false)?;
+        let from_ledger: Option<LedgerSigner> = from_ledger(m)?;
+        let max_cell_capacity: u64 = CapacityParser.from_matches(m, "max-cell-capacity")?;
+        let min_cells: usize = FromStrParser::<usize>::default().from_matches(m, "min-cells")?;
+        let max_inputs: usize = FromStrParser::<usize>::default().from_matches(m, "max-inputs")?;
+        let max_txs: usize = FromStrParser::<usize>::default().from_matches(m, "max-txs")?;
+        if max_txs == 0 {
+            return Err("max-txs must >= 1".to_owned());
+        }
+        let min_cells = min_cells.max(2);
+
+        let network_type = get_network_type(self.rpc_client)?;
+        let genesis_info = self.genesis_info()?;
+        let from_address = if let Some(from_privkey) = from_privkey.as_ref() {
+            let from_pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, from_privkey);
+            let pubkey_hash = blake2b_256(&from_pubkey.serialize()[..]);
+            Address::from_lock_arg(&pubkey_hash[0..20])?
+        } else if let Some(ledger) = from_ledger.as_ref() {
+            ledger.address()?
+        } else if let Some(lock_arg) = from_account.as_ref() {
+            Address::from_lock_arg(lock_arg.as_bytes())?
+        } else {
+            // Report only, the cells are counted by the index
+            let frozen = load_frozen(m)?;
+            let is_spent = self.spent_filter()?;
+            let mut lock_args = self
+                .key_store
+                .get_accounts()
+                .keys()
+                .cloned()
+                .collect::<Vec<_>>();
+            lock_args.sort();
+            let mut accounts = Vec::new();
+            for lock_arg in lock_args {
+                let address = Address::from_lock_arg(lock_arg.as_bytes())?;
+                let lock = genesis_info.lock_script(&address)?;
+                let infos = self.live_cells(CellQuery::Lock(lock), None, |_, info| {
+                    let is_small = is_plain_cell(info)
+                        && info.capacity <= max_cell_capacity
+                        && !frozen.as_ref().map(|f| f.contains(info)).unwrap_or(false)
+                        && !is_spent(info);
+                    (false, is_small)
+                })?;
+                if infos.len() < min_cells {
+                    continue;
+                }
+                accounts.push(serde_json::json!({
+                    "lock_arg": lock_arg,
+                    "address": address.to_string(network_type),
+                    "small_cells": infos.len(),
+                    "small_capacity": infos.iter().map(|info| info.capacity).sum::<u64>(),
+                    "reclaimable_occupied_capacity": reclaimed_occupied_capacity(infos.len()),
+                }));
+            }
+            return Ok(serde_json::json!({ "accounts": accounts }).render(format, color));
+        };
+
+        let from_lock = genesis_info.lock_script(&from_address)?;
+        let (mut infos, _) = self.spendable_secp_cells(m, from_lock)?;
+        infos.retain(|info| info.capacity <= max_cell_capacity);
+        let small_cells = infos.len();
+        let mut resp = serde_json::json!({
+            "address": from_address.to_string(network_type),
+            "small_cells": small_cells,
+        });
+        if small_cells < min_cells {
+            resp["transactions"] = serde_json::json!([]);
+            return Ok(resp.render(format, color));
+        }
+        infos.truncate(max_chained_cells(max_inputs, max_txs));
+        // The cells are sent back to the account, only the fees (known once the transactions are
+        // built) leave it
+        let policy = self.check_policy(m, SignKind::Transfer, &from_address, &[], 0)?;
+        let is_watch_only = match from_account.as_ref() {
+            Some(lock_arg) => self.is_watch_only(lock_arg)?,
+            None => false,
+        };
+        let (reports, capacity) = self.send_chained(
+            m,
+            from_privkey.as_ref(),
+            from_ledger.as_ref(),
+            from_account.as_ref(),
+            &from_address,
+            &from_address,
+            &infos,
+            format,
+            color,
+            debug,
+        )?;
+        if !is_watch_only {
+            if let Some(policy) = policy {
+                let total_capacity: u64 = infos.iter().map(|info| info.capacity).sum();
+                policy.record(total_capacity - capacity)?;
+            }
+        }
+        resp["consolidated_cells"] = serde_json::json!(infos.len());
+        resp["remaining_cells"] = serde_json::json!(small_cells - infos.len());
+        resp["capacity"] = serde_json::json!(capacity);
+        resp["reclaimed_occupied_capacity"] =
+            serde_json::json!(reclaimed_occupied_capacity(infos.len()));
+        resp["transactions"] = serde_json::json!(reports);
+        Ok(resp.render(format, color))
+    }
+    /// The live secp256k1 cells of the lock (frozen and spent cells excluded unless
+    /// `--include-frozen`) and the number of the immature cellbase cells skipped
+    fn spendable_secp_cells(
+        &mut self,
+        m: &ArgMatches,
+        from_lock: Script,
+    ) -> Result<(Vec<LiveCellInfo>, usize), String> {
+        let frozen = load_frozen(m)?;
+        let is_spent = self.spent_filter()?;
+        let candidates = self.live_cells(CellQuery::Lock(from_lock), None, |_, info| {
+            let is_frozen =
+                frozen.as_ref().map(|f| f.contains(info)).unwrap_or(false) || is_spent(info);
+            (false, !is_frozen)
+        })?;
+        let tip_epoch = self.tip_epoch()?;
+        let mut infos = Vec::new();
+        let mut immature = 0;
+        for info in candidates {
+            let resp: CellWithStatus = self
+                .rpc_client
+                .get_live_cell(info.out_point().into(), true)
+                .call()
+                .map_err(|err| format!("get_live_cell by RPC call failed: {}", err))?;
+            if !is_live_cell(&resp) || !is_secp_cell(&resp) {
+                continue;
+            }
+            if spendable::is_cellbase(&info)
+                && !spendable::is_mature(self.block_epoch(info.number)?, tip_epoch)
+            {
+                immature += 1;
+                continue;
+            }
+            infos.push(info);
+        }
+        Ok((infos, immature))
+    }
+
+    /// Send the cells to `to_address` by chained transactions (see `split_sweep_inputs`), return
+    /// the reports of the transactions and the capacity of the last output
+    #[allow(clippy::too_many_arguments)]
+    fn send_chained(
+        &mut self,
+        m: &ArgMatches,
+        from_privkey: Option<&PrivkeyWrapper>,
+        from_ledger: Option<&LedgerSigner>,
+        from_account: Option<&H160>,
+        from_address: &Address,
+        to_address: &Address,
+        infos: &[LiveCellInfo],
+        format: OutputFormat,
+        color: bool,
+        debug: bool,
+    ) -> Result<(Vec<serde_json::Value>, u64), String> {
+        let max_inputs: usize = FromStrParser::<usize>::default().from_matches(m, "max-inputs")?;
+        let fee_rate = self.fee_rate(m)?;
+        let fixed_tx_fee: Option<u64> = CapacityParser.from_matches_opt(m, "tx-fee", false)?;
+        let with_password = m.is_present("with-password");
+        let genesis_info = self.genesis_info()?;
+        let password = if from_privkey.is_none() && from_ledger.is_none() && with_password {
+            Some(read_password(false, None)?)
+        } else {
+            None
+        };
+        let is_watch_only = match from_account {
+            Some(lock_arg) => self.is_watch_only(lock_arg)?,
+            None => false,
+        };
+
+        let to_data = Bytes::new();
+        let chunks = split_sweep_inputs(infos, max_inputs)?;
+        let mut reports = Vec::new();
+        // The output of previous transaction: (input, capacity)
+        let mut carried: Option<(CellInput, u64)> = None;
+        for (idx, chunk) in chunks.iter().enumerate() {
+            let is_last = idx + 1 == chunks.len();
+            // Intermediate transactions send back to sender, spent by the next transaction
+            let target = if is_last { to_address } else { from_address };
+            let mut inputs = Vec::with_capacity(chunk.len() + 1);
+            let mut input_capacity = 0;
+            if let Some((input, capacity)) = carried.take() {
+                inputs.push(input);
+                input_capacity += capacity;
+            }
+            inputs.extend(chunk.iter().map(LiveCellInfo::input));
+            input_capacity += chunk.iter().map(|info| info.capacity).sum::<u64>();
+            let tx_fee = match fee_rate {
+                Some(fee_rate) => {
+                    let dry_run_tx = TransferTransactionBuilder::new(
+                        from_address,
+                        input_capacity,
+                        &to_data,
+                        target,
+                        input_capacity,
+                        0,
+                        inputs.clone(),
+                    )
+                    .transfer(&genesis_info, |_| Ok(LockWitness::Sighash.placeholder()))?;
+                    calculate_tx_fee(tx_size_in_block(&dry_run_tx), fee_rate)
+                }
+                None => fixed_tx_fee.unwrap(),
+            };
+            if input_capacity < tx_fee + *MIN_SECP_CELL_CAPACITY {
+                return Err(format!(
+                    "Capacity not enough to sweep: {} (transaction {}, fee: {}), sent transactions: {}",
+                    input_capacity,
+                    idx,
+                    tx_fee,
+                    serde_json::json!(reports)
+                ));
+            }
+            let output_capacity = input_capacity - tx_fee;
+            let mut tx_args = TransferTransactionBuilder::new(
+                from_address,
+                input_capacity,
+                &to_data,
+                target,
+                output_capacity,
+                tx_fee,
+                inputs,
             );
+            let transaction = if let Some(privkey) = from_privkey {
+                tx_args.par_transfer_from_locks(&genesis_info, |_, args| {
+                    witness_with_key(privkey, args)
+                })
+            } else if let Some(ledger) = from_ledger {
+                tx_args.transfer(&genesis_info, |args| ledger.build_witness(args))
+            } else {
+                let lock_arg = from_account.unwrap();
+                tx_args.transfer(&genesis_info, |args| {
+                    self.build_witness_with_keystore(lock_arg, args, &password)
+                })
+            }?;
+            let tx_hash: H256 = transaction.hash().unpack();
+            let mut report = serde_json::json!({
+                "transaction_hash": tx_hash,
+                "inputs": transaction.inputs().len(),
+                "capacity": output_capacity,
+                "tx_fee": tx_fee,
+            });
+            if is_watch_only {
+                let transaction_view: ckb_jsonrpc_types::TransactionView = transaction.into();
+                report["signed"] = serde_json::json!(false);
+                report["transaction"] = serde_json::json!(transaction_view);
+            } else {
+                self.send_transaction(m, transaction, format, color, debug)
+                    .map_err(|err| {
+                        format!(
+                            "Send transaction {} failed: {}, sent transactions: {}",
+                            idx,
+                            err,
+                            serde_json::json!(reports)
+                        )
+                    })?;
+            }
+            self.spent_inputs.extend(chunk.iter().map(out_point_key));
+            let out_point = OutPoint::new_builder()
+                .tx_hash(tx_hash.pack())
+                .index(0u32.pack())
+                .build();
+            carried = Some((CellInput::new(out_point, 0), output_capacity));
+            reports.push(report);
         }
+        let (_, capacity) = carried.ok_or_else(|| "No cell to send".to_owned())?;
+        Ok((reports, capacity))
+    }
+
+    pub fn bump_fee(
+        &mut self,
+        m: &ArgMatches,
+        format: OutputFormat,
+        color: bool,
+        debug: bool,
+    ) -> Result<String, String> {
+        let tx_hash: H256 = FixedHashParser::<H256>::default().from_matches(m, "tx-hash")?;
+        let from_privkey: Option<PrivkeyWrapper> =
+            PrivkeyPathParser.from_matches_opt(m, "privkey-path", false)?;
+        let from_account: Option<H160> =
+            LabeledLockArgParser.from_matches_opt(m, "from-account", false)?;
+        let from_ledger: Option<LedgerSigner> = from_ledger(m)?;
+        let from_address = if let Some(from_privkey) = from_privkey.as_ref() {
+            let from_pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, from_privkey);
+            let pubkey_hash = blake2b_256(&from_pubkey.serialize()[..]);
+            Address::from_lock_arg(&pubkey_hash[0..20])?
+        } else if let Some(ledger) = from_ledger.as_ref() {
+            ledger.address()?
+        } else {
+            Address::from_lock_arg(from_account.as_ref().unwrap().as_bytes())?
+        };
+        let change_index: Option<usize> =
+            FromStrParser::<usize>::default().from_matches_opt(m, "change-index", false)?;
+        let fee_rate = self.fee_rate(m)?;
+        let with_password = m.is_present("with-password");
 
-        let resp = self
+        let mut pending_txs = PendingTxs::load_default()?;
+        let old_tx = pending_txs.get(&tx_hash)?.transaction();
+        let tx_opt = self
             .rpc_client
-            .send_transaction(transaction.data().into())
+            .get_transaction(tx_hash.clone())
             .call()
-            .map_err(|err| format!("Send transaction error: {}", err))?;
+            .map_err(|err| format!("Send get_transaction error: {}", err))?
+            .0;
+        if let Some(block_hash) = tx_opt
+            .as_ref()
+            .and_then(|tx| tx.tx_status.block_hash.clone())
+        {
+            pending_txs.remove(&tx_hash)?;
+            return Err(format!(
+                "Transaction {:#x} already committed in block {:#x}",
+                tx_hash, block_hash
+            ));
+        }
+        // The node (v0.24) has no replace-by-fee, a transaction spending the same inputs as one
+        // in the pool is rejected as a conflict. So a transaction in the pool is bumped by a child
+        // transaction spending its change with the extra fee (child-pays-for-parent), and a
+        // transaction dropped by the node is rebuilt with a higher fee.
+        let in_pool = tx_opt.is_some();
+
+        let genesis_info = self.genesis_info()?;
+        let from_lock_hash = genesis_info.lock_script(&from_address)?.calc_script_hash();
+        let mut input_capacity = 0;
+        for input in old_tx.inputs().into_iter() {
+            let output = self.get_cell_output(&input.previous_output())?;
+            if !in_pool && output.lock().calc_script_hash() != from_lock_hash {
+                return Err(format!(
+                    "Input {} is not owned by sender, only the transaction signed by one secp256k1 lock can be rebuilt",
+                    out_point_to_string(&input.previous_output())
+                ));
+            }
+            let capacity: u64 = output.capacity().unpack();
+            input_capacity += capacity;
+        }
+        let mut outputs = old_tx.outputs().into_iter().collect::<Vec<_>>();
+        let output_capacity: u64 = outputs
+            .iter()
+            .map(|output| Unpack::<u64>::unpack(&output.capacity()))
+            .sum();
+        let old_fee = input_capacity
+            .checked_sub(output_capacity)
+            .ok_or_else(|| "Outputs capacity exceeds inputs capacity".to_owned())?;
+        let change_index = match change_index {
+            Some(index) if index < outputs.len() => index,
+            Some(index) => return Err(format!("Output index out of range: {}", index)),
+            None => outputs
+                .iter()
+                .rposition(|output| output.lock().calc_script_hash() == from_lock_hash)
+                .ok_or_else(|| {
+                    "No output of sender found to pay the extra fee, use --change-index".to_owned()
+                })?,
+        };
+        if in_pool {
+            let parent_output = outputs[change_index].clone();
+            if parent_output.lock().calc_script_hash() != from_lock_hash {
+                return Err(format!(
+                    "Output {} is not owned by sender, it can not be spent by the child transaction",
+                    change_index
+                ));
+            }
+            let has_data = old_tx
+                .outputs_data()
+                .get(change_index)
+                .map(|data| !data.raw_data().is_empty())
+                .unwrap_or(false);
+            if parent_output.type_().to_opt().is_some() || has_data {
+                return Err(format!(
+                    "Output {} has a type script or data, it can not be spent by the child transaction",
+                    change_index
+                ));
+            }
+            let parent_capacity: u64 = parent_output.capacity().unpack();
+            let out_point = OutPoint::new_builder()
+                .tx_hash(tx_hash.pack())
+                .index((change_index as u32).pack())
+                .build();
+            let inputs = vec![CellInput::new(out_point, 0)];
+            let to_data = Bytes::new();
+            // The fee of both the parent and the child transaction
+            let package_fee = match fee_rate {
+                Some(fee_rate) => {
+                    let dry_run_tx = TransferTransactionBuilder::new(
+                        &from_address,
+                        parent_capacity,
+                        &to_data,
+                        &from_address,
+                        parent_capacity,
+                        0,
+                        inputs.clone(),
+                    )
+                    .transfer(&genesis_info, |_| Ok(LockWitness::Sighash.placeholder()))?;
+                    calculate_tx_fee(
+                        tx_size_in_block(&old_tx) + tx_size_in_block(&dry_run_tx),
+                        fee_rate,
+                    )
+                }
+                None => CapacityParser.from_matches(m, "tx-fee")?,
+            };
+            if package_fee <= old_fee {
+                return Err(format!(
+                    "New fee ({}) must be greater than the old fee ({})",
+                    package_fee, old_fee
+                ));
+            }
+            let child_fee = package_fee - old_fee;
+            if parent_capacity < child_fee + *MIN_SECP_CELL_CAPACITY {
+                return Err(format!(
+                    "Output {} can not pay the fee of the child transaction: {} < {} + {} (minimal capacity)",
+                    change_index, parent_capacity, child_fee, *MIN_SECP_CELL_CAPACITY
+                ));
+            }
+            // Sent back to sender, only the fee is spent
+            let policy = self.check_policy(m, SignKind::Transfer, &from_address, &[], child_fee)?;
+            let mut tx_args = TransferTransactionBuilder::new(
+                &from_address,
+                parent_capacity,
+                &to_data,
+                &from_address,
+                parent_capacity - child_fee,
+                child_fee,
+                inputs,
+            );
+            let transaction = if let Some(privkey) = from_privkey.as_ref() {
+                tx_args.transfer(&genesis_info, |args| witness_with_key(privkey, args))
+            } else if let Some(ledger) = from_ledger.as_ref() {
+                tx_args.transfer(&genesis_info, |args| ledger.build_witness(args))
+            } else {
+                let lock_arg = from_account.as_ref().unwrap();
+                let password = if with_password {
+                    Some(read_password(false, None)?)
+                } else {
+                    None
+                };
+                tx_args.transfer(&genesis_info, |args| {
+                    self.build_witness_with_keystore(lock_arg, args, &password)
+                })
+            }?;
+            if let Some(lock_arg) = from_account.as_ref() {
+                if self.is_watch_only(lock_arg)? {
+                    return Ok(unsigned_transaction(transaction, format, color));
+                }
+            }
+            let child_tx_hash: H256 = transaction.hash().unpack();
+            self.send_transaction(m, transaction, format, color, debug)?;
+            if let Some(policy) = policy {
+                policy.record(child_fee)?;
+            }
+            let resp = serde_json::json!({
+                "parent": tx_hash,
+                "transaction_hash": child_tx_hash,
+                "old_fee": old_fee,
+                "new_fee": package_fee,
+                "child_fee": child_fee,
+            });
+            return Ok(resp.render(format, color));
+        }
+
+        let new_fee = match fee_rate {
+            // Only the capacity of an output and the signature are changed, so is the size
+            Some(fee_rate) => calculate_tx_fee(tx_size_in_block(&old_tx), fee_rate),
+            None => CapacityParser.from_matches(m, "tx-fee")?,
+        };
+        if new_fee <= old_fee {
+            return Err(format!(
+                "New fee ({}) must be greater than the old fee ({})",
+                new_fee, old_fee
+            ));
+        }
+        let extra_fee = new_fee - old_fee;
+        let change_capacity: u64 = outputs[change_index].capacity().unpack();
+        let data_len = old_tx
+            .outputs_data()
+            .get(change_index)
+            .map(|data| data.raw_data().len())
+            .unwrap_or(0);
+        let occupied = outputs[change_index]
+            .occupied_capacity(Capacity::bytes(data_len).map_err(|err| err.to_string())?)
+            .map_err(|err| err.to_string())?
+            .as_u64();
+        if change_capacity < occupied + extra_fee {
+            return Err(format!(
+                "Output {} can not pay the extra fee: {} - {} < {} (occupied)",
+                change_index, change_capacity, extra_fee, occupied
+            ));
+        }
+        outputs[change_index] = outputs[change_index]
+            .clone()
+            .as_builder()
+            .capacity(Capacity::shannons(change_capacity - extra_fee).pack())
+            .build();
+        // The sent capacity is recorded already, only the extra fee is new
+        let to_addresses = outputs
+            .iter()
+            .filter(|output| output.lock().calc_script_hash() != from_lock_hash)
+            .map(|output| policy_address(&output.lock(), &genesis_info))
+            .collect::<Result<Vec<_>, String>>()?;
+        let policy = self.check_policy(
+            m,
+            SignKind::Transfer,
+            &from_address,
+            &to_addresses,
+            extra_fee,
+        )?;
+        let unsigned_tx = old_tx.as_advanced_builder().set_outputs(outputs).build();
+
+        let transaction = if let Some(privkey) = from_privkey.as_ref() {
+            sign_secp_transaction(&unsigned_tx, |args| witness_with_key(privkey, args))
+        } else if let Some(ledger) = from_ledger.as_ref() {
+            sign_secp_transaction(&unsigned_tx, |args| ledger.build_witness(args))
+        } else {
+            let lock_arg = from_account.as_ref().unwrap();
+            let password = if with_password {
+                Some(read_password(false, None)?)
+            } else {
+                None
+            };
+            sign_secp_transaction(&unsigned_tx, |args| {
+                self.build_witness_with_keystore(lock_arg, args, &password)
+            })
+        }?;
+        if let Some(lock_arg) = from_account.as_ref() {
+            if self.is_watch_only(lock_arg)? {
+                return Ok(unsigned_transaction(transaction, format, color));
+            }
+        }
+        let new_tx_hash: H256 = transaction.hash().unpack();
+        self.send_transaction(m, transaction, format, color, debug)?;
+        if let Some(policy) = policy {
+            policy.record(extra_fee)?;
+        }
+        // Reload, the new transaction is added by `send_transaction`
+        PendingTxs::load_default()?.remove(&tx_hash)?;
+        let resp = serde_json::json!({
+            "replaced": tx_hash,
+            "transaction_hash": new_tx_hash,
+            "old_fee": old_fee,
+            "new_fee": new_fee,
+        });
+        Ok(resp.render(format, color))
+    }
+
+    /// The anyone-can-pay lock deployment, by `--acp-code-hash` and `--acp-cell-dep` or the
+    /// known deployment of current network
+    fn acp_config(&mut self, m: &ArgMatches) -> Result<Option<AcpConfig>, String> {
+        let code_hash: Option<H256> =
+            FixedHashParser::<H256>::default().from_matches_opt(m, "acp-code-hash", false)?;
+        let dep_out_point: Option<OutPoint> =
+            OutPointParser.from_matches_opt(m, "acp-cell-dep", false)?;
+        if let (Some(code_hash), Some(dep_out_point)) = (code_hash, dep_out_point) {
+            return Ok(Some(AcpConfig::new(code_hash, dep_out_point)));
+        }
+        Ok(AcpConfig::from_network(get_network_type(self.rpc_client)?))
+    }
+
+    // The live anyone-can-pay cell (without type script) of the owner to top up
+    fn find_acp_cell(
+        &mut self,
+        m: &ArgMatches,
+        owner: &Address,
+        capacity: u64,
+    ) -> Result<(CellDep, CellInput, CellOutput, Bytes), String> {
+        let acp_config = self.acp_config(m)?.ok_or_else(|| {
+            "No known anyone-can-pay deployment of this chain, use --acp-code-hash and --acp-cell-dep".to_owned()
+        })?;
+        let acp_lock = acp_config.lock_script(owner);
+        let lock_hash: H256 = acp_lock.calc_script_hash().unpack();
+        let is_spent = self.spent_filter()?;
+        // Stop at the first one
+        let infos = self.live_cells(CellQuery::Lock(acp_lock), None, |_, info| {
+            let found = info.type_hashes.is_none() && !is_spent(info);
+            (found, found)
+        })?;
+        let info = infos.into_iter().next().ok_or_else(|| {
+            format!(
+                "No anyone-can-pay cell found (lock hash: {:#x}), the receiver must create one first",
+                lock_hash
+            )
+        })?;
+        let cell = self
+            .rpc_client
+            .get_live_cell(info.out_point().into(), true)
+            .call()
+            .map_err(|err| format!("get_live_cell by RPC call failed: {}", err))?
+            .cell
+            .ok_or_else(|| format!("Anyone-can-pay cell is not live: {:#x}", lock_hash))?;
+        let output: CellOutput = cell.output.into();
+        let min_capacity = acp_min_capacity(&output.lock());
+        if capacity < min_capacity {
+            return Err(format!(
+                "The anyone-can-pay cell only accepts at least {} shannons",
+                min_capacity
+            ));
+        }
+        let data = cell
+            .data
+            .map(|data| data.content.into_bytes())
+            .unwrap_or_default();
+        Ok((acp_config.cell_dep(), info.input(), output, data))
+    }
+
+    // The epoch number of a block
+    fn block_epoch(&mut self, number: u64) -> Result<EpochNumberWithFraction, String> {
+        self.rpc_client
+            .get_header_by_number(BlockNumber::from(number))
+            .call()
+            .map_err(|err| format!("Send get_header_by_number error: {}", err))?
+            .0
+            .map(|header| CoreHeaderView::from(header).epoch())
+            .ok_or_else(|| format!("Header #{} not found", number))
+    }
+
+    fn tip_epoch(&mut self) -> Result<EpochNumberWithFraction, String> {
+        self.rpc_client
+            .get_tip_header()
+            .call()
+            .map(|header| CoreHeaderView::from(header).epoch())
+            .map_err(|err| format!("Send get_tip_header error: {}", err))
+    }
+
+    /// Group the live cells of a lock by `spendable` predicates, the data of NervosDAO cells are
+    /// fetched by RPC to tell deposited from prepared cells
+    fn get_balance(&mut self, query: CellQuery) -> Result<Balance, String> {
+        let dao_type_hash: H256 = self.genesis_info()?.dao_type_hash().unpack();
+        let infos = self.live_cells(query, None, |_, _| (false, true))?;
+        let tip_epoch = self.tip_epoch()?;
+        let mut balance = Balance::default();
+        for info in infos {
+            balance.total.add(&info);
+            if spendable::is_plain_cell(&info) {
+                if spendable::is_cellbase(&info)
+                    && !spendable::is_mature(self.block_epoch(info.number)?, tip_epoch)
+                {
+                    balance.immature.add(&info);
+                } else {
+                    balance.spendable.add(&info);
+                }
+            } else if spendable::is_dao_cell(&info, &dao_type_hash) {
+                let cell: CellWithStatus = self
+                    .rpc_client
+                    .get_live_cell(info.out_point().into(), true)
+                    .call()
+                    .map_err(|err| format!("get_live_cell by RPC call failed: {}", err))?;
+                let data = cell
+                    .cell
+                    .and_then(|cell| cell.data)
+                    .map(|data| data.content.into_bytes())
+                    .unwrap_or_default();
+                if spendable::is_dao_deposit(&data) {
+                    balance.dao_deposited.add(&info);
+                } else {
+                    balance.dao_prepared.add(&info);
+                }
+            } else {
+                balance.unknown.add(&info);
+            }
+        }
+        Ok(balance)
+    }
+
+    // The output of a cell which may be already spent
+    fn get_cell_output(&mut self, out_point: &OutPoint) -> Result<CellOutput, String> {
+        let tx_hash: H256 = out_point.tx_hash().unpack();
+        let index: u32 = out_point.index().unpack();
+        let tx_with_status = self
+            .rpc_client
+            .get_transaction(tx_hash.clone())
+            .call()
+            .map_err(|err| format!("Send get_transaction error: {}", err))?
+            .0
+            .ok_or_else(|| format!("Transaction not found: {:#x}", tx_hash))?;
+        tx_with_status
+            .transaction
+            .inner
+            .outputs
+            .get(index as usize)
+            .cloned()
+            .map(Into::into)
+            .ok_or_else(|| format!("Output not found: {:#x}-{}", tx_hash, index))
+    }
+
+    fn build_witness_with_keystore(
+        &mut self,
+        lock_arg: &H160,
+        args: &[Vec<u8>],
+        password: &Option<String>,
+    ) -> Result<Bytes, String> {
+        // Placeholder signature, the transaction will be signed elsewhere
+        if self.is_watch_only(lock_arg)? {
+            return Ok(Bytes::from(vec![0u8; 65]));
+        }
+        let interactive = self.interactive;
+        let key_store = &mut self.key_store;
+        sign_witness(lock_arg, args, |sign_hash| {
+            // Unattended signing outside interactive mode
+            let password = match password {
+                None if !interactive && has_password_file() => Some(read_password(false, None)?),
+                password => password.clone(),
+            };
+            if interactive && password.is_none() {
+                key_store
+                    .sign_recoverable(lock_arg, sign_hash)
+                    .map_err(|err| match err {
+                        KeyStoreError::AccountLocked(lock_arg) => {
+                            tf("account-locked", &[&format!("{:x}", lock_arg)])
+                        }
+                        err => err.to_string(),
+                    })
+            } else if let Some(password) = password {
+                key_store
+                    .sign_recoverable_with_password(lock_arg, sign_hash, password.as_bytes())
+                    .map_err(|err| err.to_string())
+            } else {
+                Err(t("password-required").to_owned())
+            }
+        })
+    }
+
+    fn is_watch_only(&mut self, lock_arg: &H160) -> Result<bool, String> {
+        if self.key_store.has_account(lock_arg) {
+            return Ok(false);
+        }
+        WatchOnlyAccounts::load_default().map(|accounts| accounts.contains(lock_arg))
+    }
+
+    fn unlock_cells(
+        &mut self,
+        m: &ArgMatches,
+        format: OutputFormat,
+        color: bool,
+    ) -> Result<String, String> {
+        let out_points: Vec<OutPoint> = OutPointParser.from_matches_vec(m, "out-point")?;
+        let mut journal = SpentCells::load_default()?;
+        let removed = if m.is_present("all") {
+            journal.unlock(|_, _| false)?
+        } else if !out_points.is_empty() {
+            let targets = out_points
+                .iter()
+                .map(out_point_to_string)
+                .collect::<HashSet<_>>();
+            journal.unlock(|out_point, _| !targets.contains(out_point))?
+        } else {
+            let tx_hashes = journal
+                .list()
+                .values()
+                .map(|cell| cell.tx_hash.clone())
+                .collect::<HashSet<_>>();
+            let indexed_number = self.indexed_number().unwrap_or(None);
+            let mut stale_txs = HashSet::new();
+            for tx_hash in tx_hashes {
+                let tx_opt = self
+                    .rpc_client
+                    .get_transaction(tx_hash.clone())
+                    .call()
+                    .map_err(|err| format!("Send get_transaction error: {}", err))?
+                    .0;
+                let block_hash = match tx_opt {
+                    Some(tx) => tx.tx_status.block_hash,
+                    // Dropped or rejected by tx-pool
+                    None => {
+                        stale_txs.insert(tx_hash);
+                        continue;
+                    }
+                };
+                // Still pending, or committed but the index has not synced the block
+                if let (Some(block_hash), Some(indexed_number)) = (block_hash, indexed_number) {
+                    let number = self
+                        .rpc_client
+                        .get_header(block_hash)
+                        .call()
+                        .map_err(|err| format!("Send get_header error: {}", err))?
+                        .0
+                        .map(|header| CoreHeaderView::from(header).number());
+                    if number
+                        .map(|number| number <= indexed_number)
+                        .unwrap_or(false)
+                    {
+                        stale_txs.insert(tx_hash);
+                    }
+                }
+            }
+            journal.unlock(|_, cell| !stale_txs.contains(&cell.tx_hash))?
+        };
+        let resp = serde_json::json!({
+            "unlocked": removed,
+            "locked": journal.list(),
+        });
+        Ok(resp.render(format, color))
+    }
+
+    /// The send path shared with `tx send` and `mock-tx send`
+    fn tx_sender(&mut self) -> TxSender {
+        TxSender::new(self.rpc_client, self.genesis_info.clone())
+            .with_fee_guard(self.fee_guard)
+            .with_confirmation(self.confirm_send)
+    }
+
+    /// Check the outputs and the fee (see `--force-fee`), preview the transaction and send it
+    /// once confirmed (see `--yes`)
+    fn send_transaction(
+        &mut self,
+        m: &ArgMatches,
+        transaction: TransactionView,
+        format: OutputFormat,
+        color: bool,
+        debug: bool,
+    ) -> Result<String, String> {
+        self.tx_sender().send(m, transaction, format, color, debug)
+    }
+
+    /// Sign the transfer by `--privkey-path` or the keystore account of `--from-account`
+    fn sign_transfer(
+        &mut self,
+        m: &ArgMatches,
+        tx_args: &mut TransferTransactionBuilder,
+        genesis_info: &GenesisInfo,
+        from_privkey: Option<&PrivkeyWrapper>,
+        from_account: Option<&H160>,
+    ) -> Result<TransactionView, String> {
+        if let Some(privkey) = from_privkey {
+            tx_args.transfer(genesis_info, |args| witness_with_key(privkey, args))
+        } else {
+            let lock_arg = from_account.unwrap();
+            let password = if m.is_present("with-password") {
+                Some(read_password(false, None)?)
+            } else {
+                None
+            };
+            tx_args.transfer(genesis_info, |args| {
+                self.build_witness_with_keystore(lock_arg, args, &password)
+            })
+        }
+    }
+
+    /// Send the transaction, or print it unsigned when the sender is a watch-only account. The
+    /// `capacity` is recorded to the signing policy once the transaction is sent.
+    #[allow(clippy::too_many_arguments)]
+    fn send_or_unsigned(
+        &mut self,
+        m: &ArgMatches,
+        transaction: TransactionView,
+        from_account: Option<&H160>,
+        policy: Option<SigningPolicy>,
+        capacity: u64,
+        format: OutputFormat,
+        color: bool,
+        debug: bool,
+    ) -> Result<String, String> {
+        if let Some(lock_arg) = from_account {
+            if self.is_watch_only(lock_arg)? {
+                return Ok(unsigned_transaction(transaction, format, color));
+            }
+        }
+        let resp = self.send_transaction(m, transaction, format, color, debug)?;
+        if let Some(policy) = policy {
+            policy.record(capacity)?;
+        }
+        Ok(resp)
+    }
+
+    /// A prepare at any time of a lock period locks the cell until the end of it, so preparing in
+    /// the last epoch of the period loses no compensation (it must be committed before the end,
+    /// otherwise the cell is locked for another period)
+    fn verify_dao_deposit(&mut self, m: &ArgMatches) -> Result<serde_json::Value, String> {
+        let tx_hash: H256 = FixedHashParser::<H256>::default().from_matches(m, "tx-hash")?;
+        let address: Address = AddressParser.from_matches(m, "address")?;
+        let genesis_info = self.genesis_info()?;
+        let lock = genesis_info.lock_script(&address)?;
+
+        let tx_with_status = self
+            .rpc_client
+            .get_transaction(tx_hash.clone())
+            .call()
+            .map_err(|err| format!("Send get_transaction error: {}", err))?
+            .0
+            .ok_or_else(|| format!("Transaction not found: {:#x}", tx_hash))?;
+        let block_hash = tx_with_status.tx_status.block_hash.ok_or_else(|| {
+            format!(
+                "Transaction {:#x} is not committed (status: {:?})",
+                tx_hash, tx_with_status.tx_status.status
+            )
+        })?;
+        let transaction = packed::Transaction::from(tx_with_status.transaction.inner).into_view();
+        let deposit_header: CoreHeaderView = self
+            .rpc_client
+            .get_header(block_hash.clone())
+            .call()
+            .map_err(|err| format!("Send get_header error: {}", err))?
+            .0
+            .ok_or_else(|| format!("Header not found: {:#x}", block_hash))?
+            .into();
+        let tip_header: CoreHeaderView = self
+            .rpc_client
+            .get_tip_header()
+            .call()
+            .map_err(|err| format!("Send get_tip_header error: {}", err))?
+            .into();
+
+        let mut deposits = Vec::new();
+        for (index, (output, data)) in transaction.outputs_with_data_iter().enumerate() {
+            let is_dao = output
+                .type_()
+                .to_opt()
+                .map(|script| {
+                    &script.code_hash() == genesis_info.dao_type_hash()
+                        && script.hash_type() == ScriptHashType::Type.into()
+                })
+                .unwrap_or(false);
+            if !is_dao || !is_dao_deposit(&data) || output.lock().as_slice() != lock.as_slice() {
+                continue;
+            }
+            let out_point = OutPoint::new(transaction.hash(), index as u32);
+            let cell_with_status: CellWithStatus = self
+                .rpc_client
+                .get_live_cell(out_point.into(), false)
+                .call()
+                .map_err(|err| format!("get_live_cell by RPC call failed: {}", err))?;
+            let capacity: u64 = output.capacity().unpack();
+            deposits.push(serde_json::json!({
+                "index": index,
+                "capacity": capacity,
+                // dead: prepared or withdrawn already
+                "status": cell_with_status.status,
+            }));
+        }
+
+        let format_epoch = |epoch: EpochNumberWithFraction| {
+            format!("{} ({}/{})", epoch.number(), epoch.index(), epoch.length())
+        };
+        let deposit_epoch = deposit_header.epoch();
+        let tip_epoch = tip_header.epoch();
+        let period_end = dao_unlock_epoch(deposit_epoch, tip_epoch);
+        let prepare_from = EpochNumberWithFraction::new(
+            period_end.number() - 1,
+            period_end.index(),
+            period_end.length(),
+        );
+        // The deposit and the period end share the epoch fraction, whole periods between them
+        let lock_period = (period_end.number() - deposit_epoch.number()) / DAO_LOCK_PERIOD_EPOCHS;
+        Ok(serde_json::json!({
+            "valid": !deposits.is_empty(),
+            "deposits": deposits,
+            "deposit_block": {
+                "number": deposit_header.number(),
+                "hash": block_hash,
+                "epoch": format_epoch(deposit_epoch),
+            },
+            "tip_epoch": format_epoch(tip_epoch),
+            "lock_period": lock_period,
+            "lock_period_end_epoch": format_epoch(period_end),
+            "prepare_advantageous_from_epoch": format_epoch(prepare_from),
+            "epochs_to_period_end": epoch_value(period_end) - epoch_value(tip_epoch),
+        }))
+    }
+
+    fn tx_status(
+        &mut self,
+        m: &ArgMatches,
+        format: OutputFormat,
+        color: bool,
+    ) -> Result<String, String> {
+        let tx_hash: H256 = FixedHashParser::<H256>::default().from_matches(m, "tx-hash")?;
+        if let Some(timeout) = wait_timeout(m)? {
+            wait_for_commit(self.rpc_client, tx_hash.clone(), timeout)?;
+        }
+        let tx_opt = self
+            .rpc_client
+            .get_transaction(tx_hash.clone())
+            .call()
+            .map_err(|err| format!("Send get_transaction error: {}", err))?
+            .0;
+        let status = tx_status_name(tx_opt.as_ref());
+        let block = match tx_opt.and_then(|tx| tx.tx_status.block_hash) {
+            Some(block_hash) => {
+                let header_opt = self
+                    .rpc_client
+                    .get_header(block_hash.clone())
+                    .call()
+                    .map_err(|err| format!("Send get_header error: {}", err))?
+                    .0;
+                serde_json::json!({
+                    "hash": block_hash,
+                    "number": header_opt.map(|header| header.inner.number),
+                })
+            }
+            None => serde_json::Value::Null,
+        };
+        let pending_txs = PendingTxs::load_default()?;
+        let local = match pending_txs.get(&tx_hash) {
+            Ok(pending_tx) => {
+                let transaction = pending_tx.transaction();
+                serde_json::json!({
+                    "sent_at": pending_tx.timestamp,
+                    "inputs": transaction
+                        .inputs()
+                        .into_iter()
+                        .map(|input| out_point_to_string(&input.previous_output()))
+                        .collect::<Vec<_>>(),
+                    "outputs": transaction
+                        .outputs()
+                        .into_iter()
+                        .map(|output| {
+                            let capacity: u64 = output.capacity().unpack();
+                            let lock_hash: H256 = output.lock().calc_script_hash().unpack();
+                            serde_json::json!({
+                                "capacity": capacity,
+                                "lock_hash": lock_hash,
+                            })
+                        })
+                        .collect::<Vec<_>>(),
+                })
+            }
+            Err(_) => serde_json::Value::Null,
+        };
+        let resp = serde_json::json!({
+            "tx_hash": tx_hash,
+            "status": status,
+            "block": block,
+            "local": local,
+        });
         Ok(resp.render(format, color))
     }
 }
@@ -567,110 +2871,223 @@ impl<'a> CliSubCommand for WalletSubCommand<'a> {
             ("transfer", Some(m)) => self.transfer(m, format, color, debug),
             ("deposit-dao", Some(m)) => self.deposit_dao(m, format, color, debug),
             ("withdraw-dao", Some(m)) => self.withdraw_dao(m, format, color, debug),
-            ("get-capacity", Some(m)) => {
-                let lock_hash_opt: Option<H256> =
-                    FixedHashParser::<H256>::default().from_matches_opt(m, "lock-hash", false)?;
-                let lock_hash = if let Some(lock_hash) = lock_hash_opt {
-                    lock_hash.pack()
+            ("payout", Some(m)) => self.payout(m, format, color, debug),
+            ("transfer-batch", Some(m)) => self.transfer_batch(m, format, color, debug),
+            ("sweep", Some(m)) => self.sweep(m, format, color, debug),
+            ("top-up", Some(m)) => self.top_up(m, format, color, debug),
+            ("bump-fee", Some(m)) => self.bump_fee(m, format, color, debug),
+            ("tx-status", Some(m)) => self.tx_status(m, format, color),
+            ("verify-dao-deposit", Some(m)) => {
+                Ok(self.verify_dao_deposit(m)?.render(format, color))
+            }
+            ("rebroadcast", Some(m)) => {
+                let transaction = if let Some(path) = m.value_of("tx-file") {
+                    read_tx_file(path)?.0
                 } else {
-                    let secp_type_hash = self.genesis_info()?.secp_type_hash().clone();
-                    let address = get_address(m)?;
-                    address.lock_script(secp_type_hash).calc_script_hash()
+                    let tx_hash: H256 =
+                        FixedHashParser::<H256>::default().from_matches(m, "tx-hash")?;
+                    PendingTxs::load_default()?.get(&tx_hash)?.transaction()
                 };
-                let capacity = self.with_db(|db| db.get_capacity(lock_hash))?;
+                let tx_hash: H256 = transaction.hash().unpack();
+                let committed_block = self
+                    .rpc_client
+                    .get_transaction(tx_hash.clone())
+                    .call()
+                    .map_err(|err| format!("Send get_transaction error: {}", err))?
+                    .0
+                    .and_then(|tx| tx.tx_status.block_hash);
+                if let Some(block_hash) = committed_block {
+                    return Err(format!(
+                        "Transaction {:#x} already committed in block {:#x}",
+                        tx_hash, block_hash
+                    ));
+                }
+                self.tx_sender()
+                    .broadcast(m, transaction, format, color, debug)
+            }
+            ("multisig", Some(m)) => self.multisig(m, format, color, debug),
+            ("sudt", Some(m)) => self.sudt(m, format, color, debug),
+            ("deploy", Some(m)) => self.deploy(m, format, color, debug),
+            ("upgrade-contract", Some(m)) => self.upgrade_contract(m, format, color, debug),
+            ("list-deployments", _) => {
+                let deployments = Deployments::load_default()?;
+                Ok(serde_json::json!(deployments.list()).render(format, color))
+            }
+            ("freeze", Some(m)) => {
+                let out_points: Vec<OutPoint> = OutPointParser.from_matches_vec(m, "out-point")?;
+                let mut frozen = FrozenCells::load(frozen_cells_path())?;
+                frozen.freeze(&out_points)?;
+                Ok(frozen.list().render(format, color))
+            }
+            ("unfreeze", Some(m)) => {
+                let out_points: Vec<OutPoint> = OutPointParser.from_matches_vec(m, "out-point")?;
+                let mut frozen = FrozenCells::load(frozen_cells_path())?;
+                frozen.unfreeze(&out_points)?;
+                Ok(frozen.list().render(format, color))
+            }
+            ("list-frozen", _) => {
+                let frozen = FrozenCells::load(frozen_cells_path())?;
+                Ok(frozen.list().render(format, color))
+            }
+            ("unlock-cells", Some(m)) => self.unlock_cells(m, format, color),
+            ("clear-frozen", _) => {
+                let mut frozen = FrozenCells::load(frozen_cells_path())?;
+                frozen.clear()?;
+                Ok("success".to_owned())
+            }
+            ("get-capacity", Some(m)) => {
+                let (query, _) = self.lock_query(m)?;
+                let capacity = self.live_capacity(query)?;
                 let resp = serde_json::json!({
                     "capacity": capacity,
                 });
                 Ok(resp.render(format, color))
             }
-            ("get-dao-capacity", Some(m)) => {
-                let secp_type_hash = self.genesis_info()?.secp_type_hash().clone();
-                let dao_type_hash = self.genesis_info()?.dao_type_hash().clone();
-                let lock_hash_opt: Option<H256> =
-                    FixedHashParser::<H256>::default().from_matches_opt(m, "lock-hash", false)?;
-                let lock_hash = if let Some(lock_hash) = lock_hash_opt {
-                    lock_hash.pack()
+            ("get-balance", Some(m)) => {
+                let (query, address_opt) = self.lock_query(m)?;
+                let mut balance = self.get_balance(query)?;
+                // The anyone-can-pay cells of the same owner
+                if let (Some(address), Some(acp_config)) = (address_opt, self.acp_config(m)?) {
+                    let acp_lock = acp_config.lock_script(&address);
+                    let infos =
+                        self.live_cells(CellQuery::Lock(acp_lock), None, |_, _| (false, true))?;
+                    for info in infos {
+                        balance.total.add(&info);
+                        balance.acp.add(&info);
+                    }
+                }
+                Ok(serde_json::to_value(&balance)
+                    .map_err(|err| err.to_string())?
+                    .render(format, color))
+            }
+            ("analyze-cells", Some(m)) => {
+                let (query, _) = self.lock_query(m)?;
+                let lock_hash: H256 = query.script_hash().expect("lock query").unpack();
+                let older_than_epochs: u64 =
+                    FromStrParser::<u64>::default().from_matches(m, "older-than-epochs")?;
+                let current_epoch = self
+                    .rpc_client
+                    .get_current_epoch()
+                    .call()
+                    .map_err(|err| err.to_string())?;
+                // Cells before the start block of that epoch are considered old
+                let old_before_number = if current_epoch.number.value() >= older_than_epochs {
+                    let epoch_number = current_epoch.number.value() - older_than_epochs;
+                    self.rpc_client
+                        .get_epoch_by_number(EpochNumber::from(epoch_number))
+                        .call()
+                        .map_err(|err| err.to_string())?
+                        .0
+                        .map(|epoch| epoch.start_number.value())
+                        .ok_or_else(|| format!("Epoch {} not found", epoch_number))?
                 } else {
-                    let address = get_address(m)?;
-                    address.lock_script(secp_type_hash).calc_script_hash()
+                    0
                 };
-                let capacity = self.with_db(|db| {
-                    let infos_by_lock = db
-                        .get_live_cells_by_lock(lock_hash, Some(0), |_, _| (false, true))
-                        .into_iter()
-                        .collect::<HashSet<_>>();
-                    let infos_by_code = db
-                        .get_live_cells_by_code(dao_type_hash, Some(0), |_, _| (false, true))
-                        .into_iter()
-                        .collect::<HashSet<_>>();
-                    infos_by_lock
-                        .intersection(&infos_by_code)
-                        .map(|info| info.capacity)
-                        .sum::<u64>()
+                let infos = self.live_cells(query, None, |_, _| (false, true))?;
+                let total_capacity: u64 = infos.iter().map(|info| info.capacity).sum();
+                let old_infos = infos
+                    .iter()
+                    .filter(|info| info.number < old_before_number)
+                    .collect::<Vec<_>>();
+                let resp = serde_json::json!({
+                    "lock_hash": lock_hash,
+                    "cells": infos.len(),
+                    "capacity": total_capacity,
+                    "oldest_block": infos.iter().map(|info| info.number).min(),
+                    "newest_block": infos.iter().map(|info| info.number).max(),
+                    "with_data": infos.iter().filter(|info| info.data_bytes > 0).count(),
+                    "with_type": infos.iter().filter(|info| info.type_hashes.is_some()).count(),
+                    "older_than": {
+                        "epochs": older_than_epochs,
+                        "before_block": old_before_number,
+                        "cells": old_infos.len(),
+                        "capacity": old_infos.iter().map(|info| info.capacity).sum::<u64>(),
+                    },
+                });
+                Ok(resp.render(format, color))
+            }
+            ("get-dao-capacity", Some(m)) => {
+                let dao_type_hash: H256 = self.genesis_info()?.dao_type_hash().unpack();
+                let (query, _) = self.lock_query(m)?;
+                let filter = CellFilter::from_matches(m)?;
+                let (infos, next_from_block) = self.live_cells_page(query, &filter, |info| {
+                    spendable::is_dao_cell(info, &dao_type_hash)
                 })?;
+                let capacity = infos.iter().map(|info| info.capacity).sum::<u64>();
                 let resp = serde_json::json!({
                     "capacity": capacity,
+                    "cells": infos.len(),
+                    "next_from_block": next_from_block,
                 });
                 Ok(resp.render(format, color))
             }
             ("get-live-cells", Some(m)) => {
+                let address_opt: Option<Address> =
+                    AddressParser.from_matches_opt(m, "address", false)?;
                 let lock_hash_opt: Option<H256> =
                     FixedHashParser::<H256>::default().from_matches_opt(m, "lock-hash", false)?;
                 let type_hash_opt: Option<H256> =
                     FixedHashParser::<H256>::default().from_matches_opt(m, "type-hash", false)?;
                 let code_hash_opt: Option<H256> =
                     FixedHashParser::<H256>::default().from_matches_opt(m, "code-hash", false)?;
-                let limit: usize = FromStrParser::<usize>::default().from_matches(m, "limit")?;
-                let from_number_opt: Option<u64> =
-                    FromStrParser::<u64>::default().from_matches_opt(m, "from", false)?;
-                let to_number_opt: Option<u64> =
-                    FromStrParser::<u64>::default().from_matches_opt(m, "to", false)?;
+                let type_script_opt: Option<Script> =
+                    ScriptParser.from_matches_opt(m, "type-script", false)?;
+                let filter = CellFilter::from_matches(m)?;
 
-                if lock_hash_opt.is_none() && type_hash_opt.is_none() && code_hash_opt.is_none() {
-                    return Err("lock-hash or type-hash or code-hash is required".to_owned());
-                }
+                let query = if let Some(address) = address_opt {
+                    CellQuery::Lock(self.genesis_info()?.lock_script(&address)?)
+                } else if let Some(lock_hash) = lock_hash_opt {
+                    CellQuery::LockHash(lock_hash.pack())
+                } else if let Some(type_hash) = type_hash_opt {
+                    CellQuery::TypeHash(type_hash.pack())
+                } else if let Some(code_hash) = code_hash_opt {
+                    CellQuery::CodeHash(code_hash.pack())
+                } else if let Some(type_script) = type_script_opt {
+                    CellQuery::TypeArgs(type_script)
+                } else {
+                    return Err(
+                        "address or lock-hash or type-hash or code-hash or type-script is required"
+                            .to_owned(),
+                    );
+                };
 
-                let to_number = to_number_opt.unwrap_or(std::u64::MAX);
-                let (infos, total_capacity) = self.with_db(|db| {
-                    let mut total_capacity = 0;
-                    let terminator = |idx, info: &LiveCellInfo| {
-                        let stop = idx >= limit || info.number > to_number;
-                        let push_info = !stop;
-                        if push_info {
-                            total_capacity += info.capacity;
-                        }
-                        (stop, push_info)
-                    };
-                    let infos = if let Some(lock_hash) = lock_hash_opt {
-                        db.get_live_cells_by_lock(
-                            lock_hash.clone().pack(),
-                            from_number_opt,
-                            terminator,
-                        )
-                    } else if let Some(type_hash) = type_hash_opt {
-                        db.get_live_cells_by_type(
-                            type_hash.clone().pack(),
-                            from_number_opt,
-                            terminator,
-                        )
-                    } else {
-                        db.get_live_cells_by_code(
-                            code_hash_opt.clone().unwrap().pack(),
-                            from_number_opt,
-                            terminator,
-                        )
-                    };
-                    (infos, total_capacity)
-                })?;
+                let (infos, next_from_block) = self.live_cells_page(query, &filter, |_| true)?;
+                let total_capacity = infos.iter().map(|info| info.capacity).sum::<u64>();
+                // Decode data of the cells typed by a script described in `cell-schemas`
+                let schemas = CellSchemas::load_default()?;
+                let mut live_cells = Vec::with_capacity(infos.len());
+                for info in infos {
+                    let mut value = serde_json::to_value(&info).unwrap();
+                    let schema = info
+                        .type_hashes
+                        .as_ref()
+                        .and_then(|(code_hash, _)| schemas.find(code_hash));
+                    if let Some(schema) = schema {
+                        let data = self
+                            .rpc_client
+                            .get_live_cell(info.out_point().into(), true)
+                            .call()
+                            .map_err(|err| format!("get_live_cell by RPC call failed: {}", err))?
+                            .cell
+                            .and_then(|cell| cell.data)
+                            .map(|data| data.content.into_bytes())
+                            .unwrap_or_default();
+                        let decoded = schema
+                            .decode(&data)
+                            .unwrap_or_else(|err| serde_json::json!({ "error": err }));
+                        value["decoded"] = decoded;
+                    }
+                    live_cells.push(value);
+                }
                 let resp = serde_json::json!({
-                    "live_cells": infos.into_iter().map(|info| {
-                        serde_json::to_value(&info).unwrap()
-                    }).collect::<Vec<_>>(),
+                    "live_cells": live_cells,
                     "total_capacity": total_capacity,
+                    "next_from_block": next_from_block,
                 });
                 Ok(resp.render(format, color))
             }
             ("get-lock-by-address", Some(m)) => {
+                self.check_local_index("get-lock-by-address")?;
                 let address: Address = AddressParser.from_matches(m, "address")?;
                 let lock_script = self.with_db(|db| {
                     db.get_lock_hash_by_address(address)
@@ -691,6 +3108,7 @@ impl<'a> CliSubCommand for WalletSubCommand<'a> {
                 Ok(lock_script.render(format, color))
             }
             ("top-capacity", Some(m)) => {
+                self.check_local_index("top-capacity")?;
                 let n: usize = m
                     .value_of("number")
                     .map(|n_str| n_str.parse().unwrap())
@@ -708,9 +3126,40 @@ impl<'a> CliSubCommand for WalletSubCommand<'a> {
                         })
                         .collect::<Vec<_>>()
                 })?;
+                let mut resp = serde_json::json!(resp);
+                annotate_labels(&mut resp, &AddressBook::load_default()?);
                 Ok(resp.render(format, color))
             }
+            ("tx-history", Some(m)) => {
+                let (query, _) = self.lock_query(m)?;
+                let limit: usize = FromStrParser::<usize>::default().from_matches(m, "limit")?;
+                let deltas = self.lock_txs(query, limit)?;
+                let resp = deltas
+                    .into_iter()
+                    .map(|delta| {
+                        let balance_change = delta.balance_change();
+                        let direction = if balance_change > 0 {
+                            "in"
+                        } else if balance_change < 0 {
+                            "out"
+                        } else {
+                            "self"
+                        };
+                        serde_json::json!({
+                            "tx_hash": delta.tx_hash,
+                            "block_number": delta.block_number,
+                            "timestamp": delta.block_timestamp,
+                            "direction": direction,
+                            "input_capacity": delta.input_capacity,
+                            "output_capacity": delta.output_capacity,
+                            "balance_change": balance_change,
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                Ok(serde_json::json!(resp).render(format, color))
+            }
             ("db-metrics", _) => {
+                self.check_local_index("db-metrics")?;
                 let metrcis = self.with_db(|db| db.get_metrics(None))?;
                 let resp = serde_json::to_value(metrcis).map_err(|err| err.to_string())?;
                 Ok(resp.render(format, color))
@@ -846,6 +3295,95 @@ fn build_dao_withdraw_hash(rpc_client: &mut HttpRpcClient) -> Result<H256, Strin
     Ok(dao_withdraw_hash)
 }
 
+// Transaction of watch-only account is returned instead of sent
+fn unsigned_transaction(transaction: TransactionView, format: OutputFormat, color: bool) -> String {
+    let transaction_view: ckb_jsonrpc_types::TransactionView = transaction.into();
+    let resp = serde_json::json!({
+        "signed": false,
+        "transaction": transaction_view,
+    });
+    resp.render(format, color)
+}
+
+// Show the lock hash and the minimal capacity of the output locked by a custom lock script, the
+// funds can only be unlocked by whoever controls that script.
+fn confirm_to_lock(lock: &Script, capacity: u64, to_data_len: usize) -> Result<(), String> {
+    let occupied = CellOutput::new_builder()
+        .lock(lock.clone())
+        .build()
+        .occupied_capacity(Capacity::bytes(to_data_len).map_err(|err| err.to_string())?)
+        .map_err(|err| err.to_string())?
+        .as_u64();
+    let lock_hash: H256 = lock.calc_script_hash().unpack();
+    if capacity < occupied {
+        return Err(format!(
+            "Capacity can not less than {} shannons (lock script and {} bytes of data)",
+            occupied, to_data_len
+        ));
+    }
+    eprintln!(
+        "{}",
+        tf("to-lock-script", &[&format!("{:#x}", lock_hash), &occupied])
+    );
+    if read_confirmation(t("confirm-to-lock-script"))? {
+        Ok(())
+    } else {
+        Err("Transfer canceled".to_owned())
+    }
+}
+
+// Labels (see: `account label`) are resolved to address without network prefix
+fn check_to_address_prefix(m: &ArgMatches, network_type: NetworkType) -> Result<(), String> {
+    match m.value_of("to-address") {
+        Some(input) if AddressParser.parse(input).is_ok() => {
+            check_address_prefix(input, network_type)
+        }
+        _ => Ok(()),
+    }
+}
+
+fn wait_timeout(m: &ArgMatches) -> Result<Option<u64>, String> {
+    if !m.is_present("wait-for-commit") {
+        return Ok(None);
+    }
+    let timeout: Option<u64> =
+        FromStrParser::<u64>::default().from_matches_opt(m, "wait-for-commit", false)?;
+    Ok(Some(timeout.unwrap_or(DEFAULT_WAIT_COMMIT_TIMEOUT)))
+}
+
+// pending / proposed / committed, or unknown when the node does not know the transaction
+fn tx_status_name(tx_opt: Option<&TransactionWithStatus>) -> String {
+    match tx_opt {
+        Some(tx) => serde_json::to_value(&tx.tx_status.status)
+            .ok()
+            .and_then(|value| value.as_str().map(ToOwned::to_owned))
+            .unwrap_or_else(|| "unknown".to_owned()),
+        None => "unknown".to_owned(),
+    }
+}
+
+fn out_point_key(info: &LiveCellInfo) -> String {
+    format!("{:#x}-{}", info.tx_hash, info.tx_index)
+}
+
+fn frozen_cells_path() -> PathBuf {
+    get_ckb_cli_dir().join("frozen-cells")
+}
+
+fn load_frozen(m: &ArgMatches) -> Result<Option<FrozenCells>, String> {
+    if m.is_present("include-frozen") {
+        Ok(None)
+    } else {
+        FrozenCells::load(frozen_cells_path()).map(Some)
+    }
+}
+
+fn from_ledger(m: &ArgMatches) -> Result<Option<LedgerSigner>, String> {
+    let path_opt: Option<DerivationPath> =
+        FromStrParser::<DerivationPath>::new().from_matches_opt(m, "from-ledger-account", false)?;
+    path_opt.map(LedgerSigner::open).transpose()
+}
+
 fn to_data(m: &ArgMatches) -> Result<Bytes, String> {
     let to_data_opt: Option<Bytes> = HexParser.from_matches_opt(m, "to-data", false)?;
     match to_data_opt {
@@ -863,3 +3401,20 @@ fn to_data(m: &ArgMatches) -> Result<Bytes, String> {
         }
     }
 }
+
+/// The sender of `--privkey-path` or `--from-account`
+fn privkey_or_account(
+    m: &ArgMatches,
+) -> Result<(Address, Option<PrivkeyWrapper>, Option<H160>), String> {
+    let from_privkey: Option<PrivkeyWrapper> =
+        PrivkeyPathParser.from_matches_opt(m, "privkey-path", false)?;
+    let from_account: Option<H160> =
+        LabeledLockArgParser.from_matches_opt(m, "from-account", false)?;
+    let from_address = if let Some(privkey) = from_privkey.as_ref() {
+        let pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, privkey);
+        Address::from_pubkey(&pubkey)?
+    } else {
+        Address::from_lock_arg(from_account.as_ref().unwrap().as_bytes())?
+    };
+    Ok((from_address, from_privkey, from_account))
+}
@@ -0,0 +1,594 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+use ckb_jsonrpc_types::{CellWithStatus, JsonBytes};
+use ckb_sdk::{
+    blake2b_args, multisig_sign_args, recover_signer, set_multisig_witness, Address,
+    MultisigConfig, NetworkType, TransferTransactionBuilder, SECP256K1,
+};
+use ckb_types::{
+    core::{Capacity, TransactionView},
+    packed::{self, CellOutput, OutPoint, Script},
+    prelude::*,
+    H160, H256,
+};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use serde_derive::{Deserialize, Serialize};
+
+use super::{
+    check_capacity, check_to_address_prefix, is_live_cell, is_secp_cell, to_data, WalletSubCommand,
+};
+use crate::utils::{
+    arg,
+    arg_parser::{
+        ArgParser, CapacityParser, FilePathParser, FixedHashParser, FromStrParser,
+        LabeledAddressParser, LabeledLockArgParser, OutPointParser, PrivkeyPathParser,
+        PrivkeyWrapper, PubkeyHexParser,
+    },
+    i18n::tf,
+    multisig_configs::MultisigConfigs,
+    other::{check_lock_activity, get_ckb_cli_dir, get_network_type, read_password},
+    policy::{check_policy, transaction_outflow, SignKind, SignRequest, SigningPolicy},
+    printer::{OutputFormat, Printable},
+    sign_history::witness_with_key,
+};
+use ckb_index::LiveCellInfo;
+
+/// The partially signed transaction file passed between signers
+#[derive(Serialize, Deserialize)]
+struct MultisigTxFile {
+    multisig_config: MultisigConfig,
+    transaction: ckb_jsonrpc_types::Transaction,
+    // signer lock arg => signature
+    signatures: BTreeMap<H160, JsonBytes>,
+}
+
+impl MultisigTxFile {
+    fn load(path: &str) -> Result<MultisigTxFile, String> {
+        let content = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        serde_json::from_str(&content)
+            .map_err(|err| format!("Parse multisig transaction file {} failed: {}", path, err))
+    }
+
+    fn save(&self, path: &str) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(self).map_err(|err| err.to_string())?;
+        fs::write(path, content).map_err(|err| err.to_string())
+    }
+
+    fn transaction(&self) -> TransactionView {
+        packed::Transaction::from(self.transaction.clone()).into_view()
+    }
+
+    /// Replace the transaction, the collected signatures are invalidated
+    fn update_transaction(&mut self, transaction: TransactionView) {
+        if !self.signatures.is_empty() {
+            eprintln!(
+                "[WARNING]: The transaction is changed, {} collected signature(s) are removed, please sign it again",
+                self.signatures.len()
+            );
+            self.signatures.clear();
+        }
+        self.transaction = transaction.data().into();
+    }
+
+    fn summary(&self) -> serde_json::Value {
+        let tx_hash: H256 = self.transaction().hash().unpack();
+        serde_json::json!({
+            "tx_hash": tx_hash,
+            "signatures": self.signatures.len(),
+            "threshold": self.multisig_config.threshold(),
+        })
+    }
+
+    fn sign_message(&self) -> Result<(Vec<Vec<u8>>, H256), String> {
+        let sign_args = multisig_sign_args(&self.transaction(), &self.multisig_config)?;
+        let message = H256::from_slice(&blake2b_args(&sign_args))
+            .expect("converting digest of [u8; 32] to H256 should be ok");
+        Ok((sign_args, message))
+    }
+}
+
+/// Add `delta` capacity to the change output (the last output locked by the multisig lock, but
+/// not the `excluded` one)
+fn rebalance_change(
+    mut outputs: Vec<CellOutput>,
+    multisig_lock: &Script,
+    excluded: Option<usize>,
+    delta: i128,
+) -> Result<Vec<CellOutput>, String> {
+    if delta == 0 {
+        return Ok(outputs);
+    }
+    let change_index = outputs
+        .iter()
+        .enumerate()
+        .rev()
+        .find(|(index, output)| Some(*index) != excluded && &output.lock() == multisig_lock)
+        .map(|(index, _)| index)
+        .ok_or_else(|| "No change output (locked by the multisig address) found".to_owned())?;
+    let change = outputs[change_index].clone();
+    let old_capacity: u64 = change.capacity().unpack();
+    let new_capacity = i128::from(old_capacity) + delta;
+    let occupied = change
+        .occupied_capacity(Capacity::zero())
+        .map_err(|err| err.to_string())?
+        .as_u64();
+    if new_capacity < i128::from(occupied) {
+        return Err(format!(
+            "Change output can not afford the difference: {} + ({}) < {}",
+            old_capacity, delta, occupied
+        ));
+    }
+    outputs[change_index] = change
+        .as_builder()
+        .capacity(Capacity::shannons(new_capacity as u64).pack())
+        .build();
+    Ok(outputs)
+}
+
+fn multisig_lock_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("multisig-lock-arg")
+        .long("multisig-lock-arg")
+        .takes_value(true)
+        .required(true)
+        .validator(|input| FixedHashParser::<H160>::default().validate(input))
+        .help("Lock argument of the multisig address (printed by `create-config`)")
+}
+
+fn tx_file<'a, 'b>(exists: bool) -> Arg<'a, 'b> {
+    Arg::with_name("tx-file")
+        .long("tx-file")
+        .takes_value(true)
+        .required(true)
+        .validator(move |input| FilePathParser::new(exists).validate(input))
+        .help("Multisig transaction file path")
+}
+
+pub fn subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("multisig")
+        .about("Multisig address and transaction (build, sign separately, combine and send)")
+        .subcommands(vec![
+            SubCommand::with_name("create-config")
+                .about("Create a multisig config (threshold of the pubkeys), saved in ~/.ckb-cli/multisig-configs")
+                .arg(
+                    Arg::with_name("pubkey")
+                        .long("pubkey")
+                        .takes_value(true)
+                        .multiple(true)
+                        .required_unless("sighash-lock-arg")
+                        .validator(|input| PubkeyHexParser.validate(input))
+                        .help("Public key of a signer (can be repeated, order matters)"),
+                )
+                .arg(
+                    Arg::with_name("sighash-lock-arg")
+                        .long("sighash-lock-arg")
+                        .takes_value(true)
+                        .multiple(true)
+                        .conflicts_with("pubkey")
+                        .validator(|input| LabeledLockArgParser.validate(input))
+                        .help("Lock argument or label of a signer (can be repeated, order matters)"),
+                )
+                .arg(
+                    Arg::with_name("require-first-n")
+                        .long("require-first-n")
+                        .takes_value(true)
+                        .default_value("0")
+                        .validator(|input| FromStrParser::<u8>::default().validate(input))
+                        .help("The first n signers must sign"),
+                )
+                .arg(
+                    Arg::with_name("threshold")
+                        .long("threshold")
+                        .takes_value(true)
+                        .required(true)
+                        .validator(|input| FromStrParser::<u8>::default().validate(input))
+                        .help("Number of signatures required"),
+                ),
+            SubCommand::with_name("address")
+                .about("Show the address and lock script of a multisig config")
+                .arg(multisig_lock_arg()),
+            SubCommand::with_name("build-tx")
+                .about("Build an unsigned transaction spending capacity of a multisig address")
+                .arg(multisig_lock_arg())
+                .arg(arg::to_address().required(true))
+                .arg(arg::to_data())
+                .arg(arg::to_data_path())
+                .arg(arg::capacity().required(true))
+                .arg(arg::tx_fee())
+                .arg(arg::fee_rate())
+                .arg(arg::include_frozen())
+                .arg(arg::coin_selection())
+                .arg(tx_file(false)),
+            SubCommand::with_name("sign-tx")
+                .about("Sign a multisig transaction file (the signature is added to the file)")
+                .arg(arg::privkey_path().required_unless("from-account"))
+                .arg(arg::from_account().required_unless("privkey-path"))
+                .arg(arg::with_password())
+                .arg(arg::override_policy())
+                .arg(tx_file(true)),
+            SubCommand::with_name("replace-input")
+                .about("Replace an input (e.g. already spent) of a multisig transaction file by another cell of the multisig address, the change output is re-balanced")
+                .arg(
+                    Arg::with_name("old-out-point")
+                        .long("old-out-point")
+                        .takes_value(true)
+                        .required(true)
+                        .validator(|input| OutPointParser.validate(input))
+                        .help("Out point of the input to replace, format: {tx-hash}-{index}"),
+                )
+                .arg(
+                    Arg::with_name("new-out-point")
+                        .long("new-out-point")
+                        .takes_value(true)
+                        .required(true)
+                        .validator(|input| OutPointParser.validate(input))
+                        .help("Out point of the new input cell, format: {tx-hash}-{index}"),
+                )
+                .arg(tx_file(true)),
+            SubCommand::with_name("remove-output")
+                .about("Remove an output of a multisig transaction file, the capacity goes back to the change output")
+                .arg(
+                    Arg::with_name("output-index")
+                        .long("output-index")
+                        .takes_value(true)
+                        .required(true)
+                        .validator(|input| FromStrParser::<usize>::default().validate(input))
+                        .help("Index of the output to remove"),
+                )
+                .arg(tx_file(true)),
+            SubCommand::with_name("combine-and-send")
+                .about("Combine signatures of multisig transaction files then send the transaction")
+                .arg(tx_file(true).multiple(true))
+                .arg(arg::wait_for_commit())
+                .arg(arg::yes())
+                .arg(arg::force_fee()),
+        ])
+}
+
+impl<'a> WalletSubCommand<'a> {
+    pub fn multisig(
+        &mut self,
+        matches: &ArgMatches,
+        format: OutputFormat,
+        color: bool,
+        debug: bool,
+    ) -> Result<String, String> {
+        match matches.subcommand() {
+            ("create-config", Some(m)) => {
+                let pubkeys: Vec<secp256k1::PublicKey> =
+                    PubkeyHexParser.from_matches_vec(m, "pubkey")?;
+                let sighash_addresses = if pubkeys.is_empty() {
+                    LabeledLockArgParser.from_matches_vec(m, "sighash-lock-arg")?
+                } else {
+                    pubkeys
+                        .iter()
+                        .map(|pubkey| Address::from_pubkey(pubkey).map(|a| a.hash().clone()))
+                        .collect::<Result<Vec<_>, String>>()?
+                };
+                let require_first_n: u8 =
+                    FromStrParser::<u8>::default().from_matches(m, "require-first-n")?;
+                let threshold: u8 = FromStrParser::<u8>::default().from_matches(m, "threshold")?;
+                let config = MultisigConfig::new(sighash_addresses, require_first_n, threshold)?;
+                let network_type = get_network_type(self.rpc_client)?;
+                let mut configs = MultisigConfigs::load_default()?;
+                for other_lock_arg in configs.similar_to(&config) {
+                    eprintln!(
+                        "[WARNING]: Multisig config {:#x} has the same signers but different order or threshold, please make sure the pubkeys order and threshold are right",
+                        other_lock_arg
+                    );
+                }
+                let address = config.to_address();
+                // Checked before saving the config so a failed check leaves nothing added
+                let on_chain_activity = match self.genesis_info()?.lock_script(&address) {
+                    Ok(lock_script) => check_lock_activity(
+                        self.rpc_client,
+                        lock_script.calc_script_hash().unpack(),
+                    )?,
+                    Err(_) => None,
+                };
+                let lock_arg = configs.add(config.clone())?;
+                let resp = serde_json::json!({
+                    "lock_arg": lock_arg,
+                    "address": address.to_string(network_type),
+                    "config": config,
+                    "on_chain_activity": on_chain_activity,
+                });
+                Ok(resp.render(format, color))
+            }
+            ("address", Some(m)) => {
+                let lock_arg: H160 =
+                    FixedHashParser::<H160>::default().from_matches(m, "multisig-lock-arg")?;
+                let configs = MultisigConfigs::load_default()?;
+                let config = configs.get(&lock_arg)?;
+                let address = config.to_address();
+                let lock_script = self.genesis_info()?.lock_script(&address)?;
+                let lock_hash: H256 = lock_script.calc_script_hash().unpack();
+                let resp = serde_json::json!({
+                    "address": {
+                        "testnet": address.to_string(NetworkType::TestNet),
+                        "mainnet": address.to_string(NetworkType::MainNet),
+                    },
+                    "lock_script": ckb_jsonrpc_types::Script::from(lock_script),
+                    "lock_hash": lock_hash,
+                    "config": config,
+                });
+                Ok(resp.render(format, color))
+            }
+            ("build-tx", Some(m)) => self.multisig_build_tx(m, format, color),
+            ("replace-input", Some(m)) => {
+                let old_out_point: OutPoint = OutPointParser.from_matches(m, "old-out-point")?;
+                let new_out_point: OutPoint = OutPointParser.from_matches(m, "new-out-point")?;
+                let path = m.value_of("tx-file").unwrap();
+                let mut tx_file = MultisigTxFile::load(path)?;
+                let transaction = tx_file.transaction();
+                let mut inputs = transaction.inputs().into_iter().collect::<Vec<_>>();
+                let input_index = inputs
+                    .iter()
+                    .position(|input| input.previous_output() == old_out_point)
+                    .ok_or_else(|| "Input not found in the transaction".to_owned())?;
+                if inputs
+                    .iter()
+                    .any(|input| input.previous_output() == new_out_point)
+                {
+                    return Err("The new cell is already an input".to_owned());
+                }
+
+                let multisig_lock = self
+                    .genesis_info()?
+                    .lock_script(&tx_file.multisig_config.to_address())?;
+                let new_cell: CellWithStatus = self
+                    .rpc_client
+                    .get_live_cell(new_out_point.clone().into(), false)
+                    .call()
+                    .map_err(|err| format!("get_live_cell by RPC call failed: {}", err))?;
+                if !is_live_cell(&new_cell) || !is_secp_cell(&new_cell) {
+                    return Err("The new cell is not a live cell without type script".to_owned());
+                }
+                let new_output: CellOutput = new_cell.cell.unwrap().output.into();
+                if new_output.lock() != multisig_lock {
+                    return Err("The new cell is not owned by the multisig address".to_owned());
+                }
+                let old_output = self.get_cell_output(&old_out_point)?;
+                let old_capacity: u64 = old_output.capacity().unpack();
+                let new_capacity: u64 = new_output.capacity().unpack();
+
+                inputs[input_index] = inputs[input_index]
+                    .clone()
+                    .as_builder()
+                    .previous_output(new_out_point)
+                    .build();
+                let outputs = rebalance_change(
+                    transaction.outputs().into_iter().collect(),
+                    &multisig_lock,
+                    None,
+                    i128::from(new_capacity) - i128::from(old_capacity),
+                )?;
+                let transaction = transaction
+                    .as_advanced_builder()
+                    .set_inputs(inputs)
+                    .set_outputs(outputs)
+                    .build();
+                tx_file.update_transaction(transaction);
+                tx_file.save(path)?;
+                Ok(tx_file.summary().render(format, color))
+            }
+            ("remove-output", Some(m)) => {
+                let output_index: usize =
+                    FromStrParser::<usize>::default().from_matches(m, "output-index")?;
+                let path = m.value_of("tx-file").unwrap();
+                let mut tx_file = MultisigTxFile::load(path)?;
+                let transaction = tx_file.transaction();
+                let outputs = transaction.outputs().into_iter().collect::<Vec<_>>();
+                let mut outputs_data = transaction.outputs_data().into_iter().collect::<Vec<_>>();
+                let removed = outputs.get(output_index).cloned().ok_or_else(|| {
+                    format!(
+                        "Output index out of range: {} >= {}",
+                        output_index,
+                        outputs.len()
+                    )
+                })?;
+                let multisig_lock = self
+                    .genesis_info()?
+                    .lock_script(&tx_file.multisig_config.to_address())?;
+                let removed_capacity: u64 = removed.capacity().unpack();
+                let mut outputs = rebalance_change(
+                    outputs,
+                    &multisig_lock,
+                    Some(output_index),
+                    i128::from(removed_capacity),
+                )?;
+                outputs.remove(output_index);
+                outputs_data.remove(output_index);
+                let transaction = transaction
+                    .as_advanced_builder()
+                    .set_outputs(outputs)
+                    .set_outputs_data(outputs_data)
+                    .build();
+                tx_file.update_transaction(transaction);
+                tx_file.save(path)?;
+                Ok(tx_file.summary().render(format, color))
+            }
+            ("sign-tx", Some(m)) => self.multisig_sign_tx(m, format, color),
+            ("combine-and-send", Some(m)) => {
+                let paths = m.values_of("tx-file").unwrap().collect::<Vec<_>>();
+                let mut tx_file = MultisigTxFile::load(paths[0])?;
+                for path in &paths[1..] {
+                    let other = MultisigTxFile::load(path)?;
+                    if other.multisig_config != tx_file.multisig_config
+                        || other.transaction().hash() != tx_file.transaction().hash()
+                    {
+                        return Err(format!("Transaction in {} is not the same one", path));
+                    }
+                    tx_file.signatures.extend(other.signatures);
+                }
+                let (_, message) = tx_file.sign_message()?;
+                let mut signatures = Vec::new();
+                for (lock_arg, signature) in &tx_file.signatures {
+                    let signature = signature.clone().into_bytes();
+                    if &recover_signer(&message, &signature)? != lock_arg {
+                        return Err(format!("Invalid signature of {:#x}", lock_arg));
+                    }
+                    signatures.push((lock_arg.clone(), signature));
+                }
+                let witness_lock = tx_file.multisig_config.build_witness_lock(&signatures)?;
+                let transaction = set_multisig_witness(&tx_file.transaction(), witness_lock)?;
+                self.send_transaction(m, transaction, format, color, debug)
+            }
+            _ => Err(matches.usage().to_owned()),
+        }
+    }
+
+    fn multisig_build_tx(
+        &mut self,
+        m: &ArgMatches,
+        format: OutputFormat,
+        color: bool,
+    ) -> Result<String, String> {
+        let lock_arg: H160 =
+            FixedHashParser::<H160>::default().from_matches(m, "multisig-lock-arg")?;
+        let to_address: Address = LabeledAddressParser.from_matches(m, "to-address")?;
+        let to_data = to_data(m)?;
+        let capacity: u64 = CapacityParser.from_matches(m, "capacity")?;
+        let path = m.value_of("tx-file").unwrap();
+        let config = MultisigConfigs::load_default()?.get(&lock_arg)?.clone();
+
+        check_capacity(capacity, to_data.len())?;
+        let network_type = get_network_type(self.rpc_client)?;
+        let genesis_info = self.genesis_info()?;
+        check_to_address_prefix(m, network_type)?;
+        // For check index database is ready
+        self.check_index_ready()?;
+        let from_address = config.to_address();
+        let from_lock = genesis_info.lock_script(&from_address)?;
+        let change_policy = self.change_policy;
+        let build_dry_run = |inputs, total_capacity, tx_fee| {
+            let mut tx_args = TransferTransactionBuilder::new(
+                &from_address,
+                total_capacity,
+                &to_data,
+                &to_address,
+                capacity,
+                tx_fee,
+                inputs,
+            );
+            tx_args.set_change_policy(change_policy);
+            tx_args.transfer_multisig(&genesis_info, &config)
+        };
+        let (infos, total_capacity, tx_fee) =
+            self.collect_live_cells_with_fee(m, from_lock, capacity, is_secp_cell, build_dry_run)?;
+        if total_capacity < capacity + tx_fee {
+            return Err(tf(
+                "capacity-not-enough",
+                &[&from_address.to_string(network_type), &total_capacity],
+            ));
+        }
+        let inputs = infos.iter().map(LiveCellInfo::input).collect::<Vec<_>>();
+        let mut tx_args = TransferTransactionBuilder::new(
+            &from_address,
+            total_capacity,
+            &to_data,
+            &to_address,
+            capacity,
+            tx_fee,
+            inputs,
+        );
+        tx_args.set_change_policy(change_policy);
+        let transaction = tx_args.transfer_multisig(&genesis_info, &config)?;
+        let tx_hash: H256 = transaction.hash().unpack();
+        let tx_file = MultisigTxFile {
+            multisig_config: config,
+            transaction: transaction.data().into(),
+            signatures: BTreeMap::default(),
+        };
+        tx_file.save(path)?;
+        let resp = serde_json::json!({
+            "tx_hash": tx_hash,
+            "tx_file": path,
+        });
+        Ok(resp.render(format, color))
+    }
+
+    fn multisig_sign_tx(
+        &mut self,
+        m: &ArgMatches,
+        format: OutputFormat,
+        color: bool,
+    ) -> Result<String, String> {
+        let from_privkey: Option<PrivkeyWrapper> =
+            PrivkeyPathParser.from_matches_opt(m, "privkey-path", false)?;
+        let from_account: Option<H160> =
+            LabeledLockArgParser.from_matches_opt(m, "from-account", false)?;
+        let path = m.value_of("tx-file").unwrap();
+        let mut tx_file = MultisigTxFile::load(path)?;
+
+        let signer = if let Some(privkey) = from_privkey.as_ref() {
+            let pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, privkey);
+            Address::from_pubkey(&pubkey)?.hash().clone()
+        } else {
+            from_account.clone().unwrap()
+        };
+        if !tx_file.multisig_config.contains(&signer) {
+            return Err(format!(
+                "{:#x} is not a signer of the multisig config",
+                signer
+            ));
+        }
+        // The policy sees the capacity the multisig lock loses and the locks receiving it, the
+        // input cells are fetched only then (the file can be signed offline otherwise)
+        let policy = if SigningPolicy::load(&get_ckb_cli_dir())?.is_some() {
+            let transaction = tx_file.transaction();
+            let genesis_info = self.genesis_info()?;
+            let multisig_lock = genesis_info.lock_script(&tx_file.multisig_config.to_address())?;
+            let input_cells = transaction
+                .input_pts_iter()
+                .map(|out_point| self.get_cell_output(&out_point))
+                .collect::<Result<Vec<_>, String>>()?;
+            let (capacity, to_addresses) =
+                transaction_outflow(&transaction, &input_cells, &multisig_lock, &genesis_info)?;
+            let from_address = Address::from_lock_arg(signer.as_bytes())?;
+            let request = SignRequest {
+                kind: SignKind::Transfer,
+                from_address: &from_address,
+                to_addresses: &to_addresses,
+                capacity,
+                cosigned: tx_file.multisig_config.threshold() >= 2,
+            };
+            check_policy(&request, m.is_present("override-policy"))?
+                .map(|policy| (policy, capacity))
+        } else {
+            None
+        };
+
+        let (sign_args, message) = tx_file.sign_message()?;
+        let signature = if let Some(privkey) = from_privkey.as_ref() {
+            witness_with_key(privkey, &sign_args)?
+        } else {
+            if !self.key_store.has_account(&signer) {
+                return Err(format!("Account not found in keystore: {:#x}", signer));
+            }
+            let password = if m.is_present("with-password") {
+                Some(read_password(false, None)?)
+            } else {
+                None
+            };
+            self.build_witness_with_keystore(&signer, &sign_args, &password)?
+        };
+        if recover_signer(&message, &signature)? != signer {
+            return Err("Signature not match the signer".to_owned());
+        }
+        tx_file
+            .signatures
+            .insert(signer.clone(), JsonBytes::from_bytes(signature));
+        tx_file.save(path)?;
+        // The transaction is sent by `combine-and-send`, record the capacity once signed
+        if let Some((policy, capacity)) = policy {
+            policy.record(capacity)?;
+        }
+        let resp = serde_json::json!({
+            "signer": signer,
+            "signatures": tx_file.signatures.len(),
+            "threshold": tx_file.multisig_config.threshold(),
+        });
+        Ok(resp.render(format, color))
+    }
+}
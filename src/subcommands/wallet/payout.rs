@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use ckb_index::LiveCellInfo;
+use ckb_sdk::{Address, MIN_SECP_CELL_CAPACITY};
+use ckb_types::H256;
+
+use crate::utils::arg_parser::{AddressParser, ArgParser, FromStrParser};
+
+/// One line of the shares CSV file: `<address>,<shares>`
+#[derive(Debug, Clone)]
+pub struct Share {
+    pub address: Address,
+    pub address_str: String,
+    pub weight: u64,
+}
+
+/// Parse shares CSV content, empty lines and lines start with `#` are ignored, a header line
+/// (`address,shares`) is allowed.
+pub fn parse_shares(content: &str) -> Result<Vec<Share>, String> {
+    let mut shares = Vec::new();
+    for (idx, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || (idx == 0 && line.starts_with("address")) {
+            continue;
+        }
+        let parts = line.split(',').map(str::trim).collect::<Vec<_>>();
+        if parts.len() != 2 {
+            return Err(format!(
+                "Invalid shares line {}: {}, format: <address>,<shares>",
+                idx + 1,
+                line
+            ));
+        }
+        let address = AddressParser
+            .parse(parts[0])
+            .map_err(|err| format!("Invalid address at line {}: {}", idx + 1, err))?;
+        let weight = FromStrParser::<u64>::default()
+            .parse(parts[1])
+            .map_err(|err| format!("Invalid shares at line {}: {}", idx + 1, err))?;
+        shares.push(Share {
+            address,
+            address_str: parts[0].to_owned(),
+            weight,
+        });
+    }
+    if shares.iter().all(|share| share.weight == 0) {
+        return Err("No shares found".to_owned());
+    }
+    Ok(shares)
+}
+
+/// Split `total` capacity proportionally by shares, the payouts which can not afford a cell are
+/// skipped (left to sender as change).
+pub fn split_rewards(total: u64, shares: &[Share]) -> (Vec<(Share, u64)>, Vec<(Share, u64)>) {
+    let total_weight: u128 = shares.iter().map(|share| u128::from(share.weight)).sum();
+    let (payouts, skipped): (Vec<_>, Vec<_>) = shares
+        .iter()
+        .filter(|share| share.weight > 0)
+        .map(|share| {
+            let amount = u128::from(total) * u128::from(share.weight) / total_weight;
+            (share.clone(), amount as u64)
+        })
+        .partition(|(_, amount)| *amount >= *MIN_SECP_CELL_CAPACITY);
+    (payouts, skipped)
+}
+
+/// Local record of cellbase cells already paid out, stored in `~/.ckb-cli/payout-ledger`
+pub struct PayoutLedger {
+    path: PathBuf,
+    // out_point => payout transaction hash
+    paid: HashMap<String, H256>,
+}
+
+impl PayoutLedger {
+    pub fn load(path: PathBuf) -> Result<PayoutLedger, String> {
+        let paid = if path.exists() {
+            let content = fs::read_to_string(&path).map_err(|err| err.to_string())?;
+            serde_json::from_str(&content)
+                .map_err(|err| format!("Parse payout ledger {:?} failed: {}", path, err))?
+        } else {
+            HashMap::default()
+        };
+        Ok(PayoutLedger { path, paid })
+    }
+
+    pub fn is_paid(&self, info: &LiveCellInfo) -> bool {
+        self.paid.contains_key(&ledger_key(info))
+    }
+
+    pub fn record(&mut self, infos: &[LiveCellInfo], tx_hash: &H256) -> Result<(), String> {
+        for info in infos {
+            self.paid.insert(ledger_key(info), tx_hash.clone());
+        }
+        let content = serde_json::to_string_pretty(&self.paid).map_err(|err| err.to_string())?;
+        fs::write(&self.path, content).map_err(|err| err.to_string())
+    }
+}
+
+fn ledger_key(info: &LiveCellInfo) -> String {
+    format!("{:#x}-{}", info.tx_hash, info.tx_index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ckb_sdk::{NetworkType, ONE_CKB};
+
+    #[test]
+    fn test_split_rewards() {
+        let address_a = Address::from_lock_arg(&[1u8; 20]).unwrap();
+        let address_b = Address::from_lock_arg(&[2u8; 20]).unwrap();
+        let content = format!(
+            "address,shares\n{},3\n\n# comment\n{},1\n",
+            address_a.to_string(NetworkType::TestNet),
+            address_b.to_string(NetworkType::TestNet),
+        );
+        let shares = parse_shares(&content).unwrap();
+        assert_eq!(shares.len(), 2);
+
+        let (payouts, skipped) = split_rewards(400 * ONE_CKB, &shares);
+        assert_eq!(payouts.len(), 2);
+        assert!(skipped.is_empty());
+        assert_eq!(payouts[0].1, 300 * ONE_CKB);
+        assert_eq!(payouts[1].1, 100 * ONE_CKB);
+
+        let (payouts, skipped) = split_rewards(200 * ONE_CKB, &shares);
+        assert_eq!(payouts.len(), 1);
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].1, 50 * ONE_CKB);
+    }
+}
@@ -0,0 +1,83 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use ckb_types::{core::TransactionView, packed, prelude::*, H256};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::utils::other::{get_ckb_cli_dir, now_seconds};
+
+// Pending transactions are forgotten after 7 days
+const EXPIRE_SECONDS: u64 = 7 * 24 * 60 * 60;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PendingTx {
+    pub transaction: ckb_jsonrpc_types::Transaction,
+    // Unix timestamp (in seconds) when the transaction was sent
+    pub timestamp: u64,
+}
+
+impl PendingTx {
+    pub fn transaction(&self) -> TransactionView {
+        packed::Transaction::from(self.transaction.clone()).into_view()
+    }
+}
+
+/// Transactions sent by wallet (inputs, outputs and the sending time), used by `wallet bump-fee`,
+/// `wallet rebroadcast`, `wallet tx-status` and `--allow-unconfirmed-inputs`.
+/// Stored in `~/.ckb-cli/pending-txs`
+pub struct PendingTxs {
+    path: PathBuf,
+    txs: BTreeMap<H256, PendingTx>,
+}
+
+impl PendingTxs {
+    pub fn load(path: PathBuf) -> Result<PendingTxs, String> {
+        let mut txs: BTreeMap<H256, PendingTx> = if path.exists() {
+            let content = fs::read_to_string(&path).map_err(|err| err.to_string())?;
+            serde_json::from_str(&content)
+                .map_err(|err| format!("Parse pending transactions {:?} failed: {}", path, err))?
+        } else {
+            BTreeMap::default()
+        };
+        let now = now_seconds();
+        txs.retain(|_, tx| tx.timestamp + EXPIRE_SECONDS > now);
+        Ok(PendingTxs { path, txs })
+    }
+
+    pub fn load_default() -> Result<PendingTxs, String> {
+        PendingTxs::load(get_ckb_cli_dir().join("pending-txs"))
+    }
+
+    pub fn add(&mut self, transaction: &TransactionView) -> Result<(), String> {
+        let tx = PendingTx {
+            transaction: transaction.data().into(),
+            timestamp: now_seconds(),
+        };
+        self.txs.insert(transaction.hash().unpack(), tx);
+        self.save()
+    }
+
+    pub fn get(&self, tx_hash: &H256) -> Result<&PendingTx, String> {
+        self.txs
+            .get(tx_hash)
+            .ok_or_else(|| format!("Pending transaction not found: {:#x}", tx_hash))
+    }
+
+    /// The pending transactions, the oldest first
+    pub fn transactions(&self) -> Vec<TransactionView> {
+        let mut txs = self.txs.values().collect::<Vec<_>>();
+        txs.sort_by_key(|tx| tx.timestamp);
+        txs.into_iter().map(PendingTx::transaction).collect()
+    }
+
+    pub fn remove(&mut self, tx_hash: &H256) -> Result<(), String> {
+        self.txs.remove(tx_hash);
+        self.save()
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(&self.txs).map_err(|err| err.to_string())?;
+        fs::write(&self.path, content).map_err(|err| err.to_string())
+    }
+}
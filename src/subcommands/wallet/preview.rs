@@ -0,0 +1,157 @@
+use ckb_sdk::{AddressPayload, GenesisInfo, NetworkType};
+use ckb_types::{bytes::Bytes, core::TransactionView, packed::CellOutput, prelude::*, H256};
+use serde_derive::Serialize;
+
+use super::fee_guard::fee_and_transferred;
+use super::spendable::is_dao_deposit;
+
+/// Summary of a transaction shown before it is sent (see `--yes`)
+#[derive(Debug, Serialize)]
+pub struct TxPreview {
+    pub tx_hash: H256,
+    pub inputs: Vec<AddressCapacity>,
+    pub outputs: Vec<AddressCapacity>,
+    /// Inputs minus outputs, unknown when the outputs include NervosDAO interest
+    pub fee: Option<u64>,
+    /// Unit: shannons/KB
+    pub fee_rate: Option<u64>,
+    pub dao_actions: Vec<DaoAction>,
+}
+
+/// The cells of an address
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AddressCapacity {
+    pub address: String,
+    pub cells: usize,
+    pub capacity: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DaoAction {
+    /// deposit, prepare (withdraw phase 1) or withdraw (phase 2)
+    pub action: &'static str,
+    pub address: String,
+    pub capacity: u64,
+}
+
+impl TxPreview {
+    /// The input cells (output and data) are in the same order as the inputs of the transaction
+    pub fn new(
+        transaction: &TransactionView,
+        input_cells: &[(CellOutput, Bytes)],
+        tx_size: u64,
+        genesis_info: &GenesisInfo,
+        network: NetworkType,
+    ) -> TxPreview {
+        let address = |output: &CellOutput| {
+            AddressPayload::from_lock_script(&output.lock(), genesis_info)
+                .to_address_string(network)
+        };
+        let is_dao = |output: &CellOutput| {
+            output
+                .type_()
+                .to_opt()
+                .map(|script| &script.code_hash() == genesis_info.dao_type_hash())
+                .unwrap_or(false)
+        };
+        let outputs = transaction.outputs_with_data_iter().collect::<Vec<_>>();
+
+        let mut dao_actions = Vec::new();
+        for (output, data) in input_cells {
+            if is_dao(output) && !is_dao_deposit(data) {
+                dao_actions.push(DaoAction {
+                    action: "withdraw",
+                    address: address(output),
+                    capacity: output.capacity().unpack(),
+                });
+            }
+        }
+        for (output, data) in &outputs {
+            if is_dao(output) {
+                let action = if is_dao_deposit(data) {
+                    "deposit"
+                } else {
+                    "prepare"
+                };
+                dao_actions.push(DaoAction {
+                    action,
+                    address: address(output),
+                    capacity: output.capacity().unpack(),
+                });
+            }
+        }
+
+        let fee = fee_and_transferred(transaction, input_cells).map(|(fee, _)| fee);
+        TxPreview {
+            tx_hash: transaction.hash().unpack(),
+            inputs: group_by_address(input_cells.iter().map(|(output, _)| output), &address),
+            outputs: group_by_address(outputs.iter().map(|(output, _)| output), &address),
+            fee,
+            fee_rate: fee.map(|fee| fee * 1000 / tx_size.max(1)),
+            dao_actions,
+        }
+    }
+}
+
+// Keep the order of the first cell of each address
+fn group_by_address<'a, I, F>(cells: I, address: F) -> Vec<AddressCapacity>
+where
+    I: Iterator<Item = &'a CellOutput>,
+    F: Fn(&CellOutput) -> String,
+{
+    let mut groups: Vec<AddressCapacity> = Vec::new();
+    for output in cells {
+        let address = address(output);
+        let capacity: u64 = output.capacity().unpack();
+        match groups.iter_mut().find(|group| group.address == address) {
+            Some(group) => {
+                group.cells += 1;
+                group.capacity += capacity;
+            }
+            None => groups.push(AddressCapacity {
+                address,
+                cells: 1,
+                capacity,
+            }),
+        }
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ckb_types::{core::Capacity, packed::Script};
+    use faster_hex::hex_string;
+
+    #[test]
+    fn test_group_by_address() {
+        let cell = |arg: u8, capacity: u64| {
+            CellOutput::new_builder()
+                .lock(
+                    Script::new_builder()
+                        .args(Bytes::from(vec![arg]).pack())
+                        .build(),
+                )
+                .capacity(Capacity::shannons(capacity).pack())
+                .build()
+        };
+        let cells = vec![cell(2, 100), cell(1, 200), cell(2, 300)];
+        let address = |output: &CellOutput| hex_string(&output.lock().args().raw_data()).unwrap();
+        assert_eq!(
+            group_by_address(cells.iter(), &address),
+            vec![
+                AddressCapacity {
+                    address: "02".to_owned(),
+                    cells: 2,
+                    capacity: 400,
+                },
+                AddressCapacity {
+                    address: "01".to_owned(),
+                    cells: 1,
+                    capacity: 200,
+                },
+            ]
+        );
+    }
+}
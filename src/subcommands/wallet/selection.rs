@@ -0,0 +1,37 @@
+use ckb_index::LiveCellInfo;
+use clap::ArgMatches;
+
+/// Coin selection strategy, the order of the candidate cells to spend
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum CoinSelection {
+    // Oldest cells (by block number) first, keep the UTXO set fresh
+    OldestFirst,
+    // Largest capacity first, use as few inputs as possible
+    LargestFirst,
+}
+
+impl CoinSelection {
+    pub fn from_str(input: &str) -> Result<CoinSelection, String> {
+        match input {
+            "oldest-first" => Ok(CoinSelection::OldestFirst),
+            "largest-first" => Ok(CoinSelection::LargestFirst),
+            _ => Err(format!("Invalid coin selection strategy: {}", input)),
+        }
+    }
+
+    pub fn from_matches(m: &ArgMatches) -> Result<CoinSelection, String> {
+        m.value_of("coin-selection")
+            .map(CoinSelection::from_str)
+            .unwrap_or(Ok(CoinSelection::OldestFirst))
+    }
+
+    pub fn sort(self, infos: &mut Vec<LiveCellInfo>) {
+        match self {
+            CoinSelection::OldestFirst => infos
+                .sort_by_key(|info| (info.number, info.index.tx_index, info.index.output_index)),
+            CoinSelection::LargestFirst => {
+                infos.sort_by(|a, b| b.capacity.cmp(&a.capacity).then(a.number.cmp(&b.number)))
+            }
+        }
+    }
+}
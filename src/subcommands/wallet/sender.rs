@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use ckb_sdk::{check_output_capacities, tx_size_in_block, GenesisInfo, HttpRpcClient};
+use ckb_types::{bytes::Bytes, core::TransactionView, packed::CellOutput, prelude::*, H256};
+use clap::ArgMatches;
+
+use super::fee_guard::{fee_and_transferred, FeeGuard};
+use super::pending::PendingTxs;
+use super::preview::TxPreview;
+use super::spent::SpentCells;
+use super::{tx_status_name, wait_timeout};
+use crate::utils::{
+    i18n::{t, tf},
+    other::{get_genesis_info, get_network_type, read_confirmation},
+    printer::{OutputFormat, Printable},
+};
+
+const WAIT_COMMIT_INTERVAL: Duration = Duration::from_secs(2);
+
+/// The send path of all the commands broadcasting a transaction (`wallet`, `tx send` and
+/// `mock-tx send`): the fee guard (see `--force-fee`), the preview and confirmation (see
+/// `--yes`), the spent-cells and pending-txs journals and `--wait-for-commit`.
+pub struct TxSender<'a> {
+    rpc_client: &'a mut HttpRpcClient,
+    genesis_info: Option<GenesisInfo>,
+    fee_guard: FeeGuard,
+    confirm_send: bool,
+}
+
+impl<'a> TxSender<'a> {
+    pub fn new(rpc_client: &'a mut HttpRpcClient, genesis_info: Option<GenesisInfo>) -> Self {
+        TxSender {
+            rpc_client,
+            genesis_info,
+            fee_guard: FeeGuard::default(),
+            confirm_send: true,
+        }
+    }
+
+    /// The limits of transaction fee checked before sending (unless `--force-fee`)
+    pub fn with_fee_guard(mut self, fee_guard: FeeGuard) -> Self {
+        self.fee_guard = fee_guard;
+        self
+    }
+
+    /// Preview the transaction and ask for confirmation before sending it (unless `--yes`)
+    pub fn with_confirmation(mut self, confirm_send: bool) -> Self {
+        self.confirm_send = confirm_send;
+        self
+    }
+
+    /// Check the outputs and the fee (see `--force-fee`), preview the transaction and send it
+    /// once confirmed (see `--yes`)
+    pub fn send(
+        &mut self,
+        m: &ArgMatches,
+        transaction: TransactionView,
+        format: OutputFormat,
+        color: bool,
+        debug: bool,
+    ) -> Result<String, String> {
+        // Also for the transactions not built by `TransferTransactionBuilder`
+        check_output_capacities(&transaction, &[])?;
+        let check_fee = !m.is_present("force-fee");
+        let confirm = self.confirm_send && !m.is_present("yes");
+        if confirm && !atty::is(atty::Stream::Stdin) {
+            return Err(t("confirm-required").to_owned());
+        }
+        if check_fee || confirm {
+            let input_cells = self.input_cells(&transaction)?;
+            if check_fee {
+                // The fee is unknown when NervosDAO interest is withdrawn
+                if let Some((fee, transferred)) = fee_and_transferred(&transaction, &input_cells) {
+                    self.fee_guard.check(fee, transferred)?;
+                }
+            }
+            if confirm {
+                self.confirm_transaction(&transaction, &input_cells, color)?;
+            }
+        }
+        self.broadcast(m, transaction, format, color, debug)
+    }
+
+    /// Send the transaction, then wait until it is committed when `--wait-for-commit` is given.
+    /// The output is `{"tx_hash": ..}`.
+    pub fn broadcast(
+        &mut self,
+        m: &ArgMatches,
+        transaction: TransactionView,
+        format: OutputFormat,
+        color: bool,
+        debug: bool,
+    ) -> Result<String, String> {
+        let transaction_view: ckb_jsonrpc_types::TransactionView = transaction.clone().into();
+        if debug {
+            eprintln!(
+                "[Send Transaction]:\n{}",
+                transaction_view.render(format, color)
+            );
+        }
+
+        let resp = self
+            .rpc_client
+            .send_transaction(transaction.data().into())
+            .call()
+            .map_err(|err| format!("Send transaction error: {}", err))?;
+        // The transaction is sent, failing to record the inputs is not fatal
+        if let Err(err) =
+            SpentCells::load_default().and_then(|mut cells| cells.record(&transaction))
+        {
+            eprintln!("{}", tf("record-spent-failed", &[&err]));
+        }
+        if let Err(err) = PendingTxs::load_default().and_then(|mut txs| txs.add(&transaction)) {
+            eprintln!("{}", tf("record-pending-failed", &[&err]));
+        }
+        if let Some(timeout) = wait_timeout(m)? {
+            wait_for_commit(self.rpc_client, resp.clone(), timeout)?;
+        }
+        // An object, so `--only-field tx_hash` picks the hash like other outputs
+        Ok(serde_json::json!({ "tx_hash": resp }).render(format, color))
+    }
+
+    /// Show the summary of the transaction (in yaml, it is for human) and ask for confirmation
+    fn confirm_transaction(
+        &mut self,
+        transaction: &TransactionView,
+        input_cells: &[(CellOutput, Bytes)],
+        color: bool,
+    ) -> Result<(), String> {
+        let genesis_info = get_genesis_info(&mut self.genesis_info, self.rpc_client)?;
+        let network_type = get_network_type(self.rpc_client)?;
+        let preview = TxPreview::new(
+            transaction,
+            input_cells,
+            tx_size_in_block(transaction),
+            &genesis_info,
+            network_type,
+        );
+        eprintln!(
+            "[Transaction Preview]:\n{}",
+            preview.render(OutputFormat::Yaml, color)
+        );
+        if read_confirmation(t("confirm-send-tx"))? {
+            Ok(())
+        } else {
+            Err("Transaction canceled".to_owned())
+        }
+    }
+
+    // The cells (output and data) spent by the inputs, one request for each previous transaction
+    fn input_cells(
+        &mut self,
+        transaction: &TransactionView,
+    ) -> Result<Vec<(CellOutput, Bytes)>, String> {
+        let mut previous_txs: HashMap<H256, ckb_jsonrpc_types::Transaction> = HashMap::new();
+        let mut cells = Vec::new();
+        for input in transaction.inputs().into_iter() {
+            let out_point = input.previous_output();
+            let tx_hash: H256 = out_point.tx_hash().unpack();
+            let index: u32 = out_point.index().unpack();
+            if !previous_txs.contains_key(&tx_hash) {
+                let tx = self
+                    .rpc_client
+                    .get_transaction(tx_hash.clone())
+                    .call()
+                    .map_err(|err| format!("Send get_transaction error: {}", err))?
+                    .0
+                    .ok_or_else(|| format!("Transaction not found: {:#x}", tx_hash))?;
+                previous_txs.insert(tx_hash.clone(), tx.transaction.inner);
+            }
+            let tx = &previous_txs[&tx_hash];
+            let output = tx
+                .outputs
+                .get(index as usize)
+                .cloned()
+                .ok_or_else(|| format!("Output not found: {:#x}-{}", tx_hash, index))?;
+            let data = tx
+                .outputs_data
+                .get(index as usize)
+                .cloned()
+                .map(|data| data.into_bytes())
+                .unwrap_or_default();
+            cells.push((output.into(), data));
+        }
+        Ok(cells)
+    }
+}
+
+/// Poll `get_transaction` until the transaction is committed (print the confirming block),
+/// fail on timeout.
+pub fn wait_for_commit(
+    rpc_client: &mut HttpRpcClient,
+    tx_hash: H256,
+    timeout: u64,
+) -> Result<(), String> {
+    let start_time = Instant::now();
+    loop {
+        let tx_opt = rpc_client
+            .get_transaction(tx_hash.clone())
+            .call()
+            .map_err(|err| format!("Send get_transaction error: {}", err))?
+            .0;
+        let status = tx_status_name(tx_opt.as_ref());
+        if let Some(block_hash) = tx_opt.and_then(|tx| tx.tx_status.block_hash) {
+            let header = rpc_client
+                .get_header(block_hash.clone())
+                .call()
+                .map_err(|err| format!("Send get_header error: {}", err))?
+                .0
+                .ok_or_else(|| format!("Block header not found: {:#x}", block_hash))?;
+            eprintln!(
+                "{}",
+                tf(
+                    "tx-committed",
+                    &[
+                        &format!("{:#x}", tx_hash),
+                        &header.inner.number.value(),
+                        &format!("{:#x}", block_hash)
+                    ]
+                )
+            );
+            return Ok(());
+        }
+        if start_time.elapsed() >= Duration::from_secs(timeout) {
+            return Err(tf(
+                "wait-commit-timeout",
+                &[&format!("{:#x}", tx_hash), &timeout, &status],
+            ));
+        }
+        thread::sleep(WAIT_COMMIT_INTERVAL);
+    }
+}
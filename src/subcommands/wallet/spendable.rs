@@ -0,0 +1,159 @@
+use ckb_index::LiveCellInfo;
+use ckb_types::{core::EpochNumberWithFraction, H256};
+use serde_derive::Serialize;
+
+pub const CELLBASE_MATURITY_EPOCHS: u64 = 4;
+
+/// Epochs of a NervosDAO lock period, a prepared cell can only be withdrawn at the end of it
+pub const DAO_LOCK_PERIOD_EPOCHS: u64 = 180;
+
+/// Plain cell: no type script and empty data
+pub fn is_plain_cell(info: &LiveCellInfo) -> bool {
+    info.type_hashes.is_none() && info.data_bytes == 0
+}
+
+pub fn is_cellbase(info: &LiveCellInfo) -> bool {
+    info.index.tx_index == 0
+}
+
+/// Cellbase outputs can only be spent `CELLBASE_MATURITY_EPOCHS` epochs later, compared with
+/// the fraction of the epochs (as the consensus does)
+pub fn is_mature(
+    cellbase_epoch: EpochNumberWithFraction,
+    tip_epoch: EpochNumberWithFraction,
+) -> bool {
+    let mature_number = cellbase_epoch.number() + CELLBASE_MATURITY_EPOCHS;
+    tip_epoch.number() > mature_number
+        || (tip_epoch.number() == mature_number
+            && tip_epoch.index() * cellbase_epoch.length()
+                >= cellbase_epoch.index() * tip_epoch.length())
+}
+
+/// NervosDAO cell, the code hash of the type script is the type hash of the dao cell in genesis
+pub fn is_dao_cell(info: &LiveCellInfo, dao_type_hash: &H256) -> bool {
+    info.type_hashes
+        .as_ref()
+        .map(|(code_hash, _)| code_hash == dao_type_hash)
+        .unwrap_or(false)
+}
+
+/// The data of a deposited cell is 8 zero bytes, a prepared (withdraw phase 1) cell stores the
+/// deposit block number instead
+pub fn is_dao_deposit(data: &[u8]) -> bool {
+    data.len() == 8 && data.iter().all(|byte| *byte == 0)
+}
+
+/// A cell can be withdrawn at the end of the lock period (180 epochs since the deposit) the
+/// prepare block is in, at least one period.
+pub fn dao_unlock_epoch(
+    deposit: EpochNumberWithFraction,
+    withdraw: EpochNumberWithFraction,
+) -> EpochNumberWithFraction {
+    let mut passed = withdraw.number().saturating_sub(deposit.number());
+    if withdraw.index() * deposit.length() > deposit.index() * withdraw.length() {
+        passed += 1;
+    }
+    let periods = std::cmp::max(
+        1,
+        (passed + DAO_LOCK_PERIOD_EPOCHS - 1) / DAO_LOCK_PERIOD_EPOCHS,
+    );
+    EpochNumberWithFraction::new(
+        deposit.number() + periods * DAO_LOCK_PERIOD_EPOCHS,
+        deposit.index(),
+        deposit.length(),
+    )
+}
+
+pub fn epoch_value(epoch: EpochNumberWithFraction) -> f64 {
+    if epoch.length() == 0 {
+        epoch.number() as f64
+    } else {
+        epoch.number() as f64 + epoch.index() as f64 / epoch.length() as f64
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Serialize)]
+pub struct Bucket {
+    pub cells: usize,
+    pub capacity: u64,
+}
+
+impl Bucket {
+    pub fn add(&mut self, info: &LiveCellInfo) {
+        self.cells += 1;
+        self.capacity += info.capacity;
+    }
+}
+
+/// Live cells of a lock grouped by whether (and when) they can be spent
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct Balance {
+    pub total: Bucket,
+    /// Plain cells (mature cellbase included)
+    pub spendable: Bucket,
+    /// Cellbase cells not mature yet
+    pub immature: Bucket,
+    pub dao_deposited: Bucket,
+    pub dao_prepared: Bucket,
+    /// Anyone-can-pay cells of the same owner (only when queried by address)
+    pub acp: Bucket,
+    /// Cells with data or type script other than NervosDAO (can not be spent by `wallet transfer`)
+    pub unknown: Bucket,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ckb_index::CellIndex;
+
+    fn cell_info(
+        tx_index: u32,
+        data_bytes: u64,
+        type_hashes: Option<(H256, H256)>,
+    ) -> LiveCellInfo {
+        LiveCellInfo {
+            tx_hash: H256::default(),
+            tx_index: 0,
+            data_bytes,
+            lock_hash: H256::default(),
+            type_hashes,
+            capacity: 100,
+            number: 1,
+            index: CellIndex {
+                tx_index,
+                output_index: 0,
+            },
+        }
+    }
+
+    #[test]
+    fn test_predicates() {
+        let dao_type_hash = H256::from_slice(&[1u8; 32]).unwrap();
+        let plain = cell_info(1, 0, None);
+        let cellbase = cell_info(0, 0, None);
+        let dao = cell_info(1, 8, Some((dao_type_hash.clone(), H256::default())));
+        let other = cell_info(1, 8, Some((H256::default(), H256::default())));
+
+        assert!(is_plain_cell(&plain) && !is_cellbase(&plain));
+        assert!(is_plain_cell(&cellbase) && is_cellbase(&cellbase));
+        assert!(!is_plain_cell(&dao) && is_dao_cell(&dao, &dao_type_hash));
+        assert!(!is_dao_cell(&other, &dao_type_hash));
+        assert!(!is_dao_cell(&plain, &dao_type_hash));
+
+        let epoch = EpochNumberWithFraction::new;
+        assert!(is_mature(epoch(1, 0, 1000), epoch(5, 0, 1000)));
+        assert!(!is_mature(epoch(1, 0, 1000), epoch(4, 999, 1000)));
+        assert!(is_mature(epoch(1, 500, 1000), epoch(5, 250, 500)));
+        assert!(!is_mature(epoch(1, 500, 1000), epoch(5, 249, 500)));
+        assert!(is_mature(epoch(1, 999, 1000), epoch(6, 0, 1000)));
+        assert!(is_dao_deposit(&[0u8; 8]));
+        assert!(!is_dao_deposit(&[1, 0, 0, 0, 0, 0, 0, 0]));
+        assert!(!is_dao_deposit(&[]));
+
+        let mut bucket = Bucket::default();
+        bucket.add(&plain);
+        bucket.add(&dao);
+        assert_eq!(bucket.cells, 2);
+        assert_eq!(bucket.capacity, 200);
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use ckb_types::{core::TransactionView, prelude::*, H256};
+use serde_derive::{Deserialize, Serialize};
+
+use super::frozen::out_point_to_string;
+use crate::utils::other::{get_ckb_cli_dir, now_seconds};
+
+// Entries are dropped after one day even not unlocked by `wallet unlock-cells`
+const EXPIRE_SECONDS: u64 = 24 * 60 * 60;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SpentCell {
+    // The transaction spent this cell
+    pub tx_hash: H256,
+    // Unix timestamp (in seconds) when the transaction was sent
+    pub timestamp: u64,
+}
+
+/// Out-points spent by sent transactions, the index database may not know them until the
+/// transactions are committed and synced, collectors skip them so quick successive commands do
+/// not pick the same inputs. Stored in `~/.ckb-cli/spent-cells`
+pub struct SpentCells {
+    path: PathBuf,
+    // out-point ({tx-hash}-{index}) => spent by
+    cells: BTreeMap<String, SpentCell>,
+}
+
+impl SpentCells {
+    pub fn load(path: PathBuf) -> Result<SpentCells, String> {
+        let mut cells: BTreeMap<String, SpentCell> = if path.exists() {
+            let content = fs::read_to_string(&path).map_err(|err| err.to_string())?;
+            serde_json::from_str(&content)
+                .map_err(|err| format!("Parse spent cells file {:?} failed: {}", path, err))?
+        } else {
+            BTreeMap::default()
+        };
+        let now = now_seconds();
+        cells.retain(|_, cell| cell.timestamp + EXPIRE_SECONDS > now);
+        Ok(SpentCells { path, cells })
+    }
+
+    pub fn load_default() -> Result<SpentCells, String> {
+        SpentCells::load(get_ckb_cli_dir().join("spent-cells"))
+    }
+
+    pub fn contains(&self, out_point: &str) -> bool {
+        self.cells.contains_key(out_point)
+    }
+
+    /// Record the inputs of a sent transaction
+    pub fn record(&mut self, transaction: &TransactionView) -> Result<(), String> {
+        let tx_hash: H256 = transaction.hash().unpack();
+        let timestamp = now_seconds();
+        for input in transaction.inputs().into_iter() {
+            let cell = SpentCell {
+                tx_hash: tx_hash.clone(),
+                timestamp,
+            };
+            self.cells
+                .insert(out_point_to_string(&input.previous_output()), cell);
+        }
+        self.save()
+    }
+
+    /// Remove the entries not matched by `keep`, return the removed out-points
+    pub fn unlock<F>(&mut self, mut keep: F) -> Result<Vec<String>, String>
+    where
+        F: FnMut(&str, &SpentCell) -> bool,
+    {
+        let removed = self
+            .cells
+            .iter()
+            .filter(|(out_point, cell)| !keep(out_point, cell))
+            .map(|(out_point, _)| out_point.clone())
+            .collect::<Vec<_>>();
+        for out_point in &removed {
+            self.cells.remove(out_point);
+        }
+        self.save()?;
+        Ok(removed)
+    }
+
+    pub fn list(&self) -> &BTreeMap<String, SpentCell> {
+        &self.cells
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(&self.cells).map_err(|err| err.to_string())?;
+        fs::write(&self.path, content).map_err(|err| err.to_string())
+    }
+}
@@ -0,0 +1,502 @@
+use std::collections::BTreeMap;
+use std::slice;
+
+use ckb_index::LiveCellInfo;
+use ckb_jsonrpc_types::CellWithStatus;
+use ckb_sdk::{
+    parse_udt_amount, udt_amount_data, udt_cell_capacity, Address, SudtConfig,
+    TransferTransactionBuilder,
+};
+use ckb_types::{
+    bytes::Bytes,
+    packed::{Byte32, CellOutput, OutPoint, Script},
+    prelude::*,
+    H256,
+};
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use super::{
+    check_to_address_prefix, is_live_cell, is_secp_cell, load_frozen, privkey_or_account,
+    CellQuery, WalletSubCommand,
+};
+use crate::utils::{
+    arg,
+    arg_parser::{
+        ArgParser, CapacityParser, FixedHashParser, FromStrParser, LabeledAddressParser,
+        OutPointParser,
+    },
+    i18n::tf,
+    other::{get_address, get_network_type},
+    policy::SignKind,
+    printer::{OutputFormat, Printable},
+};
+
+fn amount<'a, 'b>(name: &'static str) -> Arg<'a, 'b> {
+    Arg::with_name(name)
+        .long(name)
+        .takes_value(true)
+        .required(true)
+        .validator(|input| FromStrParser::<u128>::default().validate(input))
+        .help("UDT amount (integer, no decimals)")
+}
+
+fn owner_address<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("owner-address")
+        .long("owner-address")
+        .takes_value(true)
+        .validator(|input| LabeledAddressParser.validate(input))
+        .help("Address (or label) of the token owner (issuer), the token is identified by its lock hash")
+}
+
+fn sudt_code_hash<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("sudt-code-hash")
+        .long("sudt-code-hash")
+        .takes_value(true)
+        .requires("sudt-cell-dep")
+        .validator(|input| FixedHashParser::<H256>::default().validate(input))
+        .help("Type hash of the simple UDT script (default: the mainnet/testnet deployment)")
+}
+
+fn sudt_cell_dep<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("sudt-cell-dep")
+        .long("sudt-cell-dep")
+        .takes_value(true)
+        .requires("sudt-code-hash")
+        .validator(|input| OutPointParser.validate(input))
+        .help("Code cell of the simple UDT script, format: {tx-hash}-{index}")
+}
+
+pub fn subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("sudt")
+        .about("Simple UDT (issue, transfer and query balance)")
+        .subcommands(vec![
+            SubCommand::with_name("issue")
+                .about("Issue UDT owned by the sender (the token is identified by the sender's lock hash)")
+                .arg(arg::privkey_path().required_unless("from-account"))
+                .arg(arg::from_account().required_unless("privkey-path"))
+                .arg(arg::to_address().help("Receiver address or label (default: sender)"))
+                .arg(amount("amount"))
+                .arg(arg::tx_fee().required(true))
+                .arg(arg::with_password())
+                .arg(arg::include_frozen())
+                .arg(arg::coin_selection())
+                .arg(sudt_code_hash())
+                .arg(sudt_cell_dep())
+                .arg(arg::wait_for_commit())
+                .arg(arg::yes())
+                .arg(arg::force_fee())
+                .arg(arg::override_policy()),
+            SubCommand::with_name("transfer")
+                .about("Transfer UDT, the change of both UDT and capacity goes back to the sender")
+                .arg(arg::privkey_path().required_unless("from-account"))
+                .arg(arg::from_account().required_unless("privkey-path"))
+                .arg(owner_address().required(true))
+                .arg(arg::to_address().required(true))
+                .arg(amount("udt-amount"))
+                .arg(arg::tx_fee().required(true))
+                .arg(arg::with_password())
+                .arg(arg::include_frozen())
+                .arg(arg::coin_selection())
+                .arg(sudt_code_hash())
+                .arg(sudt_cell_dep())
+                .arg(arg::wait_for_commit())
+                .arg(arg::yes())
+                .arg(arg::force_fee())
+                .arg(arg::override_policy()),
+            SubCommand::with_name("query")
+                .about("Sum UDT amounts of an address (by token)")
+                .arg(arg::address())
+                .arg(arg::pubkey())
+                .arg(arg::lock_arg())
+                .arg(owner_address())
+                .arg(sudt_code_hash())
+                .arg(sudt_cell_dep()),
+            SubCommand::with_name("holders")
+                .about("Sum UDT amounts of a token by holder (lock hash)")
+                .arg(owner_address().required(true))
+                .arg(sudt_code_hash())
+                .arg(sudt_cell_dep()),
+        ])
+}
+
+impl<'a> WalletSubCommand<'a> {
+    pub fn sudt(
+        &mut self,
+        matches: &ArgMatches,
+        format: OutputFormat,
+        color: bool,
+        debug: bool,
+    ) -> Result<String, String> {
+        match matches.subcommand() {
+            ("issue", Some(m)) => self.sudt_issue(m, format, color, debug),
+            ("transfer", Some(m)) => self.sudt_transfer(m, format, color, debug),
+            ("query", Some(m)) => self.sudt_query(m, format, color),
+            ("holders", Some(m)) => self.sudt_holders(m, format, color),
+            _ => Err(matches.usage().to_owned()),
+        }
+    }
+
+    fn sudt_issue(
+        &mut self,
+        m: &ArgMatches,
+        format: OutputFormat,
+        color: bool,
+        debug: bool,
+    ) -> Result<String, String> {
+        let (from_address, from_privkey, from_account) = privkey_or_account(m)?;
+        let amount: u128 = FromStrParser::<u128>::default().from_matches(m, "amount")?;
+        let tx_fee: u64 = CapacityParser.from_matches(m, "tx-fee")?;
+        let to_address: Address = LabeledAddressParser
+            .from_matches_opt(m, "to-address", false)?
+            .unwrap_or_else(|| from_address.clone());
+
+        let network_type = get_network_type(self.rpc_client)?;
+        check_to_address_prefix(m, network_type)?;
+        let config = self.sudt_config(m)?;
+        let genesis_info = self.genesis_info()?;
+        let owner_lock = genesis_info.lock_script(&from_address)?;
+        let type_script = config.type_script(&owner_lock.calc_script_hash());
+        let to_lock = genesis_info.lock_script(&to_address)?;
+        let capacity = udt_cell_capacity(&to_lock, &type_script);
+        let to_data = udt_amount_data(amount);
+
+        // For check index database is ready
+        self.check_index_ready()?;
+        let (infos, total_capacity) =
+            self.collect_live_cells(m, owner_lock, capacity + tx_fee, is_secp_cell)?;
+        if total_capacity < capacity + tx_fee {
+            return Err(tf(
+                "capacity-not-enough",
+                &[&from_address.to_string(network_type), &total_capacity],
+            ));
+        }
+        let policy = self.check_policy(
+            m,
+            SignKind::Transfer,
+            &from_address,
+            slice::from_ref(&to_address),
+            capacity,
+        )?;
+        let inputs = infos.iter().map(LiveCellInfo::input).collect::<Vec<_>>();
+        let change_address = self.change_address(from_account.as_ref(), &from_address)?;
+        let mut tx_args = TransferTransactionBuilder::new(
+            &from_address,
+            total_capacity,
+            &to_data,
+            &to_address,
+            capacity,
+            tx_fee,
+            inputs,
+        );
+        tx_args.set_change_address(change_address);
+        tx_args.set_change_policy(self.change_policy);
+        tx_args.set_output_type(type_script, Some(config.cell_dep()));
+        let transaction = self.sign_transfer(
+            m,
+            &mut tx_args,
+            &genesis_info,
+            from_privkey.as_ref(),
+            from_account.as_ref(),
+        )?;
+        self.send_or_unsigned(
+            m,
+            transaction,
+            from_account.as_ref(),
+            policy,
+            capacity,
+            format,
+            color,
+            debug,
+        )
+    }
+
+    fn sudt_transfer(
+        &mut self,
+        m: &ArgMatches,
+        format: OutputFormat,
+        color: bool,
+        debug: bool,
+    ) -> Result<String, String> {
+        let (from_address, from_privkey, from_account) = privkey_or_account(m)?;
+        let owner: Address = LabeledAddressParser.from_matches(m, "owner-address")?;
+        let to_address: Address = LabeledAddressParser.from_matches(m, "to-address")?;
+        let udt_amount: u128 = FromStrParser::<u128>::default().from_matches(m, "udt-amount")?;
+        let tx_fee: u64 = CapacityParser.from_matches(m, "tx-fee")?;
+
+        let network_type = get_network_type(self.rpc_client)?;
+        check_to_address_prefix(m, network_type)?;
+        let config = self.sudt_config(m)?;
+        let genesis_info = self.genesis_info()?;
+        let from_lock = genesis_info.lock_script(&from_address)?;
+        let to_lock = genesis_info.lock_script(&to_address)?;
+        let type_script = config.type_script(&genesis_info.lock_script(&owner)?.calc_script_hash());
+
+        // For check index database is ready
+        self.check_index_ready()?;
+        let (udt_infos, udt_total) =
+            self.collect_udt_cells(m, from_lock.clone(), &type_script, udt_amount)?;
+        if udt_total < udt_amount {
+            return Err(format!(
+                "UDT amount not enough: {} => {}",
+                from_address.to_string(network_type),
+                udt_total
+            ));
+        }
+        let udt_capacity: u64 = udt_infos.iter().map(|info| info.capacity).sum();
+        let to_capacity = udt_cell_capacity(&to_lock, &type_script);
+        let udt_change = udt_total - udt_amount;
+        let udt_change_capacity = if udt_change > 0 {
+            udt_cell_capacity(&from_lock, &type_script)
+        } else {
+            0
+        };
+        // The capacity of UDT inputs pays for the new UDT cells first
+        let target = (to_capacity + udt_change_capacity).saturating_sub(udt_capacity) + tx_fee;
+        let (infos, total_capacity) =
+            self.collect_live_cells(m, from_lock, target, is_secp_cell)?;
+        let from_capacity = udt_capacity + total_capacity;
+        if from_capacity < to_capacity + udt_change_capacity + tx_fee {
+            return Err(tf(
+                "capacity-not-enough",
+                &[&from_address.to_string(network_type), &from_capacity],
+            ));
+        }
+
+        let policy = self.check_policy(
+            m,
+            SignKind::Transfer,
+            &from_address,
+            slice::from_ref(&to_address),
+            to_capacity,
+        )?;
+        let inputs = udt_infos
+            .iter()
+            .chain(infos.iter())
+            .map(LiveCellInfo::input)
+            .collect::<Vec<_>>();
+        let to_data = udt_amount_data(udt_amount);
+        let change_address = self.change_address(from_account.as_ref(), &from_address)?;
+        let mut tx_args = TransferTransactionBuilder::new(
+            &from_address,
+            from_capacity,
+            &to_data,
+            &to_address,
+            to_capacity,
+            tx_fee,
+            inputs,
+        );
+        if udt_change > 0 {
+            tx_args.add_output(
+                from_address.clone(),
+                udt_change_capacity,
+                udt_amount_data(udt_change),
+            )?;
+        }
+        tx_args.set_change_address(change_address);
+        tx_args.set_change_policy(self.change_policy);
+        tx_args.set_output_type(type_script, Some(config.cell_dep()));
+        let transaction = self.sign_transfer(
+            m,
+            &mut tx_args,
+            &genesis_info,
+            from_privkey.as_ref(),
+            from_account.as_ref(),
+        )?;
+        self.send_or_unsigned(
+            m,
+            transaction,
+            from_account.as_ref(),
+            policy,
+            to_capacity,
+            format,
+            color,
+            debug,
+        )
+    }
+
+    fn sudt_query(
+        &mut self,
+        m: &ArgMatches,
+        format: OutputFormat,
+        color: bool,
+    ) -> Result<String, String> {
+        let address = get_address(m)?;
+        let owner: Option<Address> =
+            LabeledAddressParser.from_matches_opt(m, "owner-address", false)?;
+        let config = self.sudt_config(m)?;
+        let genesis_info = self.genesis_info()?;
+        let lock = genesis_info.lock_script(&address)?;
+        let owner_lock_hash = match owner {
+            Some(owner) => Some(genesis_info.lock_script(&owner)?.calc_script_hash()),
+            None => None,
+        };
+
+        let code_hash: H256 = config.code_hash().unpack();
+        let infos = self.live_cells(CellQuery::Lock(lock), None, |_, info| {
+            let is_udt = info
+                .type_hashes
+                .as_ref()
+                .map(|(cell_code_hash, _)| cell_code_hash == &code_hash)
+                .unwrap_or(false);
+            (false, is_udt)
+        })?;
+        // type hash => (owner lock hash, amount, cells, capacity)
+        let mut tokens: BTreeMap<H256, (H256, u128, usize, u64)> = BTreeMap::default();
+        for info in infos {
+            let (output, data) = match self.live_cell_with_data(&info)? {
+                Some(cell) => cell,
+                None => continue,
+            };
+            let type_script = match output.type_().to_opt() {
+                Some(script) if config.is_sudt_type(&script) => script,
+                _ => continue,
+            };
+            let token_owner = Byte32::from_slice(&type_script.args().raw_data())
+                .map_err(|err| format!("Invalid simple UDT args: {}", err))?;
+            if owner_lock_hash.is_some() && owner_lock_hash.as_ref() != Some(&token_owner) {
+                continue;
+            }
+            let amount = parse_udt_amount(&data)?;
+            let entry = tokens
+                .entry(type_script.calc_script_hash().unpack())
+                .or_insert_with(|| (token_owner.unpack(), 0, 0, 0));
+            entry.1 = entry
+                .1
+                .checked_add(amount)
+                .ok_or_else(|| "UDT amount overflow".to_owned())?;
+            entry.2 += 1;
+            entry.3 += info.capacity;
+        }
+        let resp = tokens
+            .into_iter()
+            .map(|(type_hash, (owner_lock_hash, amount, cells, capacity))| {
+                serde_json::json!({
+                    "type_hash": type_hash,
+                    "owner_lock_hash": owner_lock_hash,
+                    // u128 is out of the range of JSON number
+                    "amount": amount.to_string(),
+                    "cells": cells,
+                    "capacity": capacity,
+                })
+            })
+            .collect::<Vec<_>>();
+        Ok(serde_json::json!(resp).render(format, color))
+    }
+
+    fn sudt_holders(
+        &mut self,
+        m: &ArgMatches,
+        format: OutputFormat,
+        color: bool,
+    ) -> Result<String, String> {
+        let owner: Address = LabeledAddressParser.from_matches(m, "owner-address")?;
+        let config = self.sudt_config(m)?;
+        let owner_lock_hash = self.genesis_info()?.lock_script(&owner)?.calc_script_hash();
+        // Searched by the args prefix, so the tokens with extended args of the owner are included
+        let type_script = config.type_script(&owner_lock_hash);
+        let infos =
+            self.live_cells(CellQuery::TypeArgs(type_script), None, |_, _| (false, true))?;
+        // lock hash => (amount, cells, capacity)
+        let mut holders: BTreeMap<H256, (u128, usize, u64)> = BTreeMap::default();
+        for info in infos {
+            let data = match self.live_cell_with_data(&info)? {
+                Some((_, data)) => data,
+                None => continue,
+            };
+            let entry = holders.entry(info.lock_hash.clone()).or_default();
+            entry.0 = entry
+                .0
+                .checked_add(parse_udt_amount(&data)?)
+                .ok_or_else(|| "UDT amount overflow".to_owned())?;
+            entry.1 += 1;
+            entry.2 += info.capacity;
+        }
+        let resp = holders
+            .into_iter()
+            .map(|(lock_hash, (amount, cells, capacity))| {
+                serde_json::json!({
+                    "lock_hash": lock_hash,
+                    // u128 is out of the range of JSON number
+                    "amount": amount.to_string(),
+                    "cells": cells,
+                    "capacity": capacity,
+                })
+            })
+            .collect::<Vec<_>>();
+        Ok(serde_json::json!(resp).render(format, color))
+    }
+
+    /// The simple UDT deployment, by `--sudt-code-hash` and `--sudt-cell-dep` or the known
+    /// deployment of current network
+    fn sudt_config(&mut self, m: &ArgMatches) -> Result<SudtConfig, String> {
+        let code_hash: Option<H256> =
+            FixedHashParser::<H256>::default().from_matches_opt(m, "sudt-code-hash", false)?;
+        let dep_out_point: Option<OutPoint> =
+            OutPointParser.from_matches_opt(m, "sudt-cell-dep", false)?;
+        if let (Some(code_hash), Some(dep_out_point)) = (code_hash, dep_out_point) {
+            return Ok(SudtConfig::new(code_hash, dep_out_point));
+        }
+        SudtConfig::from_network(get_network_type(self.rpc_client)?).ok_or_else(|| {
+            "No known simple UDT deployment of this chain, use --sudt-code-hash and --sudt-cell-dep"
+                .to_owned()
+        })
+    }
+
+    // Collect the UDT cells (of the type script) of a lock for `target` amount
+    fn collect_udt_cells(
+        &mut self,
+        m: &ArgMatches,
+        lock: Script,
+        type_script: &Script,
+        target: u128,
+    ) -> Result<(Vec<LiveCellInfo>, u128), String> {
+        let type_hash: H256 = type_script.calc_script_hash().unpack();
+        let frozen = load_frozen(m)?;
+        let is_spent = self.spent_filter()?;
+        let candidates = self.live_cells(CellQuery::Lock(lock), None, |_, info| {
+            let is_udt = info
+                .type_hashes
+                .as_ref()
+                .map(|(_, script_hash)| script_hash == &type_hash)
+                .unwrap_or(false);
+            let is_frozen =
+                frozen.as_ref().map(|f| f.contains(info)).unwrap_or(false) || is_spent(info);
+            (false, is_udt && !is_frozen)
+        })?;
+        let mut total_amount: u128 = 0;
+        let mut infos = Vec::new();
+        for info in candidates {
+            if total_amount >= target {
+                break;
+            }
+            if let Some((_, data)) = self.live_cell_with_data(&info)? {
+                total_amount = total_amount
+                    .checked_add(parse_udt_amount(&data)?)
+                    .ok_or_else(|| "UDT amount overflow".to_owned())?;
+                infos.push(info);
+            }
+        }
+        Ok((infos, total_amount))
+    }
+
+    fn live_cell_with_data(
+        &mut self,
+        info: &LiveCellInfo,
+    ) -> Result<Option<(CellOutput, Bytes)>, String> {
+        let resp: CellWithStatus = self
+            .rpc_client
+            .get_live_cell(info.out_point().into(), true)
+            .call()
+            .map_err(|err| format!("get_live_cell by RPC call failed: {}", err))?;
+        if !is_live_cell(&resp) {
+            return Ok(None);
+        }
+        Ok(resp.cell.map(|cell| {
+            let data = cell
+                .data
+                .map(|data| data.content.into_bytes())
+                .unwrap_or_default();
+            (cell.output.into(), data)
+        }))
+    }
+}
@@ -0,0 +1,61 @@
+use ckb_sdk::MIN_SECP_CELL_CAPACITY;
+
+/// Split the cells into chained sweep transactions. Except the first one, every transaction
+/// also spends the output of the previous transaction, so it takes at most `max_inputs - 1`
+/// new cells.
+pub fn split_sweep_inputs<T>(cells: &[T], max_inputs: usize) -> Result<Vec<&[T]>, String> {
+    if max_inputs < 2 {
+        return Err("max-inputs must >= 2".to_owned());
+    }
+    let mut chunks = Vec::new();
+    let mut rest = cells;
+    let mut chunk_size = max_inputs;
+    while !rest.is_empty() {
+        let (chunk, others) = rest.split_at(chunk_size.min(rest.len()));
+        chunks.push(chunk);
+        rest = others;
+        chunk_size = max_inputs - 1;
+    }
+    Ok(chunks)
+}
+
+/// Max cells spent by `max_txs` chained transactions (see `split_sweep_inputs`)
+pub fn max_chained_cells(max_inputs: usize, max_txs: usize) -> usize {
+    if max_txs == 0 {
+        return 0;
+    }
+    max_inputs + (max_txs - 1) * max_inputs.saturating_sub(1)
+}
+
+/// The occupied capacity released by merging the cells into one secp256k1 cell
+pub fn reclaimed_occupied_capacity(cells: usize) -> u64 {
+    cells.saturating_sub(1) as u64 * *MIN_SECP_CELL_CAPACITY
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_sweep_inputs() {
+        let cells = (0..10).collect::<Vec<u32>>();
+        let chunks = split_sweep_inputs(&cells, 4).unwrap();
+        assert_eq!(chunks, vec![&cells[0..4], &cells[4..7], &cells[7..10]]);
+        let chunks = split_sweep_inputs(&cells, 10).unwrap();
+        assert_eq!(chunks, vec![&cells[..]]);
+        assert!(split_sweep_inputs::<u32>(&[], 4).unwrap().is_empty());
+        assert!(split_sweep_inputs(&cells, 1).is_err());
+    }
+
+    #[test]
+    fn test_top_up_limits() {
+        let cells = (0..10).collect::<Vec<u32>>();
+        let max_cells = max_chained_cells(4, 2);
+        assert_eq!(max_cells, 7);
+        assert_eq!(split_sweep_inputs(&cells[..max_cells], 4).unwrap().len(), 2);
+        assert_eq!(max_chained_cells(4, 0), 0);
+        assert_eq!(reclaimed_occupied_capacity(0), 0);
+        assert_eq!(reclaimed_occupied_capacity(1), 0);
+        assert_eq!(reclaimed_occupied_capacity(3), 2 * *MIN_SECP_CELL_CAPACITY);
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use ckb_types::H160;
+
+use super::arg_parser::{AddressParser, ArgParser, FixedHashParser};
+use super::other::get_ckb_cli_dir;
+
+/// Labels of lock-args (local accounts or others), stored in `~/.ckb-cli/address-book`
+pub struct AddressBook {
+    path: PathBuf,
+    // label => lock_arg
+    labels: BTreeMap<String, H160>,
+}
+
+impl AddressBook {
+    pub fn load(path: PathBuf) -> Result<AddressBook, String> {
+        let labels = if path.exists() {
+            let content = fs::read_to_string(&path).map_err(|err| err.to_string())?;
+            serde_json::from_str(&content)
+                .map_err(|err| format!("Parse address book {:?} failed: {}", path, err))?
+        } else {
+            BTreeMap::default()
+        };
+        Ok(AddressBook { path, labels })
+    }
+
+    pub fn load_default() -> Result<AddressBook, String> {
+        AddressBook::load(get_ckb_cli_dir().join("address-book"))
+    }
+
+    pub fn set(&mut self, label: &str, lock_arg: H160) -> Result<(), String> {
+        check_label(label)?;
+        self.labels.insert(label.to_owned(), lock_arg);
+        self.save()
+    }
+
+    pub fn remove(&mut self, label: &str) -> Result<H160, String> {
+        let lock_arg = self
+            .labels
+            .remove(label)
+            .ok_or_else(|| format!("Label not found: {}", label))?;
+        self.save()?;
+        Ok(lock_arg)
+    }
+
+    pub fn resolve(&self, label: &str) -> Option<&H160> {
+        self.labels.get(label)
+    }
+
+    /// Labels of a lock-arg joined by ", "
+    pub fn label_of(&self, lock_arg: &H160) -> Option<String> {
+        let labels = self
+            .labels
+            .iter()
+            .filter(|(_, value)| *value == lock_arg)
+            .map(|(label, _)| label.as_str())
+            .collect::<Vec<_>>();
+        if labels.is_empty() {
+            None
+        } else {
+            Some(labels.join(", "))
+        }
+    }
+
+    pub fn list(&self) -> &BTreeMap<String, H160> {
+        &self.labels
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(&self.labels).map_err(|err| err.to_string())?;
+        fs::write(&self.path, content).map_err(|err| err.to_string())
+    }
+}
+
+/// A label must not be confused with an address or a lock-arg
+fn check_label(label: &str) -> Result<(), String> {
+    if label.is_empty() || label.chars().any(char::is_whitespace) {
+        return Err(format!("Invalid label: {:?}", label));
+    }
+    if AddressParser.parse(label).is_ok() || FixedHashParser::<H160>::default().parse(label).is_ok()
+    {
+        return Err(format!(
+            "Label looks like an address or lock-arg: {}",
+            label
+        ));
+    }
+    Ok(())
+}
+
+/// Insert a "label" field into every object which has a labeled "lock_arg" or "address" field
+pub fn annotate_labels(value: &mut serde_json::Value, book: &AddressBook) {
+    match value {
+        serde_json::Value::Array(items) => {
+            for item in items {
+                annotate_labels(item, book);
+            }
+        }
+        serde_json::Value::Object(map) => {
+            let lock_arg_opt = map
+                .get("lock_arg")
+                .and_then(|value| value.as_str())
+                .and_then(|input| FixedHashParser::<H160>::default().parse(input).ok())
+                .or_else(|| {
+                    map.get("address")
+                        .and_then(|value| value.as_str())
+                        .and_then(|input| AddressParser.parse(input).ok())
+                        .map(|address| address.hash().clone())
+                });
+            if let Some(label) = lock_arg_opt.and_then(|lock_arg| book.label_of(&lock_arg)) {
+                map.insert("label".to_owned(), serde_json::Value::String(label));
+            }
+            for (_, item) in map.iter_mut() {
+                annotate_labels(item, book);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_label() {
+        assert!(check_label("alice").is_ok());
+        assert!(check_label("").is_err());
+        assert!(check_label("cold wallet").is_err());
+        assert!(check_label("0x0000000000000000000000000000000000000000").is_err());
+    }
+}
@@ -1,7 +1,9 @@
 use crate::utils::arg_parser::{
     AddressParser, ArgParser, CapacityParser, FilePathParser, FixedHashParser, FromStrParser,
-    HexParser, PrivkeyPathParser, PubkeyHexParser,
+    HexParser, LabeledAddressParser, LabeledLockArgParser, OutPointParser, PrivkeyPathParser,
+    PubkeyHexParser, ScriptParser,
 };
+use ckb_sdk::wallet::DerivationPath;
 use ckb_types::{H160, H256};
 use clap::Arg;
 
@@ -51,16 +53,67 @@ pub fn from_account<'a, 'b>() -> Arg<'a, 'b> {
     Arg::with_name("from-account")
         .long("from-account")
         .takes_value(true)
-        .validator(|input| FixedHashParser::<H160>::default().validate(input))
-        .help("The account's lock-arg (transfer from this account)")
+        .validator(|input| LabeledLockArgParser.validate(input))
+        .help("The account's lock-arg or label (transfer from this account)")
+}
+
+pub fn from_all<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("from-all")
+        .long("from-all")
+        .conflicts_with_all(&["from-account", "privkey-path", "from-ledger-account"])
+        .help("Transfer from all the accounts in keystore (in one transaction)")
+}
+
+pub fn from_ledger_account<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("from-ledger-account")
+        .long("from-ledger-account")
+        .takes_value(true)
+        .validator(|input| FromStrParser::<DerivationPath>::new().validate(input))
+        .help("The derivation path of ledger account (transfer from this account), example: m/44'/309'/0'/0/0")
 }
 
 pub fn to_address<'a, 'b>() -> Arg<'a, 'b> {
     Arg::with_name("to-address")
         .long("to-address")
         .takes_value(true)
-        .validator(|input| AddressParser.validate(input))
-        .help("Target address")
+        .validator(|input| LabeledAddressParser.validate(input))
+        .help("Target address or label in address book")
+}
+
+pub fn to_lock_script<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("to-lock-script")
+        .long("to-lock-script")
+        .takes_value(true)
+        .validator(|input| ScriptParser.validate(input))
+        .conflicts_with("to-address")
+        .help("Target lock script (JSON or hex of the serialized script) instead of --to-address, for multisig, anyone-can-pay or other locks")
+}
+
+pub fn to_acp_address<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("to-acp-address")
+        .long("to-acp-address")
+        .takes_value(true)
+        .validator(|input| LabeledAddressParser.validate(input))
+        .conflicts_with_all(&["to-address", "to-lock-script"])
+        .help("Top up the existing anyone-can-pay cell owned by this address (or label) instead of creating a new cell")
+}
+
+pub fn acp_code_hash<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("acp-code-hash")
+        .long("acp-code-hash")
+        .takes_value(true)
+        .requires("acp-cell-dep")
+        .validator(|input| FixedHashParser::<H256>::default().validate(input))
+        .help("Type hash of the anyone-can-pay lock (default: the mainnet/testnet deployment)")
+}
+
+pub fn acp_cell_dep<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("acp-cell-dep")
+        .long("acp-cell-dep")
+        .takes_value(true)
+        .requires("acp-code-hash")
+        .validator(|input| OutPointParser.validate(input))
+        .help("Dep group of the anyone-can-pay lock, format: {tx-hash}-{index}")
 }
 
 pub fn to_data<'a, 'b>() -> Arg<'a, 'b> {
@@ -95,12 +148,99 @@ pub fn tx_fee<'a, 'b>() -> Arg<'a, 'b> {
         .help("The transaction fee capacity (unit: CKB, format: 0.335)")
 }
 
+pub fn fee_rate<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("fee-rate")
+        .long("fee-rate")
+        .takes_value(true)
+        .conflicts_with("tx-fee")
+        .validator(|input| FromStrParser::<u64>::default().validate(input))
+        .help("Estimate the transaction fee by size of the signed transaction (unit: shannons/KB)")
+}
+
+pub fn wait_for_commit<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("wait-for-commit")
+        .long("wait-for-commit")
+        .takes_value(true)
+        .min_values(0)
+        .max_values(1)
+        // The optional value never takes the next argument
+        .require_equals(true)
+        .validator(|input| FromStrParser::<u64>::default().validate(input))
+        .help("Wait until the transaction is committed, fail if it is not committed before the timeout (--wait-for-commit=<seconds>, default: 600)")
+}
+
+pub fn yes<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("yes")
+        .long("yes")
+        .help("Send the transaction without the preview and confirmation (required when stdin is not a terminal)")
+}
+
+pub fn force_fee<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("force-fee")
+        .long("force-fee")
+        .help("Send the transaction even if the fee exceeds `max_tx_fee` or `max_fee_percent` of the transferred capacity (see config)")
+}
+
 pub fn with_password<'a, 'b>() -> Arg<'a, 'b> {
     Arg::with_name("with-password")
         .long("with-password")
         .help("Input password to unlock keystore account just for current transfer transaction")
 }
 
+pub fn override_policy<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("override-policy")
+        .long("override-policy")
+        .help("Sign even if the local signing policy is violated (confirmation required)")
+}
+
+pub fn include_frozen<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("include-frozen")
+        .long("include-frozen")
+        .help("Also spend the frozen cells (see: `wallet freeze`)")
+}
+
+pub fn allow_unconfirmed_inputs<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("allow-unconfirmed-inputs")
+        .long("allow-unconfirmed-inputs")
+        .help("Also spend the outputs of the transactions sent by wallet but not committed yet (like the change of the previous transfer)")
+}
+
+pub fn coin_selection<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("coin-selection")
+        .long("coin-selection")
+        .takes_value(true)
+        .possible_values(&["oldest-first", "largest-first"])
+        .default_value("oldest-first")
+        .help("The order to pick input cells: oldest-first (consolidate old cells) or largest-first (fewer inputs)")
+}
+
+pub fn since_epoch<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("since-epoch")
+        .long("since-epoch")
+        .takes_value(true)
+        .conflicts_with("since-timestamp")
+        .validator(|input| FromStrParser::<u64>::default().validate(input))
+        .help("Lock the target cell until this epoch (absolute epoch number)")
+}
+
+pub fn since_timestamp<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("since-timestamp")
+        .long("since-timestamp")
+        .takes_value(true)
+        .conflicts_with("since-epoch")
+        .validator(|input| FromStrParser::<u64>::default().validate(input))
+        .help("Lock the target cell until this unix timestamp (in seconds, compared with median time of recent blocks)")
+}
+
+pub fn out_points<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("out-point")
+        .long("out-point")
+        .takes_value(true)
+        .multiple(true)
+        .validator(|input| OutPointParser.validate(input))
+        .help("Out point of a cell, format: {tx-hash}-{index} (can be repeated)")
+}
+
 pub fn type_hash<'a, 'b>() -> Arg<'a, 'b> {
     Arg::with_name("type-hash")
         .long("type-hash")
@@ -117,6 +257,14 @@ pub fn code_hash<'a, 'b>() -> Arg<'a, 'b> {
         .help("The type script's code hash")
 }
 
+pub fn type_script<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("type-script")
+        .long("type-script")
+        .takes_value(true)
+        .validator(|input| ScriptParser.validate(input))
+        .help("The type script (JSON or hex of the serialized script), match the cells of the same code hash and hash type whose args start with its args (ordered by args then block number, --limit ends the search without next_from_block)")
+}
+
 pub fn live_cells_limit<'a, 'b>() -> Arg<'a, 'b> {
     Arg::with_name("limit")
         .long("limit")
@@ -128,7 +276,8 @@ pub fn live_cells_limit<'a, 'b>() -> Arg<'a, 'b> {
 
 pub fn from_block_number<'a, 'b>() -> Arg<'a, 'b> {
     Arg::with_name("from")
-        .long("from")
+        .long("from-block")
+        .alias("from")
         .takes_value(true)
         .validator(|input| FromStrParser::<u64>::default().validate(input))
         .help("From block number")
@@ -136,12 +285,29 @@ pub fn from_block_number<'a, 'b>() -> Arg<'a, 'b> {
 
 pub fn to_block_number<'a, 'b>() -> Arg<'a, 'b> {
     Arg::with_name("to")
-        .long("to")
+        .long("to-block")
+        .alias("to")
         .takes_value(true)
         .validator(|input| FromStrParser::<u64>::default().validate(input))
         .help("To block number")
 }
 
+pub fn page_limit<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("limit")
+        .long("limit")
+        .takes_value(true)
+        .validator(|input| FromStrParser::<usize>::default().validate(input))
+        .help("Only take this many cells, continue from `next_from_block` of the output")
+}
+
+pub fn min_capacity<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("min-capacity")
+        .long("min-capacity")
+        .takes_value(true)
+        .validator(|input| CapacityParser.validate(input))
+        .help("Skip the cells with less capacity (unit: CKB, format: 123.335)")
+}
+
 pub fn top_n<'a, 'b>() -> Arg<'a, 'b> {
     Arg::with_name("number")
         .short("n")
@@ -10,11 +10,18 @@ use ckb_sdk::{
     wallet::{zeroize_privkey, MasterPrivKey},
     Address, NetworkType, OldAddress, ONE_CKB,
 };
-use ckb_types::{packed::OutPoint, prelude::*, H160, H256};
+use ckb_types::{
+    packed::{OutPoint, Script},
+    prelude::*,
+    H160, H256,
+};
 use clap::ArgMatches;
 use faster_hex::hex_decode;
+use multiaddr::Multiaddr;
 use url::Url;
 
+use super::address_book::AddressBook;
+
 pub trait ArgParser<T> {
     fn parse(&self, input: &str) -> Result<T, String>;
 
@@ -327,6 +334,36 @@ impl ArgParser<Address> for AddressParser {
     }
 }
 
+/// Address or a label in address book (see: `account label`)
+pub struct LabeledAddressParser;
+
+impl ArgParser<Address> for LabeledAddressParser {
+    fn parse(&self, input: &str) -> Result<Address, String> {
+        AddressParser.parse(input).or_else(|err| {
+            match AddressBook::load_default()?.resolve(input) {
+                Some(lock_arg) => Address::from_lock_arg(lock_arg.as_bytes()),
+                None => Err(err),
+            }
+        })
+    }
+}
+
+/// Lock-arg or a label in address book (see: `account label`)
+pub struct LabeledLockArgParser;
+
+impl ArgParser<H160> for LabeledLockArgParser {
+    fn parse(&self, input: &str) -> Result<H160, String> {
+        FixedHashParser::<H160>::default()
+            .parse(input)
+            .or_else(|err| {
+                AddressBook::load_default()?
+                    .resolve(input)
+                    .cloned()
+                    .ok_or(err)
+            })
+    }
+}
+
 /// Default unit CKB format: xxx.xxxxx
 pub struct CapacityParser;
 
@@ -354,7 +391,6 @@ impl ArgParser<u64> for CapacityParser {
     }
 }
 
-#[allow(dead_code)]
 pub struct OutPointParser;
 
 impl ArgParser<OutPoint> for OutPointParser {
@@ -372,6 +408,23 @@ impl ArgParser<OutPoint> for OutPointParser {
     }
 }
 
+/// Script in JSON (`{"code_hash": "0x..", "hash_type": "type", "args": "0x.."}`) or hex of the
+/// molecule serialized bytes
+pub struct ScriptParser;
+
+impl ArgParser<Script> for ScriptParser {
+    fn parse(&self, input: &str) -> Result<Script, String> {
+        if input.trim_start().starts_with('{') {
+            let script: ckb_jsonrpc_types::Script = serde_json::from_str(input)
+                .map_err(|err| format!("Invalid script JSON: {}", err))?;
+            Ok(script.into())
+        } else {
+            let bytes = HexParser.parse(input)?;
+            Script::from_slice(&bytes).map_err(|err| format!("Invalid script bytes: {}", err))
+        }
+    }
+}
+
 pub struct DurationParser;
 
 impl ArgParser<Duration> for DurationParser {
@@ -398,6 +451,18 @@ impl ArgParser<Duration> for DurationParser {
     }
 }
 
+/// Peer id of a node (base58 encoded multihash of the public key)
+pub struct PeerIdParser;
+
+impl ArgParser<String> for PeerIdParser {
+    fn parse(&self, input: &str) -> Result<String, String> {
+        format!("/p2p/{}", input)
+            .parse::<Multiaddr>()
+            .map(|_| input.to_owned())
+            .map_err(|err| format!("Invalid peer id {}: {}", input, err))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use ckb_types::{h160, h256};
@@ -427,6 +492,17 @@ mod tests {
         assert!(HexParser.parse("abcdefghi").is_err());
     }
 
+    #[test]
+    fn test_script() {
+        let json = r#"{"code_hash": "0x9bd7e06f3ecf4be0f2fcd2188b23f1b9fcc88e5d4b65a8637b17723bbda3cce8", "hash_type": "type", "args": "0x0102"}"#;
+        let script = ScriptParser.parse(json).unwrap();
+        assert_eq!(script.args().raw_data().as_ref(), &[1u8, 2]);
+        let hex = format!("0x{}", faster_hex::hex_string(script.as_slice()).unwrap());
+        assert_eq!(ScriptParser.parse(&hex), Ok(script));
+        assert!(ScriptParser.parse("0x0102").is_err());
+        assert!(ScriptParser.parse(r#"{"code_hash": "0x00"}"#).is_err());
+    }
+
     #[test]
     fn test_fixed_hash() {
         assert_eq!(
@@ -484,6 +560,14 @@ mod tests {
             .is_err());
     }
 
+    #[test]
+    fn test_peer_id() {
+        let peer_id = "QmaQ7Fb1NDxW2cjVxqiAYWSJWAbPXMgeoN8vEYTJjc7U2F";
+        assert_eq!(PeerIdParser.parse(peer_id), Ok(peer_id.to_owned()));
+        assert!(PeerIdParser.parse("QmaQ7Fb1NDxW2cjVxqiAYWSJWA").is_err());
+        assert!(PeerIdParser.parse("0OIl").is_err());
+    }
+
     #[test]
     fn test_capacity() {
         assert_eq!(CapacityParser.parse("12345"), Ok(12345 * ONE_CKB));
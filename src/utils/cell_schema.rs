@@ -0,0 +1,189 @@
+use std::convert::TryInto;
+use std::fs;
+use std::path::PathBuf;
+
+use ckb_types::H256;
+use faster_hex::hex_string;
+use serde_derive::Deserialize;
+
+use super::other::get_ckb_cli_dir;
+
+/// Layout of the cell data of a type script, one TOML file per schema in
+/// `~/.ckb-cli/cell-schemas`:
+///
+/// ```toml
+/// name = "sudt"
+/// code_hash = "0x5e7a36a77e68eecc013dfa2fe6a23f3b6c344b04005808694ae6dd45eea4cfd5"
+///
+/// [[fields]]
+/// name = "amount"
+/// type = "u128"
+/// offset = 0
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct CellSchema {
+    pub name: String,
+    /// Code hash of the type script
+    pub code_hash: H256,
+    pub fields: Vec<FieldSchema>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FieldSchema {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: FieldKind,
+    pub offset: usize,
+    /// Length of `hex`/`utf8` field, to the end of data by default
+    pub length: Option<usize>,
+}
+
+/// Integers are little endian
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldKind {
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    Hex,
+    Utf8,
+}
+
+impl FieldKind {
+    fn size(self) -> Option<usize> {
+        match self {
+            FieldKind::U8 => Some(1),
+            FieldKind::U16 => Some(2),
+            FieldKind::U32 => Some(4),
+            FieldKind::U64 => Some(8),
+            FieldKind::U128 => Some(16),
+            FieldKind::Hex | FieldKind::Utf8 => None,
+        }
+    }
+}
+
+impl FieldSchema {
+    fn decode(&self, data: &[u8]) -> Result<serde_json::Value, String> {
+        let end = match (self.kind.size(), self.length) {
+            (Some(size), _) | (None, Some(size)) => self.offset + size,
+            (None, None) => data.len(),
+        };
+        let bytes = data.get(self.offset..end).ok_or_else(|| {
+            format!(
+                "Field {} out of range: {}..{} (data length: {})",
+                self.name,
+                self.offset,
+                end,
+                data.len()
+            )
+        })?;
+        let value = match self.kind {
+            FieldKind::U8 => serde_json::json!(bytes[0]),
+            FieldKind::U16 => serde_json::json!(u16::from_le_bytes(bytes.try_into().unwrap())),
+            FieldKind::U32 => serde_json::json!(u32::from_le_bytes(bytes.try_into().unwrap())),
+            FieldKind::U64 => serde_json::json!(u64::from_le_bytes(bytes.try_into().unwrap())),
+            // u128 is out of the range of JSON number
+            FieldKind::U128 => {
+                serde_json::json!(u128::from_le_bytes(bytes.try_into().unwrap()).to_string())
+            }
+            FieldKind::Hex => serde_json::json!(format!("0x{}", hex_string(bytes).unwrap())),
+            FieldKind::Utf8 => serde_json::json!(String::from_utf8_lossy(bytes)),
+        };
+        Ok(value)
+    }
+}
+
+impl CellSchema {
+    pub fn parse(content: &str) -> Result<CellSchema, String> {
+        toml::from_str(content).map_err(|err| err.to_string())
+    }
+
+    /// The named fields of cell data (with the schema name)
+    pub fn decode(&self, data: &[u8]) -> Result<serde_json::Value, String> {
+        let mut fields = serde_json::Map::new();
+        fields.insert("schema".to_owned(), serde_json::json!(self.name));
+        for field in &self.fields {
+            fields.insert(field.name.clone(), field.decode(data)?);
+        }
+        Ok(serde_json::Value::Object(fields))
+    }
+}
+
+pub struct CellSchemas {
+    schemas: Vec<CellSchema>,
+}
+
+impl CellSchemas {
+    /// Load all the `*.toml` files of a directory (empty if not exists)
+    pub fn load(dir: PathBuf) -> Result<CellSchemas, String> {
+        let mut schemas = Vec::new();
+        if dir.exists() {
+            let mut paths = fs::read_dir(&dir)
+                .map_err(|err| err.to_string())?
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().map(|ext| ext == "toml").unwrap_or(false))
+                .collect::<Vec<_>>();
+            paths.sort();
+            for path in paths {
+                let content = fs::read_to_string(&path).map_err(|err| err.to_string())?;
+                let schema = CellSchema::parse(&content)
+                    .map_err(|err| format!("Parse cell schema {:?} failed: {}", path, err))?;
+                schemas.push(schema);
+            }
+        }
+        Ok(CellSchemas { schemas })
+    }
+
+    pub fn load_default() -> Result<CellSchemas, String> {
+        CellSchemas::load(get_ckb_cli_dir().join("cell-schemas"))
+    }
+
+    /// The schema of a type script code hash (the first one if more than one)
+    pub fn find(&self, code_hash: &H256) -> Option<&CellSchema> {
+        self.schemas
+            .iter()
+            .find(|schema| &schema.code_hash == code_hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        let schema = CellSchema::parse(
+            r#"
+name = "nft"
+code_hash = "0x5e7a36a77e68eecc013dfa2fe6a23f3b6c344b04005808694ae6dd45eea4cfd5"
+
+[[fields]]
+name = "amount"
+type = "u128"
+offset = 0
+
+[[fields]]
+name = "id"
+type = "u32"
+offset = 16
+
+[[fields]]
+name = "title"
+type = "utf8"
+offset = 20
+"#,
+        )
+        .unwrap();
+        let mut data = 1000u128.to_le_bytes().to_vec();
+        data.extend_from_slice(&7u32.to_le_bytes());
+        data.extend_from_slice(b"ckb");
+        assert_eq!(
+            schema.decode(&data).unwrap(),
+            serde_json::json!({"schema": "nft", "amount": "1000", "id": 7, "title": "ckb"})
+        );
+        assert!(schema.decode(&data[..18]).is_err());
+        assert!(CellSchema::parse("name = \"x\"").is_err());
+    }
+}
@@ -4,12 +4,16 @@ use std::iter;
 use std::sync::Arc;
 
 use ansi_term::Colour::{Green, Red};
-use rustyline::completion::{extract_word, Completer, Pair};
+use ckb_sdk::wallet::KeyStore;
+use ckb_util::Mutex;
+use rustyline::completion::{extract_word, Completer, FilenameCompleter, Pair};
 use rustyline::error::ReadlineError;
 use rustyline::highlight::Highlighter;
 use rustyline::hint::Hinter;
 use rustyline::{CompletionType, Context, Helper};
 
+use super::address_book::AddressBook;
+
 #[cfg(unix)]
 static DEFAULT_BREAK_CHARS: [u8; 18] = [
     b' ', b'\t', b'\n', b'"', b'\\', b'\'', b'`', b'@', b'$', b'>', b'<', b'=', b';', b'|', b'&',
@@ -32,12 +36,69 @@ where
     'a: 'b,
 {
     clap_app: Arc<clap::App<'a, 'b>>,
+    // Complete `--from-account` by the accounts of the keystore
+    key_store: Option<Mutex<KeyStore>>,
+    file_completer: FilenameCompleter,
 }
 
 impl<'a, 'b> CkbCompleter<'a, 'b> {
     pub fn new(clap_app: clap::App<'a, 'b>) -> Self {
         CkbCompleter {
             clap_app: Arc::new(clap_app),
+            key_store: None,
+            file_completer: FilenameCompleter::new(),
+        }
+    }
+
+    pub fn with_key_store(mut self, key_store: KeyStore) -> Self {
+        self.key_store = Some(Mutex::new(key_store));
+        self
+    }
+
+    /// Candidates of an option value: lock-args (and labels) of the keystore accounts for
+    /// `--from-account`, labels of the address book for `--to-address`. `None` if the option
+    /// is not completed by value.
+    fn value_completions(&self, option: &str) -> Option<Vec<(String, String)>> {
+        let address_book = AddressBook::load_default().ok();
+        match option {
+            "--from-account" => {
+                let mut lock_args = self
+                    .key_store
+                    .as_ref()
+                    .map(|key_store| {
+                        key_store
+                            .lock()
+                            .get_accounts()
+                            .keys()
+                            .cloned()
+                            .collect::<Vec<_>>()
+                    })
+                    .unwrap_or_default();
+                lock_args.sort();
+                let completions = lock_args
+                    .into_iter()
+                    .flat_map(|lock_arg| {
+                        let hex = format!("{:#x}", lock_arg);
+                        let label = address_book
+                            .as_ref()
+                            .and_then(|book| book.label_of(&lock_arg));
+                        iter::once((hex.clone(), hex))
+                            .chain(label.map(|label| (label.clone(), label)))
+                    })
+                    .collect();
+                Some(completions)
+            }
+            "--to-address" => Some(
+                address_book
+                    .map(|book| {
+                        book.list()
+                            .keys()
+                            .map(|label| (label.clone(), label.clone()))
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            ),
+            _ => None,
         }
     }
 
@@ -144,11 +205,34 @@ impl<'a, 'b> Completer for CkbCompleter<'a, 'b> {
         &self,
         line: &str,
         pos: usize,
-        _context: &Context,
+        context: &Context,
     ) -> Result<(usize, Vec<Pair>), ReadlineError> {
         let (start, word) = extract_word(line, pos, ESCAPE_CHAR, &DEFAULT_BREAK_CHARS);
         let args = shell_words::split(&line[..pos]).unwrap();
         let word_lower = word.to_lowercase();
+
+        // Complete the value of the option before current word
+        let option = if word.is_empty() {
+            args.last()
+        } else {
+            args.iter().rev().nth(1)
+        };
+        if let Some(option) = option {
+            if option == "--privkey-path" || option.ends_with("-file") {
+                return self.file_completer.complete(line, pos, context);
+            }
+            if let Some(completions) = self.value_completions(option) {
+                let pairs = completions
+                    .into_iter()
+                    .filter(|(_, replacement)| replacement.to_lowercase().starts_with(&word_lower))
+                    .map(|(display, replacement)| Pair {
+                        display,
+                        replacement,
+                    })
+                    .collect::<Vec<_>>();
+                return Ok((start, pairs));
+            }
+        }
         let tmp_pair = Self::find_subcommand(
             self.clap_app.clone(),
             args.iter().map(String::as_str).peekable(),
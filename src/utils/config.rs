@@ -1,26 +1,92 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
+use std::fmt;
 use std::ops::Deref;
 use std::path::PathBuf;
 use std::sync::Arc;
 
 use ansi_term::Colour::Yellow;
+use ckb_sdk::{set_retry_policy, ChangePolicy, RetryPolicy};
 use ckb_util::RwLock;
 use regex::{Captures, Regex};
 
-use crate::subcommands::wallet::IndexThreadState;
+use crate::subcommands::wallet::{FeeGuard, IndexThreadState};
+use crate::utils::arg_parser::{AddressParser, ArgParser, UrlParser};
+use crate::utils::other::mask_url_password;
 use crate::utils::printer::{OutputFormat, Printable};
 
 const DEFAULT_JSONRPC_URL: &str = "http://127.0.0.1:8114";
 
+/// Where the change output of a transaction goes
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChangeStrategy {
+    /// Send back to the sender address
+    Reuse,
+    /// Send to the first empty BIP-44 change address (m/44'/309'/0'/1/i) of the sender account
+    Derive,
+    /// Always send to a fixed address
+    Fixed(String),
+}
+
+impl Default for ChangeStrategy {
+    fn default() -> ChangeStrategy {
+        ChangeStrategy::Reuse
+    }
+}
+
+impl ChangeStrategy {
+    pub fn from_str(input: &str) -> Result<ChangeStrategy, String> {
+        match input {
+            "reuse" => Ok(ChangeStrategy::Reuse),
+            "derive" => Ok(ChangeStrategy::Derive),
+            _ if input.starts_with("fixed:") => {
+                let address = &input["fixed:".len()..];
+                AddressParser.parse(address)?;
+                Ok(ChangeStrategy::Fixed(address.to_owned()))
+            }
+            _ => Err(format!(
+                "Invalid change strategy: {}, expected: reuse|derive|fixed:<address>",
+                input
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ChangeStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            ChangeStrategy::Reuse => write!(f, "reuse"),
+            ChangeStrategy::Derive => write!(f, "derive"),
+            ChangeStrategy::Fixed(address) => write!(f, "fixed:{}", address),
+        }
+    }
+}
+
+/// Commands kept in the history file of interactive mode
+pub const DEFAULT_HISTORY_SIZE: usize = 1000;
+
 pub struct GlobalConfig {
     url: Option<String>,
+    // Named RPC urls (like mainnet/testnet/dev) and the selected one
+    nodes: BTreeMap<String, String>,
+    node: Option<String>,
     color: bool,
     debug: bool,
+    timings: bool,
     output_format: OutputFormat,
     path: PathBuf,
     completion_style: bool,
     edit_style: bool,
+    history_size: usize,
+    change_strategy: ChangeStrategy,
+    change_policy: ChangePolicy,
+    retry_policy: RetryPolicy,
+    // Search the live cells by this ckb-indexer instead of the local index database
+    indexer_url: Option<String>,
+    // The profile of `~/.ckb-cli/config.toml` in use
+    profile: Option<String>,
+    default_fee_rate: Option<u64>,
+    fee_guard: FeeGuard,
     env_variable: HashMap<String, serde_json::Value>,
     index_state: Arc<RwLock<IndexThreadState>>,
 }
@@ -29,12 +95,23 @@ impl GlobalConfig {
     pub fn new(url: Option<String>, index_state: Arc<RwLock<IndexThreadState>>) -> Self {
         GlobalConfig {
             url,
+            nodes: BTreeMap::new(),
+            node: None,
             color: true,
             debug: false,
+            timings: false,
             output_format: OutputFormat::Yaml,
             path: env::current_dir().unwrap(),
             completion_style: true,
             edit_style: true,
+            history_size: DEFAULT_HISTORY_SIZE,
+            change_strategy: ChangeStrategy::default(),
+            change_policy: ChangePolicy::default(),
+            retry_policy: RetryPolicy::default(),
+            indexer_url: None,
+            profile: None,
+            default_fee_rate: None,
+            fee_guard: FeeGuard::default(),
             env_variable: HashMap::new(),
             index_state,
         }
@@ -97,7 +174,16 @@ impl GlobalConfig {
                     })
                     .next()
                     .unwrap_or_default(),
-                None => String::new(),
+                // `$NAME` is kept as it is when the variable is not set
+                None => match caps.name("name") {
+                    Some(name) => match self.env_variable.get(name.as_str()) {
+                        Some(serde_json::Value::String(s)) => s.to_owned(),
+                        Some(serde_json::Value::Number(n)) => n.to_string(),
+                        Some(_) => String::new(),
+                        None => caps[0].to_owned(),
+                    },
+                    None => String::new(),
+                },
             })
             .into_owned()
     }
@@ -110,6 +196,54 @@ impl GlobalConfig {
         }
     }
 
+    /// Add (or replace) a named node, input format: <name>=<url>
+    pub fn add_node(&mut self, input: &str) -> Result<(), String> {
+        let (name, url) = parse_node(input)?;
+        self.nodes.insert(name, url);
+        Ok(())
+    }
+
+    /// Use the url of a named node
+    pub fn select_node(&mut self, name: &str) -> Result<(), String> {
+        let url = self.nodes.get(name).cloned().ok_or_else(|| {
+            format!(
+                "Node not found: {}, configured nodes: [{}]",
+                name,
+                self.nodes.keys().cloned().collect::<Vec<_>>().join(", ")
+            )
+        })?;
+        self.set_url(url);
+        self.node = Some(name.to_owned());
+        Ok(())
+    }
+
+    pub fn set_nodes(&mut self, nodes: BTreeMap<String, String>) {
+        self.nodes = nodes;
+    }
+
+    pub fn nodes(&self) -> &BTreeMap<String, String> {
+        &self.nodes
+    }
+
+    /// The selected node name, `None` when the url is given directly
+    pub fn node(&self) -> Option<&str> {
+        self.node.as_ref().map(String::as_str)
+    }
+
+    pub fn remove_node(&mut self, name: &str) -> Result<(), String> {
+        self.nodes
+            .remove(name)
+            .ok_or_else(|| format!("Node not found: {}", name))?;
+        if self.node() == Some(name) {
+            self.node = None;
+        }
+        Ok(())
+    }
+
+    pub fn clear_node(&mut self) {
+        self.node = None;
+    }
+
     pub fn get_url(&self) -> &str {
         &self
             .url
@@ -126,6 +260,10 @@ impl GlobalConfig {
         self.debug = !self.debug;
     }
 
+    pub fn switch_timings(&mut self) {
+        self.timings = !self.timings;
+    }
+
     pub fn switch_completion_style(&mut self) {
         self.completion_style = !self.completion_style;
     }
@@ -142,6 +280,10 @@ impl GlobalConfig {
         self.debug = value;
     }
 
+    pub fn set_timings(&mut self, value: bool) {
+        self.timings = value;
+    }
+
     pub fn set_output_format(&mut self, value: OutputFormat) {
         self.output_format = value;
     }
@@ -154,6 +296,40 @@ impl GlobalConfig {
         self.edit_style = value;
     }
 
+    pub fn set_history_size(&mut self, value: usize) {
+        self.history_size = value;
+    }
+
+    pub fn set_change_strategy(&mut self, value: ChangeStrategy) {
+        self.change_strategy = value;
+    }
+
+    pub fn set_change_policy(&mut self, value: ChangePolicy) {
+        self.change_policy = value;
+    }
+
+    /// Also used by the RPC clients created after this call (including the index thread)
+    pub fn set_retry_policy(&mut self, value: RetryPolicy) {
+        set_retry_policy(value.clone());
+        self.retry_policy = value;
+    }
+
+    pub fn set_indexer_url(&mut self, value: Option<String>) {
+        self.indexer_url = value;
+    }
+
+    pub fn set_profile(&mut self, value: Option<String>) {
+        self.profile = value;
+    }
+
+    pub fn set_default_fee_rate(&mut self, value: Option<u64>) {
+        self.default_fee_rate = value;
+    }
+
+    pub fn set_fee_guard(&mut self, value: FeeGuard) {
+        self.fee_guard = value;
+    }
+
     pub fn color(&self) -> bool {
         self.color
     }
@@ -162,6 +338,10 @@ impl GlobalConfig {
         self.debug
     }
 
+    pub fn timings(&self) -> bool {
+        self.timings
+    }
+
     pub fn output_format(&self) -> OutputFormat {
         self.output_format
     }
@@ -174,10 +354,43 @@ impl GlobalConfig {
         self.edit_style
     }
 
+    pub fn history_size(&self) -> usize {
+        self.history_size
+    }
+
+    pub fn change_strategy(&self) -> &ChangeStrategy {
+        &self.change_strategy
+    }
+
+    pub fn change_policy(&self) -> ChangePolicy {
+        self.change_policy
+    }
+
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+
+    pub fn indexer_url(&self) -> Option<&str> {
+        self.indexer_url.as_ref().map(String::as_str)
+    }
+
+    pub fn profile(&self) -> Option<&str> {
+        self.profile.as_ref().map(String::as_str)
+    }
+
+    pub fn default_fee_rate(&self) -> Option<u64> {
+        self.default_fee_rate
+    }
+
+    pub fn fee_guard(&self) -> FeeGuard {
+        self.fee_guard
+    }
+
     pub fn print(&self) {
         let path = self.path.to_string_lossy();
         let color = self.color.to_string();
         let debug = self.debug.to_string();
+        let timings = self.timings.to_string();
         let output_format = self.output_format.to_string();
         let completion_style = if self.completion_style {
             "List"
@@ -185,18 +398,56 @@ impl GlobalConfig {
             "Circular"
         };
         let edit_style = if self.edit_style { "Emacs" } else { "Vi" };
+        let history_size = self.history_size.to_string();
+        let change_strategy = self.change_strategy.to_string();
+        let min_change_capacity = self.change_policy.min_change_capacity().to_string();
+        let dust_policy = self.change_policy.dust().to_string();
         let index_state = self.index_state.read().to_string();
+        let indexer_url = self
+            .indexer_url()
+            .map(mask_url_password)
+            .unwrap_or_else(|| "-".to_owned());
+        let url = mask_url_password(self.get_url());
+        let node = self.node().unwrap_or("-");
+        let profile = self.profile().unwrap_or("-");
+        let default_fee_rate = self
+            .default_fee_rate
+            .map(|fee_rate| fee_rate.to_string())
+            .unwrap_or_else(|| "-".to_owned());
+        let max_tx_fee = self.fee_guard.max_fee.to_string();
+        let max_fee_percent = format!("{}%", self.fee_guard.max_fee_percent);
+        let rpc_retries = format!(
+            "{} (failover: {})",
+            self.retry_policy.max_retries,
+            self.retry_policy
+                .failover_urls
+                .get(self.get_url())
+                .map(String::as_str)
+                .unwrap_or("-")
+        );
         let version = crate::get_version();
         let version_long = version.long();
         let values = [
             ("ckb-cli version", version_long.as_str()),
-            ("url", self.get_url()),
+            ("url", url.as_str()),
+            ("node", node),
+            ("profile", profile),
+            ("rpc retries", rpc_retries.as_str()),
             ("pwd", path.deref()),
             ("color", color.as_str()),
             ("debug", debug.as_str()),
+            ("timings", timings.as_str()),
             ("output format", output_format.as_str()),
             ("completion style", completion_style),
             ("edit style", edit_style),
+            ("history size", history_size.as_str()),
+            ("change strategy", change_strategy.as_str()),
+            ("min change capacity", min_change_capacity.as_str()),
+            ("dust policy", dust_policy.as_str()),
+            ("default fee rate", default_fee_rate.as_str()),
+            ("max tx fee", max_tx_fee.as_str()),
+            ("max fee percent", max_fee_percent.as_str()),
+            ("indexer url", indexer_url.as_str()),
             ("index db state", index_state.as_str()),
         ];
 
@@ -221,6 +472,18 @@ impl GlobalConfig {
     }
 }
 
+/// Parse `<name>=<url>` of a named node
+pub fn parse_node(input: &str) -> Result<(String, String), String> {
+    let mut parts = input.splitn(2, '=');
+    match (parts.next(), parts.next()) {
+        (Some(name), Some(url)) if !name.trim().is_empty() && !url.trim().is_empty() => {
+            UrlParser.parse(url.trim())?;
+            Ok((name.trim().to_owned(), url.trim().to_owned()))
+        }
+        _ => Err(format!("Invalid node: {}, format: <name>=<url>", input)),
+    }
+}
+
 #[derive(Clone)]
 pub enum KV<'a> {
     Value(Option<&'a serde_json::Value>),
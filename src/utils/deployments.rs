@@ -0,0 +1,66 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use ckb_types::H256;
+use serde_derive::{Deserialize, Serialize};
+
+use super::other::get_ckb_cli_dir;
+
+/// A script binary deployed by `wallet deploy`
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Deployment {
+    pub tx_hash: H256,
+    pub index: u32,
+    /// Code hash for `hash_type: data` (blake2b of the binary)
+    pub data_hash: H256,
+    /// Args of the type id script (only when deployed with `--type-id`)
+    pub type_id_args: Option<H256>,
+    /// Code hash for `hash_type: type` (only when deployed with `--type-id`)
+    pub type_hash: Option<H256>,
+    pub binary_path: String,
+    pub deployed_at: u64,
+}
+
+/// Deployments by name, stored in `~/.ckb-cli/deployments`
+pub struct Deployments {
+    path: PathBuf,
+    deployments: BTreeMap<String, Deployment>,
+}
+
+impl Deployments {
+    pub fn load(path: PathBuf) -> Result<Deployments, String> {
+        let deployments = if path.exists() {
+            let content = fs::read_to_string(&path).map_err(|err| err.to_string())?;
+            serde_json::from_str(&content)
+                .map_err(|err| format!("Parse deployments {:?} failed: {}", path, err))?
+        } else {
+            BTreeMap::default()
+        };
+        Ok(Deployments { path, deployments })
+    }
+
+    pub fn load_default() -> Result<Deployments, String> {
+        Deployments::load(get_ckb_cli_dir().join("deployments"))
+    }
+
+    /// Record a deployment, the old one of the same name is replaced
+    pub fn add(&mut self, name: String, deployment: Deployment) -> Result<(), String> {
+        self.deployments.insert(name, deployment);
+        self.save()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Deployment> {
+        self.deployments.get(name)
+    }
+
+    pub fn list(&self) -> &BTreeMap<String, Deployment> {
+        &self.deployments
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let content =
+            serde_json::to_string_pretty(&self.deployments).map_err(|err| err.to_string())?;
+        fs::write(&self.path, content).map_err(|err| err.to_string())
+    }
+}
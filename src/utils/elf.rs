@@ -0,0 +1,95 @@
+use goblin::elf::{program_header::PT_LOAD, sym::STT_FUNC, Elf};
+
+/// The symbol which contains (or is nearest below) a program counter
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SymbolInfo {
+    pub name: String,
+    pub address: u64,
+    pub offset: u64,
+}
+
+/// Map a program counter to the function symbol in a RISC-V ELF binary. Returns `Ok(None)` when
+/// the binary is stripped or no symbol is below the pc.
+pub fn find_symbol(binary: &[u8], pc: u64) -> Result<Option<SymbolInfo>, String> {
+    let elf = Elf::parse(binary).map_err(|err| format!("Parse ELF failed: {}", err))?;
+    let mut nearest: Option<SymbolInfo> = None;
+    for sym in elf.syms.iter() {
+        if sym.st_type() != STT_FUNC || sym.st_value > pc {
+            continue;
+        }
+        let name = match elf.strtab.get(sym.st_name).and_then(Result::ok) {
+            Some(name) if !name.is_empty() => name,
+            _ => continue,
+        };
+        let offset = pc - sym.st_value;
+        // Exact hit of a sized function symbol
+        if sym.st_size > 0 && offset < sym.st_size {
+            return Ok(Some(SymbolInfo {
+                name: name.to_owned(),
+                address: sym.st_value,
+                offset,
+            }));
+        }
+        if nearest
+            .as_ref()
+            .map(|info| info.address < sym.st_value)
+            .unwrap_or(true)
+        {
+            nearest = Some(SymbolInfo {
+                name: name.to_owned(),
+                address: sym.st_value,
+                offset,
+            });
+        }
+    }
+    Ok(nearest)
+}
+
+/// The memory occupied by the loadable segments of a RISC-V ELF binary once loaded into the VM,
+/// which is the lower bound of the memory a script uses (stack and heap are not included).
+pub fn loaded_size(binary: &[u8]) -> Result<u64, String> {
+    let elf = Elf::parse(binary).map_err(|err| format!("Parse ELF failed: {}", err))?;
+    Ok(elf
+        .program_headers
+        .iter()
+        .filter(|header| header.p_type == PT_LOAD)
+        .map(|header| header.p_memsz)
+        .sum())
+}
+
+/// Extract program counter from verification error message, example: "... at pc 0x12a4"
+pub fn extract_pc(message: &str) -> Option<u64> {
+    let lower = message.to_lowercase();
+    let start = lower.find("pc")?;
+    let rest = lower[start + 2..].trim_start_matches(|c: char| c == ':' || c == '=' || c == ' ');
+    let hex = if rest.starts_with("0x") {
+        &rest[2..]
+    } else {
+        rest
+    };
+    let end = hex
+        .find(|c: char| !c.is_ascii_hexdigit())
+        .unwrap_or_else(|| hex.len());
+    u64::from_str_radix(&hex[..end], 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_pc() {
+        assert_eq!(extract_pc("exit code -1 at pc 0x12a4"), Some(0x12a4));
+        assert_eq!(
+            extract_pc("VMError(InvalidInstruction), pc: 0x10078"),
+            Some(0x10078)
+        );
+        assert_eq!(extract_pc("ValidationFailure(-1)"), None);
+    }
+
+    #[test]
+    fn test_find_symbol_invalid_elf() {
+        assert!(find_symbol(b"not an elf", 0x100).is_err());
+        assert!(loaded_size(b"not an elf").is_err());
+    }
+}
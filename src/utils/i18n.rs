@@ -0,0 +1,220 @@
+//! Message catalog of the user-facing strings (prompts, warnings and common errors). The
+//! language is selected by `--lang`, or `CKB_CLI_LANG`/`LANG` environment variable.
+
+use std::env;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Lang {
+    En = 0,
+    ZhCn = 1,
+}
+
+impl Lang {
+    pub fn from_str(input: &str) -> Result<Lang, String> {
+        match input.to_lowercase().replace('_', "-").as_str() {
+            "en" | "en-us" => Ok(Lang::En),
+            "zh" | "zh-cn" => Ok(Lang::ZhCn),
+            _ => Err(format!(
+                "Unsupported language: {}, expected: en, zh-CN",
+                input
+            )),
+        }
+    }
+
+    /// `CKB_CLI_LANG` first, then the locale in `LANG` (like `zh_CN.UTF-8`), English by default
+    pub fn from_env() -> Lang {
+        if let Ok(lang) = env::var("CKB_CLI_LANG").map(|value| Lang::from_str(&value)) {
+            return lang.unwrap_or(Lang::En);
+        }
+        env::var("LANG")
+            .ok()
+            .and_then(|value| value.split('.').next().map(Lang::from_str))
+            .and_then(Result::ok)
+            .unwrap_or(Lang::En)
+    }
+}
+
+impl fmt::Display for Lang {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Lang::En => write!(f, "en"),
+            Lang::ZhCn => write!(f, "zh-CN"),
+        }
+    }
+}
+
+static CURRENT_LANG: AtomicUsize = AtomicUsize::new(Lang::En as usize);
+
+pub fn set_lang(lang: Lang) {
+    CURRENT_LANG.store(lang as usize, Ordering::SeqCst);
+}
+
+pub fn lang() -> Lang {
+    match CURRENT_LANG.load(Ordering::SeqCst) {
+        1 => Lang::ZhCn,
+        _ => Lang::En,
+    }
+}
+
+// (key, en, zh-CN), `{}` are the placeholders filled by `tf`
+const MESSAGES: &[(&str, &str, &str)] = &[
+    ("password", "Password", "密码"),
+    ("repeat-password", "Repeat password", "重复密码"),
+    (
+        "password-not-match",
+        "Passwords do not match",
+        "两次输入的密码不一致",
+    ),
+    (
+        "password-required",
+        "Password required to unlock the keystore",
+        "需要输入密码解锁 keystore",
+    ),
+    (
+        "account-locked",
+        "Account(lock_arg={}) locked or not exists, your may use `account unlock` to unlock it or use --with-password",
+        "账户(lock_arg={})已锁定或不存在, 请使用 `account unlock` 解锁或使用 --with-password",
+    ),
+    ("confirm-choices", "[y/N]", "[y/N]"),
+    (
+        "override-policy",
+        "Override signing policy and continue?",
+        "忽略签名策略并继续?",
+    ),
+    (
+        "capacity-not-enough",
+        "Capacity not enough: {} => {}",
+        "余额不足: {} => {}",
+    ),
+    (
+        "index-not-ready",
+        "index database may not ready, sync process: {}",
+        "索引数据库可能尚未就绪, 同步进度: {}",
+    ),
+    (
+        "interactive-only",
+        "ERROR: This is an interactive mode only sub-command (or run it with --sync-first)",
+        "错误: 该子命令只能在交互模式下使用 (或使用 --sync-first 参数)",
+    ),
+    (
+        "no-lock-activity",
+        "[WARNING]: No transaction found of lock hash {}, please double check it before sending funds (only lock hashes registered by `rpc index_lock_hash` are indexed by node)",
+        "[警告]: 未找到 lock hash {} 的任何交易, 转账前请仔细核对 (节点只索引通过 `rpc index_lock_hash` 注册的 lock hash)",
+    ),
+    (
+        "lock-activity-failed",
+        "Check on-chain activity of lock hash {} failed: {}",
+        "检查 lock hash {} 的链上记录失败: {}",
+    ),
+    (
+        "record-spent-failed",
+        "[WARNING]: Record spent cells failed: {}",
+        "[警告]: 记录已花费的 cell 失败: {}",
+    ),
+    (
+        "record-pending-failed",
+        "[WARNING]: Record pending transaction failed: {}",
+        "[警告]: 记录待确认交易失败: {}",
+    ),
+    (
+        "record-deployment-failed",
+        "[WARNING]: Record deployment failed: {}",
+        "[警告]: 记录部署失败: {}",
+    ),
+    (
+        "node-lacks-method",
+        "Node {} lacks method {}; {}",
+        "节点 {} 不支持方法 {}; {}",
+    ),
+    (
+        "lock-activity-skipped",
+        "[WARNING]: Node {} lacks the Indexer RPC, on-chain activity of lock hash {} is not checked",
+        "[警告]: 节点 {} 不支持 Indexer RPC, 未检查 lock hash {} 的链上记录",
+    ),
+    (
+        "to-lock-script",
+        "Target lock hash: {}, minimal capacity of the output: {} shannons",
+        "目标 lock hash: {}, 输出的最小容量: {} shannons",
+    ),
+    (
+        "confirm-to-lock-script",
+        "Only the owner of this lock script can spend the funds, continue?",
+        "只有该 lock script 的所有者能花费这笔资金, 是否继续?",
+    ),
+    (
+        "confirm-send-tx",
+        "Send this transaction?",
+        "是否发送该交易?",
+    ),
+    (
+        "confirm-required",
+        "Confirmation required to send the transaction but stdin is not a terminal, use --yes to send it without confirmation",
+        "发送交易需要确认但标准输入不是终端, 请使用 --yes 跳过确认",
+    ),
+    (
+        "tx-committed",
+        "Transaction {} committed in block {} ({})",
+        "交易 {} 已上链, 区块 {} ({})",
+    ),
+    (
+        "wait-commit-timeout",
+        "Transaction {} is not committed in {} seconds, status: {}",
+        "交易 {} 在 {} 秒内未上链, 当前状态: {}",
+    ),
+];
+
+/// The message of current language, unknown key is returned as it is
+pub fn t(key: &'static str) -> &'static str {
+    let lang = lang();
+    MESSAGES
+        .iter()
+        .find(|(k, _, _)| *k == key)
+        .map(|(_, en, zh_cn)| match lang {
+            Lang::En => *en,
+            Lang::ZhCn => *zh_cn,
+        })
+        .unwrap_or(key)
+}
+
+/// The message of current language with the `{}` placeholders filled by `args` in order
+pub fn tf(key: &'static str, args: &[&dyn fmt::Display]) -> String {
+    let mut parts = t(key).split("{}");
+    let mut output = parts.next().unwrap_or("").to_owned();
+    let mut args = args.iter();
+    for part in parts {
+        if let Some(arg) = args.next() {
+            output.push_str(&arg.to_string());
+        }
+        output.push_str(part);
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_messages() {
+        assert_eq!(Lang::from_str("zh_CN").unwrap(), Lang::ZhCn);
+        assert_eq!(Lang::from_str("en-US").unwrap(), Lang::En);
+        assert!(Lang::from_str("fr").is_err());
+
+        for (key, en, zh_cn) in MESSAGES {
+            assert_eq!(
+                en.matches("{}").count(),
+                zh_cn.matches("{}").count(),
+                "placeholders of {} not match",
+                key
+            );
+        }
+        assert_eq!(t("password"), "Password");
+        assert_eq!(t("unknown-key"), "unknown-key");
+        assert_eq!(
+            tf("capacity-not-enough", &[&"ckt1xx", &100]),
+            "Capacity not enough: ckt1xx => 100"
+        );
+    }
+}
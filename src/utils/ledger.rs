@@ -0,0 +1,135 @@
+use ckb_sdk::{wallet::DerivationPath, Address, SECP256K1};
+use ckb_types::{bytes::Bytes, H256};
+use secp256k1::recovery::{RecoverableSignature, RecoveryId};
+
+use super::sign_history::sign_witness;
+
+#[cfg(feature = "ledger")]
+use ledger::{ApduCommand, LedgerApp};
+
+// APDU of the CKB Ledger app
+#[cfg(feature = "ledger")]
+const CLA: u8 = 0x80;
+#[cfg(feature = "ledger")]
+const INS_GET_PUBLIC_KEY: u8 = 0x02;
+#[cfg(feature = "ledger")]
+const INS_SIGN_HASH: u8 = 0x06;
+#[cfg(feature = "ledger")]
+const SW_OK: u16 = 0x9000;
+
+/// Sign with the secp256k1 key inside a Ledger device. The digest to sign is displayed on
+/// the device and must be confirmed by user.
+#[cfg_attr(not(feature = "ledger"), allow(dead_code))]
+pub struct LedgerSigner {
+    path: DerivationPath,
+    pubkey: secp256k1::PublicKey,
+    #[cfg(feature = "ledger")]
+    app: LedgerApp,
+}
+
+impl LedgerSigner {
+    #[cfg(feature = "ledger")]
+    pub fn open(path: DerivationPath) -> Result<LedgerSigner, String> {
+        let app = LedgerApp::new().map_err(|err| format!("Open ledger device failed: {}", err))?;
+        let data = exchange(&app, INS_GET_PUBLIC_KEY, serialize_path(&path))?;
+        let pubkey_len = *data
+            .get(0)
+            .ok_or_else(|| "Invalid public key response from ledger".to_owned())?
+            as usize;
+        if data.len() < 1 + pubkey_len {
+            return Err("Invalid public key response from ledger".to_owned());
+        }
+        let pubkey = secp256k1::PublicKey::from_slice(&data[1..=pubkey_len])
+            .map_err(|err| format!("Invalid public key from ledger: {}", err))?;
+        Ok(LedgerSigner { path, pubkey, app })
+    }
+
+    #[cfg(not(feature = "ledger"))]
+    pub fn open(_path: DerivationPath) -> Result<LedgerSigner, String> {
+        Err("ckb-cli is built without ledger support, rebuild with `--features ledger`".to_owned())
+    }
+
+    pub fn address(&self) -> Result<Address, String> {
+        Address::from_pubkey(&self.pubkey)
+    }
+
+    /// The device returns the signature in the same 65 bytes format as `serialize_signature`
+    pub fn sign_recoverable(&self, hash: &H256) -> Result<RecoverableSignature, String> {
+        let data = self.sign_hash(hash)?;
+        if data.len() != 65 {
+            return Err(format!(
+                "Invalid signature length from ledger: {}",
+                data.len()
+            ));
+        }
+        let recov_id = RecoveryId::from_i32(i32::from(data[64])).map_err(|err| err.to_string())?;
+        let signature = RecoverableSignature::from_compact(&data[0..64], recov_id)
+            .map_err(|err| err.to_string())?;
+        // Make sure the signature is produced by the expected key
+        let message =
+            secp256k1::Message::from_slice(hash.as_bytes()).expect("Convert to message failed");
+        let pubkey = SECP256K1
+            .recover(&message, &signature)
+            .map_err(|err| err.to_string())?;
+        if pubkey != self.pubkey {
+            return Err("Signature from ledger not match the account public key".to_owned());
+        }
+        Ok(signature)
+    }
+
+    pub fn build_witness(&self, args: &[Vec<u8>]) -> Result<Bytes, String> {
+        sign_witness(self.address()?.hash(), args, |sign_hash| {
+            eprintln!(
+                "Please confirm the signing of {:#x} on your ledger device",
+                sign_hash
+            );
+            self.sign_recoverable(sign_hash)
+        })
+    }
+
+    #[cfg(feature = "ledger")]
+    fn sign_hash(&self, hash: &H256) -> Result<Vec<u8>, String> {
+        let mut data = serialize_path(&self.path);
+        data.extend_from_slice(hash.as_bytes());
+        exchange(&self.app, INS_SIGN_HASH, data)
+    }
+
+    #[cfg(not(feature = "ledger"))]
+    fn sign_hash(&self, _hash: &H256) -> Result<Vec<u8>, String> {
+        Err("ckb-cli is built without ledger support".to_owned())
+    }
+}
+
+#[cfg(feature = "ledger")]
+fn exchange(app: &LedgerApp, ins: u8, data: Vec<u8>) -> Result<Vec<u8>, String> {
+    let command = ApduCommand {
+        cla: CLA,
+        ins,
+        p1: 0x00,
+        p2: 0x00,
+        length: data.len() as u8,
+        data,
+    };
+    let answer = app
+        .exchange(command)
+        .map_err(|err| format!("Communicate with ledger failed: {}", err))?;
+    if answer.retcode != SW_OK {
+        return Err(format!(
+            "Ledger returned error code: {:#06x} (is the CKB app opened?)",
+            answer.retcode
+        ));
+    }
+    Ok(answer.data)
+}
+
+/// Path format: [count(u8), index_0(u32 BE), index_1(u32 BE), ...]
+#[cfg(feature = "ledger")]
+fn serialize_path(path: &DerivationPath) -> Vec<u8> {
+    let children = path.as_ref();
+    let mut data = Vec::with_capacity(1 + children.len() * 4);
+    data.push(children.len() as u8);
+    for child in children {
+        data.extend_from_slice(&u32::from(*child).to_be_bytes());
+    }
+    data
+}
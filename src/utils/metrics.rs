@@ -0,0 +1,126 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+// A stalled client must not hold the connection thread forever
+const IO_TIMEOUT: Duration = Duration::from_secs(5);
+// The request line and headers of a scrape are small
+const MAX_REQUEST_LINES: usize = 100;
+
+/// Serve `GET /metrics` (Prometheus text format) on `address`, the content is rendered by
+/// `render` for every request. Each connection is served by its own thread, so a slow client
+/// does not block the others. Returns the actual listening address.
+pub fn start_metrics_server<F>(address: &str, render: F) -> Result<SocketAddr, String>
+where
+    F: Fn() -> String + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind(address)
+        .map_err(|err| format!("Bind metrics address {} failed: {}", address, err))?;
+    let local_addr = listener.local_addr().map_err(|err| err.to_string())?;
+    thread::Builder::new()
+        .name("metrics".to_string())
+        .spawn(move || {
+            let render = Arc::new(render);
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let render = Arc::clone(&render);
+                        let spawned = thread::Builder::new()
+                            .name("metrics-conn".to_string())
+                            .spawn(move || {
+                                if let Err(err) = handle_request(stream, render.as_ref()) {
+                                    log::debug!("metrics request error: {}", err);
+                                }
+                            });
+                        if let Err(err) = spawned {
+                            log::debug!("metrics connection thread error: {}", err);
+                        }
+                    }
+                    Err(err) => log::debug!("metrics connection error: {}", err),
+                }
+            }
+        })
+        .expect("Spawn metrics thread failed");
+    Ok(local_addr)
+}
+
+fn handle_request<F: Fn() -> String>(stream: TcpStream, render: &F) -> Result<(), String> {
+    stream
+        .set_read_timeout(Some(IO_TIMEOUT))
+        .and_then(|_| stream.set_write_timeout(Some(IO_TIMEOUT)))
+        .map_err(|err| err.to_string())?;
+    let mut reader = BufReader::new(stream.try_clone().map_err(|err| err.to_string())?);
+    let mut request_line = String::new();
+    reader
+        .read_line(&mut request_line)
+        .map_err(|err| err.to_string())?;
+    // Drain the headers
+    for lines in 0.. {
+        if lines >= MAX_REQUEST_LINES {
+            return Err("Too many request headers".to_owned());
+        }
+        let mut line = String::new();
+        let size = reader.read_line(&mut line).map_err(|err| err.to_string())?;
+        if size == 0 || line.trim().is_empty() {
+            break;
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (status, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) | (Some("GET"), Some("/")) => ("200 OK", render()),
+        _ => ("404 Not Found", "Not Found\n".to_owned()),
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    let mut writer = stream;
+    writer
+        .write_all(response.as_bytes())
+        .map_err(|err| err.to_string())
+}
+
+/// Append one metric (with HELP and TYPE lines) to `output`
+pub fn write_metric(output: &mut String, name: &str, kind: &str, help: &str, value: f64) {
+    output.push_str(&format!("# HELP {} {}\n", name, help));
+    output.push_str(&format!("# TYPE {} {}\n", name, kind));
+    output.push_str(&format!("{} {}\n", name, value));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn test_metrics_server() {
+        let address = start_metrics_server("127.0.0.1:0", || {
+            let mut output = String::new();
+            write_metric(&mut output, "test_height", "gauge", "Test height", 42.0);
+            output
+        })
+        .unwrap();
+
+        // An idle connection does not block the others
+        let _idle = TcpStream::connect(address).unwrap();
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream
+            .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("# TYPE test_height gauge\ntest_height 42\n"));
+
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream.write_all(b"GET /other HTTP/1.1\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 404"));
+    }
+}
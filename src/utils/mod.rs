@@ -1,10 +1,25 @@
+pub mod address_book;
 pub mod arg;
 pub mod arg_parser;
+pub mod cell_schema;
 pub mod completer;
 pub mod config;
+pub mod deployments;
+pub mod elf;
+pub mod i18n;
 pub mod json_color;
+pub mod ledger;
+pub mod metrics;
+pub mod multisig_configs;
+pub mod node_capabilities;
 pub mod other;
+pub mod policy;
 pub mod printer;
+pub mod profile;
+pub mod qr;
+pub mod sign_history;
+pub mod tabular;
+pub mod watch_only;
 
 #[allow(clippy::cast_lossless)]
 pub mod yaml_ser;
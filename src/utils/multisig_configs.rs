@@ -0,0 +1,109 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use ckb_sdk::MultisigConfig;
+use ckb_types::H160;
+
+use super::other::get_ckb_cli_dir;
+
+/// Multisig configs created by `wallet multisig create-config`, stored in
+/// `~/.ckb-cli/multisig-configs`
+pub struct MultisigConfigs {
+    path: PathBuf,
+    // multisig lock arg => config
+    configs: BTreeMap<H160, MultisigConfig>,
+}
+
+impl MultisigConfigs {
+    pub fn load(path: PathBuf) -> Result<MultisigConfigs, String> {
+        let configs = if path.exists() {
+            let content = fs::read_to_string(&path).map_err(|err| err.to_string())?;
+            serde_json::from_str(&content)
+                .map_err(|err| format!("Parse multisig configs {:?} failed: {}", path, err))?
+        } else {
+            BTreeMap::default()
+        };
+        Ok(MultisigConfigs { path, configs })
+    }
+
+    pub fn load_default() -> Result<MultisigConfigs, String> {
+        MultisigConfigs::load(get_ckb_cli_dir().join("multisig-configs"))
+    }
+
+    pub fn add(&mut self, config: MultisigConfig) -> Result<H160, String> {
+        let lock_arg = config.hash160();
+        if self
+            .configs
+            .get(&lock_arg)
+            .map(|other| other != &config)
+            .unwrap_or(false)
+        {
+            return Err(format!(
+                "Lock hash collision: {:#x} is used by another multisig config",
+                lock_arg
+            ));
+        }
+        self.configs.insert(lock_arg.clone(), config);
+        self.save()?;
+        Ok(lock_arg)
+    }
+
+    pub fn contains(&self, lock_arg: &H160) -> bool {
+        self.configs.contains_key(lock_arg)
+    }
+
+    /// Configs with the same signers as `config` (maybe in different order or threshold)
+    pub fn similar_to(&self, config: &MultisigConfig) -> Vec<H160> {
+        let mut signers = config.sighash_addresses().to_vec();
+        signers.sort();
+        self.configs
+            .iter()
+            .filter(|(_, other)| *other != config)
+            .filter(|(_, other)| {
+                let mut other_signers = other.sighash_addresses().to_vec();
+                other_signers.sort();
+                other_signers == signers
+            })
+            .map(|(lock_arg, _)| lock_arg.clone())
+            .collect()
+    }
+
+    pub fn get(&self, lock_arg: &H160) -> Result<&MultisigConfig, String> {
+        self.configs.get(lock_arg).ok_or_else(|| {
+            format!(
+                "Multisig config not found: {:#x}, use `wallet multisig create-config` first",
+                lock_arg
+            )
+        })
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(&self.configs).map_err(|err| err.to_string())?;
+        fs::write(&self.path, content).map_err(|err| err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_similar_configs() {
+        let signer_a = H160::from_slice(&[1u8; 20]).unwrap();
+        let signer_b = H160::from_slice(&[2u8; 20]).unwrap();
+        let config = MultisigConfig::new(vec![signer_a.clone(), signer_b.clone()], 0, 2).unwrap();
+        let reordered = MultisigConfig::new(vec![signer_b, signer_a.clone()], 0, 2).unwrap();
+        let other = MultisigConfig::new(vec![signer_a], 0, 1).unwrap();
+        let mut configs = MultisigConfigs {
+            path: PathBuf::new(),
+            configs: BTreeMap::default(),
+        };
+        configs.configs.insert(config.hash160(), config.clone());
+        configs.configs.insert(other.hash160(), other);
+        assert!(configs.similar_to(&config).is_empty());
+        assert_eq!(configs.similar_to(&reordered), vec![config.hash160()]);
+        assert!(configs.contains(&config.hash160()));
+        assert!(!configs.contains(&reordered.hash160()));
+    }
+}
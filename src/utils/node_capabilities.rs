@@ -0,0 +1,187 @@
+//! What the connected node supports. RPC modules (like `Indexer`) can be disabled in `ckb.toml`
+//! and the methods vary between versions, so the methods are probed on demand and the result is
+//! cached until the url changed.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+
+use ckb_jsonrpc_types::Uint64;
+use ckb_sdk::HttpRpcClient;
+use ckb_types::H256;
+
+use super::i18n::tf;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Feature {
+    // get_live_cells_by_lock_hash / get_transactions_by_lock_hash / index_lock_hash ...
+    Indexer,
+    BlockEconomicState,
+    Consensus,
+    FeeRateStatics,
+}
+
+impl Feature {
+    pub fn method(self) -> &'static str {
+        match self {
+            Feature::Indexer => "get_live_cells_by_lock_hash",
+            Feature::BlockEconomicState => "get_block_economic_state",
+            Feature::Consensus => "get_consensus",
+            Feature::FeeRateStatics => "get_fee_rate_statics",
+        }
+    }
+
+    fn hint(self) -> &'static str {
+        match self {
+            Feature::Indexer => "enable the Indexer module in ckb.toml, or use the local index (`wallet get-capacity`, `wallet get-live-cells`)",
+            Feature::BlockEconomicState | Feature::Consensus | Feature::FeeRateStatics => {
+                "upgrade the node to a newer version"
+            }
+        }
+    }
+}
+
+impl fmt::Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.method())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NodeCapabilities {
+    version: String,
+    supported: HashMap<Feature, bool>,
+}
+
+impl NodeCapabilities {
+    /// Query the version of the node, the methods are probed when first required
+    pub fn detect(rpc_client: &mut HttpRpcClient) -> NodeCapabilities {
+        let version = rpc_client
+            .local_node_info()
+            .call()
+            .map(|node| node.version)
+            .unwrap_or_else(|_| "unknown".to_owned());
+        NodeCapabilities {
+            version,
+            supported: HashMap::default(),
+        }
+    }
+
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    pub fn supports(&mut self, rpc_client: &mut HttpRpcClient, feature: Feature) -> bool {
+        if let Some(supported) = self.supported.get(&feature) {
+            return *supported;
+        }
+        let result = match feature {
+            Feature::Indexer => rpc_client
+                .get_live_cells_by_lock_hash(
+                    H256::default(),
+                    Uint64::from(0),
+                    Uint64::from(1),
+                    None,
+                )
+                .call()
+                .map(|_| ())
+                .map_err(|err| err.to_string()),
+            Feature::BlockEconomicState => rpc_client
+                .get_block_economic_state(H256::default())
+                .call()
+                .map(|_| ())
+                .map_err(|err| err.to_string()),
+            Feature::Consensus => rpc_client
+                .get_consensus()
+                .call()
+                .map(|_| ())
+                .map_err(|err| err.to_string()),
+            Feature::FeeRateStatics => rpc_client
+                .get_fee_rate_statics(None)
+                .call()
+                .map(|_| ())
+                .map_err(|err| err.to_string()),
+        };
+        // Other errors (like the invalid params) mean the method exists
+        let supported = match result {
+            Ok(()) => true,
+            Err(err) => !is_method_not_found(&err),
+        };
+        self.supported.insert(feature, supported);
+        supported
+    }
+
+    pub fn require(
+        &mut self,
+        rpc_client: &mut HttpRpcClient,
+        feature: Feature,
+    ) -> Result<(), String> {
+        if self.supports(rpc_client, feature) {
+            Ok(())
+        } else {
+            Err(tf(
+                "node-lacks-method",
+                &[&self.version, &feature, &feature.hint()],
+            ))
+        }
+    }
+}
+
+fn is_method_not_found(err: &str) -> bool {
+    err.contains("Method not found") || err.contains("-32601")
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<NodeCapabilities>> = RefCell::new(None);
+}
+
+/// Forget the capabilities of previous node (when the url changed), detected again on demand
+pub fn reset() {
+    CURRENT.with(|current| *current.borrow_mut() = None);
+}
+
+fn with_current<F, T>(rpc_client: &mut HttpRpcClient, func: F) -> T
+where
+    F: FnOnce(&mut NodeCapabilities, &mut HttpRpcClient) -> T,
+{
+    CURRENT.with(|current| {
+        let mut current = current.borrow_mut();
+        if current.is_none() {
+            *current = Some(NodeCapabilities::detect(rpc_client));
+        }
+        func(current.as_mut().unwrap(), rpc_client)
+    })
+}
+
+pub fn supports(rpc_client: &mut HttpRpcClient, feature: Feature) -> bool {
+    with_current(rpc_client, |capabilities, rpc_client| {
+        capabilities.supports(rpc_client, feature)
+    })
+}
+
+/// Fail with the node version, the missing method and how to work around
+pub fn require(rpc_client: &mut HttpRpcClient, feature: Feature) -> Result<(), String> {
+    with_current(rpc_client, |capabilities, rpc_client| {
+        capabilities.require(rpc_client, feature)
+    })
+}
+
+pub fn node_version(rpc_client: &mut HttpRpcClient) -> String {
+    with_current(rpc_client, |capabilities, _| {
+        capabilities.version().to_owned()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_method_not_found() {
+        assert!(is_method_not_found(
+            "Server responded with error: Method not found"
+        ));
+        assert!(is_method_not_found("code: -32601"));
+        assert!(!is_method_not_found("Invalid params"));
+    }
+}
@@ -1,40 +1,204 @@
+use std::cell::RefCell;
+use std::env;
 use std::fs;
-use std::path::PathBuf;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use ckb_jsonrpc_types::{AlertMessage, BlockNumber};
+use ckb_jsonrpc_types::{AlertMessage, BlockNumber, Uint64};
 use ckb_sdk::{
+    split_basic_auth, stats,
     wallet::{KeyStore, ScryptType},
-    Address, GenesisInfo, HttpRpcClient, NetworkType,
+    Address, GenesisInfo, HttpRpcClient, NetworkType, ONE_CKB,
+};
+use ckb_types::{
+    core::{BlockView, TransactionView},
+    packed,
+    prelude::*,
+    H160, H256,
 };
-use ckb_types::{core::BlockView, H160, H256};
 use clap::ArgMatches;
 use colored::Colorize;
-use rpassword::prompt_password_stdout;
+use rpassword::prompt_password_stderr;
 
 use super::arg_parser::{AddressParser, ArgParser, FixedHashParser, PubkeyHexParser};
+use super::i18n::{t, tf};
+use super::node_capabilities::{self, Feature};
+use super::printer::{OutputFormat, Printable};
+use super::sign_history::record_digest;
+
+/// File holding the keystore password (the first line), it is read instead of prompting for
+/// unattended usage. `-` is stdin, and a file descriptor can be given as `/dev/fd/<n>`.
+pub const PASSWORD_FILE_ENV: &str = "CKB_CLI_PASSWORD_FILE";
+
+thread_local! {
+    // Stdin can only be read once, the password is kept for the other signatures
+    static STDIN_PASSWORD: RefCell<Option<String>> = RefCell::new(None);
+}
+
+lazy_static::lazy_static! {
+    // Shared by the threads reading passwords (like the server handlers)
+    static ref PASSWORD_FILE: RwLock<Option<PathBuf>> =
+        RwLock::new(env::var_os(PASSWORD_FILE_ENV).map(PathBuf::from));
+}
+
+/// Read the password from this file instead of prompting (`--password-file`), overrides
+/// `CKB_CLI_PASSWORD_FILE`
+pub fn set_password_file(path: PathBuf) {
+    *PASSWORD_FILE.write().expect("Password file lock poisoned") = Some(path);
+}
+
+fn password_file() -> Option<PathBuf> {
+    PASSWORD_FILE
+        .read()
+        .expect("Password file lock poisoned")
+        .clone()
+}
+
+/// The password is read from a file (`--password-file` or `CKB_CLI_PASSWORD_FILE`) instead of
+/// prompting
+pub fn has_password_file() -> bool {
+    password_file().is_some()
+}
 
 pub fn read_password(repeat: bool, prompt: Option<&str>) -> Result<String, String> {
-    let prompt = prompt.unwrap_or("Password");
+    if let Some(path) = password_file() {
+        return if path == Path::new("-") {
+            read_password_stdin()
+        } else {
+            read_password_file(&path)
+        };
+    }
+    let prompt = prompt.unwrap_or_else(|| t("password"));
     let pass =
-        prompt_password_stdout(format!("{}: ", prompt).as_str()).map_err(|err| err.to_string())?;
+        prompt_password_stderr(format!("{}: ", prompt).as_str()).map_err(|err| err.to_string())?;
     if repeat {
-        let repeat_pass =
-            prompt_password_stdout("Repeat password: ").map_err(|err| err.to_string())?;
+        let repeat_pass = prompt_password_stderr(format!("{}: ", t("repeat-password")).as_str())
+            .map_err(|err| err.to_string())?;
         if pass != repeat_pass {
-            return Err("Passwords do not match".to_owned());
+            return Err(t("password-not-match").to_owned());
         }
     }
     Ok(pass)
 }
 
-pub fn get_key_store(ckb_cli_dir: &PathBuf) -> Result<KeyStore, String> {
-    let mut keystore_dir = ckb_cli_dir.clone();
-    keystore_dir.push("keystore");
-    fs::create_dir_all(&keystore_dir)
+pub fn read_password_file(path: &Path) -> Result<String, String> {
+    check_password_file_mode(path)?;
+    let content = fs::read_to_string(path)
+        .map_err(|err| format!("Read password file {:?} failed: {}", path, err))?;
+    Ok(content.lines().next().unwrap_or_default().to_owned())
+}
+
+/// A regular password file must not be accessible by group or others (like `chmod 600`), pipes
+/// and file descriptors are not checked
+#[cfg(unix)]
+fn check_password_file_mode(path: &Path) -> Result<(), String> {
+    use std::os::unix::fs::PermissionsExt;
+
+    // Opened by the caller (like `3<password.txt`), the metadata is of the file it refers to
+    if path.starts_with("/dev/fd") || path.starts_with("/proc/self/fd") {
+        return Ok(());
+    }
+    let metadata = fs::metadata(path)
+        .map_err(|err| format!("Read password file {:?} failed: {}", path, err))?;
+    let mode = metadata.permissions().mode() & 0o777;
+    if metadata.is_file() && mode & 0o077 != 0 {
+        return Err(format!(
+            "Password file {:?} is accessible by other users (mode: {:o}), run `chmod 600` on it",
+            path, mode
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn check_password_file_mode(_path: &Path) -> Result<(), String> {
+    Ok(())
+}
+
+fn read_password_stdin() -> Result<String, String> {
+    STDIN_PASSWORD.with(|password| {
+        let mut password = password.borrow_mut();
+        if password.is_none() {
+            let mut line = String::new();
+            io::stdin()
+                .read_line(&mut line)
+                .map_err(|err| format!("Read password from stdin failed: {}", err))?;
+            *password = Some(line.trim_end_matches(|c| c == '\n' || c == '\r').to_owned());
+        }
+        Ok(password.clone().unwrap())
+    })
+}
+
+/// Read a transaction JSON file (see: rpc send_transaction), the `transaction` field is used if
+/// present (like the output of watch-only accounts or the multisig transaction file). The whole
+/// JSON value is also returned for the extra fields.
+pub fn read_tx_file(path: &str) -> Result<(TransactionView, serde_json::Value), String> {
+    let content = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let value: serde_json::Value = serde_json::from_str(&content).map_err(|err| err.to_string())?;
+    let tx_value = if value.get("transaction").is_some() {
+        value["transaction"].clone()
+    } else {
+        value.clone()
+    };
+    let tx: ckb_jsonrpc_types::Transaction = serde_json::from_value(tx_value)
+        .map_err(|err| format!("Parse transaction file failed: {}", err))?;
+    Ok((packed::Transaction::from(tx).into_view(), value))
+}
+
+pub fn read_confirmation(prompt: &str) -> Result<bool, String> {
+    // On stderr, so stdout only has the output of the command
+    eprint!("{} {}: ", prompt, t("confirm-choices"));
+    io::stderr().flush().map_err(|err| err.to_string())?;
+    let mut input = String::new();
+    io::stdin()
+        .read_line(&mut input)
+        .map_err(|err| err.to_string())?;
+    let input = input.trim().to_lowercase();
+    Ok(input == "y" || input == "yes")
+}
+
+/// Create a file only readable by the owner (like the keystore files) for the keys, tokens and
+/// backups, an existing file is never overwritten
+#[cfg(unix)]
+pub fn create_private_file<P: AsRef<Path>>(path: P) -> io::Result<fs::File> {
+    use std::os::unix::fs::OpenOptionsExt;
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)
+}
+
+#[cfg(not(unix))]
+pub fn create_private_file<P: AsRef<Path>>(path: P) -> io::Result<fs::File> {
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+}
+
+/// Current unix timestamp in seconds
+pub fn now_seconds() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+pub fn get_ckb_cli_dir() -> PathBuf {
+    let mut ckb_cli_dir = dirs::home_dir().unwrap();
+    ckb_cli_dir.push(".ckb-cli");
+    ckb_cli_dir
+}
+
+pub fn get_key_store(keystore_dir: &PathBuf) -> Result<KeyStore, String> {
+    fs::create_dir_all(keystore_dir)
         .map_err(|err| err.to_string())
         .and_then(|_| {
-            KeyStore::from_dir(keystore_dir, ScryptType::default()).map_err(|err| err.to_string())
+            KeyStore::from_dir(keystore_dir.clone(), ScryptType::default())
+                .map_err(|err| err.to_string())
         })
 }
 
@@ -60,6 +224,7 @@ pub fn get_singer(
         let signature = key_store
             .sign_recoverable_with_password(lock_arg, tx_hash_hash, password.as_bytes())
             .map_err(|err| err.to_string())?;
+        record_digest(lock_arg, None, tx_hash_hash.clone())?;
         let (recov_id, data) = signature.serialize_compact();
         let mut signature_bytes = [0u8; 65];
         signature_bytes[0..64].copy_from_slice(&data[0..64]);
@@ -129,6 +294,121 @@ pub fn get_network_type(rpc_client: &mut HttpRpcClient) -> Result<NetworkType, S
         .ok_or_else(|| format!("Unexpected network type: {}", chain_info.chain))
 }
 
+/// Check if a lock has any transaction (by node indexer RPC), print warning if not. Funds sent
+/// to a mistyped lock-arg or a wrong multisig config (pubkeys order, threshold) are lost. It is
+/// None when the node lacks the Indexer RPC, and the RPC error is returned (not taken as no
+/// activity).
+pub fn check_lock_activity(
+    rpc_client: &mut HttpRpcClient,
+    lock_hash: H256,
+) -> Result<Option<bool>, String> {
+    if !node_capabilities::supports(rpc_client, Feature::Indexer) {
+        eprintln!(
+            "{}",
+            tf(
+                "lock-activity-skipped",
+                &[
+                    &node_capabilities::node_version(rpc_client),
+                    &format!("{:#x}", lock_hash)
+                ]
+            )
+        );
+        return Ok(None);
+    }
+    let txs = rpc_client
+        .get_transactions_by_lock_hash(
+            lock_hash.clone(),
+            Uint64::from(0),
+            Uint64::from(1),
+            Some(true),
+        )
+        .call()
+        .map_err(|err| {
+            tf(
+                "lock-activity-failed",
+                &[&format!("{:#x}", lock_hash), &err],
+            )
+        })?;
+    if txs.0.is_empty() {
+        eprintln!(
+            "{}",
+            tf("no-lock-activity", &[&format!("{:#x}", lock_hash)])
+        );
+    }
+    Ok(Some(!txs.0.is_empty()))
+}
+
+/// Print the `--timings` report of current command to stderr (keep stdout parsable)
+pub fn print_timings(elapsed: Duration) {
+    let stats = stats::take();
+    let caches = stats
+        .caches
+        .iter()
+        .map(|(name, cache)| {
+            let value = serde_json::json!({
+                "hits": cache.hits,
+                "misses": cache.misses,
+                "hit_rate": format!("{:.1}%", cache.hit_rate() * 100.0),
+            });
+            (name.clone(), value)
+        })
+        .collect::<serde_json::Map<_, _>>();
+    let report = serde_json::json!({
+        "wall_time": format!("{:.3}s", elapsed.as_millis() as f64 / 1000.0),
+        "rpc_calls_total": stats.rpc_calls.values().sum::<u64>(),
+        "rpc_calls": stats.rpc_calls,
+        "index_queries_total": stats.index_queries.values().sum::<u64>(),
+        "index_queries": stats.index_queries,
+        "caches": caches,
+    });
+    eprintln!("[Timings]:\n{}", report.render(OutputFormat::Yaml, false));
+}
+
+/// Render shannons in CKB, example: 6100000001 => "61.00000001 CKB"
+pub fn ckb_string(shannons: u64) -> String {
+    let fraction = shannons % ONE_CKB;
+    if fraction == 0 {
+        format!("{} CKB", shannons / ONE_CKB)
+    } else {
+        let fraction = format!("{:08}", fraction);
+        format!(
+            "{}.{} CKB",
+            shannons / ONE_CKB,
+            fraction.trim_end_matches('0')
+        )
+    }
+}
+
+/// The url with the password of basic auth masked, for display
+pub fn mask_url_password(url: &str) -> String {
+    match split_basic_auth(url) {
+        (url, Some((username, password))) => {
+            let scheme_end = url.find("://").map(|pos| pos + 3).unwrap_or(0);
+            let masked = if password.is_some() { ":***" } else { "" };
+            format!(
+                "{}{}{}@{}",
+                &url[..scheme_end],
+                username,
+                masked,
+                &url[scheme_end..]
+            )
+        }
+        (url, None) => url,
+    }
+}
+
+/// The url without the password of basic auth, saved to the config file (the password is read
+/// from `--rpc-password-file`)
+pub fn strip_url_password(url: &str) -> String {
+    match split_basic_auth(url) {
+        (url, Some((username, _))) => {
+            let scheme_end = url.find("://").map(|pos| pos + 3).unwrap_or(0);
+            format!("{}{}@{}", &url[..scheme_end], username, &url[scheme_end..])
+        }
+        (url, None) => url,
+    }
+}
+
 pub fn check_address_prefix(address: &str, network_type: NetworkType) -> Result<(), String> {
     if address.len() < 3 {
         Err(format!("Invalid address length: {}", address))
@@ -138,3 +418,19 @@ pub fn check_address_prefix(address: &str, network_type: NetworkType) -> Result<
         Ok(())
     }
 }
+
+/// Total size of the files in a directory (recursively)
+pub fn dir_size(path: &Path) -> u64 {
+    fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .map(|entry| match entry.metadata() {
+                    Ok(ref metadata) if metadata.is_dir() => dir_size(&entry.path()),
+                    Ok(metadata) => metadata.len(),
+                    Err(_) => 0,
+                })
+                .sum()
+        })
+        .unwrap_or(0)
+}
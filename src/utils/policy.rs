@@ -0,0 +1,475 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use chrono::Utc;
+use ckb_sdk::{Address, GenesisInfo};
+use ckb_types::{
+    core::{ScriptHashType, TransactionView},
+    packed::{CellOutput, Script},
+    prelude::*,
+    H160,
+};
+use serde_derive::{Deserialize, Serialize};
+
+use super::arg_parser::{AddressParser, ArgParser, CapacityParser, FixedHashParser};
+use super::i18n::t;
+use super::other::{get_ckb_cli_dir, read_confirmation};
+
+const POLICY_FILE: &str = "policy";
+const POLICY_LEDGER_FILE: &str = "policy-ledger";
+
+lazy_static::lazy_static! {
+    // The accounts whose request passed the policy in the current command, the shared signing
+    // path (see `sign_history::sign_witness`) refuses to sign for the others
+    static ref APPROVED_SIGNERS: RwLock<HashSet<H160>> = RwLock::new(HashSet::new());
+}
+
+/// What kind of transaction a signature is requested for
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SignKind {
+    Transfer,
+    DepositDao,
+    WithdrawDao,
+}
+
+/// The information a policy rule can inspect before a signature is produced
+pub struct SignRequest<'a> {
+    pub kind: SignKind,
+    pub from_address: &'a Address,
+    /// All the receivers of the transaction
+    pub to_addresses: &'a [Address],
+    /// The total capacity sent
+    pub capacity: u64,
+    /// The lock needs the signatures of two or more signers (multisig)
+    pub cosigned: bool,
+}
+
+/// Local signing policy, loaded from `~/.ckb-cli/policy` (json format):
+///
+/// ```json
+/// {
+///   "max_per_tx": "1000.0",
+///   "max_daily_total": "5000.0",
+///   "allowed_addresses": ["ckt1qyq..."],
+///   "deny_dao_withdraw": true,
+///   "two_person_rule": {
+///     "threshold": "10000.0",
+///     "accounts": ["<lock-arg-1>", "<lock-arg-2>"]
+///   }
+/// }
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PolicyConfig {
+    /// Max capacity (unit: CKB) allowed per transaction
+    pub max_per_tx: Option<String>,
+    /// Max capacity (unit: CKB) allowed to be signed in one (UTC) day
+    pub max_daily_total: Option<String>,
+    /// If present, only these addresses are allowed as destination
+    pub allowed_addresses: Option<Vec<String>>,
+    #[serde(default)]
+    pub deny_dao_withdraw: bool,
+    pub two_person_rule: Option<TwoPersonRuleConfig>,
+}
+
+/// More than `threshold` (unit: CKB) sent from one of the two `accounts` can not be signed by
+/// the account alone, it must be co-signed by a multisig lock. This rule can not be overridden
+/// by `--override-policy`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TwoPersonRuleConfig {
+    pub threshold: String,
+    pub accounts: Vec<String>,
+}
+
+struct TwoPersonRule {
+    threshold: u64,
+    accounts: Vec<H160>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct PolicyLedger {
+    date: String,
+    total: u64,
+}
+
+pub struct SigningPolicy {
+    max_per_tx: Option<u64>,
+    max_daily_total: Option<u64>,
+    allowed_addresses: Option<Vec<Address>>,
+    deny_dao_withdraw: bool,
+    two_person_rule: Option<TwoPersonRule>,
+    ledger_path: PathBuf,
+}
+
+impl SigningPolicy {
+    /// Load the policy from ckb-cli directory, return `None` when no policy file exists
+    pub fn load(ckb_cli_dir: &Path) -> Result<Option<SigningPolicy>, String> {
+        let policy_path = ckb_cli_dir.join(POLICY_FILE);
+        if !policy_path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&policy_path).map_err(|err| err.to_string())?;
+        let config: PolicyConfig = serde_json::from_str(&content)
+            .map_err(|err| format!("Parse policy file {:?} failed: {}", policy_path, err))?;
+        SigningPolicy::from_config(config, ckb_cli_dir.join(POLICY_LEDGER_FILE)).map(Some)
+    }
+
+    pub fn from_config(
+        config: PolicyConfig,
+        ledger_path: PathBuf,
+    ) -> Result<SigningPolicy, String> {
+        let max_per_tx = config
+            .max_per_tx
+            .map(|value| CapacityParser.parse(&value))
+            .transpose()?;
+        let max_daily_total = config
+            .max_daily_total
+            .map(|value| CapacityParser.parse(&value))
+            .transpose()?;
+        let allowed_addresses = config
+            .allowed_addresses
+            .map(|addresses| {
+                addresses
+                    .iter()
+                    .map(|address| AddressParser.parse(address))
+                    .collect::<Result<Vec<_>, String>>()
+            })
+            .transpose()?;
+        let two_person_rule = config
+            .two_person_rule
+            .map(|rule| {
+                let threshold = CapacityParser.parse(&rule.threshold)?;
+                let accounts = rule
+                    .accounts
+                    .iter()
+                    .map(|lock_arg| FixedHashParser::<H160>::default().parse(lock_arg))
+                    .collect::<Result<Vec<_>, String>>()?;
+                if accounts.len() != 2 || accounts[0] == accounts[1] {
+                    return Err("two_person_rule requires two different accounts".to_owned());
+                }
+                Ok(TwoPersonRule {
+                    threshold,
+                    accounts,
+                })
+            })
+            .transpose()?;
+        Ok(SigningPolicy {
+            max_per_tx,
+            max_daily_total,
+            allowed_addresses,
+            deny_dao_withdraw: config.deny_dao_withdraw,
+            two_person_rule,
+            ledger_path,
+        })
+    }
+
+    /// Return all violated rules of this request
+    pub fn check(&self, request: &SignRequest) -> Result<Vec<String>, String> {
+        let mut violations = Vec::new();
+        if let Some(max_per_tx) = self.max_per_tx {
+            if request.capacity > max_per_tx {
+                violations.push(format!(
+                    "capacity {} exceed max_per_tx {} (shannons)",
+                    request.capacity, max_per_tx
+                ));
+            }
+        }
+        if let Some(max_daily_total) = self.max_daily_total {
+            let signed_today = self.signed_today()?;
+            if signed_today + request.capacity > max_daily_total {
+                violations.push(format!(
+                    "daily total {} + {} exceed max_daily_total {} (shannons)",
+                    signed_today, request.capacity, max_daily_total
+                ));
+            }
+        }
+        if let Some(ref allowed_addresses) = self.allowed_addresses {
+            for to_address in request.to_addresses {
+                if !allowed_addresses.contains(to_address) {
+                    violations.push(format!(
+                        "target address (lock_arg={:x}) is not in allowed_addresses",
+                        to_address.hash()
+                    ));
+                }
+            }
+        }
+        if self.deny_dao_withdraw && request.kind == SignKind::WithdrawDao {
+            violations.push("withdraw from NervosDAO is denied".to_owned());
+        }
+        Ok(violations)
+    }
+
+    /// Refuse a request of one of the two accounts above the threshold unless it is co-signed,
+    /// the co-signing flows are pointed out
+    pub fn check_two_person_rule(&self, request: &SignRequest) -> Result<(), String> {
+        let rule = match self.two_person_rule.as_ref() {
+            Some(rule) => rule,
+            None => return Ok(()),
+        };
+        let from = request.from_address.hash();
+        if request.cosigned || request.capacity <= rule.threshold || !rule.accounts.contains(from) {
+            return Ok(());
+        }
+        Err(format!(
+            "Two-person rule: {0} shannons sent by account {1:x} exceed the threshold {2} shannons, \
+             it must be co-signed by accounts {3:x} and {4:x} (--override-policy does not apply).\n\
+             Keep the capacity in their 2-of-2 multisig address \
+             (`wallet multisig create-config --sighash-lock-arg {3:#x} --sighash-lock-arg {4:#x} --threshold 2`) \
+             and send it by `wallet multisig build-tx` + `sign-tx` (by both accounts) + `combine-and-send`, \
+             or by a tx helper file (`tx add-multisig-config`, `tx sign-inputs` by both accounts, `tx send`)",
+            request.capacity, from, rule.threshold, rule.accounts[0], rule.accounts[1],
+        ))
+    }
+
+    /// Check the request, the violations can only be passed with `--override-policy` and
+    /// an explicit confirmation from user (except the two-person rule).
+    pub fn enforce(&self, request: &SignRequest, override_policy: bool) -> Result<(), String> {
+        self.check_two_person_rule(request)?;
+        let violations = self.check(request)?;
+        if violations.is_empty() {
+            return Ok(());
+        }
+        let message = format!(
+            "Signing policy violated by account {:x}:\n{}",
+            request.from_address.hash(),
+            violations
+                .iter()
+                .map(|violation| format!("  - {}", violation))
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+        if !override_policy {
+            return Err(format!("{}\nuse --override-policy to sign anyway", message));
+        }
+        eprintln!("[WARNING] {}", message);
+        if read_confirmation(t("override-policy"))? {
+            Ok(())
+        } else {
+            Err("Canceled by user".to_owned())
+        }
+    }
+
+    /// Record signed capacity into daily total
+    pub fn record(&self, capacity: u64) -> Result<(), String> {
+        if self.max_daily_total.is_none() {
+            return Ok(());
+        }
+        let ledger = PolicyLedger {
+            date: today(),
+            total: self.signed_today()? + capacity,
+        };
+        let content = serde_json::to_string_pretty(&ledger).map_err(|err| err.to_string())?;
+        fs::write(&self.ledger_path, content).map_err(|err| err.to_string())
+    }
+
+    fn signed_today(&self) -> Result<u64, String> {
+        if !self.ledger_path.exists() {
+            return Ok(0);
+        }
+        let content = fs::read_to_string(&self.ledger_path).map_err(|err| err.to_string())?;
+        // Fail closed, a broken ledger must not reset the daily total
+        let ledger: PolicyLedger = serde_json::from_str(&content).map_err(|err| {
+            format!(
+                "Parse policy ledger {:?} failed: {}, fix or remove it to sign again",
+                self.ledger_path, err
+            )
+        })?;
+        if ledger.date == today() {
+            Ok(ledger.total)
+        } else {
+            Ok(0)
+        }
+    }
+}
+
+/// Load the signing policy and enforce it on a request, all the signing commands go through
+/// it before a transaction is signed. The returned policy records the capacity once the
+/// transaction is sent.
+pub fn check_policy(
+    request: &SignRequest,
+    override_policy: bool,
+) -> Result<Option<SigningPolicy>, String> {
+    let policy = SigningPolicy::load(&get_ckb_cli_dir())?;
+    if let Some(ref policy) = policy {
+        policy.enforce(request, override_policy)?;
+    }
+    APPROVED_SIGNERS
+        .write()
+        .expect("Approved signers lock poisoned")
+        .insert(request.from_address.hash().clone());
+    Ok(policy)
+}
+
+/// Checked before a transaction is signed: when a policy exists, the request of the signer must
+/// have passed `check_policy` in the current command
+pub fn authorize_signer(lock_arg: &H160) -> Result<(), String> {
+    if APPROVED_SIGNERS
+        .read()
+        .expect("Approved signers lock poisoned")
+        .contains(lock_arg)
+        || SigningPolicy::load(&get_ckb_cli_dir())?.is_none()
+    {
+        return Ok(());
+    }
+    Err(format!(
+        "The signing policy is not checked for account {:x} by this command, refuse to sign",
+        lock_arg
+    ))
+}
+
+/// Forget the approvals of the previous command (interactive mode and the server run many)
+pub fn clear_approvals() {
+    APPROVED_SIGNERS
+        .write()
+        .expect("Approved signers lock poisoned")
+        .clear();
+}
+
+/// The address a policy sees for a lock: the secp256k1 sighash address, or the lock hash
+/// prefix for other locks (never in `allowed_addresses`)
+pub fn policy_address(lock: &Script, genesis_info: &GenesisInfo) -> Result<Address, String> {
+    let args = lock.args().raw_data();
+    if &lock.code_hash() == genesis_info.secp_type_hash()
+        && lock.hash_type() == ScriptHashType::Type.into()
+        && args.len() == 20
+    {
+        Address::from_lock_arg(&args)
+    } else {
+        Address::from_lock_arg(&lock.calc_script_hash().as_slice()[0..20])
+    }
+}
+
+/// The capacity a transaction takes from the lock (its inputs minus the outputs back to it,
+/// the fee included) and the addresses of the other outputs, for signing a transaction not
+/// built by ckb-cli
+pub fn transaction_outflow(
+    transaction: &TransactionView,
+    input_cells: &[CellOutput],
+    lock: &Script,
+    genesis_info: &GenesisInfo,
+) -> Result<(u64, Vec<Address>), String> {
+    let input_capacity: u64 = input_cells
+        .iter()
+        .filter(|output| output.lock().as_slice() == lock.as_slice())
+        .map(|output| Unpack::<u64>::unpack(&output.capacity()))
+        .sum();
+    let mut returned_capacity: u64 = 0;
+    let mut to_addresses = Vec::new();
+    for output in transaction.outputs().into_iter() {
+        if output.lock().as_slice() == lock.as_slice() {
+            returned_capacity += Unpack::<u64>::unpack(&output.capacity());
+        } else {
+            let address = policy_address(&output.lock(), genesis_info)?;
+            if !to_addresses.contains(&address) {
+                to_addresses.push(address);
+            }
+        }
+    }
+    Ok((
+        input_capacity.saturating_sub(returned_capacity),
+        to_addresses,
+    ))
+}
+
+fn today() -> String {
+    Utc::now().format("%Y-%m-%d").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ckb_sdk::ONE_CKB;
+
+    #[test]
+    fn test_check_policy() {
+        let config = PolicyConfig {
+            max_per_tx: Some("100".to_owned()),
+            max_daily_total: None,
+            allowed_addresses: None,
+            deny_dao_withdraw: true,
+            two_person_rule: None,
+        };
+        let policy = SigningPolicy::from_config(config, PathBuf::from("/not/exists")).unwrap();
+        let from_address = Address::from_lock_arg(&[0u8; 20]).unwrap();
+        let to_address = Address::from_lock_arg(&[1u8; 20]).unwrap();
+        let mut request = SignRequest {
+            kind: SignKind::Transfer,
+            from_address: &from_address,
+            to_addresses: std::slice::from_ref(&to_address),
+            capacity: 100 * ONE_CKB,
+            cosigned: false,
+        };
+        assert!(policy.check(&request).unwrap().is_empty());
+        request.capacity = 101 * ONE_CKB;
+        assert_eq!(policy.check(&request).unwrap().len(), 1);
+        request.kind = SignKind::WithdrawDao;
+        assert_eq!(policy.check(&request).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_two_person_rule() {
+        let config = PolicyConfig {
+            two_person_rule: Some(TwoPersonRuleConfig {
+                threshold: "1000".to_owned(),
+                accounts: vec![
+                    format!("{:x}", H160([0u8; 20])),
+                    format!("{:x}", H160([2u8; 20])),
+                ],
+            }),
+            ..Default::default()
+        };
+        let policy = SigningPolicy::from_config(config, PathBuf::from("/not/exists")).unwrap();
+        let from_address = Address::from_lock_arg(&[0u8; 20]).unwrap();
+        let other_address = Address::from_lock_arg(&[1u8; 20]).unwrap();
+        let to_address = Address::from_lock_arg(&[3u8; 20]).unwrap();
+        let mut request = SignRequest {
+            kind: SignKind::Transfer,
+            from_address: &from_address,
+            to_addresses: std::slice::from_ref(&to_address),
+            capacity: 1000 * ONE_CKB,
+            cosigned: false,
+        };
+        assert!(policy.check_two_person_rule(&request).is_ok());
+        request.capacity = 1001 * ONE_CKB;
+        assert!(policy.check_two_person_rule(&request).is_err());
+        // Not overridable
+        assert!(policy.enforce(&request, true).is_err());
+        request.cosigned = true;
+        assert!(policy.check_two_person_rule(&request).is_ok());
+        request.cosigned = false;
+        request.from_address = &other_address;
+        assert!(policy.check_two_person_rule(&request).is_ok());
+    }
+
+    #[test]
+    fn test_approved_signers() {
+        let lock_arg = H160::from_slice(&[2u8; 20]).unwrap();
+        APPROVED_SIGNERS.write().unwrap().insert(lock_arg.clone());
+        assert!(authorize_signer(&lock_arg).is_ok());
+        clear_approvals();
+        assert!(!APPROVED_SIGNERS.read().unwrap().contains(&lock_arg));
+    }
+
+    #[test]
+    fn test_corrupt_ledger() {
+        let ledger_path = std::env::temp_dir().join("ckb-cli-test-policy-ledger");
+        fs::write(&ledger_path, "{\"date\": ").unwrap();
+        let config = PolicyConfig {
+            max_daily_total: Some("100".to_owned()),
+            ..Default::default()
+        };
+        let policy = SigningPolicy::from_config(config, ledger_path.clone()).unwrap();
+        let from_address = Address::from_lock_arg(&[0u8; 20]).unwrap();
+        let request = SignRequest {
+            kind: SignKind::Transfer,
+            from_address: &from_address,
+            to_addresses: &[],
+            capacity: ONE_CKB,
+            cosigned: false,
+        };
+        assert!(policy.check(&request).is_err());
+        assert!(policy.record(ONE_CKB).is_err());
+        fs::remove_file(&ledger_path).unwrap();
+    }
+}
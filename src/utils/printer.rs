@@ -1,10 +1,15 @@
 use std::env;
 use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use atty;
 use colored::Colorize;
 
+use ckb_sdk::ONE_CKB;
+use serde_json::json;
+
 use crate::utils::json_color::Colorizer;
+use crate::utils::tabular::Rows;
 use crate::utils::yaml_ser;
 
 pub fn is_a_tty(stderr: bool) -> bool {
@@ -20,10 +25,18 @@ pub fn is_term_dumb() -> bool {
     env::var("TERM").ok() == Some(String::from("dumb"))
 }
 
+/// Version of the document printed by `--output-format json --structured-output` outside
+/// interactive mode, it is increased when the document changes incompatibly
+pub const OUTPUT_SCHEMA_VERSION: u32 = 1;
+
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum OutputFormat {
     Yaml,
     Json,
+    /// One row per item of list-shaped outputs, with a header line
+    Csv,
+    /// Like `Csv` but the columns are aligned for reading in terminal
+    Table,
 }
 
 impl fmt::Display for OutputFormat {
@@ -34,6 +47,8 @@ impl fmt::Display for OutputFormat {
             match self {
                 OutputFormat::Yaml => "yaml",
                 OutputFormat::Json => "json",
+                OutputFormat::Csv => "csv",
+                OutputFormat::Table => "table",
             }
         )
     }
@@ -44,11 +59,196 @@ impl OutputFormat {
         match format {
             "yaml" => Ok(OutputFormat::Yaml),
             "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            "table" => Ok(OutputFormat::Table),
             _ => Err(format!("Invalid output format: {}", format)),
         }
     }
 }
 
+/// The unit of the capacities in the output of commands, selected by `--capacity-unit`
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CapacityUnit {
+    /// Raw shannons as returned by the node (default)
+    Shannon = 0,
+    /// CKBytes with thousands separators and 8 decimals: `1,250.00000000`
+    Ckb = 1,
+    /// CKBytes without trailing zeros (`1,250.5 CKB`), shannons when less than 1 CKB
+    Auto = 2,
+}
+
+impl CapacityUnit {
+    pub fn from_str(input: &str) -> Result<CapacityUnit, String> {
+        match input {
+            "shannon" => Ok(CapacityUnit::Shannon),
+            "ckb" => Ok(CapacityUnit::Ckb),
+            "auto" => Ok(CapacityUnit::Auto),
+            _ => Err(format!("Invalid capacity unit: {}", input)),
+        }
+    }
+}
+
+impl fmt::Display for CapacityUnit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let unit = match self {
+            CapacityUnit::Shannon => "shannon",
+            CapacityUnit::Ckb => "ckb",
+            CapacityUnit::Auto => "auto",
+        };
+        write!(f, "{}", unit)
+    }
+}
+
+static CAPACITY_UNIT: AtomicUsize = AtomicUsize::new(CapacityUnit::Shannon as usize);
+
+pub fn set_capacity_unit(unit: CapacityUnit) {
+    CAPACITY_UNIT.store(unit as usize, Ordering::SeqCst);
+}
+
+pub fn capacity_unit() -> CapacityUnit {
+    match CAPACITY_UNIT.load(Ordering::SeqCst) {
+        1 => CapacityUnit::Ckb,
+        2 => CapacityUnit::Auto,
+        _ => CapacityUnit::Shannon,
+    }
+}
+
+fn group_thousands(value: u128) -> String {
+    let digits = value.to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (idx, digit) in digits.chars().enumerate() {
+        if idx > 0 && (digits.len() - idx) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// Format a capacity (in shannons, negative for balance changes) in the unit
+pub fn format_capacity(shannons: i128, unit: CapacityUnit) -> String {
+    let sign = if shannons < 0 { "-" } else { "" };
+    let value = if shannons < 0 { -shannons } else { shannons } as u128;
+    let one_ckb = u128::from(ONE_CKB);
+    let (integer, fraction) = (value / one_ckb, value % one_ckb);
+    match unit {
+        CapacityUnit::Shannon => format!("{}{}", sign, value),
+        CapacityUnit::Ckb => format!("{}{}.{:08}", sign, group_thousands(integer), fraction),
+        CapacityUnit::Auto if integer == 0 && fraction > 0 => {
+            format!("{}{} shannons", sign, group_thousands(fraction))
+        }
+        CapacityUnit::Auto if fraction == 0 => {
+            format!("{}{} CKB", sign, group_thousands(integer))
+        }
+        CapacityUnit::Auto => {
+            let decimals = format!("{:08}", fraction);
+            format!(
+                "{}{}.{} CKB",
+                sign,
+                group_thousands(integer),
+                decimals.trim_end_matches('0')
+            )
+        }
+    }
+}
+
+// Fields holding capacities in shannons, the `total` of block rewards (`chain block-reward`,
+// `chain miner-reward`) is recognized by the parent field
+fn is_capacity_key(parent: &str, key: &str) -> bool {
+    key == "capacity"
+        || key.ends_with("_capacity")
+        || key.ends_with("-capacity")
+        || [
+            "balance_change",
+            "interest",
+            "fee",
+            "tx_fee",
+            "primary",
+            "secondary",
+            "proposal_reward",
+        ]
+        .contains(&key)
+        || (key == "total" && (parent == "reward" || parent == "total"))
+}
+
+// Numbers, decimal strings and hex strings (JSON-RPC `Capacity`)
+fn parse_shannons(value: &serde_json::Value) -> Option<i128> {
+    match value {
+        serde_json::Value::Number(number) => number
+            .as_u64()
+            .map(i128::from)
+            .or_else(|| number.as_i64().map(i128::from)),
+        serde_json::Value::String(content) => {
+            if content.starts_with("0x") {
+                u64::from_str_radix(&content[2..], 16).ok().map(i128::from)
+            } else {
+                content.parse::<i128>().ok()
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Convert the capacity fields of the output to the unit
+pub fn convert_capacities(value: &mut serde_json::Value, unit: CapacityUnit) {
+    convert_capacities_in(value, "", unit)
+}
+
+fn convert_capacities_in(value: &mut serde_json::Value, parent: &str, unit: CapacityUnit) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, item) in map.iter_mut() {
+                match parse_shannons(item) {
+                    Some(shannons) if is_capacity_key(parent, key) => {
+                        *item = serde_json::Value::String(format_capacity(shannons, unit));
+                    }
+                    _ => convert_capacities_in(item, key, unit),
+                }
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for item in items {
+                convert_capacities_in(item, parent, unit);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Wrap the output of a command (rendered as JSON without color, or plain text like a hash) as
+/// the structured document: `{"schema_version": 1, "result": <output>}`
+pub fn structured_output(output: &str) -> serde_json::Value {
+    let result = serde_json::from_str(output)
+        .unwrap_or_else(|_| serde_json::Value::String(output.trim_end().to_owned()));
+    json!({
+        "schema_version": OUTPUT_SCHEMA_VERSION,
+        "result": result,
+    })
+}
+
+/// Pick a field of the output by a path like `live_cells.0.capacity` or `live_cells[0].capacity`
+/// (`.` for the whole output), strings are returned without quotes and other values as compact
+/// JSON.
+pub fn extract_field(value: &serde_json::Value, path: &str) -> Result<String, String> {
+    let normalized = path.replace('[', ".").replace(']', "");
+    let mut current = value;
+    for segment in normalized.split('.').filter(|segment| !segment.is_empty()) {
+        let next = match current {
+            serde_json::Value::Object(map) => map.get(segment),
+            serde_json::Value::Array(items) => segment
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| items.get(index)),
+            _ => None,
+        };
+        current = next.ok_or_else(|| format!("Field not found in output: {}", path))?;
+    }
+    Ok(match current {
+        serde_json::Value::String(content) => content.clone(),
+        other => other.to_string(),
+    })
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum ColorWhen {
     Auto,
@@ -99,17 +299,35 @@ where
     T: serde::ser::Serialize,
 {
     fn render(&self, format: OutputFormat, color: bool) -> String {
-        match format {
-            OutputFormat::Yaml => yaml_ser::to_string(self, color).unwrap(),
-            OutputFormat::Json => {
-                let value = serde_json::to_value(self).unwrap();
-                if color {
-                    Colorizer::arbitrary().colorize_json_value(&value).unwrap()
-                } else {
-                    serde_json::to_string_pretty(&value).unwrap()
-                }
+        let unit = capacity_unit();
+        if unit != CapacityUnit::Shannon {
+            let mut value = serde_json::to_value(self).unwrap();
+            convert_capacities(&mut value, unit);
+            return render_serialize(&value, format, color);
+        }
+        render_serialize(self, format, color)
+    }
+}
+
+fn render_serialize<T: ?Sized + serde::ser::Serialize>(
+    content: &T,
+    format: OutputFormat,
+    color: bool,
+) -> String {
+    match format {
+        OutputFormat::Yaml => yaml_ser::to_string(content, color).unwrap(),
+        OutputFormat::Json => {
+            let value = serde_json::to_value(content).unwrap();
+            if color {
+                Colorizer::arbitrary().colorize_json_value(&value).unwrap()
+            } else {
+                serde_json::to_string_pretty(&value).unwrap()
             }
         }
+        OutputFormat::Csv => Rows::from_value(&serde_json::to_value(content).unwrap()).to_csv(),
+        OutputFormat::Table => {
+            Rows::from_value(&serde_json::to_value(content).unwrap()).to_table(color)
+        }
     }
 }
 
@@ -158,3 +376,67 @@ impl<'a> TypedStr<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_field() {
+        let output = json!({
+            "tx_hash": "0x01",
+            "live_cells": [{"capacity": 100}, {"capacity": 200}],
+            "total": null,
+        });
+        assert_eq!(extract_field(&output, "tx_hash").unwrap(), "0x01");
+        assert_eq!(
+            extract_field(&output, "live_cells.1.capacity").unwrap(),
+            "200"
+        );
+        assert_eq!(
+            extract_field(&output, "live_cells[0]").unwrap(),
+            r#"{"capacity":100}"#
+        );
+        assert_eq!(extract_field(&output, "total").unwrap(), "null");
+        assert!(extract_field(&output, "live_cells.2").is_err());
+        assert!(extract_field(&output, "tx_hash.0").is_err());
+        assert_eq!(extract_field(&json!("0x02"), ".").unwrap(), "0x02");
+    }
+
+    #[test]
+    fn test_format_capacity() {
+        let one = i128::from(ONE_CKB);
+        assert_eq!(
+            format_capacity(1250 * one, CapacityUnit::Ckb),
+            "1,250.00000000"
+        );
+        assert_eq!(format_capacity(-one / 2, CapacityUnit::Ckb), "-0.50000000");
+        assert_eq!(
+            format_capacity(1_000_000 * one, CapacityUnit::Auto),
+            "1,000,000 CKB"
+        );
+        assert_eq!(
+            format_capacity(one + one / 4, CapacityUnit::Auto),
+            "1.25 CKB"
+        );
+        assert_eq!(format_capacity(1234, CapacityUnit::Auto), "1,234 shannons");
+        assert_eq!(format_capacity(1234, CapacityUnit::Shannon), "1234");
+
+        let mut output = json!({
+            "total_capacity": 100_000_000,
+            "live_cells": [{"capacity": "0x5f5e100", "number": 7}],
+            "fee_rate": 1000,
+            "total": {"blocks": 2, "total": 200_000_000},
+        });
+        convert_capacities(&mut output, CapacityUnit::Ckb);
+        assert_eq!(
+            output,
+            json!({
+                "total_capacity": "1.00000000",
+                "live_cells": [{"capacity": "1.00000000", "number": 7}],
+                "fee_rate": 1000,
+                "total": {"blocks": 2, "total": "2.00000000"},
+            })
+        );
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ckb_sdk::NetworkType;
+use serde_derive::Deserialize;
+
+use super::other::get_ckb_cli_dir;
+use super::printer::OutputFormat;
+
+/// Named sets of defaults in `~/.ckb-cli/config.toml`, selected by `--profile` (or
+/// `default_profile`). The command line arguments take precedence over the profile:
+///
+/// ```toml
+/// default_profile = "testnet"
+///
+/// [profiles.testnet]
+/// url = "http://127.0.0.1:8114"
+/// network = "testnet"
+/// output_format = "json"
+/// fee_rate = 1000
+/// keystore_dir = "~/.ckb-cli/keystore-testnet"
+/// index_dir = "~/.ckb-cli/index-testnet"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ProfileFile {
+    pub default_profile: Option<String>,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct Profile {
+    pub url: Option<String>,
+    /// Expected network of the node: mainnet, testnet or dev
+    pub network: Option<String>,
+    pub output_format: Option<String>,
+    /// Default `--fee-rate` (shannons/KB) when neither `--tx-fee` nor `--fee-rate` is given
+    pub fee_rate: Option<u64>,
+    pub keystore_dir: Option<String>,
+    pub index_dir: Option<String>,
+}
+
+impl ProfileFile {
+    pub fn parse(content: &str) -> Result<ProfileFile, String> {
+        toml::from_str(content).map_err(|err| err.to_string())
+    }
+
+    /// Empty if the file not exists
+    pub fn load(path: &Path) -> Result<ProfileFile, String> {
+        if !path.exists() {
+            return Ok(ProfileFile::default());
+        }
+        let content = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        ProfileFile::parse(&content).map_err(|err| format!("Parse {:?} failed: {}", path, err))
+    }
+
+    pub fn load_default() -> Result<ProfileFile, String> {
+        ProfileFile::load(&get_ckb_cli_dir().join("config.toml"))
+    }
+
+    /// The profile by name, or the default profile (if any)
+    pub fn select(&self, name: Option<&str>) -> Result<Option<(String, Profile)>, String> {
+        let name = match name.or_else(|| self.default_profile.as_ref().map(String::as_str)) {
+            Some(name) => name,
+            None => return Ok(None),
+        };
+        self.profiles
+            .get(name)
+            .map(|profile| Some((name.to_owned(), profile.clone())))
+            .ok_or_else(|| {
+                format!(
+                    "Profile not found: {}, available: {}",
+                    name,
+                    self.profiles.keys().cloned().collect::<Vec<_>>().join(", ")
+                )
+            })
+    }
+}
+
+impl Profile {
+    pub fn network_type(&self) -> Result<Option<NetworkType>, String> {
+        self.network
+            .as_ref()
+            .map(|network| match network.as_str() {
+                "mainnet" | "ckb" => Ok(NetworkType::MainNet),
+                "testnet" | "ckb_testnet" => Ok(NetworkType::TestNet),
+                "dev" | "ckb_dev" => Ok(NetworkType::Dev),
+                _ => Err(format!(
+                    "Invalid network: {}, expected: mainnet, testnet, dev",
+                    network
+                )),
+            })
+            .transpose()
+    }
+
+    pub fn output_format(&self) -> Result<Option<OutputFormat>, String> {
+        self.output_format
+            .as_ref()
+            .map(|format| OutputFormat::from_str(format))
+            .transpose()
+    }
+
+    pub fn keystore_dir(&self) -> Option<PathBuf> {
+        self.keystore_dir.as_ref().map(|path| expand_home(path))
+    }
+
+    pub fn index_dir(&self) -> Option<PathBuf> {
+        self.index_dir.as_ref().map(|path| expand_home(path))
+    }
+}
+
+// `~/...` is relative to the home directory
+fn expand_home(path: &str) -> PathBuf {
+    if path.starts_with("~/") {
+        if let Some(home) = dirs::home_dir() {
+            return home.join(&path[2..]);
+        }
+    }
+    PathBuf::from(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_profile() {
+        let file = ProfileFile::parse(
+            r#"
+            default_profile = "testnet"
+
+            [profiles.testnet]
+            url = "http://127.0.0.1:8114"
+            network = "testnet"
+            fee_rate = 1000
+
+            [profiles.mainnet]
+            network = "mainnet"
+            output_format = "json"
+            "#,
+        )
+        .unwrap();
+        let (name, profile) = file.select(None).unwrap().unwrap();
+        assert_eq!(name, "testnet");
+        assert_eq!(profile.url.as_ref().unwrap(), "http://127.0.0.1:8114");
+        assert_eq!(profile.fee_rate, Some(1000));
+        assert_eq!(profile.network_type(), Ok(Some(NetworkType::TestNet)));
+
+        let (_, profile) = file.select(Some("mainnet")).unwrap().unwrap();
+        assert_eq!(profile.output_format(), Ok(Some(OutputFormat::Json)));
+        assert_eq!(profile.url, None);
+        assert!(file.select(Some("dev")).is_err());
+        assert_eq!(ProfileFile::default().select(None), Ok(None));
+    }
+}
@@ -0,0 +1,273 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::BufWriter;
+use std::path::Path;
+
+use qrcode::{types::Color, EcLevel, Version};
+
+/// A QR code symbol of the data, medium error correction level (recovers ~15% damage), a
+/// trade-off between the size of the frames and the scanning reliability from screens.
+pub struct QrCode(qrcode::QrCode);
+
+impl QrCode {
+    /// Encode by the smallest version the data fits in
+    pub fn encode(data: &[u8]) -> Result<QrCode, String> {
+        qrcode::QrCode::with_error_correction_level(data, EcLevel::M)
+            .map(QrCode)
+            .map_err(|err| format!("Encode QR code of {} bytes failed: {}", data.len(), err))
+    }
+
+    pub fn version(&self) -> usize {
+        match self.0.version() {
+            Version::Normal(version) | Version::Micro(version) => version as usize,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.0.width()
+    }
+
+    /// The module at column x and row y is dark
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        self.0[(x, y)] == Color::Dark
+    }
+
+    // The 4 modules quiet zone around the symbol is light
+    fn is_dark(&self, x: isize, y: isize) -> bool {
+        x >= 0
+            && y >= 0
+            && (x as usize) < self.size()
+            && (y as usize) < self.size()
+            && self.get(x as usize, y as usize)
+    }
+
+    /// Two rows per line by half block characters, with the 4 modules quiet zone. Dark modules
+    /// are drawn in the background color, so it scans on both dark and light terminals.
+    pub fn to_terminal_string(&self) -> String {
+        let mut output = String::new();
+        let end = self.size() as isize + 4;
+        let mut y = -4;
+        while y < end {
+            for x in -4..end {
+                output.push(match (self.is_dark(x, y), self.is_dark(x, y + 1)) {
+                    (true, true) => ' ',
+                    (true, false) => '▄',
+                    (false, true) => '▀',
+                    (false, false) => '█',
+                });
+            }
+            output.push('\n');
+            y += 2;
+        }
+        output
+    }
+
+    /// Grayscale pixels, `scale` pixels per module, with the 4 modules quiet zone: (width, pixels)
+    pub fn to_pixels(&self, scale: usize) -> (usize, Vec<u8>) {
+        let width = (self.size() + 8) * scale;
+        let mut pixels = Vec::with_capacity(width * width);
+        for py in 0..width {
+            for px in 0..width {
+                let (x, y) = ((px / scale) as isize - 4, (py / scale) as isize - 4);
+                pixels.push(if self.is_dark(x, y) { 0 } else { 255 });
+            }
+        }
+        (width, pixels)
+    }
+
+    /// Grayscale PNG image, `scale` pixels per module, with the 4 modules quiet zone
+    pub fn write_png(&self, path: &Path, scale: usize) -> Result<(), String> {
+        let (width, pixels) = self.to_pixels(scale);
+        let file = fs::File::create(path)
+            .map_err(|err| format!("Create {} failed: {}", path.display(), err))?;
+        let mut encoder = png::Encoder::new(BufWriter::new(file), width as u32, width as u32);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&pixels))
+            .map_err(|err| format!("Write {} failed: {}", path.display(), err))
+    }
+}
+
+const FRAME_PREFIX: &str = "ckb-cli";
+
+/// Split a text into frames of at most `chunk_size` bytes content, each one is encoded into a
+/// QR code: `ckb-cli:<crc32 of the text>:<index>/<total>:<content>`. The frames can be scanned
+/// in any order, the crc32 tells the frames of different texts apart.
+pub fn split_frames(text: &str, chunk_size: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        let mut end = chunk_size.min(rest.len());
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        // A character longer than the chunk size
+        if end == 0 {
+            end = rest.chars().next().unwrap().len_utf8();
+        }
+        chunks.push(&rest[..end]);
+        rest = &rest[end..];
+    }
+    let checksum = crc32fast::hash(text.as_bytes());
+    let total = chunks.len();
+    chunks
+        .into_iter()
+        .enumerate()
+        .map(|(idx, chunk)| {
+            format!(
+                "{}:{:08x}:{}/{}:{}",
+                FRAME_PREFIX,
+                checksum,
+                idx + 1,
+                total,
+                chunk
+            )
+        })
+        .collect()
+}
+
+/// Collect the scanned frames (in any order, duplicates ignored) of one text
+#[derive(Default)]
+pub struct FrameCollector {
+    checksum: Option<u32>,
+    total: usize,
+    chunks: BTreeMap<usize, String>,
+}
+
+impl FrameCollector {
+    /// Returns false if the frame was already added
+    pub fn add(&mut self, frame: &str) -> Result<bool, String> {
+        let invalid = || format!("Invalid frame: {}", frame);
+        let mut parts = frame.splitn(4, ':');
+        if parts.next() != Some(FRAME_PREFIX) {
+            return Err(invalid());
+        }
+        let checksum = parts
+            .next()
+            .and_then(|value| u32::from_str_radix(value, 16).ok())
+            .ok_or_else(invalid)?;
+        let (index, total) = parts
+            .next()
+            .and_then(|value| {
+                let mut numbers = value.splitn(2, '/').map(|number| number.parse::<usize>());
+                match (numbers.next(), numbers.next()) {
+                    (Some(Ok(index)), Some(Ok(total))) => Some((index, total)),
+                    _ => None,
+                }
+            })
+            .filter(|(index, total)| *index >= 1 && index <= total)
+            .ok_or_else(invalid)?;
+        let content = parts.next().ok_or_else(invalid)?;
+
+        match self.checksum {
+            Some(current) if current != checksum || self.total != total => {
+                return Err(format!(
+                    "The frame belongs to another transaction (checksum: {:08x}, expected: {:08x})",
+                    checksum, current
+                ));
+            }
+            Some(_) => {}
+            None => {
+                self.checksum = Some(checksum);
+                self.total = total;
+            }
+        }
+        if self.chunks.contains_key(&index) {
+            return Ok(false);
+        }
+        self.chunks.insert(index, content.to_owned());
+        Ok(true)
+    }
+
+    pub fn received(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.total > 0 && self.chunks.len() == self.total
+    }
+
+    /// The text, checked by the crc32
+    pub fn text(&self) -> Result<String, String> {
+        if !self.is_complete() {
+            return Err(format!(
+                "Missing frames: {}",
+                (1..=self.total)
+                    .filter(|index| !self.chunks.contains_key(index))
+                    .map(|index| index.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+        let text = self.chunks.values().cloned().collect::<String>();
+        if Some(crc32fast::hash(text.as_bytes())) != self.checksum {
+            return Err("Checksum mismatch, the frames are corrupted".to_owned());
+        }
+        Ok(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        let qr = QrCode::encode(b"hello").unwrap();
+        assert_eq!((qr.version(), qr.size()), (1, 21));
+        assert!(QrCode::encode(&[0; 2332]).is_err());
+    }
+
+    // Decoded by an independent decoder from the PNG file
+    #[test]
+    fn test_png_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("ckb-cli-qr-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let text = "{\"transaction\":{\"version\":\"0x0\"},\"signatures\":{}}".repeat(20);
+        for (idx, frame) in split_frames(&text, 300).iter().enumerate() {
+            let path = dir.join(format!("frame-{}.png", idx));
+            QrCode::encode(frame.as_bytes())
+                .unwrap()
+                .write_png(&path, 4)
+                .unwrap();
+
+            let decoder = png::Decoder::new(fs::File::open(&path).unwrap());
+            let (info, mut reader) = decoder.read_info().unwrap();
+            let mut pixels = vec![0; info.buffer_size()];
+            reader.next_frame(&mut pixels).unwrap();
+            let (width, height) = (info.width as usize, info.height as usize);
+            let mut image = rqrr::PreparedImage::prepare_from_greyscale(width, height, |x, y| {
+                pixels[y * width + x]
+            });
+            let grids = image.detect_grids();
+            assert_eq!(grids.len(), 1);
+            let (_meta, content) = grids[0].decode().unwrap();
+            assert_eq!(&content, frame);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_frames() {
+        let text = "{\"transaction\":\"测试\",\"signatures\":{}}".repeat(5);
+        let frames = split_frames(&text, 30);
+        assert!(frames.len() > 1);
+        let mut collector = FrameCollector::default();
+        for frame in frames.iter().rev() {
+            assert!(!collector.is_complete());
+            assert_eq!(collector.add(frame), Ok(true));
+        }
+        assert_eq!(collector.add(&frames[0]), Ok(false));
+        assert_eq!(collector.text(), Ok(text));
+
+        let other = split_frames("another", 30);
+        assert!(collector.add(&other[0]).is_err());
+        assert!(collector.add("ckb-cli:00000000:2/1:x").is_err());
+    }
+}
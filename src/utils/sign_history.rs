@@ -0,0 +1,146 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+
+use ckb_sdk::{blake2b_args, serialize_signature, Address, SECP256K1};
+use ckb_types::{bytes::Bytes, H160, H256};
+use secp256k1::recovery::RecoverableSignature;
+use serde_derive::{Deserialize, Serialize};
+
+use super::other::{get_ckb_cli_dir, now_seconds};
+use super::policy::authorize_signer;
+
+/// One signature produced by ckb-cli
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SignRecord {
+    /// Unix timestamp in seconds
+    pub timestamp: u64,
+    pub lock_arg: H160,
+    /// The transaction hash (if the message is a transaction)
+    pub tx_hash: Option<H256>,
+    /// The message (blake2b digest) signed
+    pub digest: H256,
+    pub command: String,
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+lazy_static::lazy_static! {
+    // The command line producing the signatures, shared by the threads signing in parallel
+    static ref COMMAND: RwLock<String> = RwLock::new(String::new());
+}
+
+/// Opt out by `"sign_history": false` in `~/.ckb-cli/config`
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::SeqCst);
+}
+
+pub fn set_command(command: &str) {
+    *COMMAND.write().expect("Sign history command lock poisoned") = command.to_owned();
+}
+
+/// Append-only audit log of the signatures, one JSON record per line in `~/.ckb-cli/sign-history`
+pub struct SignHistory {
+    path: PathBuf,
+}
+
+impl SignHistory {
+    pub fn new(path: PathBuf) -> SignHistory {
+        SignHistory { path }
+    }
+
+    pub fn default_path() -> SignHistory {
+        SignHistory::new(get_ckb_cli_dir().join("sign-history"))
+    }
+
+    pub fn append(&self, record: &SignRecord) -> Result<(), String> {
+        let line = serde_json::to_string(record).map_err(|err| err.to_string())?;
+        let mut file = open_append(&self.path)
+            .map_err(|err| format!("Open sign history {:?} failed: {}", self.path, err))?;
+        // One write for each record, the signatures may be recorded by several threads
+        file.write_all(format!("{}\n", line).as_bytes())
+            .map_err(|err| format!("Write sign history {:?} failed: {}", self.path, err))
+    }
+
+    /// All the records, the oldest first
+    pub fn records(&self) -> Result<Vec<SignRecord>, String> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = fs::read_to_string(&self.path).map_err(|err| err.to_string())?;
+        content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .enumerate()
+            .map(|(idx, line)| {
+                serde_json::from_str(line)
+                    .map_err(|err| format!("Parse sign history line {} failed: {}", idx + 1, err))
+            })
+            .collect()
+    }
+}
+
+// Only readable by the owner, like the keystore files
+#[cfg(unix)]
+fn open_append(path: &Path) -> std::io::Result<fs::File> {
+    use std::os::unix::fs::OpenOptionsExt;
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .mode(0o600)
+        .open(path)
+}
+
+#[cfg(not(unix))]
+fn open_append(path: &Path) -> std::io::Result<fs::File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+/// The shared signing path of the sighash witnesses (private key, keystore and Ledger): the
+/// signing policy is checked for the signer, then the digest of the sighash arguments (the first
+/// one is the transaction hash) is signed by `sign` and recorded
+pub fn sign_witness<F>(lock_arg: &H160, args: &[Vec<u8>], sign: F) -> Result<Bytes, String>
+where
+    F: FnOnce(&H256) -> Result<RecoverableSignature, String>,
+{
+    authorize_signer(lock_arg)?;
+    let digest = H256::from_slice(&blake2b_args(args))
+        .expect("converting digest of [u8; 32] to H256 should be ok");
+    let signature = sign(&digest)?;
+    let tx_hash = args
+        .first()
+        .filter(|arg| arg.len() == 32)
+        .map(|arg| H256::from_slice(arg).unwrap());
+    record_digest(lock_arg, tx_hash, digest)?;
+    Ok(serialize_signature(&signature))
+}
+
+/// Record a signature of a message digest
+pub fn record_digest(lock_arg: &H160, tx_hash: Option<H256>, digest: H256) -> Result<(), String> {
+    if !ENABLED.load(Ordering::SeqCst) {
+        return Ok(());
+    }
+    let record = SignRecord {
+        timestamp: now_seconds(),
+        lock_arg: lock_arg.clone(),
+        tx_hash,
+        digest,
+        command: COMMAND
+            .read()
+            .expect("Sign history command lock poisoned")
+            .clone(),
+    };
+    SignHistory::default_path().append(&record)
+}
+
+/// `build_witness_with_key` through `sign_witness`
+pub fn witness_with_key(privkey: &secp256k1::SecretKey, args: &[Vec<u8>]) -> Result<Bytes, String> {
+    let pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, privkey);
+    sign_witness(Address::from_pubkey(&pubkey)?.hash(), args, |digest| {
+        let message = secp256k1::Message::from_slice(digest.as_bytes())
+            .expect("Convert to secp256k1 message failed");
+        Ok(SECP256K1.sign_recoverable(&message, privkey))
+    })
+}
@@ -0,0 +1,204 @@
+//! Render the output of commands as rows: `--output-format csv` and `--output-format table`.
+//!
+//! A list (`[...]`, or the first list of objects in the output like `live_cells`) is rendered as
+//! one row per item, other outputs as a single row. Nested fields are flattened as columns named
+//! by the path (`out_point.tx_hash`).
+
+use colored::Colorize;
+use serde_json::Value;
+
+pub struct Rows {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl Rows {
+    pub fn from_value(value: &Value) -> Rows {
+        let items: Vec<&Value> = match list_of(value) {
+            Some(items) => items.iter().collect(),
+            None => vec![value],
+        };
+        let mut columns: Vec<String> = Vec::new();
+        let flattened = items
+            .into_iter()
+            .map(|item| {
+                let mut fields = Vec::new();
+                flatten("", item, &mut fields);
+                for (name, _) in &fields {
+                    if !columns.contains(name) {
+                        columns.push(name.clone());
+                    }
+                }
+                fields
+            })
+            .collect::<Vec<_>>();
+        let rows = flattened
+            .into_iter()
+            .map(|fields| {
+                columns
+                    .iter()
+                    .map(|column| {
+                        fields
+                            .iter()
+                            .find(|(name, _)| name == column)
+                            .map(|(_, content)| content.clone())
+                            .unwrap_or_default()
+                    })
+                    .collect()
+            })
+            .collect();
+        Rows { columns, rows }
+    }
+
+    /// RFC 4180 CSV with a header line
+    pub fn to_csv(&self) -> String {
+        let mut lines = Vec::with_capacity(self.rows.len() + 1);
+        lines.push(csv_line(&self.columns));
+        lines.extend(self.rows.iter().map(|row| csv_line(row)));
+        lines.join("\n")
+    }
+
+    /// Aligned columns with a header line
+    pub fn to_table(&self, color: bool) -> String {
+        let widths = self
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(idx, column)| {
+                self.rows
+                    .iter()
+                    .map(|row| row[idx].chars().count())
+                    .chain(std::iter::once(column.chars().count()))
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect::<Vec<_>>();
+        let header = table_line(&self.columns, &widths);
+        let mut lines = Vec::with_capacity(self.rows.len() + 2);
+        lines.push(if color {
+            header.blue().to_string()
+        } else {
+            header
+        });
+        lines.push(
+            widths
+                .iter()
+                .map(|width| "-".repeat(*width))
+                .collect::<Vec<_>>()
+                .join("  "),
+        );
+        lines.extend(self.rows.iter().map(|row| table_line(row, &widths)));
+        lines.join("\n")
+    }
+}
+
+// The output itself, or the first field holding a list of objects
+fn list_of(value: &Value) -> Option<&Vec<Value>> {
+    match value {
+        Value::Array(items) => Some(items),
+        Value::Object(map) => map.values().find_map(|item| match item {
+            Value::Array(items) if items.iter().any(Value::is_object) => Some(items),
+            _ => None,
+        }),
+        _ => None,
+    }
+}
+
+fn flatten(prefix: &str, value: &Value, fields: &mut Vec<(String, String)>) {
+    let name = |key: &str| {
+        if prefix.is_empty() {
+            key.to_owned()
+        } else {
+            format!("{}.{}", prefix, key)
+        }
+    };
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (key, item) in map {
+                flatten(&name(key), item, fields);
+            }
+        }
+        // Lists of plain values are kept in one column
+        Value::Array(items) if items.iter().any(|item| item.is_object() || item.is_array()) => {
+            for (idx, item) in items.iter().enumerate() {
+                flatten(&name(&idx.to_string()), item, fields);
+            }
+        }
+        Value::Array(items) => {
+            let content = items.iter().map(plain).collect::<Vec<_>>().join(" ");
+            fields.push((column_name(prefix), content));
+        }
+        _ => fields.push((column_name(prefix), plain(value))),
+    }
+}
+
+fn column_name(prefix: &str) -> String {
+    if prefix.is_empty() {
+        "value".to_owned()
+    } else {
+        prefix.to_owned()
+    }
+}
+
+fn plain(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(content) => content.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn csv_line(fields: &[String]) -> String {
+    fields
+        .iter()
+        .map(|field| {
+            if field.contains(|c| c == ',' || c == '"' || c == '\n' || c == '\r') {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn table_line(fields: &[String], widths: &[usize]) -> String {
+    fields
+        .iter()
+        .zip(widths)
+        .map(|(field, width)| format!("{:<width$}", field, width = width))
+        .collect::<Vec<_>>()
+        .join("  ")
+        .trim_end()
+        .to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_rows() {
+        let output = json!({
+            "live_cells": [
+                {"capacity": 100, "out_point": {"index": 0, "tx_hash": "0x01"}},
+                {"capacity": 200, "note": "a,\"b\"", "out_point": {"index": 1, "tx_hash": "0x02"}},
+            ],
+            "total_capacity": 300,
+        });
+        let rows = Rows::from_value(&output);
+        assert_eq!(
+            rows.to_csv(),
+            "capacity,out_point.index,out_point.tx_hash,note\n\
+             100,0,0x01,\n\
+             200,1,0x02,\"a,\"\"b\"\"\""
+        );
+        let rows = Rows::from_value(&json!({"capacity": 300, "count": 2}));
+        assert_eq!(
+            rows.to_table(false),
+            "capacity  count\n--------  -----\n300       2"
+        );
+        assert_eq!(Rows::from_value(&json!("0x03")).to_csv(), "value\n0x03");
+    }
+}
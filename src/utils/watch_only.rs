@@ -0,0 +1,64 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use ckb_types::H160;
+use serde_derive::{Deserialize, Serialize};
+
+use super::other::get_ckb_cli_dir;
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct WatchOnlyAccount {
+    // Hex of compressed secp256k1 public key (when imported by pubkey)
+    pub pubkey: Option<String>,
+}
+
+/// Accounts without private key (only lock-arg or public key), can be queried and used to
+/// build unsigned transactions. Stored in `~/.ckb-cli/watch-only`
+pub struct WatchOnlyAccounts {
+    path: PathBuf,
+    accounts: BTreeMap<H160, WatchOnlyAccount>,
+}
+
+impl WatchOnlyAccounts {
+    pub fn load(path: PathBuf) -> Result<WatchOnlyAccounts, String> {
+        let accounts = if path.exists() {
+            let content = fs::read_to_string(&path).map_err(|err| err.to_string())?;
+            serde_json::from_str(&content)
+                .map_err(|err| format!("Parse watch-only accounts {:?} failed: {}", path, err))?
+        } else {
+            BTreeMap::default()
+        };
+        Ok(WatchOnlyAccounts { path, accounts })
+    }
+
+    pub fn load_default() -> Result<WatchOnlyAccounts, String> {
+        WatchOnlyAccounts::load(get_ckb_cli_dir().join("watch-only"))
+    }
+
+    pub fn contains(&self, lock_arg: &H160) -> bool {
+        self.accounts.contains_key(lock_arg)
+    }
+
+    pub fn add(&mut self, lock_arg: H160, account: WatchOnlyAccount) -> Result<(), String> {
+        self.accounts.insert(lock_arg, account);
+        self.save()
+    }
+
+    pub fn remove(&mut self, lock_arg: &H160) -> Result<(), String> {
+        self.accounts
+            .remove(lock_arg)
+            .ok_or_else(|| format!("Watch-only account not found: {:x}", lock_arg))?;
+        self.save()
+    }
+
+    pub fn list(&self) -> &BTreeMap<H160, WatchOnlyAccount> {
+        &self.accounts
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let content =
+            serde_json::to_string_pretty(&self.accounts).map_err(|err| err.to_string())?;
+        fs::write(&self.path, content).map_err(|err| err.to_string())
+    }
+}